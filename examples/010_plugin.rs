@@ -88,6 +88,11 @@ impl MonitoringPlugin for HttpPlugin {
                         result: plugin_result,
                     }),
                     timestamp: chrono::Utc::now(),
+                    warmup: None,
+                    degraded: None,
+                    failure_kind: None,
+                    status: None,
+                    notes: Vec::new(),
                 };
                 Ok(result)
             }
@@ -54,6 +54,11 @@ impl MonitoringPlugin for DummyPlugin {
                 result: plugin_result,
             }),
             timestamp: chrono::Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
         })
     }
 
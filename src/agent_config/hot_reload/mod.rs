@@ -3,7 +3,10 @@
 //! Coordinates config hot-reload from multiple sources:
 //! - File system changes to the config file (via notify debouncer)
 //! - SIGHUP signal on Unix systems
-//! - Future: Server-initiated config version changes
+//! - Reload requests through `Agent::request_reload()` (the Windows service
+//!   maps `SERVICE_CONTROL_PARAMCHANGE` to it)
+//! - A newer configuration version in the server's report acknowledgment
+//!   (sent by the status reporter), which pulls the server-side configuration
 //!
 //! ## Architecture
 //!
@@ -15,7 +18,10 @@
 //! └─────────────┘  │
 //!                  ├──► ReloadTrigger ──► Reload Coordinator ──► Config ──► Agent::start()
 //! ┌─────────────┐  │                      (load + validate)        (mpsc)
-//! │SIGHUP Handler │──┘
+//! │SIGHUP Handler │──┤
+//! └─────────────┘  │
+//! ┌─────────────┐  │
+//! │   Reporter  │──┘ ServerVersionChange (pull server config)
 //! └─────────────┘
 //! ```
 //!
@@ -30,7 +36,10 @@
 //! - Simplifies error handling and shutdown coordination
 
 mod config_file_watcher;
+mod reload_request;
 mod server;
 mod sighup;
+mod status;
 
-pub use server::run_hot_reload;
+pub use server::{run_hot_reload, ReloadTrigger};
+pub use status::{ReloadSource, ReloadStatus, ReloadStatusHandle};
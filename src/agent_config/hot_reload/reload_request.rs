@@ -0,0 +1,145 @@
+//! Reload requests from within the process
+//!
+//! Windows has no SIGHUP. There the service control handler asks the agent
+//! to reload when it receives `SERVICE_CONTROL_PARAMCHANGE`
+//! (`sc control smotra paramchange`), which ends up in
+//! `Agent::request_reload()`. The request is forwarded to the reload
+//! coordinator like any other trigger, so it goes through the same
+//! cooldown, loading and validation. The mechanism is available on every
+//! platform for embedders that want to trigger a reload programmatically.
+//!
+//! Like file changes, requests are debounced before they are forwarded: all
+//! requests made within [`REQUEST_DEBOUNCE`] of the first become a single
+//! trigger, so a burst of requests never queues more than one reload.
+
+use futures::FutureExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, info};
+
+use crate::core::{wait_for_shutdown, ShutdownReceiver};
+use crate::error::{Error, Result};
+
+use super::server::ReloadTrigger;
+
+/// Window after a reload request in which further requests are absorbed,
+/// the same as the config file watcher's debounce
+pub(super) const REQUEST_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Forward reload requests signalled on `requests` as reload triggers,
+/// one per [`REQUEST_DEBOUNCE`] window
+///
+/// # Arguments
+///
+/// * `requests` - Notified once per reload request; a request made while
+///   nobody is waiting is kept until the next wait
+/// * `reload_tx` - Channel to send reload triggers
+/// * `shutdown_rx` - Channel to receive shutdown signals
+pub async fn handle_reload_requests(
+    requests: Arc<Notify>,
+    reload_tx: mpsc::UnboundedSender<ReloadTrigger>,
+    mut shutdown_rx: ShutdownReceiver,
+) -> Result<()> {
+    loop {
+        tokio::select! {
+            _ = requests.notified() => {
+                tokio::select! {
+                    _ = tokio::time::sleep(REQUEST_DEBOUNCE) => {}
+                    _ = wait_for_shutdown(&mut shutdown_rx) => {
+                        info!("Reload request handler shutting down");
+                        break;
+                    }
+                }
+                // Requests made in the meantime left at most one permit
+                if requests.notified().now_or_never().is_some() {
+                    debug!("Coalesced further reload requests within {:?}", REQUEST_DEBOUNCE);
+                }
+
+                info!("Reload requested, triggering config reload");
+                reload_tx.send(ReloadTrigger::Request)
+                    .map_err(|e| Error::Config(format!("Failed to send reload trigger: {}", e)))?;
+            }
+            _ = wait_for_shutdown(&mut shutdown_rx) => {
+                info!("Reload request handler shutting down");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{shutdown_channel, trigger_shutdown};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_request_is_forwarded_as_trigger() {
+        let requests = Arc::new(Notify::new());
+        let (trigger_tx, mut trigger_rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        // Requested before the handler waits: the permit is kept.
+        requests.notify_one();
+        let handle = tokio::spawn(handle_reload_requests(
+            Arc::clone(&requests),
+            trigger_tx,
+            shutdown_rx,
+        ));
+
+        let trigger = tokio::time::timeout(Duration::from_secs(1), trigger_rx.recv())
+            .await
+            .expect("request should be forwarded")
+            .unwrap();
+        assert!(matches!(trigger, ReloadTrigger::Request));
+
+        trigger_shutdown(&shutdown_tx);
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("handler should stop on shutdown")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_burst_is_forwarded_once() {
+        let requests = Arc::new(Notify::new());
+        let (trigger_tx, mut trigger_rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+        let handle = tokio::spawn(handle_reload_requests(
+            Arc::clone(&requests),
+            trigger_tx,
+            shutdown_rx,
+        ));
+
+        for _ in 0..5 {
+            requests.notify_one();
+            tokio::time::sleep(REQUEST_DEBOUNCE / 10).await;
+        }
+
+        let trigger = tokio::time::timeout(REQUEST_DEBOUNCE * 2, trigger_rx.recv())
+            .await
+            .expect("burst should be forwarded")
+            .unwrap();
+        assert!(matches!(trigger, ReloadTrigger::Request));
+        assert!(
+            tokio::time::timeout(REQUEST_DEBOUNCE * 2, trigger_rx.recv())
+                .await
+                .is_err(),
+            "burst should be forwarded as a single trigger"
+        );
+
+        // A later request is a reload of its own
+        requests.notify_one();
+        tokio::time::timeout(REQUEST_DEBOUNCE * 2, trigger_rx.recv())
+            .await
+            .expect("later request should be forwarded")
+            .unwrap();
+
+        trigger_shutdown(&shutdown_tx);
+        handle.await.unwrap().unwrap();
+    }
+}
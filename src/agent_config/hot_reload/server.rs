@@ -3,15 +3,27 @@
 //! Coordinates config hot-reload from multiple sources:
 //! - File system changes to the config file (via notify debouncer)
 //! - SIGHUP signal on Unix systems
-//! - Future: Server-initiated config version changes
+//! - Reload requests through `Agent::request_reload()`
+//! - A newer configuration version advertised in the server's report
+//!   acknowledgment, which pulls the server-side configuration
+//!
+//! Triggers arriving within the configured cooldown (`hot_reload.cooldown_ms`)
+//! of the first one are coalesced into a single reload, so a config management
+//! tool that signals several times, or a signal racing a file change, does not
+//! reload the config repeatedly.
 
-use std::path::PathBuf;
-use tokio::sync::{broadcast, mpsc};
-use tracing::{error, info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, error, info, warn};
 
 use super::config_file_watcher::ConfigFileWatcher;
+use super::reload_request::handle_reload_requests;
 use super::sighup::handle_sighup;
-use crate::agent_config::Config;
+use super::status::{ReloadSource, ReloadStatus, ReloadStatusHandle};
+use crate::agent_config::{Config, EndpointSync};
+use crate::core::{wait_for_shutdown, ShutdownReceiver};
 use crate::error::Result;
 
 /// Events that trigger config reload
@@ -25,8 +37,9 @@ pub enum ReloadTrigger {
     FileChange(),
     /// SIGHUP signal received (Unix only)
     Signal,
-    /// Server reported a new config version (future implementation)
-    #[allow(dead_code)]
+    /// Reload requested through `Agent::request_reload()`
+    Request,
+    /// The server acknowledged a report with a newer configuration version
     ServerVersionChange(u32),
 }
 
@@ -40,6 +53,12 @@ pub enum ReloadTrigger {
 ///
 /// * `config_path` - Path to the configuration file to watch and reload
 /// * `reload_tx` - Channel to send validated configs to Agent::start()
+/// * `reload_status` - Records the outcome of every reload attempt
+/// * `reload_requests` - Notified by `Agent::request_reload()`
+/// * `triggers` - Channel for reload triggers; the agent hands further senders
+///   to the status reporter for server version changes
+/// * `cooldown` - Window in which further triggers are coalesced into one reload;
+///   replaced by `hot_reload.cooldown_ms` of every successfully loaded config
 /// * `shutdown_rx` - Channel to receive shutdown signals
 ///
 /// # Returns
@@ -48,21 +67,26 @@ pub enum ReloadTrigger {
 ///
 /// # Architecture
 ///
-/// Creates three coordinated components:
+/// Creates four coordinated components:
 /// 1. **File watcher**: Monitors config file for changes (ConfigFileWatcher)
 /// 2. **SIGHUP handler**: Listens for SIGHUP signals and sends reload triggers
-/// 3. **Main event loop**: Receives triggers from internal channel, loads/validates config,
+/// 3. **Reload request handler**: Turns `Agent::request_reload()` calls into reload triggers
+/// 4. **Main event loop**: Receives triggers from internal channel, loads/validates config,
 ///    and sends validated configs to Agent through the provided channel
 pub async fn run_hot_reload(
     config_path: PathBuf,
     config_tx: mpsc::Sender<Config>,
-    shutdown_rx: broadcast::Receiver<()>,
+    reload_status: ReloadStatusHandle,
+    reload_requests: Arc<Notify>,
+    (trigger_tx, trigger_rx): (
+        mpsc::UnboundedSender<ReloadTrigger>,
+        mpsc::UnboundedReceiver<ReloadTrigger>,
+    ),
+    cooldown: Duration,
+    shutdown_rx: ShutdownReceiver,
 ) -> Result<()> {
     info!("Starting config hot-reload orchestration");
 
-    // Create internal channel for reload triggers
-    let (trigger_tx, mut trigger_rx) = mpsc::unbounded_channel::<ReloadTrigger>();
-
     // Create the config file watcher
     let mut file_watcher = ConfigFileWatcher::new(config_path.clone(), trigger_tx.clone())?;
 
@@ -77,7 +101,7 @@ pub async fn run_hot_reload(
     // Spawn SIGHUP handler task
     let sighup_handle = {
         let trigger_tx = trigger_tx.clone();
-        let shutdown_rx = shutdown_rx.resubscribe();
+        let shutdown_rx = shutdown_rx.clone();
         tokio::spawn(async move {
             if let Err(e) = handle_sighup(trigger_tx, shutdown_rx).await {
                 error!("SIGHUP handler error: {}", e);
@@ -85,18 +109,75 @@ pub async fn run_hot_reload(
         })
     };
 
+    // Spawn reload request handler task
+    let request_handle = {
+        let trigger_tx = trigger_tx.clone();
+        let shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_reload_requests(reload_requests, trigger_tx, shutdown_rx).await {
+                error!("Reload request handler error: {}", e);
+            }
+        })
+    };
+
     info!("Config hot-reload enabled");
 
-    // Run the main reload coordinator loop
-    let mut shutdown_rx = shutdown_rx;
+    run_reload_coordinator(
+        config_path,
+        config_tx,
+        reload_status,
+        trigger_rx,
+        cooldown,
+        shutdown_rx,
+    )
+    .await;
+
+    // Keep file_watcher alive until shutdown (it owns the Debouncer)
+    drop(file_watcher);
+
+    // Wait for SIGHUP and reload request handlers to complete (with short timeout)
+    let _ = tokio::time::timeout(std::time::Duration::from_millis(500), sighup_handle).await;
+    let _ = tokio::time::timeout(std::time::Duration::from_millis(500), request_handle).await;
+
+    info!("Config hot-reload orchestration stopped");
+    Ok(())
+}
+
+/// Receive reload triggers, coalesce those arriving within `cooldown` and
+/// load, validate and forward the config once per coalesced batch
+async fn run_reload_coordinator(
+    config_path: PathBuf,
+    config_tx: mpsc::Sender<Config>,
+    reload_status: ReloadStatusHandle,
+    mut trigger_rx: mpsc::UnboundedReceiver<ReloadTrigger>,
+    mut cooldown: Duration,
+    mut shutdown_rx: ShutdownReceiver,
+) {
+    // Latest server configuration version handled, so one advertised in
+    // every report acknowledgment is pulled only once
+    let mut server_version = 0;
+
     loop {
         tokio::select! {
             Some(trigger) = trigger_rx.recv() => {
                 info!("Config reload triggered: {:?}", trigger);
 
+                let trigger = tokio::select! {
+                    trigger = coalesce_triggers(trigger, &mut trigger_rx, cooldown) => trigger,
+                    _ = wait_for_shutdown(&mut shutdown_rx) => {
+                        info!("Config hot-reload orchestration shutting down");
+                        break;
+                    }
+                };
+
                 match trigger {
-                    ReloadTrigger::FileChange() | ReloadTrigger::Signal
+                    ReloadTrigger::FileChange() | ReloadTrigger::Signal | ReloadTrigger::Request
                      => {
+                        let source = match trigger {
+                            ReloadTrigger::Signal => ReloadSource::Signal,
+                            ReloadTrigger::Request => ReloadSource::Request,
+                            _ => ReloadSource::FileChange,
+                        };
 
                         match Config::load_and_validate_config(&config_path) {
                             Ok(new_config) => {
@@ -104,6 +185,11 @@ pub async fn run_hot_reload(
                                     "Config loaded and validated successfully (version: {})",
                                     new_config.version
                                 );
+                                // Recorded before sending, so a rejection by
+                                // the agent overwrites it and not vice versa.
+                                *reload_status.write() =
+                                    Some(ReloadStatus::succeeded(source, new_config.version));
+                                cooldown = new_config.hot_reload.cooldown();
 
                                 // Send the validated config to Agent::start() for application
                                 if let Err(e) = config_tx.send(new_config).await {
@@ -116,33 +202,106 @@ pub async fn run_hot_reload(
                             }
                             Err(e) => {
                                 error!("Failed to load config during reload: {}", e);
+                                *reload_status.write() = Some(ReloadStatus::failed(source, None, &e));
                                 // Continue running even if one reload fails
                             }
                         }
 
                     }
                     ReloadTrigger::ServerVersionChange(version) => {
-                        info!("Reload triggered by server version change: {}", version);
-                        unimplemented!("Server-initiated config version change handling is not implemented yet");
+                        if version <= server_version {
+                            debug!("Server configuration version {} already handled", version);
+                            continue;
+                        }
+
+                        match pull_server_config(&config_path).await {
+                            Ok(Some(new_config)) => {
+                                server_version = version;
+                                info!(
+                                    "Server configuration adopted (version: {})",
+                                    new_config.version
+                                );
+                                *reload_status.write() = Some(ReloadStatus::succeeded(
+                                    ReloadSource::Server,
+                                    new_config.version,
+                                ));
+                                cooldown = new_config.hot_reload.cooldown();
+
+                                if let Err(e) = config_tx.send(new_config).await {
+                                    error!("Failed to send config to a closed channel: {}", e);
+                                    break;
+                                }
+                            }
+                            Ok(None) => {
+                                server_version = version;
+                                info!(
+                                    "Server offers configuration version {}, but server.endpoint_sync is off",
+                                    version
+                                );
+                            }
+                            Err(e) => {
+                                // Retried when the next report is acknowledged
+                                error!("Failed to pull server configuration version {}: {}", version, e);
+                                *reload_status.write() = Some(ReloadStatus::failed(
+                                    ReloadSource::Server,
+                                    Some(version),
+                                    &e,
+                                ));
+                            }
+                        }
                     }
                 }
 
             }
-            _ = shutdown_rx.recv() => {
+            _ = wait_for_shutdown(&mut shutdown_rx) => {
                 info!("Config hot-reload orchestration shutting down");
                 break;
             }
         }
     }
+}
 
-    // Keep file_watcher alive until shutdown (it owns the Debouncer)
-    drop(file_watcher);
+/// Load the config file and adopt the server-side configuration into it
+///
+/// Changed endpoints are persisted to `config_path`, as at startup. Returns
+/// `None` without contacting the server if `server.endpoint_sync` is off.
+async fn pull_server_config(config_path: &Path) -> Result<Option<Config>> {
+    let mut config = Config::load_and_validate_config(config_path)?;
+    if config.server.endpoint_sync == EndpointSync::Off {
+        return Ok(None);
+    }
 
-    // Wait for SIGHUP handler to complete (with short timeout)
-    let _ = tokio::time::timeout(std::time::Duration::from_millis(500), sighup_handle).await;
+    if config.sync_endpoints_from_server().await? {
+        config.validate()?;
+        config.save_to_file_secure(config_path).await?;
+    }
+    Ok(Some(config))
+}
 
-    info!("Config hot-reload orchestration stopped");
-    Ok(())
+/// Wait out `cooldown` after `first` and absorb every trigger received in
+/// the meantime, returning the latest one
+async fn coalesce_triggers(
+    first: ReloadTrigger,
+    trigger_rx: &mut mpsc::UnboundedReceiver<ReloadTrigger>,
+    cooldown: Duration,
+) -> ReloadTrigger {
+    if !cooldown.is_zero() {
+        tokio::time::sleep(cooldown).await;
+    }
+
+    let mut latest = first;
+    let mut coalesced = 0;
+    while let Ok(trigger) = trigger_rx.try_recv() {
+        latest = trigger;
+        coalesced += 1;
+    }
+    if coalesced > 0 {
+        debug!(
+            "Coalesced {} further reload trigger(s) within {:?}",
+            coalesced, cooldown
+        );
+    }
+    latest
 }
 
 /// Test helpers for unit tests
@@ -173,7 +332,7 @@ mod test_helpers {
         config_path: PathBuf,
         config_tx: mpsc::Sender<Config>,
         mut trigger_rx: mpsc::UnboundedReceiver<ReloadTrigger>,
-        shutdown_rx: broadcast::Receiver<()>,
+        shutdown_rx: ShutdownReceiver,
     ) -> Result<()> {
         info!("Starting test hot-reload orchestration with custom trigger channel");
 
@@ -202,7 +361,7 @@ mod test_helpers {
                         }
                     }
                 }
-                _ = shutdown_rx.recv() => {
+                _ = wait_for_shutdown(&mut shutdown_rx) => {
                     info!("Test hot-reload orchestration shutting down");
                     break;
                 }
@@ -216,11 +375,12 @@ mod test_helpers {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::Agent;
+    use crate::core::{shutdown_channel, trigger_shutdown, Agent};
     use crate::{Endpoint, MonitoringConfig};
     use std::fs;
+    use std::sync::Arc;
     use tempfile::NamedTempFile;
-    use tokio::time::{sleep, Duration};
+    use tokio::time::sleep;
 
     /// Helper to create a valid config file content
     fn config_with_endpoints(version: u32, interval_secs: u64, endpoints_count: usize) -> Config {
@@ -250,19 +410,30 @@ mod tests {
         };
         config.save_to_file_secure(temp_file.path()).await.unwrap();
 
-        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
         let (config_tx, config_rx) = mpsc::channel(1);
+        let triggers = test_helpers::create_reload_trigger_channel();
 
         // Spawn the hot reload task
         let config_path = temp_file.path().to_path_buf();
-        let handle =
-            tokio::spawn(async move { run_hot_reload(config_path, config_tx, shutdown_rx).await });
+        let handle = tokio::spawn(async move {
+            run_hot_reload(
+                config_path,
+                config_tx,
+                Default::default(),
+                Default::default(),
+                triggers,
+                Duration::from_secs(1),
+                shutdown_rx,
+            )
+            .await
+        });
 
         // Give it a moment to start
         sleep(Duration::from_millis(50)).await;
 
         // Send shutdown signal
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
 
         // Wait for task to complete
         let result = tokio::time::timeout(Duration::from_secs(1), handle).await;
@@ -295,7 +466,7 @@ mod tests {
             .await
             .unwrap();
 
-        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
         let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
         let (config_tx, mut config_rx) = mpsc::channel(1);
 
@@ -330,7 +501,7 @@ mod tests {
         assert_eq!(current_config.endpoints.len(), 4);
 
         // Shutdown
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let _ = tokio::time::timeout(Duration::from_secs(1), reload_handle)
             .await
             .expect("Hot reload task should shutdown cleanly");
@@ -348,7 +519,7 @@ mod tests {
 
         let agent = Agent::new(config_path.path().to_path_buf()).unwrap();
 
-        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
         let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
         let (config_tx, mut config_rx) = mpsc::channel(1);
 
@@ -388,7 +559,7 @@ mod tests {
         assert_eq!(current_config.monitoring.interval_secs, 60);
 
         // Shutdown
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let _ = tokio::time::timeout(Duration::from_secs(1), reload_handle)
             .await
             .expect("Hot reload task should shutdown cleanly");
@@ -406,7 +577,7 @@ mod tests {
 
         let agent = Agent::new(config_path.path().to_path_buf()).unwrap();
 
-        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
         let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
         let (config_tx, mut config_rx) = mpsc::channel(1);
 
@@ -442,7 +613,7 @@ mod tests {
         assert_eq!(current_config.monitoring.interval_secs, 60);
 
         // Shutdown
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let _ = tokio::time::timeout(Duration::from_secs(1), reload_handle).await;
     }
 
@@ -459,7 +630,7 @@ mod tests {
         let agent = Agent::new(config_path.path().to_path_buf()).unwrap();
         let agent_arc = std::sync::Arc::new(agent);
 
-        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
         let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
         let (config_tx, mut config_rx) = mpsc::channel(1);
 
@@ -508,7 +679,7 @@ mod tests {
         }
 
         // Shutdown
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let timeout_handle = tokio::time::timeout(Duration::from_secs(1), reload_handle).await;
 
         assert!(
@@ -517,6 +688,86 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_rapid_signals_coalesce_into_one_reload() {
+        let config_path = NamedTempFile::new().unwrap();
+        config_with_endpoints(1, 60, 1)
+            .save_to_file_secure(&config_path)
+            .await
+            .unwrap();
+
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+        let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
+        let (config_tx, mut config_rx) = mpsc::channel(4);
+        let reload_status = ReloadStatusHandle::default();
+
+        let coordinator = tokio::spawn(run_reload_coordinator(
+            config_path.path().to_path_buf(),
+            config_tx,
+            Arc::clone(&reload_status),
+            trigger_rx,
+            Duration::from_millis(300),
+            shutdown_rx,
+        ));
+
+        for _ in 0..5 {
+            trigger_tx.send(ReloadTrigger::Signal).unwrap();
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        let config = tokio::time::timeout(Duration::from_secs(2), config_rx.recv())
+            .await
+            .expect("Should receive the coalesced reload")
+            .expect("Should receive Some(config)");
+        assert_eq!(config.version, 1);
+
+        let second = tokio::time::timeout(Duration::from_millis(600), config_rx.recv()).await;
+        assert!(second.is_err(), "Rapid SIGHUPs should reload only once");
+        assert_eq!(
+            reload_status.read().as_ref().map(|s| s.source),
+            Some(ReloadSource::Signal)
+        );
+
+        trigger_shutdown(&shutdown_tx);
+        tokio::time::timeout(Duration::from_secs(1), coordinator)
+            .await
+            .expect("Coordinator should shut down")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_triggers_after_cooldown_reload_again() {
+        let config_path = NamedTempFile::new().unwrap();
+        config_with_endpoints(1, 60, 1)
+            .save_to_file_secure(&config_path)
+            .await
+            .unwrap();
+
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+        let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
+        let (config_tx, mut config_rx) = mpsc::channel(4);
+
+        let coordinator = tokio::spawn(run_reload_coordinator(
+            config_path.path().to_path_buf(),
+            config_tx,
+            Default::default(),
+            trigger_rx,
+            Duration::from_millis(100),
+            shutdown_rx,
+        ));
+
+        for _ in 0..2 {
+            trigger_tx.send(ReloadTrigger::Signal).unwrap();
+            tokio::time::timeout(Duration::from_secs(2), config_rx.recv())
+                .await
+                .expect("Each trigger after the cooldown should reload")
+                .expect("Should receive Some(config)");
+        }
+
+        trigger_shutdown(&shutdown_tx);
+        let _ = tokio::time::timeout(Duration::from_secs(1), coordinator).await;
+    }
+
     #[tokio::test]
     async fn test_reload_trigger_variants() {
         // Create config file
@@ -527,7 +778,7 @@ mod tests {
             .await
             .unwrap();
 
-        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
         let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
         let (config_tx, mut config_rx) = mpsc::channel(1);
 
@@ -568,7 +819,146 @@ mod tests {
         assert!(config.is_some(), "Expected config received");
 
         // Shutdown
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let _ = tokio::time::timeout(Duration::from_secs(1), reload_handle).await;
     }
+
+    #[tokio::test]
+    async fn test_server_version_change_pulls_server_config_once() {
+        let mut server = mockito::Server::new_async().await;
+        let mut local = config_with_endpoints(1, 60, 1);
+        local.server.url = server.url();
+        local.server.api_key = Some("sk_test".to_string());
+        local.server.endpoint_sync = EndpointSync::Replace;
+        let config_path = NamedTempFile::new().unwrap();
+        local.save_to_file_secure(&config_path).await.unwrap();
+
+        let remote = Endpoint::new("10.1.0.1");
+        let mock = server
+            .mock(
+                "GET",
+                format!("/agent/{}/configuration", local.agent_id).as_str(),
+            )
+            .match_header("X-Agent-API-Key", "sk_test")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "version": 7,
+                    "agent_id": local.agent_id,
+                    "agent_name": "Managed Agent",
+                    "tags": [],
+                    "monitoring": {
+                        "interval_secs": 60,
+                        "timeout_secs": 5,
+                        "ping_count": 3,
+                        "max_concurrent": 10,
+                        "traceroute_on_failure": false,
+                        "traceroute_max_hops": 30
+                    },
+                    "server": {
+                        "url": null,
+                        "api_key": null,
+                        "report_interval_secs": 300,
+                        "heartbeat_interval_secs": 300,
+                        "verify_tls": true,
+                        "timeout_secs": 5,
+                        "retry_attempts": 3
+                    },
+                    "storage": {
+                        "cache_dir": "./cache",
+                        "max_cached_results": 10000,
+                        "max_cache_age_secs": 86400
+                    },
+                    "self_upgrade": {
+                        "enabled": false,
+                        "github_repo_url": "https://github.com/smotra-monitoring/agent",
+                        "check_interval_secs": 3600
+                    },
+                    "endpoints": [remote]
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+        let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
+        let (config_tx, mut config_rx) = mpsc::channel(4);
+        let reload_status = ReloadStatusHandle::default();
+
+        let coordinator = tokio::spawn(run_reload_coordinator(
+            config_path.path().to_path_buf(),
+            config_tx,
+            Arc::clone(&reload_status),
+            trigger_rx,
+            Duration::ZERO,
+            shutdown_rx,
+        ));
+
+        trigger_tx
+            .send(ReloadTrigger::ServerVersionChange(7))
+            .unwrap();
+        let config = tokio::time::timeout(Duration::from_secs(2), config_rx.recv())
+            .await
+            .expect("Should receive the server config")
+            .expect("Should receive Some(config)");
+        assert_eq!(config.version, 7);
+        assert_eq!(config.endpoints.len(), 1);
+        assert_eq!(config.endpoints[0].id, remote.id);
+        assert_eq!(
+            reload_status.read().as_ref().map(|s| s.source),
+            Some(ReloadSource::Server)
+        );
+
+        // Adopted endpoints are persisted like at startup
+        let persisted = Config::load_and_validate_config(config_path.path()).unwrap();
+        assert_eq!(persisted.endpoints[0].id, remote.id);
+
+        // The same version advertised again is not pulled a second time
+        trigger_tx
+            .send(ReloadTrigger::ServerVersionChange(7))
+            .unwrap();
+        let second = tokio::time::timeout(Duration::from_millis(300), config_rx.recv()).await;
+        assert!(second.is_err(), "Version 7 should be pulled only once");
+        mock.assert_async().await;
+
+        trigger_shutdown(&shutdown_tx);
+        tokio::time::timeout(Duration::from_secs(1), coordinator)
+            .await
+            .expect("Coordinator should shut down")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_version_change_ignored_without_endpoint_sync() {
+        let config_path = NamedTempFile::new().unwrap();
+        config_with_endpoints(1, 60, 1)
+            .save_to_file_secure(&config_path)
+            .await
+            .unwrap();
+
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+        let (trigger_tx, trigger_rx) = test_helpers::create_reload_trigger_channel();
+        let (config_tx, mut config_rx) = mpsc::channel(1);
+
+        let coordinator = tokio::spawn(run_reload_coordinator(
+            config_path.path().to_path_buf(),
+            config_tx,
+            Default::default(),
+            trigger_rx,
+            Duration::ZERO,
+            shutdown_rx,
+        ));
+
+        trigger_tx
+            .send(ReloadTrigger::ServerVersionChange(3))
+            .unwrap();
+        let reload = tokio::time::timeout(Duration::from_millis(300), config_rx.recv()).await;
+        assert!(reload.is_err(), "endpoint_sync off should not reload");
+
+        trigger_shutdown(&shutdown_tx);
+        let _ = tokio::time::timeout(Duration::from_secs(1), coordinator).await;
+    }
 }
@@ -3,9 +3,10 @@
 //! Provides Unix-specific signal handling to trigger config reloads via SIGHUP.
 //! On non-Unix platforms, this is a no-op.
 
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::mpsc;
 use tracing::info;
 
+use crate::core::{wait_for_shutdown, ShutdownReceiver};
 use crate::error::{Error, Result};
 
 use super::server::ReloadTrigger;
@@ -21,7 +22,7 @@ use super::server::ReloadTrigger;
 #[cfg(unix)]
 pub async fn handle_sighup(
     reload_tx: mpsc::UnboundedSender<ReloadTrigger>,
-    mut shutdown_rx: broadcast::Receiver<()>,
+    mut shutdown_rx: ShutdownReceiver,
 ) -> Result<()> {
     use tokio::signal::unix::{signal, SignalKind};
 
@@ -37,7 +38,7 @@ pub async fn handle_sighup(
                 reload_tx.send(ReloadTrigger::Signal)
                     .map_err(|e| Error::SigHup(format!("Failed to send reload trigger: {}", e)))?;
             }
-            _ = shutdown_rx.recv() => {
+            _ = wait_for_shutdown(&mut shutdown_rx) => {
                 info!("SIGHUP handler shutting down");
                 break;
             }
@@ -51,9 +52,9 @@ pub async fn handle_sighup(
 #[cfg(not(unix))]
 pub async fn handle_sighup(
     _reload_tx: mpsc::UnboundedSender<ReloadTrigger>,
-    mut shutdown_rx: broadcast::Receiver<()>,
+    mut shutdown_rx: ShutdownReceiver,
 ) -> Result<()> {
     info!("SIGHUP handler not supported on this platform");
-    let _ = shutdown_rx.recv().await;
+    wait_for_shutdown(&mut shutdown_rx).await;
     Ok(())
 }
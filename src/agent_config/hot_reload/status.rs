@@ -0,0 +1,92 @@
+//! Outcome of the most recent hot reload
+//!
+//! The hot reload task records every attempt it makes, successful or not, in
+//! a shared [`ReloadStatusHandle`]. A configuration that loads and validates
+//! but is then rejected by `Agent::reload_config()` is recorded as failed by
+//! the agent. `Agent::last_reload_status()` exposes the latest entry so
+//! operators can tell whether a SIGHUP or file change took effect.
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// What caused a reload attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloadSource {
+    /// The config file changed on disk
+    FileChange,
+
+    /// SIGHUP was received
+    Signal,
+
+    /// A reload was requested through `Agent::request_reload()`, e.g. by the
+    /// Windows service control handler
+    Request,
+
+    /// The server advertised a newer configuration version
+    Server,
+}
+
+impl fmt::Display for ReloadSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FileChange => write!(f, "file change"),
+            Self::Signal => write!(f, "SIGHUP"),
+            Self::Request => write!(f, "reload request"),
+            Self::Server => write!(f, "server configuration"),
+        }
+    }
+}
+
+/// Result of one reload attempt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReloadStatus {
+    /// What triggered the reload
+    pub source: ReloadSource,
+
+    /// When the reload was attempted
+    pub at: DateTime<Utc>,
+
+    /// Version of the loaded configuration, `None` if it could not be loaded
+    pub config_version: Option<u32>,
+
+    /// Why the reload was rejected, `None` if it was applied
+    pub error: Option<String>,
+}
+
+impl ReloadStatus {
+    /// A reload that loaded configuration `config_version`
+    pub fn succeeded(source: ReloadSource, config_version: u32) -> Self {
+        Self {
+            source,
+            at: Utc::now(),
+            config_version: Some(config_version),
+            error: None,
+        }
+    }
+
+    /// A reload that was rejected with `error`
+    pub fn failed(
+        source: ReloadSource,
+        config_version: Option<u32>,
+        error: impl fmt::Display,
+    ) -> Self {
+        Self {
+            source,
+            at: Utc::now(),
+            config_version,
+            error: Some(error.to_string()),
+        }
+    }
+
+    /// Whether the new configuration was applied
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Latest reload status, shared between the hot reload task and the agent
+pub type ReloadStatusHandle = Arc<RwLock<Option<ReloadStatus>>>;
@@ -0,0 +1,598 @@
+//! Environment variable interpolation in configuration files
+//!
+//! Before a config file is parsed, `${NAME}` is replaced with the value of
+//! the environment variable `NAME`, so secrets and per-host values can be
+//! injected by the deployment:
+//!
+//! - `${NAME}` - the variable's value; an error if it is not set
+//! - `${NAME:-default}` - `default` if the variable is unset or empty
+//! - `$${` - a literal `${`
+//!
+//! Comments are left as they are, so commented-out settings do not need
+//! their variables. Inside double-quoted strings values are TOML-escaped;
+//! single-quoted strings cannot hold a quote or control character and bare
+//! values cannot hold a newline or `#`, so such values are an error rather
+//! than silently changing the document.
+//!
+//! Every reference is remembered as an [`EnvTemplate`], so saving the
+//! configuration writes `${NAME}` back instead of the expanded value.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use toml::de::{DeTable, DeValue};
+use toml::{Spanned, Table, Value};
+
+use crate::error::{Error, Result};
+
+/// A config value that was written with `${…}` references
+///
+/// Recorded when the file is loaded and used on save to write the original
+/// references back, as long as the value has not been changed since.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvTemplate {
+    /// Keys and array indices leading to the value
+    path: Vec<PathSegment>,
+    /// The value as it was loaded
+    value: Value,
+    /// The value's source text in the config file, references included
+    source: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// What to do with a reference to an unset variable that has no default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Unset {
+    /// Fail, naming the variable
+    Error,
+    /// Keep the reference itself; only possible inside a quoted string
+    Placeholder,
+}
+
+/// A config file with its references expanded
+pub(crate) struct Interpolated {
+    pub(crate) content: String,
+    /// Where each expanded reference or `$${` escape ended up in `content`,
+    /// and where it came from in the original file
+    replacements: Vec<Replacement>,
+}
+
+struct Replacement {
+    expanded: Range<usize>,
+    raw: Range<usize>,
+}
+
+/// Where in the TOML document a reference appears
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+    Bare,
+    Comment,
+    Basic,
+    MultiBasic,
+    Literal,
+    MultiLiteral,
+}
+
+impl Context {
+    fn is_string(self) -> bool {
+        !matches!(self, Context::Bare | Context::Comment)
+    }
+
+    /// `value` in a form that reads back as itself in this context
+    fn quote(self, value: &str) -> std::result::Result<Cow<'_, str>, &'static str> {
+        match self {
+            Context::Basic | Context::MultiBasic => Ok(escape_basic(value)),
+            Context::Literal | Context::MultiLiteral => {
+                if value
+                    .chars()
+                    .any(|c| c == '\'' || (c.is_control() && c != '\t'))
+                {
+                    Err("contains a quote or control character, which a single-quoted string cannot hold; use double quotes")
+                } else {
+                    Ok(Cow::Borrowed(value))
+                }
+            }
+            Context::Bare | Context::Comment => {
+                if value.chars().any(|c| c == '#' || c.is_control()) {
+                    Err("contains a newline, control character or '#', which a value outside quotes cannot hold")
+                } else {
+                    Ok(Cow::Borrowed(value))
+                }
+            }
+        }
+    }
+}
+
+/// Expand `${NAME}` and `${NAME:-default}` in `content` from the process
+/// environment
+pub(crate) fn interpolate_env(content: &str, unset: Unset) -> Result<Interpolated> {
+    interpolate(content, |name| std::env::var(name).ok(), unset)
+}
+
+/// Expand `content`, reading variables with `lookup`
+fn interpolate(
+    content: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+    unset: Unset,
+) -> Result<Interpolated> {
+    let mut expanded = String::with_capacity(content.len());
+    let mut replacements = Vec::new();
+    let mut context = Context::Bare;
+    let mut line = 1;
+    let mut pos = 0;
+
+    while pos < content.len() {
+        let rest = &content[pos..];
+
+        if context != Context::Comment && rest.starts_with("$${") {
+            replacements.push(Replacement {
+                expanded: expanded.len()..expanded.len() + 2,
+                raw: pos..pos + 3,
+            });
+            expanded.push_str("${");
+            pos += 3;
+            continue;
+        }
+
+        if context != Context::Comment && rest.starts_with("${") {
+            let Some(len) = rest[2..]
+                .find(['}', '\n'])
+                .filter(|&len| rest[2 + len..].starts_with('}'))
+            else {
+                return Err(Error::Config(format!(
+                    "Unterminated ${{ in config file on line {}",
+                    line
+                )));
+            };
+            let reference = &rest[2..2 + len];
+            let (name, default) = match reference.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (reference, None),
+            };
+            if !is_variable_name(name) {
+                return Err(Error::Config(format!(
+                    "Invalid environment variable name {:?} in config file on line {}",
+                    name, line
+                )));
+            }
+
+            let value = match (lookup(name), default) {
+                (Some(value), Some(default)) if value.is_empty() => Some(default.to_string()),
+                (Some(value), _) => Some(value),
+                (None, Some(default)) => Some(default.to_string()),
+                (None, None) => None,
+            };
+            let raw = &rest[..2 + len + 1];
+            let text = match value {
+                Some(value) => context
+                    .quote(&value)
+                    .map_err(|reason| {
+                        Error::Config(format!(
+                            "Environment variable {} referenced in config file on line {} {}",
+                            name, line, reason
+                        ))
+                    })?
+                    .into_owned(),
+                None if unset == Unset::Placeholder && context.is_string() => raw.to_string(),
+                None => {
+                    return Err(Error::Config(format!(
+                        "Environment variable {} referenced in config file on line {} is not set",
+                        name, line
+                    )));
+                }
+            };
+
+            replacements.push(Replacement {
+                expanded: expanded.len()..expanded.len() + text.len(),
+                raw: pos..pos + raw.len(),
+            });
+            expanded.push_str(&text);
+            pos += raw.len();
+            continue;
+        }
+
+        let mut chars = rest.chars();
+        let c = chars.next().unwrap_or_default();
+        let mut len = c.len_utf8();
+        match (context, c) {
+            (_, '\n') => {
+                line += 1;
+                if !matches!(context, Context::MultiBasic | Context::MultiLiteral) {
+                    context = Context::Bare;
+                }
+            }
+            (Context::Bare, '#') => context = Context::Comment,
+            (Context::Bare, '"') if rest.starts_with("\"\"\"") => {
+                context = Context::MultiBasic;
+                len = 3;
+            }
+            (Context::Bare, '"') => context = Context::Basic,
+            (Context::Bare, '\'') if rest.starts_with("'''") => {
+                context = Context::MultiLiteral;
+                len = 3;
+            }
+            (Context::Bare, '\'') => context = Context::Literal,
+            (Context::Basic | Context::MultiBasic, '\\') => {
+                // Copy the escaped character too, so `\"` does not end the
+                // string
+                len += chars
+                    .next()
+                    .filter(|&c| c != '\n')
+                    .map_or(0, char::len_utf8);
+            }
+            (Context::Basic, '"') | (Context::Literal, '\'') => context = Context::Bare,
+            (Context::MultiBasic, '"') if rest.starts_with("\"\"\"") => {
+                context = Context::Bare;
+                len = 3;
+            }
+            (Context::MultiLiteral, '\'') if rest.starts_with("'''") => {
+                context = Context::Bare;
+                len = 3;
+            }
+            _ => {}
+        }
+        expanded.push_str(&rest[..len]);
+        pos += len;
+    }
+
+    Ok(Interpolated {
+        content: expanded,
+        replacements,
+    })
+}
+
+impl Interpolated {
+    /// The values of the expanded document that contain a reference, with
+    /// their source in `raw`, the file this was expanded from
+    ///
+    /// Empty if the expanded document is not valid TOML; parsing the config
+    /// reports that.
+    pub(crate) fn templates(&self, raw: &str) -> Vec<EnvTemplate> {
+        if self.replacements.is_empty() {
+            return Vec::new();
+        }
+        let (Ok(document), Ok(values)) =
+            (DeTable::parse(&self.content), self.content.parse::<Table>())
+        else {
+            return Vec::new();
+        };
+
+        let mut spans = Vec::new();
+        collect_spans(
+            &Spanned::new(document.span(), DeValue::Table(document.into_inner())),
+            &self.replacements,
+            &mut Vec::new(),
+            &mut spans,
+        );
+
+        let values = Value::Table(values);
+        spans
+            .into_iter()
+            .filter_map(|(path, span)| {
+                let value = lookup(&values, &path)?.clone();
+                let source =
+                    raw[self.raw_offset(span.start)..self.raw_offset(span.end)].to_string();
+                Some(EnvTemplate {
+                    path,
+                    value,
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// Map an offset in the expanded document outside any replacement to
+    /// the same place in the original file
+    fn raw_offset(&self, offset: usize) -> usize {
+        self.replacements
+            .iter()
+            .filter(|r| r.expanded.end <= offset)
+            .fold(offset, |offset, r| offset + r.raw.len() - r.expanded.len())
+    }
+}
+
+/// Record the path and span of each value in `value` that contains a
+/// replacement
+///
+/// Tables are always descended into, since a value's span lies inside its
+/// key/value pair rather than the table header. An inline array is recorded
+/// as a whole if a replacement is not inside any single element.
+fn collect_spans(
+    value: &Spanned<DeValue<'_>>,
+    replacements: &[Replacement],
+    path: &mut Vec<PathSegment>,
+    spans: &mut Vec<(Vec<PathSegment>, Range<usize>)>,
+) {
+    let span = value.span();
+    let mut inside = replacements
+        .iter()
+        .filter(|r| r.expanded.start < span.end && span.start < r.expanded.end);
+
+    match value.get_ref() {
+        DeValue::Table(table) => {
+            for (key, value) in table.iter() {
+                path.push(PathSegment::Key(key.get_ref().to_string()));
+                collect_spans(value, replacements, path, spans);
+                path.pop();
+            }
+        }
+        DeValue::Array(array) => {
+            let inline = !array
+                .iter()
+                .any(|element| matches!(element.get_ref(), DeValue::Table(_)));
+            let outside_elements = inside.any(|r| {
+                !array.iter().any(|element| {
+                    element.span().start <= r.expanded.start && r.expanded.end <= element.span().end
+                })
+            });
+            if inline && outside_elements {
+                spans.push((path.clone(), span));
+                return;
+            }
+            for (index, element) in array.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                collect_spans(element, replacements, path, spans);
+                path.pop();
+            }
+        }
+        _ => {
+            if inside.next().is_some() {
+                spans.push((path.clone(), span));
+            }
+        }
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[PathSegment]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, segment| match segment {
+        PathSegment::Key(key) => value.get(key),
+        PathSegment::Index(index) => value.get(*index),
+    })
+}
+
+fn lookup_span(value: &Spanned<DeValue<'_>>, path: &[PathSegment]) -> Option<Range<usize>> {
+    let Some((segment, path)) = path.split_first() else {
+        return match value.get_ref() {
+            DeValue::Table(_) => None,
+            DeValue::Array(array)
+                if array
+                    .iter()
+                    .any(|element| matches!(element.get_ref(), DeValue::Table(_))) =>
+            {
+                None
+            }
+            _ => Some(value.span()),
+        };
+    };
+    let child = match (value.get_ref(), segment) {
+        (DeValue::Table(table), PathSegment::Key(key)) => table.get(key.as_str())?,
+        (DeValue::Array(array), PathSegment::Index(index)) => array.get(*index)?,
+        _ => return None,
+    };
+    lookup_span(child, path)
+}
+
+/// Write the references in `templates` back into `content`, a serialized
+/// config, where the value has not changed since it was loaded
+///
+/// Any other `${` in `content` is escaped as `$${`, so it does not turn
+/// into a reference when the file is loaded again.
+pub(crate) fn restore_templates(content: &str, templates: &[EnvTemplate]) -> String {
+    let mut restore: Vec<(Range<usize>, &str)> = Vec::new();
+    if !templates.is_empty() {
+        if let (Ok(document), Ok(values)) = (DeTable::parse(content), content.parse::<Table>()) {
+            let document = Spanned::new(document.span(), DeValue::Table(document.into_inner()));
+            let values = Value::Table(values);
+            for template in templates {
+                if lookup(&values, &template.path) != Some(&template.value) {
+                    continue;
+                }
+                if let Some(span) = lookup_span(&document, &template.path) {
+                    restore.push((span, &template.source));
+                }
+            }
+        }
+    }
+    restore.sort_by_key(|(span, _)| span.start);
+
+    let mut restored = String::with_capacity(content.len());
+    let mut pos = 0;
+    for (span, source) in restore {
+        if span.start < pos {
+            continue;
+        }
+        restored.push_str(&content[pos..span.start].replace("${", "$${"));
+        restored.push_str(source);
+        pos = span.end;
+    }
+    restored.push_str(&content[pos..].replace("${", "$${"));
+    restored
+}
+
+/// `value` as the body of a double-quoted TOML string
+fn escape_basic(value: &str) -> Cow<'_, str> {
+    if !value
+        .chars()
+        .any(|c| c == '"' || c == '\\' || c.is_control())
+    {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Whether `name` is a valid environment variable name: letters, digits and
+/// underscores, not starting with a digit
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "SMOTRA_API_KEY" => Some("sk_from_env".to_string()),
+            "EMPTY" => Some(String::new()),
+            "QUOTED" => Some("a\"b\\c\nd".to_string()),
+            _ => None,
+        }
+    }
+
+    fn expand(content: &str) -> Result<String> {
+        interpolate(content, lookup, Unset::Error).map(|i| i.content)
+    }
+
+    #[test]
+    fn test_defined_variable_is_expanded() {
+        assert_eq!(
+            expand("api_key = \"${SMOTRA_API_KEY}\"\n").unwrap(),
+            "api_key = \"sk_from_env\"\n"
+        );
+        assert_eq!(expand("key = \"${EMPTY}\"").unwrap(), "key = \"\"");
+    }
+
+    #[test]
+    fn test_undefined_variable_is_an_error() {
+        let err = expand("url = \"x\"\napi_key = \"${MISSING}\"\n").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("MISSING"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+    }
+
+    #[test]
+    fn test_default_used_when_unset_or_empty() {
+        assert_eq!(
+            expand("interval_secs = ${INTERVAL:-60}").unwrap(),
+            "interval_secs = 60"
+        );
+        assert_eq!(
+            expand("name = \"${EMPTY:-agent}\"").unwrap(),
+            "name = \"agent\""
+        );
+        assert_eq!(
+            expand("key = \"${SMOTRA_API_KEY:-unused}\"").unwrap(),
+            "key = \"sk_from_env\""
+        );
+        assert_eq!(expand("a = \"${MISSING:-}\"").unwrap(), "a = \"\"");
+    }
+
+    #[test]
+    fn test_escapes_comments_and_invalid_references() {
+        assert_eq!(
+            expand("cmd = \"echo $${HOME}\"").unwrap(),
+            "cmd = \"echo ${HOME}\""
+        );
+        assert_eq!(
+            expand("  # api_key = \"${MISSING}\"\n").unwrap(),
+            "  # api_key = \"${MISSING}\"\n"
+        );
+        assert!(expand("a = \"${MISSING\"").is_err());
+        assert!(expand("a = \"${MISSING\n}\"").is_err());
+        assert!(expand("a = \"${1BAD}\"").is_err());
+        assert!(expand("a = \"${}\"").is_err());
+    }
+
+    #[test]
+    fn test_inline_comment_is_not_expanded() {
+        assert_eq!(
+            expand("a = \"x\" # set ${MISSING} to override\nb = ${INTERVAL:-1}\n").unwrap(),
+            "a = \"x\" # set ${MISSING} to override\nb = 1\n"
+        );
+        assert_eq!(
+            expand("a = \"# ${SMOTRA_API_KEY}\"").unwrap(),
+            "a = \"# sk_from_env\""
+        );
+        assert_eq!(
+            expand("a = 'it''s' # ${MISSING}").unwrap(),
+            "a = 'it''s' # ${MISSING}"
+        );
+    }
+
+    #[test]
+    fn test_values_are_escaped_for_their_context() {
+        let content = expand("a = \"${QUOTED}\"\nb = \"\"\"\n${QUOTED}\"\"\"\n").unwrap();
+        assert_eq!(
+            content,
+            "a = \"a\\\"b\\\\c\\nd\"\nb = \"\"\"\na\\\"b\\\\c\\nd\"\"\"\n"
+        );
+        let table: Table = content.parse().unwrap();
+        assert_eq!(table["a"].as_str(), Some("a\"b\\c\nd"));
+        assert_eq!(table["b"].as_str(), Some("a\"b\\c\nd"));
+
+        assert_eq!(
+            expand("a = \"\\\"${SMOTRA_API_KEY}\"").unwrap(),
+            "a = \"\\\"sk_from_env\""
+        );
+        assert!(expand("a = '${QUOTED}'").is_err());
+        assert!(expand("a = ${QUOTED}").is_err());
+        assert!(expand("a = ${X:-1 # 2}").is_err());
+    }
+
+    #[test]
+    fn test_placeholder_keeps_unset_references_in_strings() {
+        let interpolated = interpolate(
+            "a = \"${MISSING}\"\nb = ${MISSING:-5}\n",
+            lookup,
+            Unset::Placeholder,
+        )
+        .unwrap();
+        assert_eq!(interpolated.content, "a = \"${MISSING}\"\nb = 5\n");
+        assert!(interpolate("b = ${MISSING}", lookup, Unset::Placeholder).is_err());
+    }
+
+    #[test]
+    fn test_templates_are_restored_when_unchanged() {
+        let raw = "key = \"${SMOTRA_API_KEY}\"\n\
+                   interval = ${INTERVAL:-60}\n\
+                   cmd = \"echo $${HOME}\"\n\
+                   name = \"plain\"\n\
+                   tags = [\"a\", \"${EMPTY:-b}\"]\n\
+                   [[endpoints]]\n\
+                   address = \"${HOST:-example.com}\"\n";
+        let interpolated = interpolate(raw, lookup, Unset::Error).unwrap();
+        let templates = interpolated.templates(raw);
+        assert_eq!(templates.len(), 5);
+
+        let mut values: Table = interpolated.content.parse().unwrap();
+        values.insert("name".to_string(), Value::from("changed ${x}"));
+        let saved = restore_templates(&toml::to_string(&values).unwrap(), &templates);
+        assert!(saved.contains("key = \"${SMOTRA_API_KEY}\""), "{}", saved);
+        assert!(saved.contains("interval = ${INTERVAL:-60}"), "{}", saved);
+        assert!(saved.contains("cmd = \"echo $${HOME}\""), "{}", saved);
+        assert!(saved.contains("\"${EMPTY:-b}\""), "{}", saved);
+        assert!(
+            saved.contains("address = \"${HOST:-example.com}\""),
+            "{}",
+            saved
+        );
+        assert!(saved.contains("name = \"changed $${x}\""), "{}", saved);
+
+        // A changed value is written as it is now
+        values.insert("interval".to_string(), Value::from(30));
+        let saved = restore_templates(&toml::to_string(&values).unwrap(), &templates);
+        assert!(saved.contains("interval = 30"), "{}", saved);
+        assert!(saved.contains("key = \"${SMOTRA_API_KEY}\""), "{}", saved);
+    }
+}
@@ -0,0 +1,317 @@
+//! Configuration linting
+//!
+//! Unlike `Config::validate()`, which rejects configurations the agent cannot
+//! run with, linting reports settings that are valid but likely to cause
+//! trouble: disabled TLS verification, intervals short enough to flood
+//! targets, very large ping counts, a cache directory that does not survive
+//! a reboot and API keys stored inline in the config file.
+
+use super::Config;
+use std::fmt;
+use std::path::Path;
+
+/// Monitoring, report and heartbeat intervals below this are flagged
+const MIN_RECOMMENDED_INTERVAL_SECS: u64 = 5;
+
+/// Ping counts above this are flagged
+const MAX_RECOMMENDED_PING_COUNT: u32 = 20;
+
+/// Area a lint warning belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintCategory {
+    /// Settings that weaken authentication or transport security
+    Security,
+
+    /// Settings that put excessive load on targets or the server
+    Load,
+
+    /// Settings that put local data at risk
+    Storage,
+}
+
+impl fmt::Display for LintCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Security => write!(f, "security"),
+            Self::Load => write!(f, "load"),
+            Self::Storage => write!(f, "storage"),
+        }
+    }
+}
+
+/// A suspicious setting found by `Config::lint()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// Area the warning belongs to
+    pub category: LintCategory,
+
+    /// Dotted path of the offending setting, e.g. `server.verify_tls`
+    pub field: &'static str,
+
+    /// Human-readable explanation
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.category, self.field, self.message)
+    }
+}
+
+impl Config {
+    /// Report valid but suspicious settings.
+    ///
+    /// Returns an empty list when nothing looks wrong.
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+
+        if !self.server.verify_tls {
+            warnings.push(LintWarning {
+                category: LintCategory::Security,
+                field: "server.verify_tls",
+                message: "TLS certificate verification is disabled".to_string(),
+            });
+        }
+
+        if self
+            .server
+            .api_key
+            .as_ref()
+            .is_some_and(|key| !key.is_empty())
+            && self.server.api_key_source.is_none()
+        {
+            warnings.push(LintWarning {
+                category: LintCategory::Security,
+                field: "server.api_key",
+                message:
+                    "API key is stored inline; consider an env:, file: or cmd: secret reference"
+                        .to_string(),
+            });
+        }
+
+        for (field, secs) in [
+            ("monitoring.interval_secs", self.monitoring.interval_secs),
+            (
+                "server.report_interval_secs",
+                self.server.report_interval_secs,
+            ),
+            (
+                "server.heartbeat_interval_secs",
+                self.server.heartbeat_interval_secs,
+            ),
+        ] {
+            if secs > 0 && secs < MIN_RECOMMENDED_INTERVAL_SECS {
+                warnings.push(LintWarning {
+                    category: LintCategory::Load,
+                    field,
+                    message: format!(
+                        "interval of {}s is below the recommended minimum of {}s",
+                        secs, MIN_RECOMMENDED_INTERVAL_SECS
+                    ),
+                });
+            }
+        }
+
+        if self.monitoring.ping_count > MAX_RECOMMENDED_PING_COUNT {
+            warnings.push(LintWarning {
+                category: LintCategory::Load,
+                field: "monitoring.ping_count",
+                message: format!(
+                    "{} pings per check exceeds the recommended maximum of {}",
+                    self.monitoring.ping_count, MAX_RECOMMENDED_PING_COUNT
+                ),
+            });
+        }
+
+        if is_on_tmpfs(Path::new(&self.storage.cache_dir)) {
+            warnings.push(LintWarning {
+                category: LintCategory::Storage,
+                field: "storage.cache_dir",
+                message: format!(
+                    "{} is on a tmpfs; cached results and agent state are lost on reboot",
+                    self.storage.cache_dir
+                ),
+            });
+        }
+
+        warnings
+    }
+}
+
+/// Whether `path` lives on a tmpfs mount
+#[cfg(target_os = "linux")]
+fn is_on_tmpfs(path: &Path) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+
+    // The cache directory may not exist yet; use its closest existing ancestor.
+    let resolved = path
+        .ancestors()
+        .find_map(|p| p.canonicalize().ok())
+        .or_else(|| std::env::current_dir().ok());
+
+    resolved
+        .and_then(|p| mount_fs_type(&mounts, &p).map(|fs| fs == "tmpfs"))
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_on_tmpfs(_path: &Path) -> bool {
+    false
+}
+
+/// Filesystem type of the mount containing `path`, given `/proc/mounts` content
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mount_fs_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            Some((mount_point, fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.len())
+        .map(|(_, fs_type)| fs_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_config::SecretSource;
+    use uuid::Uuid;
+
+    /// Config that produces no warnings, independent of where tests run
+    fn clean_config() -> Config {
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Default::default()
+        };
+        config.storage.cache_dir = env!("CARGO_MANIFEST_DIR").to_string();
+        config
+    }
+
+    fn fields(config: &Config) -> Vec<&'static str> {
+        config.lint().iter().map(|w| w.field).collect()
+    }
+
+    #[test]
+    fn test_default_config_has_no_warnings() {
+        let config = clean_config();
+        if !is_on_tmpfs(Path::new(&config.storage.cache_dir)) {
+            assert!(config.lint().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_verify_tls_disabled_warns() {
+        let mut config = clean_config();
+        config.server.verify_tls = false;
+
+        let warning = config
+            .lint()
+            .into_iter()
+            .find(|w| w.field == "server.verify_tls")
+            .expect("verify_tls warning");
+        assert_eq!(warning.category, LintCategory::Security);
+    }
+
+    #[test]
+    fn test_short_monitoring_interval_warns() {
+        let mut config = clean_config();
+        config.monitoring.interval_secs = 2;
+
+        let warning = config
+            .lint()
+            .into_iter()
+            .find(|w| w.field == "monitoring.interval_secs")
+            .expect("interval warning");
+        assert_eq!(warning.category, LintCategory::Load);
+        assert!(warning.message.contains("2s"));
+    }
+
+    #[test]
+    fn test_interval_at_threshold_does_not_warn() {
+        let mut config = clean_config();
+        config.monitoring.interval_secs = MIN_RECOMMENDED_INTERVAL_SECS;
+        assert!(!fields(&config).contains(&"monitoring.interval_secs"));
+    }
+
+    #[test]
+    fn test_huge_ping_count_warns() {
+        let mut config = clean_config();
+        config.monitoring.ping_count = 500;
+
+        let warning = config
+            .lint()
+            .into_iter()
+            .find(|w| w.field == "monitoring.ping_count")
+            .expect("ping_count warning");
+        assert_eq!(warning.category, LintCategory::Load);
+    }
+
+    #[test]
+    fn test_inline_api_key_warns() {
+        let mut config = clean_config();
+        config.server.api_key = Some("sk_inline".to_string());
+
+        let warning = config
+            .lint()
+            .into_iter()
+            .find(|w| w.field == "server.api_key")
+            .expect("api_key warning");
+        assert_eq!(warning.category, LintCategory::Security);
+    }
+
+    #[test]
+    fn test_api_key_from_secret_source_does_not_warn() {
+        let mut config = clean_config();
+        config.server.api_key = Some("sk_resolved".to_string());
+        config.server.api_key_source = Some(SecretSource::Env("SMOTRA_API_KEY".to_string()));
+
+        assert!(!fields(&config).contains(&"server.api_key"));
+    }
+
+    #[test]
+    fn test_mount_fs_type_picks_longest_prefix() {
+        let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+tmpfs /tmp tmpfs rw,nosuid,nodev 0 0
+/dev/sdb1 /tmp/data xfs rw 0 0
+";
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/tmp/smotra/cache")),
+            Some("tmpfs")
+        );
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/tmp/data/cache")),
+            Some("xfs")
+        );
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/var/lib/smotra")),
+            Some("ext4")
+        );
+    }
+
+    #[test]
+    fn test_mount_fs_type_respects_path_components() {
+        let mounts = "tmpfs /tmp tmpfs rw 0 0\n/dev/sda1 / ext4 rw 0 0\n";
+        assert_eq!(mount_fs_type(mounts, Path::new("/tmpdata")), Some("ext4"));
+    }
+
+    #[test]
+    fn test_warning_display() {
+        let warning = LintWarning {
+            category: LintCategory::Security,
+            field: "server.verify_tls",
+            message: "TLS certificate verification is disabled".to_string(),
+        };
+        assert_eq!(
+            warning.to_string(),
+            "[security] server.verify_tls: TLS certificate verification is disabled"
+        );
+    }
+}
@@ -1,30 +1,81 @@
 //! Configuration loading, saving, and validation
 
-use tracing::info;
+use tracing::{info, warn};
 use uuid::Uuid;
 
-use super::Config;
+use super::interpolate::{interpolate_env, restore_templates, Unset};
+use super::{
+    Config, DnsResolverConfig, IpVersion, ReportMode, SecretSource, MAX_PING_PAYLOAD_BYTES,
+};
 use crate::claim::AgentCredentials;
+use crate::core::SuccessQuorum;
 use crate::error::{Error, Result};
+use crate::sinks::parse_broker_url;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::fs as async_fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncWriteExt};
 
 impl Config {
     /// Load configuration from a TOML file
+    ///
+    /// `${NAME}` and `${NAME:-default}` in the file are replaced with
+    /// environment variables before it is parsed; an undefined variable
+    /// without a default is an error. The references are recorded in
+    /// `env_templates` and written back on save.
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
-        let content = fs::read_to_string(path.as_ref())
-            .map_err(|e| Error::Config(format!("Failed to read config file: {}", e)))?;
+        let mut config = Self::read_interpolated(path, Unset::Error)?;
+        config.server.resolve_api_key()?;
+        Ok(config)
+    }
+
+    /// Load configuration from a TOML file without resolving secrets.
+    ///
+    /// A secret reference in `server.api_key` is recorded in `api_key_source`
+    /// but not read, so no environment lookup, file read or command runs;
+    /// `api_key` keeps the reference itself. `${NAME}` references are
+    /// expanded as by [`Config::from_file`], except that an undefined
+    /// variable without a default stays as it is inside a quoted string.
+    /// Meant for offline checks such as CI validation, not for running the
+    /// agent.
+    pub fn from_file_unresolved(path: impl AsRef<Path>) -> Result<Self> {
+        let mut config = Self::read_interpolated(path, Unset::Placeholder)?;
+        config.server.api_key_source = config
+            .server
+            .api_key
+            .as_deref()
+            .and_then(SecretSource::parse);
+        Ok(config)
+    }
+
+    fn read_file(path: impl AsRef<Path>) -> Result<String> {
+        fs::read_to_string(path.as_ref())
+            .map_err(|e| Error::Config(format!("Failed to read config file: {}", e)))
+    }
+
+    fn read_interpolated(path: impl AsRef<Path>, unset: Unset) -> Result<Self> {
+        let raw = Self::read_file(path)?;
+        let interpolated = interpolate_env(&raw, unset)?;
+        let mut config = Self::parse(&interpolated.content)?;
+        config.env_templates = interpolated.templates(&raw);
+        Ok(config)
+    }
 
-        toml::from_str(&content)
-            .map_err(|e| Error::Config(format!("Failed to parse config: {}", e)))
+    fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| Error::Config(format!("Failed to parse config: {}", e)))
     }
 
     /// Save configuration to a TOML file asynchronously with secure permissions
     ///
-    /// This method writes the configuration and sets file permissions to 0600
-    /// on Unix systems (owner read/write only) to protect sensitive data like API keys.
+    /// This method writes the configuration with file permissions 0600 on Unix
+    /// systems (owner read/write only) to protect sensitive data like API keys.
+    /// If the API key was resolved from a secret reference, the reference is
+    /// written instead of the resolved key. Values loaded from `${NAME}`
+    /// references are written as the references, unless they have been
+    /// changed since.
+    ///
+    /// The configuration is written to a temporary file next to `path` and
+    /// renamed into place, so a crash mid-write leaves the previous file intact.
     ///
     /// # Arguments
     ///
@@ -34,57 +85,89 @@ impl Config {
     ///
     /// Returns an error if:
     /// * Serialization to TOML fails
-    /// * File cannot be created or written
+    /// * The temporary file cannot be created, written or renamed into place
     /// * Permissions cannot be set (Unix only)
     pub async fn save_to_file_secure(&self, path: impl AsRef<Path>) -> Result<()> {
-        let path = path.as_ref();
-        let content = toml::to_string_pretty(self)
-            .map_err(|e| Error::Config(format!("Failed to serialize config: {}", e)))?;
+        let mut persisted = self.clone();
+        if let Some(source) = &self.server.api_key_source {
+            persisted.server.api_key = Some(source.to_string());
+        }
 
-        // Write to file
-        let mut file = async_fs::File::create(path)
-            .await
-            .map_err(|e| Error::Config(format!("Failed to create config file: {}", e)))?;
+        let content = toml::to_string_pretty(&persisted)
+            .map_err(|e| Error::Config(format!("Failed to serialize config: {}", e)))?;
+        let content = restore_templates(&content, &self.env_templates);
 
-        file.write_all(content.as_bytes())
-            .await
-            .map_err(|e| Error::Config(format!("Failed to write config file: {}", e)))?;
+        replace_file_secure(path.as_ref(), content.as_bytes()).await
+    }
 
-        file.flush()
-            .await
-            .map_err(|e| Error::Config(format!("Failed to flush config file: {}", e)))?;
+    /// Save the configuration after a successful claim, backing up the file
+    /// it replaces
+    ///
+    /// Unless `server.claiming.backup_config` is off, an existing file at
+    /// `path` is first copied to `server.claiming.backup_path` (by default
+    /// `path` with `.bak` appended) with 0600 permissions on Unix, so a
+    /// hand-edited config survives a bad merge. The configuration is then
+    /// written with [`Config::save_to_file_secure`].
+    ///
+    /// Returns the path of the backup, if one was written.
+    pub async fn save_claimed(&self, path: impl AsRef<Path>) -> Result<Option<PathBuf>> {
+        let path = path.as_ref();
+        let claiming = &self.server.claiming;
 
-        // Set secure permissions on Unix systems
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
+        let backup = match &claiming.backup_path {
+            _ if !claiming.backup_config || !path.exists() => None,
+            Some(backup_path) => Some(PathBuf::from(backup_path)),
+            None => {
+                let mut backup_path = path.as_os_str().to_owned();
+                backup_path.push(".bak");
+                Some(PathBuf::from(backup_path))
+            }
+        };
 
-            let mut perms = file
-                .metadata()
+        if let Some(backup) = &backup {
+            let content = async_fs::read(path)
                 .await
-                .map_err(|e| Error::Config(format!("Failed to read file metadata: {}", e)))?
-                .permissions();
-            perms.set_mode(0o600); // Owner read/write only
-            async_fs::set_permissions(path, perms)
+                .map_err(|e| Error::Config(format!("Failed to read config file: {}", e)))?;
+            replace_file_secure(backup, content.as_slice())
                 .await
-                .map_err(|e| Error::Config(format!("Failed to set file permissions: {}", e)))?;
+                .map_err(|e| match e {
+                    Error::Config(msg) => Error::Config(format!(
+                        "Failed to back up config file to {}: {}",
+                        backup.display(),
+                        msg
+                    )),
+                    e => e,
+                })?;
         }
 
-        Ok(())
+        self.save_to_file_secure(path).await?;
+        Ok(backup)
     }
 
     /// Apply claim result to configuration
     ///
-    /// Updates the configuration with the agent ID and API key obtained
-    /// from the claiming workflow. This method should be called before
+    /// Updates the configuration with the agent ID, API key and configuration
+    /// URL obtained from the claiming workflow. This method should be called before
     /// saving the configuration to persist the claim result.
     ///
+    /// If the API key comes from a secret reference, the claimed key is only
+    /// kept in memory and must be stored in that secret source by the operator.
+    ///
     /// # Arguments
     ///
     /// * `claim_result` - Result from the claiming workflow containing API key and agent ID
     pub fn apply_claim_result(&mut self, claim_result: AgentCredentials) {
+        if let Some(source) = &self.server.api_key_source {
+            warn!(
+                "API key is read from {}; the claimed key will not be written to the config file",
+                source
+            );
+        }
         self.agent_id = claim_result.agent_id;
         self.server.api_key = Some(claim_result.api_key);
+        if claim_result.config_url.is_some() {
+            self.server.config_url = claim_result.config_url;
+        }
     }
 
     /// Validate the configuration
@@ -105,15 +188,299 @@ impl Config {
             ));
         }
 
+        if self.server.heartbeat_interval_secs == 0 {
+            return Err(Error::Config(
+                "server heartbeat_interval must be greater than 0".to_string(),
+            ));
+        }
+
         if self.server.timeout_secs == 0 {
             return Err(Error::Config(
                 "server timeout must be greater than 0".to_string(),
             ));
         }
 
-        if self.server.report_interval_secs < self.server.timeout_secs * 2 {
+        if self.server.connection_failure_grace == Some(0) {
+            return Err(Error::Config(
+                "server connection_failure_grace must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.monitoring.timeout_secs >= self.monitoring.interval_secs {
+            return Err(Error::Config(
+                "monitoring timeout must be less than the monitoring interval".to_string(),
+            ));
+        }
+
+        if self.logging.success_sample_rate == 0 {
+            return Err(Error::Config(
+                "logging success_sample_rate must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.server.max_report_bytes == Some(0) {
+            return Err(Error::Config(
+                "server max_report_bytes must be greater than 0".to_string(),
+            ));
+        }
+
+        let thresholds = &self.server.heartbeat_thresholds;
+        for (name, percent) in [
+            ("cpu_percent", thresholds.cpu_percent),
+            ("memory_percent", thresholds.memory_percent),
+        ] {
+            if !(percent > 0.0 && percent <= 100.0) {
+                return Err(Error::Config(format!(
+                    "server heartbeat_thresholds.{} must be greater than 0 and at most 100",
+                    name
+                )));
+            }
+        }
+        if thresholds
+            .memory_mb
+            .is_some_and(|mb| !(mb > 0.0 && mb.is_finite()))
+        {
+            return Err(Error::Config(
+                "server heartbeat_thresholds.memory_mb must be greater than 0".to_string(),
+            ));
+        }
+
+        for (kind, &limit) in &self.monitoring.max_concurrent_per_kind {
+            if limit == 0 || limit > self.monitoring.max_concurrent {
+                return Err(Error::Config(format!(
+                    "monitoring max_concurrent_per_kind.{} must be between 1 and max_concurrent ({})",
+                    kind, self.monitoring.max_concurrent
+                )));
+            }
+        }
+
+        if let DnsResolverConfig::Nameservers {
+            nameservers,
+            protocol,
+            tls_name,
+        } = &self.monitoring.resolver
+        {
+            if nameservers.is_empty() {
+                return Err(Error::Config(
+                    "monitoring resolver.nameservers must list at least one nameserver".to_string(),
+                ));
+            }
+            self.monitoring
+                .resolver
+                .nameserver_addrs()
+                .map_err(|e| Error::Config(format!("monitoring resolver: {}", e)))?;
+            if protocol.uses_tls() && tls_name.as_deref().is_none_or(str::is_empty) {
+                return Err(Error::Config(
+                    "monitoring resolver.tls_name is required for the tls and https protocols"
+                        .to_string(),
+                ));
+            }
+        }
+
+        if self
+            .monitoring
+            .ping_payload_bytes
+            .is_some_and(|bytes| bytes > MAX_PING_PAYLOAD_BYTES)
+        {
+            return Err(Error::Config(format!(
+                "monitoring ping_payload_bytes must be at most {}",
+                MAX_PING_PAYLOAD_BYTES
+            )));
+        }
+
+        if self.monitoring.traceroute_probes_per_hop == Some(0) {
+            return Err(Error::Config(
+                "monitoring traceroute_probes_per_hop must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.monitoring.dscp.is_some_and(|dscp| dscp > 63) {
+            return Err(Error::Config(
+                "monitoring dscp must be between 0 and 63".to_string(),
+            ));
+        }
+
+        if self.monitoring.dns_timeout_secs == Some(0) {
+            return Err(Error::Config(
+                "monitoring dns_timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.monitoring.reverse_dns_timeout_secs == Some(0) {
+            return Err(Error::Config(
+                "monitoring reverse_dns_timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+
+        if let Some(deadline) = self.monitoring.cycle_deadline_secs {
+            if deadline == 0 || deadline > self.monitoring.interval_secs {
+                return Err(Error::Config(
+                    "monitoring cycle_deadline_secs must be greater than 0 and at most the monitoring interval"
+                        .to_string(),
+                ));
+            }
+        }
+
+        if self
+            .monitoring
+            .check_latency_budget_percent
+            .is_some_and(|percent| percent == 0 || percent > 100)
+        {
+            return Err(Error::Config(
+                "monitoring check_latency_budget_percent must be between 1 and 100".to_string(),
+            ));
+        }
+
+        if let Some((start, end)) = self.monitoring.source_port_range {
+            if start == 0 || start > end {
+                return Err(Error::Config(format!(
+                    "monitoring source_port_range [{}, {}] must satisfy 0 < start <= end",
+                    start, end
+                )));
+            }
+        }
+
+        if let Some(source) = self.monitoring.source_address {
+            if source.is_unspecified() || source.is_multicast() {
+                return Err(Error::Config(format!(
+                    "monitoring source_address {} must be a unicast address of this host",
+                    source
+                )));
+            }
+            let conflicting = match self.monitoring.ip_version {
+                IpVersion::V4 if source.is_ipv6() => Some("v4"),
+                IpVersion::V6 if source.is_ipv4() => Some("v6"),
+                _ => None,
+            };
+            if let Some(ip_version) = conflicting {
+                return Err(Error::Config(format!(
+                    "monitoring source_address {} does not match ip_version {}",
+                    source, ip_version
+                )));
+            }
+        }
+
+        if self.monitoring.http.connect_timeout_secs == Some(0)
+            || self.monitoring.http.read_timeout_secs == Some(0)
+        {
+            return Err(Error::Config(
+                "monitoring http connect_timeout_secs and read_timeout_secs must be greater than 0"
+                    .to_string(),
+            ));
+        }
+
+        if let Some(SuccessQuorum::AtLeast(count)) = self.monitoring.success_quorum {
+            if count > self.monitoring.ping_count {
+                return Err(Error::Config(format!(
+                    "monitoring success_quorum of {} exceeds ping_count {}",
+                    count, self.monitoring.ping_count
+                )));
+            }
+        }
+
+        if self.storage.encrypt
+            && self
+                .storage
+                .encryption_key
+                .as_deref()
+                .is_none_or(|key| key.trim().is_empty())
+        {
+            return Err(Error::Config(
+                "storage encrypt requires storage encryption_key".to_string(),
+            ));
+        }
+
+        if self.alerting.enabled {
+            if !self.alerting.webhook_url.starts_with("http://")
+                && !self.alerting.webhook_url.starts_with("https://")
+            {
+                return Err(Error::Config(
+                    "alerting webhook_url must be an http:// or https:// URL".to_string(),
+                ));
+            }
+            if self.alerting.failure_threshold == 0 {
+                return Err(Error::Config(
+                    "alerting failure_threshold must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        if let Some(breaker) = &self.monitoring.circuit_breaker {
+            if breaker.failure_threshold == 0 || breaker.cooldown_secs == 0 {
+                return Err(Error::Config(
+                    "monitoring circuit_breaker failure_threshold and cooldown_secs must be greater than 0"
+                        .to_string(),
+                ));
+            }
+        }
+
+        if self.reporting.bucket_secs == Some(0) {
+            return Err(Error::Config(
+                "reporting bucket_secs must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.reporting.mode == ReportMode::Stream && self.reporting.bucket_secs.is_some() {
+            return Err(Error::Config(
+                "reporting mode \"stream\" cannot be combined with bucket_secs".to_string(),
+            ));
+        }
+
+        for entry in &self.thresholds {
+            if entry.tag.is_empty() {
+                return Err(Error::Config("thresholds tag cannot be empty".to_string()));
+            }
+            validate_thresholds(
+                &format!("thresholds for tag {}", entry.tag),
+                entry.warning_ms,
+                entry.critical_ms,
+            )?;
+            validate_packet_loss_thresholds(
+                &format!("thresholds for tag {}", entry.tag),
+                entry.packet_loss_warning_percent,
+                entry.packet_loss_critical_percent,
+            )?;
+        }
+
+        for endpoint in &self.endpoints {
+            endpoint.validate()?;
+            validate_thresholds(
+                &format!("endpoint {}", endpoint.address),
+                endpoint.warning_ms,
+                endpoint.critical_ms,
+            )?;
+            if endpoint
+                .interval_secs
+                .is_some_and(|secs| secs <= self.monitoring.timeout_secs as i64)
+            {
+                return Err(Error::Config(format!(
+                    "endpoint {} interval_secs must be greater than the monitoring timeout",
+                    endpoint.address
+                )));
+            }
+            if let Some(quorum) = &endpoint.success_quorum {
+                let quorum: SuccessQuorum = quorum.parse()?;
+                if let SuccessQuorum::AtLeast(count) = quorum {
+                    if count > self.monitoring.ping_count {
+                        return Err(Error::Config(format!(
+                            "endpoint {} success_quorum of {} exceeds ping_count {}",
+                            endpoint.address, count, self.monitoring.ping_count
+                        )));
+                    }
+                }
+            }
+        }
+
+        if self.server.report_interval_secs < self.monitoring.interval_secs {
+            return Err(Error::Config(
+                "server report_interval must be greater than or equal to the monitoring interval"
+                    .to_string(),
+            ));
+        }
+
+        if self.server.heartbeat_interval_secs > self.server.report_interval_secs {
             return Err(Error::Config(
-                "server report_interval should be at least two times greater than the monitoring interval"
+                "server heartbeat_interval must be less than or equal to the report interval"
                     .to_string(),
             ));
         }
@@ -128,6 +495,41 @@ impl Config {
             ));
         }
 
+        if let Some(mqtt) = &self.sinks.mqtt {
+            parse_broker_url(&mqtt.broker_url)?;
+
+            if mqtt.qos > 2 {
+                return Err(Error::Config(
+                    "sinks.mqtt qos must be 0, 1 or 2".to_string(),
+                ));
+            }
+
+            if mqtt.topic_template.is_empty() {
+                return Err(Error::Config(
+                    "sinks.mqtt topic_template cannot be empty".to_string(),
+                ));
+            }
+        }
+
+        if let Some(remote_write) = &self.sinks.remote_write {
+            if !remote_write.url.starts_with("http://") && !remote_write.url.starts_with("https://")
+            {
+                return Err(Error::Config(
+                    "sinks.remote_write url must be an http:// or https:// URL".to_string(),
+                ));
+            }
+
+            if remote_write.flush_interval_secs == 0
+                || remote_write.batch_size == 0
+                || remote_write.timeout_secs == 0
+            {
+                return Err(Error::Config(
+                    "sinks.remote_write flush_interval_secs, batch_size and timeout_secs must be greater than 0"
+                        .to_string(),
+                ));
+            }
+        }
+
         if self.update.github_repo_url.is_empty() {
             return Err(Error::Config(
                 "update check_url cannot be empty".to_string(),
@@ -166,10 +568,141 @@ impl Config {
     }
 }
 
+/// Replace `path` with everything read from `content`
+///
+/// The content goes to `<path>.tmp` with 0600 permissions on Unix, is synced
+/// to disk and then renamed over `path`. The directory is synced after the
+/// rename, so the new file survives power loss. On failure the temporary
+/// file is removed and `path` is left untouched.
+async fn replace_file_secure(path: &Path, mut content: impl AsyncRead + Unpin) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let written = async {
+        let mut options = async_fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600); // Owner read/write only
+        let mut file = options
+            .open(&tmp_path)
+            .await
+            .map_err(|e| Error::Config(format!("Failed to create config file: {}", e)))?;
+
+        // A temporary file left over from an earlier crash keeps its mode.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            async_fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))
+                .await
+                .map_err(|e| Error::Config(format!("Failed to set file permissions: {}", e)))?;
+        }
+
+        tokio::io::copy(&mut content, &mut file)
+            .await
+            .map_err(|e| Error::Config(format!("Failed to write config file: {}", e)))?;
+        file.flush()
+            .await
+            .map_err(|e| Error::Config(format!("Failed to flush config file: {}", e)))?;
+        file.sync_all()
+            .await
+            .map_err(|e| Error::Config(format!("Failed to sync config file: {}", e)))?;
+
+        async_fs::rename(&tmp_path, path)
+            .await
+            .map_err(|e| Error::Config(format!("Failed to replace config file: {}", e)))
+    }
+    .await;
+
+    if written.is_err() {
+        let _ = async_fs::remove_file(&tmp_path).await;
+    }
+    written?;
+    sync_parent_dir(path)
+        .await
+        .map_err(|e| Error::Config(format!("Failed to sync config directory: {}", e)))
+}
+
+/// Sync the directory holding `path`, so a rename in it survives power
+/// loss. Directories cannot be synced on Windows.
+async fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        async_fs::File::open(parent).await?.sync_all().await?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Latency thresholds must be positive, with warning at most critical
+fn validate_thresholds(
+    owner: &str,
+    warning_ms: Option<f64>,
+    critical_ms: Option<f64>,
+) -> Result<()> {
+    for value in [warning_ms, critical_ms].into_iter().flatten() {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(Error::Config(format!(
+                "{} warning_ms and critical_ms must be greater than 0",
+                owner
+            )));
+        }
+    }
+
+    if let (Some(warning), Some(critical)) = (warning_ms, critical_ms) {
+        if warning > critical {
+            return Err(Error::Config(format!(
+                "{} warning_ms of {} exceeds critical_ms of {}",
+                owner, warning, critical
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Packet loss thresholds must be percentages, with warning at most critical
+fn validate_packet_loss_thresholds(
+    owner: &str,
+    warning_percent: Option<f64>,
+    critical_percent: Option<f64>,
+) -> Result<()> {
+    for value in [warning_percent, critical_percent].into_iter().flatten() {
+        if !(0.0..=100.0).contains(&value) {
+            return Err(Error::Config(format!(
+                "{} packet_loss_warning_percent and packet_loss_critical_percent must be between 0 and 100",
+                owner
+            )));
+        }
+    }
+
+    if let (Some(warning), Some(critical)) = (warning_percent, critical_percent) {
+        if warning > critical {
+            return Err(Error::Config(format!(
+                "{} packet_loss_warning_percent of {} exceeds packet_loss_critical_percent of {}",
+                owner, warning, critical
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent_config::{
+        CircuitBreakerConfig, HttpCheckConfig, MonitoringConfig, MqttSinkConfig, PluginsConfig,
+    };
     use crate::claim::AgentCredentials;
+    use crate::core::{Endpoint, EndpointCheckType};
+    use crate::plugin::PluginInitPolicy;
+    use std::time::Duration;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -182,6 +715,7 @@ mod tests {
         let claim_result = AgentCredentials {
             api_key: "sk_test_123456".to_string(),
             agent_id: new_agent_id,
+            config_url: None,
         };
 
         config.apply_claim_result(claim_result);
@@ -206,6 +740,7 @@ mod tests {
         let claim_result = AgentCredentials {
             api_key: "new_key".to_string(),
             agent_id: new_agent_id,
+            config_url: None,
         };
 
         config.apply_claim_result(claim_result);
@@ -271,6 +806,7 @@ mod tests {
         let claim_result = AgentCredentials {
             api_key: "sk_integration_test".to_string(),
             agent_id,
+            config_url: None,
         };
         config.apply_claim_result(claim_result);
 
@@ -287,6 +823,1133 @@ mod tests {
         assert_eq!(loaded_config.agent_name, "Integration Test Agent");
     }
 
+    #[tokio::test]
+    async fn test_save_claimed_backs_up_previous_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let hand_edited = format!(
+            "# hand-edited, keep me\n{}",
+            toml::to_string(&valid_config()).unwrap()
+        );
+        std::fs::write(&path, &hand_edited).unwrap();
+
+        let mut config = Config::from_file(&path).unwrap();
+        config.apply_claim_result(AgentCredentials {
+            api_key: "sk_claimed".to_string(),
+            agent_id: Uuid::now_v7(),
+            config_url: None,
+        });
+        let backup = config.save_claimed(&path).await.unwrap();
+
+        assert_eq!(backup, Some(dir.path().join("config.toml.bak")));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("config.toml.bak")).unwrap(),
+            hand_edited
+        );
+        assert_eq!(
+            Config::from_file(&path).unwrap().server.api_key.as_deref(),
+            Some("sk_claimed")
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.path().join("config.toml.bak"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_claimed_backup_path_and_opt_out() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut config = valid_config();
+
+        // Nothing to back up before the first save
+        assert_eq!(config.save_claimed(&path).await.unwrap(), None);
+
+        let custom = dir.path().join("before-claim.toml");
+        config.server.claiming.backup_path = Some(custom.to_string_lossy().into_owned());
+        assert_eq!(
+            config.save_claimed(&path).await.unwrap(),
+            Some(custom.clone())
+        );
+        assert!(custom.exists());
+
+        config.server.claiming.backup_config = false;
+        std::fs::remove_file(&custom).unwrap();
+        assert_eq!(config.save_claimed(&path).await.unwrap(), None);
+        assert!(!custom.exists());
+        assert!(!dir.path().join("config.toml.bak").exists());
+    }
+
+    /// Reader that fails like a crash in the middle of a write
+    struct FailingReader;
+
+    impl AsyncRead for FailingReader {
+        fn poll_read(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            _buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Err(std::io::Error::other("injected write failure")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_leaves_original_config_untouched() {
+        use tokio::io::AsyncReadExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        let config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Default::default()
+        };
+        config.save_to_file_secure(&path).await.unwrap();
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        // Half of a new config is written before the failure.
+        let replacement = "agent_name = \"half written\"\n"
+            .as_bytes()
+            .chain(FailingReader);
+        let err = replace_file_secure(&path, replacement).await.unwrap_err();
+        assert!(err.to_string().contains("injected write failure"));
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        assert_eq!(Config::from_file(&path).unwrap().agent_id, config.agent_id);
+        assert_eq!(
+            std::fs::read_dir(dir.path()).unwrap().count(),
+            1,
+            "the temporary file should be removed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_parent_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        sync_parent_dir(&dir.path().join("config.toml"))
+            .await
+            .unwrap();
+        // A bare file name lives in the working directory
+        sync_parent_dir(Path::new("config.toml")).await.unwrap();
+    }
+
+    mod secret_tests {
+        use super::*;
+        use std::io::Write;
+
+        fn write_config(api_key: &str) -> NamedTempFile {
+            let config = Config {
+                agent_id: Uuid::now_v7(),
+                server: crate::ServerConfig {
+                    api_key: Some(api_key.to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(toml::to_string_pretty(&config).unwrap().as_bytes())
+                .unwrap();
+            file
+        }
+
+        #[test]
+        fn test_from_file_resolves_env_reference() {
+            std::env::set_var("SMOTRA_TEST_LOADER_ENV_KEY", "sk_env_loaded");
+            let file = write_config("env:SMOTRA_TEST_LOADER_ENV_KEY");
+
+            let config = Config::from_file(file.path()).unwrap();
+            assert_eq!(config.server.api_key.as_deref(), Some("sk_env_loaded"));
+            assert!(!config.server.is_claim_required());
+        }
+
+        #[test]
+        fn test_from_file_resolves_file_reference() {
+            let mut secret = NamedTempFile::new().unwrap();
+            writeln!(secret, "sk_file_loaded").unwrap();
+            let file = write_config(&format!("file:{}", secret.path().display()));
+
+            let config = Config::from_file(file.path()).unwrap();
+            assert_eq!(config.server.api_key.as_deref(), Some("sk_file_loaded"));
+        }
+
+        #[test]
+        fn test_from_file_fails_on_unresolvable_reference() {
+            let file = write_config("env:SMOTRA_TEST_LOADER_UNSET_KEY");
+            assert!(matches!(
+                Config::from_file(file.path()),
+                Err(Error::ConfigApiKey(_))
+            ));
+        }
+
+        #[test]
+        fn test_from_file_interpolates_env_variables() {
+            std::env::set_var("SMOTRA_TEST_LOADER_INTERPOLATED_KEY", "sk_interpolated");
+            std::env::remove_var("SMOTRA_TEST_LOADER_UNSET_INTERVAL");
+            let file = write_config("${SMOTRA_TEST_LOADER_INTERPOLATED_KEY}");
+            let content = std::fs::read_to_string(file.path()).unwrap().replace(
+                "\ninterval_secs = 60",
+                "\ninterval_secs = ${SMOTRA_TEST_LOADER_UNSET_INTERVAL:-42}",
+            );
+            std::fs::write(file.path(), content).unwrap();
+
+            let config = Config::from_file(file.path()).unwrap();
+            assert_eq!(config.server.api_key.as_deref(), Some("sk_interpolated"));
+            assert_eq!(config.monitoring.interval_secs, 42);
+        }
+
+        #[test]
+        fn test_from_file_fails_on_undefined_variable() {
+            std::env::remove_var("SMOTRA_TEST_LOADER_UNDEFINED_KEY");
+            let file = write_config("${SMOTRA_TEST_LOADER_UNDEFINED_KEY}");
+
+            let err = Config::from_file(file.path()).unwrap_err();
+            assert!(matches!(err, Error::Config(_)));
+            assert!(err.to_string().contains("SMOTRA_TEST_LOADER_UNDEFINED_KEY"));
+
+            // Offline checks keep the reference as a placeholder
+            let config = Config::from_file_unresolved(file.path()).unwrap();
+            assert_eq!(
+                config.server.api_key.as_deref(),
+                Some("${SMOTRA_TEST_LOADER_UNDEFINED_KEY}")
+            );
+        }
+
+        #[test]
+        fn test_from_file_unresolved_keeps_reference() {
+            let file = write_config("cmd:false");
+
+            let config = Config::from_file_unresolved(file.path()).unwrap();
+            assert_eq!(config.server.api_key.as_deref(), Some("cmd:false"));
+            assert_eq!(
+                config.server.api_key_source,
+                Some(SecretSource::Command("false".to_string()))
+            );
+            assert!(config.validate().is_ok());
+        }
+
+        #[tokio::test]
+        async fn test_save_does_not_write_back_resolved_key() {
+            std::env::set_var("SMOTRA_TEST_LOADER_NO_WRITEBACK", "sk_must_not_leak");
+            let file = write_config("env:SMOTRA_TEST_LOADER_NO_WRITEBACK");
+            let config = Config::from_file(file.path()).unwrap();
+
+            config.save_to_file_secure(file.path()).await.unwrap();
+
+            let content = fs::read_to_string(file.path()).unwrap();
+            assert!(!content.contains("sk_must_not_leak"));
+            assert!(content.contains("env:SMOTRA_TEST_LOADER_NO_WRITEBACK"));
+        }
+
+        #[tokio::test]
+        async fn test_save_writes_back_interpolated_values() {
+            std::env::set_var("SMOTRA_TEST_LOADER_TEMPLATE_KEY", "sk_\"must_not_leak");
+            let file = write_config("${SMOTRA_TEST_LOADER_TEMPLATE_KEY}");
+            let content = fs::read_to_string(file.path()).unwrap().replace(
+                "\ninterval_secs = 60",
+                "\ninterval_secs = ${SMOTRA_TEST_LOADER_TEMPLATE_INTERVAL:-60}",
+            );
+            fs::write(file.path(), content).unwrap();
+            let mut config = Config::from_file(file.path()).unwrap();
+            assert_eq!(config.server.api_key.as_deref(), Some("sk_\"must_not_leak"));
+
+            config.agent_name = "renamed".to_string();
+            config.save_to_file_secure(file.path()).await.unwrap();
+
+            let content = fs::read_to_string(file.path()).unwrap();
+            assert!(!content.contains("must_not_leak"));
+            assert!(content.contains("api_key = \"${SMOTRA_TEST_LOADER_TEMPLATE_KEY}\""));
+            assert!(
+                content.contains("\ninterval_secs = ${SMOTRA_TEST_LOADER_TEMPLATE_INTERVAL:-60}\n")
+            );
+            assert_eq!(
+                Config::from_file(file.path()).unwrap().agent_name,
+                "renamed"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_claim_result_not_written_back_with_secret_source() {
+            std::env::set_var("SMOTRA_TEST_LOADER_CLAIM", "sk_old");
+            let file = write_config("env:SMOTRA_TEST_LOADER_CLAIM");
+            let mut config = Config::from_file(file.path()).unwrap();
+
+            config.apply_claim_result(AgentCredentials {
+                api_key: "sk_claimed".to_string(),
+                agent_id: Uuid::now_v7(),
+                config_url: None,
+            });
+            assert_eq!(config.server.api_key.as_deref(), Some("sk_claimed"));
+
+            config.save_to_file_secure(file.path()).await.unwrap();
+
+            let content = fs::read_to_string(file.path()).unwrap();
+            assert!(!content.contains("sk_claimed"));
+            assert!(content.contains("env:SMOTRA_TEST_LOADER_CLAIM"));
+        }
+
+        #[tokio::test]
+        async fn test_inline_key_is_still_persisted() {
+            let file = write_config("sk_inline");
+            let config = Config::from_file(file.path()).unwrap();
+            assert_eq!(config.server.api_key_source, None);
+
+            config.save_to_file_secure(file.path()).await.unwrap();
+            let reloaded = Config::from_file(file.path()).unwrap();
+            assert_eq!(reloaded.server.api_key.as_deref(), Some("sk_inline"));
+        }
+    }
+
+    fn valid_config() -> Config {
+        Config {
+            agent_id: Uuid::now_v7(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_default_intervals_are_consistent() {
+        let config = valid_config();
+        assert!(
+            config.validate().is_ok(),
+            "default intervals should pass cross-field validation"
+        );
+    }
+
+    #[test]
+    fn test_validate_fails_when_report_interval_below_monitoring_interval() {
+        let mut config = valid_config();
+        config.monitoring.interval_secs = 120;
+        config.server.report_interval_secs = 60;
+        config.server.heartbeat_interval_secs = 60;
+
+        let result = config.validate();
+        assert!(
+            result.is_err(),
+            "report_interval shorter than the monitoring interval should fail validation"
+        );
+    }
+
+    #[test]
+    fn test_validate_allows_report_interval_equal_to_monitoring_interval() {
+        let mut config = valid_config();
+        config.monitoring.interval_secs = 60;
+        config.server.report_interval_secs = 60;
+        config.server.heartbeat_interval_secs = 60;
+
+        assert!(
+            config.validate().is_ok(),
+            "report_interval equal to the monitoring interval should be accepted"
+        );
+    }
+
+    /// Regression test: the old rule compared `report_interval_secs` against
+    /// `server.timeout_secs * 2` instead of the monitoring interval, so a large
+    /// server timeout could reject a perfectly sane report interval.
+    #[test]
+    fn test_validate_report_interval_not_compared_to_server_timeout() {
+        let mut config = valid_config();
+        config.monitoring.interval_secs = 10;
+        config.server.report_interval_secs = 30;
+        config.server.heartbeat_interval_secs = 30;
+        config.server.timeout_secs = 20;
+
+        assert!(
+            config.validate().is_ok(),
+            "report_interval must not be validated against the server timeout"
+        );
+    }
+
+    #[test]
+    fn test_validate_fails_when_heartbeat_interval_exceeds_report_interval() {
+        let mut config = valid_config();
+        config.server.report_interval_secs = 300;
+        config.server.heartbeat_interval_secs = 301;
+
+        let result = config.validate();
+        assert!(
+            result.is_err(),
+            "heartbeat_interval longer than report_interval should fail validation"
+        );
+    }
+
+    #[test]
+    fn test_validate_fails_when_heartbeat_interval_is_zero() {
+        let mut config = valid_config();
+        config.server.heartbeat_interval_secs = 0;
+
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("heartbeat_interval"), "{}", err);
+    }
+
+    #[test]
+    fn test_heartbeat_interval_round_trips_through_toml() {
+        let mut config = valid_config();
+        config.server.heartbeat_interval_secs = 45;
+
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(
+            content.contains("heartbeat_interval_secs = 45"),
+            "{}",
+            content
+        );
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.server.heartbeat_interval_secs, 45);
+        assert_eq!(parsed.server.heartbeat_interval(), Duration::from_secs(45));
+    }
+
+    #[test]
+    fn test_validate_fails_when_timeout_not_below_monitoring_interval() {
+        let mut config = valid_config();
+        config.monitoring.interval_secs = 5;
+        config.monitoring.timeout_secs = 5;
+
+        let result = config.validate();
+        assert!(
+            result.is_err(),
+            "monitoring timeout equal to the interval should fail validation"
+        );
+
+        config.monitoring.timeout_secs = 4;
+        assert!(
+            config.validate().is_ok(),
+            "monitoring timeout below the interval should be accepted"
+        );
+    }
+
+    #[test]
+    fn test_validate_dscp_range() {
+        let mut config = valid_config();
+        config.monitoring.dscp = Some(46);
+        assert!(config.validate().is_ok(), "DSCP 46 (EF) should be accepted");
+
+        config.monitoring.dscp = Some(64);
+        assert!(
+            config.validate().is_err(),
+            "DSCP values above 63 do not fit in six bits and should fail validation"
+        );
+    }
+
+    #[test]
+    fn test_validate_mqtt_sink() {
+        let mut config = valid_config();
+        config.sinks.mqtt = Some(MqttSinkConfig::default());
+        assert!(
+            config.validate().is_ok(),
+            "default MQTT sink should be valid"
+        );
+
+        config.sinks.mqtt = Some(MqttSinkConfig {
+            qos: 3,
+            ..Default::default()
+        });
+        assert!(
+            config.validate().is_err(),
+            "QoS above 2 should fail validation"
+        );
+
+        config.sinks.mqtt = Some(MqttSinkConfig {
+            broker_url: "http://broker.local".to_string(),
+            ..Default::default()
+        });
+        assert!(
+            config.validate().is_err(),
+            "non-MQTT broker URL should fail validation"
+        );
+    }
+
+    #[test]
+    fn test_mqtt_sink_from_toml() {
+        let toml = r#"
+            broker_url = "mqtts://broker.local:8883"
+            topic_template = "edge/{agent_id}/{endpoint}"
+            qos = 0
+            username = "agent"
+        "#;
+        let mqtt: MqttSinkConfig = toml::from_str(toml).unwrap();
+        assert_eq!(mqtt.topic_template, "edge/{agent_id}/{endpoint}");
+        assert_eq!(mqtt.qos, 0);
+        assert_eq!(mqtt.username.as_deref(), Some("agent"));
+        assert_eq!(mqtt.keep_alive_secs, 30);
+    }
+
+    #[test]
+    fn test_validate_connection_failure_grace() {
+        let mut config = valid_config();
+        assert_eq!(config.server.connection_failure_grace(), 3);
+
+        config.server.connection_failure_grace = Some(0);
+        assert!(config.validate().is_err());
+
+        config.server.connection_failure_grace = Some(1);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.server.connection_failure_grace(), 1);
+    }
+
+    #[test]
+    fn test_validate_storage_encryption_key() {
+        let mut config = valid_config();
+        config.storage.encrypt = true;
+        assert!(config.validate().is_err());
+
+        config.storage.encryption_key = Some("  ".to_string());
+        assert!(config.validate().is_err());
+
+        config.storage.encryption_key = Some("env:SMOTRA_CACHE_KEY".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_stream_report_mode() {
+        use crate::core::CheckKind;
+
+        let mut config = valid_config();
+        assert_eq!(config.reporting.mode, ReportMode::Batch);
+        config.reporting.mode = ReportMode::Stream;
+        assert!(config.validate().is_ok());
+
+        config.reporting.bucket_secs = Some(60);
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("stream"), "{}", err);
+
+        // Routed kinds are sent in batches next to the stream
+        config.reporting.bucket_secs = None;
+        config
+            .reporting
+            .routes
+            .insert(CheckKind::Ping, "/ingest/ping".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_success_sample_rate() {
+        let mut config = valid_config();
+        assert_eq!(config.logging.success_sample_rate, 100);
+        assert!(config.validate().is_ok());
+
+        config.logging.success_sample_rate = 0;
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("success_sample_rate"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_max_report_bytes() {
+        let mut config = valid_config();
+        assert_eq!(config.server.max_report_bytes, None);
+
+        config.server.max_report_bytes = Some(1_000_000);
+        assert!(config.validate().is_ok());
+
+        config.server.max_report_bytes = Some(0);
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("max_report_bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_heartbeat_thresholds() {
+        let mut config = valid_config();
+        config.server.heartbeat_thresholds.cpu_percent = 100.0;
+        config.server.heartbeat_thresholds.memory_mb = Some(512.0);
+        assert!(config.validate().is_ok());
+
+        for cpu_percent in [0.0, 100.5, f64::NAN] {
+            let mut config = valid_config();
+            config.server.heartbeat_thresholds.cpu_percent = cpu_percent;
+            let err = config.validate().unwrap_err();
+            assert!(err.to_string().contains("cpu_percent"), "{}", err);
+        }
+
+        let mut config = valid_config();
+        config.server.heartbeat_thresholds.memory_percent = -5.0;
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("memory_percent"), "{}", err);
+
+        let mut config = valid_config();
+        config.server.heartbeat_thresholds.memory_mb = Some(0.0);
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("memory_mb"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_max_concurrent_per_kind() {
+        use crate::core::CheckKind;
+
+        let mut config = valid_config();
+        config.monitoring.max_concurrent = 10;
+        config
+            .monitoring
+            .max_concurrent_per_kind
+            .insert(CheckKind::Http, 4);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.monitoring.max_concurrent_for(CheckKind::Http), 4);
+        assert_eq!(config.monitoring.max_concurrent_for(CheckKind::Ping), 10);
+
+        config
+            .monitoring
+            .max_concurrent_per_kind
+            .insert(CheckKind::Ping, 0);
+        assert!(config.validate().is_err());
+
+        config
+            .monitoring
+            .max_concurrent_per_kind
+            .insert(CheckKind::Ping, 11);
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.to_string().contains("max_concurrent_per_kind.ping"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_max_concurrent_per_kind_round_trips_through_toml() {
+        use crate::core::CheckKind;
+
+        let mut config = valid_config();
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(!content.contains("max_concurrent_per_kind"));
+
+        config
+            .monitoring
+            .max_concurrent_per_kind
+            .insert(CheckKind::Http, 4);
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(content.contains("http = 4"), "{}", content);
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.monitoring.max_concurrent_for(CheckKind::Http), 4);
+    }
+
+    #[test]
+    fn test_validate_resolver() {
+        use crate::agent_config::DnsProtocol;
+
+        let mut config = valid_config();
+        config.monitoring.resolver = DnsResolverConfig::Nameservers {
+            nameservers: vec!["192.0.2.53".to_string(), "[2001:db8::53]:5353".to_string()],
+            protocol: DnsProtocol::Udp,
+            tls_name: None,
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.monitoring.resolver.nameserver_addrs().unwrap(),
+            vec![
+                "192.0.2.53:53".parse().unwrap(),
+                "[2001:db8::53]:5353".parse().unwrap()
+            ]
+        );
+
+        config.monitoring.resolver = DnsResolverConfig::Nameservers {
+            nameservers: Vec::new(),
+            protocol: DnsProtocol::Udp,
+            tls_name: None,
+        };
+        assert!(config.validate().is_err());
+
+        config.monitoring.resolver = DnsResolverConfig::Nameservers {
+            nameservers: vec!["dns.example.com".to_string()],
+            protocol: DnsProtocol::Udp,
+            tls_name: None,
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("dns.example.com"), "{}", err);
+
+        config.monitoring.resolver = DnsResolverConfig::Nameservers {
+            nameservers: vec!["1.1.1.1".to_string()],
+            protocol: DnsProtocol::Https,
+            tls_name: None,
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("tls_name"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolver_round_trips_through_toml() {
+        use crate::agent_config::DnsProtocol;
+
+        let mut config = valid_config();
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(!content.contains("resolver"));
+
+        config.monitoring.resolver = DnsResolverConfig::Nameservers {
+            nameservers: vec!["1.1.1.1".to_string()],
+            protocol: DnsProtocol::Tls,
+            tls_name: Some("cloudflare-dns.com".to_string()),
+        };
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(content.contains("backend = \"nameservers\""), "{}", content);
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.monitoring.resolver, config.monitoring.resolver);
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_endpoint_interval() {
+        let mut config = valid_config();
+        config.monitoring.timeout_secs = 5;
+        config
+            .endpoints
+            .push(Endpoint::new("10.0.0.1").with_interval_secs(10));
+        assert!(config.validate().is_ok());
+
+        config.endpoints[0].interval_secs = Some(5);
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("interval_secs must be greater than the monitoring timeout"),
+            "{}",
+            err
+        );
+
+        config.endpoints[0].interval_secs = Some(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_ping_payload_bytes() {
+        let mut config = valid_config();
+        assert_eq!(config.monitoring.ping_payload_bytes(), 56);
+
+        config.monitoring.ping_payload_bytes = Some(1472);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.monitoring.ping_payload_bytes(), 1472);
+
+        config.monitoring.ping_payload_bytes = Some(0);
+        assert!(config.validate().is_ok());
+
+        config.monitoring.ping_payload_bytes = Some(65507);
+        assert!(config.validate().is_ok());
+
+        config.monitoring.ping_payload_bytes = Some(65508);
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("ping_payload_bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_traceroute_probes_per_hop() {
+        let mut config = valid_config();
+        assert_eq!(config.monitoring.traceroute_probes_per_hop(), 3);
+
+        config.monitoring.traceroute_probes_per_hop = Some(0);
+        assert!(config.validate().is_err());
+
+        config.monitoring.traceroute_probes_per_hop = Some(5);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.monitoring.traceroute_probes_per_hop(), 5);
+    }
+
+    #[test]
+    fn test_validate_success_quorum() {
+        let mut config = valid_config();
+        config.monitoring.ping_count = 3;
+
+        config.monitoring.success_quorum = Some(SuccessQuorum::AtLeast(3));
+        assert!(config.validate().is_ok());
+
+        config.monitoring.success_quorum = Some(SuccessQuorum::AtLeast(4));
+        assert!(
+            config.validate().is_err(),
+            "a quorum above ping_count can never be met"
+        );
+
+        config.monitoring.success_quorum = None;
+        let mut endpoint = Endpoint::new("10.0.0.1");
+        endpoint.success_quorum = Some("two".to_string());
+        config.endpoints = vec![endpoint];
+        assert!(config.validate().is_err());
+
+        config.endpoints[0].success_quorum = Some("60%".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_thresholds() {
+        let mut config = valid_config();
+        let toml = r#"
+            [[thresholds]]
+            tag = "db"
+            warning_ms = 50
+            critical_ms = 200
+
+            [[thresholds]]
+            tag = "web"
+            warning_ms = 500
+        "#;
+        config.thresholds = toml::from_str::<Config>(&format!(
+            "{}\n{}",
+            toml::to_string(&valid_config()).unwrap(),
+            toml
+        ))
+        .unwrap()
+        .thresholds;
+        assert_eq!(config.thresholds.len(), 2);
+        assert_eq!(config.thresholds[1].critical_ms, None);
+        assert!(config.validate().is_ok());
+
+        config.thresholds[0].warning_ms = Some(300.0);
+        assert!(
+            config.validate().is_err(),
+            "warning above critical should fail validation"
+        );
+
+        config.thresholds[0].warning_ms = Some(50.0);
+        config.thresholds[1].tag = String::new();
+        assert!(config.validate().is_err());
+
+        config.thresholds.pop();
+        let mut endpoint = Endpoint::new("10.0.0.1");
+        endpoint.critical_ms = Some(0.0);
+        config.endpoints = vec![endpoint];
+        assert!(config.validate().is_err());
+
+        config.endpoints[0].critical_ms = Some(100.0);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_packet_loss_thresholds() {
+        let toml = r#"
+            [[thresholds]]
+            tag = "wan"
+            packet_loss_warning_percent = 5
+            packet_loss_critical_percent = 50
+        "#;
+        let mut config = valid_config();
+        config.thresholds = toml::from_str::<Config>(&format!(
+            "{}\n{}",
+            toml::to_string(&valid_config()).unwrap(),
+            toml
+        ))
+        .unwrap()
+        .thresholds;
+        assert_eq!(
+            config.thresholds[0].packet_loss_critical_percent,
+            Some(50.0)
+        );
+        assert!(config.validate().is_ok());
+
+        config.thresholds[0].packet_loss_warning_percent = Some(60.0);
+        assert!(
+            config.validate().is_err(),
+            "warning above critical should fail validation"
+        );
+
+        config.thresholds[0].packet_loss_warning_percent = Some(5.0);
+        config.thresholds[0].packet_loss_critical_percent = Some(101.0);
+        assert!(config.validate().is_err());
+
+        config.thresholds[0].packet_loss_critical_percent = Some(-1.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_alerting() {
+        let mut config = valid_config();
+        config.alerting.enabled = true;
+        assert!(
+            config.validate().is_err(),
+            "enabled alerting needs a webhook"
+        );
+
+        config.alerting.webhook_url = "https://hooks.example.com/smotra".to_string();
+        assert!(config.validate().is_ok());
+
+        config.alerting.failure_threshold = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_circuit_breaker() {
+        let mut config = valid_config();
+        config.monitoring.circuit_breaker = Some(CircuitBreakerConfig {
+            failure_threshold: 3,
+            cooldown_secs: 300,
+        });
+        assert!(config.validate().is_ok());
+
+        config.monitoring.circuit_breaker = Some(CircuitBreakerConfig {
+            failure_threshold: 0,
+            cooldown_secs: 300,
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_success_quorum_round_trips_through_toml() {
+        let mut config = valid_config();
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(!content.contains("success_quorum"));
+
+        config.monitoring.success_quorum = Some(SuccessQuorum::Percent(60));
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(content.contains("success_quorum = \"60%\""));
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(
+            parsed.monitoring.success_quorum,
+            Some(SuccessQuorum::Percent(60))
+        );
+    }
+
+    #[test]
+    fn test_dns_timeout_defaults_to_check_timeout() {
+        let mut config = valid_config();
+        config.monitoring.timeout_secs = 3;
+        assert_eq!(config.monitoring.dns_timeout(), Duration::from_secs(3));
+
+        config.monitoring.dns_timeout_secs = Some(1);
+        assert_eq!(config.monitoring.dns_timeout(), Duration::from_secs(1));
+        assert!(config.validate().is_ok());
+
+        config.monitoring.dns_timeout_secs = Some(0);
+        assert!(
+            config.validate().is_err(),
+            "a zero DNS timeout should fail validation"
+        );
+    }
+
+    #[test]
+    fn test_ip_version_round_trip() {
+        let config: MonitoringConfig = toml::from_str(
+            r#"
+interval_secs = 60
+timeout_secs = 1
+ping_count = 3
+max_concurrent = 10
+traceroute_on_failure = false
+traceroute_max_hops = 30
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.ip_version, IpVersion::Auto);
+
+        for (name, version) in [("v4", IpVersion::V4), ("v6", IpVersion::V6)] {
+            let toml = toml::to_string(&MonitoringConfig {
+                ip_version: version,
+                ..MonitoringConfig::default()
+            })
+            .unwrap();
+            assert!(toml.contains(&format!("ip_version = \"{}\"", name)));
+            let parsed: MonitoringConfig = toml::from_str(&toml).unwrap();
+            assert_eq!(parsed.ip_version, version);
+        }
+    }
+
+    #[test]
+    fn test_dns_cache_ttl_defaults_to_five_minutes() {
+        let mut config = valid_config();
+        assert_eq!(config.monitoring.dns_cache_ttl(), Duration::from_secs(300));
+
+        config.monitoring.dns_cache_ttl_secs = Some(0);
+        assert_eq!(config.monitoring.dns_cache_ttl(), Duration::ZERO);
+        assert!(config.validate().is_ok(), "0 disables the cache");
+    }
+
+    #[test]
+    fn test_reverse_dns_timeout_defaults_to_dns_timeout() {
+        let mut config = valid_config();
+        config.monitoring.dns_timeout_secs = Some(2);
+        assert_eq!(
+            config.monitoring.reverse_dns_timeout(),
+            Duration::from_secs(2)
+        );
+
+        config.monitoring.reverse_dns_timeout_secs = Some(1);
+        assert_eq!(
+            config.monitoring.reverse_dns_timeout(),
+            Duration::from_secs(1)
+        );
+        assert!(config.validate().is_ok());
+
+        config.monitoring.reverse_dns_timeout_secs = Some(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_source_port_range_validation() {
+        let mut config = valid_config();
+        config.monitoring.source_port_range = Some((40000, 40100));
+        assert!(config.validate().is_ok());
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(content.contains("source_port_range = [\n    40000,\n    40100,\n]"));
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.monitoring.source_port_range, Some((40000, 40100)));
+
+        config.monitoring.source_port_range = Some((40000, 40000));
+        assert!(config.validate().is_ok());
+
+        config.monitoring.source_port_range = Some((40100, 40000));
+        assert!(
+            config.validate().is_err(),
+            "an inverted range should fail validation"
+        );
+
+        config.monitoring.source_port_range = Some((0, 40000));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_source_address_validation() {
+        let mut config = valid_config();
+        config.monitoring.source_address = Some("192.0.2.10".parse().unwrap());
+        assert!(config.validate().is_ok());
+
+        let content = toml::to_string(&config).unwrap();
+        assert!(content.contains("source_address = \"192.0.2.10\""));
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(
+            parsed.monitoring.source_address,
+            config.monitoring.source_address
+        );
+
+        let invalid = content.replace("192.0.2.10", "192.0.2.300");
+        assert!(toml::from_str::<Config>(&invalid).is_err());
+
+        for address in ["0.0.0.0", "::", "224.0.0.1"] {
+            config.monitoring.source_address = Some(address.parse().unwrap());
+            let err = config.validate().unwrap_err();
+            assert!(
+                err.to_string().contains("must be a unicast address"),
+                "{}",
+                err
+            );
+        }
+
+        config.monitoring.source_address = Some("2001:db8::10".parse().unwrap());
+        config.monitoring.ip_version = IpVersion::V4;
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.to_string().contains("does not match ip_version"),
+            "{}",
+            err
+        );
+        config.monitoring.ip_version = IpVersion::V6;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_check_latency_budget_percent() {
+        let mut config = valid_config();
+        assert_eq!(config.monitoring.check_latency_budget(), None);
+
+        config.monitoring.interval_secs = 60;
+        config.monitoring.check_latency_budget_percent = Some(25);
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.monitoring.check_latency_budget(),
+            Some(Duration::from_secs(15))
+        );
+
+        config.monitoring.check_latency_budget_percent = Some(0);
+        assert!(config.validate().is_err());
+
+        config.monitoring.check_latency_budget_percent = Some(101);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_cycle_deadline_must_fit_the_interval() {
+        let mut config = valid_config();
+        assert_eq!(config.monitoring.cycle_deadline(), None);
+
+        config.monitoring.cycle_deadline_secs = Some(config.monitoring.interval_secs);
+        assert_eq!(
+            config.monitoring.cycle_deadline(),
+            Some(Duration::from_secs(config.monitoring.interval_secs))
+        );
+        assert!(config.validate().is_ok());
+
+        config.monitoring.cycle_deadline_secs = Some(0);
+        assert!(config.validate().is_err());
+
+        config.monitoring.cycle_deadline_secs = Some(config.monitoring.interval_secs + 1);
+        assert!(
+            config.validate().is_err(),
+            "a deadline longer than the interval should fail validation"
+        );
+    }
+
+    #[test]
+    fn test_http_verify_tls_defaults_to_true() {
+        let http: HttpCheckConfig = toml::from_str("max_body_bytes = 1024").unwrap();
+        assert!(http.verify_tls);
+
+        let http: HttpCheckConfig = toml::from_str("verify_tls = false").unwrap();
+        assert!(!http.verify_tls);
+    }
+
+    #[test]
+    fn test_dscp_is_optional_in_toml() {
+        let mut config = valid_config();
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert!(
+            !content.contains("dscp"),
+            "unset dscp should not be written"
+        );
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.monitoring.dscp, None);
+
+        config.monitoring.dscp = Some(34);
+        let content = toml::to_string_pretty(&config).unwrap();
+        let parsed: Config = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.monitoring.dscp, Some(34));
+    }
+
+    #[test]
+    fn test_plugin_init_failure_policy_in_toml() {
+        let plugins: PluginsConfig = toml::from_str("dirs = []").unwrap();
+        assert_eq!(plugins.on_init_failure, None);
+
+        for (value, policy) in [
+            ("fail-fast", PluginInitPolicy::FailFast),
+            ("fail", PluginInitPolicy::FailFast),
+            ("skip-failed", PluginInitPolicy::SkipFailed),
+            ("skip", PluginInitPolicy::SkipFailed),
+        ] {
+            let plugins: PluginsConfig =
+                toml::from_str(&format!("on_init_failure = {:?}", value)).unwrap();
+            assert_eq!(plugins.on_init_failure, Some(policy), "{}", value);
+        }
+
+        let err = toml::from_str::<PluginsConfig>(r#"on_init_failure = "retry""#).unwrap_err();
+        assert!(err.to_string().contains("unknown variant"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_rejects_tcp_and_udp_checks_without_port() {
+        for (endpoint, kind) in [
+            (
+                Endpoint::new("db.example.com").with_check_type(EndpointCheckType::Tcp),
+                "tcp",
+            ),
+            (
+                Endpoint::new("10.0.0.53").with_check_type(EndpointCheckType::Udp),
+                "udp",
+            ),
+            (Endpoint::new("tcp://db.example.com"), "tcp"),
+        ] {
+            let mut config = valid_config();
+            config.endpoints = vec![endpoint.clone()];
+            let err = config.validate().unwrap_err().to_string();
+            assert!(
+                err.contains(&endpoint.address)
+                    && err.contains(&format!("{} check needs a port", kind)),
+                "{}",
+                err
+            );
+
+            config.endpoints = vec![endpoint.with_port(5432)];
+            assert!(config.validate().is_ok());
+        }
+
+        let mut config = valid_config();
+        config.endpoints = vec![Endpoint::new("tcp://db.example.com:5432")];
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_validate_fails_when_update_url_is_empty() {
         let mut config = Config {
@@ -2,10 +2,22 @@
 //! - Agent self-registration and claiming workflow
 
 mod hot_reload;
+mod interpolate;
+mod lint;
 mod loader;
+mod redact;
+mod remote;
+mod schema;
+mod secret;
 mod server_config;
 mod types;
 
-pub use hot_reload::run_hot_reload;
-pub use server_config::{ClaimConfig, ServerConfig};
+pub use hot_reload::{
+    run_hot_reload, ReloadSource, ReloadStatus, ReloadStatusHandle, ReloadTrigger,
+};
+pub use lint::{LintCategory, LintWarning};
+pub use secret::SecretSource;
+pub use server_config::{
+    ClaimConfig, EndpointSync, HeartbeatMetrics, HeartbeatThresholds, PreflightMode, ServerConfig,
+};
 pub use types::*;
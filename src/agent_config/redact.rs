@@ -0,0 +1,112 @@
+//! Secret redaction for displaying or reporting the configuration
+//!
+//! The effective configuration is shown in the TUI's configuration tab and
+//! sent with the startup diagnostics. Both go through [`Config::redacted`]
+//! or [`Config::redacted_json`], which replace with `"[redacted]"`:
+//!
+//! - values of [`SECRET_KEYS`], wherever they appear,
+//! - every value of [`SECRET_MAPS`], e.g. an endpoint's HTTP headers,
+//! - passwords embedded in URLs.
+
+use super::Config;
+use crate::error::Result;
+use serde_json::Value;
+
+/// Replacement for redacted values
+pub(crate) const REDACTED: &str = "[redacted]";
+
+/// Config keys whose values are secrets
+const SECRET_KEYS: &[&str] = &[
+    "api_key",
+    "password",
+    "bearer_token",
+    "webhook_url",
+    "encryption_key",
+];
+
+/// Config maps whose values are all secrets, e.g. an endpoint's HTTP
+/// headers, which may carry `Authorization` or API key headers
+const SECRET_MAPS: &[&str] = &["headers"];
+
+impl Config {
+    /// The configuration as JSON with secrets redacted
+    pub fn redacted_json(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or(Value::Null);
+        redact(&mut value);
+        value
+    }
+
+    /// Copy of the configuration with secrets redacted, for display
+    pub fn redacted(&self) -> Result<Self> {
+        Ok(serde_json::from_value(self.redacted_json())?)
+    }
+}
+
+/// Replace secrets in a serialized config
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else if let (true, Value::Object(secrets)) =
+                    (SECRET_MAPS.contains(&key.as_str()), &mut *value)
+                {
+                    // Header names stay visible, only their values are secret
+                    for secret in secrets.values_mut() {
+                        *secret = Value::String(REDACTED.to_string());
+                    }
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        Value::String(s) if s.contains("://") => {
+            if let Ok(mut url) = url::Url::parse(s) {
+                if url.password().is_some() && url.set_password(Some(REDACTED)).is_ok() {
+                    *s = url.to_string();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_config::MqttSinkConfig;
+
+    #[test]
+    fn test_redacted_config_hides_secrets() {
+        let mut config = Config::default();
+        config.server.api_key = Some("super-secret-key".to_string());
+        config.storage.encrypt = true;
+        config.storage.encryption_key = Some("cache-secret".to_string());
+        config.sinks.mqtt = Some(MqttSinkConfig {
+            password: Some("mqtt-secret".to_string()),
+            ..MqttSinkConfig::default()
+        });
+
+        let redacted = config.redacted().unwrap();
+        assert_eq!(redacted.server.api_key.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.storage.encryption_key.as_deref(), Some(REDACTED));
+        assert_eq!(
+            redacted.sinks.mqtt.unwrap().password.as_deref(),
+            Some(REDACTED)
+        );
+
+        let shown = toml::to_string_pretty(&config.redacted().unwrap()).unwrap();
+        for secret in ["super-secret-key", "cache-secret", "mqtt-secret"] {
+            assert!(!shown.contains(secret), "{} leaked: {}", secret, shown);
+        }
+    }
+
+    #[test]
+    fn test_unset_secrets_stay_unset() {
+        let redacted = Config::default().redacted().unwrap();
+        assert_eq!(redacted.server.api_key, None);
+        assert_eq!(redacted.storage.encryption_key, None);
+    }
+}
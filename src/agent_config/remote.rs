@@ -0,0 +1,295 @@
+//! Endpoint discovery from the server-side agent configuration
+//!
+//! Once claimed, an agent can pull the configuration managed for it on the
+//! server (`GET {server.url}{server.config_url}`, an OpenAPI `AgentConfig`)
+//! and adopt its endpoints according to `server.endpoint_sync`. Only the
+//! endpoint list and the configuration version are taken over; monitoring,
+//! storage and server settings stay under local control.
+
+use super::{Config, EndpointSync};
+use crate::core::Endpoint;
+use crate::error::{Error, Result};
+use crate::openapi::AgentConfig;
+use tracing::{debug, info};
+
+impl Config {
+    /// Fetch the server-side configuration and adopt its endpoints.
+    ///
+    /// Does nothing when `server.endpoint_sync` is `off`. Returns whether the
+    /// endpoint list changed, in which case the caller should persist the
+    /// configuration.
+    pub async fn sync_endpoints_from_server(&mut self) -> Result<bool> {
+        if self.server.endpoint_sync == EndpointSync::Off {
+            return Ok(false);
+        }
+
+        let remote = self.fetch_server_config().await?;
+        Ok(self.adopt_server_endpoints(remote))
+    }
+
+    async fn fetch_server_config(&self) -> Result<AgentConfig> {
+        let url = self.server.configuration_url(self.agent_id);
+        debug!("Fetching agent configuration from {}", url);
+
+        let client = reqwest::Client::builder()
+            .timeout(self.server.timeout())
+            .danger_accept_invalid_certs(!self.server.verify_tls)
+            .build()?;
+
+        let mut request = client.get(&url);
+        if let Some(api_key) = &self.server.api_key {
+            request = request.header("X-Agent-API-Key", api_key);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Error::Network(format!(
+                "Fetching agent configuration from {} failed: {}",
+                url,
+                response.status()
+            )));
+        }
+
+        Ok(response.json::<AgentConfig>().await?)
+    }
+
+    /// Merge or replace local endpoints with `remote`'s, returning whether
+    /// the endpoint list changed
+    fn adopt_server_endpoints(&mut self, remote: AgentConfig) -> bool {
+        let before = serde_json::to_value(&self.endpoints).ok();
+
+        match self.server.endpoint_sync {
+            EndpointSync::Off => return false,
+            EndpointSync::Replace => self.endpoints = remote.endpoints,
+            EndpointSync::Merge => merge_endpoints(&mut self.endpoints, remote.endpoints),
+        }
+        if remote.version > 0 {
+            self.version = remote.version as u32;
+        }
+
+        let changed = serde_json::to_value(&self.endpoints).ok() != before;
+        info!(
+            "Adopted server configuration version {} ({} endpoints, {})",
+            remote.version,
+            self.endpoints.len(),
+            if changed { "changed" } else { "unchanged" }
+        );
+        changed
+    }
+}
+
+/// Update endpoints in `local` that share an ID with one in `remote` and
+/// append the rest
+fn merge_endpoints(local: &mut Vec<Endpoint>, remote: Vec<Endpoint>) {
+    for endpoint in remote {
+        match local.iter_mut().find(|e| e.id == endpoint.id) {
+            Some(existing) => *existing = endpoint,
+            None => local.push(endpoint),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn server_config_json(agent_id: Uuid, endpoints: &[Endpoint]) -> String {
+        json!({
+            "version": 7,
+            "agent_id": agent_id,
+            "agent_name": "Managed Agent",
+            "tags": ["prod"],
+            "monitoring": {
+                "interval_secs": 60,
+                "timeout_secs": 5,
+                "ping_count": 3,
+                "max_concurrent": 10,
+                "traceroute_on_failure": false,
+                "traceroute_max_hops": 30
+            },
+            "server": {
+                "url": null,
+                "api_key": null,
+                "report_interval_secs": 300,
+                "heartbeat_interval_secs": 300,
+                "verify_tls": true,
+                "timeout_secs": 5,
+                "retry_attempts": 3
+            },
+            "storage": {
+                "cache_dir": "./cache",
+                "max_cached_results": 10000,
+                "max_cache_age_secs": 86400
+            },
+            "self_upgrade": {
+                "enabled": false,
+                "github_repo_url": "https://github.com/smotra-monitoring/agent",
+                "check_interval_secs": 3600
+            },
+            "endpoints": endpoints
+        })
+        .to_string()
+    }
+
+    fn managed_config(server_url: String, sync: EndpointSync, local: Vec<Endpoint>) -> Config {
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            endpoints: local,
+            ..Default::default()
+        };
+        config.server.url = server_url;
+        config.server.api_key = Some("sk_test".to_string());
+        config.server.config_url = Some(format!("/agent/{}/configuration", config.agent_id));
+        config.server.endpoint_sync = sync;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_replace_adopts_server_endpoints() {
+        let mut server = Server::new_async().await;
+        let remote = vec![
+            Endpoint::new("10.1.0.1"),
+            Endpoint::new("https://api.example.com/health"),
+        ];
+        let mut config = managed_config(
+            server.url(),
+            EndpointSync::Replace,
+            vec![Endpoint::new("10.0.0.1")],
+        );
+
+        let mock = server
+            .mock(
+                "GET",
+                format!("/agent/{}/configuration", config.agent_id).as_str(),
+            )
+            .match_header("X-Agent-API-Key", "sk_test")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(server_config_json(config.agent_id, &remote))
+            .create_async()
+            .await;
+
+        assert!(config.sync_endpoints_from_server().await.unwrap());
+        mock.assert_async().await;
+
+        let addresses: Vec<_> = config
+            .endpoints
+            .iter()
+            .map(|e| e.address.as_str())
+            .collect();
+        assert_eq!(addresses, ["10.1.0.1", "https://api.example.com/health"]);
+        assert_eq!(config.endpoints[0].id, remote[0].id);
+        assert_eq!(config.version, 7);
+    }
+
+    #[tokio::test]
+    async fn test_merge_keeps_local_only_endpoints() {
+        let mut server = Server::new_async().await;
+        let shared = Endpoint::new("10.0.0.1");
+        let local_only = Endpoint::new("10.0.0.2");
+        let mut updated = shared.clone();
+        updated.enabled = false;
+        let added = Endpoint::new("10.1.0.1");
+
+        let mut config = managed_config(
+            server.url(),
+            EndpointSync::Merge,
+            vec![shared.clone(), local_only.clone()],
+        );
+        server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(server_config_json(
+                config.agent_id,
+                &[updated.clone(), added.clone()],
+            ))
+            .create_async()
+            .await;
+
+        assert!(config.sync_endpoints_from_server().await.unwrap());
+
+        let ids: Vec<_> = config.endpoints.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [shared.id, local_only.id, added.id]);
+        assert!(!config.endpoints[0].enabled);
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_endpoints_are_reported_as_such() {
+        let mut server = Server::new_async().await;
+        let endpoint = Endpoint::new("10.0.0.1");
+        let mut config =
+            managed_config(server.url(), EndpointSync::Replace, vec![endpoint.clone()]);
+        server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_body(server_config_json(config.agent_id, &[endpoint]))
+            .create_async()
+            .await;
+
+        assert!(!config.sync_endpoints_from_server().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_server_error_leaves_endpoints_untouched() {
+        let mut server = Server::new_async().await;
+        let mut config = managed_config(
+            server.url(),
+            EndpointSync::Replace,
+            vec![Endpoint::new("10.0.0.1")],
+        );
+        server
+            .mock("GET", Matcher::Any)
+            .with_status(500)
+            .create_async()
+            .await;
+
+        assert!(config.sync_endpoints_from_server().await.is_err());
+        assert_eq!(config.endpoints.len(), 1);
+        assert_eq!(config.endpoints[0].address, "10.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn test_sync_off_does_not_contact_server() {
+        let mut config = managed_config(
+            "http://127.0.0.1:1".to_string(),
+            EndpointSync::Off,
+            vec![Endpoint::new("10.0.0.1")],
+        );
+        assert!(!config.sync_endpoints_from_server().await.unwrap());
+    }
+
+    #[test]
+    fn test_configuration_url() {
+        let agent_id = Uuid::now_v7();
+        let mut config = managed_config(
+            "https://api.example.com/v1".to_string(),
+            EndpointSync::Merge,
+            Vec::new(),
+        );
+
+        config.server.config_url = None;
+        assert_eq!(
+            config.server.configuration_url(agent_id),
+            format!(
+                "https://api.example.com/v1/agent/{}/configuration",
+                agent_id
+            )
+        );
+
+        config.server.config_url = Some("/agent/x/configuration".to_string());
+        assert_eq!(
+            config.server.configuration_url(agent_id),
+            "https://api.example.com/v1/agent/x/configuration"
+        );
+
+        config.server.config_url = Some("https://cfg.example.com/agent/x".to_string());
+        assert_eq!(
+            config.server.configuration_url(agent_id),
+            "https://cfg.example.com/agent/x"
+        );
+    }
+}
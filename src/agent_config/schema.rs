@@ -0,0 +1,75 @@
+//! JSON Schema of the configuration file
+//!
+//! `smotra-cli schema` prints the schema returned by [`Config::json_schema()`]
+//! so editors and CI pipelines can check a configuration (converted from TOML
+//! to JSON) without running the agent. The schema is derived from the same
+//! types the agent deserializes, so every field, default and enum value it
+//! lists is exactly what the loader accepts. Rules spanning several fields
+//! are still only enforced by `Config::validate()`.
+
+use super::Config;
+
+impl Config {
+    /// JSON Schema (draft 2020-12) describing the configuration file
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Config))
+            .expect("a JSON schema always serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CheckKind, Endpoint};
+    use serde_json::json;
+
+    fn validator() -> jsonschema::Validator {
+        jsonschema::validator_for(&Config::json_schema()).unwrap()
+    }
+
+    fn known_good() -> serde_json::Value {
+        let mut config = Config::default();
+        config.reporting.filter.kinds = vec![CheckKind::Ping, CheckKind::Http];
+        config.endpoints = vec![Endpoint::new("https://api.example.com/health")
+            .with_tags(vec!["production".to_string()])];
+        serde_json::to_value(&config).unwrap()
+    }
+
+    #[test]
+    fn test_schema_accepts_known_good_config() {
+        let config = known_good();
+        let errors: Vec<String> = validator()
+            .iter_errors(&config)
+            .map(|e| e.to_string())
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_schema_rejects_known_bad_config() {
+        let validator = validator();
+
+        let mut config = known_good();
+        config["monitoring"]["interval_secs"] = json!("fast");
+        assert!(!validator.is_valid(&config));
+
+        let mut config = known_good();
+        config["reporting"]["filter"]["kinds"] = json!(["smoke_signal"]);
+        assert!(!validator.is_valid(&config));
+
+        let mut config = known_good();
+        config["monitoring"]["success_quorum"] = json!("most");
+        assert!(!validator.is_valid(&config));
+    }
+
+    #[test]
+    fn test_schema_lists_defaults_and_enums() {
+        let schema = Config::json_schema();
+        let text = schema.to_string();
+
+        assert!(schema["properties"]["monitoring"].is_object());
+        assert!(text.contains("\"default\""));
+        assert!(text.contains("\"ping\""));
+        assert!(text.contains("\"http\""));
+    }
+}
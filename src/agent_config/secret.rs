@@ -0,0 +1,191 @@
+//! Secret references for sensitive configuration values
+//!
+//! Instead of storing the API key inline, `server.api_key` may reference an
+//! external secret source:
+//!
+//! - `env:NAME` - read from the environment variable `NAME`
+//! - `file:/path/to/key` - read from a file (e.g. a Docker/Kubernetes secret)
+//! - `cmd:command args` - run a shell command and use its standard output
+//!
+//! References are resolved once at load time. The reference, never the
+//! resolved value, is written back when the configuration is saved.
+
+use crate::error::{Error, Result};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// External source a secret value is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretSource {
+    /// Environment variable name
+    Env(String),
+
+    /// Path to a file holding the secret
+    File(PathBuf),
+
+    /// Shell command printing the secret on standard output
+    Command(String),
+}
+
+impl SecretSource {
+    /// Parse a secret reference.
+    ///
+    /// Returns `None` if `value` is an inline (literal) secret.
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Some(name) = value.strip_prefix("env:") {
+            Some(Self::Env(name.to_string()))
+        } else if let Some(path) = value.strip_prefix("file:") {
+            Some(Self::File(PathBuf::from(path)))
+        } else {
+            value
+                .strip_prefix("cmd:")
+                .map(|cmd| Self::Command(cmd.to_string()))
+        }
+    }
+
+    /// Read the secret value from its source.
+    ///
+    /// Surrounding whitespace (such as the trailing newline of a secret file)
+    /// is trimmed. An empty value is treated as an error.
+    pub fn resolve(&self) -> Result<String> {
+        let value = match self {
+            Self::Env(name) => std::env::var(name).map_err(|e| {
+                Error::ConfigApiKey(format!(
+                    "Failed to read environment variable {}: {}",
+                    name, e
+                ))
+            })?,
+            Self::File(path) => std::fs::read_to_string(path).map_err(|e| {
+                Error::ConfigApiKey(format!(
+                    "Failed to read secret file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?,
+            Self::Command(cmd) => run_command(cmd)?,
+        };
+
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(Error::ConfigApiKey(format!(
+                "Secret source {} is empty",
+                self
+            )));
+        }
+
+        Ok(value.to_string())
+    }
+}
+
+impl fmt::Display for SecretSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env(name) => write!(f, "env:{}", name),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::Command(cmd) => write!(f, "cmd:{}", cmd),
+        }
+    }
+}
+
+/// Run `cmd` through the platform shell and return its standard output
+fn run_command(cmd: &str) -> Result<String> {
+    #[cfg(unix)]
+    let output = Command::new("sh").arg("-c").arg(cmd).output();
+    #[cfg(windows)]
+    let output = Command::new("cmd").arg("/C").arg(cmd).output();
+
+    let output =
+        output.map_err(|e| Error::ConfigApiKey(format!("Failed to run secret command: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::ConfigApiKey(format!(
+            "Secret command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|e| Error::ConfigApiKey(format!("Secret command output is not UTF-8: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(
+            SecretSource::parse("env:SMOTRA_API_KEY"),
+            Some(SecretSource::Env("SMOTRA_API_KEY".to_string()))
+        );
+        assert_eq!(
+            SecretSource::parse("file:/run/secrets/key"),
+            Some(SecretSource::File(PathBuf::from("/run/secrets/key")))
+        );
+        assert_eq!(
+            SecretSource::parse("cmd:pass show smotra"),
+            Some(SecretSource::Command("pass show smotra".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_inline_value() {
+        assert_eq!(SecretSource::parse("sk_live_abc123"), None);
+        assert_eq!(SecretSource::parse(""), None);
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for reference in ["env:KEY", "file:/run/secrets/key", "cmd:echo key"] {
+            let source = SecretSource::parse(reference).unwrap();
+            assert_eq!(source.to_string(), reference);
+        }
+    }
+
+    #[test]
+    fn test_resolve_env() {
+        std::env::set_var("SMOTRA_TEST_SECRET_RESOLVE_ENV", "sk_from_env");
+        let source = SecretSource::Env("SMOTRA_TEST_SECRET_RESOLVE_ENV".to_string());
+        assert_eq!(source.resolve().unwrap(), "sk_from_env");
+    }
+
+    #[test]
+    fn test_resolve_missing_env_fails() {
+        let source = SecretSource::Env("SMOTRA_TEST_SECRET_DEFINITELY_UNSET".to_string());
+        assert!(source.resolve().is_err());
+    }
+
+    #[test]
+    fn test_resolve_file_trims_newline() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "sk_from_file").unwrap();
+
+        let source = SecretSource::File(file.path().to_path_buf());
+        assert_eq!(source.resolve().unwrap(), "sk_from_file");
+    }
+
+    #[test]
+    fn test_resolve_empty_file_fails() {
+        let file = NamedTempFile::new().unwrap();
+        let source = SecretSource::File(file.path().to_path_buf());
+        assert!(source.resolve().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_command() {
+        let source = SecretSource::Command("echo sk_from_cmd".to_string());
+        assert_eq!(source.resolve().unwrap(), "sk_from_cmd");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_failing_command() {
+        let source = SecretSource::Command("exit 3".to_string());
+        assert!(source.resolve().is_err());
+    }
+}
@@ -2,4 +2,6 @@
 
 mod types;
 
-pub use types::{ClaimConfig, ServerConfig};
+pub use types::{
+    ClaimConfig, EndpointSync, HeartbeatMetrics, HeartbeatThresholds, PreflightMode, ServerConfig,
+};
@@ -1,34 +1,160 @@
 //! Server configuration types
 
+use crate::agent_config::secret::SecretSource;
+use crate::error::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use uuid::Uuid;
 
 /// Server connection configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServerConfig {
     /// Server URL
     pub url: String,
 
-    /// API key for authentication
+    /// API key for authentication.
+    /// May be a secret reference (`env:`, `file:` or `cmd:`) that is
+    /// resolved at load time.
     pub api_key: Option<String>,
 
+    /// Source `api_key` was resolved from, if it was a secret reference.
+    /// Used to persist the reference instead of the resolved key.
+    #[serde(skip)]
+    pub api_key_source: Option<SecretSource>,
+
     /// Report interval in seconds
     pub report_interval_secs: u64,
 
     /// Heartbeat interval in seconds
     pub heartbeat_interval_secs: u64,
 
+    /// Whether heartbeats report the CPU and memory use of the host or of
+    /// the agent process
+    #[serde(default)]
+    pub heartbeat_metrics: HeartbeatMetrics,
+
+    /// Resource use above which heartbeats report the agent as degraded
+    #[serde(default)]
+    pub heartbeat_thresholds: HeartbeatThresholds,
+
     /// Enable TLS verification
     pub verify_tls: bool,
 
     /// Connection timeout in seconds
     pub timeout_secs: u64,
 
-    /// Retry attempts on failure
+    /// Retries of a failed status report within one report interval, with
+    /// exponential backoff starting at 1 second. 0 sends each report once.
     pub retry_attempts: u32,
 
     /// Claiming configuration
     pub claiming: ClaimConfig,
+
+    /// Path (relative to `url`) or URL of this agent's configuration on the
+    /// server. Delivered when the agent is claimed; defaults to
+    /// `/agent/{agent_id}/configuration`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_url: Option<String>,
+
+    /// How endpoints managed on the server are adopted at startup
+    #[serde(default)]
+    pub endpoint_sync: EndpointSync,
+
+    /// Consecutive failed reports after which the agent is shown as
+    /// disconnected from the server. `None` allows 3.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_failure_grace: Option<u32>,
+
+    /// What to do when the server API is incompatible with this agent
+    #[serde(default)]
+    pub preflight: PreflightMode,
+
+    /// Largest result batch payload, in bytes, the server accepts. Larger
+    /// batches are split; a single larger result goes to the dead-letter
+    /// file. `None` sends batches of any size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_report_bytes: Option<usize>,
+
+    /// Window in seconds within which the first server contact after
+    /// startup is delayed by a random amount, so a fleet booting at once
+    /// does not reach the server at the same moment. 0 disables the delay.
+    #[serde(default)]
+    pub startup_splay_secs: u64,
+
+    /// Gzip-compress the JSON bodies of reports and result batches and send
+    /// them with `Content-Encoding: gzip`. Only enable it for a server that
+    /// accepts compressed requests.
+    #[serde(default)]
+    pub compress_reports: bool,
+}
+
+/// Startup check of the server API version, see `reporter::run_preflight`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightMode {
+    /// Do not query the server at startup
+    Off,
+
+    /// Log a warning and keep running
+    #[default]
+    Warn,
+
+    /// Refuse to start
+    Enforce,
+}
+
+/// Source of the CPU and memory figures sent with heartbeats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HeartbeatMetrics {
+    /// CPU and memory use of the whole host
+    #[default]
+    Host,
+
+    /// CPU and resident memory of the agent process
+    Process,
+}
+
+/// Limits above which a heartbeat reports `degraded` instead of `healthy`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HeartbeatThresholds {
+    /// CPU usage in percent, 0-100
+    pub cpu_percent: f64,
+
+    /// Memory in use as a share of the host's memory, in percent, 0-100
+    pub memory_percent: f64,
+
+    /// Memory in use in megabytes. `None` checks only `memory_percent`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<f64>,
+}
+
+impl Default for HeartbeatThresholds {
+    fn default() -> Self {
+        Self {
+            cpu_percent: 90.0,
+            memory_percent: 90.0,
+            memory_mb: None,
+        }
+    }
+}
+
+/// How server-managed endpoints are combined with the local endpoint list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointSync {
+    /// Only use the endpoints from the local config file
+    #[default]
+    Off,
+
+    /// Add server endpoints and update local endpoints with the same ID,
+    /// keeping endpoints that only exist locally
+    Merge,
+
+    /// Use exactly the endpoints configured on the server
+    Replace,
 }
 
 impl Default for ServerConfig {
@@ -36,12 +162,22 @@ impl Default for ServerConfig {
         Self {
             url: "https://api.smotra.net/v1".to_string(),
             api_key: None,
+            api_key_source: None,
             report_interval_secs: 300,
             heartbeat_interval_secs: 300,
+            heartbeat_metrics: HeartbeatMetrics::default(),
+            heartbeat_thresholds: HeartbeatThresholds::default(),
             verify_tls: true,
             timeout_secs: 5,
             retry_attempts: 3,
             claiming: ClaimConfig::default(),
+            config_url: None,
+            endpoint_sync: EndpointSync::default(),
+            connection_failure_grace: None,
+            preflight: PreflightMode::default(),
+            max_report_bytes: None,
+            startup_splay_secs: 0,
+            compress_reports: false,
         }
     }
 }
@@ -59,27 +195,73 @@ impl ServerConfig {
         Duration::from_secs(self.timeout_secs)
     }
 
+    pub fn startup_splay(&self) -> Duration {
+        Duration::from_secs(self.startup_splay_secs)
+    }
+
+    /// Consecutive failed reports tolerated before `server_connected` turns
+    /// false, 3 unless configured
+    pub fn connection_failure_grace(&self) -> u32 {
+        self.connection_failure_grace.unwrap_or(3)
+    }
+
     pub fn is_configured(&self) -> bool {
         !self.url.is_empty()
             && (self.api_key.is_some() && !self.api_key.as_ref().unwrap().is_empty())
     }
 
+    /// Full URL of the agent configuration for `agent_id`
+    pub fn configuration_url(&self, agent_id: Uuid) -> String {
+        match self.config_url.as_deref() {
+            Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                url.to_string()
+            }
+            Some(path) => format!("{}{}", self.url, path),
+            None => format!("{}/agent/{}/configuration", self.url, agent_id),
+        }
+    }
+
     pub fn is_claim_required(&self) -> bool {
         self.api_key.is_none() || self.api_key.as_ref().unwrap().is_empty()
     }
+
+    /// Replace a secret reference in `api_key` with the value it points to.
+    ///
+    /// Inline keys are left untouched.
+    pub fn resolve_api_key(&mut self) -> Result<()> {
+        let Some(source) = self.api_key.as_deref().and_then(SecretSource::parse) else {
+            return Ok(());
+        };
+
+        self.api_key = Some(source.resolve()?);
+        self.api_key_source = Some(source);
+        Ok(())
+    }
 }
 
 /// Claiming workflow configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct ClaimConfig {
     /// Maximum registration retry attempts
     pub max_registration_retries: u32,
+
+    /// Copy the existing config file to `backup_path` before the claim
+    /// result is saved over it
+    pub backup_config: bool,
+
+    /// Where the config backup is written. `None` appends `.bak` to the
+    /// config file path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_path: Option<String>,
 }
 
 impl Default for ClaimConfig {
     fn default() -> Self {
         Self {
             max_registration_retries: 5,
+            backup_config: true,
+            backup_path: None,
         }
     }
 }
@@ -1,14 +1,20 @@
 //! Configuration types
 
+use super::interpolate::EnvTemplate;
 use super::server_config::ServerConfig;
-use crate::core::Endpoint;
+use crate::core::{CheckKind, Endpoint, SuccessQuorum, TagThresholds};
 use crate::openapi;
+use crate::plugin::PluginInitPolicy;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use uuid::Uuid;
 
 /// Main configuration for the agent
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
     /// Configuration version (used for syncing with server)
     /// 0 means unregistered, will be set to 1+ after pulling registration from server
@@ -20,9 +26,17 @@ pub struct Config {
     /// Human-readable agent name
     pub agent_name: String,
 
+    /// Hostname reported to the server instead of the system hostname
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+
     /// Tags for this agent (used for mesh organization)
     pub tags: Vec<String>,
 
+    /// Address of the local Prometheus `/metrics` endpoint; disabled if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_addr: Option<SocketAddr>,
+
     /// Monitoring configuration
     pub monitoring: MonitoringConfig,
 
@@ -35,8 +49,49 @@ pub struct Config {
     /// Self-upgrade configuration (OpenAPI-generated type)
     pub update: openapi::SelfUpgradeConfig,
 
+    /// Local audit trail of check results
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Additional destinations check results are exported to
+    #[serde(default)]
+    pub sinks: SinksConfig,
+
+    /// Which results are reported to the server
+    #[serde(default)]
+    pub reporting: ReportingConfig,
+
+    /// Local control socket for runtime commands
+    #[serde(default)]
+    pub control: ControlConfig,
+
+    /// Local webhook alerts on sustained endpoint failures
+    #[serde(default)]
+    pub alerting: AlertingConfig,
+
+    /// Config hot-reload behaviour
+    #[serde(default)]
+    pub hot_reload: HotReloadConfig,
+
+    /// Dynamically loaded plugins
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+
+    /// Agent log output
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    /// Latency thresholds inherited by endpoints with a given tag
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thresholds: Vec<TagThresholds>,
+
     /// Endpoints to monitor
     pub endpoints: Vec<Endpoint>,
+
+    /// Values that were loaded from `${NAME}` references. Used to persist
+    /// the references instead of the expanded values.
+    #[serde(skip)]
+    pub env_templates: Vec<EnvTemplate>,
 }
 
 impl Default for Config {
@@ -45,12 +100,24 @@ impl Default for Config {
             version: 0, // 0 means unregistered, will be set to 1+ after pulling registration from server
             agent_id: Uuid::nil(), // nil UUID means unregistered, will be set after registration
             agent_name: String::from("Unnamed Agent"),
+            hostname: None,
             tags: Vec::new(),
+            metrics_addr: None,
             monitoring: MonitoringConfig::default(),
             server: ServerConfig::default(),
             storage: StorageConfig::default(),
             update: openapi::SelfUpgradeConfig::default(),
+            audit: AuditConfig::default(),
+            sinks: SinksConfig::default(),
+            reporting: ReportingConfig::default(),
+            control: ControlConfig::default(),
+            alerting: AlertingConfig::default(),
+            hot_reload: HotReloadConfig::default(),
+            plugins: PluginsConfig::default(),
+            logging: LoggingConfig::default(),
+            thresholds: Vec::new(),
             endpoints: Vec::new(),
+            env_templates: Vec::new(),
         }
     }
 }
@@ -65,8 +132,14 @@ impl Default for openapi::SelfUpgradeConfig {
     }
 }
 
+/// ICMP echo payload size used unless `ping_payload_bytes` is set
+pub const DEFAULT_PING_PAYLOAD_BYTES: usize = 56;
+
+/// Largest ICMP echo payload that fits an IPv4 packet
+pub const MAX_PING_PAYLOAD_BYTES: usize = 65507;
+
 /// Monitoring-specific configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct MonitoringConfig {
     /// Interval between checks in seconds
     pub interval_secs: u64,
@@ -77,14 +150,126 @@ pub struct MonitoringConfig {
     /// Number of pings to send per check
     pub ping_count: u32,
 
+    /// Payload size of ICMP echo requests in bytes. `None` sends the
+    /// default 56 bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ping_payload_bytes: Option<usize>,
+
     /// Maximum number of concurrent checks
     pub max_concurrent: usize,
 
+    /// Maximum number of concurrent checks per check kind, e.g.
+    /// `{ http = 4 }`. Kinds due together share `max_concurrent` fairly
+    /// whether or not they have an entry; `max_concurrent` also caps every
+    /// entry.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub max_concurrent_per_kind: BTreeMap<CheckKind, usize>,
+
     /// Enable traceroute on failed pings
     pub traceroute_on_failure: bool,
 
     /// Maximum TTL for traceroute
     pub traceroute_max_hops: u8,
+
+    /// Probes sent per traceroute hop. `None` sends 3.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceroute_probes_per_hop: Option<u8>,
+
+    /// DSCP value (0-63) to mark check traffic with. `None` leaves the
+    /// operating system default (usually best effort) untouched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dscp: Option<u8>,
+
+    /// Timeout for resolving endpoint hostnames in seconds. `None` uses
+    /// `timeout_secs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_timeout_secs: Option<u64>,
+
+    /// Seconds a resolved endpoint hostname is reused before it is looked
+    /// up again. `None` caches for 300 seconds, `0` resolves on every check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_cache_ttl_secs: Option<u64>,
+
+    /// Successful pings required for a check to succeed, e.g. `"2"` or
+    /// `"60%"` of `ping_count`. `None` requires a single reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success_quorum: Option<SuccessQuorum>,
+
+    /// Suspend checks of endpoints that keep failing. `None` always checks
+    /// every enabled endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+
+    /// Dispatch endpoints in a new random order on every tick instead of
+    /// the configured order
+    #[serde(default)]
+    pub shuffle_endpoints: bool,
+
+    /// Seed for the shuffled order, for reproducible runs. `None` seeds
+    /// from the operating system.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shuffle_seed: Option<u64>,
+
+    /// Seconds after startup, reload or an endpoint change during which an
+    /// endpoint's failures are reported but not alerted on or counted as
+    /// failed checks. `None` disables the warmup window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warmup_secs: Option<u64>,
+
+    /// Look up the PTR hostname of each ping target's resolved IP and
+    /// report it as `resolved_hostname`
+    #[serde(default)]
+    pub reverse_dns: bool,
+
+    /// Timeout for PTR lookups in seconds. `None` uses the DNS resolution
+    /// timeout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverse_dns_timeout_secs: Option<u64>,
+
+    /// Maximum duration of one check cycle in seconds. Checks still running
+    /// (or not yet started) at the deadline are cancelled and counted as
+    /// skipped. `None` lets a cycle run until every check has finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle_deadline_secs: Option<u64>,
+
+    /// Share of the monitoring interval, in percent (1-100), a single check
+    /// may take. Slower checks are flagged as degraded with a note. `None`
+    /// disables the budget.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_latency_budget_percent: Option<u8>,
+
+    /// Inclusive `[start, end]` range of local ports that checks opening
+    /// their own TCP connections or UDP sockets bind to. `None` uses
+    /// ephemeral ports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_port_range: Option<(u16, u16)>,
+
+    /// Local address that ping, traceroute, TCP, UDP, HTTP and gRPC checks
+    /// send from, selecting the egress interface on multi-homed hosts.
+    /// `None` lets the operating system choose.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_address: Option<IpAddr>,
+
+    /// HTTP check settings
+    #[serde(default)]
+    pub http: HttpCheckConfig,
+
+    /// UDP probe settings
+    #[serde(default)]
+    pub udp: UdpCheckConfig,
+
+    /// Report the agent ready only after a check cycle in which at least one
+    /// check succeeded, instead of once the first cycle is scheduled
+    #[serde(default)]
+    pub ready_after_successful_check: bool,
+
+    /// Resolver used to look up endpoint hostnames
+    #[serde(default, skip_serializing_if = "DnsResolverConfig::is_system")]
+    pub resolver: DnsResolverConfig,
+
+    /// Address family checked when a hostname resolves to several addresses
+    #[serde(default)]
+    pub ip_version: IpVersion,
 }
 
 impl Default for MonitoringConfig {
@@ -93,9 +278,31 @@ impl Default for MonitoringConfig {
             interval_secs: 60,
             timeout_secs: 1,
             ping_count: 3,
+            ping_payload_bytes: None,
             max_concurrent: 10,
+            max_concurrent_per_kind: BTreeMap::new(),
             traceroute_on_failure: false,
             traceroute_max_hops: 30,
+            traceroute_probes_per_hop: None,
+            dscp: None,
+            dns_timeout_secs: None,
+            dns_cache_ttl_secs: None,
+            success_quorum: None,
+            circuit_breaker: None,
+            shuffle_endpoints: false,
+            shuffle_seed: None,
+            warmup_secs: None,
+            reverse_dns: false,
+            reverse_dns_timeout_secs: None,
+            cycle_deadline_secs: None,
+            check_latency_budget_percent: None,
+            source_port_range: None,
+            source_address: None,
+            http: HttpCheckConfig::default(),
+            udp: UdpCheckConfig::default(),
+            ready_after_successful_check: false,
+            resolver: DnsResolverConfig::System,
+            ip_version: IpVersion::Auto,
         }
     }
 }
@@ -108,10 +315,271 @@ impl MonitoringConfig {
     pub fn timeout(&self) -> Duration {
         Duration::from_secs(self.timeout_secs)
     }
+
+    /// Concurrent checks allowed for `kind` before fair sharing: its own
+    /// limit, capped by `max_concurrent`
+    pub fn max_concurrent_for(&self, kind: CheckKind) -> usize {
+        self.max_concurrent_per_kind
+            .get(&kind)
+            .map_or(self.max_concurrent, |&limit| limit.min(self.max_concurrent))
+    }
+
+    /// ICMP echo payload size in bytes, 56 unless configured
+    pub fn ping_payload_bytes(&self) -> usize {
+        self.ping_payload_bytes
+            .unwrap_or(DEFAULT_PING_PAYLOAD_BYTES)
+    }
+
+    /// Probes sent per traceroute hop, 3 unless configured
+    pub fn traceroute_probes_per_hop(&self) -> u8 {
+        self.traceroute_probes_per_hop.unwrap_or(3)
+    }
+
+    /// Longest a traceroute after a failed ping may run: every probe of
+    /// every hop waiting out the check timeout
+    pub fn traceroute_timeout(&self) -> Duration {
+        self.timeout()
+            * u32::from(self.traceroute_max_hops)
+            * u32::from(self.traceroute_probes_per_hop())
+    }
+
+    /// Warmup window of new and changed endpoints, zero when disabled
+    pub fn warmup(&self) -> Duration {
+        Duration::from_secs(self.warmup_secs.unwrap_or(0))
+    }
+
+    /// Maximum duration of one check cycle, `None` when unbounded
+    pub fn cycle_deadline(&self) -> Option<Duration> {
+        self.cycle_deadline_secs.map(Duration::from_secs)
+    }
+
+    /// Time a single check may take before it is flagged as degraded,
+    /// `None` when no budget is configured
+    pub fn check_latency_budget(&self) -> Option<Duration> {
+        self.check_latency_budget_percent
+            .map(|percent| self.interval() * u32::from(percent) / 100)
+    }
+
+    /// Hostname resolution timeout, falling back to the check timeout
+    pub fn dns_timeout(&self) -> Duration {
+        self.dns_timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.timeout())
+    }
+
+    /// How long resolved hostnames are cached, zero when caching is disabled
+    pub fn dns_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.dns_cache_ttl_secs.unwrap_or(300))
+    }
+
+    /// PTR lookup timeout, falling back to the DNS resolution timeout
+    pub fn reverse_dns_timeout(&self) -> Duration {
+        self.reverse_dns_timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.dns_timeout())
+    }
+}
+
+/// Default cap on HTTP response body reads, 1 MiB
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// HTTP check settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HttpCheckConfig {
+    /// Stop reading a response body after this many bytes and flag it as
+    /// truncated. Body assertions fail on bodies that exceed it.
+    pub max_body_bytes: u64,
+
+    /// Time allowed to establish the TCP (and TLS) connection. `None`
+    /// leaves connecting bounded by the read timeout only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<u64>,
+
+    /// Time allowed for the whole request, from connecting until the body
+    /// has been read. `None` uses `monitoring.timeout_secs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_timeout_secs: Option<u64>,
+
+    /// Verify TLS certificates of checked `https://` endpoints. Disable for
+    /// endpoints with self-signed certificates.
+    pub verify_tls: bool,
+}
+
+impl Default for HttpCheckConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            connect_timeout_secs: None,
+            read_timeout_secs: None,
+            verify_tls: true,
+        }
+    }
+}
+
+impl HttpCheckConfig {
+    /// Connect timeout, if set
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout_secs.map(Duration::from_secs)
+    }
+
+    /// Overall request timeout, falling back to `check_timeout`
+    pub fn read_timeout(&self, check_timeout: Duration) -> Duration {
+        self.read_timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(check_timeout)
+    }
+}
+
+/// UDP probe settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct UdpCheckConfig {
+    /// Payload of each probe datagram. `None` sends an empty datagram.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+
+    /// Count a probe that gets no response within the check timeout as
+    /// successful. Services that never answer arbitrary datagrams can then
+    /// only fail with an ICMP port unreachable.
+    pub no_response_is_success: bool,
+}
+
+/// Resolver backend for endpoint hostnames
+///
+/// ```toml
+/// [monitoring.resolver]
+/// backend = "nameservers"
+/// nameservers = ["1.1.1.1", "1.0.0.1"]
+/// protocol = "tls"
+/// tls_name = "cloudflare-dns.com"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum DnsResolverConfig {
+    /// Operating system resolver (`getaddrinfo`)
+    #[default]
+    System,
+
+    /// Query the listed nameservers directly, ignoring the hosts file
+    Nameservers {
+        /// Nameserver addresses, `ip` or `ip:port`, tried in order. The port
+        /// defaults to the standard port of `protocol`.
+        nameservers: Vec<String>,
+
+        /// Transport used to reach the nameservers
+        #[serde(default)]
+        protocol: DnsProtocol,
+
+        /// Name the nameservers' certificates are issued for, required for
+        /// `tls` and `https`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tls_name: Option<String>,
+    },
+}
+
+impl DnsResolverConfig {
+    /// Whether the operating system resolver is used
+    pub fn is_system(&self) -> bool {
+        matches!(self, Self::System)
+    }
+
+    /// Socket addresses of the configured nameservers, empty for the system
+    /// resolver
+    pub fn nameserver_addrs(&self) -> Result<Vec<SocketAddr>, String> {
+        let Self::Nameservers {
+            nameservers,
+            protocol,
+            ..
+        } = self
+        else {
+            return Ok(Vec::new());
+        };
+
+        nameservers
+            .iter()
+            .map(|nameserver| {
+                nameserver
+                    .parse::<SocketAddr>()
+                    .or_else(|_| {
+                        nameserver
+                            .parse::<IpAddr>()
+                            .map(|ip| SocketAddr::new(ip, protocol.default_port()))
+                    })
+                    .map_err(|_| format!("invalid nameserver address {:?}", nameserver))
+            })
+            .collect()
+    }
+}
+
+/// Address family of resolved endpoint hostnames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IpVersion {
+    /// IPv6 if the agent has a route to the endpoint's IPv6 address,
+    /// otherwise IPv4
+    #[default]
+    Auto,
+
+    /// IPv4 addresses only
+    V4,
+
+    /// IPv6 addresses only
+    V6,
+}
+
+/// Transport for queries to configured nameservers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsProtocol {
+    /// Plain DNS over UDP
+    #[default]
+    Udp,
+
+    /// Plain DNS over TCP
+    Tcp,
+
+    /// DNS over TLS (DoT)
+    Tls,
+
+    /// DNS over HTTPS (DoH)
+    Https,
+}
+
+impl DnsProtocol {
+    /// Standard nameserver port for the protocol
+    pub fn default_port(self) -> u16 {
+        match self {
+            Self::Udp | Self::Tcp => 53,
+            Self::Tls => 853,
+            Self::Https => 443,
+        }
+    }
+
+    /// Whether the protocol authenticates the nameserver with TLS
+    pub fn uses_tls(self) -> bool {
+        matches!(self, Self::Tls | Self::Https)
+    }
+}
+
+/// Per-endpoint circuit breaker settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed checks after which an endpoint's circuit opens
+    pub failure_threshold: u32,
+
+    /// Seconds checks stay suspended before a single trial check is run
+    pub cooldown_secs: u64,
+}
+
+impl CircuitBreakerConfig {
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown_secs)
+    }
 }
 
 /// Local storage configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StorageConfig {
     /// Directory for storing cached data
     pub cache_dir: String,
@@ -133,6 +601,16 @@ pub struct StorageConfig {
     /// How often (in seconds) the result reporter drains the cache
     /// and attempts to send a batch to the server.
     pub cache_report_interval_secs: u64,
+
+    /// Encrypt the on-disk cache segment with AES-256-GCM.
+    /// Requires `encryption_key`.
+    #[serde(default)]
+    pub encrypt: bool,
+
+    /// Secret the cache encryption key is derived from: an inline value or
+    /// an `env:`/`file:`/`cmd:` reference, resolved when the agent starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_key: Option<String>,
 }
 
 impl Default for StorageConfig {
@@ -144,6 +622,8 @@ impl Default for StorageConfig {
             cache_enabled: true,
             cache_batch_size: 100,
             cache_report_interval_secs: 60,
+            encrypt: false,
+            encryption_key: None,
         }
     }
 }
@@ -152,4 +632,404 @@ impl StorageConfig {
     pub fn max_cache_age(&self) -> Duration {
         Duration::from_secs(self.max_cache_age_secs)
     }
+
+    /// Path of the persistent agent state file inside `cache_dir`
+    pub fn state_file(&self) -> PathBuf {
+        Path::new(&self.cache_dir).join("agent_state.json")
+    }
+
+    /// Path of the cache segment holding unsent results across restarts
+    pub fn cache_segment_file(&self) -> PathBuf {
+        Path::new(&self.cache_dir).join("results.seg")
+    }
+
+    /// Path of the journal keeping cached results across crashes
+    pub fn cache_journal_file(&self) -> PathBuf {
+        Path::new(&self.cache_dir).join("results.ndjson")
+    }
+
+    /// Path of the dead-letter file for results the server cannot accept
+    pub fn dead_letter_file(&self) -> PathBuf {
+        Path::new(&self.cache_dir).join("dead_letter.ndjson")
+    }
+}
+
+/// Local NDJSON audit trail of every check result
+///
+/// Independent of the server reporter: when enabled, every `MonitoringResult`
+/// is appended as one JSON line to `path`, which is rotated by size and/or age.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Write every check result to the audit file
+    pub enabled: bool,
+
+    /// Path of the active audit file. Rotated files get `.1`, `.2`, ... suffixes,
+    /// `.1` being the most recent.
+    pub path: String,
+
+    /// Rotate before the active file would grow beyond this many bytes (0 disables)
+    pub max_file_size_bytes: u64,
+
+    /// Rotate once the active file has been open for this many seconds (0 disables)
+    pub rotate_interval_secs: u64,
+
+    /// Number of rotated files to keep
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "./audit/results.ndjson".to_string(),
+            max_file_size_bytes: 10 * 1024 * 1024, // 10 MiB
+            rotate_interval_secs: 0,
+            max_files: 5,
+        }
+    }
+}
+
+impl AuditConfig {
+    pub fn rotate_interval(&self) -> Option<Duration> {
+        (self.rotate_interval_secs > 0).then(|| Duration::from_secs(self.rotate_interval_secs))
+    }
+}
+
+/// Additional destinations check results are exported to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SinksConfig {
+    /// Publish every check result to an MQTT broker
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mqtt: Option<MqttSinkConfig>,
+
+    /// Push check metrics to a Prometheus remote-write endpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_write: Option<RemoteWriteSinkConfig>,
+}
+
+/// MQTT result sink
+///
+/// Each `MonitoringResult` is published as JSON to a topic rendered from
+/// `topic_template`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MqttSinkConfig {
+    /// Broker URL: `mqtt://host:1883` or `mqtts://host:8883` for TLS
+    pub broker_url: String,
+
+    /// Topic for each result. `{agent_id}` and `{endpoint}` (the endpoint ID)
+    /// are replaced per result.
+    pub topic_template: String,
+
+    /// MQTT quality of service level (0, 1 or 2)
+    pub qos: u8,
+
+    /// Client identifier; defaults to `smotra-{agent_id}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// Username for broker authentication
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    /// Password for broker authentication
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// Keep-alive interval in seconds
+    pub keep_alive_secs: u64,
+}
+
+impl Default for MqttSinkConfig {
+    fn default() -> Self {
+        Self {
+            broker_url: "mqtt://localhost:1883".to_string(),
+            topic_template: "smotra/{agent_id}/{endpoint}".to_string(),
+            qos: 1,
+            client_id: None,
+            username: None,
+            password: None,
+            keep_alive_secs: 30,
+        }
+    }
+}
+
+impl MqttSinkConfig {
+    pub fn keep_alive(&self) -> Duration {
+        Duration::from_secs(self.keep_alive_secs)
+    }
+}
+
+/// Prometheus remote-write sink
+///
+/// Check results are converted to time series (success, latency, packet
+/// loss) and pushed as snappy-compressed protobuf `WriteRequest`s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RemoteWriteSinkConfig {
+    /// Remote-write endpoint, e.g. `http://prometheus:9090/api/v1/write`
+    pub url: String,
+
+    /// Sent as `Authorization: Bearer <token>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+
+    /// Labels added to every series, e.g. `{ job = "smotra" }`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+
+    /// Push buffered results at least this often
+    pub flush_interval_secs: u64,
+
+    /// Push as soon as this many results are buffered
+    pub batch_size: usize,
+
+    /// Request timeout in seconds
+    pub timeout_secs: u64,
+}
+
+impl Default for RemoteWriteSinkConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:9090/api/v1/write".to_string(),
+            bearer_token: None,
+            labels: BTreeMap::new(),
+            flush_interval_secs: 15,
+            batch_size: 500,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl RemoteWriteSinkConfig {
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_secs(self.flush_interval_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Local control socket
+///
+/// Accepts runtime commands from operators, e.g. temporarily disabling a
+/// noisy endpoint. Unix only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ControlConfig {
+    /// Listen on the control socket
+    pub enabled: bool,
+
+    /// Path of the Unix domain socket
+    pub socket_path: String,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            socket_path: "./cache/control.sock".to_string(),
+        }
+    }
+}
+
+/// Agent log output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Which successful checks log an info line. Unset logs every success
+    /// at debug level; failures are always logged at info level.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_successful_checks: Option<SuccessLogging>,
+
+    /// With `sampled`, log one in this many successful checks
+    pub success_sample_rate: u32,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            log_successful_checks: None,
+            success_sample_rate: 100,
+        }
+    }
+}
+
+/// Logging of successful checks, see `LoggingConfig::log_successful_checks`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SuccessLogging {
+    /// Do not log successful checks
+    Off,
+
+    /// Log about one in `success_sample_rate` successful checks
+    Sampled,
+
+    /// Log every successful check
+    Every,
+}
+
+/// Local alerting
+///
+/// Sends a webhook when an endpoint fails `failure_threshold` checks in a row
+/// and again when it recovers. Alert state is persisted to `state_path` so
+/// alerts neither re-fire nor miss their recovery across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AlertingConfig {
+    /// Evaluate alerts and send webhooks
+    pub enabled: bool,
+
+    /// URL alert events are POSTed to as JSON
+    pub webhook_url: String,
+
+    /// Consecutive failed checks after which an endpoint's alert fires
+    pub failure_threshold: u32,
+
+    /// Timeout for webhook requests in seconds
+    pub webhook_timeout_secs: u64,
+
+    /// File the per-endpoint alert state is persisted in
+    pub state_path: String,
+
+    /// Maximum number of endpoints whose alert state is kept
+    pub max_tracked_endpoints: usize,
+}
+
+impl Default for AlertingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            webhook_url: String::new(),
+            failure_threshold: 3,
+            webhook_timeout_secs: 10,
+            state_path: "./cache/alert_state.json".to_string(),
+            max_tracked_endpoints: 10_000,
+        }
+    }
+}
+
+impl AlertingConfig {
+    pub fn webhook_timeout(&self) -> Duration {
+        Duration::from_secs(self.webhook_timeout_secs)
+    }
+}
+
+/// Config hot-reload
+///
+/// Reload triggers (file changes, SIGHUP) arriving within `cooldown_ms` of
+/// the first one are coalesced into a single reload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HotReloadConfig {
+    /// Window in milliseconds during which further reload triggers are
+    /// coalesced (0 reloads on every trigger)
+    pub cooldown_ms: u64,
+}
+
+impl Default for HotReloadConfig {
+    fn default() -> Self {
+        Self { cooldown_ms: 1000 }
+    }
+}
+
+impl HotReloadConfig {
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_millis(self.cooldown_ms)
+    }
+}
+
+/// Dynamic plugin loading configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Directories searched for plugin libraries, in load order. A plugin
+    /// from a later directory replaces a same-named plugin from an earlier
+    /// one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dirs: Vec<String>,
+
+    /// What to do when a plugin fails to initialize on start: `fail-fast`
+    /// (alias `fail`) stops the agent from starting, `skip-failed` (alias
+    /// `skip`) logs the failure and runs without the plugin. `None` keeps
+    /// the policy of the registry handed to `Agent::with_plugins`, which
+    /// defaults to `fail-fast`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_init_failure: Option<PluginInitPolicy>,
+}
+
+/// Server result reporting configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ReportingConfig {
+    /// Predicates a result must match to be cached and reported
+    pub filter: ResultFilter,
+
+    /// Report URLs per check kind, overriding the default
+    /// `{server.url}/agent/{agent_id}/results`. A value starting with `/`
+    /// is relative to `server.url`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub routes: BTreeMap<CheckKind, String>,
+
+    /// Report results aggregated into time buckets of this many seconds,
+    /// sent to `{server.url}/agent/{agent_id}/reports` as `ResultReport`s,
+    /// instead of individually. `routes` do not apply to bucketed reports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket_secs: Option<u64>,
+
+    /// How results are sent to the server
+    pub mode: ReportMode,
+}
+
+/// Transport of reported results, see `ReportingConfig::mode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportMode {
+    /// POST cached results in batches every `storage.cache_report_interval_secs`
+    #[default]
+    Batch,
+
+    /// Stream results as NDJSON over one long-lived request to
+    /// `{server.url}/agent/{agent_id}/results/stream`, falling back to
+    /// batches if the server does not support it. Kinds with a route are
+    /// still sent in batches.
+    Stream,
+}
+
+/// Predicates selecting which results are reported to the server
+///
+/// All predicates must match. Filtered-out results still count toward local
+/// statistics and are still written to the audit log and sinks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ResultFilter {
+    /// Report results with this outcome
+    pub status: StatusFilter,
+
+    /// Report only these check kinds (empty reports every kind)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<CheckKind>,
+
+    /// Report only endpoints carrying at least one of these tags
+    /// (empty reports every endpoint)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Result outcome matched by `ResultFilter::status`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusFilter {
+    /// Successful and failed results
+    #[default]
+    All,
+
+    /// Failed results only
+    Failures,
+
+    /// Successful results only
+    Successes,
 }
@@ -0,0 +1,499 @@
+//! Webhook alerts on sustained endpoint failures
+//!
+//! Every check result updates the endpoint's failure streak. When the streak
+//! reaches `failure_threshold` the endpoint's alert fires; the next successful
+//! check resolves it. Each transition produces one [`AlertEvent`], which is
+//! POSTed as JSON to `webhook_url`:
+//!
+//! ```json
+//! {
+//!   "status": "firing",
+//!   "agent_id": "0193...",
+//!   "endpoint_id": "0194...",
+//!   "endpoint_address": "10.0.0.1",
+//!   "failure_streak": 3,
+//!   "error": "Request timeout",
+//!   "timestamp": "2026-01-01T00:00:00Z"
+//! }
+//! ```
+//!
+//! Failures of results marked as warmup (see `monitoring.warmup_secs`) are
+//! ignored: they neither extend the failure streak nor fire an alert. So are
+//! traceroutes, which follow a failed check (see
+//! `monitoring.traceroute_on_failure`) whose failure was already counted.
+//!
+//! The state behind these transitions lives in an [`AlertStateStore`] that is
+//! saved after every change and loaded on startup, so a restart neither fires
+//! an already firing alert again nor forgets to send its recovery. An
+//! endpoint's alert status only changes once the webhook has accepted the
+//! event. Until then the transition stays pending, and the endpoint's next
+//! check result raises it again, so a failed delivery is retried instead of
+//! being recorded as sent.
+
+use super::state::{AlertStateStore, AlertStatus};
+use crate::agent_config::AlertingConfig;
+use crate::core::{CheckType, MonitoringResult};
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// An alert transition of one endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertEvent {
+    /// `firing` when the alert starts, `ok` when it resolves
+    pub status: AlertStatus,
+    pub agent_id: Uuid,
+    pub endpoint_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint_address: Option<String>,
+    /// Consecutive failed checks at the time of the event
+    pub failure_streak: u32,
+    /// Error of the check that triggered the event, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Delivers alert events to the configured webhook
+#[derive(Clone)]
+pub struct AlertWebhook {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl AlertWebhook {
+    fn new(config: &AlertingConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.webhook_timeout())
+            .build()?;
+        Ok(Self {
+            client,
+            url: config.webhook_url.clone(),
+        })
+    }
+
+    /// POST `event` to the webhook
+    pub async fn send(&self, event: &AlertEvent) -> Result<()> {
+        let response = self.client.post(&self.url).json(event).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::Network(format!(
+                "Alert webhook returned {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Alert state shared between the alerter and its webhook deliveries
+struct Shared {
+    store: AlertStateStore,
+    /// Endpoints with an event being delivered
+    in_flight: HashSet<Uuid>,
+}
+
+impl Shared {
+    /// Record the outcome of delivering `event`; only a delivered event
+    /// changes the endpoint's alert status
+    fn finish_delivery(&mut self, config: &AlertingConfig, event: &AlertEvent, delivered: bool) {
+        self.in_flight.remove(&event.endpoint_id);
+        if !delivered {
+            return;
+        }
+
+        let mut state = self.store.get(event.endpoint_id);
+        state.status = event.status;
+        state.last_alert_at = Some(event.timestamp);
+        state.updated_at = Some(Utc::now());
+        self.store
+            .set(event.endpoint_id, state, config.max_tracked_endpoints);
+        self.save(config);
+    }
+
+    fn save(&self, config: &AlertingConfig) {
+        if let Err(e) = self.store.save(&config.state_path) {
+            warn!("Failed to save alert state to {}: {}", config.state_path, e);
+        }
+    }
+}
+
+/// Evaluates check results into alert events, persisting alert state
+pub struct Alerter {
+    config: AlertingConfig,
+    shared: Arc<Mutex<Shared>>,
+    webhook: AlertWebhook,
+}
+
+impl Alerter {
+    /// Create an alerter for `config`, restoring the state of a previous run
+    pub fn new(config: AlertingConfig) -> Result<Self> {
+        let store = AlertStateStore::load(&config.state_path)?;
+        if !store.is_empty() {
+            info!(
+                "Restored alert state of {} endpoints from {}",
+                store.len(),
+                config.state_path
+            );
+        }
+        let webhook = AlertWebhook::new(&config)?;
+
+        Ok(Self {
+            config,
+            shared: Arc::new(Mutex::new(Shared {
+                store,
+                in_flight: HashSet::new(),
+            })),
+            webhook,
+        })
+    }
+
+    /// Configuration this alerter was created with
+    pub fn config(&self) -> &AlertingConfig {
+        &self.config
+    }
+
+    /// Send `event` to the webhook. The endpoint's alert status is updated
+    /// and saved only if the webhook accepts it; otherwise the transition
+    /// stays pending and is raised again by the next check result.
+    pub fn deliver(&self, event: AlertEvent) -> impl Future<Output = Result<()>> + Send + 'static {
+        let webhook = self.webhook.clone();
+        let shared = Arc::clone(&self.shared);
+        let config = self.config.clone();
+        async move {
+            let sent = webhook.send(&event).await;
+            shared.lock().finish_delivery(&config, &event, sent.is_ok());
+            sent?;
+
+            info!(
+                "Alert for endpoint {} {}",
+                event
+                    .endpoint_address
+                    .clone()
+                    .unwrap_or_else(|| event.endpoint_id.to_string()),
+                if event.status == AlertStatus::Firing {
+                    "firing"
+                } else {
+                    "resolved"
+                }
+            );
+            Ok(())
+        }
+    }
+
+    /// Update the alert state with `result`, returning the transition to
+    /// deliver, if any. `endpoint_address` is included in the event.
+    ///
+    /// No event is returned while one for the same endpoint is being
+    /// delivered.
+    pub fn observe(
+        &mut self,
+        result: &MonitoringResult,
+        endpoint_address: Option<&str>,
+    ) -> Option<AlertEvent> {
+        if result.is_warmup() && !result.is_successful() {
+            return None;
+        }
+        if matches!(result.check_type, CheckType::TracerouteCheck(_)) {
+            return None;
+        }
+
+        let mut shared = self.shared.lock();
+        let mut state = shared.store.get(result.endpoint_id);
+        let previous = state.clone();
+        let now = Utc::now();
+
+        // `state.status` is the last delivered status; it changes in
+        // `finish_delivery` once the webhook accepted the event
+        let status = if result.is_successful() {
+            state.failure_streak = 0;
+            AlertStatus::Ok
+        } else {
+            state.failure_streak = state.failure_streak.saturating_add(1);
+            if state.status == AlertStatus::Firing
+                || state.failure_streak >= self.config.failure_threshold
+            {
+                AlertStatus::Firing
+            } else {
+                AlertStatus::Ok
+            }
+        };
+
+        if state.failure_streak != previous.failure_streak {
+            state.updated_at = Some(now);
+            shared.store.set(
+                result.endpoint_id,
+                state.clone(),
+                self.config.max_tracked_endpoints,
+            );
+            shared.save(&self.config);
+        }
+
+        if status == state.status || !shared.in_flight.insert(result.endpoint_id) {
+            return None;
+        }
+
+        Some(AlertEvent {
+            status,
+            agent_id: result.agent_id,
+            endpoint_id: result.endpoint_id,
+            endpoint_address: endpoint_address.map(str::to_string),
+            failure_streak: if status == AlertStatus::Firing {
+                state.failure_streak
+            } else {
+                previous.failure_streak
+            },
+            error: result.error_message(),
+            timestamp: now,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        HttpGetCheck, HttpGetCheckType, HttpGetResult, TracerouteCheck, TracerouteCheckType,
+        TracerouteResult,
+    };
+    use mockito::Matcher;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn result(endpoint_id: Uuid, success: bool) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::nil(),
+            endpoint_id,
+            check_type: CheckType::HttpGetCheck(HttpGetCheck {
+                r#type: HttpGetCheckType::Httpget,
+                result: HttpGetResult {
+                    status_code: if success { 200 } else { 503 },
+                    response_time_ms: Some(1.0),
+                    dns_resolution_ms: None,
+                    response_size_bytes: None,
+                    body_truncated: None,
+                    body_matched: None,
+                    body_match_detail: None,
+                    error_details: None,
+                    success,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn traceroute(endpoint_id: Uuid) -> MonitoringResult {
+        MonitoringResult {
+            check_type: CheckType::TracerouteCheck(TracerouteCheck {
+                r#type: TracerouteCheckType::Traceroute,
+                result: TracerouteResult {
+                    hops: Vec::new(),
+                    target_reached: false,
+                    error_details: None,
+                },
+            }),
+            ..result(endpoint_id, false)
+        }
+    }
+
+    /// Record `event` as accepted by the webhook
+    fn delivered(alerter: &Alerter, event: &AlertEvent) {
+        alerter
+            .shared
+            .lock()
+            .finish_delivery(&alerter.config, event, true);
+    }
+
+    fn alerting_config(dir: &TempDir, webhook_url: String) -> AlertingConfig {
+        AlertingConfig {
+            enabled: true,
+            webhook_url,
+            failure_threshold: 2,
+            state_path: dir.path().join("alert_state.json").display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_traceroutes_do_not_extend_the_failure_streak() {
+        let dir = TempDir::new().unwrap();
+        let mut alerter = Alerter::new(alerting_config(&dir, "http://localhost".into())).unwrap();
+        let id = Uuid::now_v7();
+
+        assert_eq!(alerter.observe(&result(id, false), None), None);
+        assert_eq!(alerter.observe(&traceroute(id), None), None);
+        assert_eq!(alerter.observe(&traceroute(id), None), None);
+
+        let fired = alerter.observe(&result(id, false), None).unwrap();
+        assert_eq!(fired.failure_streak, 2);
+    }
+
+    #[test]
+    fn test_fires_once_after_threshold_and_resolves() {
+        let dir = TempDir::new().unwrap();
+        let mut alerter = Alerter::new(alerting_config(&dir, "http://localhost".into())).unwrap();
+        let id = Uuid::now_v7();
+
+        assert_eq!(alerter.observe(&result(id, false), None), None);
+        let fired = alerter
+            .observe(&result(id, false), Some("10.0.0.1"))
+            .unwrap();
+        assert_eq!(fired.status, AlertStatus::Firing);
+        assert_eq!(fired.failure_streak, 2);
+        assert_eq!(fired.endpoint_address.as_deref(), Some("10.0.0.1"));
+        delivered(&alerter, &fired);
+
+        assert_eq!(alerter.observe(&result(id, false), None), None);
+
+        let resolved = alerter.observe(&result(id, true), None).unwrap();
+        assert_eq!(resolved.status, AlertStatus::Ok);
+        assert_eq!(resolved.failure_streak, 3);
+        delivered(&alerter, &resolved);
+        assert_eq!(alerter.observe(&result(id, true), None), None);
+    }
+
+    #[test]
+    fn test_warmup_failures_do_not_fire() {
+        let dir = TempDir::new().unwrap();
+        let mut alerter = Alerter::new(alerting_config(&dir, "http://localhost".into())).unwrap();
+        let id = Uuid::now_v7();
+        let warmup_failure = || MonitoringResult {
+            warmup: Some(true),
+            ..result(id, false)
+        };
+
+        // Enough failures to fire, but all within the warmup window.
+        for _ in 0..3 {
+            assert_eq!(alerter.observe(&warmup_failure(), None), None);
+        }
+
+        // The streak starts counting once warmup is over.
+        assert_eq!(alerter.observe(&result(id, false), None), None);
+        let fired = alerter.observe(&result(id, false), None).unwrap();
+        assert_eq!(fired.status, AlertStatus::Firing);
+        assert_eq!(fired.failure_streak, 2);
+    }
+
+    #[tokio::test]
+    async fn test_firing_alert_survives_restart() {
+        let mut server = mockito::Server::new_async().await;
+        let firing = server
+            .mock("POST", "/alerts")
+            .match_body(Matcher::PartialJson(json!({ "status": "firing" })))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        let resolved = server
+            .mock("POST", "/alerts")
+            .match_body(Matcher::PartialJson(json!({ "status": "ok" })))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let config = alerting_config(&dir, format!("{}/alerts", server.url()));
+        let id = Uuid::now_v7();
+
+        // First run: the endpoint fails twice and the alert fires.
+        {
+            let mut alerter = Alerter::new(config.clone()).unwrap();
+            for _ in 0..2 {
+                if let Some(event) = alerter.observe(&result(id, false), None) {
+                    alerter.deliver(event).await.unwrap();
+                }
+            }
+        }
+
+        // Second run: further failures must not fire again, and the first
+        // success resolves the alert raised by the previous run.
+        let mut alerter = Alerter::new(config).unwrap();
+        assert_eq!(alerter.observe(&result(id, false), None), None);
+        let event = alerter
+            .observe(&result(id, true), None)
+            .expect("recovery after restart");
+        assert_eq!(event.status, AlertStatus::Ok);
+        alerter.deliver(event).await.unwrap();
+
+        firing.assert_async().await;
+        resolved.assert_async().await;
+    }
+
+    #[test]
+    fn test_no_event_while_one_is_in_flight() {
+        let dir = TempDir::new().unwrap();
+        let mut config = alerting_config(&dir, "http://localhost".into());
+        config.failure_threshold = 1;
+        let mut alerter = Alerter::new(config).unwrap();
+        let id = Uuid::now_v7();
+
+        let fired = alerter.observe(&result(id, false), None).unwrap();
+        assert_eq!(alerter.observe(&result(id, false), None), None);
+        assert_eq!(alerter.observe(&result(id, true), None), None);
+
+        // Delivered while the endpoint already recovered: the recovery
+        // follows with the next result.
+        delivered(&alerter, &fired);
+        let resolved = alerter.observe(&result(id, true), None).unwrap();
+        assert_eq!(resolved.status, AlertStatus::Ok);
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_alert_is_not_persisted_and_raised_again() {
+        let mut server = mockito::Server::new_async().await;
+        let rejected = server
+            .mock("POST", "/alerts")
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let mut config = alerting_config(&dir, format!("{}/alerts", server.url()));
+        config.failure_threshold = 1;
+        let id = Uuid::now_v7();
+
+        let mut alerter = Alerter::new(config.clone()).unwrap();
+        let event = alerter.observe(&result(id, false), None).unwrap();
+        assert!(alerter.deliver(event).await.is_err());
+        rejected.assert_async().await;
+
+        let stored = AlertStateStore::load(&config.state_path).unwrap().get(id);
+        assert_eq!(stored.status, AlertStatus::Ok);
+        assert_eq!(stored.failure_streak, 1);
+
+        // The next failure retries the alert.
+        let retry = alerter.observe(&result(id, false), None).unwrap();
+        assert_eq!(retry.status, AlertStatus::Firing);
+        assert_eq!(retry.failure_streak, 2);
+
+        // So does a restart, since the alert was never recorded as firing.
+        let accepted = server
+            .mock("POST", "/alerts")
+            .match_body(Matcher::PartialJson(json!({ "status": "firing" })))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        let mut alerter = Alerter::new(config.clone()).unwrap();
+        let event = alerter.observe(&result(id, false), None).unwrap();
+        alerter.deliver(event).await.unwrap();
+        accepted.assert_async().await;
+
+        let stored = AlertStateStore::load(&config.state_path).unwrap().get(id);
+        assert_eq!(stored.status, AlertStatus::Firing);
+        assert_eq!(alerter.observe(&result(id, false), None), None);
+    }
+}
@@ -0,0 +1,8 @@
+//! Local alerting on sustained endpoint failures.
+//!
+//! See [`alerter::Alerter`] for full documentation.
+
+mod alerter;
+mod state;
+
+pub use alerter::Alerter;
@@ -0,0 +1,212 @@
+//! Persistent per-endpoint alert state
+//!
+//! Stored as JSON at `[alerting] state_path` and rewritten atomically after
+//! every change, so a restarted agent continues each endpoint's failure
+//! streak and firing/ok status where the previous run left off.
+//!
+//! Only endpoints with something to remember (a failure streak or a firing
+//! alert) are stored. The store is bounded by `max_tracked_endpoints`; when
+//! it is full, non-firing entries are evicted before firing ones, least
+//! recently changed first.
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Whether an endpoint's alert is currently firing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    #[default]
+    Ok,
+    Firing,
+}
+
+/// Alert state of one endpoint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointAlertState {
+    /// Consecutive failed checks
+    pub failure_streak: u32,
+
+    /// Current alert status
+    pub status: AlertStatus,
+
+    /// When the last alert event (firing or resolved) was raised
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_alert_at: Option<DateTime<Utc>>,
+
+    /// When this entry last changed, used for eviction
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl EndpointAlertState {
+    /// Whether the entry carries no information worth storing
+    fn is_idle(&self) -> bool {
+        self.failure_streak == 0 && self.status == AlertStatus::Ok
+    }
+}
+
+/// Alert state of all tracked endpoints
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertStateStore {
+    endpoints: HashMap<Uuid, EndpointAlertState>,
+}
+
+impl AlertStateStore {
+    /// Load state from `path`, returning an empty store if it does not exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                Error::Config(format!(
+                    "Failed to parse alert state file {}: {}",
+                    path.display(),
+                    e
+                ))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save state to `path`, replacing the previous file atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// State of `endpoint_id`; untracked endpoints are ok with no streak
+    pub fn get(&self, endpoint_id: Uuid) -> EndpointAlertState {
+        self.endpoints
+            .get(&endpoint_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Store `state` for `endpoint_id`, keeping at most `max_entries` entries
+    pub fn set(&mut self, endpoint_id: Uuid, state: EndpointAlertState, max_entries: usize) {
+        if state.is_idle() {
+            self.endpoints.remove(&endpoint_id);
+            return;
+        }
+
+        self.endpoints.insert(endpoint_id, state);
+        while self.endpoints.len() > max_entries.max(1) {
+            let evict = self
+                .endpoints
+                .iter()
+                .filter(|(id, _)| **id != endpoint_id)
+                .min_by_key(|(_, s)| (s.status == AlertStatus::Firing, s.updated_at))
+                .map(|(id, _)| *id);
+            match evict {
+                Some(id) => self.endpoints.remove(&id),
+                None => break,
+            };
+        }
+    }
+
+    /// Number of tracked endpoints
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Whether no endpoint is tracked
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    fn failing(streak: u32, status: AlertStatus, updated_at: DateTime<Utc>) -> EndpointAlertState {
+        EndpointAlertState {
+            failure_streak: streak,
+            status,
+            last_alert_at: None,
+            updated_at: Some(updated_at),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested/alert_state.json");
+        let id = Uuid::now_v7();
+
+        let mut store = AlertStateStore::default();
+        store.set(id, failing(4, AlertStatus::Firing, Utc::now()), 10);
+        store.save(&path).unwrap();
+
+        let loaded = AlertStateStore::load(&path).unwrap();
+        assert_eq!(loaded, store);
+        assert_eq!(loaded.get(id).status, AlertStatus::Firing);
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_load_missing_file_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let store = AlertStateStore::load(dir.path().join("alert_state.json")).unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_idle_entries_are_not_stored() {
+        let id = Uuid::now_v7();
+        let mut store = AlertStateStore::default();
+
+        store.set(id, failing(1, AlertStatus::Ok, Utc::now()), 10);
+        assert_eq!(store.len(), 1);
+
+        store.set(id, EndpointAlertState::default(), 10);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_eviction_keeps_firing_and_recent_entries() {
+        let now = Utc::now();
+        let (firing, old, recent, new) = (
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+            Uuid::now_v7(),
+        );
+        let mut store = AlertStateStore::default();
+
+        store.set(
+            firing,
+            failing(5, AlertStatus::Firing, now - Duration::hours(2)),
+            3,
+        );
+        store.set(
+            old,
+            failing(1, AlertStatus::Ok, now - Duration::hours(1)),
+            3,
+        );
+        store.set(recent, failing(1, AlertStatus::Ok, now), 3);
+        store.set(new, failing(1, AlertStatus::Ok, now), 3);
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(old), EndpointAlertState::default());
+        assert_eq!(store.get(firing).status, AlertStatus::Firing);
+        assert_eq!(store.get(recent).failure_streak, 1);
+        assert_eq!(store.get(new).failure_streak, 1);
+    }
+}
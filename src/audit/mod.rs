@@ -0,0 +1,7 @@
+//! Local NDJSON audit trail of check results.
+//!
+//! See [`writer::AuditLog`] for full documentation.
+
+mod writer;
+
+pub use writer::AuditLog;
@@ -0,0 +1,379 @@
+//! Rotating NDJSON audit file writer
+//!
+//! Each `MonitoringResult` is serialized as a single JSON line and appended to
+//! the active audit file. Before a write would push the file past
+//! `max_file_size_bytes`, or once it has been open for `rotate_interval_secs`,
+//! the file is rotated logrotate-style:
+//!
+//! ```text
+//! results.ndjson    -> results.ndjson.1
+//! results.ndjson.1  -> results.ndjson.2
+//! ...
+//! results.ndjson.N  -> deleted (N = max_files)
+//! ```
+//!
+//! Writing never blocks the result pipeline. Lines are queued to a writer
+//! task that appends them on the blocking thread pool; lines beyond
+//! `QUEUE_CAPACITY` pending ones are dropped. Dropping the log lets the task
+//! finish the queued lines, [`AuditLog::close`] also waits for them.
+
+use crate::agent_config::AuditConfig;
+use crate::error::Result;
+use crate::sinks::EncodedResult;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Lines queued for the writer task before new ones are dropped
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// Most queued lines appended in one blocking call
+const WRITE_BATCH: usize = 256;
+
+/// Append-only NDJSON audit log with size and time based rotation
+pub struct AuditLog {
+    config: AuditConfig,
+    queue: mpsc::Sender<Vec<u8>>,
+    task: JoinHandle<()>,
+}
+
+/// Audit file state owned by the writer task
+struct AuditFile {
+    config: AuditConfig,
+    active: Option<ActiveFile>,
+}
+
+/// Currently open audit file
+struct ActiveFile {
+    file: File,
+    size: u64,
+    opened_at: Instant,
+}
+
+impl AuditLog {
+    /// Create an audit log for `config` and start its writer task. The file
+    /// is opened lazily on first write.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn new(config: AuditConfig) -> Self {
+        let (queue, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let task = tokio::spawn(run_writer(rx, AuditFile::new(config.clone())));
+        Self {
+            config,
+            queue,
+            task,
+        }
+    }
+
+    /// Configuration this audit log was created with
+    pub fn config(&self) -> &AuditConfig {
+        &self.config
+    }
+
+    /// Queue `result` as one JSON line for the writer task
+    pub(crate) fn write(&self, result: &EncodedResult) -> Result<()> {
+        let json = result.json()?;
+        let mut line = Vec::with_capacity(json.len() + 1);
+        line.extend_from_slice(json);
+        line.push(b'\n');
+
+        self.queue.try_send(line).map_err(|e| {
+            std::io::Error::other(format!("Failed to queue audit record: {}", e)).into()
+        })
+    }
+
+    /// Stop accepting results and wait until the queued ones are written
+    pub(crate) async fn close(self) {
+        drop(self.queue);
+        if let Err(e) = self.task.await {
+            warn!("Audit writer task failed: {}", e);
+        }
+    }
+}
+
+/// Append queued lines until every sender is gone
+async fn run_writer(mut rx: mpsc::Receiver<Vec<u8>>, mut file: AuditFile) {
+    let mut lines = Vec::with_capacity(WRITE_BATCH);
+    while rx.recv_many(&mut lines, WRITE_BATCH).await > 0 {
+        let batch = std::mem::take(&mut lines);
+        let written = tokio::task::spawn_blocking(move || {
+            for line in &batch {
+                if let Err(e) = file.append(line) {
+                    warn!("Failed to write audit record: {}", e);
+                }
+            }
+            file
+        })
+        .await;
+        match written {
+            Ok(returned) => file = returned,
+            Err(e) => {
+                warn!("Audit write task failed: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+impl AuditFile {
+    fn new(config: AuditConfig) -> Self {
+        Self {
+            config,
+            active: None,
+        }
+    }
+
+    /// Append one line, rotating first if required
+    fn append(&mut self, line: &[u8]) -> Result<()> {
+        let path = PathBuf::from(&self.config.path);
+
+        if let Some(current) = self.active.as_ref() {
+            if self.needs_rotation(current, line.len() as u64) {
+                self.active = None;
+                rotate_files(&path, self.config.max_files)?;
+            }
+        }
+
+        if self.active.is_none() {
+            let opened = open(&path)?;
+            // A file left behind by a previous run may already be too large.
+            if self.needs_rotation(&opened, line.len() as u64) {
+                drop(opened);
+                rotate_files(&path, self.config.max_files)?;
+                self.active = Some(open(&path)?);
+            } else {
+                self.active = Some(opened);
+            }
+        }
+
+        let current = self.active.as_mut().expect("audit file is open");
+        current.file.write_all(line)?;
+        current.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn needs_rotation(&self, current: &ActiveFile, incoming: u64) -> bool {
+        let max_size = self.config.max_file_size_bytes;
+        let too_large = max_size > 0 && current.size > 0 && current.size + incoming > max_size;
+        let too_old = self
+            .config
+            .rotate_interval()
+            .is_some_and(|interval| current.opened_at.elapsed() >= interval);
+
+        too_large || too_old
+    }
+}
+
+/// Open `path` for appending, creating parent directories as needed
+fn open(path: &Path) -> Result<ActiveFile> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+
+    Ok(ActiveFile {
+        file,
+        size,
+        opened_at: Instant::now(),
+    })
+}
+
+/// Path of the `index`-th rotated file
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Shift rotated files up by one and move the active file to `.1`
+fn rotate_files(path: &Path, max_files: usize) -> Result<()> {
+    if max_files == 0 {
+        debug!("Discarding audit file {}", path.display());
+        return remove_if_exists(path);
+    }
+
+    remove_if_exists(&rotated_path(path, max_files))?;
+    for index in (1..max_files).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, index + 1))?;
+        }
+    }
+
+    debug!("Rotating audit file {}", path.display());
+    if path.exists() {
+        fs::rename(path, rotated_path(path, 1))?;
+    }
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CheckType, MonitoringResult, PingCheck, PingCheckType, PingResult};
+    use chrono::Utc;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn make_result() -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "127.0.0.1".to_string(),
+                    successes: 3,
+                    failures: 0,
+                    success_latencies: vec![1.0, 2.0, 3.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn line_len() -> u64 {
+        serde_json::to_vec(&make_result()).unwrap().len() as u64 + 1
+    }
+
+    fn append_result(file: &mut AuditFile) {
+        let mut line = serde_json::to_vec(&make_result()).unwrap();
+        line.push(b'\n');
+        file.append(&line).unwrap();
+    }
+
+    fn audit_config(dir: &TempDir, max_file_size_bytes: u64, max_files: usize) -> AuditConfig {
+        AuditConfig {
+            enabled: true,
+            path: dir
+                .path()
+                .join("nested/results.ndjson")
+                .to_string_lossy()
+                .into_owned(),
+            max_file_size_bytes,
+            rotate_interval_secs: 0,
+            max_files,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_results_written_as_ndjson() {
+        let dir = TempDir::new().unwrap();
+        let log = AuditLog::new(audit_config(&dir, 0, 5));
+        let path = log.config().path.clone();
+
+        let results: Vec<_> = (0..3).map(|_| make_result()).collect();
+        for result in &results {
+            log.write(&EncodedResult::new(result)).unwrap();
+        }
+        log.close().await;
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 3, "one line per result");
+        for (line, expected) in lines.iter().zip(&results) {
+            let parsed: MonitoringResult = serde_json::from_str(line).unwrap();
+            assert_eq!(parsed.id, expected.id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dropped_log_finishes_queued_lines() {
+        let dir = TempDir::new().unwrap();
+        let log = AuditLog::new(audit_config(&dir, 0, 5));
+        let path = log.config().path.clone();
+
+        for _ in 0..100 {
+            log.write(&EncodedResult::new(&make_result())).unwrap();
+        }
+        drop(log);
+
+        let lines = || {
+            fs::read_to_string(&path)
+                .map(|content| content.lines().count())
+                .unwrap_or(0)
+        };
+        for _ in 0..100 {
+            if lines() == 100 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(lines(), 100, "queued lines must survive dropping the log");
+    }
+
+    #[test]
+    fn test_rotates_at_configured_size() {
+        let dir = TempDir::new().unwrap();
+        // Room for exactly two lines per file.
+        let mut file = AuditFile::new(audit_config(&dir, line_len() * 2, 5));
+        let path = PathBuf::from(&file.config.path);
+
+        for _ in 0..5 {
+            append_result(&mut file);
+        }
+
+        let count = |p: &Path| fs::read_to_string(p).unwrap().lines().count();
+        assert_eq!(count(&path), 1, "active file holds the latest result");
+        assert_eq!(count(&rotated_path(&path, 1)), 2);
+        assert_eq!(count(&rotated_path(&path, 2)), 2);
+        assert!(!rotated_path(&path, 3).exists());
+        for p in [&path, &rotated_path(&path, 1), &rotated_path(&path, 2)] {
+            assert!(fs::metadata(p).unwrap().len() <= line_len() * 2);
+        }
+    }
+
+    #[test]
+    fn test_rotation_keeps_at_most_max_files() {
+        let dir = TempDir::new().unwrap();
+        let mut file = AuditFile::new(audit_config(&dir, line_len(), 2));
+        let path = PathBuf::from(&file.config.path);
+
+        for _ in 0..6 {
+            append_result(&mut file);
+        }
+
+        assert!(path.exists());
+        assert!(rotated_path(&path, 1).exists());
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn test_oversized_file_from_previous_run_is_rotated() {
+        let dir = TempDir::new().unwrap();
+        let config = audit_config(&dir, line_len(), 5);
+        let path = PathBuf::from(&config.path);
+
+        append_result(&mut AuditFile::new(config.clone()));
+        append_result(&mut AuditFile::new(config));
+
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        assert!(rotated_path(&path, 1).exists());
+    }
+}
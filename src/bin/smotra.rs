@@ -1,10 +1,10 @@
 //! Main agent daemon binary
 
-use clap::Parser;
-use smotra::{Agent, Claim, Config, Endpoint, Result};
+use clap::{Parser, ValueEnum};
+use smotra::{wait_startup_splay, Agent, Claim, Config, Endpoint, EndpointSync, Result};
 use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Parser)]
 #[command(name = "smotra")]
@@ -27,6 +27,20 @@ struct Cli {
     /// Generate default configuration and exit
     #[arg(long)]
     gen_config: bool,
+
+    /// Manage or run the agent as a Windows service (Windows only)
+    #[arg(long, value_enum)]
+    service: Option<ServiceCommand>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ServiceCommand {
+    /// Register the agent as a Windows service using the given --config and --log-file
+    Install,
+    /// Stop and remove the Windows service
+    Uninstall,
+    /// Run under the service control manager (used by the installed service)
+    Run,
 }
 
 /// Initializes the tracing subscriber.
@@ -115,12 +129,39 @@ async fn ensure_claimed(config: &mut Config, config_path: &Path) -> Result<()> {
     info!("Agent ID: {}", claim_result.agent_id);
 
     config.apply_claim_result(claim_result);
-    config.save_to_file_secure(config_path).await?;
+    if let Some(backup) = config.save_claimed(config_path).await? {
+        info!("Previous configuration backed up to: {}", backup.display());
+    }
     info!("Configuration saved to: {}", config_path.display());
 
     Ok(())
 }
 
+/// Adopts the endpoints managed for this agent on the server.
+///
+/// Runs only when `server.endpoint_sync` is enabled and the agent holds an
+/// API key. A failed fetch is not fatal: the agent keeps monitoring its
+/// local endpoints. Changed endpoints are persisted to `config_path`.
+async fn sync_endpoints(config: &mut Config, config_path: &Path) -> Result<()> {
+    if config.server.endpoint_sync == EndpointSync::Off || config.server.api_key.is_none() {
+        return Ok(());
+    }
+
+    match config.sync_endpoints_from_server().await {
+        Ok(true) => {
+            config.save_to_file_secure(config_path).await?;
+            info!("Server endpoints saved to: {}", config_path.display());
+        }
+        Ok(false) => info!("Endpoints already match the server configuration"),
+        Err(e) => warn!(
+            "Failed to fetch server configuration, keeping local endpoints: {}",
+            e
+        ),
+    }
+
+    Ok(())
+}
+
 /// Validates config, logs a summary, then creates and runs the agent.
 fn print_info(config: &Config) -> Result<()> {
     config.validate().map_err(|e| {
@@ -146,13 +187,36 @@ async fn main() -> Result<()> {
         return generate_config(&cli.config).await;
     }
 
+    match cli.service {
+        Some(ServiceCommand::Install) => {
+            return smotra::service::install(&cli.config, cli.log_file.as_deref());
+        }
+        Some(ServiceCommand::Uninstall) => return smotra::service::uninstall(),
+        Some(ServiceCommand::Run) => {
+            // A service has no console to claim from; claim the agent interactively first
+            info!("Starting the agent as a Windows service");
+            let config = cli.config;
+            return tokio::task::spawn_blocking(move || smotra::service::run(&config)).await?;
+        }
+        None => {}
+    }
+
     // Scoping is only to make sure that config is dropped before we start the agent,
     // since Agent::new() will re-open the config file for reading and writing
     {
         let mut config = load_config(&cli.config)?;
+        // Spread the first server contact of agents booting at once
+        let interrupt = async {
+            let _ = tokio::signal::ctrl_c().await;
+        };
+        if !wait_startup_splay(config.server.startup_splay(), interrupt).await {
+            info!("Interrupted during startup splay");
+            return Ok(());
+        }
         if config.server.is_claim_required() {
             ensure_claimed(&mut config, &cli.config).await?;
         }
+        sync_endpoints(&mut config, &cli.config).await?;
         print_info(&config)?;
     }
 
@@ -30,10 +30,47 @@ pub enum Commands {
     /// Validate configuration
     ValidateConfig,
 
+    /// Warn about suspicious (but valid) configuration settings
+    Lint {
+        /// Exit with an error if any warning is reported
+        #[arg(long)]
+        deny_warnings: bool,
+    },
+
+    /// Run every configuration check for CI: load, validate and lint with
+    /// warnings denied. Prints a key=value summary and exits non-zero per
+    /// failure class (2 load, 3 validation, 4 lint).
+    Ci,
+
+    /// Validate an endpoint and append it to the configuration file
+    AddEndpoint {
+        /// IP address, hostname, or http(s)://, grpc(s)://, tcp://, udp:// or dns:// URL
+        address: String,
+
+        /// Port to check
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// Tag to attach (repeatable)
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// Add the endpoint disabled
+        #[arg(long)]
+        disabled: bool,
+    },
+
     /// Generate default configuration
     GenConfig {
         /// Output file path
         #[arg(short, long, default_value = "config.toml")]
         output: PathBuf,
     },
+
+    /// Print the JSON Schema of the configuration file
+    Schema {
+        /// Write the schema to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 }
@@ -1,7 +1,7 @@
 //! Command handlers for CLI operations
 
-use smotra::{Agent, Config, Result};
-use std::path::PathBuf;
+use smotra::{Agent, Config, Endpoint, Error, LintWarning, Result};
+use std::path::{Path, PathBuf};
 
 /// Show current status
 pub async fn show_status(config_path: PathBuf) -> Result<()> {
@@ -10,10 +10,36 @@ pub async fn show_status(config_path: PathBuf) -> Result<()> {
 
     println!("Agent Status:");
     println!("  Running: {}", status.is_running);
+    if let Some(uptime) = status.uptime() {
+        println!("  Uptime: {}s", uptime.as_secs());
+    }
+    if let Some(restart_count) = status.restart_count {
+        println!("  Restart Count: {}", restart_count);
+    }
     println!("  Checks Performed: {}", status.checks_performed);
     println!("  Checks Successful: {}", status.checks_successful);
     println!("  Checks Failed: {}", status.checks_failed);
+    println!(
+        "  Checks Failed During Warmup: {}",
+        status.checks_failed_warmup
+    );
+    println!(
+        "  Checks Skipped: {} disabled, {} circuit open, {} deadline exceeded",
+        status.checks_skipped.disabled,
+        status.checks_skipped.circuit_open,
+        status.checks_skipped.deadline_exceeded
+    );
+    if let Some(loss) = status.packet_loss_percent() {
+        println!(
+            "  Ping Packet Loss: {:.1}% ({} of {} echo requests)",
+            loss, status.ping_requests_lost, status.ping_requests_sent
+        );
+    }
     println!("  Server Connected: {}", status.server_connected);
+    println!(
+        "  Failed Reports: {} ({} in a row)",
+        status.failed_report_count, status.consecutive_failed_reports
+    );
     println!(
         "  Cached Results: {} / {} (used/capacity)",
         status.cache_stats.len, status.cache_stats.capacity
@@ -41,6 +67,161 @@ pub async fn validate_config(config_path: PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// Lint configuration for suspicious settings
+///
+/// Secret references are recognised but not resolved, so linting runs no
+/// `cmd:` provider and needs no secret to be present.
+pub async fn lint_config(config_path: PathBuf, deny_warnings: bool) -> Result<()> {
+    let config = Config::from_file_unresolved(&config_path).inspect_err(|e| {
+        eprintln!("✗ Failed to load configuration: {}", e);
+    })?;
+
+    let warnings = config.lint();
+    if warnings.is_empty() {
+        println!("✓ No warnings");
+        return Ok(());
+    }
+
+    for warning in &warnings {
+        println!("⚠ {}", warning);
+    }
+    println!("{} warning(s)", warnings.len());
+
+    if deny_warnings {
+        return Err(Error::Config(format!(
+            "{} lint warning(s) with --deny-warnings",
+            warnings.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Result of `ci_check`, one per failure class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiOutcome {
+    /// Loaded, valid and without lint warnings
+    Clean,
+    /// The file could not be read or parsed
+    LoadFailed,
+    /// `Config::validate()` rejected the configuration
+    Invalid,
+    /// The linter reported warnings
+    LintWarnings,
+}
+
+impl CiOutcome {
+    /// Process exit code for this outcome
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Clean => 0,
+            Self::LoadFailed => 2,
+            Self::Invalid => 3,
+            Self::LintWarnings => 4,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Clean => "clean",
+            Self::LoadFailed => "load_failed",
+            Self::Invalid => "invalid",
+            Self::LintWarnings => "lint_warnings",
+        }
+    }
+}
+
+/// Load, validate and lint a configuration for CI
+///
+/// Has no side effects: secret references are not resolved and nothing is
+/// written. Prints one `key=value` line per stage, one line per lint warning
+/// and a final `result=` line; later stages are reported as skipped after a
+/// failure.
+pub fn ci_check(config_path: &Path) -> CiOutcome {
+    ci_check_with(config_path, Config::lint)
+}
+
+/// [`ci_check`] with `lint` run as the lint stage
+fn ci_check_with(config_path: &Path, lint: fn(&Config) -> Vec<LintWarning>) -> CiOutcome {
+    let outcome = ci_stages(config_path, lint);
+    println!(
+        "result={} exit_code={}",
+        outcome.as_str(),
+        outcome.exit_code()
+    );
+    outcome
+}
+
+fn ci_stages(config_path: &Path, lint: fn(&Config) -> Vec<LintWarning>) -> CiOutcome {
+    let config = match Config::from_file_unresolved(config_path) {
+        Ok(config) => {
+            println!("stage=load status=ok");
+            config
+        }
+        Err(e) => {
+            println!("stage=load status=failed error={:?}", e.to_string());
+            println!("stage=validate status=skipped");
+            println!("stage=lint status=skipped");
+            return CiOutcome::LoadFailed;
+        }
+    };
+
+    if let Err(e) = config.validate() {
+        println!("stage=validate status=failed error={:?}", e.to_string());
+        println!("stage=lint status=skipped");
+        return CiOutcome::Invalid;
+    }
+    println!("stage=validate status=ok");
+
+    let warnings = lint(&config);
+    if warnings.is_empty() {
+        println!("stage=lint status=ok warnings=0");
+        return CiOutcome::Clean;
+    }
+
+    println!("stage=lint status=failed warnings={}", warnings.len());
+    for warning in &warnings {
+        println!(
+            "warning category={} field={} message={:?}",
+            warning.category, warning.field, warning.message
+        );
+    }
+    CiOutcome::LintWarnings
+}
+
+/// Validate `endpoint` and append it to the configuration file
+///
+/// The file is loaded without resolving secrets, so a secret reference in
+/// `server.api_key` is written back unchanged.
+pub async fn add_endpoint(config_path: PathBuf, endpoint: Endpoint) -> Result<()> {
+    if let Err(e) = endpoint.validate() {
+        eprintln!("✗ Invalid endpoint: {}", e);
+        return Err(e);
+    }
+
+    let mut config = Config::from_file_unresolved(&config_path)?;
+    if config
+        .endpoints
+        .iter()
+        .any(|e| e.address == endpoint.address)
+    {
+        println!(
+            "Note: an endpoint with address {} is already configured",
+            endpoint.address
+        );
+    }
+    config.endpoints.push(endpoint.clone());
+    config.save_to_file_secure(&config_path).await?;
+
+    println!(
+        "✓ Added endpoint {} ({}) to {}",
+        endpoint.address,
+        endpoint.id,
+        config_path.display()
+    );
+    Ok(())
+}
+
 /// Generate default configuration
 pub async fn generate_config(output: PathBuf) -> Result<()> {
     let config = Config::default();
@@ -48,3 +229,199 @@ pub async fn generate_config(output: PathBuf) -> Result<()> {
     println!("Generated default configuration at: {}", output.display());
     Ok(())
 }
+
+/// Print the configuration JSON Schema, or write it to `output`
+pub async fn export_schema(output: Option<PathBuf>) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&Config::json_schema())?;
+    match output {
+        Some(output) => {
+            tokio::fs::write(&output, schema + "\n").await?;
+            eprintln!("Wrote configuration schema to: {}", output.display());
+        }
+        None => println!("{}", schema),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+    use uuid::Uuid;
+
+    /// Write `config` to a fresh directory that also serves as its cache dir
+    async fn write_config(mut config: Config) -> (TempDir, PathBuf) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        config.storage.cache_dir = env!("CARGO_MANIFEST_DIR").to_string();
+        config.save_to_file_secure(&path).await.unwrap();
+        (dir, path)
+    }
+
+    fn clean_config() -> Config {
+        Config {
+            agent_id: Uuid::now_v7(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn add_endpoint_appends_valid_endpoint() {
+        let (_dir, path) = write_config(clean_config()).await;
+
+        add_endpoint(
+            path.clone(),
+            Endpoint::new("https://api.example.com/health"),
+        )
+        .await
+        .unwrap();
+
+        let saved = Config::from_file(&path).unwrap();
+        assert_eq!(saved.endpoints.len(), 1);
+        assert_eq!(saved.endpoints[0].address, "https://api.example.com/health");
+    }
+
+    #[tokio::test]
+    async fn export_schema_writes_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.schema.json");
+
+        export_schema(Some(path.clone())).await.unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, Config::json_schema());
+    }
+
+    #[tokio::test]
+    async fn add_endpoint_rejects_invalid_endpoint() {
+        let (_dir, path) = write_config(clean_config()).await;
+
+        assert!(add_endpoint(path.clone(), Endpoint::new("")).await.is_err());
+        assert!(Config::from_file(&path).unwrap().endpoints.is_empty());
+    }
+
+    #[tokio::test]
+    async fn ci_clean_config_exits_zero() {
+        let (_dir, path) = write_config(clean_config()).await;
+
+        // The checkout itself may live on a tmpfs, which is a lint warning
+        // about the cache directory, not about the config under test.
+        let outcome = ci_check_with(&path, |config| {
+            config
+                .lint()
+                .into_iter()
+                .filter(|warning| warning.field != "storage.cache_dir")
+                .collect()
+        });
+        assert_eq!(outcome, CiOutcome::Clean);
+        assert_eq!(outcome.exit_code(), 0);
+    }
+
+    #[tokio::test]
+    async fn ci_validation_error_exits_three() {
+        let mut config = clean_config();
+        config.monitoring.interval_secs = 0;
+        let (_dir, path) = write_config(config).await;
+
+        let outcome = ci_check(&path);
+        assert_eq!(outcome, CiOutcome::Invalid);
+        assert_eq!(outcome.exit_code(), 3);
+    }
+
+    #[tokio::test]
+    async fn ci_lint_warning_exits_four() {
+        let mut config = clean_config();
+        config.server.verify_tls = false;
+        let (_dir, path) = write_config(config).await;
+
+        let outcome = ci_check(&path);
+        assert_eq!(outcome, CiOutcome::LintWarnings);
+        assert_eq!(outcome.exit_code(), 4);
+    }
+
+    #[test]
+    fn ci_unparseable_config_exits_two() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "not valid toml ][[[").unwrap();
+
+        let outcome = ci_check(&path);
+        assert_eq!(outcome, CiOutcome::LoadFailed);
+        assert_eq!(outcome.exit_code(), 2);
+    }
+
+    #[tokio::test]
+    async fn ci_expands_interpolated_fields() {
+        std::env::remove_var("SMOTRA_TEST_CI_UNSET_INTERVAL");
+        std::env::remove_var("SMOTRA_TEST_CI_UNSET_KEY");
+        let (_dir, path) = write_config(clean_config()).await;
+        let content = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace(
+                "\ninterval_secs = 60",
+                "\ninterval_secs = ${SMOTRA_TEST_CI_UNSET_INTERVAL:-60}",
+            )
+            .replace(
+                "\n[server]\n",
+                "\n[server]\napi_key = \"${SMOTRA_TEST_CI_UNSET_KEY}\"\n",
+            );
+        std::fs::write(&path, content).unwrap();
+
+        let outcome = ci_check(&path);
+        assert!(
+            matches!(outcome, CiOutcome::Clean | CiOutcome::LintWarnings),
+            "{:?}",
+            outcome
+        );
+    }
+
+    #[tokio::test]
+    async fn add_endpoint_keeps_interpolated_fields() {
+        std::env::set_var("SMOTRA_TEST_ADD_ENDPOINT_INTERVAL", "45");
+        let (_dir, path) = write_config(clean_config()).await;
+        let content = std::fs::read_to_string(&path).unwrap().replace(
+            "\ninterval_secs = 60",
+            "\ninterval_secs = ${SMOTRA_TEST_ADD_ENDPOINT_INTERVAL}",
+        );
+        std::fs::write(&path, content).unwrap();
+
+        add_endpoint(
+            path.clone(),
+            Endpoint::new("https://api.example.com/health"),
+        )
+        .await
+        .unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\ninterval_secs = ${SMOTRA_TEST_ADD_ENDPOINT_INTERVAL}\n"));
+        assert_eq!(
+            Config::from_file(&path).unwrap().monitoring.interval_secs,
+            45
+        );
+    }
+
+    #[tokio::test]
+    async fn ci_does_not_run_secret_commands() {
+        let dir = tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let mut config = clean_config();
+        config.server.api_key = Some(format!("cmd:touch {}", marker.display()));
+        let (_config_dir, path) = write_config(config).await;
+
+        ci_check(&path);
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn lint_does_not_run_secret_commands() {
+        let dir = tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let mut config = clean_config();
+        config.server.api_key = Some(format!("cmd:touch {}", marker.display()));
+        let (_config_dir, path) = write_config(config).await;
+
+        lint_config(path, false).await.unwrap();
+        assert!(!marker.exists());
+    }
+}
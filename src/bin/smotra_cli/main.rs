@@ -7,7 +7,7 @@ mod tui;
 
 use clap::Parser;
 use cli_args::{Cli, Commands};
-use smotra::Result;
+use smotra::{Endpoint, Result};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -28,10 +28,38 @@ async fn main() -> Result<()> {
             logging::init_stdout_logging(&cli.log_level);
             commands::validate_config(cli.config).await?
         }
+        Some(Commands::Lint { deny_warnings }) => {
+            logging::init_stdout_logging(&cli.log_level);
+            commands::lint_config(cli.config, deny_warnings).await?
+        }
+        Some(Commands::Ci) => {
+            // No logging setup: stdout carries only the machine-parseable summary.
+            let outcome = commands::ci_check(&cli.config);
+            if outcome != commands::CiOutcome::Clean {
+                std::process::exit(outcome.exit_code());
+            }
+        }
+        Some(Commands::AddEndpoint {
+            address,
+            port,
+            tags,
+            disabled,
+        }) => {
+            logging::init_stdout_logging(&cli.log_level);
+            let mut endpoint = Endpoint::new(address)
+                .with_tags(tags)
+                .with_enabled(!disabled);
+            endpoint.port = port.map(i64::from);
+            commands::add_endpoint(cli.config, endpoint).await?
+        }
         Some(Commands::GenConfig { output }) => {
             logging::init_stdout_logging(&cli.log_level);
             commands::generate_config(output).await?
         }
+        Some(Commands::Schema { output }) => {
+            // No logging setup: stdout may carry the schema itself.
+            commands::export_schema(output).await?
+        }
     }
 
     Ok(())
@@ -2,13 +2,13 @@
 
 use crate::logging::LogEntry;
 use ratatui::{
-    layout::Rect,
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph},
     Frame,
 };
-use smotra::Config;
+use smotra::{Config, EndpointHealth, MetricStatus, MonitoringResult, ReloadStatus};
 use tracing::Level;
 
 pub fn render_header(f: &mut Frame, area: Rect, tabs: &[&str], selected: usize) {
@@ -40,7 +40,13 @@ pub fn render_header(f: &mut Frame, area: Rect, tabs: &[&str], selected: usize)
     f.render_widget(header, area);
 }
 
-pub fn render_status(f: &mut Frame, area: Rect, status: &smotra::AgentStatus, config: &Config) {
+pub fn render_status(
+    f: &mut Frame,
+    area: Rect,
+    status: &smotra::AgentStatus,
+    config: &Config,
+    reload: Option<&ReloadStatus>,
+) {
     use ratatui::layout::{Constraint, Direction, Layout};
 
     let chunks = Layout::default()
@@ -51,6 +57,8 @@ pub fn render_status(f: &mut Frame, area: Rect, status: &smotra::AgentStatus, co
             Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
             Constraint::Min(0),
         ])
         .split(area);
@@ -104,31 +112,178 @@ pub fn render_status(f: &mut Frame, area: Rect, status: &smotra::AgentStatus, co
     .block(Block::default().borders(Borders::ALL).title("Server"));
     f.render_widget(server_widget, chunks[2]);
 
+    // Uptime and restarts
+    let uptime_text = format!(
+        "Uptime: {} | Restarts: {}",
+        status
+            .uptime()
+            .map(format_duration)
+            .unwrap_or_else(|| "-".to_string()),
+        status
+            .restart_count
+            .map(|count| count.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    );
+    let uptime_widget =
+        Paragraph::new(uptime_text).block(Block::default().borders(Borders::ALL).title("Uptime"));
+    f.render_widget(uptime_widget, chunks[3]);
+
     // Statistics
+    // Failures during warmup count toward the total but not the rate.
     let total = status.checks_performed;
-    let success_rate = if total > 0 {
-        (status.checks_successful as f64 / total as f64) * 100.0
+    let rated = status.checks_successful + status.checks_failed;
+    let success_rate = if rated > 0 {
+        (status.checks_successful as f64 / rated as f64) * 100.0
     } else {
         0.0
     };
 
     let stats_text = format!(
-        "Total: {} | Success: {} | Failed: {} | Rate: {:.1}%",
-        total, status.checks_successful, status.checks_failed, success_rate
+        "Total: {} | Success: {} | Failed: {} | Warmup: {} | Skipped: {} | Rate: {:.1}%",
+        total,
+        status.checks_successful,
+        status.checks_failed,
+        status.checks_failed_warmup,
+        status.checks_skipped.disabled
+            + status.checks_skipped.circuit_open
+            + status.checks_skipped.deadline_exceeded,
+        success_rate
     );
     let stats_widget = Paragraph::new(stats_text)
         .block(Block::default().borders(Borders::ALL).title("Statistics"));
-    f.render_widget(stats_widget, chunks[3]);
+    f.render_widget(stats_widget, chunks[4]);
 
     // Success rate gauge
     let gauge = Gauge::default()
         .block(Block::default().borders(Borders::ALL).title("Success Rate"))
         .gauge_style(Style::default().fg(Color::Green))
         .ratio(success_rate / 100.0);
-    f.render_widget(gauge, chunks[4]);
+    f.render_widget(gauge, chunks[5]);
+
+    // Last hot reload
+    let (reload_text, reload_color) = format_reload_status(reload);
+    let reload_widget = Paragraph::new(Line::from(Span::styled(
+        reload_text,
+        Style::default().fg(reload_color),
+    )))
+    .block(Block::default().borders(Borders::ALL).title("Last Reload"));
+    f.render_widget(reload_widget, chunks[6]);
+
+    // Per-endpoint health, failing endpoints first
+    let mut health: Vec<&EndpointHealth> = status.per_endpoint.values().collect();
+    health.sort_by(|a, b| (a.last_successful, &a.address).cmp(&(b.last_successful, &b.address)));
+    let items: Vec<ListItem> = health
+        .into_iter()
+        .map(|health| {
+            let (text, color) = format_endpoint_health(health);
+            ListItem::new(text).style(Style::default().fg(color))
+        })
+        .collect();
+    let health_widget = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Endpoint Health ({})", status.per_endpoint.len())),
+    );
+    f.render_widget(health_widget, chunks[7]);
+}
+
+/// Line and color of one endpoint in the "Endpoint Health" panel: green when
+/// its latest check succeeded, red with the failures in a row otherwise
+fn format_endpoint_health(health: &EndpointHealth) -> (String, Color) {
+    let latency = health
+        .last_latency_ms
+        .map(|ms| format!(" | {:.2} ms", ms))
+        .unwrap_or_default();
+    let at = health.last_checked_at.format("%H:%M:%S");
+
+    if !health.last_successful {
+        return (
+            format!(
+                "FAIL  {}{} | {} in a row | at {}",
+                health.address, latency, health.consecutive_failures, at
+            ),
+            Color::Red,
+        );
+    }
+
+    // Successful checks are still rated against the latency and packet
+    // loss thresholds of the endpoint.
+    let (label, color) = match health.last_status {
+        Some(MetricStatus::Degraded) => ("WARN", Color::Yellow),
+        Some(MetricStatus::Unreachable) => ("CRIT", Color::Red),
+        _ => ("OK", Color::Green),
+    };
+    (
+        format!("{:<6}{}{} | at {}", label, health.address, latency, at),
+        color,
+    )
+}
+
+/// Text and color of the "Last Reload" panel: green when the reload was
+/// applied, red with the error when it was rejected
+fn format_reload_status(reload: Option<&ReloadStatus>) -> (String, Color) {
+    let Some(reload) = reload else {
+        return ("No reload since start".to_string(), Color::Gray);
+    };
+
+    let at = reload.at.format("%Y-%m-%d %H:%M:%S UTC");
+    let version = reload
+        .config_version
+        .map(|version| format!("v{}", version))
+        .unwrap_or_else(|| "-".to_string());
+
+    match &reload.error {
+        None => (
+            format!("{} at {} | {} applied", reload.source, at, version),
+            Color::Green,
+        ),
+        Some(error) => (
+            format!(
+                "{} at {} | {} rejected: {}",
+                reload.source, at, version, error
+            ),
+            Color::Red,
+        ),
+    }
 }
 
-pub fn render_endpoints(f: &mut Frame, area: Rect, config: &Config) {
+/// Format a duration as `[Nd ]HH:MM:SS`
+fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) = (
+        secs / 86_400,
+        secs % 86_400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+
+    if days > 0 {
+        format!("{}d {:02}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    }
+}
+
+/// On-demand check started from the Endpoints tab
+pub enum CheckNow {
+    /// The check of the endpoint at this address is running
+    Running(String),
+    /// The check of the endpoint at this address finished, or could not run
+    Done(String, Result<Box<MonitoringResult>, String>),
+}
+
+pub fn render_endpoints(
+    f: &mut Frame,
+    area: Rect,
+    config: &Config,
+    selected: usize,
+    check_now: Option<&CheckNow>,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .split(area);
+
     let items: Vec<ListItem> = config
         .endpoints
         .iter()
@@ -149,15 +304,55 @@ pub fn render_endpoints(f: &mut Frame, area: Rect, config: &Config) {
                 .borders(Borders::ALL)
                 .title(format!("Endpoints ({})", config.endpoints.len())),
         )
-        .style(Style::default().fg(Color::White));
+        .style(Style::default().fg(Color::White))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(selected));
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    let (check_text, check_color) = format_check_now(check_now);
+    let check_widget = Paragraph::new(Line::from(Span::styled(
+        check_text,
+        Style::default().fg(check_color),
+    )))
+    .block(Block::default().borders(Borders::ALL).title("Check Now"));
+    f.render_widget(check_widget, chunks[1]);
+}
 
-    f.render_widget(list, area);
+/// Text and color of the "Check Now" panel: green when the check succeeded,
+/// red when it failed or could not run
+fn format_check_now(check_now: Option<&CheckNow>) -> (String, Color) {
+    match check_now {
+        None => (
+            "Press c or Enter to check the selected endpoint now".to_string(),
+            Color::Gray,
+        ),
+        Some(CheckNow::Running(address)) => (format!("Checking {}...", address), Color::Yellow),
+        Some(CheckNow::Done(address, Err(error))) => (
+            format!("{}: check could not run: {}", address, error),
+            Color::Red,
+        ),
+        Some(CheckNow::Done(address, Ok(result))) => {
+            let latency = result
+                .response_time_ms()
+                .map(|ms| format!(" in {:.2} ms", ms))
+                .unwrap_or_default();
+            if result.is_successful() {
+                (format!("{}: succeeded{}", address, latency), Color::Green)
+            } else {
+                (format!("{}: failed{}", address, latency), Color::Red)
+            }
+        }
+    }
 }
 
 pub fn render_config(f: &mut Frame, area: Rect, config: &Config, scroll_offset: usize) {
-    // Serialize the full config structure to TOML format
-    let config_str = match toml::to_string_pretty(config) {
-        Ok(s) => s,
+    // Serialize the full config structure to TOML format, without secrets
+    let config_str = match config
+        .redacted()
+        .map(|config| toml::to_string_pretty(&config))
+    {
+        Ok(Ok(s)) => s,
+        Ok(Err(e)) => format!("Error serializing config: {}", e),
         Err(e) => format!("Error serializing config: {}", e),
     };
 
@@ -253,6 +448,8 @@ pub fn render_footer(f: &mut Frame, area: Rect) {
         Span::raw("] Navigate | ["),
         Span::styled("↑↓/j/k", Style::default().fg(Color::Yellow)),
         Span::raw("] Scroll | ["),
+        Span::styled("c", Style::default().fg(Color::Yellow)),
+        Span::raw("] Check now | ["),
         Span::styled("s", Style::default().fg(Color::Yellow)),
         Span::raw("] Start"),
     ]);
@@ -261,3 +458,157 @@ pub fn render_footer(f: &mut Frame, area: Rect) {
 
     f.render_widget(footer, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use smotra::ReloadSource;
+
+    fn reload(source: ReloadSource, version: Option<u32>, error: Option<&str>) -> ReloadStatus {
+        ReloadStatus {
+            source,
+            at: Utc.with_ymd_and_hms(2026, 3, 1, 12, 30, 0).unwrap(),
+            config_version: version,
+            error: error.map(str::to_string),
+        }
+    }
+
+    fn tcp_result(connected: bool) -> MonitoringResult {
+        use smotra::{CheckType, TcpConnectCheck, TcpConnectCheckType, TcpConnectResult};
+        MonitoringResult {
+            id: uuid::Uuid::nil(),
+            agent_id: uuid::Uuid::nil(),
+            endpoint_id: uuid::Uuid::nil(),
+            check_type: CheckType::TcpConnectCheck(TcpConnectCheck {
+                r#type: TcpConnectCheckType::Tcpconnect,
+                result: TcpConnectResult {
+                    connected,
+                    connect_time_ms: connected.then_some(3.5),
+                    error_details: None,
+                    resolved_ip: "10.0.0.1".to_string(),
+                    dns_resolution_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_format_check_now() {
+        let address = "tcp://10.0.0.1:443".to_string();
+        assert_eq!(format_check_now(None).1, Color::Gray);
+        assert_eq!(
+            format_check_now(Some(&CheckNow::Running(address.clone()))),
+            ("Checking tcp://10.0.0.1:443...".to_string(), Color::Yellow)
+        );
+        assert_eq!(
+            format_check_now(Some(&CheckNow::Done(
+                address.clone(),
+                Ok(Box::new(tcp_result(true)))
+            ))),
+            (
+                "tcp://10.0.0.1:443: succeeded in 3.50 ms".to_string(),
+                Color::Green
+            )
+        );
+        assert_eq!(
+            format_check_now(Some(&CheckNow::Done(
+                address.clone(),
+                Ok(Box::new(tcp_result(false)))
+            ))),
+            ("tcp://10.0.0.1:443: failed".to_string(), Color::Red)
+        );
+        assert_eq!(
+            format_check_now(Some(&CheckNow::Done(address, Err("bad".to_string())))).1,
+            Color::Red
+        );
+    }
+
+    #[test]
+    fn test_format_endpoint_health() {
+        let mut health = EndpointHealth {
+            address: "tcp://10.0.0.1:443".to_string(),
+            last_successful: true,
+            last_checked_at: Utc.with_ymd_and_hms(2026, 3, 1, 12, 30, 0).unwrap(),
+            last_latency_ms: Some(3.5),
+            consecutive_failures: 0,
+            last_status: Some(MetricStatus::Reachable),
+        };
+        assert_eq!(
+            format_endpoint_health(&health),
+            (
+                "OK    tcp://10.0.0.1:443 | 3.50 ms | at 12:30:00".to_string(),
+                Color::Green
+            )
+        );
+
+        health.last_status = Some(MetricStatus::Degraded);
+        assert_eq!(
+            format_endpoint_health(&health),
+            (
+                "WARN  tcp://10.0.0.1:443 | 3.50 ms | at 12:30:00".to_string(),
+                Color::Yellow
+            )
+        );
+
+        health.last_status = Some(MetricStatus::Unreachable);
+        assert_eq!(format_endpoint_health(&health).1, Color::Red);
+
+        health.last_successful = false;
+        health.last_latency_ms = None;
+        health.consecutive_failures = 3;
+        assert_eq!(
+            format_endpoint_health(&health),
+            (
+                "FAIL  tcp://10.0.0.1:443 | 3 in a row | at 12:30:00".to_string(),
+                Color::Red
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_reload_status_without_reload() {
+        let (text, color) = format_reload_status(None);
+        assert_eq!(text, "No reload since start");
+        assert_eq!(color, Color::Gray);
+    }
+
+    #[test]
+    fn test_format_applied_reload() {
+        let (text, color) =
+            format_reload_status(Some(&reload(ReloadSource::Signal, Some(5), None)));
+        assert_eq!(text, "SIGHUP at 2026-03-01 12:30:00 UTC | v5 applied");
+        assert_eq!(color, Color::Green);
+    }
+
+    #[test]
+    fn test_format_rejected_reload() {
+        let (text, color) = format_reload_status(Some(&reload(
+            ReloadSource::FileChange,
+            Some(6),
+            Some("Configuration error: monitoring interval must be greater than 0"),
+        )));
+        assert_eq!(
+            text,
+            "file change at 2026-03-01 12:30:00 UTC | v6 rejected: Configuration error: monitoring interval must be greater than 0"
+        );
+        assert_eq!(color, Color::Red);
+    }
+
+    #[test]
+    fn test_format_reload_that_failed_to_load() {
+        let (text, color) = format_reload_status(Some(&reload(
+            ReloadSource::FileChange,
+            None,
+            Some("Configuration error: Failed to parse config"),
+        )));
+        assert!(text.contains("| - rejected: Configuration error: Failed to parse config"));
+        assert_eq!(color, Color::Red);
+    }
+}
@@ -1,7 +1,7 @@
 //! TUI main loop and event handling
 
 use crate::logging::LogEntry;
-use crate::tui::render;
+use crate::tui::render::{self, CheckNow};
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use parking_lot::Mutex;
 use ratatui::{
@@ -29,6 +29,8 @@ pub async fn run_ui_loop(
 ) -> Result<()> {
     let mut selected_tab = 0;
     let mut config_scroll_offset = 0usize;
+    let mut endpoint_selected = 0usize;
+    let check_now: Arc<Mutex<Option<CheckNow>>> = Arc::default();
     let tabs = vec!["Status", "Endpoints", "Configuration", "Logs"];
     // let agent = Arc::new(agent);
 
@@ -36,7 +38,10 @@ pub async fn run_ui_loop(
         // Update data
         let status = agent.status();
         let config = agent.config_clone();
+        let reload = agent.last_reload_status();
         let logs: Vec<LogEntry> = log_entries.lock().iter().cloned().collect();
+        // A reload may have removed endpoints
+        endpoint_selected = endpoint_selected.min(config.endpoints.len().saturating_sub(1));
 
         terminal.draw(|f| {
             let size = f.area();
@@ -56,8 +61,16 @@ pub async fn run_ui_loop(
 
             // Render content based on selected tab
             match selected_tab {
-                TAB_STATUS => render::render_status(f, chunks[1], &status, &config),
-                TAB_ENDPOINTS => render::render_endpoints(f, chunks[1], &config),
+                TAB_STATUS => {
+                    render::render_status(f, chunks[1], &status, &config, reload.as_ref())
+                }
+                TAB_ENDPOINTS => render::render_endpoints(
+                    f,
+                    chunks[1],
+                    &config,
+                    endpoint_selected,
+                    check_now.lock().as_ref(),
+                ),
                 TAB_CONFIG => render::render_config(f, chunks[1], &config, config_scroll_offset),
                 TAB_LOGS => render::render_logs(f, chunks[1], &logs),
                 _ => {}
@@ -87,37 +100,55 @@ pub async fn run_ui_loop(
                     KeyCode::Left | KeyCode::Char('h') => {
                         selected_tab = selected_tab.saturating_sub(1);
                     }
-                    KeyCode::Right | KeyCode::Char('l') => {
-                        if selected_tab < tabs.len() - 1 {
-                            selected_tab += 1;
-                        }
+                    KeyCode::Right | KeyCode::Char('l') if selected_tab < tabs.len() - 1 => {
+                        selected_tab += 1;
                     }
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        if selected_tab == TAB_CONFIG {
-                            // Configuration tab - scroll up
-                            config_scroll_offset = config_scroll_offset.saturating_sub(1);
-                        }
+                    KeyCode::Up | KeyCode::Char('k') if selected_tab == TAB_ENDPOINTS => {
+                        endpoint_selected = endpoint_selected.saturating_sub(1);
                     }
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        if selected_tab == TAB_CONFIG {
-                            // Configuration tab - scroll down
-                            config_scroll_offset = config_scroll_offset.saturating_add(1);
-                        }
+                    KeyCode::Down | KeyCode::Char('j')
+                        if selected_tab == TAB_ENDPOINTS
+                            && endpoint_selected + 1 < config.endpoints.len() =>
+                    {
+                        endpoint_selected += 1;
                     }
-                    KeyCode::PageUp => {
-                        if selected_tab == TAB_CONFIG {
-                            config_scroll_offset = config_scroll_offset.saturating_sub(10);
+                    KeyCode::Char('c') | KeyCode::Enter if selected_tab == TAB_ENDPOINTS => {
+                        // Check the selected endpoint in the background, one at a time
+                        let Some(endpoint) = config.endpoints.get(endpoint_selected).cloned()
+                        else {
+                            continue;
+                        };
+                        if matches!(*check_now.lock(), Some(CheckNow::Running(_))) {
+                            continue;
                         }
+                        *check_now.lock() = Some(CheckNow::Running(endpoint.address.clone()));
+                        let agent = Arc::clone(&agent);
+                        let check_now = Arc::clone(&check_now);
+                        tokio::spawn(async move {
+                            let result = agent
+                                .check_endpoint_now(&endpoint)
+                                .await
+                                .map(Box::new)
+                                .map_err(|e| e.to_string());
+                            *check_now.lock() = Some(CheckNow::Done(endpoint.address, result));
+                        });
                     }
-                    KeyCode::PageDown => {
-                        if selected_tab == TAB_CONFIG {
-                            config_scroll_offset = config_scroll_offset.saturating_add(10);
-                        }
+                    KeyCode::Up | KeyCode::Char('k') if selected_tab == TAB_CONFIG => {
+                        // Configuration tab - scroll up
+                        config_scroll_offset = config_scroll_offset.saturating_sub(1);
                     }
-                    KeyCode::Home => {
-                        if selected_tab == TAB_CONFIG {
-                            config_scroll_offset = 0;
-                        }
+                    KeyCode::Down | KeyCode::Char('j') if selected_tab == TAB_CONFIG => {
+                        // Configuration tab - scroll down
+                        config_scroll_offset = config_scroll_offset.saturating_add(1);
+                    }
+                    KeyCode::PageUp if selected_tab == TAB_CONFIG => {
+                        config_scroll_offset = config_scroll_offset.saturating_sub(10);
+                    }
+                    KeyCode::PageDown if selected_tab == TAB_CONFIG => {
+                        config_scroll_offset = config_scroll_offset.saturating_add(10);
+                    }
+                    KeyCode::Home if selected_tab == TAB_CONFIG => {
+                        config_scroll_offset = 0;
                     }
                     KeyCode::Char('s') if !status.is_running => {
                         // Start agent in background
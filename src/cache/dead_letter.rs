@@ -0,0 +1,217 @@
+//! Dead-letter file for results the server can never accept.
+//!
+//! Some results cannot be delivered however often they are retried, e.g. a
+//! single result larger than `server.max_report_bytes`. Keeping them cached
+//! would block every page behind them, so they are appended to
+//! `{cache_dir}/dead_letter.ndjson` instead, one JSON line per result with
+//! the reason it was set aside:
+//!
+//! ```text
+//! {"reason":"result of 70312 bytes exceeds server.max_report_bytes (65536)","result":{...}}
+//! ```
+//!
+//! With `storage.encrypt = true` each line is instead the hex-encoded entry
+//! sealed by the cache's [`SegmentCodec`], so dead-lettered results are
+//! encrypted at rest like the cache segment.
+//!
+//! The file is created with owner-only permissions and never rotated; it is
+//! meant to stay empty and be inspected when it is not.
+
+use super::SegmentCodec;
+use crate::core::MonitoringResult;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One result set aside with the reason it could not be delivered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterEntry {
+    /// Why the result was not delivered
+    pub reason: String,
+
+    /// The undelivered result
+    pub result: MonitoringResult,
+}
+
+/// Append-only NDJSON file of undeliverable results.
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    path: PathBuf,
+    codec: SegmentCodec,
+}
+
+impl DeadLetter {
+    /// Dead-letter file at `path`, created on the first record. Entries are
+    /// sealed with `codec` when it encrypts.
+    pub fn new(path: impl Into<PathBuf>, codec: SegmentCodec) -> Self {
+        Self {
+            path: path.into(),
+            codec,
+        }
+    }
+
+    /// Path of the dead-letter file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `result` with `reason`.
+    pub fn record(&self, result: &MonitoringResult, reason: &str) -> Result<()> {
+        let mut line = serde_json::to_vec(&DeadLetterEntry {
+            reason: reason.to_string(),
+            result: result.clone(),
+        })?;
+        if self.codec.is_encrypted() {
+            line = hex::encode(self.codec.seal(&line)?).into_bytes();
+        }
+        line.push(b'\n');
+
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&self.path)?.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Every entry recorded so far; a missing file has none.
+    #[cfg(test)]
+    pub(crate) fn entries(&self) -> Result<Vec<DeadLetterEntry>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                if line.starts_with('{') {
+                    return Ok(serde_json::from_str(line)?);
+                }
+                let sealed = hex::decode(line).map_err(|e| {
+                    crate::error::Error::Cache(format!("Invalid dead-letter line: {}", e))
+                })?;
+                Ok(serde_json::from_slice(&self.codec.open(&sealed)?)?)
+            })
+            .collect()
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CheckType, PingCheck, PingCheckType, PingResult};
+    use chrono::Utc;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn make_result() -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "192.0.2.1".to_string(),
+                    successes: 1,
+                    failures: 0,
+                    success_latencies: vec![1.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn records_are_appended() {
+        let dir = TempDir::new().unwrap();
+        let dead_letter = DeadLetter::new(
+            dir.path().join("nested").join("dead_letter.ndjson"),
+            SegmentCodec::plain(),
+        );
+        assert!(dead_letter.entries().unwrap().is_empty());
+
+        let first = make_result();
+        let second = make_result();
+        dead_letter.record(&first, "too large").unwrap();
+        dead_letter.record(&second, "also too large").unwrap();
+
+        let entries = dead_letter.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].result.id, first.id);
+        assert_eq!(entries[0].reason, "too large");
+        assert_eq!(entries[1].result.id, second.id);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dead_letter.path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn encrypted_records_are_sealed() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("dead_letter.ndjson");
+        let dead_letter = DeadLetter::new(&path, SegmentCodec::encrypted("secret"));
+
+        let result = make_result();
+        dead_letter.record(&result, "too large").unwrap();
+        dead_letter.record(&make_result(), "too large").unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(!content.contains("too large"), "{}", content);
+        assert!(!content.contains(&result.id.to_string()), "{}", content);
+
+        let entries = dead_letter.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].result.id, result.id);
+        assert_eq!(entries[0].reason, "too large");
+
+        let err = DeadLetter::new(&path, SegmentCodec::encrypted("wrong"))
+            .entries()
+            .unwrap_err();
+        assert!(err.to_string().contains("Failed to decrypt"), "{}", err);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Write-ahead journal of cached results.
+//!
+//! The shutdown segment (see [`super::SegmentCodec`]) only survives graceful
+//! stops. To keep unsent results across crashes and power loss as well,
+//! every result entering the cache is also appended to
+//! `{cache_dir}/results.ndjson`, one JSON object per line. When the server
+//! acknowledges results their IDs are appended as an ack line:
+//!
+//! ```text
+//! {"id":"0193...","agent_id":"...","check_type":{...},...}
+//! {"acked":["0193..."]}
+//! ```
+//!
+//! `load_pending` replays the file on start: results acknowledged later in
+//! the file, older than `max_cache_age_secs` or beyond the newest
+//! `max_cached_results` are dropped. A line cut short by a crash is skipped.
+//!
+//! Appending keeps writes cheap; the file is compacted (rewritten with only
+//! the pending results) on load, once acknowledged IDs make up half of it,
+//! and once it holds twice `max_cached_results` results. Every line is synced
+//! to disk before the write returns, and a compacted file is synced before
+//! it is renamed into place, followed by its directory. Like the dead-letter
+//! file it is created with owner-only permissions.
+//!
+//! All methods do blocking file IO; async callers run them with
+//! `spawn_blocking`.
+
+use crate::agent_config::StorageConfig;
+use crate::core::MonitoringResult;
+use crate::error::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Acknowledged IDs in the journal before it is compacted, at the least.
+const COMPACT_AFTER_ACKS: usize = 1000;
+
+/// One line of the journal.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Line {
+    /// IDs of results the server acknowledged
+    Acked { acked: Vec<Uuid> },
+    /// A cached result
+    Result(Box<MonitoringResult>),
+}
+
+/// Lines written since the journal was last compacted.
+#[derive(Debug, Default)]
+struct Written {
+    results: usize,
+    acks: usize,
+}
+
+/// Append-only NDJSON journal of the results in the cache.
+#[derive(Debug)]
+pub struct CacheManager {
+    path: PathBuf,
+    max_results: usize,
+    max_age: Duration,
+    written: Mutex<Written>,
+}
+
+impl CacheManager {
+    /// Journal at `path` keeping at most `max_results` results (`0` for no
+    /// limit) no older than `max_age`.
+    pub fn new(path: impl Into<PathBuf>, max_results: usize, max_age: Duration) -> Self {
+        Self {
+            path: path.into(),
+            max_results,
+            max_age,
+            written: Mutex::default(),
+        }
+    }
+
+    /// Journal in `storage.cache_dir` with the storage limits.
+    pub fn from_config(storage: &StorageConfig) -> Self {
+        Self::new(
+            storage.cache_journal_file(),
+            storage.max_cached_results,
+            storage.max_cache_age(),
+        )
+    }
+
+    /// Path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `result` to the journal.
+    pub fn append(&self, result: &MonitoringResult) -> Result<()> {
+        let mut written = self.written.lock();
+        self.write_line(&Line::Result(Box::new(result.clone())))?;
+        written.results += 1;
+        self.compact_if_due(&mut written)
+    }
+
+    /// Record that the results with `ids` were acknowledged and need not be
+    /// restored.
+    pub fn remove_acked(&self, ids: impl IntoIterator<Item = Uuid>) -> Result<()> {
+        let acked: Vec<Uuid> = ids.into_iter().collect();
+        if acked.is_empty() {
+            return Ok(());
+        }
+
+        let mut written = self.written.lock();
+        let count = acked.len();
+        self.write_line(&Line::Acked { acked })?;
+        written.acks += count;
+        self.compact_if_due(&mut written)
+    }
+
+    /// Results in the journal not acknowledged yet, oldest first, and
+    /// compact the journal to them.
+    ///
+    /// Results older than `max_age` and all but the newest `max_results`
+    /// are dropped. A missing journal has no pending results.
+    pub fn load_pending(&self) -> Result<Vec<MonitoringResult>> {
+        let mut written = self.written.lock();
+        let pending = self.read_pending()?;
+        self.rewrite(&pending)?;
+        *written = Written {
+            results: pending.len(),
+            acks: 0,
+        };
+        Ok(pending)
+    }
+
+    /// Pending results of the journal file, without changing it.
+    fn read_pending(&self) -> Result<Vec<MonitoringResult>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut results = Vec::new();
+        let mut acked = HashSet::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(Line::Result(result)) => results.push(*result),
+                Ok(Line::Acked { acked: ids }) => acked.extend(ids),
+                Err(e) => warn!(
+                    "Skipping unreadable line {} of {}: {}",
+                    number + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+
+        let oldest = chrono::Utc::now()
+            - chrono::Duration::from_std(self.max_age).unwrap_or(chrono::TimeDelta::MAX);
+        let mut seen = HashSet::new();
+        results.retain(|result| {
+            !acked.contains(&result.id) && result.timestamp >= oldest && seen.insert(result.id)
+        });
+        if self.max_results > 0 && results.len() > self.max_results {
+            let evicted = results.len() - self.max_results;
+            results.drain(..evicted);
+            debug!(
+                "Journal holds more than {} results, dropped the {} oldest",
+                self.max_results, evicted
+            );
+        }
+        Ok(results)
+    }
+
+    /// Compact the journal once acknowledged IDs or evicted results make up
+    /// a large share of it.
+    fn compact_if_due(&self, written: &mut Written) -> Result<()> {
+        let mostly_acked =
+            written.acks >= COMPACT_AFTER_ACKS && written.acks * 2 >= written.results;
+        let oversized = self.max_results > 0 && written.results > self.max_results * 2;
+        if !(mostly_acked || oversized) {
+            return Ok(());
+        }
+
+        let pending = self.read_pending()?;
+        self.rewrite(&pending)?;
+        debug!(
+            "Compacted {} to {} pending results",
+            self.path.display(),
+            pending.len()
+        );
+        *written = Written {
+            results: pending.len(),
+            acks: 0,
+        };
+        Ok(())
+    }
+
+    /// Replace the journal with `results`, removing it when there are none.
+    fn rewrite(&self, results: &[MonitoringResult]) -> Result<()> {
+        if results.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Ok(()) => sync_parent_dir(&self.path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            };
+        }
+
+        let mut content = Vec::new();
+        for result in results {
+            serde_json::to_writer(&mut content, result)?;
+            content.push(b'\n');
+        }
+        let tmp_path = self.path.with_extension("ndjson.tmp");
+        let mut file = open_private(&tmp_path, false)?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        sync_parent_dir(&self.path)
+    }
+
+    fn write_line(&self, line: &Line) -> Result<()> {
+        let mut bytes = serde_json::to_vec(line)?;
+        bytes.push(b'\n');
+        let mut file = open_private(&self.path, true)?;
+        file.write_all(&bytes)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// Sync the directory holding `path`, so a rename or removal in it survives
+/// power loss. Directories cannot be synced on Windows.
+fn sync_parent_dir(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Open `path` for writing with owner-only permissions, appending to or
+/// truncating an existing file, and create its directory.
+fn open_private(path: &Path, append: bool) -> Result<File> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.create(true);
+    if append {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    Ok(options.open(path)?)
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CheckType, PingCheck, PingCheckType, PingResult};
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    const DAY: Duration = Duration::from_secs(86_400);
+
+    fn make_result(age: chrono::Duration) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "192.0.2.1".to_string(),
+                    successes: 1,
+                    failures: 0,
+                    success_latencies: vec![1.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now() - age,
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn ids(results: &[MonitoringResult]) -> Vec<Uuid> {
+        results.iter().map(|result| result.id).collect()
+    }
+
+    #[test]
+    fn pending_results_survive_a_restart() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("results.ndjson");
+        let results: Vec<_> = (0..3)
+            .map(|_| make_result(chrono::Duration::zero()))
+            .collect();
+
+        let journal = CacheManager::new(&path, 100, DAY);
+        for result in &results {
+            journal.append(result).unwrap();
+        }
+        journal.remove_acked([results[1].id]).unwrap();
+        drop(journal);
+
+        // A new manager stands in for the restarted agent
+        let restarted = CacheManager::new(&path, 100, DAY);
+        let pending = restarted.load_pending().unwrap();
+        assert_eq!(ids(&pending), vec![results[0].id, results[2].id]);
+        assert!(pending[0].is_successful());
+
+        // Loading compacted the journal to the pending results
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(!content.contains("acked"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn results_older_than_max_age_are_dropped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("results.ndjson");
+        let stale = make_result(chrono::Duration::hours(2));
+        let fresh = make_result(chrono::Duration::minutes(5));
+
+        let journal = CacheManager::new(&path, 100, Duration::from_secs(3600));
+        journal.append(&stale).unwrap();
+        journal.append(&fresh).unwrap();
+
+        let pending = CacheManager::new(&path, 100, Duration::from_secs(3600))
+            .load_pending()
+            .unwrap();
+        assert_eq!(ids(&pending), vec![fresh.id]);
+    }
+
+    #[test]
+    fn oldest_results_beyond_max_results_are_evicted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("results.ndjson");
+        let results: Vec<_> = (0..5)
+            .map(|_| make_result(chrono::Duration::zero()))
+            .collect();
+
+        let journal = CacheManager::new(&path, 3, DAY);
+        for result in &results {
+            journal.append(result).unwrap();
+        }
+
+        let pending = journal.load_pending().unwrap();
+        assert_eq!(ids(&pending), ids(&results[2..]));
+    }
+
+    #[test]
+    fn journal_is_compacted_when_it_outgrows_max_results() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("results.ndjson");
+
+        let journal = CacheManager::new(&path, 2, DAY);
+        let results: Vec<_> = (0..5)
+            .map(|_| make_result(chrono::Duration::zero()))
+            .collect();
+        for result in &results {
+            journal.append(result).unwrap();
+        }
+
+        // The fifth append exceeded twice the limit and kept the newest two
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(content.contains(&results[4].id.to_string()));
+    }
+
+    #[test]
+    fn truncated_lines_are_skipped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("results.ndjson");
+        let result = make_result(chrono::Duration::zero());
+
+        let journal = CacheManager::new(&path, 100, DAY);
+        journal.append(&result).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"id\":\"0193").unwrap();
+
+        let pending = journal.load_pending().unwrap();
+        assert_eq!(ids(&pending), vec![result.id]);
+    }
+
+    #[test]
+    fn missing_journal_has_nothing_pending() {
+        let dir = TempDir::new().unwrap();
+        let journal = CacheManager::new(dir.path().join("results.ndjson"), 100, DAY);
+
+        assert!(journal.load_pending().unwrap().is_empty());
+        assert!(!journal.path().exists());
+    }
+}
@@ -1,7 +1,16 @@
 //! In-memory result cache module.
 //!
-//! See [`store::ResultCache`] for full documentation.
+//! See [`store::ResultCache`] for full documentation, [`segment`] for the
+//! on-disk format used across restarts, [`manager`] for the journal keeping
+//! results across crashes and [`dead_letter`] for results the server can
+//! never accept.
 
+mod dead_letter;
+mod manager;
+mod segment;
 mod store;
 
+pub use dead_letter::DeadLetter;
+pub use manager::CacheManager;
+pub use segment::SegmentCodec;
 pub use store::ResultCache;
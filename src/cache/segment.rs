@@ -0,0 +1,327 @@
+//! On-disk cache segment format.
+//!
+//! Cached results that have not reached the server yet are written to a
+//! segment file when the agent stops and restored when it starts again. A
+//! segment is a small header followed by the snappy-compressed JSON array of
+//! results:
+//!
+//! ```text
+//! +--------+---------+-------+---------------------+-----------------------+
+//! | "SMRC" | version | flags | nonce (12 bytes,    | payload               |
+//! |        | (1)     |       |  encrypted only)    |                       |
+//! +--------+---------+-------+---------------------+-----------------------+
+//! ```
+//!
+//! With `storage.encrypt = true` the compressed payload is sealed with
+//! AES-256-GCM. The key is the SHA-256 digest of `storage.encryption_key`,
+//! which accepts the same `env:`/`file:`/`cmd:` references as
+//! `server.api_key`. The header is authenticated together with the payload,
+//! so a tampered header fails decryption like a wrong key does.
+//!
+//! Decoding never guesses: an encrypted segment without a key, or with a key
+//! that does not match, is an error rather than an empty cache.
+
+use crate::agent_config::{SecretSource, StorageConfig};
+use crate::core::MonitoringResult;
+use crate::error::{Error, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Magic bytes identifying a cache segment file.
+const MAGIC: &[u8; 4] = b"SMRC";
+
+/// Current segment format version.
+const VERSION: u8 = 1;
+
+/// Payload is snappy-compressed.
+const FLAG_COMPRESSED: u8 = 0b01;
+
+/// Payload is sealed with AES-256-GCM.
+const FLAG_ENCRYPTED: u8 = 0b10;
+
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Encodes and decodes cache segments, encrypting them when a key is set.
+#[derive(Clone)]
+pub struct SegmentCodec {
+    key: Option<[u8; 32]>,
+}
+
+impl fmt::Debug for SegmentCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SegmentCodec")
+            .field("encrypted", &self.key.is_some())
+            .finish()
+    }
+}
+
+impl SegmentCodec {
+    /// Codec writing compressed, unencrypted segments.
+    pub fn plain() -> Self {
+        Self { key: None }
+    }
+
+    /// Codec encrypting segments with a key derived from `secret`.
+    pub fn encrypted(secret: &str) -> Self {
+        Self {
+            key: Some(Sha256::digest(secret.as_bytes()).into()),
+        }
+    }
+
+    /// Build the codec for the storage configuration.
+    ///
+    /// Resolves `storage.encryption_key` when `storage.encrypt` is set. A
+    /// missing key or a secret source that cannot be read is an error.
+    pub fn from_config(storage: &StorageConfig) -> Result<Self> {
+        if !storage.encrypt {
+            return Ok(Self::plain());
+        }
+
+        let reference = storage.encryption_key.as_deref().ok_or_else(|| {
+            Error::Cache("storage.encrypt is enabled but storage.encryption_key is not set".into())
+        })?;
+        let secret = match SecretSource::parse(reference) {
+            Some(source) => source.resolve().map_err(|e| {
+                Error::Cache(format!("Failed to resolve storage.encryption_key: {}", e))
+            })?,
+            None => reference.to_string(),
+        };
+
+        Ok(Self::encrypted(&secret))
+    }
+
+    /// Whether segments written by this codec are encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Serialize, compress and (if a key is set) encrypt `results`.
+    pub fn encode(&self, results: &[MonitoringResult]) -> Result<Vec<u8>> {
+        self.seal(&serde_json::to_vec(results)?)
+    }
+
+    /// Decrypt (if needed), decompress and deserialize a segment.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Vec<MonitoringResult>> {
+        Ok(serde_json::from_slice(&self.open(bytes)?)?)
+    }
+
+    /// Compress and (if a key is set) encrypt `json` into the segment
+    /// format.
+    pub(crate) fn seal(&self, json: &[u8]) -> Result<Vec<u8>> {
+        let mut payload = snap::raw::Encoder::new()
+            .compress_vec(json)
+            .map_err(|e| Error::Cache(format!("Failed to compress cache segment: {}", e)))?;
+
+        let mut flags = FLAG_COMPRESSED;
+        if self.key.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+
+        let mut out = Vec::with_capacity(HEADER_LEN + NONCE_LEN + payload.len() + 16);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(flags);
+
+        if let Some(key) = self.key.as_ref() {
+            let mut nonce = [0u8; NONCE_LEN];
+            SystemRandom::new()
+                .fill(&mut nonce)
+                .map_err(|_| Error::Cache("Failed to generate a nonce".into()))?;
+            sealing_key(key)
+                .seal_in_place_append_tag(
+                    Nonce::assume_unique_for_key(nonce),
+                    Aad::from(&out[..HEADER_LEN]),
+                    &mut payload,
+                )
+                .map_err(|_| Error::Cache("Failed to encrypt cache segment".into()))?;
+            out.extend_from_slice(&nonce);
+        }
+
+        out.extend_from_slice(&payload);
+        Ok(out)
+    }
+
+    /// Decrypt (if needed) and decompress a segment written by
+    /// [`Self::seal`].
+    pub(crate) fn open(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(Error::Cache("Not a cache segment".into()));
+        }
+        let version = bytes[MAGIC.len()];
+        if version != VERSION {
+            return Err(Error::Cache(format!(
+                "Unsupported cache segment version {}",
+                version
+            )));
+        }
+        let flags = bytes[MAGIC.len() + 1];
+        let (header, rest) = bytes.split_at(HEADER_LEN);
+
+        let compressed = if flags & FLAG_ENCRYPTED != 0 {
+            let key = self.key.as_ref().ok_or_else(|| {
+                Error::Cache(
+                    "Cache segment is encrypted but storage.encryption_key is not set".into(),
+                )
+            })?;
+            if rest.len() < NONCE_LEN {
+                return Err(Error::Cache("Truncated cache segment".into()));
+            }
+            let (nonce, sealed) = rest.split_at(NONCE_LEN);
+            let nonce = Nonce::try_assume_unique_for_key(nonce)
+                .map_err(|_| Error::Cache("Invalid cache segment nonce".into()))?;
+            let mut buf = sealed.to_vec();
+            let len = sealing_key(key)
+                .open_in_place(nonce, Aad::from(header), &mut buf)
+                .map_err(|_| {
+                    Error::Cache(
+                        "Failed to decrypt cache segment: wrong storage.encryption_key or corrupted file"
+                            .into(),
+                    )
+                })?
+                .len();
+            buf.truncate(len);
+            buf
+        } else {
+            rest.to_vec()
+        };
+
+        if flags & FLAG_COMPRESSED != 0 {
+            snap::raw::Decoder::new()
+                .decompress_vec(&compressed)
+                .map_err(|e| Error::Cache(format!("Failed to decompress cache segment: {}", e)))
+        } else {
+            Ok(compressed)
+        }
+    }
+}
+
+fn sealing_key(key: &[u8; 32]) -> LessSafeKey {
+    // A 32-byte key is always valid for AES-256-GCM.
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("AES-256 key length"))
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CheckType, PingCheck, PingCheckType, PingResult};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn make_result() -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "192.0.2.1".to_string(),
+                    successes: 3,
+                    failures: 0,
+                    success_latencies: vec![1.0, 2.0, 3.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn plain_round_trip() {
+        let codec = SegmentCodec::plain();
+        let results = vec![make_result(), make_result()];
+
+        let bytes = codec.encode(&results).unwrap();
+        assert_eq!(bytes[MAGIC.len() + 1], FLAG_COMPRESSED);
+
+        let decoded = codec.decode(&bytes).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].id, results[0].id);
+        assert_eq!(decoded[1].id, results[1].id);
+    }
+
+    #[test]
+    fn encrypted_round_trip() {
+        let codec = SegmentCodec::encrypted("correct horse battery staple");
+        let results = vec![make_result()];
+
+        let bytes = codec.encode(&results).unwrap();
+        assert_eq!(bytes[MAGIC.len() + 1], FLAG_COMPRESSED | FLAG_ENCRYPTED);
+        let plain = serde_json::to_vec(&results).unwrap();
+        assert!(!bytes.windows(16).any(|w| plain.windows(16).any(|p| p == w)));
+
+        let decoded = codec.decode(&bytes).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].id, results[0].id);
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let bytes = SegmentCodec::encrypted("right")
+            .encode(&[make_result()])
+            .unwrap();
+
+        let err = SegmentCodec::encrypted("wrong").decode(&bytes).unwrap_err();
+        assert!(err.to_string().contains("Failed to decrypt"), "{}", err);
+    }
+
+    #[test]
+    fn missing_key_fails() {
+        let bytes = SegmentCodec::encrypted("secret")
+            .encode(&[make_result()])
+            .unwrap();
+
+        let err = SegmentCodec::plain().decode(&bytes).unwrap_err();
+        assert!(err.to_string().contains("encrypted"), "{}", err);
+    }
+
+    #[test]
+    fn tampered_header_fails() {
+        let codec = SegmentCodec::encrypted("secret");
+        let mut bytes = codec.encode(&[make_result()]).unwrap();
+        bytes[MAGIC.len() + 1] &= !FLAG_COMPRESSED;
+
+        assert!(codec.decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_foreign_file() {
+        let err = SegmentCodec::plain().decode(b"{}").unwrap_err();
+        assert!(err.to_string().contains("Not a cache segment"));
+    }
+
+    #[test]
+    fn from_config_requires_key() {
+        let storage = StorageConfig {
+            encrypt: true,
+            ..StorageConfig::default()
+        };
+        assert!(SegmentCodec::from_config(&storage).is_err());
+
+        let storage = StorageConfig {
+            encrypt: true,
+            encryption_key: Some("inline-secret".to_string()),
+            ..StorageConfig::default()
+        };
+        assert!(SegmentCodec::from_config(&storage).unwrap().is_encrypted());
+        assert!(!SegmentCodec::from_config(&StorageConfig::default())
+            .unwrap()
+            .is_encrypted());
+    }
+}
@@ -12,18 +12,61 @@
 //! acknowledges the batch. This guarantees at-least-once delivery: if the server
 //! call fails, the same items will be retried on the next tick.
 //!
+//! # Idempotency
+//!
+//! Every result carries a UUID `id`, and the server treats a result ID it
+//! already stored as a no-op. A send that fails ambiguously (the server
+//! processed the request but the connection dropped before the response
+//! arrived) is therefore safe to retry with the same IDs. To keep retries
+//! small and avoid double counting on the agent side, the cache also tracks
+//! IDs:
+//!
+//! - A result whose ID is already cached, or was acknowledged recently, is
+//!   not cached again.
+//! - The reporter marks results the server acknowledged with `acknowledge`
+//!   even when the rest of their page failed. `unacknowledged` filters them
+//!   out of the retry, and `drain_front` remembers their IDs once they leave
+//!   the cache.
+//!
+//! # Streaming
+//!
+//! `subscribe` returns a receiver of every result newly cached by `push`, so
+//! a streaming reporter can send results as they are produced. Streamed
+//! results stay cached until acknowledged and are then removed with
+//! `drain_acknowledged`; a result the receiver missed stays cached for the
+//! batch reporter.
+//!
+//! # Restarts
+//!
+//! `save_segment` writes the unsent results to a segment file (see
+//! [`SegmentCodec`]) when the agent stops, and `restore_segment` pushes them
+//! back into the cache on the next start. Restored results older than
+//! `max_age` are dropped.
+//!
+//! A cache built `with_journal` also appends every pushed result to a
+//! [`CacheManager`] journal and records drained IDs there, so results survive
+//! a crash as well; `restore_journal` loads the pending ones on start.
+//!
 //! # Memory sizing
 //!
 //! Each `MonitoringResult` is roughly 200–600 bytes in memory. With the default
 //! cap of 10 000 items that is ≤ 6 MB. If you raise the cap significantly
 //! (e.g. to cover weeks of data without a server connection) budget accordingly.
 
+use super::{CacheManager, SegmentCodec};
 use crate::core::MonitoringResult;
-use std::collections::VecDeque;
+use crate::error::Result;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::Mutex;
-use tracing::{debug, trace};
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, info, trace, warn};
+use uuid::Uuid;
+
+/// Number of drained result IDs remembered to reject re-caching.
+const RECENT_ACKS: usize = 10_000;
 
 /// An entry in the cache paired with the wall-clock time it was inserted.
 #[derive(Debug, Clone)]
@@ -34,6 +77,39 @@ struct CacheEntry {
     inserted_at: Instant,
 }
 
+/// Cached entries together with the IDs tracked for idempotency.
+#[derive(Debug, Default)]
+struct Inner {
+    entries: VecDeque<CacheEntry>,
+    /// IDs of all cached entries (in flight until drained).
+    queued: HashSet<Uuid>,
+    /// IDs of cached entries the server already acknowledged.
+    acknowledged: HashSet<Uuid>,
+    /// IDs of recently drained entries, oldest first.
+    recent: VecDeque<Uuid>,
+    recent_set: HashSet<Uuid>,
+}
+
+impl Inner {
+    fn pop_front(&mut self) -> Option<CacheEntry> {
+        let entry = self.entries.pop_front()?;
+        self.queued.remove(&entry.result.id);
+        self.acknowledged.remove(&entry.result.id);
+        Some(entry)
+    }
+
+    fn remember(&mut self, id: Uuid) {
+        if self.recent_set.insert(id) {
+            self.recent.push_back(id);
+        }
+        while self.recent.len() > RECENT_ACKS {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.recent_set.remove(&oldest);
+            }
+        }
+    }
+}
+
 /// Summary statistics for cache introspection.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CacheStats {
@@ -46,11 +122,16 @@ pub struct CacheStats {
 /// Thread-safe in-memory cache for `MonitoringResult` items.
 #[derive(Debug, Clone)]
 pub struct ResultCache {
-    inner: Arc<Mutex<VecDeque<CacheEntry>>>,
+    inner: Arc<Mutex<Inner>>,
     max_size: usize,
     max_age: Duration,
+    pushed: broadcast::Sender<MonitoringResult>,
+    journal: Option<Arc<CacheManager>>,
 }
 
+/// Results a lagging subscriber may fall behind before it misses some
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
 impl ResultCache {
     /// Create a new `ResultCache`.
     ///
@@ -62,25 +143,73 @@ impl ResultCache {
     ///   removed lazily before each new insertion.
     pub fn new(max_size: usize, max_age: Duration) -> Self {
         Self {
-            inner: Arc::new(Mutex::new(VecDeque::with_capacity(
-                max_size.min(4096), // pre-allocate a reasonable chunk
-            ))),
+            inner: Arc::new(Mutex::new(Inner {
+                entries: VecDeque::with_capacity(
+                    max_size.min(4096), // pre-allocate a reasonable chunk
+                ),
+                ..Inner::default()
+            })),
             max_size,
             max_age,
+            pushed: broadcast::channel(SUBSCRIBER_CAPACITY).0,
+            journal: None,
         }
     }
 
+    /// Persist cached results to `journal` as they are pushed and drained.
+    ///
+    /// Journal write errors are logged; the in-memory cache keeps working.
+    pub fn with_journal(mut self, journal: CacheManager) -> Self {
+        self.journal = Some(Arc::new(journal));
+        self
+    }
+
+    /// Receive every result cached from now on.
+    ///
+    /// A receiver more than 1024 results behind misses the oldest ones; they
+    /// stay cached.
+    pub fn subscribe(&self) -> broadcast::Receiver<MonitoringResult> {
+        self.pushed.subscribe()
+    }
+
     /// Push a new result into the cache.
     ///
     /// Before inserting:
-    /// 1. TTL eviction removes all entries older than `max_age`.
-    /// 2. If still at `max_size`, the oldest entry is dropped (FIFO).
+    /// 1. A result whose ID is already cached or was recently acknowledged
+    ///    is ignored.
+    /// 2. TTL eviction removes all entries older than `max_age`.
+    /// 3. If still at `max_size`, the oldest entry is dropped (FIFO).
+    ///
+    /// The result is appended to the journal, if any, on the blocking thread
+    /// pool.
     pub async fn push(&self, result: MonitoringResult) {
+        let Some(journal) = &self.journal else {
+            self.insert(result).await;
+            return;
+        };
+        if let Some(result) = self.insert(result).await {
+            let journal = Arc::clone(journal);
+            let id = result.id;
+            match tokio::task::spawn_blocking(move || journal.append(&result)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!(result_id = %id, "Failed to journal cached result: {}", e),
+                Err(e) => warn!(result_id = %id, "Journal write task failed: {}", e),
+            }
+        }
+    }
+
+    /// Cache `result`, returning a copy of it unless it was ignored.
+    async fn insert(&self, result: MonitoringResult) -> Option<MonitoringResult> {
         let mut inner = self.inner.lock().await;
         let now = Instant::now();
 
+        if inner.queued.contains(&result.id) || inner.recent_set.contains(&result.id) {
+            debug!(result_id = %result.id, "Result already cached or acknowledged, skipping");
+            return None;
+        }
+
         // Lazy TTL eviction: drop entries from the front that are too old.
-        while let Some(front) = inner.front() {
+        while let Some(front) = inner.entries.front() {
             if now.duration_since(front.inserted_at) > self.max_age {
                 inner.pop_front();
             } else {
@@ -89,7 +218,7 @@ impl ResultCache {
         }
 
         // Hard size cap: drop oldest if at capacity.
-        if self.max_size > 0 && inner.len() >= self.max_size {
+        if self.max_size > 0 && inner.entries.len() >= self.max_size {
             inner.pop_front();
             debug!(
                 "Cache at capacity ({}), evicted oldest entry",
@@ -103,10 +232,14 @@ impl ResultCache {
             "Caching monitoring result"
         );
 
-        inner.push_back(CacheEntry {
-            result,
+        inner.queued.insert(result.id);
+        // No subscriber is not an error
+        let _ = self.pushed.send(result.clone());
+        inner.entries.push_back(CacheEntry {
+            result: result.clone(),
             inserted_at: now,
         });
+        Some(result)
     }
 
     /// Return a cloned slice of the first `n` entries without removing them.
@@ -115,38 +248,215 @@ impl ResultCache {
     /// Items are only removed via `drain_front` after the server acknowledges.
     pub async fn peek_batch(&self, n: usize) -> Vec<MonitoringResult> {
         let inner = self.inner.lock().await;
-        inner.iter().take(n).map(|e| e.result.clone()).collect()
+        inner
+            .entries
+            .iter()
+            .take(n)
+            .map(|e| e.result.clone())
+            .collect()
+    }
+
+    /// Mark cached results as acknowledged by the server.
+    ///
+    /// Used when only part of a page was delivered: the acknowledged results
+    /// stay cached (their page is drained as a whole) but are skipped when
+    /// the page is retried.
+    pub async fn acknowledge(&self, ids: impl IntoIterator<Item = Uuid>) {
+        let mut inner = self.inner.lock().await;
+        for id in ids {
+            if inner.queued.contains(&id) {
+                inner.acknowledged.insert(id);
+            }
+        }
+    }
+
+    /// Keep only the `results` the server has not acknowledged yet.
+    pub async fn unacknowledged(&self, results: Vec<MonitoringResult>) -> Vec<MonitoringResult> {
+        let inner = self.inner.lock().await;
+        results
+            .into_iter()
+            .filter(|result| !inner.acknowledged.contains(&result.id))
+            .collect()
     }
 
     /// Remove the first `n` entries from the front of the queue.
     ///
     /// Call this after a successful server transmission to confirm delivery.
-    /// If `n` exceeds the current length, all entries are drained.
+    /// If `n` exceeds the current length, all entries are drained. Their IDs
+    /// are remembered so the same results are not cached again.
     pub async fn drain_front(&self, n: usize) {
         let mut inner = self.inner.lock().await;
-        let to_drain = n.min(inner.len());
+        let to_drain = n.min(inner.entries.len());
+        let mut drained = Vec::with_capacity(to_drain);
         for _ in 0..to_drain {
-            inner.pop_front();
+            if let Some(entry) = inner.pop_front() {
+                inner.remember(entry.result.id);
+                drained.push(entry.result.id);
+            }
         }
         debug!(
             "Drained {} entries from cache, {} remaining",
             to_drain,
-            inner.len()
+            inner.entries.len()
         );
+        drop(inner);
+        self.journal_acked(drained).await;
+    }
+
+    /// Remove acknowledged entries from the front of the queue.
+    ///
+    /// Stops at the first unacknowledged entry, which must still be sent.
+    /// Returns the number of entries removed.
+    pub async fn drain_acknowledged(&self) -> usize {
+        let mut inner = self.inner.lock().await;
+        let mut drained = Vec::new();
+        while let Some(front) = inner.entries.front() {
+            if !inner.acknowledged.contains(&front.result.id) {
+                break;
+            }
+            if let Some(entry) = inner.pop_front() {
+                inner.remember(entry.result.id);
+                drained.push(entry.result.id);
+            }
+        }
+        drop(inner);
+        let count = drained.len();
+        self.journal_acked(drained).await;
+        count
+    }
+
+    /// Record drained results in the journal, if any, on the blocking
+    /// thread pool.
+    async fn journal_acked(&self, ids: Vec<Uuid>) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        let journal = Arc::clone(journal);
+        match tokio::task::spawn_blocking(move || journal.remove_acked(ids)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to journal drained results: {}", e),
+            Err(e) => warn!("Journal write task failed: {}", e),
+        }
     }
 
     /// Return summary statistics for cache introspection.
     pub async fn stats(&self) -> CacheStats {
         CacheStats {
-            len: self.inner.lock().await.len(),
+            len: self.inner.lock().await.entries.len(),
             capacity: self.max_size,
         }
     }
 
+    /// Write all cached results to the segment file at `path`.
+    ///
+    /// The file is replaced atomically. An empty cache removes a stale
+    /// segment instead of writing an empty one. Returns the number of results
+    /// written.
+    pub async fn save_segment(&self, path: &Path, codec: &SegmentCodec) -> Result<usize> {
+        let results: Vec<MonitoringResult> = {
+            let inner = self.inner.lock().await;
+            inner.entries.iter().map(|e| e.result.clone()).collect()
+        };
+
+        if results.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(0);
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("seg.tmp");
+        fs::write(&tmp_path, codec.encode(&results)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp_path, path)?;
+
+        info!(
+            "Saved {} cached results to {}{}",
+            results.len(),
+            path.display(),
+            if codec.is_encrypted() {
+                " (encrypted)"
+            } else {
+                ""
+            }
+        );
+        Ok(results.len())
+    }
+
+    /// Load the segment file at `path` into the cache and remove it.
+    ///
+    /// A missing file restores nothing. A segment that cannot be decoded
+    /// (for example an encrypted one with a wrong key) is an error and is left
+    /// on disk untouched. Returns the number of results restored.
+    pub async fn restore_segment(&self, path: &Path, codec: &SegmentCodec) -> Result<usize> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let results = codec.decode(&bytes)?;
+
+        let oldest = chrono::Utc::now()
+            - chrono::Duration::from_std(self.max_age).unwrap_or(chrono::TimeDelta::MAX);
+        let mut restored = 0;
+        for result in results {
+            if result.timestamp >= oldest {
+                self.push(result).await;
+                restored += 1;
+            }
+        }
+        fs::remove_file(path)?;
+
+        info!(
+            "Restored {} cached results from {}",
+            restored,
+            path.display()
+        );
+        Ok(restored)
+    }
+
+    /// Load the results pending in the journal into the cache.
+    ///
+    /// Without a journal nothing is restored. Returns the number of results
+    /// restored.
+    pub async fn restore_journal(&self) -> Result<usize> {
+        let Some(journal) = &self.journal else {
+            return Ok(0);
+        };
+
+        let pending = {
+            let journal = Arc::clone(journal);
+            tokio::task::spawn_blocking(move || journal.load_pending())
+                .await
+                .map_err(|e| std::io::Error::other(format!("journal load task failed: {}", e)))??
+        };
+
+        let mut restored = 0;
+        for result in pending {
+            if self.insert(result).await.is_some() {
+                restored += 1;
+            }
+        }
+
+        info!(
+            "Restored {} cached results from {}",
+            restored,
+            journal.path().display()
+        );
+        Ok(restored)
+    }
+
     /// Return the current number of entries in the cache.
     #[cfg(test)]
     pub async fn len(&self) -> usize {
-        self.inner.lock().await.len()
+        self.inner.lock().await.entries.len()
     }
 }
 
@@ -175,9 +485,19 @@ mod tests {
                     failures: 0,
                     success_latencies: vec![1.0],
                     error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
                 },
             }),
             timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
         }
     }
 
@@ -375,6 +695,87 @@ mod tests {
         }
     }
 
+    mod idempotency_tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn push_ignores_result_already_cached() {
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let result = make_result(Uuid::now_v7());
+            cache.push(result.clone()).await;
+            cache.push(result).await;
+            assert_eq!(cache.len().await, 1);
+        }
+
+        #[tokio::test]
+        async fn push_ignores_result_already_drained() {
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let result = make_result(Uuid::now_v7());
+            cache.push(result.clone()).await;
+            cache.drain_front(1).await;
+
+            cache.push(result).await;
+            assert_eq!(
+                cache.len().await,
+                0,
+                "acknowledged result must not be re-cached"
+            );
+        }
+
+        #[tokio::test]
+        async fn acknowledged_results_are_skipped_until_drained() {
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let r1 = make_result(Uuid::now_v7());
+            let r2 = make_result(Uuid::now_v7());
+            let id2 = r2.id;
+            cache.push(r1.clone()).await;
+            cache.push(r2).await;
+
+            cache.acknowledge([r1.id]).await;
+            let pending = cache.unacknowledged(cache.peek_batch(2).await).await;
+            assert_eq!(pending.len(), 1);
+            assert_eq!(pending[0].id, id2);
+            assert_eq!(cache.len().await, 2, "acknowledge must not remove");
+
+            cache.drain_front(2).await;
+            cache.push(r1).await;
+            assert_eq!(cache.len().await, 0);
+        }
+
+        #[tokio::test]
+        async fn drain_acknowledged_stops_at_first_pending_result() {
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let results: Vec<_> = (0..3).map(|_| make_result(Uuid::now_v7())).collect();
+            for result in &results {
+                cache.push(result.clone()).await;
+            }
+
+            cache.acknowledge([results[0].id, results[2].id]).await;
+            assert_eq!(cache.drain_acknowledged().await, 1);
+            assert_eq!(cache.peek_batch(1).await[0].id, results[1].id);
+
+            cache.acknowledge([results[1].id]).await;
+            assert_eq!(cache.drain_acknowledged().await, 2);
+            assert_eq!(cache.len().await, 0);
+
+            cache.push(results[2].clone()).await;
+            assert_eq!(cache.len().await, 0, "drained results are not cached again");
+        }
+
+        #[tokio::test]
+        async fn subscribers_receive_newly_cached_results() {
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let mut pushed = cache.subscribe();
+            let result = make_result(Uuid::now_v7());
+
+            cache.push(result.clone()).await;
+            cache.push(result.clone()).await;
+
+            assert_eq!(pushed.recv().await.unwrap().id, result.id);
+            assert!(pushed.try_recv().is_err(), "a duplicate is not published");
+        }
+    }
+
     mod stats_tests {
         use super::*;
 
@@ -446,4 +847,141 @@ mod tests {
             assert!(batch.len() == 50);
         }
     }
+
+    mod segment_tests {
+        use super::*;
+        use tempfile::TempDir;
+
+        #[tokio::test]
+        async fn save_and_restore_encrypted() {
+            let dir = TempDir::new().unwrap();
+            let path = dir.path().join("results.seg");
+            let codec = SegmentCodec::encrypted("secret");
+
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let first = make_result(Uuid::now_v7());
+            cache.push(first.clone()).await;
+            cache.push(make_result(Uuid::now_v7())).await;
+            assert_eq!(cache.save_segment(&path, &codec).await.unwrap(), 2);
+
+            let restored = ResultCache::new(100, Duration::from_secs(3600));
+            assert_eq!(restored.restore_segment(&path, &codec).await.unwrap(), 2);
+            assert_eq!(restored.peek_batch(1).await[0].id, first.id);
+            assert!(!path.exists());
+        }
+
+        #[tokio::test]
+        async fn restore_with_wrong_key_keeps_segment() {
+            let dir = TempDir::new().unwrap();
+            let path = dir.path().join("results.seg");
+
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            cache.push(make_result(Uuid::now_v7())).await;
+            cache
+                .save_segment(&path, &SegmentCodec::encrypted("right"))
+                .await
+                .unwrap();
+
+            let restored = ResultCache::new(100, Duration::from_secs(3600));
+            assert!(restored
+                .restore_segment(&path, &SegmentCodec::encrypted("wrong"))
+                .await
+                .is_err());
+            assert!(restored
+                .restore_segment(&path, &SegmentCodec::plain())
+                .await
+                .is_err());
+            assert_eq!(restored.len().await, 0);
+            assert!(path.exists());
+        }
+
+        #[tokio::test]
+        async fn restore_missing_segment_is_empty() {
+            let dir = TempDir::new().unwrap();
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let restored = cache
+                .restore_segment(&dir.path().join("results.seg"), &SegmentCodec::plain())
+                .await
+                .unwrap();
+            assert_eq!(restored, 0);
+        }
+
+        #[tokio::test]
+        async fn save_empty_cache_removes_segment() {
+            let dir = TempDir::new().unwrap();
+            let path = dir.path().join("results.seg");
+            std::fs::write(&path, b"stale").unwrap();
+
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            assert_eq!(
+                cache
+                    .save_segment(&path, &SegmentCodec::plain())
+                    .await
+                    .unwrap(),
+                0
+            );
+            assert!(!path.exists());
+        }
+
+        #[tokio::test]
+        async fn restore_drops_stale_results() {
+            let dir = TempDir::new().unwrap();
+            let path = dir.path().join("results.seg");
+
+            let mut stale = make_result(Uuid::now_v7());
+            stale.timestamp = Utc::now() - chrono::Duration::hours(2);
+            std::fs::write(
+                &path,
+                SegmentCodec::plain()
+                    .encode(&[stale, make_result(Uuid::now_v7())])
+                    .unwrap(),
+            )
+            .unwrap();
+
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            let restored = cache
+                .restore_segment(&path, &SegmentCodec::plain())
+                .await
+                .unwrap();
+            assert_eq!(restored, 1);
+        }
+    }
+
+    mod journal_tests {
+        use super::*;
+        use tempfile::TempDir;
+
+        fn journaled_cache(dir: &TempDir) -> ResultCache {
+            ResultCache::new(100, Duration::from_secs(3600)).with_journal(CacheManager::new(
+                dir.path().join("results.ndjson"),
+                100,
+                Duration::from_secs(3600),
+            ))
+        }
+
+        #[tokio::test]
+        async fn undrained_results_are_restored_after_a_crash() {
+            let dir = TempDir::new().unwrap();
+            let cache = journaled_cache(&dir);
+            let first = make_result(Uuid::now_v7());
+            let second = make_result(Uuid::now_v7());
+            cache.push(first).await;
+            cache.push(second.clone()).await;
+            cache.drain_front(1).await;
+            // Dropped without saving a segment, as in a crash
+            drop(cache);
+
+            let restarted = journaled_cache(&dir);
+            assert_eq!(restarted.restore_journal().await.unwrap(), 1);
+            let batch = restarted.peek_batch(10).await;
+            assert_eq!(batch.len(), 1);
+            assert_eq!(batch[0].id, second.id);
+        }
+
+        #[tokio::test]
+        async fn restore_without_journal_is_empty() {
+            let cache = ResultCache::new(100, Duration::from_secs(3600));
+            assert_eq!(cache.restore_journal().await.unwrap(), 0);
+        }
+    }
 }
@@ -10,7 +10,8 @@ use tracing::{error, info};
 /// Poll for claim status
 ///
 /// Polls the server periodically to check if the agent has been claimed.
-/// Returns the API key when the agent is successfully claimed.
+/// Returns the claimed status, carrying the API key and configuration URL,
+/// when the agent is successfully claimed.
 ///
 /// # Arguments
 ///
@@ -19,10 +20,13 @@ use tracing::{error, info};
 ///
 /// # Returns
 ///
-/// * `Ok(Some(api_key))` - Agent was claimed, API key received
+/// * `Ok(Some(claimed))` - Agent was claimed, API key received
 /// * `Ok(None)` - Claim expired or not found
 /// * `Err(...)` - Network or other error
-pub async fn poll_claim_status(client: &Client, poll_url: &str) -> Result<Option<String>> {
+pub async fn poll_claim_status(
+    client: &Client,
+    poll_url: &str,
+) -> Result<Option<openapi::ClaimStatusClaimed>> {
     info!("Starting claim status polling");
     info!("Poll URL: {}", poll_url);
 
@@ -63,7 +67,7 @@ pub async fn poll_claim_status(client: &Client, poll_url: &str) -> Result<Option
                     "Agent claimed successfully (status: {:?}) !",
                     claimed.status
                 );
-                return Ok(Some(claimed.api_key));
+                return Ok(Some(claimed));
             }
         }
     }
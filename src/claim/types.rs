@@ -40,6 +40,9 @@ pub struct AgentCredentials {
 
     /// Agent ID (newly generated or existing)
     pub agent_id: Uuid,
+
+    /// Path (relative to the server URL) or URL of the agent configuration
+    pub config_url: Option<String>,
 }
 
 #[cfg(test)]
@@ -54,6 +57,7 @@ mod tests {
         let result = AgentCredentials {
             api_key: api_key.clone(),
             agent_id,
+            config_url: None,
         };
 
         assert_eq!(result.api_key, api_key);
@@ -66,6 +70,7 @@ mod tests {
         let result = AgentCredentials {
             api_key: "test_key".to_string(),
             agent_id,
+            config_url: None,
         };
 
         let cloned = result.clone();
@@ -64,10 +64,7 @@ impl<'a> Claim<'a> {
 
         info!("Claim token generated (hash will be sent to server)");
 
-        // Get hostname
-        let hostname = hostname::get()
-            .map(|h| h.to_string_lossy().to_string())
-            .unwrap_or_else(|_| "unknown".to_string());
+        let hostname = crate::core::agent_hostname(self.config);
 
         // Collect non-loopback, non-link-local interfaces.
         // The recommended flag is set to the source IP the OS routing table
@@ -121,17 +118,21 @@ impl<'a> Claim<'a> {
         info!("Waiting for agent to be claimed... (press Ctrl+C to cancel)");
 
         // Poll for claim status
-        let api_key = poll_claim_status(
+        let claimed = poll_claim_status(
             &client,
             format!("{}{}", server_url, registration_response.poll_url).as_str(),
         )
         .await?;
 
-        match api_key {
-            Some(api_key) => {
+        match claimed {
+            Some(claimed) => {
                 info!("API key received");
 
-                Ok(AgentCredentials { api_key, agent_id })
+                Ok(AgentCredentials {
+                    api_key: claimed.api_key,
+                    agent_id,
+                    config_url: Some(claimed.config_url),
+                })
             }
             None => {
                 error!("Claim expired or cancelled");
@@ -0,0 +1,327 @@
+//! Control socket commands
+//!
+//! Commands are single text lines of whitespace-separated words:
+//!
+//! ```text
+//! endpoint disable <address> [--persist]
+//! endpoint enable <address> [--persist]
+//! reporting pause
+//! reporting resume
+//! reporting status
+//! ready
+//! ```
+//!
+//! Endpoint commands flip the `enabled` flag of every configured endpoint
+//! with that address in the shared in-memory config. The check loop reads a
+//! fresh config snapshot on every tick, so the change applies from the next
+//! tick on. Without `--persist` the change is lost on restart or on the next
+//! config reload; with it the current in-memory config is written back to the
+//! config file.
+//!
+//! Reporting commands hold back or release the results sent by the result
+//! reporter. Checks keep running and their results stay cached while
+//! reporting is paused; resuming flushes the backlog. The pause is not
+//! persisted and ends with a restart.
+//!
+//! `ready` reports whether the agent has finished starting, or which startup
+//! steps it is still waiting for.
+
+use crate::agent_config::Config;
+use crate::core::{Readiness, ReportingSwitch};
+use crate::error::{Error, Result};
+use parking_lot::RwLock;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+/// Agent state commands operate on
+#[derive(Clone)]
+pub(crate) struct ControlContext {
+    pub(crate) config: Arc<RwLock<Config>>,
+    pub(crate) config_path: PathBuf,
+    pub(crate) reporting: ReportingSwitch,
+    pub(crate) readiness: Readiness,
+}
+
+/// A parsed control command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// Enable or disable checks of the endpoints with `address`
+    SetEndpointEnabled {
+        address: String,
+        enabled: bool,
+        persist: bool,
+    },
+
+    /// Pause (`false`) or resume (`true`) sending results
+    SetReporting { enabled: bool },
+
+    /// Report whether result reporting is paused
+    ReportingStatus,
+
+    /// Report whether startup has completed
+    ReadinessStatus,
+}
+
+impl Command {
+    /// Parse one command line
+    pub(crate) fn parse(line: &str) -> Result<Self> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["endpoint", action @ ("enable" | "disable"), address, flags @ ..] => {
+                let persist = match flags {
+                    [] => false,
+                    ["--persist"] => true,
+                    _ => {
+                        return Err(Error::Config(format!(
+                            "Unexpected arguments: {}",
+                            flags.join(" ")
+                        )))
+                    }
+                };
+                Ok(Self::SetEndpointEnabled {
+                    address: address.to_string(),
+                    enabled: *action == "enable",
+                    persist,
+                })
+            }
+            ["endpoint", ..] => Err(Error::Config(
+                "Usage: endpoint <enable|disable> <address> [--persist]".to_string(),
+            )),
+            ["reporting", "pause"] => Ok(Self::SetReporting { enabled: false }),
+            ["reporting", "resume"] => Ok(Self::SetReporting { enabled: true }),
+            ["reporting", "status"] => Ok(Self::ReportingStatus),
+            ["reporting", ..] => Err(Error::Config(
+                "Usage: reporting <pause|resume|status>".to_string(),
+            )),
+            ["ready"] => Ok(Self::ReadinessStatus),
+            [] => Err(Error::Config("Empty command".to_string())),
+            [other, ..] => Err(Error::Config(format!("Unknown command: {}", other))),
+        }
+    }
+
+    /// Run the command and return the reply message
+    pub(crate) async fn execute(self, ctx: &ControlContext) -> Result<String> {
+        match self {
+            Self::SetEndpointEnabled {
+                address,
+                enabled,
+                persist,
+            } => {
+                let matched = {
+                    let mut config = ctx.config.write();
+                    let mut matched = 0;
+                    for endpoint in config.endpoints.iter_mut().filter(|e| e.address == address) {
+                        endpoint.enabled = enabled;
+                        matched += 1;
+                    }
+                    matched
+                };
+
+                if matched == 0 {
+                    return Err(Error::Config(format!(
+                        "No endpoint with address {}",
+                        address
+                    )));
+                }
+
+                let state = if enabled { "enabled" } else { "disabled" };
+                info!(
+                    "Endpoint {} {} via control socket ({} matched)",
+                    address, state, matched
+                );
+
+                if persist {
+                    let snapshot = ctx.config.read().clone();
+                    snapshot.save_to_file_secure(&ctx.config_path).await?;
+                    info!("Configuration saved to {}", ctx.config_path.display());
+                }
+
+                Ok(format!(
+                    "endpoint {} {}{}",
+                    address,
+                    state,
+                    if persist { " (persisted)" } else { "" }
+                ))
+            }
+            Self::SetReporting { enabled } => {
+                let changed = if enabled {
+                    ctx.reporting.resume()
+                } else {
+                    ctx.reporting.pause()
+                };
+                let state = if enabled { "resumed" } else { "paused" };
+                if changed {
+                    info!("Result reporting {} via control socket", state);
+                }
+
+                Ok(format!(
+                    "reporting {}{}",
+                    state,
+                    if changed { "" } else { " (unchanged)" }
+                ))
+            }
+            Self::ReportingStatus => Ok(format!(
+                "reporting {}",
+                if ctx.reporting.is_paused() {
+                    "paused"
+                } else {
+                    "active"
+                }
+            )),
+            Self::ReadinessStatus => {
+                let pending = ctx.readiness.pending();
+                if pending.is_empty() {
+                    return Ok("ready".to_string());
+                }
+                let pending: Vec<String> = pending.iter().map(ToString::to_string).collect();
+                Ok(format!("not ready, waiting for {}", pending.join(", ")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Endpoint;
+    use tempfile::NamedTempFile;
+
+    fn context(endpoints: Vec<Endpoint>, config_path: PathBuf) -> ControlContext {
+        ControlContext {
+            config: Arc::new(RwLock::new(Config {
+                endpoints,
+                ..Default::default()
+            })),
+            config_path,
+            reporting: ReportingSwitch::default(),
+            readiness: Readiness::default(),
+        }
+    }
+
+    #[test]
+    fn test_parse_endpoint_commands() {
+        assert_eq!(
+            Command::parse("endpoint disable 10.0.0.1").unwrap(),
+            Command::SetEndpointEnabled {
+                address: "10.0.0.1".to_string(),
+                enabled: false,
+                persist: false,
+            }
+        );
+        assert_eq!(
+            Command::parse("  endpoint enable https://example.com/health --persist ").unwrap(),
+            Command::SetEndpointEnabled {
+                address: "https://example.com/health".to_string(),
+                enabled: true,
+                persist: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_commands() {
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("reboot").is_err());
+        assert!(Command::parse("endpoint disable").is_err());
+        assert!(Command::parse("endpoint pause 10.0.0.1").is_err());
+        assert!(Command::parse("endpoint disable 10.0.0.1 --force").is_err());
+        assert!(Command::parse("reporting").is_err());
+        assert!(Command::parse("reporting stop").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reporting_pause_and_resume() {
+        let file = NamedTempFile::new().unwrap();
+        let ctx = context(Vec::new(), file.path().to_path_buf());
+
+        let run = |line: &'static str| {
+            let ctx = ctx.clone();
+            async move { Command::parse(line).unwrap().execute(&ctx).await.unwrap() }
+        };
+
+        assert_eq!(run("reporting status").await, "reporting active");
+        assert_eq!(run("reporting pause").await, "reporting paused");
+        assert!(ctx.reporting.is_paused());
+        assert_eq!(run("reporting pause").await, "reporting paused (unchanged)");
+        assert_eq!(run("reporting status").await, "reporting paused");
+        assert_eq!(run("reporting resume").await, "reporting resumed");
+        assert!(!ctx.reporting.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_ready_lists_pending_steps() {
+        use crate::core::ReadinessStep;
+
+        let file = NamedTempFile::new().unwrap();
+        let ctx = context(Vec::new(), file.path().to_path_buf());
+        let ready = || async {
+            Command::parse("ready")
+                .unwrap()
+                .execute(&ctx)
+                .await
+                .unwrap()
+        };
+
+        ctx.readiness
+            .complete(ReadinessStep::ConfigLoaded, "config version 1 loaded");
+        assert_eq!(ready().await, "not ready, waiting for check cycle, server");
+
+        ctx.readiness
+            .complete(ReadinessStep::CheckCycle, "first check cycle scheduled");
+        ctx.readiness
+            .complete(ReadinessStep::ServerReachable, "no server configured");
+        assert_eq!(ready().await, "ready");
+    }
+
+    #[tokio::test]
+    async fn test_disable_flips_in_memory_flag_only() {
+        let file = NamedTempFile::new().unwrap();
+        let ctx = context(
+            vec![Endpoint::new("10.0.0.1"), Endpoint::new("10.0.0.2")],
+            file.path().to_path_buf(),
+        );
+
+        Command::parse("endpoint disable 10.0.0.1")
+            .unwrap()
+            .execute(&ctx)
+            .await
+            .unwrap();
+
+        let config = ctx.config.read();
+        assert!(!config.endpoints[0].enabled);
+        assert!(config.endpoints[1].enabled);
+        assert!(std::fs::read_to_string(file.path()).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_persist_writes_config_file() {
+        let file = NamedTempFile::new().unwrap();
+        let ctx = context(vec![Endpoint::new("10.0.0.1")], file.path().to_path_buf());
+
+        Command::parse("endpoint disable 10.0.0.1 --persist")
+            .unwrap()
+            .execute(&ctx)
+            .await
+            .unwrap();
+
+        let saved = Config::from_file(file.path()).unwrap();
+        assert!(!saved.endpoints[0].enabled);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_address_fails() {
+        let file = NamedTempFile::new().unwrap();
+        let ctx = context(vec![Endpoint::new("10.0.0.1")], file.path().to_path_buf());
+
+        let err = Command::parse("endpoint enable 10.9.9.9")
+            .unwrap()
+            .execute(&ctx)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("No endpoint with address 10.9.9.9"));
+    }
+}
@@ -0,0 +1,8 @@
+//! Local control socket for runtime operator commands.
+//!
+//! See [`server::run_control_socket`] for full documentation.
+
+mod command;
+mod server;
+
+pub use server::run_control_socket;
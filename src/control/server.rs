@@ -0,0 +1,417 @@
+//! Control socket server
+//!
+//! Listens on a Unix domain socket (`[control] socket_path`) for operator
+//! commands such as `endpoint disable <address>`, `reporting pause` or `ready`. Each connection may send
+//! any number of command lines; every line is answered with one line,
+//! `ok <message>` or `error <message>`. The socket is created with owner-only
+//! permissions and removed on shutdown. See [`super::command`] for the
+//! available commands.
+//!
+//! The socket is only available on Unix; on other platforms the task logs a
+//! warning and exits.
+
+use super::command::{Command, ControlContext};
+use crate::agent_config::Config;
+use crate::core::{Readiness, ReportingSwitch, ShutdownReceiver};
+use crate::error::Result;
+use parking_lot::RwLock;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+/// Run the control socket until shutdown
+///
+/// Returns immediately when `[control] enabled` is false. Enabling or moving
+/// the socket requires a restart; changes from hot reload are ignored.
+pub async fn run_control_socket(
+    config: Arc<RwLock<Config>>,
+    config_path: PathBuf,
+    reporting: ReportingSwitch,
+    readiness: Readiness,
+    shutdown_rx: ShutdownReceiver,
+) -> Result<()> {
+    let control = config.read().control.clone();
+    if !control.enabled {
+        info!("Control socket disabled");
+        return Ok(());
+    }
+
+    let ctx = ControlContext {
+        config,
+        config_path,
+        reporting,
+        readiness,
+    };
+    serve(PathBuf::from(control.socket_path), ctx, shutdown_rx).await
+}
+
+#[cfg(unix)]
+async fn serve(
+    socket_path: PathBuf,
+    ctx: ControlContext,
+    mut shutdown_rx: ShutdownReceiver,
+) -> Result<()> {
+    use crate::core::wait_for_shutdown;
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::UnixListener;
+    use tracing::warn;
+
+    if let Some(parent) = socket_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+    }
+    // A socket left behind by a previous run would make bind() fail.
+    if tokio::fs::symlink_metadata(&socket_path).await.is_ok() {
+        tokio::fs::remove_file(&socket_path).await?;
+    }
+
+    let listener = UnixListener::bind(&socket_path)?;
+    tokio::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600)).await?;
+    info!("Control socket listening on {}", socket_path.display());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let ctx = ctx.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, ctx).await {
+                            warn!("Control connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept control connection: {}", e),
+            },
+            _ = wait_for_shutdown(&mut shutdown_rx) => {
+                info!("Control socket shutting down");
+                break;
+            }
+        }
+    }
+
+    if let Err(e) = tokio::fs::remove_file(&socket_path).await {
+        warn!(
+            "Failed to remove control socket {}: {}",
+            socket_path.display(),
+            e
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn serve(
+    _socket_path: PathBuf,
+    _ctx: ControlContext,
+    _shutdown_rx: ShutdownReceiver,
+) -> Result<()> {
+    tracing::warn!("Control socket is only supported on Unix platforms");
+    Ok(())
+}
+
+/// Answer every command line sent over `stream`
+#[cfg(unix)]
+async fn handle_connection(stream: tokio::net::UnixStream, ctx: ControlContext) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tracing::debug;
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        debug!("Control command: {}", line);
+        let reply = match Command::parse(&line) {
+            Ok(command) => command.execute(&ctx).await,
+            Err(e) => Err(e),
+        };
+        let reply = match reply {
+            Ok(message) => format!("ok {}\n", message),
+            Err(e) => format!("error {}\n", e),
+        };
+        writer.write_all(reply.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::cache::ResultCache;
+    use crate::core::{shutdown_channel, trigger_shutdown, AgentStatus, Endpoint};
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    use uuid::Uuid;
+
+    /// Send one command line and return the reply line
+    async fn send(socket_path: &std::path::Path, command: &str) -> String {
+        let stream = UnixStream::connect(socket_path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        writer
+            .write_all(format!("{}\n", command).as_bytes())
+            .await
+            .unwrap();
+        let mut reply = String::new();
+        BufReader::new(reader).read_line(&mut reply).await.unwrap();
+        reply.trim_end().to_string()
+    }
+
+    async fn wait_for_socket(socket_path: &std::path::Path) {
+        for _ in 0..100 {
+            if UnixStream::connect(socket_path).await.is_ok() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("control socket did not come up");
+    }
+
+    fn control_config(dir: &TempDir, endpoints: Vec<Endpoint>) -> Config {
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            endpoints,
+            ..Default::default()
+        };
+        config.control.enabled = true;
+        config.control.socket_path = dir.path().join("control.sock").display().to_string();
+        config
+    }
+
+    #[tokio::test]
+    async fn test_disabled_endpoint_stops_producing_results() {
+        // Nothing listens on this port, so every check fails quickly but
+        // still produces a result.
+        let closed_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let address = format!("http://127.0.0.1:{}/health", closed_port);
+
+        let dir = TempDir::new().unwrap();
+        let mut config = control_config(&dir, vec![Endpoint::new(&address)]);
+        config.monitoring.interval_secs = 1;
+        let socket_path = PathBuf::from(&config.control.socket_path);
+        let config = Arc::new(RwLock::new(config));
+
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let control_handle = tokio::spawn(run_control_socket(
+            Arc::clone(&config),
+            dir.path().join("config.toml"),
+            ReportingSwitch::default(),
+            Readiness::default(),
+            shutdown_rx.clone(),
+        ));
+        let monitor_handle = {
+            let config = Arc::clone(&config);
+            let cache = Arc::clone(&cache);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                crate::monitor::run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::default(),
+                    Readiness::default(),
+                    Default::default(),
+                    tokio::sync::watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        wait_for_socket(&socket_path).await;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while cache.stats().await.len == 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("endpoint produced no results");
+
+        let reply = send(&socket_path, &format!("endpoint disable {}", address)).await;
+        assert_eq!(reply, format!("ok endpoint {} disabled", address));
+        assert!(!config.read().endpoints[0].enabled);
+
+        // Let a check that was already running finish, then make sure no
+        // further ticks produce results.
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let produced = cache.stats().await.len;
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(cache.stats().await.len, produced);
+
+        trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+        control_handle.await.unwrap().unwrap();
+        assert!(!socket_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_paused_reporting_caches_then_flushes_on_resume() {
+        // Checks against a closed port fail quickly but still produce results.
+        let closed_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let dir = TempDir::new().unwrap();
+        let mut config = control_config(
+            &dir,
+            vec![Endpoint::new(format!(
+                "http://127.0.0.1:{}/health",
+                closed_port
+            ))],
+        );
+        config.monitoring.interval_secs = 1;
+        config.storage.cache_report_interval_secs = 1;
+
+        let mut server = mockito::Server::new_async().await;
+        let results_mock = server
+            .mock(
+                "POST",
+                format!("/agent/{}/results", config.agent_id).as_str(),
+            )
+            .with_status(202)
+            .with_body(
+                r#"{"submission_id":"00000000-0000-0000-0000-000000000001","accepted":1,"received_at":"2026-01-01T00:00:00Z"}"#,
+            )
+            .expect_at_least(1)
+            .create_async()
+            .await;
+        config.server.url = server.url();
+        config.server.api_key = Some("test-api-key".to_string());
+
+        let socket_path = PathBuf::from(&config.control.socket_path);
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let reporting = ReportingSwitch::default();
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let control_handle = tokio::spawn(run_control_socket(
+            Arc::clone(&config),
+            dir.path().join("config.toml"),
+            reporting.clone(),
+            Readiness::default(),
+            shutdown_rx.clone(),
+        ));
+        wait_for_socket(&socket_path).await;
+        assert_eq!(
+            send(&socket_path, "reporting pause").await,
+            "ok reporting paused"
+        );
+
+        let monitor_handle = {
+            let config = Arc::clone(&config);
+            let cache = Arc::clone(&cache);
+            let status = Arc::clone(&status);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                crate::monitor::run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::default(),
+                    Readiness::default(),
+                    Default::default(),
+                    tokio::sync::watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+        let reporter_handle = tokio::spawn(crate::results::run_result_reporter(
+            Arc::clone(&config),
+            Arc::clone(&cache),
+            status,
+            crate::reporter::ServerClient::default(),
+            reporting,
+            shutdown_rx,
+        ));
+
+        // Checks keep running and the cache grows while nothing is sent.
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while cache.stats().await.len < 2 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("cache did not grow while reporting was paused");
+        assert!(
+            !results_mock.matched_async().await,
+            "nothing is sent while paused"
+        );
+
+        assert_eq!(
+            send(&socket_path, "reporting resume").await,
+            "ok reporting resumed"
+        );
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !results_mock.matched_async().await {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("backlog was not flushed after resume");
+
+        trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+        reporter_handle.await.unwrap().unwrap();
+        control_handle.await.unwrap().unwrap();
+        results_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_reported_per_line() {
+        let dir = TempDir::new().unwrap();
+        let config = control_config(&dir, vec![Endpoint::new("10.0.0.1")]);
+        let socket_path = PathBuf::from(&config.control.socket_path);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let handle = tokio::spawn(run_control_socket(
+            Arc::new(RwLock::new(config)),
+            dir.path().join("config.toml"),
+            ReportingSwitch::default(),
+            Readiness::default(),
+            shutdown_rx,
+        ));
+        wait_for_socket(&socket_path).await;
+
+        assert_eq!(
+            send(&socket_path, "endpoint enable 10.9.9.9").await,
+            "error Configuration error: No endpoint with address 10.9.9.9"
+        );
+        assert!(send(&socket_path, "bogus").await.starts_with("error "));
+        assert_eq!(
+            send(&socket_path, "endpoint enable 10.0.0.1").await,
+            "ok endpoint 10.0.0.1 enabled"
+        );
+
+        trigger_shutdown(&shutdown_tx);
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disabled_control_socket_exits() {
+        let (_shutdown_tx, shutdown_rx) = shutdown_channel();
+        let config = Arc::new(RwLock::new(Config::default()));
+
+        run_control_socket(
+            config,
+            PathBuf::from("config.toml"),
+            ReportingSwitch::default(),
+            Readiness::default(),
+            shutdown_rx,
+        )
+        .await
+        .unwrap();
+    }
+}
@@ -3,24 +3,42 @@
 //! Contains the main Agent struct and its implementation for coordinating
 //! monitoring tasks and managing agent lifecycle.
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{mpsc, watch, Notify};
 use tracing::{error, info, warn};
 
-use super::AgentStatus;
-use crate::agent_config::Config;
-use crate::cache::ResultCache;
+use super::{
+    shutdown_channel, trigger_shutdown, wait_for_shutdown, wait_startup_splay, AgentState,
+    AgentStatus, Endpoint, MonitoringResult, Readiness, ReadinessStep, ReportingSwitch,
+    ShutdownReceiver, ShutdownSender,
+};
+use crate::agent_config::{Config, ReloadStatus, ReloadStatusHandle};
+use crate::cache::{CacheManager, ResultCache, SegmentCodec};
 use crate::error::Result;
+use crate::metrics::CheckMetrics;
+use crate::monitor::{Checkers, DnsCache, PtrCache};
+use crate::plugin::PluginRegistry;
+use crate::reporter::ServerClient;
 
 /// Main agent instance that coordinates all monitoring tasks
 pub struct Agent {
     config: Arc<RwLock<Config>>,
     config_path: PathBuf,
     status: Arc<RwLock<AgentStatus>>,
+    reload_status: ReloadStatusHandle,
+    reload_requests: Arc<Notify>,
     result_cache: Arc<ResultCache>,
-    shutdown_tx: broadcast::Sender<()>,
+    /// Bumped on every applied reload so the check loop wakes up at once
+    config_reloads: watch::Sender<u64>,
+    reporting: ReportingSwitch,
+    readiness: Readiness,
+    shutdown_tx: ShutdownSender,
+    /// Plugins registered in code, handed to the monitoring loop on start
+    plugins: Mutex<PluginRegistry>,
+    /// Initialized plugins while the agent runs, for on-demand checks
+    running_plugins: Mutex<Option<Arc<PluginRegistry>>>,
 }
 
 impl Agent {
@@ -37,60 +55,201 @@ impl Agent {
         // Load and validate configuration from file
         let config = Config::load_and_validate_config(&config_path)?;
 
-        let (shutdown_tx, _) = broadcast::channel(1);
+        let (shutdown_tx, _) = shutdown_channel();
         let mut status = AgentStatus::new();
         status.config_version = config.version as i64;
+        match AgentState::load(config.storage.state_file()) {
+            Ok(state) => status.restart_count = Some(state.restart_count as i64),
+            Err(e) => warn!("Failed to load agent state: {}", e),
+        }
 
-        let result_cache = Arc::new(ResultCache::new(
+        let mut result_cache = ResultCache::new(
             config.storage.max_cached_results,
             std::time::Duration::from_secs(config.storage.max_cache_age_secs),
-        ));
+        );
+        // The journal is plain JSON; an encrypted cache only persists its
+        // segment on shutdown
+        if config.storage.cache_enabled && config.storage.encrypt {
+            warn!(
+                "Cache encryption is enabled, so the crash-safe cache journal is disabled; \
+                 cached results only survive graceful shutdowns"
+            );
+        } else if config.storage.cache_enabled {
+            result_cache = result_cache.with_journal(CacheManager::from_config(&config.storage));
+        }
+        let result_cache = Arc::new(result_cache);
 
         Ok(Self {
             config: Arc::new(RwLock::new(config)),
             config_path,
             status: Arc::new(RwLock::new(status)),
+            reload_status: ReloadStatusHandle::default(),
+            reload_requests: Arc::new(Notify::new()),
             result_cache,
+            config_reloads: watch::Sender::new(0),
+            reporting: ReportingSwitch::default(),
+            readiness: Readiness::default(),
             shutdown_tx,
+            plugins: Mutex::new(PluginRegistry::new()),
+            running_plugins: Mutex::new(None),
         })
     }
 
+    /// Check endpoints naming a plugin with the plugins of `plugins`
+    ///
+    /// On start, libraries from `plugins.dirs` are loaded into the registry,
+    /// replacing same-named plugins, and every plugin is initialized.
+    pub fn with_plugins(self, plugins: PluginRegistry) -> Self {
+        *self.plugins.lock() = plugins;
+        self
+    }
+
     /// Start the agent and all monitoring tasks
+    ///
+    /// An agent that has stopped can be started again.
     pub async fn start(&self) -> Result<()> {
+        // Re-arm the signal latched by the previous run. A stop requested
+        // before the first start still applies.
+        if self.status.read().stopped_at.is_some() {
+            self.shutdown_tx.send_replace(false);
+        }
         let mut shutdown_rx = self.subscribe_shutdown();
 
-        info!("Starting agent id {}", self.config.read().agent_id);
+        {
+            let config = self.config.read();
+            info!(
+                "Starting agent id {} on host {}",
+                config.agent_id,
+                super::agent_hostname(&config)
+            );
+        }
+
+        // Spread the first server contact of agents booting at once. A no-op
+        // if the binary already waited before claiming.
+        let splay = self.config.read().server.startup_splay();
+        if !wait_startup_splay(splay, wait_for_shutdown(&mut shutdown_rx)).await {
+            info!("Shutdown requested during startup splay");
+            return Ok(());
+        }
+
+        // Check the server API version before sending anything, and before
+        // the cache segment is consumed or plugins are started, so a refused
+        // start leaves both untouched
+        let preflight_config = self.config.read().clone();
+        tokio::select! {
+            result = crate::reporter::run_preflight(&preflight_config) => result?,
+            _ = wait_for_shutdown(&mut shutdown_rx) => {
+                info!("Shutdown requested during server preflight");
+                return Ok(());
+            }
+        }
+
+        // Restore results left unsent by the previous run. A segment that
+        // cannot be decrypted stops the agent instead of being discarded.
+        let segment = self.restore_cache_segment().await?;
+
+        // Load and initialize plugins before the first check. The restored
+        // results go back to disk if that fails.
+        let plugins = match self.load_plugins().await {
+            Ok(plugins) => Arc::new(plugins),
+            Err(e) => {
+                self.save_cache_segment(segment).await;
+                return Err(e);
+            }
+        };
+        *self.running_plugins.lock() = Some(Arc::clone(&plugins));
 
         // Create channel for config hot-reload
         let (reload_config_tx, mut reload_config_rx) = mpsc::channel(1);
+        // Reload triggers, also sent by the reporter for server config versions
+        let reload_triggers = mpsc::unbounded_channel();
 
         // Update status. Agent is considered "running".
+        let started = tokio::time::Instant::now();
         {
             let mut status = self.status.write();
             status.is_running = true;
             status.started_at = chrono::Utc::now();
+            status.stopped_at = None;
         }
+        self.record_start();
+
+        {
+            let config = self.config.read();
+            self.readiness.complete(
+                ReadinessStep::ConfigLoaded,
+                format!(
+                    "config version {} loaded ({} endpoints)",
+                    config.version,
+                    config.endpoints.len()
+                ),
+            );
+            if !config.server.is_configured() {
+                self.readiness
+                    .complete(ReadinessStep::ServerReachable, "no server configured");
+            }
+        }
+
+        // Report startup diagnostics once, without waiting for them
+        tokio::spawn(crate::reporter::run_startup_diagnostics(Arc::clone(
+            &self.config,
+        )));
+
+        // Check metrics, counted by the monitoring tasks and served locally
+        let metrics = CheckMetrics::default();
 
         // Start monitoring tasks
         let monitor_handle = {
             let config = Arc::clone(&self.config);
             let status = Arc::clone(&self.status);
             let cache = Arc::clone(&self.result_cache);
+            let plugins = Arc::clone(&plugins);
+            let readiness = self.readiness.clone();
+            let metrics = metrics.clone();
+            let config_reloads = self.config_reloads.subscribe();
             let mut shutdown_rx = self.subscribe_shutdown();
 
             tokio::spawn(async move {
-                crate::monitor::run_monitoring(config, status, cache, &mut shutdown_rx).await
+                crate::monitor::run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    plugins,
+                    readiness,
+                    metrics,
+                    config_reloads,
+                    &mut shutdown_rx,
+                )
+                .await
             })
         };
 
+        // The status reporter, the result reporter and the heartbeat share
+        // one connection pool
+        let server_client = ServerClient::default();
+
         // Start reporter task
         let reporter_handle = {
             let config = Arc::clone(&self.config);
             let status = Arc::clone(&self.status);
+            let client = server_client.clone();
+            let readiness = self.readiness.clone();
+            let reporting = self.reporting.clone();
+            let reload_tx = reload_triggers.0.clone();
             let mut shutdown_rx = self.subscribe_shutdown();
 
             tokio::spawn(async move {
-                crate::reporter::run_reporter(config, status, &mut shutdown_rx).await
+                crate::reporter::run_reporter(
+                    config,
+                    status,
+                    started,
+                    client,
+                    readiness,
+                    reporting,
+                    reload_tx,
+                    &mut shutdown_rx,
+                )
+                .await
             })
         };
 
@@ -99,10 +258,20 @@ impl Agent {
             let config = Arc::clone(&self.config);
             let status = Arc::clone(&self.status);
             let cache = Arc::clone(&self.result_cache);
+            let client = server_client.clone();
+            let reporting = self.reporting.clone();
             let shutdown_rx = self.subscribe_shutdown();
 
             tokio::spawn(async move {
-                crate::results::run_result_reporter(config, cache, status, shutdown_rx).await
+                crate::results::run_result_reporter(
+                    config,
+                    cache,
+                    status,
+                    client,
+                    reporting,
+                    shutdown_rx,
+                )
+                .await
             })
         };
 
@@ -110,10 +279,11 @@ impl Agent {
         let heartbeat_handle = {
             let config = Arc::clone(&self.config);
             let status = Arc::clone(&self.status);
+            let client = server_client.clone();
             let shutdown_rx = self.subscribe_shutdown();
 
             tokio::spawn(async move {
-                crate::reporter::run_heartbeat(config, status, shutdown_rx).await
+                crate::reporter::run_heartbeat(config, status, client, shutdown_rx).await
             })
         };
 
@@ -131,10 +301,59 @@ impl Agent {
         let hot_reload_handle = {
             let config_path = self.config_path.clone();
             let config_tx = reload_config_tx;
+            let reload_status = Arc::clone(&self.reload_status);
+            let reload_requests = Arc::clone(&self.reload_requests);
+            let cooldown = self.config.read().hot_reload.cooldown();
+            let shutdown_rx = self.subscribe_shutdown();
+
+            tokio::spawn(async move {
+                crate::agent_config::run_hot_reload(
+                    config_path,
+                    config_tx,
+                    reload_status,
+                    reload_requests,
+                    reload_triggers,
+                    cooldown,
+                    shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        // Start control socket task
+        let control_handle = {
+            let config = Arc::clone(&self.config);
+            let config_path = self.config_path.clone();
+            let reporting = self.reporting.clone();
+            let readiness = self.readiness.clone();
+            let shutdown_rx = self.subscribe_shutdown();
+
+            tokio::spawn(async move {
+                crate::control::run_control_socket(
+                    config,
+                    config_path,
+                    reporting,
+                    readiness,
+                    shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        // Start metrics endpoint task
+        let metrics_handle = {
+            let config = Arc::clone(&self.config);
+            let readiness = self.readiness.clone();
             let shutdown_rx = self.subscribe_shutdown();
 
             tokio::spawn(async move {
-                crate::agent_config::run_hot_reload(config_path, config_tx, shutdown_rx).await
+                let result =
+                    crate::metrics::run_metrics_server(config, metrics, readiness, shutdown_rx)
+                        .await;
+                if let Err(e) = &result {
+                    error!("Metrics endpoint failed: {}", e);
+                }
+                result
             })
         };
 
@@ -148,22 +367,27 @@ impl Agent {
             tokio::select! {
                 Some(new_config) = reload_config_rx.recv() => {
                     info!("Config reload received from hot reload task");
+                    let version = new_config.version;
                     if let Err(e) = self.reload_config(new_config) {
                         error!("Failed to apply reloaded config: {}", e);
+                        let mut reload_status = self.reload_status.write();
+                        if let Some(last) = reload_status.as_mut() {
+                            *last = ReloadStatus::failed(last.source, Some(version), &e);
+                        }
                     }
                 }
-                _ = shutdown_rx.recv() => {
+                _ = wait_for_shutdown(&mut shutdown_rx) => {
                     info!("Shutdown signal received");
                     break;
                 }
                 _ = tokio::signal::ctrl_c() => {
                     info!("SIGINT received, shutting down");
-                    let _ = self.shutdown_tx.send(());
+                    trigger_shutdown(&self.shutdown_tx);
                     break;
                 }
                 _ = &mut sigterm => {
                     info!("SIGTERM received, shutting down");
-                    let _ = self.shutdown_tx.send(());
+                    trigger_shutdown(&self.shutdown_tx);
                     break;
                 }
             }
@@ -179,10 +403,44 @@ impl Agent {
             let _ = heartbeat_handle.await;
             let _ = updater_handle.await;
             let _ = hot_reload_handle.await;
+            let _ = control_handle.await;
+            let _ = metrics_handle.await;
         })
         .await
         .ok(); // Ignore timeout error, we just want to wait for tasks to finish if they can
 
+        // Hand the plugins back to the registry so that the next start
+        // initializes them again
+        self.running_plugins.lock().take();
+        match Arc::try_unwrap(plugins) {
+            Ok(mut plugins) => {
+                if let Err(e) = plugins.shutdown_all().await {
+                    error!("Failed to shut down plugins: {}", e);
+                }
+                *self.plugins.lock() = plugins;
+            }
+            Err(_) => {
+                warn!("Plugins are still in use by unfinished checks, not shutting them down; they are unavailable after a restart")
+            }
+        }
+
+        // Every task producing results has stopped: send what is cached once
+        // more and persist whatever the server did not take
+        let config = self.config_clone();
+        let sent = crate::results::flush_on_shutdown(
+            &config,
+            &self.result_cache,
+            &self.status,
+            &server_client,
+            &self.reporting,
+        )
+        .await;
+        let persisted = self.save_cache_segment(segment).await;
+        info!(
+            "Shutdown drain: {} cached results sent, {} persisted to disk",
+            sent, persisted
+        );
+
         // Update status.Agent is considered "stopped".
         {
             let mut status = self.status.write();
@@ -194,18 +452,104 @@ impl Agent {
         Ok(())
     }
 
+    /// Plugins registered in code plus those loaded from `plugins.dirs`,
+    /// initialized
+    ///
+    /// The registry is taken for the run and handed back when the agent
+    /// stops, or right away if loading fails.
+    async fn load_plugins(&self) -> Result<PluginRegistry> {
+        let mut plugins = std::mem::take(&mut *self.plugins.lock());
+        let (dirs, on_init_failure) = {
+            let config = self.config.read();
+            (config.plugins.dirs.clone(), config.plugins.on_init_failure)
+        };
+        if let Some(policy) = on_init_failure {
+            plugins = plugins.with_init_policy(policy);
+        }
+        let loaded = match plugins.load_dirs(&dirs) {
+            Ok(_) => plugins.initialize_all().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = loaded {
+            *self.plugins.lock() = plugins;
+            return Err(e);
+        }
+        Ok(plugins)
+    }
+
     /// Stop the agent gracefully
     pub fn stop(&self) -> Result<()> {
         info!("Stopping agent");
-        let _ = self.shutdown_tx.send(());
+        trigger_shutdown(&self.shutdown_tx);
         Ok(())
     }
 
+    /// Stop sending results to the server while checks keep running
+    ///
+    /// Results accumulate in the cache, bounded by its size and age limits.
+    /// Status reports and heartbeats are not affected.
+    pub fn pause_reporting(&self) {
+        if self.reporting.pause() {
+            info!("Result reporting paused");
+        }
+    }
+
+    /// Send results again, flushing the backlog cached while paused
+    pub fn resume_reporting(&self) {
+        if self.reporting.resume() {
+            info!("Result reporting resumed");
+        }
+    }
+
+    /// Whether result reporting is paused
+    pub fn is_reporting_paused(&self) -> bool {
+        self.reporting.is_paused()
+    }
+
+    /// Whether startup has completed: config loaded, first check cycle
+    /// scheduled (or succeeded) and the server reached if one is configured
+    pub fn is_ready(&self) -> bool {
+        self.readiness.is_ready()
+    }
+
+    /// Wait until the agent is ready, see [`Agent::is_ready`]
+    pub async fn wait_ready(&self) {
+        self.readiness.wait().await
+    }
+
+    /// Reload the config file as if SIGHUP was received
+    ///
+    /// Works on every platform; on Windows it replaces SIGHUP and is used by
+    /// the service control handler. A request made while the agent is not
+    /// running is applied once it starts.
+    pub fn request_reload(&self) {
+        info!("Config reload requested");
+        self.reload_requests.notify_one();
+    }
+
+    /// Check `endpoint` once, right now, and return the result
+    ///
+    /// The check runs outside the monitoring loop with the current monitoring
+    /// settings: it is not scheduled, not cached for reporting and not
+    /// counted in the agent status. Endpoints naming a plugin can only be
+    /// checked while the agent is running. Fails if the checkers cannot be
+    /// built from the current config.
+    pub async fn check_endpoint_now(&self, endpoint: &Endpoint) -> Result<MonitoringResult> {
+        let config = self.config_clone();
+        let plugins = self.running_plugins.lock().clone().unwrap_or_default();
+        let checkers = Checkers::from_config(&config, &DnsCache::default(), &PtrCache::default())?
+            .with_plugins(plugins);
+
+        info!("Checking endpoint {} on demand", endpoint.address);
+        Ok(checkers.check(config.agent_id, endpoint).await)
+    }
+
     /// Subscribe to shutdown signals
     ///
-    /// Returns a broadcast receiver that will receive a signal when the agent is shutting down.
+    /// Returns a latching receiver that observes the signal when the agent is shutting down,
+    /// even if it subscribes after shutdown was requested.
     /// Useful for coordinating shutdown of related tasks like config reload managers.
-    pub fn subscribe_shutdown(&self) -> broadcast::Receiver<()> {
+    pub fn subscribe_shutdown(&self) -> ShutdownReceiver {
         self.shutdown_tx.subscribe()
     }
 
@@ -214,11 +558,80 @@ impl Agent {
         self.status.read().clone()
     }
 
+    /// Outcome of the most recent hot reload (file change or SIGHUP), `None`
+    /// if no reload was attempted since the agent started
+    pub fn last_reload_status(&self) -> Option<ReloadStatus> {
+        self.reload_status.read().clone()
+    }
+
+    /// Increment the persisted restart count and reflect it in the status
+    ///
+    /// Failing to persist the state is logged but does not prevent the agent
+    /// from starting; the in-memory count is still incremented.
+    fn record_start(&self) {
+        let path = self.config.read().storage.state_file();
+
+        let mut state = AgentState::load(&path).unwrap_or_else(|e| {
+            warn!("Failed to load agent state, starting a new one: {}", e);
+            AgentState::default()
+        });
+        state.restart_count += 1;
+
+        if let Err(e) = state.save(&path) {
+            warn!("Failed to save agent state to {}: {}", path.display(), e);
+        }
+
+        self.status.write().restart_count = Some(state.restart_count as i64);
+        info!("Agent start #{}", state.restart_count);
+    }
+
+    /// Load the results journaled and the cache segment written by the
+    /// previous run
+    ///
+    /// An unreadable journal is logged and skipped. Returns the segment path
+    /// and codec to save the cache with on shutdown, or `None` when caching
+    /// is disabled.
+    async fn restore_cache_segment(&self) -> Result<Option<(PathBuf, SegmentCodec)>> {
+        let storage = self.config.read().storage.clone();
+        if !storage.cache_enabled {
+            return Ok(None);
+        }
+
+        if let Err(e) = self.result_cache.restore_journal().await {
+            warn!(
+                "Failed to restore cached results from {}: {}",
+                storage.cache_journal_file().display(),
+                e
+            );
+        }
+
+        let codec = SegmentCodec::from_config(&storage)?;
+        let path = storage.cache_segment_file();
+        self.result_cache.restore_segment(&path, &codec).await?;
+        Ok(Some((path, codec)))
+    }
+
+    /// Write the cached results to the segment returned by
+    /// [`Agent::restore_cache_segment`] and return how many were written
+    async fn save_cache_segment(&self, segment: Option<(PathBuf, SegmentCodec)>) -> usize {
+        let Some((path, codec)) = segment else {
+            return 0;
+        };
+        match self.result_cache.save_segment(&path, &codec).await {
+            Ok(saved) => saved,
+            Err(e) => {
+                error!("Failed to save cached results to {}: {}", path.display(), e);
+                0
+            }
+        }
+    }
+
     /// Hot-reload configuration with validation
     ///
     /// This method validates the new configuration before applying it.
-    /// On successful validation, the configuration is updated atomically.
-    /// Monitoring tasks will pick up the new configuration on their next cycle.
+    /// On successful validation, the configuration is updated atomically and
+    /// the running check loop is woken up, so added endpoints are checked
+    /// right away and a changed monitoring interval takes effect at once.
     ///
     /// # Arguments
     ///
@@ -232,9 +645,10 @@ impl Agent {
     ///
     /// # Note
     ///
-    /// This method does not restart monitoring tasks. They will pick up
-    /// the new configuration on their next iteration. For changes that require
-    /// immediate effect (like API keys or server URLs), consider restarting the agent.
+    /// Endpoints that were already scheduled keep their next check time unless
+    /// their interval changed. The reporter and heartbeat tasks read their
+    /// intervals, server URL and API key on their next tick. Settings read
+    /// only on start (like `metrics_addr`) require restarting the agent.
     pub fn reload_config(&self, new_config: Config) -> Result<()> {
         info!(
             "Attempting to reload configuration (version: {})",
@@ -251,6 +665,8 @@ impl Agent {
         // Apply the new configuration atomically
         *self.config.write() = new_config.clone();
         self.status.write().config_version = new_config.version as i64;
+        self.config_reloads
+            .send_modify(|generation| *generation += 1);
 
         info!(
             "Configuration reloaded successfully (version: {})",
@@ -316,7 +732,7 @@ async fn wait_sigterm() {
         }
         Err(e) => {
             // If we can't install the handler, log and park forever so the process
-            // can still be stopped via SIGINT / shutdown broadcast.
+            // can still be stopped via SIGINT / shutdown signal.
             tracing::warn!("Failed to install SIGTERM handler: {}", e);
             std::future::pending::<()>().await;
         }
@@ -331,9 +747,41 @@ async fn wait_sigterm() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::plugin::PluginInitPolicy;
     use tempfile::NamedTempFile;
     use uuid::Uuid;
 
+    fn cached_result(agent_id: Uuid) -> MonitoringResult {
+        use crate::core::{CheckType, PingCheck, PingCheckType, PingResult};
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "10.0.0.1".to_string(),
+                    successes: 1,
+                    failures: 0,
+                    success_latencies: vec![1.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: chrono::Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
     #[tokio::test]
     async fn test_reload_config_success() {
         let config = Config {
@@ -371,6 +819,340 @@ mod tests {
         );
     }
 
+    /// Each start of a fresh `Agent` (a simulated process restart) increments
+    /// the restart count persisted in the state file.
+    #[tokio::test]
+    async fn test_restart_count_increments_across_restarts() {
+        let state_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.storage.cache_dir = state_dir.path().to_string_lossy().into_owned();
+
+        let temp_file = NamedTempFile::new().unwrap();
+        config.save_to_file_secure(temp_file.path()).await.unwrap();
+
+        for expected in 1..=3 {
+            let agent = Agent::new(temp_file.path().to_path_buf()).unwrap();
+            assert_eq!(agent.status().restart_count, Some(expected - 1));
+
+            agent.record_start();
+            assert_eq!(agent.status().restart_count, Some(expected));
+        }
+
+        let state = AgentState::load(config.storage.state_file()).unwrap();
+        assert_eq!(state.restart_count, 3);
+    }
+
+    /// A cache segment encrypted with another key prevents the agent from
+    /// starting instead of silently dropping the cached results.
+    #[tokio::test]
+    async fn test_cache_segment_with_wrong_key_fails_start() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+        config.storage.encrypt = true;
+        config.storage.encryption_key = Some("new-key".to_string());
+
+        std::fs::write(
+            config.storage.cache_segment_file(),
+            SegmentCodec::encrypted("old-key").encode(&[]).unwrap(),
+        )
+        .unwrap();
+
+        let temp_file = NamedTempFile::new().unwrap();
+        config.save_to_file_secure(temp_file.path()).await.unwrap();
+        let agent = Agent::new(temp_file.path().to_path_buf()).unwrap();
+
+        let err = agent.start().await.unwrap_err();
+        assert!(err.to_string().contains("Failed to decrypt"), "{}", err);
+        assert!(!agent.status().is_running);
+    }
+
+    /// A start refused by the server preflight leaves the cache segment of
+    /// the previous run on disk for the next start.
+    #[tokio::test]
+    async fn test_failed_preflight_keeps_cache_segment() {
+        use crate::agent_config::PreflightMode;
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/health")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status": "ok", "api_version": "2.0.0", "report_schema_version": 1}"#)
+            .create_async()
+            .await;
+
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+        config.server.url = server.url();
+        config.server.api_key = Some("test-api-key".to_string());
+        config.server.preflight = PreflightMode::Enforce;
+
+        let codec = SegmentCodec::from_config(&config.storage).unwrap();
+        let segment = codec.encode(&[cached_result(config.agent_id)]).unwrap();
+        std::fs::write(config.storage.cache_segment_file(), &segment).unwrap();
+
+        let temp_file = NamedTempFile::new().unwrap();
+        config.save_to_file_secure(temp_file.path()).await.unwrap();
+        let agent = Agent::new(temp_file.path().to_path_buf()).unwrap();
+
+        let err = agent.start().await.unwrap_err();
+        assert!(err.to_string().contains("2.0.0"), "{}", err);
+        assert_eq!(
+            std::fs::read(config.storage.cache_segment_file()).unwrap(),
+            segment
+        );
+        assert_eq!(agent.result_cache.stats().await.len, 0);
+    }
+
+    /// An on-demand check of a plain address pings it without starting the
+    /// agent and without touching the cache.
+    #[tokio::test]
+    async fn test_check_endpoint_now_returns_ping_result() {
+        use crate::core::CheckType;
+
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.monitoring.timeout_secs = 1;
+        config.monitoring.ping_count = 1;
+        let temp_file = NamedTempFile::new().unwrap();
+        config.save_to_file_secure(temp_file.path()).await.unwrap();
+        let agent = Agent::new(temp_file.path().to_path_buf()).unwrap();
+
+        let endpoint = Endpoint::new("127.0.0.1");
+        let result = agent.check_endpoint_now(&endpoint).await.unwrap();
+
+        assert!(
+            matches!(result.check_type, CheckType::PingCheck(_)),
+            "{:?}",
+            result.check_type
+        );
+        assert_eq!(result.endpoint_id, endpoint.id);
+        assert_eq!(result.agent_id, config.agent_id);
+        assert_eq!(agent.result_cache.stats().await.len, 0);
+        assert_eq!(agent.status().checks_performed, 0);
+    }
+
+    /// Results cached when the agent stops, with no server to send them to,
+    /// are written to the cache dir.
+    #[tokio::test]
+    async fn test_shutdown_persists_cached_results() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+        let temp_file = NamedTempFile::new().unwrap();
+        config.save_to_file_secure(temp_file.path()).await.unwrap();
+        let agent = Arc::new(Agent::new(temp_file.path().to_path_buf()).unwrap());
+
+        let agent_handle = {
+            let agent = Arc::clone(&agent);
+            tokio::spawn(async move { agent.start().await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        for _ in 0..3 {
+            agent
+                .result_cache
+                .push(cached_result(config.agent_id))
+                .await;
+        }
+        agent.stop().unwrap();
+        agent_handle.await.unwrap().unwrap();
+
+        let segment = std::fs::read(config.storage.cache_segment_file())
+            .expect("cached results were not persisted");
+        let codec = SegmentCodec::from_config(&config.storage).unwrap();
+        assert_eq!(codec.decode(&segment).unwrap().len(), 3);
+    }
+
+    /// Plugin counting its initializations
+    struct CountingPlugin {
+        initialized: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::plugin::MonitoringPlugin for CountingPlugin {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        async fn check(&self, _agent_id: &Uuid, _endpoint: &Endpoint) -> Result<MonitoringResult> {
+            Err(crate::error::Error::Plugin("not checked".to_string()))
+        }
+
+        async fn initialize(&mut self) -> Result<()> {
+            self.initialized
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    struct FailingPlugin;
+
+    #[async_trait::async_trait]
+    impl crate::plugin::MonitoringPlugin for FailingPlugin {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        async fn check(&self, _agent_id: &Uuid, _endpoint: &Endpoint) -> Result<MonitoringResult> {
+            Err(crate::error::Error::Plugin("not checked".to_string()))
+        }
+
+        async fn initialize(&mut self) -> Result<()> {
+            Err(crate::error::Error::Plugin("no backend".to_string()))
+        }
+    }
+
+    /// `plugins.on_init_failure` decides whether a plugin that fails to
+    /// initialize stops the agent from starting.
+    #[tokio::test]
+    async fn test_plugin_init_failure_policy_from_config() {
+        for policy in [PluginInitPolicy::FailFast, PluginInitPolicy::SkipFailed] {
+            let cache_dir = tempfile::TempDir::new().unwrap();
+            let mut config = Config {
+                agent_id: Uuid::now_v7(),
+                ..Config::default()
+            };
+            config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+            config.plugins.on_init_failure = Some(policy);
+            let temp_file = NamedTempFile::new().unwrap();
+            config.save_to_file_secure(temp_file.path()).await.unwrap();
+
+            let initialized = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let mut plugins = PluginRegistry::new();
+            plugins.register(Box::new(FailingPlugin));
+            plugins.register(Box::new(CountingPlugin {
+                initialized: Arc::clone(&initialized),
+            }));
+            let agent = Arc::new(
+                Agent::new(temp_file.path().to_path_buf())
+                    .unwrap()
+                    .with_plugins(plugins),
+            );
+
+            let agent_handle = {
+                let agent = Arc::clone(&agent);
+                tokio::spawn(async move { agent.start().await })
+            };
+            if policy == PluginInitPolicy::FailFast {
+                let err = agent_handle.await.unwrap().unwrap_err();
+                assert!(err.to_string().contains("no backend"), "{}", err);
+                continue;
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            assert!(agent.status().is_running);
+            let running = agent.running_plugins.lock().clone().expect("plugins");
+            assert!(running.get("failing").is_none());
+            assert!(running.get("counting").is_some());
+            drop(running);
+
+            agent.stop().unwrap();
+            agent_handle.await.unwrap().unwrap();
+        }
+    }
+
+    /// A stopped agent starts again with the plugins it was built with.
+    #[tokio::test]
+    async fn test_restart_keeps_plugins() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+        let temp_file = NamedTempFile::new().unwrap();
+        config.save_to_file_secure(temp_file.path()).await.unwrap();
+
+        let initialized = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut plugins = PluginRegistry::new();
+        plugins.register(Box::new(CountingPlugin {
+            initialized: Arc::clone(&initialized),
+        }));
+        let agent = Arc::new(
+            Agent::new(temp_file.path().to_path_buf())
+                .unwrap()
+                .with_plugins(plugins),
+        );
+
+        for run in 1..=2 {
+            let agent_handle = {
+                let agent = Arc::clone(&agent);
+                tokio::spawn(async move { agent.start().await })
+            };
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+            assert!(agent.status().is_running, "run {}", run);
+            let running = agent.running_plugins.lock().clone().expect("plugins");
+            assert!(running.get("counting").is_some(), "run {}", run);
+            drop(running);
+            assert_eq!(initialized.load(std::sync::atomic::Ordering::SeqCst), run);
+
+            agent.stop().unwrap();
+            agent_handle.await.unwrap().unwrap();
+            assert!(!agent.status().is_running);
+        }
+    }
+
+    /// A task that is slow to subscribe or to poll must still observe a
+    /// shutdown requested before it got there.
+    #[tokio::test]
+    async fn test_stop_reaches_slow_subscriber() {
+        let config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        let temp_file = NamedTempFile::new().unwrap();
+        config.save_to_file_secure(temp_file.path()).await.unwrap();
+        let agent = Agent::new(temp_file.path().to_path_buf()).unwrap();
+
+        let mut early_rx = agent.subscribe_shutdown();
+        let slow = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            wait_for_shutdown(&mut early_rx).await;
+        });
+
+        agent.stop().unwrap();
+        agent.stop().unwrap();
+
+        tokio::time::timeout(std::time::Duration::from_secs(1), slow)
+            .await
+            .expect("slow subscriber must observe shutdown")
+            .unwrap();
+
+        let mut late_rx = agent.subscribe_shutdown();
+        tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            wait_for_shutdown(&mut late_rx),
+        )
+        .await
+        .expect("subscriber created after stop() must observe shutdown");
+    }
+
     #[tokio::test]
     async fn test_reload_config_validation_failure() {
         let original_config = Config {
@@ -0,0 +1,134 @@
+//! Hostname reported to the server
+//!
+//! The configured `hostname` override wins, then the system hostname. When
+//! the system hostname cannot be determined, the agent generates a synthetic
+//! name (`agent-{8 hex digits}`) once and persists it in the agent state
+//! file, so it reports the same name across restarts instead of `"unknown"`.
+
+use super::AgentState;
+use crate::agent_config::Config;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Hostname of this agent: the override, the system hostname, or a stable
+/// synthetic name
+pub fn agent_hostname(config: &Config) -> String {
+    resolve_hostname(
+        config.hostname.as_deref(),
+        hostname::get,
+        &config.storage.state_file(),
+    )
+}
+
+fn resolve_hostname(
+    hostname_override: Option<&str>,
+    system_hostname: impl FnOnce() -> io::Result<OsString>,
+    state_path: &Path,
+) -> String {
+    if let Some(hostname) = hostname_override.filter(|h| !h.is_empty()) {
+        return hostname.to_string();
+    }
+
+    match system_hostname() {
+        Ok(hostname) if !hostname.is_empty() => return hostname.to_string_lossy().into_owned(),
+        Ok(_) => warn!("System hostname is empty"),
+        Err(e) => warn!("Failed to determine system hostname: {}", e),
+    }
+
+    synthetic_hostname(state_path)
+}
+
+/// Synthetic hostname persisted in the state file, generated on first use
+fn synthetic_hostname(state_path: &Path) -> String {
+    let mut state = match AgentState::load(state_path) {
+        Ok(state) => state,
+        Err(e) => {
+            warn!("Failed to load agent state, starting a new one: {}", e);
+            AgentState::default()
+        }
+    };
+
+    if let Some(hostname) = &state.synthetic_hostname {
+        return hostname.clone();
+    }
+
+    // The leading digits of a UUIDv7 are a timestamp, its trailing ones random
+    let id = Uuid::now_v7().simple().to_string();
+    let hostname = format!("agent-{}", &id[id.len() - 8..]);
+    warn!(
+        "Using synthetic hostname {}; set `hostname` in the config to override it",
+        hostname
+    );
+
+    state.synthetic_hostname = Some(hostname.clone());
+    if let Err(e) = state.save(state_path) {
+        warn!(
+            "Failed to save agent state to {}: {}",
+            state_path.display(),
+            e
+        );
+    }
+
+    hostname
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn failing() -> io::Result<OsString> {
+        Err(io::Error::other("gethostname failed"))
+    }
+
+    #[test]
+    fn test_synthetic_hostname_is_generated_and_persisted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("agent_state.json");
+        AgentState {
+            restart_count: 3,
+            ..AgentState::default()
+        }
+        .save(&path)
+        .unwrap();
+
+        let hostname = resolve_hostname(None, failing, &path);
+        assert!(hostname.starts_with("agent-"), "{}", hostname);
+        assert_eq!(hostname.len(), "agent-".len() + 8);
+
+        let state = AgentState::load(&path).unwrap();
+        assert_eq!(state.synthetic_hostname.as_deref(), Some(hostname.as_str()));
+        assert_eq!(state.restart_count, 3, "other state must be kept");
+
+        assert_eq!(
+            resolve_hostname(None, failing, &path),
+            hostname,
+            "the synthetic name must be reused after a restart"
+        );
+    }
+
+    #[test]
+    fn test_system_hostname_and_override_win() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("agent_state.json");
+
+        assert_eq!(
+            resolve_hostname(None, || Ok(OsString::from("web-01")), &path),
+            "web-01"
+        );
+        assert_eq!(resolve_hostname(Some("edge-7"), failing, &path), "edge-7");
+        assert!(!path.exists(), "no synthetic name is needed");
+    }
+
+    #[test]
+    fn test_empty_system_hostname_falls_back() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("agent_state.json");
+
+        let hostname = resolve_hostname(Some(""), || Ok(OsString::new()), &path);
+        assert!(hostname.starts_with("agent-"));
+    }
+}
@@ -1,7 +1,28 @@
 //! Core agent types and implementation
 
 mod agent;
+mod hostname;
+mod quorum;
+mod readiness;
+mod reporting;
+mod shutdown;
+mod splay;
+mod state;
+mod target;
+mod thresholds;
 mod types;
 
 pub use agent::Agent;
+pub use hostname::agent_hostname;
+pub use quorum::SuccessQuorum;
+pub use readiness::{Readiness, ReadinessStep};
+pub use reporting::ReportingSwitch;
+pub use shutdown::{
+    shutdown_channel, trigger_shutdown, wait_for_shutdown, ShutdownReceiver, ShutdownSender,
+    SHUTDOWN_FLUSH_TIMEOUT,
+};
+pub use splay::wait_startup_splay;
+pub use state::AgentState;
+pub use target::{CheckKind, CheckTarget};
+pub use thresholds::{Classification, TagThresholds, Thresholds};
 pub use types::*;
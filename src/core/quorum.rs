@@ -0,0 +1,139 @@
+//! Success quorum for multi-sample checks
+//!
+//! A ping check sends `ping_count` echo requests. By default one reply is
+//! enough for the check to succeed. A quorum raises that bar, either to an
+//! absolute number of replies (`"2"`, i.e. at least 2 of the samples) or to
+//! a share of the samples (`"60%"`). Percentages round up, so `"60%"` of 3
+//! samples requires 2 replies.
+
+use crate::error::{Error, Result};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Minimum number of successful samples for a check to succeed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SuccessQuorum {
+    /// At least this many samples
+    AtLeast(u32),
+
+    /// At least this percentage (1-100) of the samples
+    Percent(u8),
+}
+
+impl SuccessQuorum {
+    /// Number of successful samples required out of `samples`.
+    ///
+    /// Never less than 1 and never more than `samples` (when `samples > 0`).
+    pub fn required(&self, samples: u32) -> u32 {
+        let required = match *self {
+            Self::AtLeast(count) => count,
+            Self::Percent(percent) => (samples * percent as u32).div_ceil(100),
+        };
+        required.clamp(1, samples.max(1))
+    }
+}
+
+impl FromStr for SuccessQuorum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let invalid = || {
+            Error::Config(format!(
+                "Invalid success quorum '{}': expected a reply count such as \"2\" or a percentage such as \"60%\"",
+                s
+            ))
+        };
+
+        if let Some(percent) = s.strip_suffix('%') {
+            let percent: u8 = percent.trim().parse().map_err(|_| invalid())?;
+            if percent == 0 || percent > 100 {
+                return Err(invalid());
+            }
+            Ok(Self::Percent(percent))
+        } else {
+            let count: u32 = s.parse().map_err(|_| invalid())?;
+            if count == 0 {
+                return Err(invalid());
+            }
+            Ok(Self::AtLeast(count))
+        }
+    }
+}
+
+impl TryFrom<String> for SuccessQuorum {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<SuccessQuorum> for String {
+    fn from(quorum: SuccessQuorum) -> Self {
+        quorum.to_string()
+    }
+}
+
+impl JsonSchema for SuccessQuorum {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "SuccessQuorum".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": "^\\s*[0-9]+\\s*%?\\s*$",
+            "description": "Reply count such as \"2\" or percentage such as \"60%\"",
+        })
+    }
+}
+
+impl fmt::Display for SuccessQuorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AtLeast(count) => write!(f, "{}", count),
+            Self::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "2".parse::<SuccessQuorum>().unwrap(),
+            SuccessQuorum::AtLeast(2)
+        );
+        assert_eq!(
+            " 60% ".parse::<SuccessQuorum>().unwrap(),
+            SuccessQuorum::Percent(60)
+        );
+        for invalid in ["0", "0%", "101%", "-1", "two", "%", ""] {
+            assert!(invalid.parse::<SuccessQuorum>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_required_samples() {
+        assert_eq!(SuccessQuorum::AtLeast(2).required(3), 2);
+        assert_eq!(SuccessQuorum::Percent(60).required(3), 2);
+        assert_eq!(SuccessQuorum::Percent(100).required(3), 3);
+        assert_eq!(SuccessQuorum::Percent(1).required(3), 1);
+        // A quorum can never require more samples than are sent.
+        assert_eq!(SuccessQuorum::AtLeast(5).required(3), 3);
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for quorum in [SuccessQuorum::AtLeast(2), SuccessQuorum::Percent(60)] {
+            assert_eq!(quorum.to_string().parse::<SuccessQuorum>().unwrap(), quorum);
+        }
+    }
+}
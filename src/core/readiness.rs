@@ -0,0 +1,149 @@
+//! Readiness latch flipped once the agent has finished starting
+//!
+//! Orchestrators want one signal that startup is over: the config is loaded,
+//! the first check cycle has been scheduled (or, with
+//! `monitoring.ready_after_successful_check`, has produced a successful
+//! check) and the server has answered if one is configured. Each startup task
+//! completes its step with a short detail; once every step is complete the
+//! latch flips, a single `Agent ready` line summarizing the steps is logged
+//! and it never flips back.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::info;
+
+/// Startup step the agent waits for before it is ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReadinessStep {
+    /// Configuration loaded and validated
+    ConfigLoaded,
+
+    /// First check cycle scheduled, or succeeded when that is required
+    CheckCycle,
+
+    /// Server answered, or no server is configured
+    ServerReachable,
+}
+
+impl ReadinessStep {
+    /// Every step, in the order they usually complete
+    pub const ALL: [Self; 3] = [Self::ConfigLoaded, Self::CheckCycle, Self::ServerReachable];
+}
+
+impl fmt::Display for ReadinessStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConfigLoaded => write!(f, "config"),
+            Self::CheckCycle => write!(f, "check cycle"),
+            Self::ServerReachable => write!(f, "server"),
+        }
+    }
+}
+
+/// Shared readiness state of the agent
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    completed: Arc<watch::Sender<BTreeMap<ReadinessStep, String>>>,
+    created: Instant,
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self {
+            completed: Arc::new(watch::Sender::new(BTreeMap::new())),
+            created: Instant::now(),
+        }
+    }
+}
+
+impl Readiness {
+    /// Mark `step` as complete, described by `detail` in the ready summary
+    ///
+    /// Completing a step twice keeps the first detail. Returns true if this
+    /// made the agent ready.
+    pub fn complete(&self, step: ReadinessStep, detail: impl Into<String>) -> bool {
+        let mut became_ready = false;
+        self.completed.send_if_modified(|completed| {
+            if completed.contains_key(&step) {
+                return false;
+            }
+            completed.insert(step, detail.into());
+            became_ready = completed.len() == ReadinessStep::ALL.len();
+            true
+        });
+
+        if became_ready {
+            let summary: Vec<String> = self.completed.borrow().values().cloned().collect();
+            info!(
+                "Agent ready after {:?}: {}",
+                self.elapsed(),
+                summary.join(", ")
+            );
+        }
+        became_ready
+    }
+
+    /// Whether every startup step is complete
+    pub fn is_ready(&self) -> bool {
+        self.completed.borrow().len() == ReadinessStep::ALL.len()
+    }
+
+    /// Steps not completed yet
+    pub fn pending(&self) -> Vec<ReadinessStep> {
+        let completed = self.completed.borrow();
+        ReadinessStep::ALL
+            .into_iter()
+            .filter(|step| !completed.contains_key(step))
+            .collect()
+    }
+
+    /// Wait until the agent is ready
+    pub async fn wait(&self) {
+        let mut rx = self.completed.subscribe();
+        // The sender lives as long as `self`, so the channel cannot close
+        let _ = rx
+            .wait_for(|completed| completed.len() == ReadinessStep::ALL.len())
+            .await;
+    }
+
+    fn elapsed(&self) -> Duration {
+        // Millisecond precision is plenty for a startup summary
+        Duration::from_millis(self.created.elapsed().as_millis() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ready_once_every_step_completes() {
+        let readiness = Readiness::default();
+        assert!(!readiness.is_ready());
+
+        assert!(!readiness.complete(ReadinessStep::ConfigLoaded, "config version 1"));
+        assert!(!readiness.complete(ReadinessStep::ConfigLoaded, "again"));
+        assert!(!readiness.complete(ReadinessStep::ServerReachable, "no server"));
+        assert_eq!(readiness.pending(), vec![ReadinessStep::CheckCycle]);
+
+        let waiter = {
+            let readiness = readiness.clone();
+            tokio::spawn(async move { readiness.wait().await })
+        };
+        assert!(readiness.complete(ReadinessStep::CheckCycle, "first cycle"));
+        assert!(readiness.is_ready());
+        assert!(readiness.pending().is_empty());
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter not woken")
+            .unwrap();
+
+        assert!(
+            !readiness.complete(ReadinessStep::CheckCycle, "second cycle"),
+            "the latch flips only once"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Switch that pauses result reporting without stopping monitoring
+//!
+//! During a server migration operators keep the checks running and results
+//! cached while nothing is sent, then resume once the agent points at the new
+//! server. The switch is a `watch` channel holding `true` while reporting is
+//! paused, so the result reporter can wake up and flush the backlog as soon
+//! as reporting resumes instead of waiting for its next tick.
+//!
+//! The status reporter also signals through the switch when a report
+//! succeeds after the server was unreachable, so results cached during the
+//! outage are flushed as soon as the server is back.
+//!
+//! Every reporter — status, results and result stream — records its send
+//! outcomes through [`ReportingSwitch::record_report_success`] and
+//! [`ReportingSwitch::record_report_failure`], so `server_connected` follows
+//! all traffic to the server, not only status reports.
+
+use super::AgentStatus;
+use std::sync::Arc;
+use tokio::sync::{watch, Notify};
+
+/// Shared pause state of the result reporter
+#[derive(Debug, Clone)]
+pub struct ReportingSwitch {
+    paused: Arc<watch::Sender<bool>>,
+    reconnected: Arc<Notify>,
+}
+
+impl Default for ReportingSwitch {
+    fn default() -> Self {
+        Self {
+            paused: Arc::new(watch::Sender::new(false)),
+            reconnected: Arc::new(Notify::new()),
+        }
+    }
+}
+
+impl ReportingSwitch {
+    /// Stop sending results. Returns false if reporting was already paused.
+    pub fn pause(&self) -> bool {
+        self.paused
+            .send_if_modified(|paused| !std::mem::replace(paused, true))
+    }
+
+    /// Send results again. Returns false if reporting was not paused.
+    pub fn resume(&self) -> bool {
+        self.paused
+            .send_if_modified(|paused| std::mem::replace(paused, false))
+    }
+
+    /// Whether results are currently held back
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Receiver notified on every pause and resume
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.paused.subscribe()
+    }
+
+    /// Signal that the server is reachable again after an outage
+    ///
+    /// A signal sent while nobody waits is kept for the next `reconnected`.
+    pub fn notify_reconnected(&self) {
+        self.reconnected.notify_one();
+    }
+
+    /// Wait until the server is reachable again
+    pub async fn reconnected(&self) {
+        self.reconnected.notified().await;
+    }
+
+    /// Record a send the server accepted, signalling a reconnect if the
+    /// agent was disconnected. Returns true in that case.
+    pub fn record_report_success(&self, status: &mut AgentStatus) -> bool {
+        let reconnected = status.record_report_success();
+        if reconnected {
+            self.notify_reconnected();
+        }
+        reconnected
+    }
+
+    /// Record a failed send. The agent is shown as disconnected once
+    /// `grace` sends in a row have failed.
+    pub fn record_report_failure(&self, status: &mut AgentStatus, grace: u32) {
+        status.record_report_failure(grace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_resume_report_changes() {
+        let switch = ReportingSwitch::default();
+        assert!(!switch.is_paused());
+
+        assert!(switch.pause());
+        assert!(!switch.pause(), "pausing twice changes nothing");
+        assert!(switch.clone().is_paused(), "clones share the state");
+
+        assert!(switch.resume());
+        assert!(!switch.resume());
+        assert!(!switch.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_sees_resume() {
+        let switch = ReportingSwitch::default();
+        switch.pause();
+        let mut rx = switch.subscribe();
+
+        switch.resume();
+        rx.changed().await.unwrap();
+        assert!(!*rx.borrow());
+    }
+
+    #[tokio::test]
+    async fn test_success_after_disconnect_signals_reconnect() {
+        let switch = ReportingSwitch::default();
+        let mut status = AgentStatus::default();
+        assert!(switch.record_report_success(&mut status));
+
+        switch.record_report_failure(&mut status, 1);
+        assert!(!status.server_connected);
+        assert!(switch.record_report_success(&mut status));
+        assert!(!switch.record_report_success(&mut status));
+
+        tokio::time::timeout(std::time::Duration::from_secs(1), switch.reconnected())
+            .await
+            .expect("reconnect must be signalled");
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_signal_is_kept_until_awaited() {
+        let switch = ReportingSwitch::default();
+        switch.clone().notify_reconnected();
+
+        tokio::time::timeout(std::time::Duration::from_secs(1), switch.reconnected())
+            .await
+            .expect("signal sent before waiting must not be lost");
+    }
+}
@@ -0,0 +1,95 @@
+//! Latching shutdown signal shared by all agent tasks
+//!
+//! The signal is a `watch` channel holding `true` once shutdown has been
+//! requested. Unlike a bounded broadcast channel, a receiver can neither lag
+//! behind and lose the message nor miss it by subscribing late: every receiver
+//! observes the latched state whenever it next polls.
+
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Time the final status report and the final flush of cached results get on
+/// shutdown before they are abandoned
+pub const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Sending half of the shutdown signal
+pub type ShutdownSender = watch::Sender<bool>;
+
+/// Receiving half of the shutdown signal
+pub type ShutdownReceiver = watch::Receiver<bool>;
+
+/// Create a shutdown signal that has not been triggered yet
+pub fn shutdown_channel() -> (ShutdownSender, ShutdownReceiver) {
+    watch::channel(false)
+}
+
+/// Request shutdown
+///
+/// Idempotent, and latches even when no receiver currently exists.
+pub fn trigger_shutdown(tx: &ShutdownSender) {
+    tx.send_replace(true);
+}
+
+/// Wait until shutdown has been requested
+///
+/// Returns immediately if shutdown was already requested, and also when the
+/// sender has been dropped since no signal can arrive after that.
+pub async fn wait_for_shutdown(rx: &mut ShutdownReceiver) {
+    let _ = rx.wait_for(|&shutdown| shutdown).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_late_subscriber_sees_shutdown() {
+        let (tx, _rx) = shutdown_channel();
+        trigger_shutdown(&tx);
+
+        let mut late = tx.subscribe();
+        tokio::time::timeout(Duration::from_millis(100), wait_for_shutdown(&mut late))
+            .await
+            .expect("a receiver created after the signal must still observe it");
+    }
+
+    #[tokio::test]
+    async fn test_trigger_without_receivers_latches() {
+        let (tx, rx) = shutdown_channel();
+        drop(rx);
+        trigger_shutdown(&tx);
+
+        assert!(*tx.subscribe().borrow());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_triggers_do_not_lag_slow_subscriber() {
+        let (tx, rx) = shutdown_channel();
+
+        let slow = tokio::spawn(async move {
+            let mut rx = rx;
+            // Busy with something else while the signal is sent several times.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            wait_for_shutdown(&mut rx).await;
+        });
+
+        for _ in 0..10 {
+            trigger_shutdown(&tx);
+        }
+
+        tokio::time::timeout(Duration::from_secs(1), slow)
+            .await
+            .expect("slow subscriber must shut down")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_sender_releases_waiters() {
+        let (tx, mut rx) = shutdown_channel();
+        drop(tx);
+
+        tokio::time::timeout(Duration::from_millis(100), wait_for_shutdown(&mut rx))
+            .await
+            .expect("waiters must not hang once the sender is gone");
+    }
+}
@@ -0,0 +1,108 @@
+//! Randomized delay of the first server contact
+//!
+//! When a whole fleet boots at once, e.g. after a datacenter restart, every
+//! agent would claim, fetch its configuration and report at the same moment.
+//! With `server.startup_splay_secs` set, the first server contact is delayed
+//! by a random time within that window, spreading the load over it.
+//!
+//! The delay is waited once per process, by whichever startup step comes
+//! first: the binary waits before claiming and syncing endpoints, the agent
+//! before its preflight check when it is started on its own (as a service).
+
+use rand::rngs::StdRng;
+use rand::RngExt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+/// Whether this process already waited out its startup splay
+static SPLAYED: AtomicBool = AtomicBool::new(false);
+
+/// Delay the first server contact by a random time within `window`, unless
+/// `shutdown` resolves first.
+///
+/// Returns `false` if the wait was interrupted by `shutdown`. Only the first
+/// call in a process waits; later calls return `true` at once.
+pub async fn wait_startup_splay(window: Duration, shutdown: impl Future<Output = ()>) -> bool {
+    if window.is_zero() || SPLAYED.swap(true, Ordering::SeqCst) {
+        return true;
+    }
+    wait_splay(splay_delay(window, &mut rand::make_rng()), shutdown).await
+}
+
+/// Random delay within `window`, at millisecond resolution
+fn splay_delay(window: Duration, rng: &mut StdRng) -> Duration {
+    let window_ms = u64::try_from(window.as_millis()).unwrap_or(u64::MAX);
+    Duration::from_millis(rng.random_range(0..=window_ms))
+}
+
+async fn wait_splay(delay: Duration, shutdown: impl Future<Output = ()>) -> bool {
+    info!(
+        "Delaying first server contact by {:?} (startup splay)",
+        delay
+    );
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => true,
+        _ = shutdown => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{shutdown_channel, trigger_shutdown, wait_for_shutdown};
+    use rand::SeedableRng;
+    use std::time::Instant;
+
+    #[test]
+    fn test_delay_stays_within_window_and_varies() {
+        let window = Duration::from_secs(30);
+        let delays: Vec<_> = (0..100)
+            .map(|seed| splay_delay(window, &mut StdRng::seed_from_u64(seed)))
+            .collect();
+
+        assert!(delays.iter().all(|delay| *delay <= window));
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+        assert_eq!(
+            splay_delay(window, &mut StdRng::seed_from_u64(7)),
+            splay_delay(window, &mut StdRng::seed_from_u64(7))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_first_contact_waits_for_the_seeded_delay() {
+        let window = Duration::from_millis(400);
+        let delay = splay_delay(window, &mut StdRng::seed_from_u64(42));
+        let (_shutdown_tx, mut shutdown_rx) = shutdown_channel();
+
+        let start = Instant::now();
+        assert!(wait_splay(delay, wait_for_shutdown(&mut shutdown_rx)).await);
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= delay, "waited {:?} of {:?}", elapsed, delay);
+        assert!(
+            elapsed < window + Duration::from_millis(200),
+            "waited {:?}, window {:?}",
+            elapsed,
+            window
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_interrupts_the_splay() {
+        let (shutdown_tx, mut shutdown_rx) = shutdown_channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            trigger_shutdown(&shutdown_tx);
+        });
+
+        let waited = tokio::time::timeout(
+            Duration::from_secs(5),
+            wait_splay(Duration::from_secs(60), wait_for_shutdown(&mut shutdown_rx)),
+        )
+        .await
+        .expect("shutdown must end the splay");
+        assert!(!waited);
+    }
+}
@@ -0,0 +1,91 @@
+//! Persistent agent state that survives restarts
+//!
+//! Stored as JSON in `StorageConfig::state_file()`. Unknown or missing fields
+//! fall back to their defaults so the file can grow without migrations.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Agent state persisted between runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentState {
+    /// Number of times the agent has been started
+    pub restart_count: u64,
+
+    /// Hostname generated because the system hostname could not be
+    /// determined, reused across restarts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub synthetic_hostname: Option<String>,
+}
+
+impl AgentState {
+    /// Load state from `path`, returning the default state if it does not exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                Error::Config(format!(
+                    "Failed to parse state file {}: {}",
+                    path.display(),
+                    e
+                ))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save state to `path`, replacing the previous file atomically
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_missing_file_is_default() {
+        let dir = TempDir::new().unwrap();
+        let state = AgentState::load(dir.path().join("agent_state.json")).unwrap();
+        assert_eq!(state, AgentState::default());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested/agent_state.json");
+
+        AgentState {
+            restart_count: 7,
+            ..AgentState::default()
+        }
+        .save(&path)
+        .unwrap();
+
+        assert_eq!(AgentState::load(&path).unwrap().restart_count, 7);
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_load_corrupt_file_fails() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("agent_state.json");
+        fs::write(&path, "not json").unwrap();
+
+        assert!(AgentState::load(&path).is_err());
+    }
+}
@@ -0,0 +1,988 @@
+//! Check targets derived from endpoint addresses
+//!
+//! An endpoint `address` is either a plain IP address / hostname, which is
+//! pinged, or a full URL such as `https://api.example.com/health`, which is
+//! split into scheme, host, port and path and checked over HTTP(S).
+//!
+//! `grpc://host:port/service.Name` (or `grpcs://` for TLS) addresses are
+//! checked with the standard gRPC health checking protocol. The optional
+//! path names the service to query; without it the server's overall health
+//! is requested.
+//!
+//! `tcp://host:port` addresses are checked by opening a TCP connection and
+//! `udp://host:port` addresses by sending a probe datagram. The port may also
+//! come from the endpoint's `port` field.
+//!
+//! `dns://nameserver[:port]/name` addresses query the nameserver for records
+//! of `name`. A plain hostname with `check_type = "dns"` is queried through
+//! the agent's resolver instead.
+//!
+//! A plain address can select another check with the endpoint's
+//! `check_type`, e.g. a TCP connect to `port` of a hostname. URLs already
+//! select their check by scheme; a `check_type` that contradicts it is
+//! rejected by [`Endpoint::validate`]. The exception is `tls_cert`, which
+//! checks the certificate of an `https://`, `grpcs://` or `tcp://` URL
+//! instead of the service behind it.
+//!
+//! An endpoint naming a `plugin` is checked by that plugin, whatever its
+//! address. The address is passed to the plugin as-is, so it may use a
+//! scheme of its own; host, port and path are still taken from URLs the
+//! agent knows.
+
+use crate::core::{CheckType, Endpoint, EndpointCheckType, MonitoringResult, SuccessQuorum};
+use crate::error::{Error, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use url::{Host, Url};
+
+/// Kind of check run against an endpoint
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    /// ICMP echo to the host
+    Ping,
+
+    /// HTTP(S) GET against a URL
+    Http,
+
+    /// gRPC `grpc.health.v1.Health/Check` call
+    Grpc,
+
+    /// TCP connection to a port
+    Tcp,
+
+    /// UDP probe datagram to a port
+    Udp,
+
+    /// Expiry of the TLS certificate presented on a port
+    TlsCert,
+
+    /// DNS query for records of a name
+    Dns,
+
+    /// Check performed by a registered plugin
+    Plugin,
+}
+
+impl fmt::Display for CheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ping => write!(f, "ping"),
+            Self::Http => write!(f, "http"),
+            Self::Grpc => write!(f, "grpc"),
+            Self::Tcp => write!(f, "tcp"),
+            Self::Udp => write!(f, "udp"),
+            Self::TlsCert => write!(f, "tls_cert"),
+            Self::Dns => write!(f, "dns"),
+            Self::Plugin => write!(f, "plugin"),
+        }
+    }
+}
+
+impl From<EndpointCheckType> for CheckKind {
+    fn from(check_type: EndpointCheckType) -> Self {
+        match check_type {
+            EndpointCheckType::Ping => Self::Ping,
+            EndpointCheckType::Tcp => Self::Tcp,
+            EndpointCheckType::Udp => Self::Udp,
+            EndpointCheckType::Http => Self::Http,
+            EndpointCheckType::Grpc => Self::Grpc,
+            EndpointCheckType::TlsCert => Self::TlsCert,
+            EndpointCheckType::Dns => Self::Dns,
+        }
+    }
+}
+
+/// Where and how an endpoint is checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckTarget {
+    /// Check kind selected for the endpoint
+    pub kind: CheckKind,
+
+    /// URL scheme (`http`, `https`, `grpc`, `grpcs`, `tcp` or `udp`). Plain
+    /// addresses have `http` or `grpc` when their `check_type` selects one of
+    /// those checks, `None` otherwise.
+    pub scheme: Option<String>,
+
+    /// Hostname or IP address, without IPv6 brackets
+    pub host: String,
+
+    /// Explicit port from the URL, or the endpoint's `port` field
+    pub port: Option<u16>,
+
+    /// Path and query for URL checks, empty for plain addresses
+    pub path: String,
+}
+
+impl CheckTarget {
+    /// Full URL for HTTP checks, `None` for other kinds
+    pub fn url(&self) -> Option<String> {
+        if self.kind != CheckKind::Http {
+            return None;
+        }
+        let scheme = self.scheme.as_deref()?;
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+
+        Some(match self.port {
+            Some(port) => format!("{}://{}:{}{}", scheme, host, port, self.path),
+            None => format!("{}://{}{}", scheme, host, self.path),
+        })
+    }
+
+    /// Service name for gRPC health checks, `None` for other kinds.
+    ///
+    /// An empty name asks for the overall health of the server.
+    pub fn grpc_service(&self) -> Option<&str> {
+        (self.kind == CheckKind::Grpc).then(|| self.path.trim_start_matches('/'))
+    }
+
+    /// Name queried by DNS checks, `None` for other kinds: the path of a
+    /// `dns://` URL, or else the host
+    pub fn dns_name(&self) -> Option<&str> {
+        if self.kind != CheckKind::Dns {
+            return None;
+        }
+        Some(match self.scheme {
+            Some(_) => self.path.trim_start_matches('/'),
+            None => &self.host,
+        })
+    }
+
+    /// Derive the check target from an endpoint's address and port.
+    ///
+    /// Addresses that are not `http(s)://`, `grpc(s)://`, `tcp://`, `udp://`
+    /// or `dns://` URLs are checked as selected by `check_type`, and keep the
+    /// historical behaviour of being pinged as-is without one. Endpoints
+    /// naming a `plugin` are checked by it.
+    fn from_endpoint(endpoint: &Endpoint) -> Self {
+        let port = endpoint.port.and_then(|p| u16::try_from(p).ok());
+
+        let mut target = match parse_url(&endpoint.address) {
+            Some((kind, url)) => {
+                let host = match url.host() {
+                    Some(Host::Ipv6(ip)) => ip.to_string(),
+                    Some(host) => host.to_string(),
+                    None => String::new(),
+                };
+                let path = match url.query() {
+                    Some(query) => format!("{}?{}", url.path(), query),
+                    None => url.path().to_string(),
+                };
+
+                // The certificate check keeps the URL's host and port
+                let kind = match endpoint.check_type {
+                    Some(EndpointCheckType::TlsCert) => CheckKind::TlsCert,
+                    _ => kind,
+                };
+
+                Self {
+                    kind,
+                    scheme: Some(url.scheme().to_string()),
+                    host,
+                    port: url.port().or(port),
+                    path,
+                }
+            }
+            None => {
+                let kind = endpoint.check_type.map_or(CheckKind::Ping, CheckKind::from);
+                let (scheme, path) = match kind {
+                    CheckKind::Http => (Some("http"), "/"),
+                    CheckKind::Grpc => (Some("grpc"), ""),
+                    _ => (None, ""),
+                };
+
+                Self {
+                    kind,
+                    scheme: scheme.map(str::to_string),
+                    host: endpoint.address.clone(),
+                    port,
+                    path: path.to_string(),
+                }
+            }
+        };
+        if endpoint.plugin.is_some() {
+            target.kind = CheckKind::Plugin;
+        }
+        target
+    }
+}
+
+/// Parse `address` as an HTTP(S), gRPC, TCP, UDP or DNS URL with a host
+fn parse_url(address: &str) -> Option<(CheckKind, Url)> {
+    if !address.contains("://") {
+        return None;
+    }
+
+    let url = Url::parse(address).ok()?;
+    let kind = match url.scheme() {
+        "http" | "https" => CheckKind::Http,
+        "grpc" | "grpcs" => CheckKind::Grpc,
+        "tcp" => CheckKind::Tcp,
+        "udp" => CheckKind::Udp,
+        "dns" => CheckKind::Dns,
+        _ => return None,
+    };
+    url.host().is_some().then_some((kind, url))
+}
+
+impl Endpoint {
+    /// Check target derived from this endpoint's address
+    pub fn check_target(&self) -> CheckTarget {
+        CheckTarget::from_endpoint(self)
+    }
+
+    /// Check kind derived from this endpoint's address
+    pub fn check_kind(&self) -> CheckKind {
+        self.check_target().kind
+    }
+
+    /// Validate this endpoint on its own, without a surrounding `Config`.
+    ///
+    /// The address must be an IP address, a hostname, or an `http(s)://`,
+    /// `grpc(s)://`, `tcp://`, `udp://` or `dns://` URL with a host, unless a `plugin`
+    /// checks the endpoint. Settings that depend on the monitoring
+    /// configuration (such as a quorum exceeding `ping_count`) are checked
+    /// by `Config::validate`.
+    pub fn validate(&self) -> Result<()> {
+        let address = self.address.trim();
+        if address.is_empty() {
+            return Err(Error::Config(
+                "endpoint address cannot be empty".to_string(),
+            ));
+        }
+
+        if let Some(port) = self.port {
+            if !(1..=65535).contains(&port) {
+                return Err(Error::Config(format!(
+                    "endpoint {} port {} must be between 1 and 65535",
+                    address, port
+                )));
+            }
+        }
+
+        if let Some(plugin) = &self.plugin {
+            if plugin.trim().is_empty() {
+                return Err(Error::Config(format!(
+                    "endpoint {} plugin cannot be empty",
+                    address
+                )));
+            }
+            if self.check_type.is_some() {
+                return Err(Error::Config(format!(
+                    "endpoint {} cannot set both plugin and check_type",
+                    address
+                )));
+            }
+        } else if address.contains("://") {
+            let Some((kind, url)) = parse_url(address) else {
+                return Err(Error::Config(format!(
+                    "endpoint {} is not a valid http(s)://, grpc(s)://, tcp://, udp:// or dns:// URL with a host",
+                    address
+                )));
+            };
+            if self.check_type == Some(EndpointCheckType::TlsCert) {
+                if !matches!(url.scheme(), "https" | "grpcs" | "tcp") {
+                    return Err(Error::Config(format!(
+                        "endpoint {} check_type tls_cert requires an https://, grpcs:// or tcp:// URL",
+                        address
+                    )));
+                }
+            } else if let Some(check_type) = self.check_type {
+                if CheckKind::from(check_type) != kind {
+                    return Err(Error::Config(format!(
+                        "endpoint {} check_type {} contradicts its {} URL",
+                        address,
+                        CheckKind::from(check_type),
+                        kind
+                    )));
+                }
+            }
+            if kind == CheckKind::Dns && url.path().trim_start_matches('/').is_empty() {
+                return Err(Error::Config(format!(
+                    "endpoint {} dns:// URL needs a name to query, e.g. dns://10.0.0.53/example.com",
+                    address
+                )));
+            }
+        } else if address.parse::<IpAddr>().is_err() {
+            if is_cidr(address) {
+                return Err(Error::Config(format!(
+                    "endpoint {} is a network range; CIDR addresses are not supported",
+                    address
+                )));
+            }
+            if Host::parse(address).is_err() {
+                return Err(Error::Config(format!(
+                    "endpoint {} is not a valid IP address, hostname or URL",
+                    address
+                )));
+            }
+        }
+
+        let target = self.check_target();
+        if matches!(target.kind, CheckKind::Tcp | CheckKind::Udp) && target.port.is_none() {
+            return Err(Error::Config(format!(
+                "endpoint {} {} check needs a port: set port or include it in the address",
+                address, target.kind
+            )));
+        }
+
+        if let Some(quorum) = &self.success_quorum {
+            quorum.parse::<SuccessQuorum>()?;
+        }
+
+        if self.interval_secs.is_some_and(|secs| secs <= 0) {
+            return Err(Error::Config(format!(
+                "endpoint {} interval_secs must be greater than 0",
+                address
+            )));
+        }
+
+        if self
+            .server_name
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err(Error::Config(format!(
+                "endpoint {} server_name cannot be empty",
+                address
+            )));
+        }
+
+        if self.body_must_contain.is_some() || self.body_regex.is_some() {
+            if self.check_kind() != CheckKind::Http {
+                return Err(Error::Config(format!(
+                    "endpoint {} body_must_contain and body_regex require an http(s):// URL",
+                    address
+                )));
+            }
+            if let Some(pattern) = &self.body_regex {
+                regex::Regex::new(pattern).map_err(|e| {
+                    Error::Config(format!("endpoint {} body_regex is invalid: {}", address, e))
+                })?;
+            }
+        }
+
+        if let Some(warn_days) = self.warn_days {
+            if self.check_kind() != CheckKind::TlsCert {
+                return Err(Error::Config(format!(
+                    "endpoint {} warn_days requires check_type tls_cert",
+                    address
+                )));
+            }
+            if warn_days < 0 {
+                return Err(Error::Config(format!(
+                    "endpoint {} warn_days cannot be negative",
+                    address
+                )));
+            }
+        }
+
+        if self
+            .check_target()
+            .dns_name()
+            .is_some_and(|name| name.parse::<IpAddr>().is_ok())
+        {
+            return Err(Error::Config(format!(
+                "endpoint {} DNS check needs a name to query, not an IP address",
+                address
+            )));
+        }
+
+        if self.record_type.is_some() || !self.expected_answers.is_empty() {
+            if self.check_kind() != CheckKind::Dns {
+                return Err(Error::Config(format!(
+                    "endpoint {} record_type and expected_answers require a DNS check",
+                    address
+                )));
+            }
+            if self
+                .expected_answers
+                .iter()
+                .any(|answer| answer.trim().is_empty())
+            {
+                return Err(Error::Config(format!(
+                    "endpoint {} expected_answers cannot contain an empty answer",
+                    address
+                )));
+            }
+        }
+
+        if let Some(http) = &self.http {
+            if self.check_kind() != CheckKind::Http {
+                return Err(Error::Config(format!(
+                    "endpoint {} http settings require an http(s):// URL",
+                    address
+                )));
+            }
+            if let Some(method) = &http.method {
+                if reqwest::Method::from_bytes(method.to_ascii_uppercase().as_bytes()).is_err() {
+                    return Err(Error::Config(format!(
+                        "endpoint {} http.method {:?} is not a valid HTTP method",
+                        address, method
+                    )));
+                }
+            }
+            for (name, value) in &http.headers {
+                if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                    return Err(Error::Config(format!(
+                        "endpoint {} http header {:?} is not a valid header name",
+                        address, name
+                    )));
+                }
+                // The value may be a credential and is not echoed
+                if reqwest::header::HeaderValue::from_str(value).is_err() {
+                    return Err(Error::Config(format!(
+                        "endpoint {} http header {} has an invalid value",
+                        address, name
+                    )));
+                }
+            }
+            if let Some(status) = http
+                .expected_status
+                .iter()
+                .find(|status| !(100..=599).contains(*status))
+            {
+                return Err(Error::Config(format!(
+                    "endpoint {} http.expected_status {} must be between 100 and 599",
+                    address, status
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `address` is written as `ip/prefix`
+fn is_cidr(address: &str) -> bool {
+    address
+        .split_once('/')
+        .is_some_and(|(ip, prefix)| ip.parse::<IpAddr>().is_ok() && prefix.parse::<u8>().is_ok())
+}
+
+impl MonitoringResult {
+    /// Check kind that produced this result, `None` for result types no
+    /// check kind produces yet
+    pub fn check_kind(&self) -> Option<CheckKind> {
+        match &self.check_type {
+            CheckType::PingCheck(_) => Some(CheckKind::Ping),
+            CheckType::HttpGetCheck(_) => Some(CheckKind::Http),
+            CheckType::GrpcHealthCheck(_) => Some(CheckKind::Grpc),
+            CheckType::TcpConnectCheck(_) => Some(CheckKind::Tcp),
+            CheckType::UdpConnectCheck(_) => Some(CheckKind::Udp),
+            CheckType::TlsCertCheck(_) => Some(CheckKind::TlsCert),
+            CheckType::DnsCheck(_) => Some(CheckKind::Dns),
+            CheckType::PluginCheck(_) => Some(CheckKind::Plugin),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DnsRecordType;
+
+    #[test]
+    fn test_https_url_with_path() {
+        let target = Endpoint::new("https://api.example.com/health").check_target();
+
+        assert_eq!(target.kind, CheckKind::Http);
+        assert_eq!(target.scheme.as_deref(), Some("https"));
+        assert_eq!(target.host, "api.example.com");
+        assert_eq!(target.port, None);
+        assert_eq!(target.path, "/health");
+        assert_eq!(
+            target.url().as_deref(),
+            Some("https://api.example.com/health")
+        );
+    }
+
+    #[test]
+    fn test_http_url_with_port() {
+        let target = Endpoint::new("http://host:8080").check_target();
+
+        assert_eq!(target.kind, CheckKind::Http);
+        assert_eq!(target.scheme.as_deref(), Some("http"));
+        assert_eq!(target.host, "host");
+        assert_eq!(target.port, Some(8080));
+        assert_eq!(target.path, "/");
+        assert_eq!(target.url().as_deref(), Some("http://host:8080/"));
+    }
+
+    #[test]
+    fn test_bare_hostname_keeps_ping() {
+        let target = Endpoint::new("example.com").with_port(443).check_target();
+
+        assert_eq!(target.kind, CheckKind::Ping);
+        assert_eq!(target.scheme, None);
+        assert_eq!(target.host, "example.com");
+        assert_eq!(target.port, Some(443));
+        assert_eq!(target.path, "");
+        assert_eq!(target.url(), None);
+    }
+
+    #[test]
+    fn test_bare_ip_keeps_ping() {
+        let endpoint = Endpoint::new("8.8.8.8");
+        assert_eq!(endpoint.check_kind(), CheckKind::Ping);
+        assert_eq!(endpoint.check_target().host, "8.8.8.8");
+    }
+
+    #[test]
+    fn test_url_query_is_kept() {
+        let target = Endpoint::new("https://example.com/status?verbose=1").check_target();
+        assert_eq!(target.path, "/status?verbose=1");
+    }
+
+    #[test]
+    fn test_endpoint_port_used_when_url_has_none() {
+        let target = Endpoint::new("http://example.com/")
+            .with_port(8081)
+            .check_target();
+        assert_eq!(target.port, Some(8081));
+        assert_eq!(target.url().as_deref(), Some("http://example.com:8081/"));
+    }
+
+    #[test]
+    fn test_ipv6_url_host_is_unbracketed() {
+        let target = Endpoint::new("http://[::1]:9000/ready").check_target();
+        assert_eq!(target.host, "::1");
+        assert_eq!(target.url().as_deref(), Some("http://[::1]:9000/ready"));
+    }
+
+    #[test]
+    fn test_grpc_url_with_service() {
+        let target = Endpoint::new("grpc://10.0.0.5:50051/payments.v1.Payments").check_target();
+
+        assert_eq!(target.kind, CheckKind::Grpc);
+        assert_eq!(target.scheme.as_deref(), Some("grpc"));
+        assert_eq!(target.host, "10.0.0.5");
+        assert_eq!(target.port, Some(50051));
+        assert_eq!(target.grpc_service(), Some("payments.v1.Payments"));
+        assert_eq!(target.url(), None);
+    }
+
+    #[test]
+    fn test_grpcs_url_without_service() {
+        let target = Endpoint::new("grpcs://api.example.com:443").check_target();
+
+        assert_eq!(target.kind, CheckKind::Grpc);
+        assert_eq!(target.scheme.as_deref(), Some("grpcs"));
+        assert_eq!(target.grpc_service(), Some(""));
+    }
+
+    #[test]
+    fn test_grpc_service_is_none_for_http() {
+        let target = Endpoint::new("https://example.com/health").check_target();
+        assert_eq!(target.grpc_service(), None);
+    }
+
+    #[test]
+    fn test_tcp_url_with_port() {
+        let target = Endpoint::new("tcp://db.example.com:5432").check_target();
+
+        assert_eq!(target.kind, CheckKind::Tcp);
+        assert_eq!(target.scheme.as_deref(), Some("tcp"));
+        assert_eq!(target.host, "db.example.com");
+        assert_eq!(target.port, Some(5432));
+        assert_eq!(target.url(), None);
+        assert_eq!(target.grpc_service(), None);
+
+        let target = Endpoint::new("tcp://[::1]").with_port(22).check_target();
+        assert_eq!(target.host, "::1");
+        assert_eq!(target.port, Some(22));
+    }
+
+    #[test]
+    fn test_udp_url_with_port() {
+        let target = Endpoint::new("udp://10.0.0.53:53").check_target();
+
+        assert_eq!(target.kind, CheckKind::Udp);
+        assert_eq!(target.scheme.as_deref(), Some("udp"));
+        assert_eq!(target.host, "10.0.0.53");
+        assert_eq!(target.port, Some(53));
+        assert_eq!(target.url(), None);
+    }
+
+    #[test]
+    fn test_check_type_selects_check_of_plain_address() {
+        let endpoint = Endpoint::new("db.example.com").with_port(5432);
+        assert_eq!(endpoint.check_kind(), CheckKind::Ping);
+
+        let target = endpoint
+            .clone()
+            .with_check_type(EndpointCheckType::Tcp)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Tcp);
+        assert_eq!(target.scheme, None);
+        assert_eq!(target.host, "db.example.com");
+        assert_eq!(target.port, Some(5432));
+
+        let target = endpoint
+            .clone()
+            .with_check_type(EndpointCheckType::Udp)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Udp);
+
+        let target = endpoint
+            .clone()
+            .with_check_type(EndpointCheckType::Http)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Http);
+        assert_eq!(target.url().as_deref(), Some("http://db.example.com:5432/"));
+
+        let target = Endpoint::new("::1")
+            .with_check_type(EndpointCheckType::Grpc)
+            .with_port(50051)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Grpc);
+        assert_eq!(target.scheme.as_deref(), Some("grpc"));
+        assert_eq!(target.grpc_service(), Some(""));
+
+        let target = endpoint
+            .with_check_type(EndpointCheckType::Ping)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Ping);
+    }
+
+    #[test]
+    fn test_validate_check_type_must_match_url() {
+        let endpoint = Endpoint::new("https://api.example.com/health");
+        assert!(endpoint
+            .clone()
+            .with_check_type(EndpointCheckType::Http)
+            .validate()
+            .is_ok());
+
+        let err = endpoint
+            .with_check_type(EndpointCheckType::Tcp)
+            .validate()
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("check_type tcp contradicts its http URL"),
+            "{}",
+            err
+        );
+
+        let endpoint = Endpoint::new("10.0.0.1")
+            .with_check_type(EndpointCheckType::Udp)
+            .with_port(53);
+        assert!(endpoint.validate().is_ok());
+    }
+
+    #[test]
+    fn test_tls_cert_check_type_keeps_url_host_and_port() {
+        let target = Endpoint::new("https://example.com:8443/health")
+            .with_check_type(EndpointCheckType::TlsCert)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::TlsCert);
+        assert_eq!(target.host, "example.com");
+        assert_eq!(target.port, Some(8443));
+        assert_eq!(target.url(), None);
+
+        let target = Endpoint::new("mail.example.com")
+            .with_check_type(EndpointCheckType::TlsCert)
+            .with_port(993)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::TlsCert);
+        assert_eq!(target.scheme, None);
+        assert_eq!(target.port, Some(993));
+    }
+
+    #[test]
+    fn test_validate_tls_cert() {
+        for address in ["https://example.com", "grpcs://example.com", "example.com"] {
+            let endpoint = Endpoint::new(address)
+                .with_check_type(EndpointCheckType::TlsCert)
+                .with_warn_days(14);
+            assert!(endpoint.validate().is_ok(), "{}", address);
+        }
+
+        let err = Endpoint::new("http://example.com")
+            .with_check_type(EndpointCheckType::TlsCert)
+            .validate()
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("check_type tls_cert requires an https://, grpcs:// or tcp:// URL"),
+            "{}",
+            err
+        );
+
+        let err = Endpoint::new("https://example.com")
+            .with_warn_days(14)
+            .validate()
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("warn_days requires check_type tls_cert"),
+            "{}",
+            err
+        );
+
+        let mut endpoint = Endpoint::new("example.com").with_check_type(EndpointCheckType::TlsCert);
+        endpoint.warn_days = Some(-1);
+        let err = endpoint.validate().unwrap_err();
+        assert!(
+            err.to_string().contains("warn_days cannot be negative"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_dns_url_and_check_type() {
+        let target = Endpoint::new("dns://10.0.0.53:5353/example.com").check_target();
+        assert_eq!(target.kind, CheckKind::Dns);
+        assert_eq!(target.host, "10.0.0.53");
+        assert_eq!(target.port, Some(5353));
+        assert_eq!(target.dns_name(), Some("example.com"));
+        assert_eq!(target.url(), None);
+
+        let target = Endpoint::new("example.com")
+            .with_check_type(EndpointCheckType::Dns)
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Dns);
+        assert_eq!(target.dns_name(), Some("example.com"));
+
+        assert_eq!(Endpoint::new("example.com").check_target().dns_name(), None);
+    }
+
+    #[test]
+    fn test_validate_dns() {
+        assert!(Endpoint::new("dns://10.0.0.53/example.com")
+            .with_record_type(DnsRecordType::Mx)
+            .with_expected_answers(vec!["10 mail.example.com".to_string()])
+            .validate()
+            .is_ok());
+
+        let cases = [
+            (
+                Endpoint::new("dns://10.0.0.53"),
+                "dns:// URL needs a name to query",
+            ),
+            (
+                Endpoint::new("10.0.0.53").with_check_type(EndpointCheckType::Dns),
+                "needs a name to query, not an IP address",
+            ),
+            (
+                Endpoint::new("example.com").with_record_type(DnsRecordType::Aaaa),
+                "record_type and expected_answers require a DNS check",
+            ),
+            (
+                Endpoint::new("example.com")
+                    .with_check_type(EndpointCheckType::Dns)
+                    .with_expected_answers(vec![" ".to_string()]),
+                "expected_answers cannot contain an empty answer",
+            ),
+        ];
+        for (endpoint, message) in cases {
+            let err = endpoint.validate().unwrap_err();
+            assert!(err.to_string().contains(message), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_plugin_selects_plugin_check() {
+        let target = Endpoint::new("ldap://ldap.example.com/dc=example,dc=com")
+            .with_plugin("ldap_probe")
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Plugin);
+        assert_eq!(target.host, "ldap://ldap.example.com/dc=example,dc=com");
+
+        let target = Endpoint::new("https://api.example.com/health")
+            .with_plugin("http_probe")
+            .check_target();
+        assert_eq!(target.kind, CheckKind::Plugin);
+        assert_eq!(target.host, "api.example.com");
+        assert_eq!(target.url(), None);
+    }
+
+    #[test]
+    fn test_validate_plugin() {
+        assert!(Endpoint::new("ldap://ldap.example.com/dc=example,dc=com")
+            .with_plugin("ldap_probe")
+            .validate()
+            .is_ok());
+
+        let err = Endpoint::new("10.0.0.1")
+            .with_plugin(" ")
+            .validate()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("plugin cannot be empty"),
+            "{}",
+            err
+        );
+
+        let err = Endpoint::new("10.0.0.1")
+            .with_plugin("ldap_probe")
+            .with_check_type(EndpointCheckType::Tcp)
+            .validate()
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("cannot set both plugin and check_type"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_non_http_scheme_keeps_ping() {
+        let endpoint = Endpoint::new("ftp://files.example.com");
+        assert_eq!(endpoint.check_kind(), CheckKind::Ping);
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_endpoints() {
+        for address in [
+            "10.0.0.1",
+            "2001:db8::1",
+            "db.example.com",
+            "https://api.example.com/health",
+            "grpc://10.0.0.5:50051/payments.v1.Payments",
+            "tcp://db.example.com:5432",
+            "udp://10.0.0.53:53",
+        ] {
+            let endpoint = Endpoint::new(address).with_port(443);
+            assert!(endpoint.validate().is_ok(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_address() {
+        for address in ["", "   "] {
+            let err = Endpoint::new(address).validate().unwrap_err();
+            assert!(
+                err.to_string().contains("address cannot be empty"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_port_zero() {
+        let mut endpoint = Endpoint::new("10.0.0.1");
+        endpoint.port = Some(0);
+        let err = endpoint.validate().unwrap_err();
+        assert!(err.to_string().contains("between 1 and 65535"), "{}", err);
+
+        endpoint.port = Some(65536);
+        assert!(endpoint.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_body_assertions() {
+        let endpoint = Endpoint::new("https://api.example.com/health")
+            .with_body_must_contain("ok")
+            .with_body_regex(r"status.\s*ok");
+        assert!(endpoint.validate().is_ok());
+
+        let err = Endpoint::new("https://api.example.com/health")
+            .with_body_regex("(unclosed")
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("body_regex is invalid"), "{}", err);
+
+        let err = Endpoint::new("10.0.0.1")
+            .with_body_must_contain("ok")
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("require an http(s)"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_http_settings() {
+        let valid = crate::core::HttpCheckConfig {
+            method: Some("head".to_string()),
+            headers: [("Authorization".to_string(), "Bearer token".to_string())].into(),
+            expected_status: vec![200, 204],
+            follow_redirects: false,
+        };
+        let endpoint = Endpoint::new("https://api.example.com/health").with_http(valid.clone());
+        assert!(endpoint.validate().is_ok());
+
+        for (http, message) in [
+            (
+                crate::core::HttpCheckConfig {
+                    method: Some("GE T".to_string()),
+                    ..valid.clone()
+                },
+                "is not a valid HTTP method",
+            ),
+            (
+                crate::core::HttpCheckConfig {
+                    headers: [("Bad Name".to_string(), "x".to_string())].into(),
+                    ..valid.clone()
+                },
+                "is not a valid header name",
+            ),
+            (
+                crate::core::HttpCheckConfig {
+                    headers: [("X-Token".to_string(), "secret\n".to_string())].into(),
+                    ..valid.clone()
+                },
+                "http header X-Token has an invalid value",
+            ),
+            (
+                crate::core::HttpCheckConfig {
+                    expected_status: vec![200, 600],
+                    ..valid.clone()
+                },
+                "expected_status 600 must be between 100 and 599",
+            ),
+        ] {
+            let err = Endpoint::new("https://api.example.com/health")
+                .with_http(http)
+                .validate()
+                .unwrap_err();
+            assert!(err.to_string().contains(message), "{}", err);
+            assert!(!err.to_string().contains("secret"), "{}", err);
+        }
+
+        let err = Endpoint::new("10.0.0.1")
+            .with_http(valid)
+            .validate()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("http settings require an http(s)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_urls_and_addresses() {
+        for address in [
+            "https://",
+            "http://exa mple.com",
+            "ftp://files.example.com",
+            "not a host",
+            "10.0.0.0/24",
+        ] {
+            assert!(Endpoint::new(address).validate().is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_quorum_and_server_name() {
+        let mut endpoint = Endpoint::new("10.0.0.1");
+        endpoint.success_quorum = Some("0%".to_string());
+        assert!(endpoint.validate().is_err());
+
+        let endpoint = Endpoint::new("https://10.0.0.1/").with_server_name(" ");
+        assert!(endpoint.validate().is_err());
+    }
+}
@@ -0,0 +1,374 @@
+//! Latency thresholds and result classification
+//!
+//! A result is classified as [`Classification::Ok`], `Warning` or
+//! `Critical` by comparing its primary response time with the warning and
+//! critical thresholds of its endpoint. Thresholds can be set on the
+//! endpoint itself (`warning_ms`, `critical_ms`) or inherited from
+//! `[[thresholds]]` entries matching one of the endpoint's tags. Each value
+//! is resolved separately: the endpoint's own value wins, otherwise the
+//! first matching entry in config order that sets it applies.
+//!
+//! Ping results are also rated by packet loss, against the
+//! `packet_loss_warning_percent` and `packet_loss_critical_percent` of the
+//! matching `[[thresholds]]` entries. The worse of both ratings wins.
+//! [`MonitoringResult::metric_status`] maps the rating to the
+//! [`MetricStatus`] reported for the result.
+
+use super::{CheckType, Endpoint, MetricStatus, MonitoringResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Latency thresholds inherited by every endpoint carrying `tag`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TagThresholds {
+    /// Endpoint tag the thresholds apply to
+    pub tag: String,
+
+    /// Response time in milliseconds above which a result is a warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning_ms: Option<f64>,
+
+    /// Response time in milliseconds above which a result is critical
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub critical_ms: Option<f64>,
+
+    /// Ping packet loss in percent above which a result is a warning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_loss_warning_percent: Option<f64>,
+
+    /// Ping packet loss in percent above which a result is critical
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_loss_critical_percent: Option<f64>,
+}
+
+/// Effective thresholds of one endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Thresholds {
+    pub warning_ms: Option<f64>,
+    pub critical_ms: Option<f64>,
+    pub packet_loss_warning_percent: Option<f64>,
+    pub packet_loss_critical_percent: Option<f64>,
+}
+
+impl Thresholds {
+    /// Thresholds of `endpoint`: its own values, falling back to the first
+    /// entry of `tag_thresholds` matching one of its tags
+    pub fn resolve(endpoint: &Endpoint, tag_thresholds: &[TagThresholds]) -> Self {
+        let mut matching = tag_thresholds
+            .iter()
+            .filter(|entry| endpoint.tags.contains(&entry.tag));
+
+        Self {
+            warning_ms: endpoint
+                .warning_ms
+                .or_else(|| matching.clone().find_map(|entry| entry.warning_ms)),
+            critical_ms: endpoint
+                .critical_ms
+                .or_else(|| matching.clone().find_map(|entry| entry.critical_ms)),
+            packet_loss_warning_percent: matching
+                .clone()
+                .find_map(|entry| entry.packet_loss_warning_percent),
+            packet_loss_critical_percent: matching
+                .find_map(|entry| entry.packet_loss_critical_percent),
+        }
+    }
+}
+
+/// Rate `value` against optional warning and critical limits
+fn rate(value: f64, warning: Option<f64>, critical: Option<f64>) -> Classification {
+    if critical.is_some_and(|critical| value > critical) {
+        Classification::Critical
+    } else if warning.is_some_and(|warning| value > warning) {
+        Classification::Warning
+    } else {
+        Classification::Ok
+    }
+}
+
+/// Severity of a single result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Classification {
+    /// Successful and within its thresholds
+    Ok,
+
+    /// Successful, but above a warning threshold
+    Warning,
+
+    /// Failed, or above a critical threshold
+    Critical,
+}
+
+impl From<Classification> for MetricStatus {
+    fn from(classification: Classification) -> Self {
+        match classification {
+            Classification::Ok => MetricStatus::Reachable,
+            Classification::Warning => MetricStatus::Degraded,
+            Classification::Critical => MetricStatus::Unreachable,
+        }
+    }
+}
+
+impl MonitoringResult {
+    /// Classify the result against the effective thresholds of `endpoint`
+    ///
+    /// Failed checks are always critical. Results without a response time
+    /// are only classified by success, and ping results additionally by
+    /// packet loss.
+    pub fn classify(
+        &self,
+        endpoint: &Endpoint,
+        tag_thresholds: &[TagThresholds],
+    ) -> Classification {
+        if !self.is_successful() {
+            return Classification::Critical;
+        }
+
+        let thresholds = Thresholds::resolve(endpoint, tag_thresholds);
+        let latency = self
+            .response_time_ms()
+            .map_or(Classification::Ok, |latency| {
+                rate(latency, thresholds.warning_ms, thresholds.critical_ms)
+            });
+        let packet_loss = match &self.check_type {
+            CheckType::PingCheck(ping) => {
+                ping.result
+                    .packet_loss_percent()
+                    .map_or(Classification::Ok, |loss| {
+                        rate(
+                            loss,
+                            thresholds.packet_loss_warning_percent,
+                            thresholds.packet_loss_critical_percent,
+                        )
+                    })
+            }
+            _ => Classification::Ok,
+        };
+
+        latency.max(packet_loss)
+    }
+
+    /// Reported status of the result: [`MetricStatus::Reachable`] within
+    /// its thresholds, `Degraded` above a warning threshold and
+    /// `Unreachable` when failed or above a critical threshold
+    pub fn metric_status(
+        &self,
+        endpoint: &Endpoint,
+        tag_thresholds: &[TagThresholds],
+    ) -> MetricStatus {
+        self.classify(endpoint, tag_thresholds).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        HttpGetCheck, HttpGetCheckType, HttpGetResult, PingCheck, PingCheckType, PingResult,
+    };
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn http_result(success: bool, response_time_ms: Option<f64>) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::HttpGetCheck(HttpGetCheck {
+                r#type: HttpGetCheckType::Httpget,
+                result: HttpGetResult {
+                    status_code: if success { 200 } else { 503 },
+                    response_time_ms,
+                    dns_resolution_ms: None,
+                    response_size_bytes: None,
+                    body_truncated: None,
+                    body_matched: None,
+                    body_match_detail: None,
+                    error_details: None,
+                    success,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Ping of ten echo requests with `lost` unanswered and 5 ms replies
+    fn ping_result(lost: i64) -> MonitoringResult {
+        MonitoringResult {
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "127.0.0.1".to_string(),
+                    successes: 10 - lost,
+                    failures: lost,
+                    success_latencies: vec![5.0; (10 - lost) as usize],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            ..http_result(true, None)
+        }
+    }
+
+    fn tag(tag: &str, warning_ms: Option<f64>, critical_ms: Option<f64>) -> TagThresholds {
+        TagThresholds {
+            tag: tag.to_string(),
+            warning_ms,
+            critical_ms,
+            packet_loss_warning_percent: None,
+            packet_loss_critical_percent: None,
+        }
+    }
+
+    fn tagged(tags: &[&str]) -> Endpoint {
+        Endpoint::new("https://example.com/")
+            .with_tags(tags.iter().map(|t| t.to_string()).collect())
+    }
+
+    #[test]
+    fn test_thresholds_inherited_from_tag() {
+        let thresholds = [
+            tag("db", Some(50.0), Some(200.0)),
+            tag("web", Some(500.0), None),
+        ];
+        let endpoint = tagged(&["db"]);
+
+        assert_eq!(
+            http_result(true, Some(20.0)).classify(&endpoint, &thresholds),
+            Classification::Ok
+        );
+        assert_eq!(
+            http_result(true, Some(100.0)).classify(&endpoint, &thresholds),
+            Classification::Warning
+        );
+        assert_eq!(
+            http_result(true, Some(300.0)).classify(&endpoint, &thresholds),
+            Classification::Critical
+        );
+        assert_eq!(
+            http_result(true, Some(300.0)).classify(&tagged(&["other"]), &thresholds),
+            Classification::Ok
+        );
+    }
+
+    #[test]
+    fn test_endpoint_overrides_win_per_value() {
+        let thresholds = [tag("db", Some(50.0), Some(200.0))];
+        let mut endpoint = tagged(&["db"]);
+        endpoint.warning_ms = Some(150.0);
+
+        assert_eq!(
+            Thresholds::resolve(&endpoint, &thresholds),
+            Thresholds {
+                warning_ms: Some(150.0),
+                critical_ms: Some(200.0),
+                packet_loss_warning_percent: None,
+                packet_loss_critical_percent: None,
+            }
+        );
+        assert_eq!(
+            http_result(true, Some(100.0)).classify(&endpoint, &thresholds),
+            Classification::Ok
+        );
+        assert_eq!(
+            http_result(true, Some(250.0)).classify(&endpoint, &thresholds),
+            Classification::Critical
+        );
+    }
+
+    #[test]
+    fn test_first_matching_tag_sets_each_value() {
+        let thresholds = [
+            tag("web", Some(500.0), None),
+            tag("db", Some(50.0), Some(200.0)),
+        ];
+
+        assert_eq!(
+            Thresholds::resolve(&tagged(&["db", "web"]), &thresholds),
+            Thresholds {
+                warning_ms: Some(500.0),
+                critical_ms: Some(200.0),
+                packet_loss_warning_percent: None,
+                packet_loss_critical_percent: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_failures_are_critical_and_missing_latency_is_ok() {
+        let thresholds = [tag("db", Some(50.0), Some(200.0))];
+        let endpoint = tagged(&["db"]);
+
+        assert_eq!(
+            http_result(false, Some(10.0)).classify(&endpoint, &thresholds),
+            Classification::Critical
+        );
+        assert_eq!(
+            http_result(true, None).classify(&endpoint, &thresholds),
+            Classification::Ok
+        );
+    }
+
+    #[test]
+    fn test_metric_status_latency_boundaries() {
+        let thresholds = [tag("db", Some(100.0), Some(300.0))];
+        let endpoint = tagged(&["db"]);
+        let status =
+            |latency| http_result(true, Some(latency)).metric_status(&endpoint, &thresholds);
+
+        assert_eq!(status(50.0), MetricStatus::Reachable, "below warning");
+        assert_eq!(status(100.0), MetricStatus::Reachable, "at warning");
+        assert_eq!(status(200.0), MetricStatus::Degraded, "between thresholds");
+        assert_eq!(status(300.0), MetricStatus::Degraded, "at critical");
+        assert_eq!(status(400.0), MetricStatus::Unreachable, "above critical");
+        assert_eq!(
+            http_result(false, Some(50.0)).metric_status(&endpoint, &thresholds),
+            MetricStatus::Unreachable
+        );
+    }
+
+    #[test]
+    fn test_metric_status_packet_loss_boundaries() {
+        let mut thresholds = [tag("wan", None, None)];
+        thresholds[0].packet_loss_warning_percent = Some(10.0);
+        thresholds[0].packet_loss_critical_percent = Some(50.0);
+        let endpoint = tagged(&["wan"]);
+        let status = |lost| ping_result(lost).metric_status(&endpoint, &thresholds);
+
+        assert_eq!(status(0), MetricStatus::Reachable, "below warning");
+        assert_eq!(status(1), MetricStatus::Reachable, "at warning");
+        assert_eq!(status(3), MetricStatus::Degraded, "between thresholds");
+        assert_eq!(status(5), MetricStatus::Degraded, "at critical");
+        assert_eq!(status(6), MetricStatus::Unreachable, "above critical");
+        assert_eq!(
+            status(10),
+            MetricStatus::Unreachable,
+            "a ping without replies failed"
+        );
+    }
+
+    #[test]
+    fn test_worse_of_latency_and_packet_loss_wins() {
+        let mut thresholds = [tag("wan", Some(1.0), Some(100.0))];
+        thresholds[0].packet_loss_critical_percent = Some(50.0);
+        let endpoint = tagged(&["wan"]);
+
+        assert_eq!(
+            ping_result(0).classify(&endpoint, &thresholds),
+            Classification::Warning
+        );
+        assert_eq!(
+            ping_result(6).classify(&endpoint, &thresholds),
+            Classification::Critical
+        );
+    }
+}
@@ -1,29 +1,53 @@
 //! Common types used throughout the agent
 
+use super::SuccessQuorum;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
 use uuid::Uuid;
 
 // Re-export from generated OpenAPI types — these are the canonical wire-level types.
 pub use crate::openapi::{
-    AgentCacheStats, AgentHealthStatus, AgentHeartbeat, AgentMetrics, AgentStatus, CheckType,
-    Endpoint, ErrorDetails, HttpGetCheck, HttpGetCheckType, HttpGetResult, MonitoringResult,
-    PingCheck, PingCheckType, PingResult, PluginCheck, PluginCheckType, PluginResult,
-    TcpConnectCheck, TcpConnectCheckType, TcpConnectResult, TracerouteCheck, TracerouteCheckType,
-    TracerouteHop, TracerouteResult, UdpConnectCheck, UdpConnectCheckType, UdpConnectResult,
+    AgentCacheStats, AgentHealthStatus, AgentHeartbeat, AgentMetrics, AgentSkippedChecks,
+    AgentStatus, CheckType, DnsCheck, DnsCheckType, DnsRecordType, DnsResult, Endpoint,
+    EndpointCheckType, EndpointHealth, ErrorDetails, FailureKind, GrpcHealthCheck,
+    GrpcHealthCheckType, GrpcHealthResult, HttpCheckConfig, HttpGetCheck, HttpGetCheckType,
+    HttpGetResult, MetricStatus, MonitoringResult, PingCheck, PingCheckType, PingResult,
+    PluginCheck, PluginCheckType, PluginResult, SkipReason, TcpConnectCheck, TcpConnectCheckType,
+    TcpConnectResult, TlsCertCheck, TlsCertCheckType, TlsCertResult, TracerouteCheck,
+    TracerouteCheckType, TracerouteHop, TracerouteResult, UdpConnectCheck, UdpConnectCheckType,
+    UdpConnectResult,
 };
 
 impl MonitoringResult {
     /// Helper method to determine if the check was successful
     pub fn is_successful(&self) -> bool {
         match &self.check_type {
-            CheckType::PingCheck(c) => c.result.successes > 0,
+            CheckType::PingCheck(c) => {
+                c.result.successes >= c.result.required_successes.unwrap_or(1).max(1)
+            }
             CheckType::TracerouteCheck(c) => c.result.target_reached,
             CheckType::TcpConnectCheck(c) => c.result.connected,
             CheckType::UdpConnectCheck(c) => c.result.probe_successful,
             CheckType::HttpGetCheck(c) => c.result.success,
+            CheckType::GrpcHealthCheck(c) => c.result.success,
+            CheckType::TlsCertCheck(c) => c.result.valid,
+            CheckType::DnsCheck(c) => c.result.success,
             CheckType::PluginCheck(c) => c.result.success,
         }
     }
 
+    /// Whether the check ran during its endpoint's warmup window
+    pub fn is_warmup(&self) -> bool {
+        self.warmup == Some(true)
+    }
+
+    /// Whether the check was flagged as degraded, e.g. for exceeding its
+    /// latency budget
+    pub fn is_degraded(&self) -> bool {
+        self.degraded == Some(true)
+    }
+
     /// Helper method to get the primary response time
     pub fn response_time_ms(&self) -> Option<f64> {
         match &self.check_type {
@@ -48,6 +72,9 @@ impl MonitoringResult {
             CheckType::TcpConnectCheck(c) => c.result.connect_time_ms,
             CheckType::UdpConnectCheck(c) => c.result.response_time_ms,
             CheckType::HttpGetCheck(c) => c.result.response_time_ms,
+            CheckType::GrpcHealthCheck(c) => c.result.response_time_ms,
+            CheckType::TlsCertCheck(c) => c.result.handshake_time_ms,
+            CheckType::DnsCheck(c) => c.result.resolve_time_ms,
             CheckType::PluginCheck(c) => c.result.response_time_ms,
         }
     }
@@ -120,6 +147,45 @@ impl MonitoringResult {
                     Some(errors.join("; "))
                 }
             }
+            CheckType::GrpcHealthCheck(c) => {
+                let errors = c
+                    .result
+                    .error_details
+                    .as_ref()
+                    .and_then(|ed| ed.errors.as_deref())
+                    .unwrap_or(&[]);
+                if errors.is_empty() {
+                    None
+                } else {
+                    Some(errors.join("; "))
+                }
+            }
+            CheckType::TlsCertCheck(c) => {
+                let errors = c
+                    .result
+                    .error_details
+                    .as_ref()
+                    .and_then(|ed| ed.errors.as_deref())
+                    .unwrap_or(&[]);
+                if errors.is_empty() {
+                    None
+                } else {
+                    Some(errors.join("; "))
+                }
+            }
+            CheckType::DnsCheck(c) => {
+                let errors = c
+                    .result
+                    .error_details
+                    .as_ref()
+                    .and_then(|ed| ed.errors.as_deref())
+                    .unwrap_or(&[]);
+                if errors.is_empty() {
+                    None
+                } else {
+                    Some(errors.join("; "))
+                }
+            }
             CheckType::PluginCheck(c) => {
                 let errors = c
                     .result
@@ -137,6 +203,186 @@ impl MonitoringResult {
     }
 }
 
+impl PingResult {
+    /// Share of echo requests without a reply, 0-100
+    ///
+    /// A failed name resolution counts as one lost request. `None` if no
+    /// request was sent.
+    pub fn packet_loss_percent(&self) -> Option<f64> {
+        let sent = self.successes + self.failures;
+        if sent <= 0 {
+            return None;
+        }
+        Some(self.failures as f64 / sent as f64 * 100.0)
+    }
+
+    /// `p`-th percentile (0-100) of the reply latencies in milliseconds
+    ///
+    /// Latencies are sorted and the percentile is linearly interpolated
+    /// between the two closest ranks, so the median of two replies is their
+    /// mean. `None` without replies.
+    pub fn percentile(&self, p: f64) -> Option<f64> {
+        let mut sorted = self.success_latencies.clone();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f64::total_cmp);
+
+        let rank = p.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64))
+    }
+
+    /// Jitter: standard deviation of the reply latencies in milliseconds
+    ///
+    /// The population standard deviation of `success_latencies`. `None`
+    /// with fewer than two replies.
+    pub fn jitter_ms(&self) -> Option<f64> {
+        let samples = &self.success_latencies;
+        if samples.len() < 2 {
+            return None;
+        }
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / n;
+        Some(variance.sqrt())
+    }
+
+    /// Median reply latency in milliseconds
+    pub fn p50(&self) -> Option<f64> {
+        self.percentile(50.0)
+    }
+
+    /// 95th percentile reply latency in milliseconds
+    pub fn p95(&self) -> Option<f64> {
+        self.percentile(95.0)
+    }
+
+    /// 99th percentile reply latency in milliseconds
+    pub fn p99(&self) -> Option<f64> {
+        self.percentile(99.0)
+    }
+}
+
+impl AgentStatus {
+    /// Time the agent has been running
+    ///
+    /// While running this is the time since `started_at`; once stopped it is
+    /// the length of the last run. `None` if the agent was never started.
+    pub fn uptime(&self) -> Option<Duration> {
+        self.uptime_at(Utc::now())
+    }
+
+    fn uptime_at(&self, now: DateTime<Utc>) -> Option<Duration> {
+        if self.started_at == DateTime::<Utc>::UNIX_EPOCH {
+            return None;
+        }
+
+        let end = match self.stopped_at {
+            Some(stopped_at) if !self.is_running => stopped_at,
+            _ => now,
+        };
+
+        Some((end - self.started_at).to_std().unwrap_or_default())
+    }
+
+    /// Share of ping echo requests without a reply since the agent started,
+    /// 0-100. `None` before the first ping check.
+    pub fn packet_loss_percent(&self) -> Option<f64> {
+        if self.ping_requests_sent <= 0 {
+            return None;
+        }
+        Some(self.ping_requests_lost as f64 / self.ping_requests_sent as f64 * 100.0)
+    }
+
+    /// Count the echo requests of a ping check
+    pub fn record_ping(&mut self, ping: &PingResult) {
+        self.ping_requests_sent += ping.successes + ping.failures;
+        self.ping_requests_lost += ping.failures;
+    }
+
+    /// Update the health of the endpoint at `address` with `result`
+    pub fn record_endpoint_result(&mut self, result: &MonitoringResult, address: &str) {
+        let successful = result.is_successful();
+        let health = self
+            .per_endpoint
+            .entry(result.endpoint_id.to_string())
+            .or_insert_with(|| EndpointHealth {
+                address: String::new(),
+                last_successful: false,
+                last_checked_at: result.timestamp,
+                last_latency_ms: None,
+                consecutive_failures: 0,
+                last_status: None,
+            });
+        health.address = address.to_string();
+        health.last_successful = successful;
+        health.last_checked_at = result.timestamp;
+        health.last_latency_ms = result.response_time_ms();
+        health.last_status = result.status;
+        health.consecutive_failures = match successful {
+            true => 0,
+            false => health.consecutive_failures + 1,
+        };
+    }
+
+    /// Forget the health of endpoints that are no longer configured
+    pub fn retain_endpoints(&mut self, endpoints: &[Endpoint]) {
+        if self.per_endpoint.len() <= endpoints.len() {
+            return;
+        }
+        self.per_endpoint.retain(|id, _| {
+            endpoints
+                .iter()
+                .any(|endpoint| endpoint.id.to_string() == *id)
+        });
+    }
+
+    /// Record a report the server accepted. The agent is connected again.
+    ///
+    /// Returns true if the agent was disconnected before.
+    pub fn record_report_success(&mut self) -> bool {
+        self.consecutive_failed_reports = 0;
+        self.reported_at = Utc::now();
+        !std::mem::replace(&mut self.server_connected, true)
+    }
+
+    /// Record a failed report. The agent is shown as disconnected once
+    /// `grace` reports in a row have failed.
+    pub fn record_report_failure(&mut self, grace: u32) {
+        self.failed_report_count += 1;
+        self.consecutive_failed_reports += 1;
+        if self.consecutive_failed_reports >= i64::from(grace.max(1)) {
+            self.server_connected = false;
+        }
+    }
+}
+
+impl Default for HttpCheckConfig {
+    fn default() -> Self {
+        Self {
+            method: None,
+            headers: HashMap::new(),
+            expected_status: Vec::new(),
+            follow_redirects: true,
+        }
+    }
+}
+
+impl HttpCheckConfig {
+    /// Whether a response with `status` passes the check: one of
+    /// `expected_status`, or any 2xx status without any
+    pub fn accepts(&self, status: u16) -> bool {
+        if self.expected_status.is_empty() {
+            (200..300).contains(&status)
+        } else {
+            self.expected_status.contains(&status)
+        }
+    }
+}
+
 impl Endpoint {
     pub fn new(address: impl Into<String>) -> Self {
         Self {
@@ -145,6 +391,19 @@ impl Endpoint {
             port: None,
             enabled: true,
             tags: Vec::new(),
+            success_quorum: None,
+            server_name: None,
+            warning_ms: None,
+            critical_ms: None,
+            body_must_contain: None,
+            body_regex: None,
+            http: None,
+            warn_days: None,
+            record_type: None,
+            expected_answers: Vec::new(),
+            interval_secs: None,
+            check_type: None,
+            plugin: None,
         }
     }
 
@@ -162,6 +421,70 @@ impl Endpoint {
         self.enabled = enabled;
         self
     }
+
+    pub fn with_success_quorum(mut self, quorum: SuccessQuorum) -> Self {
+        self.success_quorum = Some(quorum.to_string());
+        self
+    }
+
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    pub fn with_body_must_contain(mut self, substring: impl Into<String>) -> Self {
+        self.body_must_contain = Some(substring.into());
+        self
+    }
+
+    pub fn with_body_regex(mut self, regex: impl Into<String>) -> Self {
+        self.body_regex = Some(regex.into());
+        self
+    }
+
+    pub fn with_http(mut self, http: HttpCheckConfig) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    pub fn with_warn_days(mut self, warn_days: u32) -> Self {
+        self.warn_days = Some(i64::from(warn_days));
+        self
+    }
+
+    pub fn with_record_type(mut self, record_type: DnsRecordType) -> Self {
+        self.record_type = Some(record_type);
+        self
+    }
+
+    pub fn with_expected_answers(mut self, answers: Vec<String>) -> Self {
+        self.expected_answers = answers;
+        self
+    }
+
+    pub fn with_interval_secs(mut self, interval_secs: u64) -> Self {
+        self.interval_secs = Some(interval_secs as i64);
+        self
+    }
+
+    /// Time between checks of this endpoint: its own `interval_secs`, or
+    /// `default` without one
+    pub fn check_interval(&self, default: Duration) -> Duration {
+        self.interval_secs
+            .and_then(|secs| u64::try_from(secs).ok())
+            .filter(|secs| *secs > 0)
+            .map_or(default, Duration::from_secs)
+    }
+
+    pub fn with_check_type(mut self, check_type: EndpointCheckType) -> Self {
+        self.check_type = Some(check_type);
+        self
+    }
+
+    pub fn with_plugin(mut self, plugin: impl Into<String>) -> Self {
+        self.plugin = Some(plugin.into());
+        self
+    }
 }
 
 #[cfg(test)]
@@ -169,6 +492,66 @@ mod tests {
     use super::*;
     use uuid::Uuid;
 
+    fn tcp_result(endpoint: &Endpoint, connected: bool) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::nil(),
+            endpoint_id: endpoint.id,
+            check_type: CheckType::TcpConnectCheck(TcpConnectCheck {
+                r#type: TcpConnectCheckType::Tcpconnect,
+                result: TcpConnectResult {
+                    connected,
+                    connect_time_ms: connected.then_some(2.5),
+                    error_details: None,
+                    resolved_ip: "10.0.0.1".to_string(),
+                    dns_resolution_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_endpoint_health_counts_failures_until_a_success() {
+        let endpoint = Endpoint::new("tcp://10.0.0.1:443");
+        let mut status = AgentStatus::new();
+
+        status.record_endpoint_result(&tcp_result(&endpoint, false), &endpoint.address);
+        status.record_endpoint_result(&tcp_result(&endpoint, false), &endpoint.address);
+        let health = &status.per_endpoint[&endpoint.id.to_string()];
+        assert!(!health.last_successful);
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.last_latency_ms, None);
+
+        status.record_endpoint_result(&tcp_result(&endpoint, true), &endpoint.address);
+        let health = &status.per_endpoint[&endpoint.id.to_string()];
+        assert!(health.last_successful);
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.last_latency_ms, Some(2.5));
+        assert_eq!(health.address, endpoint.address);
+    }
+
+    #[test]
+    fn test_endpoint_health_forgets_removed_endpoints() {
+        let kept = Endpoint::new("tcp://10.0.0.1:443");
+        let removed = Endpoint::new("tcp://10.0.0.2:443");
+        let mut status = AgentStatus::new();
+        status.record_endpoint_result(&tcp_result(&kept, true), &kept.address);
+        status.record_endpoint_result(&tcp_result(&removed, true), &removed.address);
+
+        status.retain_endpoints(std::slice::from_ref(&kept));
+
+        assert_eq!(
+            status.per_endpoint.keys().collect::<Vec<_>>(),
+            vec![&kept.id.to_string()]
+        );
+    }
+
     #[test]
     fn test_endpoint_enabled_by_default() {
         let endpoint = Endpoint::new("example.com");
@@ -235,6 +618,125 @@ tags = []
         );
     }
 
+    #[test]
+    fn test_endpoint_check_type_round_trip() {
+        #[derive(serde::Deserialize, serde::Serialize)]
+        struct Wrapper {
+            endpoints: Vec<Endpoint>,
+        }
+
+        let toml_input = r#"
+[[endpoints]]
+id = "01931ab4-b27a-7f64-a32f-dae3cabe1ff5"
+address = "db.example.com"
+port = 5432
+enabled = true
+tags = []
+check_type = "tcp"
+
+[[endpoints]]
+id = "01931ab4-b27a-7f64-a32f-dae3cabe1ff6"
+address = "db.example.com"
+enabled = true
+tags = []
+"#;
+        let parsed: Wrapper = toml::from_str(toml_input).unwrap();
+        assert_eq!(parsed.endpoints[0].check_type, Some(EndpointCheckType::Tcp));
+        assert_eq!(parsed.endpoints[1].check_type, None);
+
+        let serialized = toml::to_string(&parsed).unwrap();
+        assert_eq!(serialized.matches("check_type").count(), 1);
+        let reparsed: Wrapper = toml::from_str(&serialized).unwrap();
+        assert_eq!(
+            reparsed.endpoints[0].check_type,
+            Some(EndpointCheckType::Tcp)
+        );
+        assert_eq!(reparsed.endpoints[1].check_type, None);
+
+        for (name, check_type) in [
+            ("ping", EndpointCheckType::Ping),
+            ("tcp", EndpointCheckType::Tcp),
+            ("udp", EndpointCheckType::Udp),
+            ("http", EndpointCheckType::Http),
+            ("grpc", EndpointCheckType::Grpc),
+            ("tls_cert", EndpointCheckType::TlsCert),
+            ("dns", EndpointCheckType::Dns),
+        ] {
+            let json = serde_json::to_string(&check_type).unwrap();
+            assert_eq!(json, format!("\"{}\"", name));
+        }
+    }
+
+    #[test]
+    fn test_endpoint_dns_settings_round_trip() {
+        let endpoint: Endpoint = toml::from_str(
+            r#"
+id = "01931ab4-b27a-7f64-a32f-dae3cabe1ff5"
+address = "dns://10.0.0.53/example.com"
+enabled = true
+tags = []
+record_type = "AAAA"
+expected_answers = ["2001:db8::1"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(endpoint.record_type, Some(DnsRecordType::Aaaa));
+        assert_eq!(endpoint.expected_answers, vec!["2001:db8::1"]);
+
+        let serialized = toml::to_string(&endpoint).unwrap();
+        assert!(
+            serialized.contains("record_type = \"AAAA\""),
+            "{}",
+            serialized
+        );
+        let plain = toml::to_string(&Endpoint::new("example.com")).unwrap();
+        assert!(!plain.contains("record_type"));
+        assert!(!plain.contains("expected_answers"));
+    }
+
+    #[test]
+    fn test_endpoint_http_settings_defaults() {
+        let endpoint: Endpoint = toml::from_str(
+            r#"
+id = "01931ab4-b27a-7f64-a32f-dae3cabe1ff5"
+address = "https://api.example.com/health"
+enabled = true
+tags = []
+
+[http]
+expected_status = [200, 204]
+"#,
+        )
+        .unwrap();
+
+        let http = endpoint.http.unwrap();
+        assert_eq!(http.method, None);
+        assert!(http.headers.is_empty());
+        assert!(http.follow_redirects);
+        assert!(http.accepts(204));
+        assert!(!http.accepts(201));
+
+        let default = HttpCheckConfig::default();
+        assert!(default.accepts(201));
+        assert!(!default.accepts(301));
+        assert!(Endpoint::new("https://api.example.com").http.is_none());
+    }
+
+    #[test]
+    fn test_endpoint_check_interval_falls_back_to_default() {
+        let default = Duration::from_secs(60);
+        assert_eq!(
+            Endpoint::new("example.com").check_interval(default),
+            default
+        );
+        assert_eq!(
+            Endpoint::new("example.com")
+                .with_interval_secs(10)
+                .check_interval(default),
+            Duration::from_secs(10)
+        );
+    }
+
     #[test]
     fn test_endpoint_with_port() {
         let endpoint = Endpoint::new("example.com").with_port(8080);
@@ -251,6 +753,165 @@ tags = []
         );
     }
 
+    fn ping_result(
+        successes: i64,
+        failures: i64,
+        quorum: Option<SuccessQuorum>,
+    ) -> MonitoringResult {
+        let samples = (successes + failures) as u32;
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "127.0.0.1".to_string(),
+                    successes,
+                    failures,
+                    success_latencies: vec![1.0; successes as usize],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: quorum.map(|q| q.required(samples) as i64),
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ping_success_without_quorum_needs_one_reply() {
+        assert!(ping_result(1, 2, None).is_successful());
+        assert!(!ping_result(0, 3, None).is_successful());
+    }
+
+    #[test]
+    fn test_ping_success_respects_quorum() {
+        let two_of_three = Some(SuccessQuorum::AtLeast(2));
+        assert!(!ping_result(1, 2, two_of_three).is_successful());
+        assert!(ping_result(2, 1, two_of_three).is_successful());
+        assert!(ping_result(3, 0, two_of_three).is_successful());
+
+        let all = Some(SuccessQuorum::Percent(100));
+        assert!(!ping_result(2, 1, all).is_successful());
+        assert!(ping_result(3, 0, all).is_successful());
+    }
+
+    fn latencies(success_latencies: Vec<f64>) -> PingResult {
+        PingResult {
+            resolved_ip: "127.0.0.1".to_string(),
+            successes: success_latencies.len() as i64,
+            failures: 0,
+            success_latencies,
+            error_details: None,
+            dns_resolution_ms: None,
+            required_successes: None,
+            resolved_hostname: None,
+            packet_loss_percent: None,
+            jitter_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_ping_percentiles_without_replies() {
+        let result = latencies(Vec::new());
+        assert_eq!(result.percentile(0.0), None);
+        assert_eq!(result.p50(), None);
+        assert_eq!(result.p95(), None);
+        assert_eq!(result.p99(), None);
+    }
+
+    #[test]
+    fn test_ping_percentiles_of_one_reply() {
+        let result = latencies(vec![12.5]);
+        assert_eq!(result.p50(), Some(12.5));
+        assert_eq!(result.p95(), Some(12.5));
+        assert_eq!(result.p99(), Some(12.5));
+    }
+
+    #[test]
+    fn test_ping_percentiles_of_two_replies_interpolate() {
+        // Unsorted on purpose: replies are stored in arrival order
+        let result = latencies(vec![30.0, 10.0]);
+        assert_eq!(result.percentile(0.0), Some(10.0));
+        assert_eq!(result.p50(), Some(20.0));
+        assert_eq!(result.p95(), Some(29.0));
+        assert_eq!(result.p99(), Some(29.8));
+        assert_eq!(result.percentile(100.0), Some(30.0));
+    }
+
+    #[test]
+    fn test_ping_percentiles_interpolate_between_ranks() {
+        let result = latencies(vec![40.0, 10.0, 30.0, 20.0, 50.0]);
+        assert_eq!(result.p50(), Some(30.0));
+        assert_eq!(result.percentile(25.0), Some(20.0));
+        assert_eq!(result.percentile(90.0), Some(46.0));
+        assert_eq!(result.percentile(150.0), Some(50.0), "clamped to 100");
+    }
+
+    fn counts(successes: i64, failures: i64) -> PingResult {
+        PingResult {
+            successes,
+            failures,
+            ..latencies(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_ping_jitter_is_standard_deviation() {
+        let jitter = latencies(vec![10.0, 12.0, 14.0, 16.0, 18.0])
+            .jitter_ms()
+            .unwrap();
+        assert!((jitter - 8f64.sqrt()).abs() < 1e-9, "jitter = {}", jitter);
+
+        let steady = latencies(vec![20.0, 20.0, 20.0]).jitter_ms();
+        assert_eq!(steady, Some(0.0));
+    }
+
+    #[test]
+    fn test_ping_jitter_needs_two_replies() {
+        assert_eq!(latencies(Vec::new()).jitter_ms(), None);
+        assert_eq!(latencies(vec![12.5]).jitter_ms(), None);
+    }
+
+    #[test]
+    fn test_ping_packet_loss_percent() {
+        assert_eq!(counts(4, 0).packet_loss_percent(), Some(0.0));
+        assert_eq!(counts(2, 2).packet_loss_percent(), Some(50.0));
+        assert_eq!(counts(0, 3).packet_loss_percent(), Some(100.0));
+    }
+
+    #[test]
+    fn test_ping_packet_loss_percent_without_requests() {
+        assert_eq!(counts(0, 0).packet_loss_percent(), None);
+    }
+
+    #[test]
+    fn test_agent_status_packet_loss_percent() {
+        let mut status = AgentStatus::new();
+        assert_eq!(status.packet_loss_percent(), None);
+
+        status.record_ping(&counts(3, 1));
+        status.record_ping(&counts(0, 4));
+        assert_eq!(status.ping_requests_sent, 8);
+        assert_eq!(status.ping_requests_lost, 5);
+        assert_eq!(status.packet_loss_percent(), Some(62.5));
+    }
+
+    #[test]
+    fn test_endpoint_with_success_quorum() {
+        let endpoint = Endpoint::new("example.com").with_success_quorum(SuccessQuorum::Percent(60));
+        assert_eq!(endpoint.success_quorum.as_deref(), Some("60%"));
+    }
+
     #[test]
     fn test_agent_status_new() {
         let status = AgentStatus::new();
@@ -281,6 +942,77 @@ tags = []
         );
     }
 
+    #[test]
+    fn test_agent_status_uptime_never_started() {
+        assert_eq!(AgentStatus::new().uptime(), None);
+    }
+
+    #[test]
+    fn test_agent_status_uptime_running() {
+        let now = Utc::now();
+        let status = AgentStatus {
+            is_running: true,
+            started_at: now - chrono::Duration::seconds(90),
+            ..AgentStatus::new()
+        };
+
+        assert_eq!(status.uptime_at(now), Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_agent_status_uptime_stopped() {
+        let now = Utc::now();
+        let started_at = now - chrono::Duration::seconds(600);
+        let status = AgentStatus {
+            is_running: false,
+            started_at,
+            stopped_at: Some(started_at + chrono::Duration::seconds(120)),
+            ..AgentStatus::new()
+        };
+
+        assert_eq!(
+            status.uptime_at(now),
+            Some(Duration::from_secs(120)),
+            "a stopped agent reports the length of its last run"
+        );
+    }
+
+    #[test]
+    fn test_single_failed_report_stays_connected() {
+        let mut status = AgentStatus::new();
+        status.record_report_success();
+
+        status.record_report_failure(3);
+        assert!(status.server_connected, "one blip must not disconnect");
+        assert_eq!(status.failed_report_count, 1);
+    }
+
+    #[test]
+    fn test_grace_consecutive_failures_disconnect_and_success_recovers() {
+        let mut status = AgentStatus::new();
+        status.record_report_success();
+
+        status.record_report_failure(3);
+        status.record_report_failure(3);
+        assert!(status.server_connected);
+        status.record_report_failure(3);
+        assert!(!status.server_connected);
+        assert_eq!(status.failed_report_count, 3);
+
+        assert!(status.record_report_success(), "reported as a reconnect");
+        assert!(status.server_connected, "first success reconnects");
+        assert!(!status.record_report_success(), "already connected");
+        assert_eq!(status.consecutive_failed_reports, 0);
+        assert_eq!(
+            status.failed_report_count, 3,
+            "total failure count is not reset"
+        );
+
+        // The streak starts over after a success
+        status.record_report_failure(3);
+        assert!(status.server_connected);
+    }
+
     #[test]
     fn test_agent_status_deserialization() {
         let json = r#"{"agent_version":"0.1.0","config_version":0,"is_running":false,"started_at":"1970-01-01T00:00:00Z","stopped_at":null,"checks_performed":0,"checks_successful":0,"checks_failed":0,"reported_at":"1970-01-01T00:00:00Z","failed_report_count":0,"server_connected":false,"cache_stats":{"len":0,"capacity":0}}"#;
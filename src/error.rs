@@ -23,6 +23,9 @@ pub enum Error {
     #[error("Network error: {0}")]
     Network(String),
 
+    #[error("Rejected by server: {0}")]
+    Rejected(String),
+
     #[error("Authentication error: {0}")]
     Authentication(String),
 
@@ -35,6 +38,12 @@ pub enum Error {
     #[error("Monitoring error: {0}")]
     Monitoring(String),
 
+    #[error("Incompatible server: {0}")]
+    IncompatibleServer(String),
+
+    #[error("Cache error: {0}")]
+    Cache(String),
+
     #[error("Plugin error: {0}")]
     Plugin(String),
 
@@ -53,6 +62,9 @@ pub enum Error {
     #[error("Github API error: {0}")]
     GithubApi(String),
 
+    #[error("Service error: {0}")]
+    Service(String),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -13,28 +13,44 @@
 mod openapi;
 
 mod agent_config;
+mod alerting;
+mod audit;
 mod cache;
 mod claim;
+mod control;
 mod core;
 mod error;
-
+mod metrics;
 mod monitor;
 mod plugin;
 mod reporter;
 mod results;
+mod sinks;
 
 pub mod self_upgrade;
+pub mod service;
 
-pub use agent_config::{ClaimConfig, Config, MonitoringConfig, ServerConfig, StorageConfig};
+pub use agent_config::{
+    AlertingConfig, AuditConfig, CircuitBreakerConfig, ClaimConfig, Config, ControlConfig,
+    EndpointSync, HeartbeatMetrics, HeartbeatThresholds, HotReloadConfig, LintCategory,
+    LintWarning, LoggingConfig, MonitoringConfig, MqttSinkConfig, PreflightMode, ReloadSource,
+    ReloadStatus, RemoteWriteSinkConfig, ReportMode, SecretSource, ServerConfig, SinksConfig,
+    StorageConfig, SuccessLogging,
+};
 pub use claim::Claim;
 pub use core::{
-    Agent, AgentCacheStats, AgentHealthStatus, AgentHeartbeat, AgentMetrics, AgentStatus,
-    CheckType, Endpoint, ErrorDetails, HttpGetCheck, HttpGetCheckType, HttpGetResult,
-    MonitoringResult, PingCheck, PingCheckType, PingResult, PluginCheck, PluginCheckType,
-    PluginResult, TcpConnectCheck, TcpConnectCheckType, TcpConnectResult, TracerouteCheck,
-    TracerouteCheckType, TracerouteHop, TracerouteResult, UdpConnectCheck, UdpConnectCheckType,
-    UdpConnectResult,
+    wait_startup_splay, Agent, AgentCacheStats, AgentHealthStatus, AgentHeartbeat, AgentMetrics,
+    AgentSkippedChecks, AgentStatus, CheckKind, CheckTarget, CheckType, Classification, DnsCheck,
+    DnsCheckType, DnsRecordType, DnsResult, Endpoint, EndpointHealth, ErrorDetails, FailureKind,
+    GrpcHealthCheck, GrpcHealthCheckType, GrpcHealthResult, HttpCheckConfig, HttpGetCheck,
+    HttpGetCheckType, HttpGetResult, MetricStatus, MonitoringResult, PingCheck, PingCheckType,
+    PingResult, PluginCheck, PluginCheckType, PluginResult, SkipReason, SuccessQuorum,
+    TagThresholds, TcpConnectCheck, TcpConnectCheckType, TcpConnectResult, Thresholds,
+    TlsCertCheck, TlsCertCheckType, TlsCertResult, TracerouteCheck, TracerouteCheckType,
+    TracerouteHop, TracerouteResult, UdpConnectCheck, UdpConnectCheckType, UdpConnectResult,
 };
 pub use error::{Error, Result};
 
-pub use plugin::{MonitoringPlugin, PluginRegistry};
+pub use plugin::{
+    MonitoringPlugin, PluginCreate, PluginInitPolicy, PluginRegistry, PLUGIN_ENTRY_SYMBOL,
+};
@@ -0,0 +1,10 @@
+//! Local Prometheus `/metrics` endpoint.
+//!
+//! See [`registry::CheckMetrics`] for the exposed metrics and
+//! [`server::run_metrics_server`] for the HTTP endpoint.
+
+mod registry;
+mod server;
+
+pub(crate) use registry::CheckMetrics;
+pub(crate) use server::run_metrics_server;
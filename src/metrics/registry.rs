@@ -0,0 +1,358 @@
+//! Check metrics kept for the local `/metrics` endpoint
+//!
+//! Every check result except traceroutes updates one series per endpoint and
+//! check type. A traceroute follows up on a failed ping and is not a check
+//! of its own.
+//!
+//!
+//! | Metric | Type | Value |
+//! |--------|------|-------|
+//! | `smotra_check_success_total` | counter | checks that succeeded |
+//! | `smotra_check_failure_total` | counter | checks that failed |
+//! | `smotra_response_time_ms` | gauge | response time of the latest check that measured one |
+//! | `smotra_packet_loss_percent` | gauge | packet loss of the latest ping check |
+//!
+//! Series are labeled with `target`, the endpoint address, and `check`
+//! (`ping`, `http`, ...). Series of addresses no longer configured are
+//! dropped by [`CheckMetrics::retain_endpoints`]. Counters start at zero when
+//! the agent starts.
+
+use crate::core::{CheckType, Endpoint, MonitoringResult};
+use crate::sinks::check_label;
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Labels of one series
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    target: String,
+    check: &'static str,
+}
+
+/// Values of one series
+#[derive(Debug, Default)]
+struct Series {
+    successes: u64,
+    failures: u64,
+    response_time_ms: Option<f64>,
+    packet_loss_percent: Option<f64>,
+}
+
+/// Per-endpoint check counters and gauges
+///
+/// Clones share the same series.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CheckMetrics {
+    series: Arc<Mutex<BTreeMap<SeriesKey, Series>>>,
+}
+
+impl CheckMetrics {
+    /// Count `result` for the endpoint at `address`. Traceroutes are not
+    /// counted.
+    pub(crate) fn observe(&self, result: &MonitoringResult, address: &str) {
+        if matches!(result.check_type, CheckType::TracerouteCheck(_)) {
+            return;
+        }
+        let key = SeriesKey {
+            target: address.to_string(),
+            check: check_label(&result.check_type),
+        };
+
+        let mut series = self.series.lock();
+        let series = series.entry(key).or_default();
+        if result.is_successful() {
+            series.successes += 1;
+        } else {
+            series.failures += 1;
+        }
+        if let Some(response_time_ms) = result.response_time_ms() {
+            series.response_time_ms = Some(response_time_ms);
+        }
+        if let CheckType::PingCheck(ping) = &result.check_type {
+            series.packet_loss_percent = ping.result.packet_loss_percent();
+        }
+    }
+
+    /// Drop the series of addresses that no configured endpoint has
+    pub(crate) fn retain_endpoints(&self, endpoints: &[Endpoint]) {
+        self.series.lock().retain(|key, _| {
+            endpoints
+                .iter()
+                .any(|endpoint| endpoint.address == key.target)
+        });
+    }
+
+    /// All series in the Prometheus text exposition format (version 0.0.4)
+    pub(crate) fn render(&self) -> String {
+        let series = self.series.lock();
+        let mut out = String::new();
+
+        let mut family =
+            |name: &str, kind: &str, help: &str, value: &dyn Fn(&Series) -> Option<f64>| {
+                let _ = writeln!(out, "# HELP {} {}", name, help);
+                let _ = writeln!(out, "# TYPE {} {}", name, kind);
+                for (key, series) in series.iter() {
+                    if let Some(value) = value(series) {
+                        let _ = writeln!(
+                            out,
+                            "{}{{target=\"{}\",check=\"{}\"}} {}",
+                            name,
+                            escape_label_value(&key.target),
+                            key.check,
+                            value
+                        );
+                    }
+                }
+            };
+
+        family(
+            "smotra_check_success_total",
+            "counter",
+            "Checks that succeeded",
+            &|s| Some(s.successes as f64),
+        );
+        family(
+            "smotra_check_failure_total",
+            "counter",
+            "Checks that failed",
+            &|s| Some(s.failures as f64),
+        );
+        family(
+            "smotra_response_time_ms",
+            "gauge",
+            "Response time of the latest check in milliseconds",
+            &|s| s.response_time_ms,
+        );
+        family(
+            "smotra_packet_loss_percent",
+            "gauge",
+            "Packet loss of the latest ping check in percent",
+            &|s| s.packet_loss_percent,
+        );
+        out
+    }
+}
+
+/// Escape `value` for use inside a quoted label value
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        PingCheck, PingCheckType, PingResult, TcpConnectCheck, TcpConnectCheckType,
+        TcpConnectResult, TracerouteCheck, TracerouteCheckType, TracerouteResult,
+    };
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn ping_result(successes: i64, failures: i64, latency_ms: f64) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::nil(),
+            endpoint_id: Uuid::from_u128(2),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "10.0.0.1".to_string(),
+                    successes,
+                    failures,
+                    success_latencies: vec![latency_ms; successes as usize],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Parse `text` as the text exposition format into sample values keyed
+    /// by metric name, `target` and `check`, panicking on a malformed line or
+    /// a sample without a preceding `# TYPE`
+    fn parse(text: &str) -> BTreeMap<(String, String, String), f64> {
+        let mut typed = Vec::new();
+        let mut samples = BTreeMap::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').expect("TYPE needs a metric type");
+                assert!(["counter", "gauge"].contains(&kind), "{:?}", line);
+                typed.push(name.to_string());
+                continue;
+            }
+            if line.starts_with("# HELP ") {
+                continue;
+            }
+
+            let (name, rest) = line.split_once('{').expect("sample needs labels");
+            assert!(typed.iter().any(|t| t == name), "{} has no TYPE line", name);
+            let (labels, value) = rest.rsplit_once("} ").expect("sample needs a value");
+            let mut labels: BTreeMap<_, _> = labels
+                .split("\",")
+                .map(|label| {
+                    let (key, value) = label.split_once("=\"").expect("label needs a value");
+                    (key, value.trim_end_matches('"').to_string())
+                })
+                .collect();
+            let key = (
+                name.to_string(),
+                labels.remove("target").expect("target label"),
+                labels.remove("check").expect("check label"),
+            );
+            assert!(labels.is_empty(), "unexpected labels in {:?}", line);
+            let value = value
+                .parse()
+                .unwrap_or_else(|e| panic!("{:?}: {}", line, e));
+            assert!(samples.insert(key, value).is_none(), "duplicate {:?}", line);
+        }
+        samples
+    }
+
+    fn sample(
+        samples: &BTreeMap<(String, String, String), f64>,
+        name: &str,
+        target: &str,
+        check: &str,
+    ) -> Option<f64> {
+        samples
+            .get(&(name.to_string(), target.to_string(), check.to_string()))
+            .copied()
+    }
+
+    fn traceroute_result() -> MonitoringResult {
+        MonitoringResult {
+            check_type: CheckType::TracerouteCheck(TracerouteCheck {
+                r#type: TracerouteCheckType::Traceroute,
+                result: TracerouteResult {
+                    hops: Vec::new(),
+                    target_reached: false,
+                    error_details: None,
+                },
+            }),
+            ..ping_result(0, 4, 0.0)
+        }
+    }
+
+    #[test]
+    fn test_results_update_counters_and_gauges() {
+        let metrics = CheckMetrics::default();
+        metrics.observe(&ping_result(4, 0, 12.5), "10.0.0.1");
+        metrics.observe(&ping_result(3, 1, 20.0), "10.0.0.1");
+        metrics.observe(&ping_result(0, 4, 0.0), "10.0.0.1");
+
+        let text = metrics.render();
+        let samples = parse(&text);
+        let value = |name| sample(&samples, name, "10.0.0.1", "ping");
+        assert_eq!(value("smotra_check_success_total"), Some(2.0));
+        assert_eq!(value("smotra_check_failure_total"), Some(1.0));
+        assert_eq!(value("smotra_response_time_ms"), Some(20.0));
+        assert_eq!(value("smotra_packet_loss_percent"), Some(100.0), "{}", text);
+        assert_eq!(samples.len(), 4, "{}", text);
+    }
+
+    #[test]
+    fn test_traceroutes_are_not_counted() {
+        let metrics = CheckMetrics::default();
+        metrics.observe(&ping_result(0, 4, 0.0), "10.0.0.1");
+        metrics.observe(&traceroute_result(), "10.0.0.1");
+
+        let samples = parse(&metrics.render());
+        assert_eq!(
+            sample(&samples, "smotra_check_failure_total", "10.0.0.1", "ping"),
+            Some(1.0)
+        );
+        assert!(
+            samples.keys().all(|(_, _, check)| check == "ping"),
+            "{:?}",
+            samples
+        );
+    }
+
+    #[test]
+    fn test_series_are_labeled_by_target_and_check() {
+        let metrics = CheckMetrics::default();
+        metrics.observe(&ping_result(1, 0, 1.0), "10.0.0.1");
+        metrics.observe(
+            &MonitoringResult {
+                check_type: CheckType::TcpConnectCheck(TcpConnectCheck {
+                    r#type: TcpConnectCheckType::Tcpconnect,
+                    result: TcpConnectResult {
+                        connected: true,
+                        connect_time_ms: Some(3.0),
+                        error_details: None,
+                        resolved_ip: "10.0.0.2".to_string(),
+                        dns_resolution_ms: None,
+                    },
+                }),
+                ..ping_result(1, 0, 1.0)
+            },
+            "tcp://10.0.0.2:443",
+        );
+
+        let samples = parse(&metrics.render());
+        assert_eq!(
+            sample(&samples, "smotra_check_success_total", "10.0.0.1", "ping"),
+            Some(1.0)
+        );
+        assert_eq!(
+            sample(
+                &samples,
+                "smotra_check_success_total",
+                "tcp://10.0.0.2:443",
+                "tcp"
+            ),
+            Some(1.0)
+        );
+        assert_eq!(
+            sample(
+                &samples,
+                "smotra_packet_loss_percent",
+                "tcp://10.0.0.2:443",
+                "tcp"
+            ),
+            None,
+            "only ping checks report packet loss"
+        );
+    }
+
+    #[test]
+    fn test_series_of_removed_endpoints_are_pruned() {
+        let metrics = CheckMetrics::default();
+        metrics.observe(&ping_result(1, 0, 1.0), "10.0.0.1");
+        metrics.observe(&ping_result(1, 0, 1.0), "10.0.0.2");
+
+        metrics.retain_endpoints(&[Endpoint::new("10.0.0.2")]);
+
+        let samples = parse(&metrics.render());
+        assert!(
+            samples.keys().all(|(_, target, _)| target == "10.0.0.2"),
+            "{:?}",
+            samples
+        );
+        assert_eq!(
+            sample(&samples, "smotra_check_success_total", "10.0.0.2", "ping"),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(escape_label_value(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_label_value("a\nb"), r"a\nb");
+    }
+}
@@ -0,0 +1,266 @@
+//! HTTP server for the local `/metrics` and `/readyz` endpoints
+//!
+//! A deliberately small HTTP/1.1 server: it reads the request head, answers
+//! `GET /metrics` with the current [`CheckMetrics`], `GET /readyz` with the
+//! agent's [`Readiness`] and everything else with `404` or `405`, and closes
+//! the connection after each response. That is all a Prometheus scrape or
+//! an orchestrator's readiness probe needs, without pulling in a web
+//! framework.
+//!
+//! `/readyz` answers `503` with the pending startup steps until every step
+//! is complete, then `200`. The server itself only starts once the server
+//! preflight has passed and plugins are initialized.
+
+use super::CheckMetrics;
+use crate::agent_config::Config;
+use crate::core::{wait_for_shutdown, Readiness, ShutdownReceiver};
+use crate::error::{Error, Result};
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/// Largest request head read before the request is rejected
+const MAX_REQUEST_HEAD_BYTES: usize = 8 * 1024;
+
+/// Time a client gets to send its request head
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Content type of the Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Content type of the readiness answer
+const READYZ_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Serve `metrics` and `readiness` on `metrics_addr` until shutdown
+///
+/// Returns immediately when `metrics_addr` is not set. Enabling or moving
+/// the endpoint requires a restart; changes from hot reload are ignored.
+pub(crate) async fn run_metrics_server(
+    config: Arc<RwLock<Config>>,
+    metrics: CheckMetrics,
+    readiness: Readiness,
+    mut shutdown_rx: ShutdownReceiver,
+) -> Result<()> {
+    let Some(addr) = config.read().metrics_addr else {
+        debug!("Metrics endpoint disabled");
+        return Ok(());
+    };
+
+    let listener = TcpListener::bind(addr).await?;
+    info!("Metrics endpoint listening on http://{}/metrics", addr);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    let metrics = metrics.clone();
+                    let readiness = readiness.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, &metrics, &readiness).await {
+                            debug!("Metrics request from {} failed: {}", peer, e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept metrics connection: {}", e),
+            },
+            _ = wait_for_shutdown(&mut shutdown_rx) => {
+                info!("Metrics endpoint shutting down");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Answer the single request sent over `stream`
+async fn handle_connection(
+    mut stream: TcpStream,
+    metrics: &CheckMetrics,
+    readiness: &Readiness,
+) -> Result<()> {
+    let head = tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream))
+        .await
+        .map_err(|_| Error::Network("timed out reading the request".to_string()))??;
+
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path.split('?').next().unwrap_or_default()) {
+        ("GET", "/metrics") => ("200 OK", CONTENT_TYPE, metrics.render()),
+        ("GET", "/readyz") => {
+            let pending = readiness.pending();
+            if pending.is_empty() {
+                ("200 OK", READYZ_CONTENT_TYPE, "ready\n".to_string())
+            } else {
+                let pending: Vec<String> = pending.iter().map(ToString::to_string).collect();
+                (
+                    "503 Service Unavailable",
+                    READYZ_CONTENT_TYPE,
+                    format!("not ready: waiting for {}\n", pending.join(", ")),
+                )
+            }
+        }
+        (_, "/metrics" | "/readyz") => ("405 Method Not Allowed", CONTENT_TYPE, String::new()),
+        _ => ("404 Not Found", CONTENT_TYPE, String::new()),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    if status.starts_with("405") {
+        response.push_str("Allow: GET\r\n");
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Read from `stream` up to the end of the request head
+async fn read_request_head(stream: &mut TcpStream) -> Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+        if head.len() > MAX_REQUEST_HEAD_BYTES {
+            return Err(Error::Network("request head too large".to_string()));
+        }
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{shutdown_channel, trigger_shutdown};
+
+    /// Start the server on a free local port and return its address
+    async fn start(metrics: CheckMetrics) -> (std::net::SocketAddr, crate::core::ShutdownSender) {
+        start_with_readiness(metrics, Readiness::default()).await
+    }
+
+    async fn start_with_readiness(
+        metrics: CheckMetrics,
+        readiness: Readiness,
+    ) -> (std::net::SocketAddr, crate::core::ShutdownSender) {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let config = Config {
+            metrics_addr: Some(addr),
+            ..Config::default()
+        };
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+        tokio::spawn(run_metrics_server(
+            Arc::new(RwLock::new(config)),
+            metrics,
+            readiness,
+            shutdown_rx,
+        ));
+
+        for _ in 0..50 {
+            if TcpStream::connect(addr).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        (addr, shutdown_tx)
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_served_with_the_exposition_content_type() {
+        let (addr, shutdown_tx) = start(CheckMetrics::default()).await;
+
+        let response = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], CONTENT_TYPE);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("# TYPE smotra_check_success_total counter"));
+
+        trigger_shutdown(&shutdown_tx);
+    }
+
+    #[tokio::test]
+    async fn test_other_paths_and_methods_are_rejected() {
+        let (addr, shutdown_tx) = start(CheckMetrics::default()).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        let response = client
+            .post(format!("http://{}/metrics", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 405);
+
+        trigger_shutdown(&shutdown_tx);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_is_unavailable_until_startup_completes() {
+        use crate::core::ReadinessStep;
+
+        let readiness = Readiness::default();
+        let (addr, shutdown_tx) =
+            start_with_readiness(CheckMetrics::default(), readiness.clone()).await;
+        let url = format!("http://{}/readyz", addr);
+
+        readiness.complete(ReadinessStep::ConfigLoaded, "config loaded");
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), 503);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("check cycle, server"), "{}", body);
+
+        readiness.complete(ReadinessStep::CheckCycle, "first check cycle scheduled");
+        readiness.complete(ReadinessStep::ServerReachable, "no server configured");
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "ready\n");
+
+        let response = reqwest::Client::new().post(&url).send().await.unwrap();
+        assert_eq!(response.status(), 405);
+
+        trigger_shutdown(&shutdown_tx);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_without_metrics_addr() {
+        let (_shutdown_tx, shutdown_rx) = shutdown_channel();
+        let config = Arc::new(RwLock::new(Config::default()));
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            run_metrics_server(
+                config,
+                CheckMetrics::default(),
+                Readiness::default(),
+                shutdown_rx,
+            ),
+        )
+        .await
+        .expect("returns immediately")
+        .unwrap();
+    }
+}
@@ -0,0 +1,646 @@
+//! Dispatch of endpoints to the checker for their check kind
+//!
+//! Every check runs inside a `check` span so a tracing layer (e.g. an
+//! OpenTelemetry exporter) can export it. The span carries `endpoint_id`,
+//! `kind` and `target`, the host the check is sent to, and once the check
+//! has finished `result_id`, `success` and `latency_ms`. The endpoint
+//! address itself is not recorded: URLs may carry credentials or tokens in
+//! their userinfo, path or query.
+//!
+//! With `monitoring.check_latency_budget_percent` set, a check whose total
+//! time (including name resolution and retries within the check) exceeds
+//! that share of the endpoint's check interval is flagged: its result is marked
+//! `degraded` and carries a note with the measured time and the budget. The
+//! check's success is not affected.
+//!
+//! Every failed check logs an info line. Successful checks log one at debug
+//! level, or follow `logging.log_successful_checks`: `off` logs none,
+//! `every` logs each at info level and `sampled` a random one in
+//! `logging.success_sample_rate`. Random sampling keeps the logged checks
+//! spread over all endpoints, even when their number is a multiple of the
+//! rate.
+//!
+//! With `monitoring.traceroute_on_failure` set, a ping check that got no
+//! reply is followed by a traceroute to the same endpoint, reported as a
+//! result of its own (see [`Checkers::traces_after`]).
+//!
+//! Endpoints naming a `plugin` are checked by that plugin of the registry
+//! passed to [`Checkers::with_plugins`]; without one no plugin is registered
+//! and their checks fail.
+
+use crate::agent_config::{Config, DnsResolverConfig, SuccessLogging};
+use crate::core::{CheckKind, CheckTarget, CheckType, Endpoint, FailureKind, MonitoringResult};
+use crate::error::{Error, Result};
+use crate::monitor::resolver::{nameserver_config, Resolver};
+use crate::monitor::reverse_dns::ReverseResolver;
+use crate::monitor::source_port::{Source, SourcePortRange};
+use crate::monitor::{
+    DnsCache, DnsChecker, GrpcHealthChecker, HttpGetChecker, PingChecker, PluginChecker, PtrCache,
+    TcpConnectChecker, TlsCertChecker, TracerouteChecker, UdpConnectChecker,
+};
+use crate::plugin::PluginRegistry;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::{debug, info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+/// Share of its check interval a single check may take
+#[derive(Debug, Clone, Copy)]
+struct LatencyBudget {
+    percent: u8,
+    interval: Duration,
+}
+
+impl LatencyBudget {
+    fn from_config(config: &Config) -> Option<Self> {
+        let monitoring = &config.monitoring;
+        Some(Self {
+            percent: monitoring.check_latency_budget_percent?,
+            interval: monitoring.interval(),
+        })
+    }
+
+    /// Flag `result` as degraded if the check of `endpoint` took longer
+    /// than the budget of its interval
+    fn apply(&self, endpoint: &Endpoint, result: &mut MonitoringResult, elapsed: Duration) {
+        let interval = endpoint.check_interval(self.interval);
+        let budget = interval * u32::from(self.percent) / 100;
+        if elapsed <= budget {
+            return;
+        }
+
+        let note = format!(
+            "check took {} ms, over its latency budget of {} ms ({}% of the {:?} interval)",
+            elapsed.as_millis(),
+            budget.as_millis(),
+            self.percent,
+            interval
+        );
+        warn!("Endpoint {}: {}", result.endpoint_id, note);
+        result.degraded = Some(true);
+        result.notes.push(note);
+    }
+}
+
+/// Which successful checks are logged
+#[derive(Debug, Clone, Copy)]
+struct SuccessLog {
+    mode: Option<SuccessLogging>,
+    sample_rate: u32,
+}
+
+impl SuccessLog {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            mode: config.logging.log_successful_checks,
+            sample_rate: config.logging.success_sample_rate.max(1),
+        }
+    }
+
+    /// Whether the next successful check logs an info line
+    fn sample(&self) -> bool {
+        match self.mode {
+            Some(SuccessLogging::Every) => true,
+            Some(SuccessLogging::Sampled) => rand::random::<u32>().is_multiple_of(self.sample_rate),
+            Some(SuccessLogging::Off) | None => false,
+        }
+    }
+
+    fn log(&self, target: &CheckTarget, result: &MonitoringResult) {
+        let latency = result
+            .response_time_ms()
+            .map(|ms| format!(" in {:.2} ms", ms))
+            .unwrap_or_default();
+
+        if !result.is_successful() {
+            info!(
+                "{} check of endpoint {} ({}) failed{}",
+                target.kind, result.endpoint_id, target.host, latency
+            );
+        } else if self.sample() {
+            info!(
+                "{} check of endpoint {} ({}) succeeded{}",
+                target.kind, result.endpoint_id, target.host, latency
+            );
+        } else if self.mode.is_none() {
+            debug!(
+                "{} check of endpoint {} ({}) succeeded{}",
+                target.kind, result.endpoint_id, target.host, latency
+            );
+        }
+    }
+}
+
+/// Checkers built from a single config snapshot
+pub struct Checkers {
+    ping: PingChecker,
+    http: HttpGetChecker,
+    grpc: GrpcHealthChecker,
+    tcp: TcpConnectChecker,
+    udp: UdpConnectChecker,
+    tls: TlsCertChecker,
+    dns: DnsChecker,
+    plugin: PluginChecker,
+    traceroute: Option<TracerouteChecker>,
+    latency_budget: Option<LatencyBudget>,
+    success_log: SuccessLog,
+}
+
+impl Checkers {
+    /// Build every checker from the current monitoring settings. Resolved
+    /// hostnames are cached in `dns_cache` and PTR answers in `ptr_cache`,
+    /// both of which outlive the checkers.
+    pub fn from_config(
+        config: &Config,
+        dns_cache: &DnsCache,
+        ptr_cache: &PtrCache,
+    ) -> Result<Self> {
+        let monitoring = &config.monitoring;
+        let configured = |resolver: Resolver| {
+            resolver
+                .with_ip_version(monitoring.ip_version)
+                .with_cache(dns_cache.clone(), monitoring.dns_cache_ttl())
+        };
+        let reverse_resolver = monitoring
+            .reverse_dns
+            .then(|| ReverseResolver::new(monitoring.reverse_dns_timeout(), ptr_cache.clone()));
+
+        let mut ping = PingChecker::new(monitoring.timeout(), monitoring.ping_count)?
+            .with_source_address(monitoring.source_address)?
+            .with_dscp(monitoring.dscp)
+            .with_payload_bytes(monitoring.ping_payload_bytes())
+            .with_resolver(configured(Resolver::new(monitoring.dns_timeout())))
+            .with_success_quorum(monitoring.success_quorum)
+            .with_reverse_dns(reverse_resolver.clone());
+        let mut http = HttpGetChecker::builder(
+            monitoring.http.connect_timeout(),
+            monitoring.http.read_timeout(monitoring.timeout()),
+        )
+        .with_verify_tls(monitoring.http.verify_tls)
+        .with_source_address(monitoring.source_address)
+        .with_max_body_bytes(monitoring.http.max_body_bytes)
+        .with_resolver(configured(Resolver::new(monitoring.dns_timeout())));
+        let source = Source::new(
+            monitoring.source_address,
+            monitoring.source_port_range.map(SourcePortRange::new),
+        );
+        let mut tcp = TcpConnectChecker::new(monitoring.timeout())
+            .with_dscp(monitoring.dscp)
+            .with_resolver(configured(Resolver::new(monitoring.dns_timeout())))
+            .with_source(source);
+        let mut traceroute = monitoring.traceroute_on_failure.then(|| {
+            TracerouteChecker::new(monitoring.timeout(), monitoring.traceroute_max_hops)
+                .with_probes_per_hop(monitoring.traceroute_probes_per_hop())
+                .with_reverse_dns(reverse_resolver)
+                .with_source_address(monitoring.source_address)
+                .with_resolver(configured(Resolver::new(monitoring.dns_timeout())))
+        });
+        let mut udp = UdpConnectChecker::new(monitoring.timeout())
+            .with_payload(monitoring.udp.payload.clone().unwrap_or_default())
+            .with_no_response_is_success(monitoring.udp.no_response_is_success)
+            .with_dscp(monitoring.dscp)
+            .with_resolver(configured(Resolver::new(monitoring.dns_timeout())))
+            .with_source(source);
+        let mut tls = TlsCertChecker::new(monitoring.timeout())?
+            .with_resolver(configured(Resolver::new(monitoring.dns_timeout())))
+            .with_source(source);
+        let mut grpc = GrpcHealthChecker::new(monitoring.timeout())
+            .with_resolver(configured(Resolver::new(monitoring.dns_timeout())))
+            .with_source(source);
+        let mut dns = DnsChecker::new(monitoring.timeout())
+            .with_resolver(configured(Resolver::new(monitoring.dns_timeout())));
+        if let DnsResolverConfig::Nameservers {
+            protocol, tls_name, ..
+        } = &monitoring.resolver
+        {
+            let addrs = monitoring
+                .resolver
+                .nameserver_addrs()
+                .map_err(Error::Config)?;
+            dns = dns.with_nameservers(nameserver_config(&addrs, *protocol, tls_name.as_deref()));
+        }
+        if let Some(resolver) =
+            Resolver::from_config(&monitoring.resolver, monitoring.dns_timeout())?
+        {
+            let resolver = configured(resolver);
+            ping = ping.with_resolver(resolver.clone());
+            tcp = tcp.with_resolver(resolver.clone());
+            grpc = grpc.with_resolver(resolver.clone());
+            udp = udp.with_resolver(resolver.clone());
+            tls = tls.with_resolver(resolver.clone());
+            dns = dns.with_resolver(resolver.clone());
+            traceroute = traceroute.map(|traceroute| traceroute.with_resolver(resolver.clone()));
+            http = http.with_resolver(resolver);
+        }
+
+        Ok(Self {
+            ping,
+            http: http.build()?,
+            grpc,
+            tcp,
+            udp,
+            tls,
+            dns,
+            plugin: PluginChecker::new(monitoring.timeout()),
+            traceroute,
+            latency_budget: LatencyBudget::from_config(config),
+            success_log: SuccessLog::from_config(config),
+        })
+    }
+
+    /// Check endpoints naming a plugin with the plugins of `registry`
+    pub fn with_plugins(mut self, registry: Arc<PluginRegistry>) -> Self {
+        self.plugin = self.plugin.with_registry(registry);
+        self
+    }
+
+    /// Check `endpoint` with the checker matching its check kind, inside a
+    /// `check` span, and flag it if it exceeded the latency budget
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let target = endpoint.check_target();
+        let span = info_span!(
+            "check",
+            endpoint_id = %endpoint.id,
+            kind = %target.kind,
+            target = %target.host,
+            result_id = Empty,
+            success = Empty,
+            latency_ms = Empty,
+        );
+
+        let started = Instant::now();
+        let mut result = match target.kind {
+            CheckKind::Ping => {
+                self.ping
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+            CheckKind::Http => {
+                self.http
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+            CheckKind::Grpc => {
+                self.grpc
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+            CheckKind::Tcp => {
+                self.tcp
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+            CheckKind::Udp => {
+                self.udp
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+            CheckKind::TlsCert => {
+                self.tls
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+            CheckKind::Dns => {
+                self.dns
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+            CheckKind::Plugin => {
+                self.plugin
+                    .check(agent_id, endpoint)
+                    .instrument(span.clone())
+                    .await
+            }
+        };
+
+        if let Some(budget) = &self.latency_budget {
+            budget.apply(endpoint, &mut result, started.elapsed());
+        }
+
+        span.record("result_id", tracing::field::display(result.id));
+        span.record("success", result.is_successful());
+        if let Some(latency_ms) = result.response_time_ms() {
+            span.record("latency_ms", latency_ms);
+        }
+        span.in_scope(|| self.success_log.log(&target, &result));
+        result
+    }
+
+    /// Whether `result` is to be followed by a traceroute: a ping check
+    /// that got no reply, with `monitoring.traceroute_on_failure` set. Other
+    /// check kinds and hosts that did not resolve are not traced.
+    pub fn traces_after(&self, result: &MonitoringResult) -> bool {
+        let CheckType::PingCheck(ping) = &result.check_type else {
+            return false;
+        };
+        self.traceroute.is_some()
+            && ping.result.successes == 0
+            && result.failure_kind != Some(FailureKind::Unresolved)
+    }
+
+    /// Trace the path to `endpoint` inside a `traceroute` span, `None`
+    /// unless `monitoring.traceroute_on_failure` is set
+    pub async fn traceroute(
+        &self,
+        agent_id: Uuid,
+        endpoint: &Endpoint,
+    ) -> Option<MonitoringResult> {
+        let traceroute = self.traceroute.as_ref()?;
+        let span = info_span!(
+            "traceroute",
+            endpoint_id = %endpoint.id,
+            target = %endpoint.check_target().host,
+        );
+        Some(traceroute.check(agent_id, endpoint).instrument(span).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::sync::Arc;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    #[derive(Default)]
+    struct Fields(HashMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    struct CapturedSpan {
+        id: Id,
+        name: &'static str,
+        fields: Fields,
+    }
+
+    /// Layer recording the name and fields of every span
+    #[derive(Clone, Default)]
+    struct SpanCapture(Arc<Mutex<Vec<CapturedSpan>>>);
+
+    impl SpanCapture {
+        fn fields(&self, name: &str) -> Vec<HashMap<String, String>> {
+            self.0
+                .lock()
+                .iter()
+                .filter(|span| span.name == name)
+                .map(|span| span.fields.0.clone())
+                .collect()
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for SpanCapture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            self.0.lock().push(CapturedSpan {
+                id: id.clone(),
+                name: attrs.metadata().name(),
+                fields,
+            });
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            if let Some(span) = self.0.lock().iter_mut().find(|span| span.id == *id) {
+                values.record(&mut span.fields);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_produces_span_without_secrets() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/health")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body("ok")
+            .create_async()
+            .await;
+        let address =
+            server.url().replace("http://", "http://monitor:s3cret@") + "/health?token=t0ken";
+        let endpoint = Endpoint::new(address);
+
+        let capture = SpanCapture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+        let checkers = Checkers::from_config(
+            &Config::default(),
+            &DnsCache::default(),
+            &PtrCache::default(),
+        )
+        .unwrap();
+        let result = checkers.check(Uuid::now_v7(), &endpoint).await;
+
+        let spans = capture.fields("check");
+        assert_eq!(spans.len(), 1);
+        let span = &spans[0];
+        assert_eq!(span["endpoint_id"], endpoint.id.to_string());
+        assert_eq!(span["kind"], "http");
+        assert_eq!(span["target"], "127.0.0.1");
+        assert_eq!(span["result_id"], result.id.to_string());
+        assert_eq!(span["success"], "true");
+        assert!(span["latency_ms"].parse::<f64>().is_ok());
+        for value in span.values() {
+            assert!(!value.contains("s3cret") && !value.contains("t0ken"));
+        }
+    }
+
+    /// Serve HTTP 200 to every connection after `delay`
+    async fn spawn_slow_server(delay: Duration) -> std::net::SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let _ = stream.read(&mut buf).await;
+                    tokio::time::sleep(delay).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                        )
+                        .await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_checks_connect_from_source_address() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answer HTTP 200 and record where every connection came from
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (peer_tx, mut peer_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut stream, peer)) = listener.accept().await {
+                let _ = peer_tx.send(peer.ip());
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                        )
+                        .await;
+                });
+            }
+        });
+
+        let source = std::net::IpAddr::from([127, 0, 0, 2]);
+        let mut config = Config::default();
+        config.monitoring.source_address = Some(source);
+        let Ok(checkers) =
+            Checkers::from_config(&config, &DnsCache::default(), &PtrCache::default())
+        else {
+            // ICMP sockets may be unavailable in restricted environments.
+            return;
+        };
+
+        for endpoint in [
+            Endpoint::new(format!("http://{}/health", addr)),
+            Endpoint::new(format!("tcp://{}", addr)),
+        ] {
+            let result = checkers.check(Uuid::now_v7(), &endpoint).await;
+            assert!(result.is_successful(), "{:?}", result.error_message());
+            assert_eq!(peer_rx.recv().await, Some(source), "{}", endpoint.address);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_over_latency_budget_is_flagged() {
+        let addr = spawn_slow_server(Duration::from_millis(300)).await;
+        let endpoint = Endpoint::new(format!("http://{}/health", addr));
+
+        let mut config = Config::default();
+        config.monitoring.interval_secs = 1;
+        config.monitoring.timeout_secs = 5;
+        // 10% of a 1s interval: 100 ms
+        config.monitoring.check_latency_budget_percent = Some(10);
+
+        let checkers =
+            Checkers::from_config(&config, &DnsCache::default(), &PtrCache::default()).unwrap();
+        let result = checkers.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(result.is_successful(), "a slow check still succeeds");
+        assert!(result.is_degraded());
+        assert_eq!(result.notes.len(), 1);
+        assert!(result.notes[0].contains("latency budget of 100 ms"));
+    }
+
+    #[tokio::test]
+    async fn test_check_within_latency_budget_is_not_flagged() {
+        let addr = spawn_slow_server(Duration::ZERO).await;
+        let endpoint = Endpoint::new(format!("http://{}/health", addr));
+
+        let mut config = Config::default();
+        config.monitoring.interval_secs = 60;
+        config.monitoring.check_latency_budget_percent = Some(25);
+
+        let checkers =
+            Checkers::from_config(&config, &DnsCache::default(), &PtrCache::default()).unwrap();
+        let result = checkers.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(result.is_successful());
+        assert!(!result.is_degraded());
+        assert!(result.notes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_unanswered_pings_are_traced() {
+        let mut config = Config::default();
+        config.monitoring.timeout_secs = 1;
+        config.monitoring.ping_count = 1;
+        config.monitoring.traceroute_on_failure = true;
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checkers) =
+            Checkers::from_config(&config, &DnsCache::default(), &PtrCache::default())
+        else {
+            return;
+        };
+        let agent_id = Uuid::now_v7();
+
+        // TEST-NET-2 is never routed to a host that answers
+        let unanswered = checkers
+            .check(agent_id, &Endpoint::new("198.51.100.1"))
+            .await;
+        assert!(checkers.traces_after(&unanswered));
+
+        let answered = checkers.check(agent_id, &Endpoint::new("127.0.0.1")).await;
+        assert!(!checkers.traces_after(&answered));
+
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let failed_http = checkers
+            .check(agent_id, &Endpoint::new(format!("http://{}/", closed)))
+            .await;
+        assert!(!failed_http.is_successful());
+        assert!(!checkers.traces_after(&failed_http));
+
+        config.monitoring.traceroute_on_failure = false;
+        let checkers =
+            Checkers::from_config(&config, &DnsCache::default(), &PtrCache::default()).unwrap();
+        assert!(!checkers.traces_after(&unanswered));
+        assert!(checkers
+            .traceroute(agent_id, &Endpoint::new("198.51.100.1"))
+            .await
+            .is_none());
+    }
+
+    fn success_log(mode: Option<SuccessLogging>, sample_rate: u32) -> SuccessLog {
+        let mut config = Config::default();
+        config.logging.log_successful_checks = mode;
+        config.logging.success_sample_rate = sample_rate;
+        SuccessLog::from_config(&config)
+    }
+
+    #[test]
+    fn test_sampled_success_log_is_about_one_in_n() {
+        let log = success_log(Some(SuccessLogging::Sampled), 10);
+        let logged = (0..10_000).filter(|_| log.sample()).count();
+        // Expected 1000, standard deviation 30
+        assert!((850..=1150).contains(&logged), "logged {} of 10000", logged);
+    }
+
+    #[test]
+    fn test_success_log_modes() {
+        let every = success_log(Some(SuccessLogging::Every), 10);
+        assert!((0..100).all(|_| every.sample()));
+
+        let off = success_log(Some(SuccessLogging::Off), 1);
+        assert!(!(0..100).any(|_| off.sample()));
+
+        // Unset keeps successes at debug level only
+        let unset = success_log(None, 1);
+        assert!(!(0..100).any(|_| unset.sample()));
+    }
+}
@@ -0,0 +1,188 @@
+//! Per-endpoint circuit breaker
+//!
+//! An endpoint that fails `failure_threshold` checks in a row has its circuit
+//! opened: its checks are skipped for `cooldown_secs`. Once the cooldown has
+//! passed a single trial check runs. Success closes the circuit again; another
+//! failure reopens it for a further cooldown.
+//!
+//! Skipped checks produce no `MonitoringResult`. They are counted per
+//! [`SkipReason`] in `AgentStatus::checks_skipped` instead, so "suppressed"
+//! can be told apart from "down".
+
+use crate::agent_config::CircuitBreakerConfig;
+use crate::core::{Endpoint, SkipReason};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Consecutive failures and open state of one endpoint
+#[derive(Debug, Default)]
+struct EndpointCircuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Circuit state of every endpoint, kept across check ticks
+#[derive(Debug, Default)]
+pub(crate) struct CircuitBreaker {
+    config: Option<CircuitBreakerConfig>,
+    circuits: HashMap<Uuid, EndpointCircuit>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: Option<CircuitBreakerConfig>) -> Self {
+        Self {
+            config,
+            circuits: HashMap::new(),
+        }
+    }
+
+    /// Apply hot-reloaded settings, closing every circuit when they change
+    pub(crate) fn update_config(&mut self, config: Option<&CircuitBreakerConfig>) {
+        if self.config.as_ref() != config {
+            *self = Self::new(config.cloned());
+        }
+    }
+
+    /// Forget endpoints that are no longer configured
+    pub(crate) fn retain(&mut self, endpoints: &[Endpoint]) {
+        self.circuits
+            .retain(|id, _| endpoints.iter().any(|e| e.id == *id));
+    }
+
+    /// Whether checks of `endpoint_id` are currently suspended
+    pub(crate) fn is_open(&self, endpoint_id: Uuid, now: Instant) -> bool {
+        self.circuits
+            .get(&endpoint_id)
+            .and_then(|circuit| circuit.open_until)
+            .is_some_and(|until| now < until)
+    }
+
+    /// Record the outcome of a check of `endpoint_id`
+    pub(crate) fn record(&mut self, endpoint_id: Uuid, success: bool, now: Instant) {
+        let Some(config) = &self.config else {
+            return;
+        };
+
+        let circuit = self.circuits.entry(endpoint_id).or_default();
+        if success {
+            if circuit.open_until.take().is_some() {
+                info!("Circuit for endpoint {} closed", endpoint_id);
+            }
+            circuit.consecutive_failures = 0;
+            return;
+        }
+
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+        if circuit.consecutive_failures >= config.failure_threshold {
+            warn!(
+                "Circuit for endpoint {} open after {} consecutive failures, skipping checks for {:?}",
+                endpoint_id,
+                circuit.consecutive_failures,
+                config.cooldown()
+            );
+            circuit.open_until = Some(now + config.cooldown());
+        }
+    }
+}
+
+/// Why `endpoint` should not be checked this tick, if at all
+pub(crate) fn skip_reason(
+    endpoint: &Endpoint,
+    breaker: &CircuitBreaker,
+    now: Instant,
+) -> Option<SkipReason> {
+    if !endpoint.enabled {
+        Some(SkipReason::Disabled)
+    } else if breaker.is_open(endpoint.id, now) {
+        Some(SkipReason::CircuitOpen)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn breaker(failure_threshold: u32) -> CircuitBreaker {
+        CircuitBreaker::new(Some(CircuitBreakerConfig {
+            failure_threshold,
+            cooldown_secs: 60,
+        }))
+    }
+
+    #[test]
+    fn test_disabled_endpoint_is_skipped_as_disabled() {
+        let mut endpoint = Endpoint::new("10.0.0.1");
+        endpoint.enabled = false;
+
+        assert_eq!(
+            skip_reason(&endpoint, &breaker(3), Instant::now()),
+            Some(SkipReason::Disabled)
+        );
+    }
+
+    #[test]
+    fn test_circuit_open_endpoint_is_skipped_as_circuit_open() {
+        let endpoint = Endpoint::new("10.0.0.1");
+        let mut breaker = breaker(2);
+        let now = Instant::now();
+
+        breaker.record(endpoint.id, false, now);
+        assert_eq!(skip_reason(&endpoint, &breaker, now), None);
+
+        breaker.record(endpoint.id, false, now);
+        assert_eq!(
+            skip_reason(&endpoint, &breaker, now),
+            Some(SkipReason::CircuitOpen)
+        );
+    }
+
+    #[test]
+    fn test_trial_check_after_cooldown() {
+        let endpoint = Endpoint::new("10.0.0.1");
+        let mut breaker = breaker(1);
+        let now = Instant::now();
+        let after_cooldown = now + Duration::from_secs(61);
+
+        breaker.record(endpoint.id, false, now);
+        assert!(breaker.is_open(endpoint.id, now));
+        assert!(!breaker.is_open(endpoint.id, after_cooldown));
+
+        // A failed trial reopens the circuit straight away.
+        breaker.record(endpoint.id, false, after_cooldown);
+        assert!(breaker.is_open(endpoint.id, after_cooldown));
+
+        // A successful trial closes it.
+        let later = after_cooldown + Duration::from_secs(61);
+        breaker.record(endpoint.id, true, later);
+        assert!(!breaker.is_open(endpoint.id, later));
+    }
+
+    #[test]
+    fn test_success_resets_failure_count() {
+        let endpoint = Endpoint::new("10.0.0.1");
+        let mut breaker = breaker(2);
+        let now = Instant::now();
+
+        breaker.record(endpoint.id, false, now);
+        breaker.record(endpoint.id, true, now);
+        breaker.record(endpoint.id, false, now);
+        assert!(!breaker.is_open(endpoint.id, now));
+    }
+
+    #[test]
+    fn test_disabled_breaker_never_opens() {
+        let endpoint = Endpoint::new("10.0.0.1");
+        let mut breaker = CircuitBreaker::new(None);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            breaker.record(endpoint.id, false, now);
+        }
+        assert_eq!(skip_reason(&endpoint, &breaker, now), None);
+    }
+}
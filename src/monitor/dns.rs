@@ -0,0 +1,470 @@
+//! DNS record checks
+//!
+//! Endpoints with a `dns://nameserver[:port]/name` address query that
+//! nameserver over UDP (port 53 without one) for records of `name`. Plain
+//! hostnames with `check_type = "dns"` are queried through the agent's
+//! resolver: the nameservers of `monitoring.resolver`, or the system's
+//! (`/etc/resolv.conf`). Either way the query bypasses the hosts file and
+//! every check asks the nameserver again instead of answering from a cache.
+//!
+//! The endpoint's `record_type` selects A (default), AAAA, MX, TXT or CNAME
+//! records. Answers are reported as text: addresses, `preference exchange`
+//! for MX, the concatenated strings of a TXT record and the target of a
+//! CNAME, names without the trailing dot.
+//!
+//! A check succeeds when the answer holds records of the queried type and,
+//! with `expected_answers` set, every expected answer among them. Names are
+//! compared case-insensitively and with or without the trailing dot. A
+//! name that does not exist or has no such records fails as `unresolved`,
+//! a nameserver that does not answer within the check timeout as
+//! `unreachable`, and a missing expected answer as `other`.
+
+use super::resolver::{nameserver_config, Resolver};
+use crate::agent_config::DnsProtocol;
+use crate::core::{
+    CheckTarget, CheckType, DnsCheck, DnsCheckType, DnsRecordType, DnsResult, Endpoint,
+    ErrorDetails, FailureKind, MonitoringResult,
+};
+use chrono::Utc;
+use hickory_resolver::config::{ResolveHosts, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use hickory_resolver::proto::ProtoErrorKind;
+use hickory_resolver::{ResolveError, ResolveErrorKind, TokioResolver};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tracing::trace;
+use uuid::Uuid;
+
+/// Port queried when a `dns://` URL has none
+const DEFAULT_DNS_PORT: u16 = 53;
+
+/// DNS checker for `dns://` endpoints and endpoints with `check_type = "dns"`
+pub struct DnsChecker {
+    timeout: Duration,
+    resolver: Resolver,
+    nameservers: Option<ResolverConfig>,
+}
+
+impl DnsChecker {
+    /// Create a DNS checker that gives up on a query after `timeout`,
+    /// querying the system's nameservers for plain hostnames
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            resolver: Resolver::new(timeout),
+            nameservers: None,
+        }
+    }
+
+    /// Resolve the nameserver hostnames of `dns://` URLs with `resolver`
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Query plain hostnames at the nameservers of `config` instead of the
+    /// system's
+    pub(crate) fn with_nameservers(mut self, config: ResolverConfig) -> Self {
+        self.nameservers = Some(config);
+        self
+    }
+
+    /// Perform a DNS check on the given endpoint
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let target = endpoint.check_target();
+        let (result, failure_kind) = self.query(&target, endpoint).await;
+
+        trace!(
+            "DNS check of {} {} ({:?}): answers={:?}, time={:.2?} ms",
+            query_type(result.record_type),
+            result.name,
+            result.nameserver,
+            result.answers,
+            result.resolve_time_ms
+        );
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: endpoint.id,
+            check_type: CheckType::DnsCheck(DnsCheck {
+                r#type: DnsCheckType::Dns,
+                result,
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Query the records `endpoint` asks for and compare them with its
+    /// expected answers. Returns the kind of failure if the check failed.
+    async fn query(
+        &self,
+        target: &CheckTarget,
+        endpoint: &Endpoint,
+    ) -> (DnsResult, Option<FailureKind>) {
+        let record_type = endpoint.record_type.unwrap_or_default();
+        let mut result = DnsResult {
+            name: target.dns_name().unwrap_or_default().to_string(),
+            record_type,
+            nameserver: None,
+            answers: Vec::new(),
+            resolve_time_ms: None,
+            success: false,
+            error_details: None,
+        };
+
+        let config = match target.scheme {
+            Some(_) => {
+                let ip = match self.resolver.resolve(&target.host).await.0 {
+                    Ok(ip) => ip,
+                    Err(e) => {
+                        return failure(
+                            result,
+                            format!("Failed to resolve nameserver: {}", e),
+                            FailureKind::Unresolved,
+                        )
+                    }
+                };
+                let addr = SocketAddr::new(ip, target.port.unwrap_or(DEFAULT_DNS_PORT));
+                result.nameserver = Some(addr.to_string());
+                Some(nameserver_config(&[addr], DnsProtocol::Udp, None))
+            }
+            None => self.nameservers.clone(),
+        };
+        let resolver = match self.build_resolver(config) {
+            Ok(resolver) => resolver,
+            Err(e) => {
+                return failure(
+                    result,
+                    format!("Failed to read the system resolver configuration: {}", e),
+                    FailureKind::Other,
+                )
+            }
+        };
+
+        let query_type = query_type(record_type);
+        let start = Instant::now();
+        let lookup = tokio::time::timeout(
+            self.timeout,
+            resolver.lookup(result.name.as_str(), query_type),
+        )
+        .await;
+        result.resolve_time_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+
+        let lookup = match lookup {
+            Ok(Ok(lookup)) => lookup,
+            Ok(Err(e)) => {
+                let kind = classify_resolve(&e);
+                return failure(
+                    result,
+                    format!("DNS query for {} failed: {}", query_type, e),
+                    kind,
+                );
+            }
+            Err(_) => {
+                return failure(
+                    result,
+                    format!("DNS query timeout after {:?}", self.timeout),
+                    FailureKind::Unreachable,
+                )
+            }
+        };
+
+        result.answers = lookup
+            .records()
+            .iter()
+            .filter(|record| record.record_type() == query_type)
+            .filter_map(|record| answer_text(record.data()))
+            .collect();
+        if result.answers.is_empty() {
+            let error = format!("No {} records for {}", query_type, result.name);
+            return failure(result, error, FailureKind::Unresolved);
+        }
+
+        let missing: Vec<&str> = endpoint
+            .expected_answers
+            .iter()
+            .filter(|expected| {
+                !result
+                    .answers
+                    .iter()
+                    .any(|answer| same_answer(answer, expected))
+            })
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            let error = format!(
+                "Expected answers missing: {} (got {})",
+                missing.join(", "),
+                result.answers.join(", ")
+            );
+            return failure(result, error, FailureKind::Other);
+        }
+
+        result.success = true;
+        (result, None)
+    }
+
+    /// Resolver querying the nameservers of `config`, or the system's
+    /// without one, without caching answers
+    fn build_resolver(
+        &self,
+        config: Option<ResolverConfig>,
+    ) -> std::result::Result<TokioResolver, ResolveError> {
+        let mut builder = match config {
+            Some(config) => {
+                TokioResolver::builder_with_config(config, TokioConnectionProvider::default())
+            }
+            None => TokioResolver::builder_tokio()?,
+        };
+        let options = builder.options_mut();
+        options.use_hosts_file = ResolveHosts::Never;
+        options.cache_size = 0;
+        options.timeout = self.timeout;
+        Ok(builder.build())
+    }
+}
+
+/// Record type to query for `record_type`
+fn query_type(record_type: DnsRecordType) -> RecordType {
+    match record_type {
+        DnsRecordType::A => RecordType::A,
+        DnsRecordType::Aaaa => RecordType::AAAA,
+        DnsRecordType::Mx => RecordType::MX,
+        DnsRecordType::Txt => RecordType::TXT,
+        DnsRecordType::Cname => RecordType::CNAME,
+    }
+}
+
+/// Text of an answer record, `None` for record data the check does not query
+fn answer_text(data: &RData) -> Option<String> {
+    let name =
+        |name: &hickory_resolver::proto::rr::Name| name.to_utf8().trim_end_matches('.').to_string();
+    match data {
+        RData::A(a) => Some(a.0.to_string()),
+        RData::AAAA(aaaa) => Some(aaaa.0.to_string()),
+        RData::MX(mx) => Some(format!("{} {}", mx.preference(), name(mx.exchange()))),
+        RData::TXT(txt) => Some(
+            txt.iter()
+                .map(|part| String::from_utf8_lossy(part))
+                .collect(),
+        ),
+        RData::CNAME(cname) => Some(name(&cname.0)),
+        _ => None,
+    }
+}
+
+/// Whether `answer` matches `expected`, ignoring case and trailing dots
+fn same_answer(answer: &str, expected: &str) -> bool {
+    answer
+        .trim_end_matches('.')
+        .eq_ignore_ascii_case(expected.trim().trim_end_matches('.'))
+}
+
+/// Failure kind of a query that returned an error
+fn classify_resolve(e: &ResolveError) -> FailureKind {
+    if e.is_nx_domain() || e.is_no_records_found() {
+        return FailureKind::Unresolved;
+    }
+    match e.kind() {
+        ResolveErrorKind::Proto(proto) if matches!(proto.kind(), ProtoErrorKind::Timeout) => {
+            FailureKind::Unreachable
+        }
+        _ => FailureKind::Other,
+    }
+}
+
+/// `result` failed with `error`
+fn failure(
+    mut result: DnsResult,
+    error: String,
+    kind: FailureKind,
+) -> (DnsResult, Option<FailureKind>) {
+    result.success = false;
+    result.error_details = Some(ErrorDetails {
+        errors: Some(vec![error]),
+    });
+    (result, Some(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::EndpointCheckType;
+    use hickory_resolver::proto::op::{Message, MessageType, ResponseCode};
+    use hickory_resolver::proto::rr::rdata::{A, MX, TXT};
+    use hickory_resolver::proto::rr::{Name, Record};
+    use std::str::FromStr;
+
+    /// UDP nameserver answering A, MX and TXT queries for `example.test`
+    /// and NXDOMAIN for every other name
+    async fn spawn_nameserver() -> SocketAddr {
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(request) = Message::from_vec(&buf[..len]) else {
+                    continue;
+                };
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(request.op_code())
+                    .set_recursion_desired(request.recursion_desired())
+                    .set_recursion_available(true)
+                    .set_authoritative(true);
+                for query in request.queries() {
+                    response.add_query(query.clone());
+                    let name = query.name().clone();
+                    if name != Name::from_str("example.test.").unwrap() {
+                        response.set_response_code(ResponseCode::NXDomain);
+                        continue;
+                    }
+                    let data = match query.query_type() {
+                        RecordType::A => vec![
+                            RData::A(A::new(192, 0, 2, 10)),
+                            RData::A(A::new(192, 0, 2, 11)),
+                        ],
+                        RecordType::MX => vec![RData::MX(MX::new(
+                            10,
+                            Name::from_str("mail.example.test.").unwrap(),
+                        ))],
+                        RecordType::TXT => {
+                            vec![RData::TXT(TXT::new(vec!["v=spf1 -all".to_string()]))]
+                        }
+                        _ => Vec::new(),
+                    };
+                    for data in data {
+                        response.add_answer(Record::from_rdata(name.clone(), 60, data));
+                    }
+                }
+                let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+            }
+        });
+
+        addr
+    }
+
+    fn dns_result(result: &MonitoringResult) -> &DnsResult {
+        match &result.check_type {
+            CheckType::DnsCheck(c) => &c.result,
+            other => panic!("expected a DNS result, got {:?}", other),
+        }
+    }
+
+    async fn check(endpoint: &Endpoint) -> MonitoringResult {
+        DnsChecker::new(Duration::from_secs(5))
+            .check(Uuid::now_v7(), endpoint)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_a_records_are_answered() {
+        let addr = spawn_nameserver().await;
+        let endpoint = Endpoint::new(format!("dns://{}/example.test", addr));
+
+        let result = check(&endpoint).await;
+
+        let dns = dns_result(&result);
+        assert!(result.is_successful(), "{:?}", dns);
+        assert_eq!(result.failure_kind, None);
+        assert_eq!(dns.name, "example.test");
+        assert_eq!(dns.record_type, DnsRecordType::A);
+        assert_eq!(dns.nameserver, Some(addr.to_string()));
+        assert_eq!(dns.answers, vec!["192.0.2.10", "192.0.2.11"]);
+        assert!(dns.resolve_time_ms.is_some());
+        assert!(dns.error_details.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expected_answers_must_all_be_present() {
+        let addr = spawn_nameserver().await;
+        let endpoint = Endpoint::new(format!("dns://{}/example.test", addr));
+
+        let matching = endpoint
+            .clone()
+            .with_record_type(DnsRecordType::Mx)
+            .with_expected_answers(vec!["10 MAIL.example.test.".to_string()]);
+        let result = check(&matching).await;
+        assert!(result.is_successful(), "{:?}", dns_result(&result));
+        assert_eq!(dns_result(&result).answers, vec!["10 mail.example.test"]);
+
+        let mismatch = endpoint
+            .with_expected_answers(vec!["192.0.2.10".to_string(), "198.51.100.1".to_string()]);
+        let result = check(&mismatch).await;
+        assert!(!result.is_successful());
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+        assert_eq!(dns_result(&result).answers.len(), 2);
+        assert_eq!(
+            result.error_message().as_deref(),
+            Some("Expected answers missing: 198.51.100.1 (got 192.0.2.10, 192.0.2.11)")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_txt_record_strings_are_joined() {
+        let addr = spawn_nameserver().await;
+        let endpoint = Endpoint::new(format!("dns://{}/example.test", addr))
+            .with_record_type(DnsRecordType::Txt)
+            .with_expected_answers(vec!["v=spf1 -all".to_string()]);
+
+        let result = check(&endpoint).await;
+
+        assert!(result.is_successful(), "{:?}", dns_result(&result));
+    }
+
+    #[tokio::test]
+    async fn test_missing_name_is_unresolved() {
+        let addr = spawn_nameserver().await;
+        let endpoint = Endpoint::new(format!("dns://{}/missing.test", addr));
+
+        let result = check(&endpoint).await;
+
+        let dns = dns_result(&result);
+        assert!(!dns.success);
+        assert!(dns.answers.is_empty());
+        assert_eq!(result.failure_kind, Some(FailureKind::Unresolved));
+    }
+
+    #[tokio::test]
+    async fn test_plain_hostname_uses_configured_nameservers() {
+        let addr = spawn_nameserver().await;
+        let checker = DnsChecker::new(Duration::from_secs(5)).with_nameservers(nameserver_config(
+            &[addr],
+            DnsProtocol::Udp,
+            None,
+        ));
+        let endpoint = Endpoint::new("example.test").with_check_type(EndpointCheckType::Dns);
+
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let dns = dns_result(&result);
+        assert!(result.is_successful(), "{:?}", dns);
+        assert_eq!(dns.nameserver, None);
+        assert_eq!(dns.answers.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_silent_nameserver_times_out() {
+        let silent = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = silent.local_addr().unwrap();
+        let endpoint = Endpoint::new(format!("dns://{}/example.test", addr));
+
+        let checker = DnsChecker::new(Duration::from_millis(200));
+        let started = Instant::now();
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!result.is_successful());
+        assert_eq!(result.failure_kind, Some(FailureKind::Unreachable));
+    }
+}
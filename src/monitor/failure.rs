@@ -0,0 +1,231 @@
+//! Classification of check failures
+//!
+//! Failed results carry a [`FailureKind`] telling apart the ways a target
+//! can fail to answer:
+//!
+//! - `refused`: the host answered with a TCP RST, nothing listens on the port
+//! - `unreachable`: no answer arrived in time, or the route is down
+//! - `blocked`: a firewall reported the traffic as administratively
+//!   prohibited, or the local host refused to send it
+//! - `unresolved`: the hostname did not resolve
+//! - `other`: the target answered but the check failed anyway
+//!
+//! Checks that make several attempts report the most telling kind among
+//! them, see [`dominant`].
+
+use super::resolver::LookupFailed;
+use crate::core::FailureKind;
+use std::error::Error as StdError;
+use std::io;
+
+/// ICMPv4 echo reply
+const ICMPV4_ECHO_REPLY: u8 = 0;
+/// ICMPv4 destination unreachable
+const ICMPV4_DEST_UNREACHABLE: u8 = 3;
+/// ICMPv4 time exceeded
+const ICMPV4_TIME_EXCEEDED: u8 = 11;
+/// Destination unreachable codes for network (9) and host (10)
+/// administratively prohibited, and communication administratively
+/// prohibited (13)
+const ICMPV4_PROHIBITED_CODES: [u8; 3] = [9, 10, 13];
+
+/// ICMPv6 echo reply
+const ICMPV6_ECHO_REPLY: u8 = 129;
+/// ICMPv6 destination unreachable
+const ICMPV6_DEST_UNREACHABLE: u8 = 1;
+/// ICMPv6 time exceeded
+const ICMPV6_TIME_EXCEEDED: u8 = 3;
+/// Destination unreachable codes for communication administratively
+/// prohibited (1), source address failed ingress/egress policy (5) and
+/// reject route to destination (6)
+const ICMPV6_PROHIBITED_CODES: [u8; 3] = [1, 5, 6];
+
+/// Kind of a failed socket operation
+pub(crate) fn classify_io(e: &io::Error) -> FailureKind {
+    match e.kind() {
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset => FailureKind::Refused,
+        io::ErrorKind::TimedOut
+        | io::ErrorKind::HostUnreachable
+        | io::ErrorKind::NetworkUnreachable
+        | io::ErrorKind::NetworkDown => FailureKind::Unreachable,
+        // Local firewall rules fail sends with EPERM or EACCES
+        io::ErrorKind::PermissionDenied => FailureKind::Blocked,
+        _ => FailureKind::Other,
+    }
+}
+
+/// Kind of a failed request, found by walking `e`'s source chain down to
+/// the error that ended it
+pub(crate) fn classify_error(e: &(dyn StdError + 'static)) -> FailureKind {
+    let mut source = Some(e);
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            match classify_io(e) {
+                // `source()` of an io error skips the error it wraps
+                FailureKind::Other if e.get_ref().is_some() => {
+                    source = e.get_ref().map(|inner| inner as _);
+                    continue;
+                }
+                FailureKind::Other => {}
+                kind => return kind,
+            }
+        }
+        if e.downcast_ref::<reqwest::Error>()
+            .is_some_and(reqwest::Error::is_timeout)
+            || e.is::<tonic::TimeoutExpired>()
+        {
+            return FailureKind::Unreachable;
+        }
+        // Lookups of the HTTP client go through the agent's resolver
+        if e.is::<LookupFailed>() || e.is::<hickory_resolver::ResolveError>() {
+            return FailureKind::Unresolved;
+        }
+        source = e.source();
+    }
+    FailureKind::Other
+}
+
+/// Kind of an ICMPv4 reply to an echo request, `None` for an echo reply
+pub(crate) fn classify_icmpv4(icmp_type: u8, code: u8) -> Option<FailureKind> {
+    match icmp_type {
+        ICMPV4_ECHO_REPLY => None,
+        ICMPV4_DEST_UNREACHABLE if ICMPV4_PROHIBITED_CODES.contains(&code) => {
+            Some(FailureKind::Blocked)
+        }
+        ICMPV4_DEST_UNREACHABLE | ICMPV4_TIME_EXCEEDED => Some(FailureKind::Unreachable),
+        _ => Some(FailureKind::Other),
+    }
+}
+
+/// Kind of an ICMPv6 reply to an echo request, `None` for an echo reply
+pub(crate) fn classify_icmpv6(icmp_type: u8, code: u8) -> Option<FailureKind> {
+    match icmp_type {
+        ICMPV6_ECHO_REPLY => None,
+        ICMPV6_DEST_UNREACHABLE if ICMPV6_PROHIBITED_CODES.contains(&code) => {
+            Some(FailureKind::Blocked)
+        }
+        ICMPV6_DEST_UNREACHABLE | ICMPV6_TIME_EXCEEDED => Some(FailureKind::Unreachable),
+        _ => Some(FailureKind::Other),
+    }
+}
+
+/// The most telling of several attempts' failure kinds, `None` if there
+/// are none.
+///
+/// A single prohibited reply explains lost attempts better than the
+/// timeouts around it, and a refusal shows the host is up, so the order is
+/// blocked, refused, unreachable, unresolved, other.
+pub(crate) fn dominant(kinds: impl IntoIterator<Item = FailureKind>) -> Option<FailureKind> {
+    kinds.into_iter().min_by_key(|kind| match kind {
+        FailureKind::Blocked => 0,
+        FailureKind::Refused => 1,
+        FailureKind::Unreachable => 2,
+        FailureKind::Unresolved => 3,
+        FailureKind::Other => 4,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors() {
+        let kind = |kind| classify_io(&io::Error::from(kind));
+        assert_eq!(kind(io::ErrorKind::ConnectionRefused), FailureKind::Refused);
+        assert_eq!(kind(io::ErrorKind::ConnectionReset), FailureKind::Refused);
+        assert_eq!(kind(io::ErrorKind::TimedOut), FailureKind::Unreachable);
+        assert_eq!(
+            kind(io::ErrorKind::HostUnreachable),
+            FailureKind::Unreachable
+        );
+        assert_eq!(kind(io::ErrorKind::PermissionDenied), FailureKind::Blocked);
+        assert_eq!(kind(io::ErrorKind::InvalidData), FailureKind::Other);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_raw_os_errors() {
+        let kind = |code| classify_io(&io::Error::from_raw_os_error(code));
+        // ECONNREFUSED, ETIMEDOUT, EHOSTUNREACH, ENETUNREACH, EPERM, EACCES
+        assert_eq!(kind(111), FailureKind::Refused);
+        assert_eq!(kind(110), FailureKind::Unreachable);
+        assert_eq!(kind(113), FailureKind::Unreachable);
+        assert_eq!(kind(101), FailureKind::Unreachable);
+        assert_eq!(kind(1), FailureKind::Blocked);
+        assert_eq!(kind(13), FailureKind::Blocked);
+    }
+
+    #[test]
+    fn test_wrapped_errors_are_classified_by_their_cause() {
+        let refused = io::Error::other(io::Error::from(io::ErrorKind::ConnectionRefused));
+        assert_eq!(classify_error(&refused), FailureKind::Refused);
+
+        let unresolved = io::Error::other(LookupFailed(crate::error::Error::Network(
+            "no such host".into(),
+        )));
+        assert_eq!(classify_error(&unresolved), FailureKind::Unresolved);
+        let unresolved = io::Error::other(hickory_resolver::ResolveError::from("no records"));
+        assert_eq!(classify_error(&unresolved), FailureKind::Unresolved);
+
+        // Only the error type counts, not what the error says
+        let other = io::Error::other(crate::error::Error::Network("dns error".into()));
+        assert_eq!(classify_error(&other), FailureKind::Other);
+        assert_eq!(
+            classify_error(&io::Error::other("dns error")),
+            FailureKind::Other
+        );
+
+        assert_eq!(
+            classify_error(&tonic::TimeoutExpired(())),
+            FailureKind::Unreachable
+        );
+        assert_eq!(
+            classify_error(&io::Error::other("bad certificate")),
+            FailureKind::Other
+        );
+    }
+
+    #[test]
+    fn test_icmpv4_replies() {
+        assert_eq!(classify_icmpv4(0, 0), None);
+        // Network and host unreachable
+        assert_eq!(classify_icmpv4(3, 0), Some(FailureKind::Unreachable));
+        assert_eq!(classify_icmpv4(3, 1), Some(FailureKind::Unreachable));
+        // Administratively prohibited
+        for code in [9, 10, 13] {
+            assert_eq!(classify_icmpv4(3, code), Some(FailureKind::Blocked));
+        }
+        assert_eq!(classify_icmpv4(11, 0), Some(FailureKind::Unreachable));
+        assert_eq!(classify_icmpv4(5, 0), Some(FailureKind::Other));
+    }
+
+    #[test]
+    fn test_icmpv6_replies() {
+        assert_eq!(classify_icmpv6(129, 0), None);
+        // No route and address unreachable
+        assert_eq!(classify_icmpv6(1, 0), Some(FailureKind::Unreachable));
+        assert_eq!(classify_icmpv6(1, 3), Some(FailureKind::Unreachable));
+        for code in [1, 5, 6] {
+            assert_eq!(classify_icmpv6(1, code), Some(FailureKind::Blocked));
+        }
+        assert_eq!(classify_icmpv6(3, 0), Some(FailureKind::Unreachable));
+    }
+
+    #[test]
+    fn test_dominant_prefers_the_most_telling_kind() {
+        assert_eq!(dominant([]), None);
+        assert_eq!(
+            dominant([FailureKind::Unreachable, FailureKind::Blocked]),
+            Some(FailureKind::Blocked)
+        );
+        assert_eq!(
+            dominant([FailureKind::Unreachable, FailureKind::Refused]),
+            Some(FailureKind::Refused)
+        );
+        assert_eq!(
+            dominant([FailureKind::Other, FailureKind::Unreachable]),
+            Some(FailureKind::Unreachable)
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! gRPC health checking
+//!
+//! Calls `grpc.health.v1.Health/Check` on endpoints whose address is a
+//! `grpc://` or `grpcs://` URL. Only a `SERVING` response counts as healthy;
+//! `NOT_SERVING`, `UNKNOWN` and `SERVICE_UNKNOWN` are reported as failures.
+//!
+//! An endpoint `server_name` is sent as TLS SNI and `:authority` while the
+//! connection goes to the address' host.
+//!
+//! Hostnames resolve through the configured resolver backend
+//! (`monitoring.resolver`), honouring `monitoring.ip_version` and the DNS
+//! cache, and the channel connects to the address picked. A failed lookup
+//! fails as `unresolved`.
+//!
+//! With `monitoring.source_address` or `monitoring.source_port_range` set,
+//! the connection is opened from that local address or a port of that range.
+//!
+//! A connection that cannot be established fails with the kind the network
+//! reported (`refused`, `unreachable`, ...); a server that answers with
+//! anything but `SERVING` fails as `other`.
+
+use crate::core::{
+    CheckTarget, CheckType, Endpoint, ErrorDetails, FailureKind, GrpcHealthCheck,
+    GrpcHealthCheckType, GrpcHealthResult, MonitoringResult,
+};
+use crate::monitor::failure::classify_error;
+use crate::monitor::resolver::Resolver;
+use crate::monitor::source_port::Source;
+use chrono::Utc;
+use hyper_util::rt::TokioIo;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tonic::transport::{Channel, ClientTlsConfig, Uri};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use tracing::trace;
+use uuid::Uuid;
+
+/// gRPC checker for endpoints whose address is a `grpc://` or `grpcs://` URL
+pub struct GrpcHealthChecker {
+    timeout: Duration,
+    resolver: Resolver,
+    source: Source,
+}
+
+impl GrpcHealthChecker {
+    /// Create a new gRPC health checker
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            resolver: Resolver::new(timeout),
+            source: Source::default(),
+        }
+    }
+
+    /// Resolve hostnames with `resolver`
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Connect from the local address and port range of `source`
+    pub(crate) fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Perform a gRPC health check on the given endpoint
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let target = endpoint.check_target();
+
+        let (result, failure_kind) = match target.grpc_service() {
+            Some(service) => {
+                self.health_check(&target, endpoint.server_name.as_deref(), service)
+                    .await
+            }
+            None => (
+                failure(
+                    String::new(),
+                    format!("Endpoint address is not a gRPC URL: {}", endpoint.address),
+                ),
+                Some(FailureKind::Other),
+            ),
+        };
+
+        trace!(
+            "gRPC health check to {}: status={:?}, success={}, time={:.2?} ms",
+            endpoint.address,
+            result.serving_status,
+            result.success,
+            result.response_time_ms
+        );
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: endpoint.id,
+            check_type: CheckType::GrpcHealthCheck(GrpcHealthCheck {
+                r#type: GrpcHealthCheckType::Grpchealth,
+                result,
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Connect to `target` and ask for the serving status of `service`.
+    ///
+    /// The response time covers name resolution, connection setup and the
+    /// RPC itself.
+    /// Returns the kind of failure if the check failed.
+    async fn health_check(
+        &self,
+        target: &CheckTarget,
+        server_name: Option<&str>,
+        service: &str,
+    ) -> (GrpcHealthResult, Option<FailureKind>) {
+        let start = Instant::now();
+
+        let channel = match self.connect(target, server_name).await {
+            Ok(channel) => channel,
+            Err((e, kind)) => return (failure(service.to_string(), e), Some(kind)),
+        };
+
+        let request = HealthCheckRequest {
+            service: service.to_string(),
+        };
+        let response = HealthClient::new(channel).check(request).await;
+        let response_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        let status = match response {
+            Ok(response) => ServingStatus::try_from(response.into_inner().status)
+                .unwrap_or(ServingStatus::Unknown),
+            Err(status) => {
+                let mut result = failure(
+                    service.to_string(),
+                    format!("Health check RPC failed: {}", status),
+                );
+                result.response_time_ms = Some(response_time_ms);
+                return (result, Some(classify_error(&status)));
+            }
+        };
+
+        let success = status == ServingStatus::Serving;
+        let result = GrpcHealthResult {
+            service: service.to_string(),
+            serving_status: Some(status.as_str_name().to_string()),
+            response_time_ms: Some(response_time_ms),
+            error_details: if success {
+                None
+            } else {
+                Some(ErrorDetails {
+                    errors: Some(vec![format!("Service is {}", status.as_str_name())]),
+                })
+            },
+            success,
+        };
+        (result, (!success).then_some(FailureKind::Other))
+    }
+
+    /// Open a channel to the resolved address of `target`, using TLS for
+    /// `grpcs://` addresses and presenting `server_name` instead of the host
+    /// when given
+    async fn connect(
+        &self,
+        target: &CheckTarget,
+        server_name: Option<&str>,
+    ) -> Result<Channel, (String, FailureKind)> {
+        let invalid = |e: String| (e, FailureKind::Other);
+        let tls = target.scheme.as_deref() == Some("grpcs");
+        let host = if target.host.contains(':') {
+            format!("[{}]", target.host)
+        } else {
+            target.host.clone()
+        };
+        let scheme = if tls { "https" } else { "http" };
+        let port = target.port.unwrap_or(if tls { 443 } else { 80 });
+        let uri = format!("{}://{}:{}", scheme, host, port);
+
+        let mut channel = Channel::from_shared(uri.clone())
+            .map_err(|e| invalid(format!("Invalid gRPC address {}: {}", uri, e)))?
+            .connect_timeout(self.timeout)
+            .timeout(self.timeout);
+
+        if let Some(server_name) = server_name {
+            let origin = format!("{}://{}:{}", scheme, server_name, port);
+            channel = channel.origin(
+                origin
+                    .parse()
+                    .map_err(|e| invalid(format!("Invalid server name {}: {}", server_name, e)))?,
+            );
+        }
+
+        if tls {
+            let mut tls_config = ClientTlsConfig::new().with_webpki_roots();
+            if let Some(server_name) = server_name {
+                tls_config = tls_config.domain_name(server_name);
+            }
+            channel = channel
+                .tls_config(tls_config)
+                .map_err(|e| invalid(format!("Failed to configure TLS: {}", e)))?;
+        }
+
+        // The URI keeps the hostname for TLS and `:authority`; the socket
+        // goes to the address the resolver picked.
+        let ip = match self.resolver.resolve(&target.host).await {
+            (Ok(ip), _) => ip,
+            (Err(e), _) => {
+                return Err((
+                    format!("Failed to resolve address: {}", e),
+                    FailureKind::Unresolved,
+                ))
+            }
+        };
+        let addr = SocketAddr::new(ip, port);
+        let source = self.source;
+        let connector =
+            tower::service_fn(
+                move |_: Uri| async move { source.connect(addr).await.map(TokioIo::new) },
+            );
+        channel
+            .connect_with_connector(connector)
+            .await
+            .map_err(|e| {
+                let kind = classify_error(&e);
+                (
+                    format!("Failed to connect to {} ({}): {}", uri, ip, e),
+                    kind,
+                )
+            })
+    }
+}
+
+/// Failed result for checks that never received a serving status
+fn failure(service: String, error: String) -> GrpcHealthResult {
+    GrpcHealthResult {
+        service,
+        serving_status: None,
+        response_time_ms: None,
+        error_details: Some(ErrorDetails {
+            errors: Some(vec![error]),
+        }),
+        success: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::source_port::SourcePortRange;
+    use tokio::net::TcpListener;
+    use tonic::transport::Server;
+    use tonic_health::server::{health_reporter, HealthReporter};
+
+    fn grpc_result(result: &MonitoringResult) -> &GrpcHealthResult {
+        match &result.check_type {
+            CheckType::GrpcHealthCheck(c) => &c.result,
+            other => panic!("expected a gRPC health result, got {:?}", other),
+        }
+    }
+
+    /// Start a health server on a random local port
+    async fn start_health_server() -> (HealthReporter, String) {
+        let (reporter, service) = health_reporter();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        (reporter, format!("grpc://{}", addr))
+    }
+
+    #[tokio::test]
+    async fn test_serving_service_succeeds() {
+        let (reporter, base) = start_health_server().await;
+        reporter
+            .set_service_status("smotra.Test", tonic_health::ServingStatus::Serving)
+            .await;
+
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5));
+        let endpoint = Endpoint::new(format!("{}/smotra.Test", base));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let grpc = grpc_result(&result);
+        assert!(grpc.success);
+        assert_eq!(grpc.service, "smotra.Test");
+        assert_eq!(grpc.serving_status.as_deref(), Some("SERVING"));
+        assert!(grpc.response_time_ms.is_some());
+        assert!(result.is_successful());
+        assert_eq!(result.endpoint_id, endpoint.id);
+        assert_eq!(result.failure_kind, None);
+    }
+
+    #[tokio::test]
+    async fn test_connection_binds_within_source_port_range() {
+        use parking_lot::Mutex;
+        use std::sync::Arc;
+        use tokio_stream::StreamExt;
+
+        let (reporter, service) = health_reporter();
+        reporter
+            .set_service_status("smotra.Test", tonic_health::ServingStatus::Serving)
+            .await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer_ports = Arc::new(Mutex::new(Vec::new()));
+        let incoming = {
+            let peer_ports = Arc::clone(&peer_ports);
+            tokio_stream::wrappers::TcpListenerStream::new(listener).map(move |stream| {
+                if let Ok(stream) = &stream {
+                    peer_ports.lock().push(stream.peer_addr().unwrap().port());
+                }
+                stream
+            })
+        };
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(incoming),
+        );
+
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5)).with_source(Source::new(
+            None,
+            Some(SourcePortRange::new((42000, 42999))),
+        ));
+        let endpoint = Endpoint::new(format!("grpc://{}/smotra.Test", addr));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(result.is_successful(), "{:?}", grpc_result(&result));
+        let peer_ports = peer_ports.lock();
+        assert!(!peer_ports.is_empty());
+        assert!(peer_ports.iter().all(|port| (42000..=42999).contains(port)));
+    }
+
+    #[tokio::test]
+    async fn test_hostname_resolves_through_resolver() {
+        use crate::agent_config::IpVersion;
+        use crate::monitor::resolver::tests::SlowLookup;
+        use std::net::IpAddr;
+        use std::sync::Arc;
+
+        let (reporter, base) = start_health_server().await;
+        reporter
+            .set_service_status("smotra.Test", tonic_health::ServingStatus::Serving)
+            .await;
+        let port = base.rsplit(':').next().unwrap();
+
+        // Only the resolver knows the name, and only its IPv4 address is up
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: vec![
+                    "2001:db8::1".parse::<IpAddr>().unwrap(),
+                    IpAddr::from([127, 0, 0, 1]),
+                ],
+            }),
+            Duration::from_secs(1),
+        )
+        .with_ip_version(IpVersion::V4);
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5)).with_resolver(resolver);
+        let endpoint = Endpoint::new(format!("grpc://grpc.smotra.test:{}/smotra.Test", port));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(result.is_successful(), "{:?}", grpc_result(&result));
+    }
+
+    #[tokio::test]
+    async fn test_failed_lookup_is_unresolved() {
+        use crate::monitor::resolver::tests::SlowLookup;
+        use std::sync::Arc;
+
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: Vec::new(),
+            }),
+            Duration::from_secs(1),
+        );
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5)).with_resolver(resolver);
+        let result = checker
+            .check(
+                Uuid::now_v7(),
+                &Endpoint::new("grpc://missing.smotra.test:50051/smotra.Test"),
+            )
+            .await;
+
+        assert!(!result.is_successful());
+        assert!(result
+            .error_message()
+            .unwrap()
+            .starts_with("Failed to resolve address"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Unresolved));
+    }
+
+    #[tokio::test]
+    async fn test_not_serving_service_fails() {
+        let (reporter, base) = start_health_server().await;
+        reporter
+            .set_service_status("smotra.Test", tonic_health::ServingStatus::NotServing)
+            .await;
+
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5));
+        let endpoint = Endpoint::new(format!("{}/smotra.Test", base));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let grpc = grpc_result(&result);
+        assert!(!grpc.success);
+        assert_eq!(grpc.serving_status.as_deref(), Some("NOT_SERVING"));
+        assert!(result.error_message().unwrap().contains("NOT_SERVING"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+    }
+
+    #[tokio::test]
+    async fn test_closed_port_is_refused() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5));
+        let endpoint = Endpoint::new(format!("grpc://{}/smotra.Test", addr));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(!result.is_successful());
+        assert!(result
+            .error_message()
+            .unwrap()
+            .contains("Failed to connect"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Refused));
+    }
+
+    #[tokio::test]
+    async fn test_overall_server_health_without_service() {
+        let (_reporter, base) = start_health_server().await;
+
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5));
+        let result = checker.check(Uuid::now_v7(), &Endpoint::new(base)).await;
+
+        let grpc = grpc_result(&result);
+        assert!(grpc.success);
+        assert_eq!(grpc.service, "");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_service_fails() {
+        let (_reporter, base) = start_health_server().await;
+
+        let checker = GrpcHealthChecker::new(Duration::from_secs(5));
+        let endpoint = Endpoint::new(format!("{}/smotra.Missing", base));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let grpc = grpc_result(&result);
+        assert!(!grpc.success);
+        assert_eq!(grpc.serving_status, None);
+        assert!(result.error_message().unwrap().contains("RPC failed"));
+    }
+
+    #[tokio::test]
+    async fn test_non_grpc_address_fails() {
+        let checker = GrpcHealthChecker::new(Duration::from_secs(1));
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("example.com"))
+            .await;
+
+        assert!(!result.is_successful());
+        assert!(result.error_message().unwrap().contains("not a gRPC URL"));
+    }
+}
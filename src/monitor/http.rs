@@ -0,0 +1,1353 @@
+//! HTTP(S) GET monitoring
+//!
+//! An endpoint's `http` settings adjust the request: `method` replaces GET,
+//! `headers` are sent along (e.g. `Authorization`), `expected_status` lists
+//! the status codes that pass instead of any 2xx, and `follow_redirects =
+//! false` checks a redirect response itself instead of its target.
+//!
+//! The URL's host is resolved through the checker's [`Resolver`] before the
+//! request is sent, and the lookup time is recorded as `dns_resolution_ms`,
+//! apart from `response_time_ms`. The request then connects to that
+//! address; only hosts the check did not resolve itself, such as redirect
+//! targets, are resolved by the HTTP client.
+//!
+//! Endpoints with a `server_name` are checked as that virtual host on the
+//! address' host: the connection goes to the address' (resolved) IP while
+//! the TLS SNI and `Host` header carry `server_name`. This checks a specific
+//! vhost behind a shared-IP load balancer or CDN edge.
+//!
+//! The response body is streamed and counted rather than buffered. Reading
+//! stops at `monitoring.http.max_body_bytes` (default 1 MiB) and the result
+//! is flagged `body_truncated`, so large responses cost neither bandwidth nor
+//! memory beyond the limit. Without body assertions, `success` depends on
+//! the status code only, never on reading the body completely.
+//!
+//! Health endpoints that answer 200 while unhealthy can be checked by their
+//! body instead: with `body_must_contain` and/or `body_regex` set on the
+//! endpoint, the body (up to the size limit) is kept and the check only
+//! succeeds if the status is a success and every assertion holds. A body
+//! over the size limit fails its assertions unevaluated. The result
+//! records `body_matched` and a `body_match_detail` describing what was (not)
+//! found.
+//!
+//! `monitoring.http.connect_timeout_secs` bounds establishing the connection
+//! and `monitoring.http.read_timeout_secs` (default `monitoring.timeout_secs`)
+//! the whole request including the body. A check that runs into either
+//! before the status line arrived fails with an error naming the timeout,
+//! `Connect timeout after ...` or `Read timeout after ...`. A read timeout
+//! while the body is downloading is recorded the same way, but like a
+//! truncated body it only fails the check if body assertions needed the
+//! rest of the body.
+//!
+//! With `monitoring.http.verify_tls = false`, invalid or self-signed
+//! certificates of checked endpoints are accepted.
+//!
+//! With `monitoring.source_address` set, connections are opened from that
+//! local address.
+//!
+//! Failed checks record a failure kind: a refused connection is `refused`,
+//! a timeout `unreachable` and a failed lookup `unresolved`. Responses that
+//! fail on their status or body are `other`.
+
+use super::failure::classify_error;
+use super::resolver::Resolver;
+use crate::agent_config::DEFAULT_MAX_BODY_BYTES;
+use crate::core::{
+    CheckTarget, CheckType, Endpoint, ErrorDetails, FailureKind, HttpCheckConfig, HttpGetCheck,
+    HttpGetCheckType, HttpGetResult, MonitoringResult,
+};
+use crate::error::{Error, Result};
+use chrono::Utc;
+use parking_lot::Mutex;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::redirect::Policy;
+use reqwest::{Method, StatusCode};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::trace;
+use uuid::Uuid;
+
+/// HTTP checker for endpoints whose address is an `http://` or `https://` URL
+pub struct HttpGetChecker {
+    client: reqwest::Client,
+    /// Client for endpoints that do not follow redirects
+    no_redirect_client: reqwest::Client,
+    settings: ClientSettings,
+    max_body_bytes: u64,
+}
+
+/// Builder for an [`HttpGetChecker`]; both HTTP clients are built once, by
+/// [`HttpGetCheckerBuilder::build`], after every setting is known
+pub struct HttpGetCheckerBuilder {
+    settings: ClientSettings,
+    max_body_bytes: u64,
+}
+
+/// Settings the checker's HTTP clients are built with
+#[derive(Clone)]
+struct ClientSettings {
+    connect_timeout: Option<Duration>,
+    timeout: Duration,
+    verify_tls: bool,
+    resolver: PinnedResolver,
+    source_address: Option<IpAddr>,
+}
+
+/// Resolver of the HTTP clients. Hosts a check already resolved connect to
+/// the address it resolved; other hosts, e.g. redirect targets, are
+/// resolved through `resolver`.
+#[derive(Clone)]
+struct PinnedResolver {
+    resolver: Resolver,
+    /// Last address each checked host resolved to, keyed by lowercase host
+    pinned: Arc<Mutex<HashMap<String, IpAddr>>>,
+}
+
+impl HttpGetChecker {
+    /// Start building an HTTP checker that gives up connecting after
+    /// `connect_timeout` (if set) and on the whole request after `timeout`
+    pub fn builder(connect_timeout: Option<Duration>, timeout: Duration) -> HttpGetCheckerBuilder {
+        HttpGetCheckerBuilder {
+            settings: ClientSettings {
+                connect_timeout,
+                timeout,
+                verify_tls: true,
+                resolver: PinnedResolver::new(Resolver::new(timeout)),
+                source_address: None,
+            },
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+
+    /// Perform an HTTP GET check on the given endpoint
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let target = endpoint.check_target();
+        let prepared = HttpRequest::from_endpoint(endpoint)
+            .and_then(|request| Ok((request, BodyAssertion::from_endpoint(endpoint)?)));
+        let mut dns_resolution_ms = None;
+        let (mut result, failure_kind) = match (prepared, target.url()) {
+            (Err(e), _) => failure(e, FailureKind::Other),
+            (Ok(_), None) => failure(
+                format!(
+                    "Endpoint address is not an HTTP(S) URL: {}",
+                    endpoint.address
+                ),
+                FailureKind::Other,
+            ),
+            (Ok((request, assertion)), Some(url)) => {
+                let (resolved, lookup_time) = self.settings.resolver.resolve(&target.host).await;
+                dns_resolution_ms = lookup_time.map(|d| d.as_secs_f64() * 1000.0);
+                match (resolved, &endpoint.server_name) {
+                    (Err(e), _) => failure(e.to_string(), FailureKind::Unresolved),
+                    (Ok(ip), Some(server_name)) => {
+                        self.get_virtual_host(
+                            &target,
+                            ip,
+                            server_name,
+                            &request,
+                            assertion.as_ref(),
+                        )
+                        .await
+                    }
+                    (Ok(ip), None) => {
+                        self.settings.resolver.pin(&target.host, ip);
+                        let client = if request.settings.follow_redirects {
+                            &self.client
+                        } else {
+                            &self.no_redirect_client
+                        };
+                        self.get(client, &url, &request, assertion.as_ref()).await
+                    }
+                }
+            }
+        };
+        result.dns_resolution_ms = dns_resolution_ms;
+        let failure_kind = failure_kind.filter(|_| !result.success);
+
+        trace!(
+            "HTTP check to {}: status={}, success={}, time={:.2?} ms",
+            endpoint.address,
+            result.status_code,
+            result.success,
+            result.response_time_ms
+        );
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: endpoint.id,
+            check_type: CheckType::HttpGetCheck(HttpGetCheck {
+                r#type: HttpGetCheckType::Httpget,
+                result,
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Fetch `target`'s path from `server_name` while connecting to `ip`,
+    /// the address of `target`'s host, so SNI and `Host` carry
+    /// `server_name`
+    async fn get_virtual_host(
+        &self,
+        target: &CheckTarget,
+        ip: IpAddr,
+        server_name: &str,
+        request: &HttpRequest,
+        assertion: Option<&BodyAssertion>,
+    ) -> (HttpGetResult, Option<FailureKind>) {
+        let default_port = if target.scheme.as_deref() == Some("https") {
+            443
+        } else {
+            80
+        };
+        let addr = SocketAddr::new(ip, target.port.unwrap_or(default_port));
+
+        // Name resolution is pinned per client, so virtual host checks get a
+        // short-lived client of their own.
+        let builder = self
+            .settings
+            .client_builder()
+            .resolve(server_name, addr)
+            .redirect(request.redirect_policy());
+        let client = match build_client(builder) {
+            Ok(client) => client,
+            Err(e) => return failure(e.to_string(), FailureKind::Other),
+        };
+
+        let vhost = CheckTarget {
+            host: server_name.to_string(),
+            ..target.clone()
+        };
+        match vhost.url() {
+            Some(url) => self.get(&client, &url, request, assertion).await,
+            None => failure(
+                format!("Invalid server name: {}", server_name),
+                FailureKind::Other,
+            ),
+        }
+    }
+
+    /// Send `request` to `url` and time the response, including the body
+    /// read, then evaluate `assertion` against the body. Returns the kind of
+    /// failure if the check failed.
+    async fn get(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        request: &HttpRequest,
+        assertion: Option<&BodyAssertion>,
+    ) -> (HttpGetResult, Option<FailureKind>) {
+        let start = Instant::now();
+
+        let sent = client
+            .request(request.method.clone(), url)
+            .headers(request.headers.clone())
+            .send()
+            .await;
+        let response = match sent {
+            Ok(response) => response,
+            Err(e) => {
+                let error = match self.timeout_error(&e) {
+                    Some(timeout) => timeout,
+                    None => format!("Request failed: {}", e),
+                };
+                return failure(error, classify_error(&e));
+            }
+        };
+
+        let status = response.status();
+        let body = read_body(response, self.max_body_bytes, assertion.is_some()).await;
+        let response_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        let accepted = request.settings.accepts(status.as_u16());
+        let mut errors = Vec::new();
+        if !accepted {
+            errors.push(request.status_error(status));
+        }
+        let mut failure_kind = (!accepted).then_some(FailureKind::Other);
+        let mut body_timed_out = false;
+        if let Some(e) = &body.error {
+            match self.timeout_error(e) {
+                Some(timeout) => {
+                    body_timed_out = true;
+                    errors.push(format!("{} while reading response body", timeout));
+                }
+                None => errors.push(format!("Failed to read response body: {}", e)),
+            }
+        }
+
+        let body_match = assertion.map(|assertion| assertion.evaluate(&body));
+        if let Some((false, detail)) = &body_match {
+            errors.push(detail.clone());
+            failure_kind.get_or_insert(if body_timed_out {
+                FailureKind::Unreachable
+            } else {
+                FailureKind::Other
+            });
+        }
+
+        let result = HttpGetResult {
+            status_code: i64::from(status.as_u16()),
+            response_time_ms: Some(response_time_ms),
+            dns_resolution_ms: None,
+            response_size_bytes: Some(body.size as i64),
+            body_truncated: Some(body.truncated),
+            body_matched: body_match.as_ref().map(|(matched, _)| *matched),
+            error_details: (!errors.is_empty()).then_some(ErrorDetails {
+                errors: Some(errors),
+            }),
+            success: accepted && body_match.as_ref().is_none_or(|(matched, _)| *matched),
+            body_match_detail: body_match.map(|(_, detail)| detail),
+        };
+        (result, failure_kind)
+    }
+
+    /// Describe `e` by the timeout that fired, `None` if it is no timeout
+    fn timeout_error(&self, e: &reqwest::Error) -> Option<String> {
+        if !e.is_timeout() {
+            return None;
+        }
+        let ClientSettings {
+            connect_timeout,
+            timeout,
+            ..
+        } = self.settings;
+        Some(match connect_timeout {
+            Some(connect_timeout) if e.is_connect() && connect_timeout < timeout => {
+                format!("Connect timeout after {:?}", connect_timeout)
+            }
+            _ => format!("Read timeout after {:?}", timeout),
+        })
+    }
+}
+
+impl HttpGetCheckerBuilder {
+    /// Resolve hostnames with `resolver` instead of the system resolver,
+    /// for plain requests as well as virtual hosts
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.settings.resolver = PinnedResolver::new(resolver);
+        self
+    }
+
+    /// Verify the TLS certificates of checked endpoints, or accept any
+    /// certificate with `false`
+    pub fn with_verify_tls(mut self, verify_tls: bool) -> Self {
+        self.settings.verify_tls = verify_tls;
+        self
+    }
+
+    /// Connect from `source_address`. `None` lets the operating system
+    /// choose.
+    pub fn with_source_address(mut self, source_address: Option<IpAddr>) -> Self {
+        self.settings.source_address = source_address;
+        self
+    }
+
+    /// Stop reading response bodies after `max_body_bytes` (default
+    /// [`DEFAULT_MAX_BODY_BYTES`])
+    pub fn with_max_body_bytes(mut self, max_body_bytes: u64) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Build the checker and its two HTTP clients
+    pub fn build(self) -> Result<HttpGetChecker> {
+        Ok(HttpGetChecker {
+            client: build_client(self.settings.client_builder())?,
+            no_redirect_client: build_client(
+                self.settings.client_builder().redirect(Policy::none()),
+            )?,
+            settings: self.settings,
+            max_body_bytes: self.max_body_bytes,
+        })
+    }
+}
+
+impl ClientSettings {
+    /// Client builder with the timeouts, TLS verification, source address
+    /// and resolver applied
+    fn client_builder(&self) -> reqwest::ClientBuilder {
+        client_builder(self.connect_timeout, self.timeout)
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .local_address(self.source_address)
+            .dns_resolver(Arc::new(self.resolver.clone()))
+    }
+}
+
+impl PinnedResolver {
+    fn new(resolver: Resolver) -> Self {
+        Self {
+            resolver,
+            pinned: Arc::default(),
+        }
+    }
+
+    /// Resolve `host` through the checker's resolver
+    async fn resolve(&self, host: &str) -> (Result<IpAddr>, Option<Duration>) {
+        self.resolver.resolve(host).await
+    }
+
+    /// Connect requests to `host` to `ip`, the address the check resolved
+    fn pin(&self, host: &str, ip: IpAddr) {
+        self.pinned.lock().insert(host.to_ascii_lowercase(), ip);
+    }
+}
+
+impl reqwest::dns::Resolve for PinnedResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let pinned = self
+            .pinned
+            .lock()
+            .get(&name.as_str().to_ascii_lowercase())
+            .copied();
+        match pinned {
+            Some(ip) => Box::pin(async move {
+                let addrs: reqwest::dns::Addrs = Box::new(std::iter::once(SocketAddr::new(ip, 0)));
+                Ok(addrs)
+            }),
+            None => reqwest::dns::Resolve::resolve(&self.resolver, name),
+        }
+    }
+}
+
+/// Request configured on the endpoint and the statuses it accepts
+struct HttpRequest {
+    method: Method,
+    headers: HeaderMap,
+    settings: HttpCheckConfig,
+}
+
+impl HttpRequest {
+    /// Request of `endpoint`: a GET without extra headers, accepting any
+    /// 2xx status and following redirects, unless its `http` settings say
+    /// otherwise
+    fn from_endpoint(endpoint: &Endpoint) -> std::result::Result<Self, String> {
+        let settings = endpoint.http.clone().unwrap_or_default();
+
+        let method = match &settings.method {
+            Some(method) => Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| format!("Invalid HTTP method: {}", method))?,
+            None => Method::GET,
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &settings.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid HTTP header name: {}", name))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| format!("Invalid value of HTTP header {}", name))?;
+            headers.insert(name, value);
+        }
+
+        Ok(Self {
+            method,
+            headers,
+            settings,
+        })
+    }
+
+    fn redirect_policy(&self) -> Policy {
+        if self.settings.follow_redirects {
+            Policy::default()
+        } else {
+            Policy::none()
+        }
+    }
+
+    /// Error recorded for a response whose `status` is not accepted
+    fn status_error(&self, status: StatusCode) -> String {
+        if self.settings.expected_status.is_empty() {
+            return format!("HTTP {}", status);
+        }
+        let expected: Vec<String> = self
+            .settings
+            .expected_status
+            .iter()
+            .map(u16::to_string)
+            .collect();
+        format!("HTTP {}, expected {}", status, expected.join(" or "))
+    }
+}
+
+/// Conditions on the response body configured on the endpoint
+struct BodyAssertion {
+    contains: Option<String>,
+    regex: Option<Regex>,
+}
+
+impl BodyAssertion {
+    /// Assertions of `endpoint`, `None` if it has none
+    fn from_endpoint(endpoint: &Endpoint) -> std::result::Result<Option<Self>, String> {
+        if endpoint.body_must_contain.is_none() && endpoint.body_regex.is_none() {
+            return Ok(None);
+        }
+
+        let regex = endpoint
+            .body_regex
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| format!("Invalid body_regex: {}", e))?;
+
+        Ok(Some(Self {
+            contains: endpoint.body_must_contain.clone(),
+            regex,
+        }))
+    }
+
+    /// Whether `body` satisfies every assertion, with a description of what
+    /// was found
+    fn evaluate(&self, body: &BodyRead) -> (bool, String) {
+        // Matching only the start of the body could pass or fail on content
+        // that is not all there, so bodies over the cap fail outright.
+        if body.truncated {
+            return (
+                false,
+                format!(
+                    "body exceeds the {} byte limit (monitoring.http.max_body_bytes), assertions not evaluated",
+                    body.size
+                ),
+            );
+        }
+        if body.error.is_some() {
+            return (
+                false,
+                "body was not read completely, assertions not evaluated".to_string(),
+            );
+        }
+
+        let text = String::from_utf8_lossy(&body.bytes);
+        let mut matched = true;
+        let mut details = Vec::new();
+
+        if let Some(substring) = &self.contains {
+            if text.contains(substring.as_str()) {
+                details.push(format!("body contains {:?}", substring));
+            } else {
+                matched = false;
+                details.push(format!("body does not contain {:?}", substring));
+            }
+        }
+
+        if let Some(regex) = &self.regex {
+            match regex.find(&text) {
+                Some(m) => details.push(format!(
+                    "body matches /{}/ at {:?}",
+                    regex,
+                    truncate(m.as_str(), MATCH_DETAIL_CHARS)
+                )),
+                None => {
+                    matched = false;
+                    details.push(format!("body does not match /{}/", regex));
+                }
+            }
+        }
+
+        (matched, details.join("; "))
+    }
+}
+
+/// Longest excerpt of a regex match recorded in `body_match_detail`
+const MATCH_DETAIL_CHARS: usize = 100;
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &s[..end]),
+        None => s.to_string(),
+    }
+}
+
+/// Outcome of reading a response body
+struct BodyRead {
+    /// Bytes read, at most the limit
+    size: u64,
+    /// The bytes read, only kept when they are needed for body assertions
+    bytes: Vec<u8>,
+    /// Whether reading stopped at the limit with more body left
+    truncated: bool,
+    /// Error that ended the read early
+    error: Option<reqwest::Error>,
+}
+
+/// Stream `response`'s body, counting at most `limit` bytes and keeping them
+/// if `keep` is set
+async fn read_body(mut response: reqwest::Response, limit: u64, keep: bool) -> BodyRead {
+    let mut body = BodyRead {
+        size: 0,
+        bytes: Vec::new(),
+        truncated: false,
+        error: None,
+    };
+
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                let remaining = limit - body.size;
+                if chunk.len() as u64 > remaining {
+                    if keep {
+                        body.bytes.extend_from_slice(&chunk[..remaining as usize]);
+                    }
+                    body.size = limit;
+                    body.truncated = true;
+                    // Dropping the response closes the connection instead of
+                    // downloading the rest of the body.
+                    break;
+                }
+                if keep {
+                    body.bytes.extend_from_slice(&chunk);
+                }
+                body.size += chunk.len() as u64;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                body.error = Some(e);
+                break;
+            }
+        }
+    }
+
+    body
+}
+
+/// Client builder with the checker's timeouts applied
+fn client_builder(connect_timeout: Option<Duration>, timeout: Duration) -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder().timeout(timeout);
+    match connect_timeout {
+        Some(connect_timeout) => builder.connect_timeout(connect_timeout),
+        None => builder,
+    }
+}
+
+fn build_client(builder: reqwest::ClientBuilder) -> Result<reqwest::Client> {
+    builder
+        .build()
+        .map_err(|e| Error::Network(format!("Failed to create HTTP client: {}", e)))
+}
+
+/// Failed result for checks that never received a response
+fn failure(error: String, kind: FailureKind) -> (HttpGetResult, Option<FailureKind>) {
+    let result = HttpGetResult {
+        status_code: 0,
+        response_time_ms: None,
+        dns_resolution_ms: None,
+        response_size_bytes: None,
+        body_truncated: None,
+        body_matched: None,
+        body_match_detail: None,
+        error_details: Some(ErrorDetails {
+            errors: Some(vec![error]),
+        }),
+        success: false,
+    };
+    (result, Some(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_result(result: &MonitoringResult) -> &HttpGetResult {
+        match &result.check_type {
+            CheckType::HttpGetCheck(c) => &c.result,
+            other => panic!("expected an HTTP GET result, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_uses_url_path() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/health")
+            .with_status(200)
+            .with_body("ok")
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("{}/health", server.url()));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        mock.assert_async().await;
+        let http = http_result(&result);
+        assert!(http.success);
+        assert_eq!(http.status_code, 200);
+        assert_eq!(http.response_size_bytes, Some(2));
+        assert_eq!(result.endpoint_id, endpoint.id);
+        assert_eq!(result.failure_kind, None);
+    }
+
+    #[tokio::test]
+    async fn test_method_and_headers_are_sent() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("HEAD", "/health")
+            .match_header("authorization", "Bearer secret")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint =
+            Endpoint::new(format!("{}/health", server.url())).with_http(HttpCheckConfig {
+                method: Some("head".to_string()),
+                headers: [("Authorization".to_string(), "Bearer secret".to_string())].into(),
+                ..HttpCheckConfig::default()
+            });
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        mock.assert_async().await;
+        assert!(http_result(&result).success);
+    }
+
+    #[tokio::test]
+    async fn test_expected_status_decides_success() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("DELETE", "/session")
+            .with_status(204)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let settings = HttpCheckConfig {
+            method: Some("DELETE".to_string()),
+            expected_status: vec![200, 204],
+            ..HttpCheckConfig::default()
+        };
+        let endpoint =
+            Endpoint::new(format!("{}/session", server.url())).with_http(settings.clone());
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let http = http_result(&result);
+        assert!(http.success);
+        assert_eq!(http.status_code, 204);
+        assert!(http.error_details.is_none());
+        assert_eq!(result.failure_kind, None);
+
+        // A 2xx status outside the expected ones fails
+        let endpoint =
+            Endpoint::new(format!("{}/session", server.url())).with_http(HttpCheckConfig {
+                expected_status: vec![200],
+                ..settings
+            });
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        mock.assert_async().await;
+        assert!(!result.is_successful());
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+        assert_eq!(
+            result.error_message().as_deref(),
+            Some("HTTP 204 No Content, expected 200")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follow_redirects_toggle() {
+        let mut server = mockito::Server::new_async().await;
+        let redirect = server
+            .mock("GET", "/old")
+            .with_status(302)
+            .with_header("location", "/new")
+            .expect(3)
+            .create_async()
+            .await;
+        let target = server
+            .mock("GET", "/new")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let url = format!("{}/old", server.url());
+
+        // Redirects are followed by default
+        let result = checker.check(Uuid::now_v7(), &Endpoint::new(&url)).await;
+        assert!(result.is_successful());
+        assert_eq!(http_result(&result).status_code, 200);
+
+        let not_followed = HttpCheckConfig {
+            follow_redirects: false,
+            ..HttpCheckConfig::default()
+        };
+        let endpoint = Endpoint::new(&url).with_http(not_followed.clone());
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+        assert!(!result.is_successful());
+        assert_eq!(http_result(&result).status_code, 302);
+
+        // The redirect itself can be the expected answer
+        let endpoint = Endpoint::new(&url).with_http(HttpCheckConfig {
+            expected_status: vec![301, 302],
+            ..not_followed
+        });
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+        assert!(result.is_successful());
+        assert_eq!(http_result(&result).status_code, 302);
+
+        redirect.assert_async().await;
+        target.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_server_error_fails_with_body_size() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/health")
+            .with_status(500)
+            .with_body("internal error")
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("{}/health", server.url()));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        mock.assert_async().await;
+        let http = http_result(&result);
+        assert!(!http.success);
+        assert_eq!(http.status_code, 500);
+        assert_eq!(http.response_size_bytes, Some(14));
+        assert!(http.response_time_ms.is_some());
+        assert_eq!(
+            result.error_message().as_deref(),
+            Some("HTTP 500 Internal Server Error")
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+    }
+
+    #[tokio::test]
+    async fn test_body_read_is_capped() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/large")
+            .with_status(200)
+            .with_body(vec![b'x'; 4 * 1024 * 1024])
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .with_max_body_bytes(64 * 1024)
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("{}/large", server.url()));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let http = http_result(&result);
+        assert!(http.success);
+        assert_eq!(http.response_size_bytes, Some(64 * 1024));
+        assert_eq!(http.body_truncated, Some(true));
+        assert!(http.error_details.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_is_not_truncated() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/small")
+            .with_status(503)
+            .with_body("unavailable")
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .with_max_body_bytes(1024)
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("{}/small", server.url()));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let http = http_result(&result);
+        assert!(!http.success);
+        assert_eq!(http.response_size_bytes, Some(11));
+        assert_eq!(http.body_truncated, Some(false));
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+    }
+
+    #[tokio::test]
+    async fn test_body_assertion_fails_despite_200() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/health")
+            .with_status(200)
+            .with_body(r#"{"status":"degraded"}"#)
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("{}/health", server.url()))
+            .with_body_must_contain(r#""status":"ok""#);
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let http = http_result(&result);
+        assert_eq!(http.status_code, 200);
+        assert!(!http.success);
+        assert_eq!(http.body_matched, Some(false));
+        let detail = http.body_match_detail.as_deref().unwrap();
+        assert_eq!(detail, r#"body does not contain "\"status\":\"ok\"""#);
+        assert_eq!(
+            http.error_details.as_ref().unwrap().errors.as_deref(),
+            Some(&[detail.to_string()][..])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_body_assertions_pass() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/health")
+            .with_status(200)
+            .with_body(r#"{"status":"ok","version":"2.3.1"}"#)
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("{}/health", server.url()))
+            .with_body_must_contain(r#""status":"ok""#)
+            .with_body_regex(r#""version":"2\.\d+"#);
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let http = http_result(&result);
+        assert!(http.success);
+        assert_eq!(http.body_matched, Some(true));
+        assert_eq!(
+            http.body_match_detail.as_deref(),
+            Some(
+                r#"body contains "\"status\":\"ok\""; body matches /"version":"2\.\d+/ at "\"version\":\"2.3""#
+            )
+        );
+        assert!(http.error_details.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_body_assertion_fails_on_body_over_limit() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/health")
+            .with_status(200)
+            .with_body(format!("status=ok{}", "x".repeat(100)))
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .with_max_body_bytes(64)
+            .build()
+            .unwrap();
+        let endpoint =
+            Endpoint::new(format!("{}/health", server.url())).with_body_regex("status=ok");
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        // The match lies within the first 64 bytes, but the body as a whole
+        // was never seen
+        let http = http_result(&result);
+        assert!(!http.success);
+        assert_eq!(http.body_truncated, Some(true));
+        assert_eq!(http.body_matched, Some(false));
+        assert_eq!(
+            http.body_match_detail.as_deref(),
+            Some(
+                "body exceeds the 64 byte limit (monitoring.http.max_body_bytes), assertions not evaluated"
+            )
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+    }
+
+    #[tokio::test]
+    async fn test_body_read_is_capped_at_1_mib_by_default() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/large")
+            .with_status(200)
+            .with_body(vec![b'x'; 2 * 1024 * 1024])
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("{}/large", server.url()));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let http = http_result(&result);
+        assert_eq!(http.response_size_bytes, Some(1024 * 1024));
+        assert_eq!(http.body_truncated, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_no_body_assertion_leaves_match_fields_empty() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_status(200)
+            .with_body("anything")
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new(server.url()))
+            .await;
+
+        let http = http_result(&result);
+        assert!(http.success);
+        assert_eq!(http.body_matched, None);
+        assert_eq!(http.body_match_detail, None);
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout_is_reported() {
+        use socket2::{Domain, Socket, Type};
+
+        // A listener with a zero backlog whose only queue slot is taken:
+        // further connection attempts are left unanswered.
+        let listener = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+        listener
+            .bind(&"127.0.0.1:0".parse::<SocketAddr>().unwrap().into())
+            .unwrap();
+        listener.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap().as_socket().unwrap();
+        let _queued = std::net::TcpStream::connect(addr).unwrap();
+
+        let checker =
+            HttpGetChecker::builder(Some(Duration::from_millis(200)), Duration::from_secs(5))
+                .build()
+                .unwrap();
+        let endpoint = Endpoint::new(format!("http://{}/health", addr));
+        let start = Instant::now();
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(start.elapsed() < Duration::from_secs(4));
+        let http = http_result(&result);
+        assert!(!http.success);
+        let errors = http
+            .error_details
+            .as_ref()
+            .unwrap()
+            .errors
+            .as_ref()
+            .unwrap();
+        assert!(
+            errors[0].starts_with("Connect timeout after 200ms"),
+            "unexpected error: {:?}",
+            errors
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Unreachable));
+    }
+
+    #[tokio::test]
+    async fn test_closed_port_is_refused() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("http://{}/health", addr));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let http = http_result(&result);
+        assert!(!http.success);
+        assert_eq!(http.status_code, 0);
+        assert_eq!(http.response_size_bytes, None);
+        assert_eq!(http.response_time_ms, None);
+        assert!(result
+            .error_message()
+            .unwrap()
+            .starts_with("Request failed"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Refused));
+    }
+
+    #[tokio::test]
+    async fn test_failed_lookup_is_unresolved() {
+        use crate::monitor::resolver::tests::SlowLookup;
+
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: Vec::new(),
+            }),
+            Duration::from_secs(1),
+        );
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .with_resolver(resolver)
+            .build()
+            .unwrap();
+
+        for endpoint in [
+            Endpoint::new("http://missing.smotra.test/health"),
+            Endpoint::new("http://missing.smotra.test/health").with_server_name("vhost.test"),
+        ] {
+            let result = checker.check(Uuid::now_v7(), &endpoint).await;
+            assert!(!result.is_successful());
+            assert_eq!(result.failure_kind, Some(FailureKind::Unresolved));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_unresolvable_host_is_unresolved() {
+        use crate::monitor::resolver::tests::SlowLookup;
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/moved")
+            .with_status(302)
+            .with_header("location", "http://missing.smotra.test/health")
+            .create_async()
+            .await;
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: Vec::new(),
+            }),
+            Duration::from_secs(1),
+        );
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .with_resolver(resolver)
+            .build()
+            .unwrap();
+
+        // The endpoint itself is an IP literal; only the redirect target
+        // is looked up, by the HTTP client
+        let endpoint = Endpoint::new(format!("{}/moved", server.url()));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+        assert!(!result.is_successful());
+        assert_eq!(result.failure_kind, Some(FailureKind::Unresolved));
+    }
+
+    #[tokio::test]
+    async fn test_dns_resolution_is_timed_apart_from_response() {
+        use crate::monitor::resolver::tests::SlowLookup;
+
+        let mut server = mockito::Server::new_async().await;
+        let port = server.socket_address().port();
+        let mock = server
+            .mock("GET", "/health")
+            .with_status(200)
+            .expect(2)
+            .create_async()
+            .await;
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::from_millis(50),
+                addrs: vec![[127, 0, 0, 1].into()],
+            }),
+            Duration::from_secs(1),
+        );
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .with_resolver(resolver)
+            .build()
+            .unwrap();
+
+        let hostname = checker
+            .check(
+                Uuid::now_v7(),
+                &Endpoint::new(format!("http://service.smotra.test:{}/health", port)),
+            )
+            .await;
+        assert!(hostname.is_successful());
+        let http = http_result(&hostname);
+        let dns_ms = http.dns_resolution_ms.expect("hostname lookup is timed");
+        assert!(dns_ms >= 40.0, "dns_resolution_ms = {}", dns_ms);
+
+        let literal = checker
+            .check(
+                Uuid::now_v7(),
+                &Endpoint::new(format!("http://127.0.0.1:{}/health", port)),
+            )
+            .await;
+        assert!(literal.is_successful());
+        assert_eq!(http_result(&literal).dns_resolution_ms, None);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_slow_body_hits_read_timeout() {
+        // A server answers one request at a time, and each request here
+        // leaves it stuck in the slow body
+        async fn slow_body_server() -> mockito::ServerGuard {
+            let mut server = mockito::Server::new_async().await;
+            server
+                .mock("GET", "/slow")
+                .with_status(200)
+                .with_chunked_body(|w| {
+                    w.write_all(b"first byte")?;
+                    w.flush()?;
+                    std::thread::sleep(Duration::from_secs(2));
+                    w.write_all(b"rest of the body")
+                })
+                .create_async()
+                .await;
+            server
+        }
+
+        let checker =
+            HttpGetChecker::builder(Some(Duration::from_secs(5)), Duration::from_millis(500))
+                .build()
+                .unwrap();
+
+        // The status line arrived, so like a truncated body the timeout is
+        // recorded without failing the check
+        let server = slow_body_server().await;
+        let endpoint = Endpoint::new(format!("{}/slow", server.url()));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+        let http = http_result(&result);
+        assert_eq!(http.status_code, 200);
+        assert!(http.success);
+        assert_eq!(
+            result.error_message().as_deref(),
+            Some("Read timeout after 500ms while reading response body")
+        );
+        assert_eq!(result.failure_kind, None);
+
+        // Body assertions need the whole body, so there it fails the check
+        let server = slow_body_server().await;
+        let endpoint =
+            Endpoint::new(format!("{}/slow", server.url())).with_body_must_contain("first byte");
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+        let http = http_result(&result);
+        assert!(!http.success);
+        assert_eq!(http.body_matched, Some(false));
+        assert_eq!(
+            http.error_details
+                .as_ref()
+                .unwrap()
+                .errors
+                .as_deref()
+                .unwrap(),
+            [
+                "Read timeout after 500ms while reading response body",
+                "body was not read completely, assertions not evaluated",
+            ]
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Unreachable));
+    }
+
+    #[tokio::test]
+    async fn test_server_name_overrides_host_header() {
+        let mut server = mockito::Server::new_async().await;
+        let port = server.socket_address().port();
+        let mock = server
+            .mock("GET", "/health")
+            .match_header("host", format!("vhost.test:{}", port).as_str())
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("http://127.0.0.1:{}/health", port))
+            .with_server_name("vhost.test");
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        mock.assert_async().await;
+        assert!(result.is_successful());
+    }
+
+    #[tokio::test]
+    async fn test_server_name_is_presented_as_sni() {
+        use tokio_rustls::rustls::server::Acceptor;
+        use tokio_rustls::LazyConfigAcceptor;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Record the SNI of the first ClientHello; the handshake itself is
+        // not completed, so the check fails after the SNI was sent.
+        let sni = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let handshake = LazyConfigAcceptor::new(Acceptor::default(), stream)
+                .await
+                .unwrap();
+            handshake.client_hello().server_name().map(str::to_string)
+        });
+
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let endpoint = Endpoint::new(format!("https://127.0.0.1:{}/health", port))
+            .with_server_name("vhost.test");
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert_eq!(sni.await.unwrap().as_deref(), Some("vhost.test"));
+        assert!(!result.is_successful());
+    }
+
+    #[tokio::test]
+    async fn test_hostname_resolves_through_configured_nameserver() {
+        use crate::agent_config::{DnsProtocol, DnsResolverConfig};
+
+        let mut server = mockito::Server::new_async().await;
+        let port = server.socket_address().port();
+        let mock = server
+            .mock("GET", "/health")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        // Only the test nameserver knows this name
+        let (nameserver, queried) =
+            super::super::resolver::tests::spawn_nameserver([127, 0, 0, 1].into()).await;
+        let config = DnsResolverConfig::Nameservers {
+            nameservers: vec![nameserver.to_string()],
+            protocol: DnsProtocol::Udp,
+            tls_name: None,
+        };
+        let resolver = Resolver::from_config(&config, Duration::from_secs(5))
+            .unwrap()
+            .unwrap();
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(5))
+            .with_resolver(resolver)
+            .build()
+            .unwrap();
+
+        let endpoint = Endpoint::new(format!("http://service.smotra.test:{}/health", port));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        mock.assert_async().await;
+        assert!(result.is_successful());
+        assert_eq!(*queried.lock(), vec!["service.smotra.test.".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_check_non_url_address_fails() {
+        let checker = HttpGetChecker::builder(None, Duration::from_secs(1))
+            .build()
+            .unwrap();
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("example.com"))
+            .await;
+
+        assert!(!result.is_successful());
+        assert!(result
+            .error_message()
+            .unwrap()
+            .contains("not an HTTP(S) URL"));
+    }
+}
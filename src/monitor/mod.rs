@@ -1,7 +1,36 @@
 //! Monitoring coordination and task management
 
+mod checkers;
+mod circuit;
+mod dns;
+mod failure;
+mod grpc;
+mod http;
+mod order;
 mod ping;
+mod plugin;
+mod qos;
+mod resolver;
+mod reverse_dns;
+mod schedule;
 mod server;
+mod source_port;
+mod tcp;
+mod tls;
+mod traceroute;
+mod udp;
+mod warmup;
 
+pub use checkers::Checkers;
+pub use dns::DnsChecker;
+pub use grpc::GrpcHealthChecker;
+pub use http::HttpGetChecker;
 pub use ping::PingChecker;
+pub use plugin::PluginChecker;
+pub use resolver::DnsCache;
+pub use reverse_dns::PtrCache;
 pub use server::run_monitoring;
+pub use tcp::TcpConnectChecker;
+pub use tls::TlsCertChecker;
+pub use traceroute::TracerouteChecker;
+pub use udp::UdpConnectChecker;
@@ -0,0 +1,115 @@
+//! Per-tick endpoint dispatch order
+//!
+//! With a concurrency limit, endpoints late in the config are consistently
+//! checked a little later than the first ones. Shuffling the order on every
+//! tick (`monitoring.shuffle_endpoints`) spreads that delay evenly over time.
+//! `monitoring.shuffle_seed` makes the sequence of orders reproducible.
+
+use crate::core::Endpoint;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Decides the order endpoints are dispatched in on each tick
+pub(crate) struct DispatchOrder {
+    shuffle: bool,
+    seed: Option<u64>,
+    rng: StdRng,
+}
+
+impl DispatchOrder {
+    pub(crate) fn new(shuffle: bool, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => rand::make_rng(),
+        };
+        Self { shuffle, seed, rng }
+    }
+
+    /// Apply hot-reloaded settings; a changed seed restarts its sequence
+    pub(crate) fn update_config(&mut self, shuffle: bool, seed: Option<u64>) {
+        if seed != self.seed {
+            *self = Self::new(shuffle, seed);
+        }
+        self.shuffle = shuffle;
+    }
+
+    /// Put `endpoints` into this tick's dispatch order. Without shuffling
+    /// the configured order is kept.
+    pub(crate) fn arrange(&mut self, endpoints: &mut [Endpoint]) {
+        if self.shuffle {
+            endpoints.shuffle(&mut self.rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn endpoints() -> Vec<Endpoint> {
+        (1..=10)
+            .map(|i| Endpoint::new(format!("10.0.0.{}", i)))
+            .collect()
+    }
+
+    /// Dispatch order of `ticks` consecutive ticks
+    fn orders(order: &mut DispatchOrder, ticks: usize) -> Vec<Vec<Uuid>> {
+        let configured = endpoints();
+        (0..ticks)
+            .map(|_| {
+                let mut tick = configured.clone();
+                order.arrange(&mut tick);
+                tick.iter().map(|e| e.id).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shuffled_order_varies_across_ticks() {
+        let mut order = DispatchOrder::new(true, Some(7));
+        let orders = orders(&mut order, 5);
+
+        assert!(
+            orders.windows(2).any(|pair| pair[0] != pair[1]),
+            "dispatch order never changed: {:?}",
+            orders
+        );
+        let mut expected = orders[0].clone();
+        expected.sort();
+        for tick in &orders {
+            let mut sorted = tick.clone();
+            sorted.sort();
+            assert_eq!(sorted, expected, "every endpoint is dispatched once");
+        }
+    }
+
+    #[test]
+    fn test_unshuffled_order_is_configured_order() {
+        let configured = endpoints();
+        let mut tick = configured.clone();
+        DispatchOrder::new(false, None).arrange(&mut tick);
+
+        let ids: Vec<_> = tick.iter().map(|e| e.address.clone()).collect();
+        let expected: Vec<_> = configured.iter().map(|e| e.address.clone()).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn test_seed_makes_order_reproducible() {
+        let mut order = DispatchOrder::new(true, Some(42));
+        let mut other = DispatchOrder::new(true, Some(42));
+        let configured = endpoints();
+        for _ in 0..3 {
+            let mut a = configured.clone();
+            let mut b = configured.clone();
+            order.arrange(&mut a);
+            other.arrange(&mut b);
+            assert_eq!(
+                a.iter().map(|e| e.id).collect::<Vec<_>>(),
+                b.iter().map(|e| e.id).collect::<Vec<_>>()
+            );
+        }
+    }
+}
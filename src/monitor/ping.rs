@@ -1,44 +1,192 @@
 //! ICMP ping monitoring
+//!
+//! Failed checks record why the echo requests went unanswered: ICMP
+//! destination unreachable replies are classified by their code, so a
+//! firewall answering "administratively prohibited" reports `blocked` while
+//! silence reports `unreachable`.
+//!
+//! Echo requests carry `monitoring.ping_payload_bytes` of payload, a
+//! recognisable ASCII pattern repeated to that size, so large pings can
+//! reveal MTU and fragmentation problems on the path.
+//!
+//! The checker holds an ICMPv4 and an ICMPv6 socket and pings each target
+//! through the socket of its resolved address' family. With
+//! `monitoring.source_address` set, only a socket of the address' family is
+//! opened and bound to it, so echo requests leave from its interface and
+//! targets of the other family cannot be pinged.
 
+use super::failure::{self, classify_icmpv4, classify_icmpv6, classify_io};
+use super::qos;
+use super::resolver::Resolver;
+use super::reverse_dns::ReverseResolver;
+use crate::agent_config::DEFAULT_PING_PAYLOAD_BYTES;
 use crate::core::{
-    CheckType, Endpoint, ErrorDetails, MonitoringResult, PingCheck, PingCheckType, PingResult,
+    CheckType, Endpoint, ErrorDetails, FailureKind, MonitoringResult, PingCheck, PingCheckType,
+    PingResult, SuccessQuorum,
 };
 use crate::error::{Error, Result};
 use chrono::Utc;
-use std::net::{IpAddr, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
-use surge_ping::{Client, Config, PingIdentifier, PingSequence};
-use tracing::debug;
+use surge_ping::{Client, Config, IcmpPacket, PingIdentifier, PingSequence, SurgeError, ICMP};
+use tracing::{debug, trace, warn};
 use uuid::{Timestamp, Uuid};
 
+/// Pattern echo request payloads are filled with
+const PAYLOAD_PATTERN: &[u8] = b"******    ping   ------ 1234567890 ===== abcdefghi ____ ";
+
 /// Ping checker for ICMP reachability tests
 pub struct PingChecker {
-    client: Client,
+    /// Socket for IPv4 targets, `None` if it could not be opened
+    v4: Option<Client>,
+    /// Socket for IPv6 targets, `None` if it could not be opened
+    v6: Option<Client>,
     timeout: Duration,
     count: u32,
+    resolver: Resolver,
+    reverse_resolver: Option<ReverseResolver>,
+    success_quorum: Option<SuccessQuorum>,
+    payload: Vec<u8>,
 }
 
 impl PingChecker {
     /// Create a new ping checker
+    ///
+    /// Fails if neither an ICMPv4 nor an ICMPv6 socket can be opened. If
+    /// only one can, targets of the other family fail their checks.
     pub fn new(timeout: Duration, count: u32) -> Result<Self> {
-        let config = Config::default();
-        let client = Client::new(&config)
-            .map_err(|e| Error::Network(format!("Failed to create ping client: {}", e)))?;
-
+        let (v4, v6) = match (client(ICMP::V4, None), client(ICMP::V6, None)) {
+            (Err(e), Err(_)) => return Err(e),
+            (v4, v6) => {
+                if let Err(e) = &v6 {
+                    debug!("IPv6 targets cannot be pinged: {}", e);
+                }
+                if let Err(e) = &v4 {
+                    debug!("IPv4 targets cannot be pinged: {}", e);
+                }
+                (v4.ok(), v6.ok())
+            }
+        };
         Ok(Self {
-            client,
+            v4,
+            v6,
             timeout,
             count,
+            resolver: Resolver::new(timeout),
+            reverse_resolver: None,
+            success_quorum: None,
+            payload: payload(DEFAULT_PING_PAYLOAD_BYTES),
         })
     }
 
+    /// Send echo requests from `source_address`. `None` lets the operating
+    /// system choose.
+    ///
+    /// Replaces the ICMP sockets with one of the address' family, so call
+    /// this before [`Self::with_dscp`].
+    pub fn with_source_address(mut self, source_address: Option<IpAddr>) -> Result<Self> {
+        match source_address {
+            Some(ip @ IpAddr::V4(_)) => {
+                self.v4 = Some(client(ICMP::V4, Some(ip))?);
+                self.v6 = None;
+            }
+            Some(ip @ IpAddr::V6(_)) => {
+                self.v4 = None;
+                self.v6 = Some(client(ICMP::V6, Some(ip))?);
+            }
+            None => {}
+        }
+        Ok(self)
+    }
+
+    /// Send echo requests with `bytes` of payload
+    pub fn with_payload_bytes(mut self, bytes: usize) -> Self {
+        self.payload = payload(bytes);
+        self
+    }
+
+    /// Require a quorum of replies for a check to succeed, unless the
+    /// endpoint sets its own. `None` keeps the default of one reply.
+    pub fn with_success_quorum(mut self, quorum: Option<SuccessQuorum>) -> Self {
+        self.success_quorum = quorum;
+        self
+    }
+
+    /// Replies required for `endpoint`, `None` when no quorum applies
+    fn required_successes(&self, endpoint: &Endpoint) -> Option<i64> {
+        let endpoint_quorum = endpoint.success_quorum.as_deref().and_then(|quorum| {
+            quorum
+                .parse::<SuccessQuorum>()
+                .inspect_err(|e| warn!("Ignoring success quorum of {}: {}", endpoint.address, e))
+                .ok()
+        });
+
+        endpoint_quorum
+            .or(self.success_quorum)
+            .map(|quorum| quorum.required(self.count) as i64)
+    }
+
+    /// Resolve hostnames with `resolver`
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Report the PTR hostname of each resolved IP, looked up with
+    /// `reverse_resolver`. `None` disables reverse lookups.
+    pub(crate) fn with_reverse_dns(mut self, reverse_resolver: Option<ReverseResolver>) -> Self {
+        self.reverse_resolver = reverse_resolver;
+        self
+    }
+
+    /// Mark outgoing echo requests with the given DSCP value, in the ToS
+    /// byte of IPv4 and the traffic class of IPv6 packets.
+    ///
+    /// Marking is best effort: if the platform or socket type does not allow
+    /// setting the ToS byte, a warning is logged and checks run unmarked.
+    pub fn with_dscp(self, dscp: Option<u8>) -> Self {
+        if let Some(dscp) = dscp {
+            for client in self.v4.iter().chain(&self.v6) {
+                if let Err(e) = Self::apply_dscp(client, dscp) {
+                    warn!("Failed to apply DSCP {} to ping socket: {}", dscp, e);
+                }
+            }
+        }
+        self
+    }
+
+    #[cfg(unix)]
+    fn apply_dscp(client: &Client, dscp: u8) -> std::io::Result<()> {
+        use std::os::fd::BorrowedFd;
+
+        let socket = client.get_socket();
+        // SAFETY: the descriptor is owned by `socket`, which outlives the borrow.
+        let fd = unsafe { BorrowedFd::borrow_raw(socket.get_native_sock()) };
+        let socket = socket2::SockRef::from(&fd);
+        // An ICMPv6 socket is marked through its traffic class
+        let ipv6 = socket.local_addr()?.is_ipv6();
+        qos::apply_dscp(socket, dscp, ipv6)
+    }
+
+    #[cfg(not(unix))]
+    fn apply_dscp(_client: &Client, _dscp: u8) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "DSCP marking of ping sockets is only supported on Unix",
+        ))
+    }
+
     /// Perform a ping check on the given endpoint
     pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
-        // Resolve the address
-        let addr = match self.resolve_address(&endpoint.address).await {
+        // Resolve the address, timing the lookup separately from the pings
+        let (resolved, lookup_time) = self.resolver.resolve(&endpoint.address).await;
+        let dns_resolution_ms = lookup_time.map(|d| d.as_secs_f64() * 1000.0);
+        let required_successes = self.required_successes(endpoint);
+
+        let addr = match resolved {
             Ok(addr) => addr,
             Err(e) => {
-                let ping_result = PingResult {
+                let mut ping_result = PingResult {
                     successes: 0,
                     failures: 1,
                     success_latencies: Vec::new(),
@@ -46,7 +194,13 @@ impl PingChecker {
                         errors: Some(vec![format!("Failed to resolve address: {}", e)]),
                     }),
                     resolved_ip: String::new(),
+                    dns_resolution_ms,
+                    required_successes,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
                 };
+                ping_result.packet_loss_percent = ping_result.packet_loss_percent();
 
                 return MonitoringResult {
                     id: Uuid::new_v7(Timestamp::now(uuid::NoContext)),
@@ -57,6 +211,11 @@ impl PingChecker {
                         result: ping_result,
                     }),
                     timestamp: Utc::now(),
+                    warmup: None,
+                    degraded: None,
+                    failure_kind: Some(FailureKind::Unresolved),
+                    status: None,
+                    notes: Vec::new(),
                 };
             }
         };
@@ -66,6 +225,7 @@ impl PingChecker {
         let mut failures = 0;
         let mut success_latencies = Vec::new();
         let mut errors = Vec::new();
+        let mut failure_kinds = Vec::new();
 
         let seq_start = rand::random::<u16>();
 
@@ -79,25 +239,37 @@ impl PingChecker {
                     let latency_ms = rtt.as_millis() as f64;
                     success_latencies.push(latency_ms);
                 }
-                Err(e) => {
+                Err((e, kind)) => {
                     failures += 1;
                     errors.push(e.to_string());
+                    failure_kinds.push(kind);
                 }
             }
         }
 
+        // Best effort: a missing PTR record only omits the hostname
+        let resolved_hostname = match &self.reverse_resolver {
+            Some(reverse_resolver) => reverse_resolver.hostname(addr).await,
+            None => None,
+        };
+
         let avg_response_time_ms = if !success_latencies.is_empty() {
             Some(success_latencies.iter().sum::<f64>() / success_latencies.len() as f64)
         } else {
             None
         };
 
-        debug!(
-            "Ping check to {} ({}): {}/{} success, avg_time={:.2?} ms",
-            endpoint.address, addr, successes, self.count, avg_response_time_ms
+        trace!(
+            "Ping check to {} ({}): {}/{} success, avg_time={:.2?} ms, dns_time={:.2?} ms",
+            endpoint.address,
+            addr,
+            successes,
+            self.count,
+            avg_response_time_ms,
+            dns_resolution_ms
         );
 
-        let ping_result = PingResult {
+        let mut ping_result = PingResult {
             resolved_ip: addr.to_string(),
             successes: successes as i64,
             failures: failures as i64,
@@ -109,9 +281,16 @@ impl PingChecker {
                     errors: Some(errors),
                 })
             },
+            dns_resolution_ms,
+            required_successes,
+            resolved_hostname,
+            packet_loss_percent: None,
+            jitter_ms: None,
         };
+        ping_result.packet_loss_percent = ping_result.packet_loss_percent();
+        ping_result.jitter_ms = ping_result.jitter_ms();
 
-        MonitoringResult {
+        let mut result = MonitoringResult {
             id: Uuid::now_v7(),
             agent_id,
             endpoint_id: endpoint.id,
@@ -120,49 +299,397 @@ impl PingChecker {
                 result: ping_result,
             }),
             timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        };
+        if !result.is_successful() {
+            result.failure_kind = failure::dominant(failure_kinds);
         }
+        result
     }
 
-    /// Perform a single ping
-    async fn ping_once(&self, addr: IpAddr, seq: u16) -> Result<Duration> {
-        let payload = "******    ping   ------ 1234567890 ===== abcdefghi ____ ".as_bytes();
+    /// Perform a single ping, failing with the kind of failure if no echo
+    /// reply arrives
+    async fn ping_once(
+        &self,
+        addr: IpAddr,
+        seq: u16,
+    ) -> std::result::Result<Duration, (Error, FailureKind)> {
         let identifier = PingIdentifier(rand::random());
         let sequence = PingSequence(seq);
 
-        let mut pinger = self.client.pinger(addr, identifier).await;
+        let client = match addr {
+            IpAddr::V4(_) => self.v4.as_ref(),
+            IpAddr::V6(_) => self.v6.as_ref(),
+        };
+        let Some(client) = client else {
+            let family = if addr.is_ipv4() { "IPv4" } else { "IPv6" };
+            return Err((
+                Error::Network(format!(
+                    "Ping failed: no ICMP socket for {} targets",
+                    family
+                )),
+                FailureKind::Other,
+            ));
+        };
+        let mut pinger = client.pinger(addr, identifier).await;
 
         // let start = Instant::now();
 
-        match tokio::time::timeout(self.timeout, pinger.ping(sequence, payload)).await {
-            Ok(Ok((_, duration))) => Ok(duration),
-            Ok(Err(e)) => Err(Error::Network(format!("Ping failed: {}", e))),
-            Err(_) => Err(Error::Network("Ping timeout".to_string())),
+        match tokio::time::timeout(self.timeout, pinger.ping(sequence, &self.payload)).await {
+            Ok(Ok((packet, duration))) => match reply_failure(&packet) {
+                None => Ok(duration),
+                Some(kind) => Err((
+                    Error::Network(format!(
+                        "Ping failed: {} from {}",
+                        describe_reply(&packet),
+                        reply_source(&packet)
+                    )),
+                    kind,
+                )),
+            },
+            Ok(Err(e)) => {
+                let kind = match &e {
+                    SurgeError::IOError(e) => classify_io(e),
+                    SurgeError::Timeout { .. } => FailureKind::Unreachable,
+                    _ => FailureKind::Other,
+                };
+                Err((Error::Network(format!("Ping failed: {}", e)), kind))
+            }
+            Err(_) => Err((
+                Error::Network("Ping timeout".to_string()),
+                FailureKind::Unreachable,
+            )),
         }
     }
+}
 
-    /// Resolve a hostname or IP address to an IP address
-    async fn resolve_address(&self, address: &str) -> Result<IpAddr> {
-        // Try parsing as IP first
-        if let Ok(ip) = address.parse::<IpAddr>() {
-            return Ok(ip);
-        }
+/// Echo request payload of `bytes` bytes
+fn payload(bytes: usize) -> Vec<u8> {
+    PAYLOAD_PATTERN
+        .iter()
+        .copied()
+        .cycle()
+        .take(bytes)
+        .collect()
+}
 
-        // Resolve as hostname
-        let addr_str = format!("{}:0", address);
-        let addrs: Vec<_> = tokio::task::spawn_blocking(move || {
-            addr_str
-                .to_socket_addrs()
-                .map(|addrs| addrs.collect::<Vec<_>>())
-        })
+/// Kind of failure a reply reports, `None` for an echo reply.
+///
+/// Errors such as destination unreachable quote the echo request and are
+/// delivered like replies.
+fn reply_failure(packet: &IcmpPacket) -> Option<FailureKind> {
+    match packet {
+        IcmpPacket::V4(p) => classify_icmpv4(p.get_icmp_type().0, p.get_icmp_code().0),
+        IcmpPacket::V6(p) => classify_icmpv6(p.get_icmpv6_type().0, p.get_icmpv6_code().0),
+    }
+}
+
+/// ICMP client of family `kind`, bound to `source_address` if given
+fn client(kind: ICMP, source_address: Option<IpAddr>) -> Result<Client> {
+    let mut config = Config::builder().kind(kind);
+    if let Some(ip) = source_address {
+        config = config.bind(SocketAddr::new(ip, 0));
+    }
+    Client::new(&config.build()).map_err(|e| {
+        let source = source_address.map_or_else(String::new, |ip| format!(" bound to {}", ip));
+        Error::Network(format!("Failed to create ping client{}: {}", source, e))
+    })
+}
+
+/// ICMP type and code of a reply, for error messages
+fn describe_reply(packet: &IcmpPacket) -> String {
+    let (version, icmp_type, code) = match packet {
+        IcmpPacket::V4(p) => ("ICMP", p.get_icmp_type().0, p.get_icmp_code().0),
+        IcmpPacket::V6(p) => ("ICMPv6", p.get_icmpv6_type().0, p.get_icmpv6_code().0),
+    };
+    format!("{} type {} code {}", version, icmp_type, code)
+}
+
+fn reply_source(packet: &IcmpPacket) -> IpAddr {
+    match packet {
+        IcmpPacket::V4(p) => IpAddr::V4(p.get_source()),
+        IcmpPacket::V6(p) => IpAddr::V6(p.get_source()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::resolver::tests::SlowLookup;
+    use crate::monitor::reverse_dns::tests::CountingLookup;
+    use crate::monitor::reverse_dns::PtrCache;
+    use std::sync::Arc;
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_dscp_sets_traffic_class_of_ipv6_socket() {
+        use std::os::fd::BorrowedFd;
+
+        // ICMPv6 sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_secs(1), 1)
+            .and_then(|checker| checker.with_source_address(Some("::1".parse().unwrap())))
+        else {
+            return;
+        };
+        let checker = checker.with_dscp(Some(46));
+
+        let socket = checker.v6.as_ref().unwrap().get_socket();
+        // SAFETY: the descriptor is owned by `socket`, which outlives the borrow.
+        let fd = unsafe { BorrowedFd::borrow_raw(socket.get_native_sock()) };
+        let tclass = socket2::SockRef::from(&fd).tclass_v6().unwrap();
+        assert_eq!(tclass, qos::tos_from_dscp(46));
+    }
+
+    #[tokio::test]
+    async fn test_hung_dns_fails_check_with_timeout_error() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_secs(1), 1) else {
+            return;
+        };
+        let checker = checker.with_resolver(Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::from_secs(30),
+                addrs: vec![IpAddr::from([127, 0, 0, 1])],
+            }),
+            Duration::from_millis(100),
+        ));
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            checker.check(Uuid::now_v7(), &Endpoint::new("hung.example")),
+        )
         .await
-        .map_err(Error::JoinError)?
-        .map_err(|e| Error::Network(format!("Resolution failed: {}", e)))?;
+        .expect("check must not hang on DNS");
+
+        assert!(!result.is_successful());
+        assert!(result.error_message().unwrap().contains("timed out"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Unresolved));
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_quorum_overrides_global_quorum() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_secs(1), 3) else {
+            return;
+        };
+        assert_eq!(checker.required_successes(&Endpoint::new("10.0.0.1")), None);
+
+        let checker = checker.with_success_quorum(Some(SuccessQuorum::AtLeast(2)));
+        assert_eq!(
+            checker.required_successes(&Endpoint::new("10.0.0.1")),
+            Some(2)
+        );
+        assert_eq!(
+            checker.required_successes(
+                &Endpoint::new("10.0.0.1").with_success_quorum(SuccessQuorum::Percent(100))
+            ),
+            Some(3)
+        );
+    }
+
+    fn dns_resolution_ms(result: &MonitoringResult) -> Option<f64> {
+        match &result.check_type {
+            CheckType::PingCheck(check) => check.result.dns_resolution_ms,
+            other => panic!("unexpected check type: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dns_resolution_time_recorded_for_hostnames_only() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_millis(200), 1) else {
+            return;
+        };
+        let checker = checker.with_resolver(Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::from_millis(20),
+                addrs: vec![IpAddr::from([127, 0, 0, 1])],
+            }),
+            Duration::from_secs(1),
+        ));
+
+        let hostname = checker
+            .check(Uuid::now_v7(), &Endpoint::new("localhost.example"))
+            .await;
+        let dns_ms = dns_resolution_ms(&hostname).expect("hostname lookup is timed");
+        assert!(dns_ms >= 20.0, "dns_resolution_ms = {}", dns_ms);
+
+        let literal = checker
+            .check(Uuid::now_v7(), &Endpoint::new("127.0.0.1"))
+            .await;
+        assert_eq!(dns_resolution_ms(&literal), None);
+    }
+
+    #[tokio::test]
+    async fn test_loopback_source_address() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_millis(500), 1) else {
+            return;
+        };
+        let checker = checker
+            .with_source_address(Some(IpAddr::from([127, 0, 0, 1])))
+            .expect("loopback is a local address");
+
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("127.0.0.1"))
+            .await;
+        assert!(result.is_successful(), "{:?}", result.error_message());
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_target_pinged_through_icmpv6_socket() {
+        // ICMPv6 sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_millis(500), 1) else {
+            return;
+        };
+        if checker.v6.is_none() {
+            return;
+        }
+
+        let result = checker.check(Uuid::now_v7(), &Endpoint::new("::1")).await;
+        assert!(result.is_successful(), "{:?}", result.error_message());
+    }
+
+    #[tokio::test]
+    async fn test_target_of_other_family_than_source_address_fails() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_millis(500), 1)
+            .and_then(|checker| checker.with_source_address(Some(IpAddr::from([127, 0, 0, 1]))))
+        else {
+            return;
+        };
+
+        let result = checker.check(Uuid::now_v7(), &Endpoint::new("::1")).await;
+        assert!(!result.is_successful());
+        let message = result.error_message().unwrap_or_default();
+        assert!(message.contains("no ICMP socket for IPv6"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_foreign_source_address_fails() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_millis(500), 1) else {
+            return;
+        };
+
+        // TEST-NET-3 is not assigned to any interface of this host
+        let err = checker
+            .with_source_address(Some(IpAddr::from([203, 0, 113, 7])))
+            .err()
+            .expect("binding to a foreign address fails");
+        assert!(err.to_string().contains("bound to 203.0.113.7"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_packet_loss_and_jitter_are_reported() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_millis(300), 2) else {
+            return;
+        };
+        let ping = |result: &MonitoringResult| match &result.check_type {
+            CheckType::PingCheck(check) => check.result.clone(),
+            other => panic!("unexpected check type: {:?}", other),
+        };
+
+        let answered = checker
+            .check(Uuid::now_v7(), &Endpoint::new("127.0.0.1"))
+            .await;
+        assert_eq!(ping(&answered).packet_loss_percent, Some(0.0));
+        assert_eq!(ping(&answered).jitter_ms, ping(&answered).jitter_ms());
+        assert!(ping(&answered).jitter_ms.is_some());
+
+        // TEST-NET-2 is never routed to a host that answers
+        let lost = checker
+            .check(Uuid::now_v7(), &Endpoint::new("198.51.100.1"))
+            .await;
+        assert_eq!(ping(&lost).packet_loss_percent, Some(100.0));
+        assert_eq!(ping(&lost).jitter_ms, None);
+    }
+
+    #[test]
+    fn test_payload_repeats_pattern_to_size() {
+        assert_eq!(payload(DEFAULT_PING_PAYLOAD_BYTES), PAYLOAD_PATTERN);
+        assert!(payload(0).is_empty());
+        assert_eq!(payload(10), &PAYLOAD_PATTERN[..10]);
+
+        let large = payload(1400);
+        assert_eq!(large.len(), 1400);
+        assert_eq!(&large[56..112], PAYLOAD_PATTERN);
+    }
+
+    #[tokio::test]
+    async fn test_custom_payload_size_is_sent() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_secs(1), 1) else {
+            return;
+        };
+        let checker = checker.with_payload_bytes(1400);
+        assert_eq!(checker.payload.len(), 1400);
+        let Some(client) = &checker.v4 else {
+            return;
+        };
+
+        // The echo reply mirrors the request: 8 bytes of ICMP header plus
+        // the payload
+        let mut pinger = client
+            .pinger(IpAddr::from([127, 0, 0, 1]), PingIdentifier(rand::random()))
+            .await;
+        let (reply, _) = pinger
+            .ping(PingSequence(0), &checker.payload)
+            .await
+            .unwrap();
+        match reply {
+            IcmpPacket::V4(packet) => assert_eq!(packet.get_size(), 8 + 1400),
+            IcmpPacket::V6(_) => panic!("expected an ICMPv4 reply"),
+        }
+
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("127.0.0.1"))
+            .await;
+        assert!(result.is_successful());
+    }
+
+    fn resolved_hostname(result: &MonitoringResult) -> Option<String> {
+        match &result.check_type {
+            CheckType::PingCheck(check) => check.result.resolved_hostname.clone(),
+            other => panic!("unexpected check type: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reverse_dns_runs_for_loopback_and_failures_do_not_fail_check() {
+        // ICMP sockets may be unavailable in restricted environments.
+        let Ok(checker) = PingChecker::new(Duration::from_secs(1), 1) else {
+            return;
+        };
+        let endpoint = Endpoint::new("127.0.0.1");
+        let plain = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let failing = CountingLookup::new(None);
+        let checker = checker.with_reverse_dns(Some(ReverseResolver::with_lookup(
+            failing.clone(),
+            Duration::from_millis(200),
+            PtrCache::default(),
+        )));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert_eq!(failing.calls(), 1);
+        assert_eq!(resolved_hostname(&result), None);
+        assert_eq!(result.is_successful(), plain.is_successful());
 
-        debug!("DNS resolution {} to {:?}", address, debug(&addrs));
+        let found = CountingLookup::new(Some("localhost"));
+        let checker = checker.with_reverse_dns(Some(ReverseResolver::with_lookup(
+            found.clone(),
+            Duration::from_millis(200),
+            PtrCache::default(),
+        )));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
 
-        addrs
-            .first()
-            .map(|addr| addr.ip())
-            .ok_or_else(|| Error::Network(format!("Could not resolve address: {}", address)))
+        assert_eq!(found.calls(), 1);
+        assert_eq!(resolved_hostname(&result).as_deref(), Some("localhost"));
     }
 }
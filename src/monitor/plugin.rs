@@ -0,0 +1,281 @@
+//! Plugin checks
+//!
+//! Endpoints naming a `plugin` are checked by the plugin of that name in the
+//! agent's [`PluginRegistry`]. The plugin's result is reported as-is, with
+//! its `agent_id` and `endpoint_id` set to the agent and endpoint checked so
+//! a plugin cannot report for another one.
+//!
+//! A plugin has `monitoring.timeout_secs` to return, so a hanging plugin
+//! cannot hold its concurrency slot or the check cycle. A plugin that is not
+//! registered, returns an error or does not return in time produces a failed
+//! plugin result naming the plugin, so the endpoint is reported as down
+//! instead of silently missing. The timeout cancels a plugin at its next
+//! `.await`; plugins must not block the thread in synchronous code.
+
+use crate::core::{
+    CheckType, Endpoint, ErrorDetails, FailureKind, MonitoringResult, PluginCheck, PluginCheckType,
+    PluginResult,
+};
+use crate::plugin::PluginRegistry;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::trace;
+use uuid::Uuid;
+
+/// Checker for endpoints handled by a registered plugin
+pub struct PluginChecker {
+    timeout: Duration,
+    registry: Arc<PluginRegistry>,
+}
+
+impl PluginChecker {
+    /// Create a plugin checker that gives up on a plugin after `timeout`,
+    /// without any plugins registered
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            registry: Arc::default(),
+        }
+    }
+
+    /// Look plugins up in `registry`
+    pub fn with_registry(mut self, registry: Arc<PluginRegistry>) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Check `endpoint` with the plugin it names
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let name = endpoint.plugin.as_deref().unwrap_or_default();
+        let Some(plugin) = self.registry.get(name) else {
+            return failure(
+                agent_id,
+                endpoint,
+                name,
+                "",
+                None,
+                format!("Plugin {} is not registered", name),
+                FailureKind::Other,
+            );
+        };
+
+        let start = Instant::now();
+        let checked = tokio::time::timeout(self.timeout, plugin.check(&agent_id, endpoint)).await;
+        let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        trace!(
+            "Plugin {} v{} checked {} in {:.2} ms",
+            plugin.name(),
+            plugin.version(),
+            endpoint.address,
+            elapsed_ms
+        );
+
+        match checked {
+            Ok(Ok(mut result)) => {
+                result.agent_id = agent_id;
+                result.endpoint_id = endpoint.id;
+                result
+            }
+            Ok(Err(e)) => failure(
+                agent_id,
+                endpoint,
+                plugin.name(),
+                plugin.version(),
+                Some(elapsed_ms),
+                format!("Plugin check failed: {}", e),
+                FailureKind::Other,
+            ),
+            Err(_) => failure(
+                agent_id,
+                endpoint,
+                plugin.name(),
+                plugin.version(),
+                Some(elapsed_ms),
+                format!(
+                    "Plugin {} timed out after {:?}",
+                    plugin.name(),
+                    self.timeout
+                ),
+                FailureKind::Unreachable,
+            ),
+        }
+    }
+}
+
+/// Failed result for plugin checks that returned no result of their own
+fn failure(
+    agent_id: Uuid,
+    endpoint: &Endpoint,
+    plugin_name: &str,
+    plugin_version: &str,
+    response_time_ms: Option<f64>,
+    error: String,
+    kind: FailureKind,
+) -> MonitoringResult {
+    MonitoringResult {
+        id: Uuid::now_v7(),
+        agent_id,
+        endpoint_id: endpoint.id,
+        check_type: CheckType::PluginCheck(PluginCheck {
+            r#type: PluginCheckType::Plugin,
+            result: PluginResult {
+                plugin_name: plugin_name.to_string(),
+                plugin_version: plugin_version.to_string(),
+                success: false,
+                response_time_ms,
+                error_details: Some(ErrorDetails {
+                    errors: Some(vec![error]),
+                }),
+                data: HashMap::new(),
+            },
+        }),
+        timestamp: Utc::now(),
+        warmup: None,
+        degraded: None,
+        failure_kind: Some(kind),
+        status: None,
+        notes: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, Result};
+    use crate::plugin::MonitoringPlugin;
+    use async_trait::async_trait;
+
+    /// Plugin answering after `delay`, failing for addresses starting with
+    /// `fail`
+    struct EchoPlugin {
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl MonitoringPlugin for EchoPlugin {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        async fn check(&self, _agent_id: &Uuid, endpoint: &Endpoint) -> Result<MonitoringResult> {
+            tokio::time::sleep(self.delay).await;
+            if endpoint.address.starts_with("fail") {
+                return Err(Error::Plugin("echo failed".to_string()));
+            }
+            Ok(MonitoringResult {
+                id: Uuid::now_v7(),
+                agent_id: Uuid::nil(),
+                endpoint_id: Uuid::nil(),
+                check_type: CheckType::PluginCheck(PluginCheck {
+                    r#type: PluginCheckType::Plugin,
+                    result: PluginResult {
+                        plugin_name: "echo".to_string(),
+                        plugin_version: "1.0.0".to_string(),
+                        success: true,
+                        response_time_ms: Some(1.0),
+                        error_details: None,
+                        data: HashMap::from([("address".to_string(), endpoint.address.clone())]),
+                    },
+                }),
+                timestamp: Utc::now(),
+                warmup: None,
+                degraded: None,
+                failure_kind: None,
+                status: None,
+                notes: Vec::new(),
+            })
+        }
+    }
+
+    fn checker(delay: Duration, timeout: Duration) -> PluginChecker {
+        let mut registry = PluginRegistry::new();
+        registry.register(Box::new(EchoPlugin { delay }));
+        PluginChecker::new(timeout).with_registry(Arc::new(registry))
+    }
+
+    fn plugin_result(result: &MonitoringResult) -> &PluginResult {
+        match &result.check_type {
+            CheckType::PluginCheck(c) => &c.result,
+            other => panic!("expected a plugin result, got {:?}", other),
+        }
+    }
+
+    fn error(result: &MonitoringResult) -> String {
+        plugin_result(result)
+            .error_details
+            .as_ref()
+            .and_then(|details| details.errors.as_ref())
+            .map(|errors| errors.join("; "))
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_plugin_result_is_reported_for_the_endpoint() {
+        let checker = checker(Duration::ZERO, Duration::from_secs(5));
+        let agent_id = Uuid::now_v7();
+        let endpoint = Endpoint::new("echo://example.com").with_plugin("echo");
+
+        let result = checker.check(agent_id, &endpoint).await;
+
+        assert!(result.is_successful());
+        assert_eq!(result.agent_id, agent_id);
+        assert_eq!(result.endpoint_id, endpoint.id);
+        assert_eq!(plugin_result(&result).data["address"], "echo://example.com");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_plugin_fails() {
+        let checker = checker(Duration::ZERO, Duration::from_secs(5));
+        let endpoint = Endpoint::new("10.0.0.1").with_plugin("missing");
+
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(!result.is_successful());
+        assert_eq!(result.endpoint_id, endpoint.id);
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+        assert_eq!(plugin_result(&result).plugin_name, "missing");
+        assert!(error(&result).contains("Plugin missing is not registered"));
+    }
+
+    #[tokio::test]
+    async fn test_plugin_error_fails() {
+        let checker = checker(Duration::ZERO, Duration::from_secs(5));
+        let endpoint = Endpoint::new("fail.example.com").with_plugin("echo");
+
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(!result.is_successful());
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+        assert_eq!(plugin_result(&result).plugin_version, "1.0.0");
+        assert!(error(&result).contains("echo failed"), "{}", error(&result));
+    }
+
+    #[tokio::test]
+    async fn test_slow_plugin_times_out() {
+        let checker = checker(Duration::from_secs(60), Duration::from_millis(50));
+        let agent_id = Uuid::now_v7();
+        let endpoint = Endpoint::new("example.com").with_plugin("echo");
+
+        let started = Instant::now();
+        let result = checker.check(agent_id, &endpoint).await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        assert!(!result.is_successful());
+        assert_eq!(result.agent_id, agent_id);
+        assert_eq!(result.endpoint_id, endpoint.id);
+        assert_eq!(result.failure_kind, Some(FailureKind::Unreachable));
+        let plugin = plugin_result(&result);
+        assert!(!plugin.success);
+        assert_eq!(plugin.plugin_name, "echo");
+        assert_eq!(plugin.plugin_version, "1.0.0");
+        assert!(plugin.response_time_ms.is_some_and(|ms| ms >= 50.0));
+        assert_eq!(error(&result), "Plugin echo timed out after 50ms");
+    }
+}
@@ -0,0 +1,105 @@
+//! DSCP / ToS marking for check sockets
+//!
+//! The DSCP value occupies the upper six bits of the IPv4 ToS byte and of the
+//! IPv6 traffic class, so it is shifted left by two before being applied.
+//!
+//! Platform limitations:
+//! - Linux, Android, macOS and the BSDs support both `IP_TOS` and
+//!   `IPV6_TCLASS`. Other targets report [`io::ErrorKind::Unsupported`].
+//! - Some kernels and middleboxes clear or rewrite the marking, so a value set
+//!   here is not guaranteed to survive the path to the endpoint.
+//! - Unprivileged ICMP (`SOCK_DGRAM`) sockets accept `IP_TOS` on Linux;
+//!   raw sockets may additionally require `CAP_NET_RAW`.
+
+use socket2::SockRef;
+use std::io;
+
+/// Convert a six-bit DSCP value into the eight-bit ToS / traffic class byte.
+pub(crate) fn tos_from_dscp(dscp: u8) -> u32 {
+    u32::from(dscp & 0x3f) << 2
+}
+
+/// Mark outgoing packets on `socket` with `dscp`.
+///
+/// `ipv6` selects `IPV6_TCLASS` instead of `IP_TOS`.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+pub(crate) fn apply_dscp(socket: SockRef<'_>, dscp: u8, ipv6: bool) -> io::Result<()> {
+    let tos = tos_from_dscp(dscp);
+    if ipv6 {
+        socket.set_tclass_v6(tos)
+    } else {
+        socket.set_tos_v4(tos)
+    }
+}
+
+/// Mark outgoing packets on `socket` with `dscp`.
+///
+/// DSCP marking is not supported on this platform.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+)))]
+pub(crate) fn apply_dscp(_socket: SockRef<'_>, _dscp: u8, _ipv6: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "DSCP marking is not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use socket2::{Domain, Socket, Type};
+    use std::net::UdpSocket;
+
+    #[test]
+    fn test_tos_from_dscp() {
+        assert_eq!(tos_from_dscp(0), 0);
+        assert_eq!(tos_from_dscp(46), 0xb8); // EF
+        assert_eq!(tos_from_dscp(34), 0x88); // AF41
+        assert_eq!(tos_from_dscp(63), 0xfc);
+    }
+
+    #[test]
+    fn test_tos_from_dscp_ignores_high_bits() {
+        assert_eq!(tos_from_dscp(0xff), tos_from_dscp(63));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_dscp_to_udp_socket() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        apply_dscp(SockRef::from(&socket), 46, false).unwrap();
+        assert_eq!(SockRef::from(&socket).tos_v4().unwrap(), 0xb8);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_dscp_to_tcp_socket() {
+        let socket = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+        apply_dscp(SockRef::from(&socket), 10, false).unwrap();
+        assert_eq!(socket.tos_v4().unwrap(), tos_from_dscp(10));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_dscp_to_ipv6_socket() {
+        // IPv6 may be disabled in the build environment.
+        let Ok(socket) = Socket::new(Domain::IPV6, Type::DGRAM, None) else {
+            return;
+        };
+        apply_dscp(SockRef::from(&socket), 46, true).unwrap();
+        assert_eq!(socket.tclass_v6().unwrap(), 0xb8);
+    }
+}
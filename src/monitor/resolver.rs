@@ -0,0 +1,807 @@
+//! Hostname resolution for checks
+//!
+//! The system resolver (`getaddrinfo`) is blocking and has no timeout of its
+//! own, so lookups run on the blocking pool and are bounded by
+//! `tokio::time::timeout`. A hung resolver therefore fails the check with a
+//! DNS timeout error instead of stalling it indefinitely. The abandoned
+//! blocking lookup finishes (or not) in the background without holding up
+//! the check.
+//!
+//! `resolve` also reports how long the lookup took, so checks can
+//! record DNS resolution time separately from the time spent probing the
+//! target.
+//!
+//! With `monitoring.resolver.backend = "nameservers"` lookups bypass the
+//! operating system and query the configured nameservers directly over UDP,
+//! TCP, TLS or HTTPS, so a specific DNS server can be monitored through the
+//! checks that depend on it.
+//!
+//! Successful lookups are kept in a [`DnsCache`] that outlives the per-tick
+//! checkers, for `monitoring.dns_cache_ttl_secs`. Within that time a
+//! hostname is resolved once, not on every check of every endpoint using
+//! it. Failed lookups are not cached and are retried by the next check.
+//! Entries are keyed by the resolver backend as well, so answers of the
+//! system resolver are not served once `monitoring.resolver` switches to
+//! nameservers (or to other nameservers) by hot reload.
+//!
+//! When a hostname resolves to several addresses, `monitoring.ip_version`
+//! picks the one that is checked: the first IPv4 or IPv6 address, or with
+//! `auto` the first IPv6 address the agent has a route to and otherwise the
+//! first IPv4 address. Like happy eyeballs clients this prefers IPv6 without
+//! getting stuck on it on IPv4-only hosts, but the choice does not depend on
+//! the order the resolver returned the families in.
+
+use crate::agent_config::{DnsProtocol, DnsResolverConfig, IpVersion};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use hickory_resolver::config::{NameServerConfig, ResolveHosts, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::xfer::Protocol;
+use hickory_resolver::TokioResolver;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Cached hostnames before expired entries are purged
+const MAX_CACHED_HOSTS: usize = 10_000;
+
+/// Backend performing the actual name lookup
+#[async_trait]
+pub(crate) trait Lookup: Send + Sync {
+    /// Look up the addresses of `host`
+    async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>>;
+}
+
+/// Lookup through the operating system resolver
+struct SystemLookup;
+
+#[async_trait]
+impl Lookup for SystemLookup {
+    async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        let addr_str = format!("{}:0", host);
+        tokio::task::spawn_blocking(move || {
+            addr_str
+                .to_socket_addrs()
+                .map(|addrs| addrs.map(|addr| addr.ip()).collect())
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+/// Lookup querying configured nameservers instead of the system resolver
+struct NameserverLookup {
+    resolver: TokioResolver,
+}
+
+impl NameserverLookup {
+    fn new(addrs: &[SocketAddr], protocol: DnsProtocol, tls_name: Option<&str>) -> Self {
+        let config = nameserver_config(addrs, protocol, tls_name);
+        let mut builder =
+            TokioResolver::builder_with_config(config, TokioConnectionProvider::default());
+        // Answers must come from the configured nameservers
+        builder.options_mut().use_hosts_file = ResolveHosts::Never;
+
+        Self {
+            resolver: builder.build(),
+        }
+    }
+}
+
+/// Resolver configuration querying `addrs` over `protocol`
+pub(crate) fn nameserver_config(
+    addrs: &[SocketAddr],
+    protocol: DnsProtocol,
+    tls_name: Option<&str>,
+) -> ResolverConfig {
+    let protocol = match protocol {
+        DnsProtocol::Udp => Protocol::Udp,
+        DnsProtocol::Tcp => Protocol::Tcp,
+        DnsProtocol::Tls => Protocol::Tls,
+        DnsProtocol::Https => Protocol::Https,
+    };
+
+    let mut config = ResolverConfig::new();
+    for addr in addrs {
+        let mut nameserver = NameServerConfig::new(*addr, protocol);
+        nameserver.tls_dns_name = tls_name.map(str::to_string);
+        config.add_name_server(nameserver);
+    }
+    config
+}
+
+#[async_trait]
+impl Lookup for NameserverLookup {
+    async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        let lookup = self
+            .resolver
+            .lookup_ip(host)
+            .await
+            .map_err(io::Error::other)?;
+        Ok(lookup.iter().collect())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedAddr {
+    ip: IpAddr,
+    expires: Instant,
+}
+
+/// Cache key: resolver backend, hostname and IP version
+type CacheKey = (Arc<str>, String, IpVersion);
+
+/// Resolved hostnames shared across check cycles
+#[derive(Debug, Clone, Default)]
+pub struct DnsCache(Arc<Mutex<HashMap<CacheKey, CachedAddr>>>);
+
+impl DnsCache {
+    /// Cached `version` address of `host` as resolved by `backend`, `None`
+    /// if absent or expired
+    fn get(
+        &self,
+        backend: &Arc<str>,
+        host: &str,
+        version: IpVersion,
+        now: Instant,
+    ) -> Option<IpAddr> {
+        self.0
+            .lock()
+            .get(&(Arc::clone(backend), host.to_string(), version))
+            .filter(|cached| cached.expires > now)
+            .map(|cached| cached.ip)
+    }
+
+    /// Cache `ip` as the `version` address of `host` resolved by `backend`
+    /// until `expires`, returning the address cached before if it was a
+    /// different one
+    fn insert(
+        &self,
+        backend: &Arc<str>,
+        host: &str,
+        version: IpVersion,
+        ip: IpAddr,
+        expires: Instant,
+    ) -> Option<IpAddr> {
+        let key = (Arc::clone(backend), host.to_string(), version);
+        let mut entries = self.0.lock();
+        if entries.len() >= MAX_CACHED_HOSTS && !entries.contains_key(&key) {
+            let now = Instant::now();
+            entries.retain(|_, cached| cached.expires > now);
+            if entries.len() >= MAX_CACHED_HOSTS {
+                return None;
+            }
+        }
+
+        entries
+            .insert(key, CachedAddr { ip, expires })
+            .map(|previous| previous.ip)
+            .filter(|previous| *previous != ip)
+    }
+}
+
+/// Resolves endpoint addresses with a bounded lookup time
+#[derive(Clone)]
+pub(crate) struct Resolver {
+    lookup: Arc<dyn Lookup>,
+    /// Identity of the lookup backend, part of the cache key
+    backend: Arc<str>,
+    timeout: Duration,
+    ip_version: IpVersion,
+    cache: Option<(DnsCache, Duration)>,
+}
+
+impl Resolver {
+    /// Resolver using the system resolver, giving up after `timeout`
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self::with_backend(Arc::new(SystemLookup), "system", timeout)
+    }
+
+    /// Resolver using a custom lookup backend
+    #[cfg(test)]
+    pub(crate) fn with_lookup(lookup: Arc<dyn Lookup>, timeout: Duration) -> Self {
+        Self::with_backend(lookup, "custom", timeout)
+    }
+
+    fn with_backend(lookup: Arc<dyn Lookup>, backend: &str, timeout: Duration) -> Self {
+        Self {
+            lookup,
+            backend: backend.into(),
+            timeout,
+            ip_version: IpVersion::Auto,
+            cache: None,
+        }
+    }
+
+    /// Pick addresses of `ip_version` from the resolved addresses
+    pub(crate) fn with_ip_version(mut self, ip_version: IpVersion) -> Self {
+        self.ip_version = ip_version;
+        self
+    }
+
+    /// Reuse successful lookups from `cache` for `ttl`. A zero `ttl`
+    /// resolves on every call.
+    pub(crate) fn with_cache(mut self, cache: DnsCache, ttl: Duration) -> Self {
+        self.cache = (!ttl.is_zero()).then_some((cache, ttl));
+        self
+    }
+
+    /// Resolver for the configured backend, `None` for the system resolver
+    pub(crate) fn from_config(
+        config: &DnsResolverConfig,
+        timeout: Duration,
+    ) -> Result<Option<Self>> {
+        let DnsResolverConfig::Nameservers {
+            protocol, tls_name, ..
+        } = config
+        else {
+            return Ok(None);
+        };
+
+        let addrs = config.nameserver_addrs().map_err(Error::Config)?;
+        let lookup = NameserverLookup::new(&addrs, *protocol, tls_name.as_deref());
+        let backend = format!(
+            "{:?} {:?} {}",
+            protocol,
+            addrs,
+            tls_name.as_deref().unwrap_or_default()
+        );
+        Ok(Some(Self::with_backend(
+            Arc::new(lookup),
+            &backend,
+            timeout,
+        )))
+    }
+
+    /// Resolve a hostname or IP address to an IP address, also returning the
+    /// lookup time.
+    ///
+    /// IP literals and cached hostnames are returned without a lookup and a
+    /// lookup time of `None`; literals are used whatever the configured IP
+    /// version. Other hostnames resolve to an address of the configured IP
+    /// version (see [`pick_address`]); their lookup time is measured whether or not
+    /// the lookup succeeds, so a DNS timeout still shows how long the check
+    /// waited on the resolver.
+    pub(crate) async fn resolve(&self, address: &str) -> (Result<IpAddr>, Option<Duration>) {
+        if let Ok(ip) = address.parse::<IpAddr>() {
+            return (Ok(ip), None);
+        }
+
+        let start = Instant::now();
+        if let Some((cache, _)) = &self.cache {
+            if let Some(ip) = cache.get(&self.backend, address, self.ip_version, start) {
+                return (Ok(ip), None);
+            }
+        }
+
+        let result = self.lookup_host(address).await;
+        if let (Ok(ip), Some((cache, ttl))) = (&result, &self.cache) {
+            if let Some(previous) =
+                cache.insert(&self.backend, address, self.ip_version, *ip, start + *ttl)
+            {
+                debug!("{} now resolves to {} instead of {}", address, ip, previous);
+            }
+        }
+        (result, Some(start.elapsed()))
+    }
+
+    async fn lookup_host(&self, address: &str) -> Result<IpAddr> {
+        let addrs = tokio::time::timeout(self.timeout, self.lookup.lookup(address))
+            .await
+            .map_err(|_| {
+                Error::Network(format!(
+                    "DNS resolution of {} timed out after {:?}",
+                    address, self.timeout
+                ))
+            })?
+            .map_err(|e| Error::Network(format!("Resolution failed: {}", e)))?;
+
+        debug!("DNS resolution {} to {:?}", address, addrs);
+
+        if addrs.is_empty() {
+            return Err(Error::Network(format!(
+                "Could not resolve address: {}",
+                address
+            )));
+        }
+
+        pick_address(&addrs, self.ip_version, has_route).ok_or_else(|| {
+            let family = match self.ip_version {
+                IpVersion::V6 => "IPv6",
+                _ => "IPv4",
+            };
+            Error::Network(format!(
+                "{} has no {} address (resolved to {:?})",
+                address, family, addrs
+            ))
+        })
+    }
+}
+
+/// Address of `addrs` to check for `version`, `None` if there is none of
+/// that family
+///
+/// `Auto` takes the first IPv6 address that is `routable`, then the first
+/// IPv4 address, then the first address at all.
+fn pick_address(
+    addrs: &[IpAddr],
+    version: IpVersion,
+    routable: impl Fn(IpAddr) -> bool,
+) -> Option<IpAddr> {
+    let mut addrs = addrs.iter().copied();
+    match version {
+        IpVersion::V4 => addrs.find(IpAddr::is_ipv4),
+        IpVersion::V6 => addrs.find(IpAddr::is_ipv6),
+        IpVersion::Auto => {
+            let all: Vec<IpAddr> = addrs.collect();
+            all.iter()
+                .copied()
+                .find(|ip| ip.is_ipv6() && routable(*ip))
+                .or_else(|| all.iter().copied().find(IpAddr::is_ipv4))
+                .or_else(|| all.first().copied())
+        }
+    }
+}
+
+/// Whether the agent has a route to `ip`
+///
+/// Connecting a UDP socket only selects a route and source address; no
+/// packet is sent.
+fn has_route(ip: IpAddr) -> bool {
+    let local: IpAddr = match ip {
+        IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    UdpSocket::bind((local, 0))
+        .and_then(|socket| socket.connect((ip, 9)))
+        .is_ok()
+}
+
+/// Failed lookup of the HTTP client's resolver, a type of its own so that
+/// failed requests are classified as unresolved by their error source
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub(crate) struct LookupFailed(#[from] pub(crate) Error);
+
+/// Lets the HTTP client resolve through the configured backend
+impl reqwest::dns::Resolve for Resolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let ip = resolver
+                .resolve(name.as_str())
+                .await
+                .0
+                .map_err(LookupFailed)?;
+            let addrs: reqwest::dns::Addrs = Box::new(std::iter::once(SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Lookup that answers after `delay`
+    pub(crate) struct SlowLookup {
+        pub(crate) delay: Duration,
+        pub(crate) addrs: Vec<IpAddr>,
+    }
+
+    #[async_trait]
+    impl Lookup for SlowLookup {
+        async fn lookup(&self, _host: &str) -> io::Result<Vec<IpAddr>> {
+            tokio::time::sleep(self.delay).await;
+            Ok(self.addrs.clone())
+        }
+    }
+
+    /// Minimal UDP nameserver answering every A query with `answer`
+    ///
+    /// Returns its address and the names it was asked for.
+    pub(crate) async fn spawn_nameserver(
+        answer: std::net::Ipv4Addr,
+    ) -> (SocketAddr, Arc<parking_lot::Mutex<Vec<String>>>) {
+        use hickory_resolver::proto::op::{Message, MessageType};
+        use hickory_resolver::proto::rr::rdata::A;
+        use hickory_resolver::proto::rr::{RData, Record, RecordType};
+
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let queried = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let seen = Arc::clone(&queried);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(request) = Message::from_vec(&buf[..len]) else {
+                    continue;
+                };
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(request.op_code())
+                    .set_recursion_desired(request.recursion_desired())
+                    .set_recursion_available(true);
+                for query in request.queries() {
+                    response.add_query(query.clone());
+                    if query.query_type() == RecordType::A {
+                        seen.lock().push(query.name().to_string());
+                        response.add_answer(Record::from_rdata(
+                            query.name().clone(),
+                            60,
+                            RData::A(A(answer)),
+                        ));
+                    }
+                }
+                let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+            }
+        });
+
+        (addr, queried)
+    }
+
+    /// Lookup answering 192.0.2.n on its n-th call
+    #[derive(Default)]
+    struct CountingLookup {
+        calls: std::sync::atomic::AtomicU8,
+    }
+
+    impl CountingLookup {
+        fn calls(&self) -> u8 {
+            self.calls.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl Lookup for CountingLookup {
+        async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+            if host == "missing.example" {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no such host"));
+            }
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            Ok(vec![IpAddr::from([192, 0, 2, call])])
+        }
+    }
+
+    fn slow_resolver(delay: Duration, timeout: Duration) -> Resolver {
+        Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay,
+                addrs: vec![IpAddr::from([192, 0, 2, 1])],
+            }),
+            timeout,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_slow_lookup_times_out() {
+        let resolver = slow_resolver(Duration::from_secs(30), Duration::from_millis(50));
+
+        let start = Instant::now();
+        let err = resolver.resolve("slow.example").await.0.unwrap_err();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(
+            err.to_string()
+                .contains("DNS resolution of slow.example timed out"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_within_timeout_succeeds() {
+        let resolver = slow_resolver(Duration::from_millis(10), Duration::from_secs(5));
+        assert_eq!(
+            resolver.resolve("fast.example").await.0.unwrap(),
+            IpAddr::from([192, 0, 2, 1])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ip_literal_skips_lookup() {
+        let resolver = slow_resolver(Duration::from_secs(30), Duration::from_millis(50));
+        assert_eq!(
+            resolver.resolve("2001:db8::1").await.0.unwrap(),
+            "2001:db8::1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_measures_hostname_lookups_only() {
+        let resolver = slow_resolver(Duration::from_millis(20), Duration::from_secs(5));
+
+        let (ip, lookup_time) = resolver.resolve("fast.example").await;
+        assert!(ip.is_ok());
+        assert!(lookup_time.unwrap() >= Duration::from_millis(20));
+
+        let (ip, lookup_time) = resolver.resolve("192.0.2.7").await;
+        assert_eq!(ip.unwrap(), IpAddr::from([192, 0, 2, 7]));
+        assert_eq!(lookup_time, None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_measures_failed_lookups() {
+        let resolver = slow_resolver(Duration::from_secs(30), Duration::from_millis(50));
+
+        let (ip, lookup_time) = resolver.resolve("slow.example").await;
+        assert!(ip.is_err());
+        assert!(lookup_time.unwrap() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_cached_hostname_is_not_looked_up_again() {
+        let lookup = Arc::new(CountingLookup::default());
+        let resolver = Resolver::with_lookup(lookup.clone(), Duration::from_secs(1))
+            .with_cache(DnsCache::default(), Duration::from_secs(300));
+
+        let (first, lookup_time) = resolver.resolve("api.example.com").await;
+        assert_eq!(first.unwrap(), IpAddr::from([192, 0, 2, 1]));
+        assert!(lookup_time.is_some());
+
+        let (second, lookup_time) = resolver.resolve("api.example.com").await;
+        assert_eq!(second.unwrap(), IpAddr::from([192, 0, 2, 1]));
+        assert_eq!(lookup_time, None, "a cached answer takes no lookup");
+        assert_eq!(lookup.calls(), 1);
+
+        resolver.resolve("other.example.com").await.0.unwrap();
+        assert_eq!(lookup.calls(), 2, "hostnames are cached separately");
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_shared_between_resolvers() {
+        let lookup = Arc::new(CountingLookup::default());
+        let cache = DnsCache::default();
+        let resolver = |cache: &DnsCache| {
+            Resolver::with_lookup(lookup.clone(), Duration::from_secs(1))
+                .with_cache(cache.clone(), Duration::from_secs(300))
+        };
+
+        resolver(&cache).resolve("api.example.com").await.0.unwrap();
+        resolver(&cache).resolve("api.example.com").await.0.unwrap();
+        assert_eq!(lookup.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entry_is_resolved_again() {
+        let lookup = Arc::new(CountingLookup::default());
+        let resolver = Resolver::with_lookup(lookup.clone(), Duration::from_secs(1))
+            .with_cache(DnsCache::default(), Duration::from_millis(50));
+
+        let first = resolver.resolve("api.example.com").await.0.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (second, lookup_time) = resolver.resolve("api.example.com").await;
+
+        assert_eq!(lookup.calls(), 2);
+        assert!(lookup_time.is_some());
+        assert_ne!(
+            second.unwrap(),
+            first,
+            "the new address replaces the old one"
+        );
+        assert_eq!(
+            resolver.resolve("api.example.com").await.0.unwrap(),
+            IpAddr::from([192, 0, 2, 2])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_and_failures_are_not_cached() {
+        let lookup = Arc::new(CountingLookup::default());
+        let resolver = Resolver::with_lookup(lookup.clone(), Duration::from_secs(1))
+            .with_cache(DnsCache::default(), Duration::ZERO);
+        resolver.resolve("api.example.com").await.0.unwrap();
+        resolver.resolve("api.example.com").await.0.unwrap();
+        assert_eq!(lookup.calls(), 2);
+
+        let cache = DnsCache::default();
+        let resolver = Resolver::with_lookup(lookup.clone(), Duration::from_secs(1))
+            .with_cache(cache.clone(), Duration::from_secs(300));
+        assert!(resolver.resolve("missing.example").await.0.is_err());
+        assert_eq!(
+            cache.get(
+                &resolver.backend,
+                "missing.example",
+                IpVersion::Auto,
+                Instant::now()
+            ),
+            None
+        );
+    }
+
+    const V4: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    const V6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+    #[test]
+    fn test_pick_address_filters_by_family() {
+        for addrs in [[V4, V6], [V6, V4]] {
+            assert_eq!(pick_address(&addrs, IpVersion::V4, |_| true), Some(V4));
+            assert_eq!(pick_address(&addrs, IpVersion::V6, |_| true), Some(V6));
+        }
+        assert_eq!(pick_address(&[V4], IpVersion::V6, |_| true), None);
+        assert_eq!(pick_address(&[V6], IpVersion::V4, |_| true), None);
+    }
+
+    #[test]
+    fn test_auto_prefers_routable_ipv6() {
+        for addrs in [[V4, V6], [V6, V4]] {
+            assert_eq!(pick_address(&addrs, IpVersion::Auto, |_| true), Some(V6));
+            assert_eq!(
+                pick_address(&addrs, IpVersion::Auto, |_| false),
+                Some(V4),
+                "IPv4 without a route to the IPv6 address"
+            );
+        }
+        assert_eq!(pick_address(&[V4], IpVersion::Auto, |_| true), Some(V4));
+        assert_eq!(
+            pick_address(&[V6], IpVersion::Auto, |_| false),
+            Some(V6),
+            "an IPv6-only host is still checked"
+        );
+    }
+
+    #[test]
+    fn test_loopback_is_routable() {
+        assert!(has_route(IpAddr::from([127, 0, 0, 1])));
+    }
+
+    #[tokio::test]
+    async fn test_missing_family_fails_with_clear_error() {
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: vec![V4],
+            }),
+            Duration::from_secs(1),
+        );
+
+        assert_eq!(resolver.resolve("v4only.example").await.0.unwrap(), V4);
+
+        let err = resolver
+            .clone()
+            .with_ip_version(IpVersion::V6)
+            .resolve("v4only.example")
+            .await
+            .0
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("v4only.example has no IPv6 address"),
+            "{}",
+            err
+        );
+
+        let literal = resolver
+            .with_ip_version(IpVersion::V6)
+            .resolve("192.0.2.7")
+            .await
+            .0
+            .unwrap();
+        assert_eq!(literal, IpAddr::from([192, 0, 2, 7]), "literals are kept");
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_host_resolves_to_requested_family() {
+        let resolver = |version| {
+            Resolver::with_lookup(
+                Arc::new(SlowLookup {
+                    delay: Duration::ZERO,
+                    addrs: vec![V4, V6],
+                }),
+                Duration::from_secs(1),
+            )
+            .with_ip_version(version)
+        };
+
+        let v4 = resolver(IpVersion::V4).resolve("dual.example").await.0;
+        assert_eq!(v4.unwrap(), V4);
+        let v6 = resolver(IpVersion::V6).resolve("dual.example").await.0;
+        assert_eq!(v6.unwrap(), V6);
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_kept_per_ip_version() {
+        let cache = DnsCache::default();
+        let resolver = |version| {
+            Resolver::with_lookup(
+                Arc::new(SlowLookup {
+                    delay: Duration::ZERO,
+                    addrs: vec![V4, V6],
+                }),
+                Duration::from_secs(1),
+            )
+            .with_ip_version(version)
+            .with_cache(cache.clone(), Duration::from_secs(300))
+        };
+
+        resolver(IpVersion::V4)
+            .resolve("dual.example")
+            .await
+            .0
+            .unwrap();
+        let v6 = resolver(IpVersion::V6).resolve("dual.example").await;
+        assert_eq!(v6.0.unwrap(), V6);
+        assert!(v6.1.is_some(), "the IPv4 answer is not reused for IPv6");
+    }
+
+    #[tokio::test]
+    async fn test_system_backend_builds_no_resolver() {
+        let resolver = Resolver::from_config(&DnsResolverConfig::System, Duration::from_secs(1));
+        assert!(resolver.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_configured_nameserver_answers_lookups() {
+        let (nameserver, queried) = spawn_nameserver([192, 0, 2, 53].into()).await;
+        let config = DnsResolverConfig::Nameservers {
+            nameservers: vec![nameserver.to_string()],
+            protocol: DnsProtocol::Udp,
+            tls_name: None,
+        };
+        let resolver = Resolver::from_config(&config, Duration::from_secs(5))
+            .unwrap()
+            .unwrap();
+
+        let (ip, lookup_time) = resolver.resolve("probe.smotra.test").await;
+
+        assert_eq!(ip.unwrap(), IpAddr::from([192, 0, 2, 53]));
+        assert!(lookup_time.is_some());
+        assert_eq!(*queried.lock(), vec!["probe.smotra.test.".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_kept_per_backend() {
+        let cache = DnsCache::default();
+        let system = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: vec![V4],
+            }),
+            Duration::from_secs(1),
+        )
+        .with_cache(cache.clone(), Duration::from_secs(300));
+        assert_eq!(system.resolve("probe.smotra.test").await.0.unwrap(), V4);
+
+        // Switching to nameservers must not serve the other backend's answer
+        let (nameserver, queried) = spawn_nameserver([192, 0, 2, 53].into()).await;
+        let config = DnsResolverConfig::Nameservers {
+            nameservers: vec![nameserver.to_string()],
+            protocol: DnsProtocol::Udp,
+            tls_name: None,
+        };
+        let resolver = |cache: &DnsCache| {
+            Resolver::from_config(&config, Duration::from_secs(5))
+                .unwrap()
+                .unwrap()
+                .with_cache(cache.clone(), Duration::from_secs(300))
+        };
+        let (ip, lookup_time) = resolver(&cache).resolve("probe.smotra.test").await;
+        assert_eq!(ip.unwrap(), IpAddr::from([192, 0, 2, 53]));
+        assert!(lookup_time.is_some());
+
+        // The same nameservers share their answers
+        let (ip, lookup_time) = resolver(&cache).resolve("probe.smotra.test").await;
+        assert_eq!(ip.unwrap(), IpAddr::from([192, 0, 2, 53]));
+        assert_eq!(lookup_time, None);
+        assert_eq!(queried.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_empty_answer_fails() {
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: Vec::new(),
+            }),
+            Duration::from_secs(1),
+        );
+        assert!(resolver.resolve("empty.example").await.0.is_err());
+    }
+}
@@ -0,0 +1,286 @@
+//! Reverse DNS (PTR) lookup of resolved addresses
+//!
+//! With `monitoring.reverse_dns` enabled, ping results carry the PTR hostname
+//! of their `resolved_ip` in `resolved_hostname`. The lookup is best effort:
+//! a missing PTR record, a resolver error or a timeout
+//! (`monitoring.reverse_dns_timeout_secs`) leaves the field empty and never
+//! fails the check.
+//!
+//! Answers are kept in a [`PtrCache`] that outlives the per-tick checkers:
+//! hostnames for an hour, missing records and failed lookups for five
+//! minutes. Each address is therefore looked up once per TTL rather than on
+//! every check.
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// How long a found hostname is cached
+const POSITIVE_TTL: Duration = Duration::from_secs(3600);
+
+/// How long a missing record or failed lookup is cached
+const NEGATIVE_TTL: Duration = Duration::from_secs(300);
+
+/// Cached addresses before expired entries are purged
+const MAX_ENTRIES: usize = 10_000;
+
+/// Backend performing the actual PTR lookup
+#[async_trait]
+pub(crate) trait ReverseLookup: Send + Sync {
+    /// Look up the hostname of `ip`
+    async fn lookup(&self, ip: IpAddr) -> io::Result<String>;
+}
+
+/// Lookup through the operating system resolver (`getnameinfo`)
+struct SystemReverseLookup;
+
+#[async_trait]
+impl ReverseLookup for SystemReverseLookup {
+    async fn lookup(&self, ip: IpAddr) -> io::Result<String> {
+        tokio::task::spawn_blocking(move || dns_lookup::lookup_addr(&ip))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedPtr {
+    hostname: Option<String>,
+    expires: Instant,
+}
+
+/// PTR answers shared across check cycles
+#[derive(Debug, Clone, Default)]
+pub struct PtrCache(Arc<Mutex<HashMap<IpAddr, CachedPtr>>>);
+
+impl PtrCache {
+    /// Cached answer for `ip`, `None` if absent or expired
+    fn get(&self, ip: IpAddr, now: Instant) -> Option<Option<String>> {
+        self.0
+            .lock()
+            .get(&ip)
+            .filter(|cached| cached.expires > now)
+            .map(|cached| cached.hostname.clone())
+    }
+
+    fn insert(&self, ip: IpAddr, hostname: Option<String>, now: Instant) {
+        let ttl = if hostname.is_some() {
+            POSITIVE_TTL
+        } else {
+            NEGATIVE_TTL
+        };
+
+        let mut entries = self.0.lock();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, cached| cached.expires > now);
+        }
+        if entries.len() < MAX_ENTRIES {
+            entries.insert(
+                ip,
+                CachedPtr {
+                    hostname,
+                    expires: now + ttl,
+                },
+            );
+        }
+    }
+}
+
+/// Looks up PTR hostnames with a bounded lookup time and a shared cache
+#[derive(Clone)]
+pub(crate) struct ReverseResolver {
+    lookup: Arc<dyn ReverseLookup>,
+    timeout: Duration,
+    cache: PtrCache,
+}
+
+impl ReverseResolver {
+    /// Resolver using the system resolver, giving up after `timeout`
+    pub(crate) fn new(timeout: Duration, cache: PtrCache) -> Self {
+        Self::with_lookup(Arc::new(SystemReverseLookup), timeout, cache)
+    }
+
+    /// Resolver using a custom lookup backend
+    pub(crate) fn with_lookup(
+        lookup: Arc<dyn ReverseLookup>,
+        timeout: Duration,
+        cache: PtrCache,
+    ) -> Self {
+        Self {
+            lookup,
+            timeout,
+            cache,
+        }
+    }
+
+    /// PTR hostname of `ip`, `None` if there is none or the lookup failed
+    pub(crate) async fn hostname(&self, ip: IpAddr) -> Option<String> {
+        let now = Instant::now();
+        if let Some(hostname) = self.cache.get(ip, now) {
+            return hostname;
+        }
+
+        let hostname = match tokio::time::timeout(self.timeout, self.lookup.lookup(ip)).await {
+            // getnameinfo answers with the numeric address when there is no PTR record
+            Ok(Ok(name)) if !name.is_empty() && name.parse::<IpAddr>().is_err() => {
+                Some(name.trim_end_matches('.').to_string())
+            }
+            Ok(Ok(_)) => {
+                debug!("No PTR record for {}", ip);
+                None
+            }
+            Ok(Err(e)) => {
+                debug!("Reverse lookup of {} failed: {}", ip, e);
+                None
+            }
+            Err(_) => {
+                debug!(
+                    "Reverse lookup of {} timed out after {:?}",
+                    ip, self.timeout
+                );
+                None
+            }
+        };
+
+        self.cache.insert(ip, hostname.clone(), now);
+        hostname
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Lookup that counts its calls and answers with `answer` after `delay`
+    pub(crate) struct CountingLookup {
+        pub(crate) calls: AtomicUsize,
+        pub(crate) delay: Duration,
+        pub(crate) answer: Option<String>,
+    }
+
+    impl CountingLookup {
+        pub(crate) fn new(answer: Option<&str>) -> Arc<Self> {
+            Arc::new(Self {
+                calls: AtomicUsize::new(0),
+                delay: Duration::ZERO,
+                answer: answer.map(str::to_string),
+            })
+        }
+
+        pub(crate) fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl ReverseLookup for CountingLookup {
+        async fn lookup(&self, _ip: IpAddr) -> io::Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.answer
+                .clone()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no PTR record"))
+        }
+    }
+
+    const LOOPBACK: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+
+    fn resolver(lookup: Arc<CountingLookup>) -> ReverseResolver {
+        ReverseResolver::with_lookup(lookup, Duration::from_millis(200), PtrCache::default())
+    }
+
+    #[tokio::test]
+    async fn test_hostname_is_cached() {
+        let lookup = CountingLookup::new(Some("localhost."));
+        let resolver = resolver(Arc::clone(&lookup));
+
+        assert_eq!(
+            resolver.hostname(LOOPBACK).await.as_deref(),
+            Some("localhost")
+        );
+        assert_eq!(
+            resolver.hostname(LOOPBACK).await.as_deref(),
+            Some("localhost")
+        );
+        assert_eq!(lookup.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_lookup_is_omitted_and_cached() {
+        let lookup = CountingLookup::new(None);
+        let resolver = resolver(Arc::clone(&lookup));
+
+        assert_eq!(resolver.hostname(LOOPBACK).await, None);
+        assert_eq!(resolver.hostname(LOOPBACK).await, None);
+        assert_eq!(lookup.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_numeric_answer_means_no_ptr_record() {
+        let resolver = resolver(CountingLookup::new(Some("127.0.0.1")));
+        assert_eq!(resolver.hostname(LOOPBACK).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_slow_lookup_times_out() {
+        let lookup = Arc::new(CountingLookup {
+            calls: AtomicUsize::new(0),
+            delay: Duration::from_secs(30),
+            answer: Some("slow.example".to_string()),
+        });
+        let resolver = resolver(lookup);
+
+        let start = Instant::now();
+        assert_eq!(resolver.hostname(LOOPBACK).await, None);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_shared_between_resolvers() {
+        let cache = PtrCache::default();
+        let lookup = CountingLookup::new(Some("localhost"));
+        let first = ReverseResolver::with_lookup(
+            Arc::clone(&lookup) as Arc<dyn ReverseLookup>,
+            Duration::from_secs(1),
+            cache.clone(),
+        );
+        let second = ReverseResolver::with_lookup(
+            Arc::clone(&lookup) as Arc<dyn ReverseLookup>,
+            Duration::from_secs(1),
+            cache,
+        );
+
+        first.hostname(LOOPBACK).await;
+        assert_eq!(
+            second.hostname(LOOPBACK).await.as_deref(),
+            Some("localhost")
+        );
+        assert_eq!(lookup.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_system_lookup_of_loopback_completes() {
+        let resolver = ReverseResolver::new(Duration::from_secs(2), PtrCache::default());
+
+        let hostname = tokio::time::timeout(Duration::from_secs(5), resolver.hostname(LOOPBACK))
+            .await
+            .expect("lookup must be bounded by its timeout");
+        assert!(hostname.is_none_or(|name| !name.is_empty()));
+    }
+
+    #[test]
+    fn test_expired_entries_are_looked_up_again() {
+        let cache = PtrCache::default();
+        let now = Instant::now();
+
+        cache.insert(LOOPBACK, None, now);
+        assert_eq!(cache.get(LOOPBACK, now), Some(None));
+        assert_eq!(cache.get(LOOPBACK, now + NEGATIVE_TTL), None);
+    }
+}
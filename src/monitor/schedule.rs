@@ -0,0 +1,175 @@
+//! Per-endpoint check schedule
+//!
+//! Every endpoint is checked on its own interval: its `interval_secs`, or
+//! `monitoring.interval_secs` without one. The monitoring loop wakes when the
+//! next endpoint falls due and checks all endpoints due at that moment in one
+//! cycle. Endpoints sharing an interval stay in step and keep being checked
+//! together.
+//!
+//! New endpoints and endpoints whose interval changed are due immediately.
+//! Checks missed while an earlier cycle was still running are not made up:
+//! like a `MissedTickBehavior::Skip` timer, an endpoint falls due at most
+//! once when it is late, and its schedule then continues on the original
+//! cadence.
+
+use crate::core::Endpoint;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::debug;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    interval: Duration,
+    next: Instant,
+}
+
+/// Next check time of every configured endpoint
+#[derive(Debug, Default)]
+pub(crate) struct Schedule {
+    entries: HashMap<Uuid, Entry>,
+}
+
+impl Schedule {
+    /// Endpoints of `endpoints` due at `now`, in configured order
+    ///
+    /// Their next check is scheduled one interval later. Endpoints no longer
+    /// configured are forgotten.
+    pub(crate) fn take_due(
+        &mut self,
+        endpoints: &[Endpoint],
+        default_interval: Duration,
+        now: Instant,
+    ) -> Vec<Endpoint> {
+        self.entries
+            .retain(|id, _| endpoints.iter().any(|endpoint| endpoint.id == *id));
+
+        let mut due = Vec::new();
+        for endpoint in endpoints {
+            let interval = endpoint.check_interval(default_interval);
+            let entry = self.entries.entry(endpoint.id).or_insert(Entry {
+                interval,
+                next: now,
+            });
+            if entry.interval != interval {
+                debug!(
+                    "Check interval of {} changed from {:?} to {:?}",
+                    endpoint.address, entry.interval, interval
+                );
+                *entry = Entry {
+                    interval,
+                    next: now,
+                };
+            }
+
+            if entry.next <= now {
+                due.push(endpoint.clone());
+                entry.next = next_after(entry.next, interval, now);
+            }
+        }
+        due
+    }
+
+    /// Time the next endpoint falls due, `None` without endpoints
+    pub(crate) fn next_due(&self) -> Option<Instant> {
+        self.entries.values().map(|entry| entry.next).min()
+    }
+}
+
+/// First time after `now` on the cadence of `interval` starting at `due`
+fn next_after(due: Instant, interval: Duration, now: Instant) -> Instant {
+    let interval = interval.max(Duration::from_millis(1));
+    let missed = now.duration_since(due).as_nanos() / interval.as_nanos();
+    due + interval * (missed as u32 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT: Duration = Duration::from_secs(60);
+
+    fn ids(endpoints: &[Endpoint]) -> Vec<Uuid> {
+        endpoints.iter().map(|endpoint| endpoint.id).collect()
+    }
+
+    #[test]
+    fn test_new_endpoints_are_due_immediately() {
+        let endpoints = vec![Endpoint::new("10.0.0.1"), Endpoint::new("10.0.0.2")];
+        let mut schedule = Schedule::default();
+        let start = Instant::now();
+
+        let due = schedule.take_due(&endpoints, DEFAULT, start);
+        assert_eq!(ids(&due), ids(&endpoints));
+        assert_eq!(schedule.next_due(), Some(start + DEFAULT));
+        assert!(schedule.take_due(&endpoints, DEFAULT, start).is_empty());
+    }
+
+    #[test]
+    fn test_short_interval_falls_due_more_often() {
+        let fast = Endpoint::new("10.0.0.1").with_interval_secs(10);
+        let slow = Endpoint::new("10.0.0.2");
+        let endpoints = vec![fast.clone(), slow.clone()];
+        let mut schedule = Schedule::default();
+        let start = Instant::now();
+
+        let mut checks = HashMap::<Uuid, usize>::new();
+        for second in 0..120 {
+            let now = start + Duration::from_secs(second);
+            for endpoint in schedule.take_due(&endpoints, DEFAULT, now) {
+                *checks.entry(endpoint.id).or_default() += 1;
+            }
+        }
+
+        assert_eq!(checks[&fast.id], 12);
+        assert_eq!(checks[&slow.id], 2);
+    }
+
+    #[test]
+    fn test_late_endpoint_is_due_once_and_keeps_its_cadence() {
+        let endpoints = vec![Endpoint::new("10.0.0.1").with_interval_secs(10)];
+        let mut schedule = Schedule::default();
+        let start = Instant::now();
+        schedule.take_due(&endpoints, DEFAULT, start);
+
+        // A cycle ran for 35 seconds; the checks at 10, 20 and 30 are missed
+        let late = start + Duration::from_secs(35);
+        assert_eq!(schedule.take_due(&endpoints, DEFAULT, late).len(), 1);
+        assert_eq!(schedule.next_due(), Some(start + Duration::from_secs(40)));
+        assert!(schedule.take_due(&endpoints, DEFAULT, late).is_empty());
+    }
+
+    #[test]
+    fn test_changed_interval_is_due_immediately() {
+        let endpoint = Endpoint::new("10.0.0.1");
+        let mut schedule = Schedule::default();
+        let start = Instant::now();
+        schedule.take_due(std::slice::from_ref(&endpoint), DEFAULT, start);
+
+        let later = start + Duration::from_secs(5);
+        let shorter = Duration::from_secs(30);
+        let due = schedule.take_due(std::slice::from_ref(&endpoint), shorter, later);
+        assert_eq!(
+            due.len(),
+            1,
+            "a changed default interval restarts the schedule"
+        );
+        assert_eq!(schedule.next_due(), Some(later + shorter));
+
+        let overridden = endpoint.with_interval_secs(10);
+        let due = schedule.take_due(std::slice::from_ref(&overridden), shorter, later);
+        assert_eq!(due.len(), 1, "a new override restarts the schedule");
+        assert_eq!(schedule.next_due(), Some(later + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_removed_endpoints_are_forgotten() {
+        let mut schedule = Schedule::default();
+        let start = Instant::now();
+        schedule.take_due(&[Endpoint::new("10.0.0.1")], DEFAULT, start);
+
+        assert!(schedule.take_due(&[], DEFAULT, start).is_empty());
+        assert_eq!(schedule.next_due(), None);
+    }
+}
@@ -1,31 +1,62 @@
 //! Monitoring task coordination and execution
 
-use crate::agent_config::Config;
+use crate::agent_config::{
+    AlertingConfig, AuditConfig, Config, LoggingConfig, MonitoringConfig, MqttSinkConfig,
+    RemoteWriteSinkConfig,
+};
+use crate::alerting::Alerter;
+use crate::audit::AuditLog;
 use crate::cache::ResultCache;
-use crate::core::AgentStatus;
+use crate::core::{
+    wait_for_shutdown, AgentStatus, CheckKind, CheckType, Endpoint, Readiness, ReadinessStep,
+    ShutdownReceiver, SkipReason,
+};
 use crate::error::Result;
-use crate::monitor::PingChecker;
-use parking_lot::RwLock;
+use crate::metrics::CheckMetrics;
+use crate::monitor::circuit::{skip_reason, CircuitBreaker};
+use crate::monitor::order::DispatchOrder;
+use crate::monitor::schedule::Schedule;
+use crate::monitor::warmup::Warmup;
+use crate::monitor::{Checkers, DnsCache, PtrCache};
+use crate::plugin::PluginRegistry;
+use crate::sinks::{EncodedResult, MqttSink, RemoteWriteSink};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashSet;
+use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::time::interval;
-use tracing::{debug, error, info};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Semaphore};
+use tokio::time::sleep_until;
+use tracing::{debug, error, info, warn};
 
 use crate::core::MonitoringResult;
 use tokio::sync::mpsc;
+use uuid::Uuid;
 
 /// Channel for sending monitoring results
 type ResultSender = mpsc::UnboundedSender<MonitoringResult>;
 
+/// Traceroutes after failed pings that may run at once
+const MAX_TRACEROUTES: usize = 4;
+
 /// Run the monitoring loop
 ///
 /// Accepts a shared `Arc<RwLock<Config>>` so that config hot-reloads applied by
-/// `Agent::reload_config()` are picked up on every monitoring tick.
+/// `Agent::reload_config()` are picked up on every monitoring tick. Endpoints
+/// naming a plugin are checked by the plugins of `plugins`. Completes the
+/// check cycle step of `readiness`. Every result is counted in `metrics`.
+/// Each change of `config_reloads` wakes the check loop so endpoints added by
+/// a reload are checked without waiting for the current interval to end.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_monitoring(
     agent_config: Arc<RwLock<Config>>,
     agent_status: Arc<RwLock<AgentStatus>>,
     result_cache: Arc<ResultCache>,
-    agent_shutdown_rx: &mut broadcast::Receiver<()>,
+    plugins: Arc<PluginRegistry>,
+    readiness: Readiness,
+    metrics: CheckMetrics,
+    config_reloads: watch::Receiver<u64>,
+    agent_shutdown_rx: &mut ShutdownReceiver,
 ) -> Result<()> {
     info!("Starting monitoring tasks");
 
@@ -36,21 +67,37 @@ pub async fn run_monitoring(
         let config = Arc::clone(&agent_config);
         let result_tx = result_tx.clone();
 
-        let mut agent_shutdown_rx = agent_shutdown_rx.resubscribe();
+        let mut agent_shutdown_rx = agent_shutdown_rx.clone();
+
+        let agent_status = Arc::clone(&agent_status);
 
-        tokio::spawn(async move { run_check_loop(config, result_tx, &mut agent_shutdown_rx).await })
+        tokio::spawn(async move {
+            run_check_loop(
+                config,
+                agent_status,
+                plugins,
+                result_tx,
+                readiness,
+                config_reloads,
+                &mut agent_shutdown_rx,
+            )
+            .await
+        })
     };
 
     // Process results
     let result_handle = {
+        let config = Arc::clone(&agent_config);
         let agent_status = Arc::clone(&agent_status);
         let result_cache = Arc::clone(&result_cache);
-        let mut agent_shutdown_rx = agent_shutdown_rx.resubscribe();
+        let mut agent_shutdown_rx = agent_shutdown_rx.clone();
 
         tokio::spawn(async move {
             result_collect_loop(
+                config,
                 agent_status,
                 result_cache,
+                metrics,
                 result_rx,
                 &mut agent_shutdown_rx,
             )
@@ -59,12 +106,10 @@ pub async fn run_monitoring(
     };
 
     // Wait for shutdown
-    // Due to result_handle spawned in another task and resubscribed to shutdown_rx we need to wait here
+    // Due to result_handle spawned in another task with its own shutdown_rx clone we need to wait here
     // for the signal as well
-    match agent_shutdown_rx.recv().await {
-        Ok(_) => tracing::info!("Monitoring task shutting down"),
-        Err(_) => tracing::warn!("Monitoring shutdown channel already closed"),
-    }
+    wait_for_shutdown(agent_shutdown_rx).await;
+    info!("Monitoring task shutting down");
 
     // Wait for tasks to complete
     let _ = tokio::join!(monitor_handle, result_handle);
@@ -74,23 +119,126 @@ pub async fn run_monitoring(
 }
 
 async fn result_collect_loop(
+    agent_config: Arc<RwLock<Config>>,
     agent_status: Arc<parking_lot::lock_api::RwLock<parking_lot::RawRwLock, AgentStatus>>,
     result_cache: Arc<ResultCache>,
+    metrics: CheckMetrics,
     mut result_rx: mpsc::UnboundedReceiver<MonitoringResult>,
-    agent_shutdown_rx: &mut broadcast::Receiver<()>,
+    agent_shutdown_rx: &mut ShutdownReceiver,
 ) {
+    let mut audit_log: Option<AuditLog> = None;
+    let mut mqtt_sink: Option<MqttSink> = None;
+    let mut remote_write_sink: Option<RemoteWriteSink> = None;
+    let mut alerter: Option<Alerter> = None;
+
     loop {
         tokio::select! {
-            Some(result) = result_rx.recv() => {
+            Some(mut result) = result_rx.recv() => {
+                {
+                    let config = agent_config.read();
+                    if let Some(endpoint) = config.endpoints.iter().find(|e| e.id == result.endpoint_id) {
+                        result.status = Some(result.metric_status(endpoint, &config.thresholds));
+                    }
+                }
+
+                // Serialized at most once per format, shared by every sink.
+                let encoded = EncodedResult::new(&result);
+
+                // Pick up [audit] changes from hot reload before writing.
+                let audit_config = agent_config.read().audit.clone();
+                sync_audit_log(&mut audit_log, &audit_config).await;
+                if let Some(log) = &audit_log {
+                    if let Err(e) = log.write(&encoded) {
+                        warn!("Failed to write audit record: {}", e);
+                    }
+                }
+
+                {
+                    let config = agent_config.read();
+                    sync_mqtt_sink(&mut mqtt_sink, config.sinks.mqtt.as_ref(), config.agent_id);
+                }
+                if let Some(sink) = &mqtt_sink {
+                    if let Err(e) = sink.publish(&encoded) {
+                        warn!("Failed to publish result to MQTT: {}", e);
+                    }
+                }
+
+                {
+                    let config = agent_config.read();
+                    sync_remote_write_sink(
+                        &mut remote_write_sink,
+                        config.sinks.remote_write.as_ref(),
+                        config.agent_id,
+                    );
+                    if let Some(sink) = &remote_write_sink {
+                        let address = config
+                            .endpoints
+                            .iter()
+                            .find(|e| e.id == result.endpoint_id)
+                            .map(|e| e.address.as_str());
+                        if let Err(e) = sink.publish(&result, address) {
+                            warn!("Failed to publish result to remote write: {}", e);
+                        }
+                    }
+                }
+
+                {
+                    let config = agent_config.read();
+                    // Results of endpoints removed by a reload are not counted
+                    if let Some(endpoint) = config.endpoints.iter().find(|e| e.id == result.endpoint_id) {
+                        metrics.observe(&result, &endpoint.address);
+                    }
+                    metrics.retain_endpoints(&config.endpoints);
+                }
+
+                {
+                    let config = agent_config.read();
+                    sync_alerter(&mut alerter, &config.alerting);
+                    if let Some(alerter) = &mut alerter {
+                        let address = config
+                            .endpoints
+                            .iter()
+                            .find(|e| e.id == result.endpoint_id)
+                            .map(|e| e.address.as_str());
+                        if let Some(event) = alerter.observe(&result, address) {
+                            let delivery = alerter.deliver(event);
+                            tokio::spawn(async move {
+                                if let Err(e) = delivery.await {
+                                    warn!("Failed to send alert webhook: {}", e);
+                                }
+                            });
+                        }
+                    }
+                }
+
                 // Update statistics
                 {
+                    let config = agent_config.read();
                     let mut s = agent_status.write();
                     s.checks_performed += 1;
                     if result.is_successful() {
                         s.checks_successful += 1;
+                    } else if result.is_warmup() {
+                        s.checks_failed_warmup += 1;
                     } else {
                         s.checks_failed += 1;
                     }
+                    if let CheckType::PingCheck(ping) = &result.check_type {
+                        s.record_ping(&ping.result);
+                    }
+                    // A traceroute follows up on a failed ping and would
+                    // overwrite its outcome
+                    if !matches!(result.check_type, CheckType::TracerouteCheck(_)) {
+                        if let Some(endpoint) = config.endpoints.iter().find(|e| e.id == result.endpoint_id) {
+                            s.record_endpoint_result(&result, &endpoint.address);
+                        }
+                    }
+                    s.retain_endpoints(&config.endpoints);
+                }
+                // Local stats above count every result; only reporting is filtered.
+                if !agent_config.read().should_report(&result) {
+                    debug!("Result for endpoint {} filtered out of reporting", result.endpoint_id);
+                    continue;
                 }
                 result_cache.push(result).await;
                 // Reflect current cache depth in agent status (after push to avoid off-by-one)
@@ -101,101 +249,1666 @@ async fn result_collect_loop(
                     s.cache_stats.capacity = stats.capacity as i64;
                 }
             }
-            _ = agent_shutdown_rx.recv() => {
+            _ = wait_for_shutdown(agent_shutdown_rx) => {
                 info!("Monitoring coordinator shutting down");
+                cache_queued_results(&agent_config, &result_cache, &mut result_rx).await;
+                if let Some(log) = audit_log.take() {
+                    log.close().await;
+                }
                 break;
             }
         }
     }
 }
 
+/// Cache the results still queued at shutdown, so they are sent or persisted
+/// with the cache instead of being dropped with the channel
+///
+/// Only reporting applies to them: sinks, alerts and statistics have already
+/// stopped.
+async fn cache_queued_results(
+    agent_config: &RwLock<Config>,
+    result_cache: &ResultCache,
+    result_rx: &mut mpsc::UnboundedReceiver<MonitoringResult>,
+) {
+    let mut queued = 0;
+    while let Ok(result) = result_rx.try_recv() {
+        if agent_config.read().should_report(&result) {
+            result_cache.push(result).await;
+            queued += 1;
+        }
+    }
+    if queued > 0 {
+        info!("Cached {} results queued at shutdown", queued);
+    }
+}
+
+/// Open, replace or close the audit log so that it matches `config`
+///
+/// A replaced log finishes its queued lines first, so the old and new writer
+/// never rotate the same file at once.
+async fn sync_audit_log(audit_log: &mut Option<AuditLog>, config: &AuditConfig) {
+    let up_to_date = match audit_log {
+        Some(log) => log.config() == config,
+        None => !config.enabled,
+    };
+
+    if !up_to_date {
+        info!(
+            "Audit log {} ({})",
+            if config.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            },
+            config.path
+        );
+        if let Some(log) = audit_log.take() {
+            log.close().await;
+        }
+        *audit_log = config.enabled.then(|| AuditLog::new(config.clone()));
+    }
+}
+
+/// Create, replace or drop the alerter so that it matches `config`
+fn sync_alerter(alerter: &mut Option<Alerter>, config: &AlertingConfig) {
+    let up_to_date = match alerter {
+        Some(alerter) => alerter.config() == config,
+        None => !config.enabled,
+    };
+    if up_to_date {
+        return;
+    }
+
+    *alerter = config
+        .enabled
+        .then(|| {
+            info!("Alerting enabled ({})", config.webhook_url);
+            Alerter::new(config.clone())
+                .inspect_err(|e| error!("Failed to create alerter: {}", e))
+                .ok()
+        })
+        .flatten();
+    if !config.enabled {
+        info!("Alerting disabled");
+    }
+}
+
+/// Create, replace or drop the MQTT sink so that it matches `config`
+fn sync_mqtt_sink(
+    sink: &mut Option<MqttSink>,
+    config: Option<&MqttSinkConfig>,
+    agent_id: uuid::Uuid,
+) {
+    let up_to_date = match (sink.as_ref(), config) {
+        (Some(sink), Some(config)) => sink.config() == config && sink.agent_id() == agent_id,
+        (None, None) => true,
+        _ => false,
+    };
+    if up_to_date {
+        return;
+    }
+
+    *sink = config.and_then(|config| {
+        info!("MQTT sink enabled ({})", config.broker_url);
+        MqttSink::new(config.clone(), agent_id)
+            .inspect_err(|e| error!("Failed to create MQTT sink: {}", e))
+            .ok()
+    });
+    if config.is_none() {
+        info!("MQTT sink disabled");
+    }
+}
+
+/// Create, replace or drop the remote-write sink so that it matches `config`
+fn sync_remote_write_sink(
+    sink: &mut Option<RemoteWriteSink>,
+    config: Option<&RemoteWriteSinkConfig>,
+    agent_id: uuid::Uuid,
+) {
+    let up_to_date = match (sink.as_ref(), config) {
+        (Some(sink), Some(config)) => sink.config() == config && sink.agent_id() == agent_id,
+        (None, None) => true,
+        _ => false,
+    };
+    if up_to_date {
+        return;
+    }
+
+    *sink = config.and_then(|config| {
+        info!("Remote-write sink enabled ({})", config.url);
+        RemoteWriteSink::new(config.clone(), agent_id)
+            .inspect_err(|e| error!("Failed to create remote-write sink: {}", e))
+            .ok()
+    });
+    if config.is_none() {
+        info!("Remote-write sink disabled");
+    }
+}
+
 /// Main check loop that runs periodically
 ///
-/// Reads a fresh config snapshot on every tick so that hot-reloaded values
-/// (endpoints, intervals, ping parameters) take effect without a restart.
+/// Every endpoint is checked on its own interval (see [`Schedule`]). The loop
+/// wakes when the next endpoint falls due, at least once per monitoring
+/// interval, and whenever `config_reloads` signals an applied reload so that
+/// newly configured endpoints are picked up at once. It reads a fresh config
+/// snapshot on every tick so that hot-reloaded values (endpoints, intervals,
+/// ping parameters) take effect without a restart. The checkers are kept
+/// across ticks and rebuilt only when the `monitoring` or `logging` settings
+/// change, so HTTP connection pools are reused.
+/// Only the endpoints due at a tick are checked. Endpoints that are disabled
+/// or whose circuit is open are skipped and counted in `agent_status` per
+/// skip reason, as are checks cancelled at the cycle deadline.
+///
+/// Ping checks without any reply are followed by a traceroute when
+/// `monitoring.traceroute_on_failure` is set. The ping result is sent and
+/// recorded first; the traceroute then runs in the background (see
+/// [`Tracer`]), outside the cycle's concurrency slots and deadline, and is
+/// sent as an additional result.
+///
+/// Results of endpoints in their warmup window (see [`Warmup`]) are marked
+/// `warmup`, and their failures are not recorded by the circuit breaker.
+///
+/// The first tick completes the check cycle step of `readiness`, unless
+/// `monitoring.ready_after_successful_check` waits for a cycle in which a
+/// check succeeded.
 async fn run_check_loop(
     agent_config: Arc<RwLock<Config>>,
+    agent_status: Arc<RwLock<AgentStatus>>,
+    plugins: Arc<PluginRegistry>,
     result_tx: ResultSender,
-    agent_shutdown_rx: &mut broadcast::Receiver<()>,
+    readiness: Readiness,
+    mut config_reloads: watch::Receiver<u64>,
+    agent_shutdown_rx: &mut ShutdownReceiver,
 ) {
-    // Bootstrap the interval from the current config.
     let mut current_interval_duration = agent_config.read().monitoring.interval();
-    let mut iv = interval(current_interval_duration);
-    iv.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut schedule = Schedule::default();
+    let mut wake = tokio::time::Instant::now();
+    let mut breaker = CircuitBreaker::new(agent_config.read().monitoring.circuit_breaker.clone());
+    let mut warmup = {
+        let config = agent_config.read();
+        Warmup::new(config.monitoring.warmup(), config.version)
+    };
+    let mut order = {
+        let monitoring = &agent_config.read().monitoring;
+        DispatchOrder::new(monitoring.shuffle_endpoints, monitoring.shuffle_seed)
+    };
+    let dns_cache = DnsCache::default();
+    let ptr_cache = PtrCache::default();
+    let mut checkers: Option<(MonitoringConfig, LoggingConfig, Arc<Checkers>)> = None;
+    let tracer = Tracer::new(MAX_TRACEROUTES, agent_shutdown_rx.clone());
 
     loop {
         tokio::select! {
-            _ = iv.tick() => {
+            _ = sleep_until(wake) => {
                 // Take a consistent snapshot for this tick.
                 let config = agent_config.read().clone();
 
-                // Detect interval changes and recreate the timer.
+                // Endpoints added or changed by a reload start warming up
+                // now, whether or not they are due
+                warmup.update_config(config.monitoring.warmup(), config.version);
+                warmup.update_endpoints(&config.endpoints, Instant::now());
+
+                // Endpoints on the default interval are rescheduled by the
+                // schedule when it changes.
                 let new_interval = config.monitoring.interval();
                 if new_interval != current_interval_duration {
                     info!(
-                        "Monitoring interval changed from {:?} to {:?}, recreating timer",
+                        "Monitoring interval changed from {:?} to {:?}, rescheduling endpoints",
                         current_interval_duration, new_interval
                     );
                     current_interval_duration = new_interval;
-                    iv = interval(current_interval_duration);
-                    iv.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-                }
-
-                // Recreate PingChecker from current config snapshot so that
-                // timeout and ping_count changes take effect immediately.
-                let ping_checker = match PingChecker::new(
-                    config.monitoring.timeout(),
-                    config.monitoring.ping_count,
-                ) {
-                    Ok(checker) => Arc::new(checker),
-                    Err(e) => {
-                        error!("Failed to create ping checker: {}", e);
-                        continue;
+                }
+
+                let tick = tokio::time::Instant::now();
+                let due_endpoints = schedule.take_due(&config.endpoints, new_interval, tick);
+                wake = schedule
+                    .next_due()
+                    .map_or(tick + new_interval, |next| next.min(tick + new_interval));
+
+                if !config.monitoring.ready_after_successful_check {
+                    readiness.complete(
+                        ReadinessStep::CheckCycle,
+                        format!("first check cycle scheduled ({} endpoints)", config.endpoints.len()),
+                    );
+                }
+
+                if due_endpoints.is_empty() {
+                    continue;
+                }
+
+                // Rebuild the checkers when the settings they are built from
+                // change, so that timeout, ping_count and dscp changes take
+                // effect at once while HTTP connection pools survive ticks.
+                let checkers = match &checkers {
+                    Some((monitoring, logging, checkers))
+                        if *monitoring == config.monitoring && *logging == config.logging =>
+                    {
+                        Arc::clone(checkers)
                     }
+                    _ => match Checkers::from_config(&config, &dns_cache, &ptr_cache) {
+                        Ok(built) => {
+                            let built = Arc::new(built.with_plugins(Arc::clone(&plugins)));
+                            checkers = Some((
+                                config.monitoring.clone(),
+                                config.logging.clone(),
+                                Arc::clone(&built),
+                            ));
+                            built
+                        }
+                        Err(e) => {
+                            error!("Failed to create checkers: {}", e);
+                            continue;
+                        }
+                    },
                 };
 
-                // Filter only enabled endpoints
-                let enabled_endpoints: Vec<_> = config.endpoints.iter()
-                    .filter(|e| e.enabled)
-                    .cloned()
-                    .collect();
+                breaker.update_config(config.monitoring.circuit_breaker.as_ref());
+                breaker.retain(&config.endpoints);
+
+                // Check only endpoints that are not skipped
+                let now = Instant::now();
+                let mut enabled_endpoints = Vec::new();
+                {
+                    let mut status = agent_status.write();
+                    for endpoint in &due_endpoints {
+                        match skip_reason(endpoint, &breaker, now) {
+                            Some(reason) => {
+                                debug!("Skipping endpoint {}: {:?}", endpoint.address, reason);
+                                status.record_skip(reason);
+                            }
+                            None => enabled_endpoints.push(endpoint.clone()),
+                        }
+                    }
+                }
 
                 if enabled_endpoints.is_empty() {
                     continue;
                 }
 
-                debug!("Running checks for {} enabled endpoints", enabled_endpoints.len());
+                order.update_config(config.monitoring.shuffle_endpoints, config.monitoring.shuffle_seed);
+                order.arrange(&mut enabled_endpoints);
 
-                // Run checks concurrently with limit
-                let semaphore = Arc::new(tokio::sync::Semaphore::new(config.monitoring.max_concurrent));
-                let mut tasks = Vec::new();
+                debug!("Running checks for {} enabled endpoints", enabled_endpoints.len());
 
-                for endpoint in enabled_endpoints {
-                    let permit = semaphore.clone().acquire_owned().await.unwrap();
-                    let ping_checker = Arc::clone(&ping_checker);
-                    let agent_id = config.agent_id;
-                    let result_tx = result_tx.clone();
+                let warming_up: HashSet<Uuid> = enabled_endpoints
+                    .iter()
+                    .map(|endpoint| endpoint.id)
+                    .filter(|id| warmup.is_warming_up(*id, now))
+                    .collect();
 
-                    let task = tokio::spawn(async move {
-                        let result = ping_checker.check(agent_id, &endpoint).await;
-                        if let Err(e) = result_tx.send(result) {
-                            error!("Failed to send result: {}", e);
+                let agent_id = config.agent_id;
+                let traceroute_timeout = config.monitoring.traceroute_timeout();
+                let outcome = run_cycle(
+                    enabled_endpoints,
+                    config.monitoring.max_concurrent,
+                    |kind| config.monitoring.max_concurrent_for(kind),
+                    config.monitoring.cycle_deadline(),
+                    |endpoint| {
+                        let checkers = Arc::clone(&checkers);
+                        let result_tx = result_tx.clone();
+                        let tracer = tracer.clone();
+                        let warmup = warming_up.contains(&endpoint.id).then_some(true);
+                        async move {
+                            let mut result = checkers.check(agent_id, &endpoint).await;
+                            result.warmup = warmup;
+                            let success = result.is_successful();
+                            let trace = checkers.traces_after(&result);
+                            if let Err(e) = result_tx.send(result) {
+                                error!("Failed to send result: {}", e);
+                            }
+                            if trace {
+                                debug!("Ping to {} got no reply, tracing the path", endpoint.address);
+                                let endpoint_id = endpoint.id;
+                                let traceroute = async move {
+                                    let mut result = checkers.traceroute(agent_id, &endpoint).await?;
+                                    result.warmup = warmup;
+                                    Some(result)
+                                };
+                                tracer.spawn(endpoint_id, traceroute, traceroute_timeout, result_tx);
+                            }
+                            success
                         }
-                        drop(permit);
-                    });
+                    },
+                )
+                .await;
 
-                    tasks.push(task);
+                let succeeded = outcome.completed.iter().filter(|(_, success)| *success).count();
+                if succeeded > 0 {
+                    readiness.complete(
+                        ReadinessStep::CheckCycle,
+                        format!(
+                            "first successful check cycle ({} of {} checks succeeded)",
+                            succeeded,
+                            outcome.completed.len() + outcome.timed_out.len()
+                        ),
+                    );
                 }
 
-                // Wait for all checks to complete
-                for task in tasks {
-                    let _ = task.await;
+                for (endpoint_id, success) in outcome.completed {
+                    // Failures while warming up do not open the circuit;
+                    // successes still close it
+                    if !success && warming_up.contains(&endpoint_id) {
+                        continue;
+                    }
+                    breaker.record(endpoint_id, success, Instant::now());
                 }
+                if !outcome.timed_out.is_empty() {
+                    warn!(
+                        "Cycle deadline of {:?} reached, cancelled {} checks",
+                        config.monitoring.cycle_deadline().unwrap_or_default(),
+                        outcome.timed_out.len()
+                    );
+                    let mut status = agent_status.write();
+                    for _ in &outcome.timed_out {
+                        status.record_skip(SkipReason::DeadlineExceeded);
+                    }
+                }
+            }
+            Ok(()) = config_reloads.changed() => {
+                debug!("Config reloaded, scheduling newly added endpoints");
+                wake = tokio::time::Instant::now();
             }
-            _ = agent_shutdown_rx.recv() => {
+            _ = wait_for_shutdown(agent_shutdown_rx) => {
                 info!("Check_loop shutting down");
                 break;
             }
         }
     }
 }
+
+/// Runs the traceroutes that follow up on failed pings in the background
+///
+/// A traceroute can take minutes on a path of silent hops, so it must not
+/// hold a check slot or be cancelled by the cycle deadline. At most `slots`
+/// run at once, and an endpoint still being traced is not traced again;
+/// traces over the limit are skipped. Each is cut off after its timeout and
+/// when the agent shuts down.
+#[derive(Clone)]
+struct Tracer {
+    slots: Arc<Semaphore>,
+    tracing: Arc<Mutex<HashSet<Uuid>>>,
+    shutdown: ShutdownReceiver,
+}
+
+impl Tracer {
+    fn new(slots: usize, shutdown: ShutdownReceiver) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(slots)),
+            tracing: Arc::default(),
+            shutdown,
+        }
+    }
+
+    /// Run `traceroute` of `endpoint_id` in the background and send its
+    /// result, giving up after `timeout`
+    ///
+    /// Returns false if it was skipped because the endpoint is already being
+    /// traced or every slot is taken.
+    fn spawn<F>(
+        &self,
+        endpoint_id: Uuid,
+        traceroute: F,
+        timeout: Duration,
+        result_tx: ResultSender,
+    ) -> bool
+    where
+        F: Future<Output = Option<MonitoringResult>> + Send + 'static,
+    {
+        let Ok(permit) = Arc::clone(&self.slots).try_acquire_owned() else {
+            debug!(
+                "Too many traceroutes running, not tracing endpoint {}",
+                endpoint_id
+            );
+            return false;
+        };
+        if !self.tracing.lock().insert(endpoint_id) {
+            debug!("Endpoint {} is already being traced", endpoint_id);
+            return false;
+        }
+
+        let tracing = Arc::clone(&self.tracing);
+        let mut shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                traced = tokio::time::timeout(timeout, traceroute) => match traced {
+                    Ok(Some(result)) => {
+                        if let Err(e) = result_tx.send(result) {
+                            error!("Failed to send traceroute result: {}", e);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(_) => warn!(
+                        "Traceroute of endpoint {} did not finish within {:?}",
+                        endpoint_id, timeout
+                    ),
+                },
+                _ = wait_for_shutdown(&mut shutdown) => {}
+            }
+            tracing.lock().remove(&endpoint_id);
+            drop(permit);
+        });
+        true
+    }
+}
+
+/// Endpoints of one check cycle by outcome
+#[derive(Debug, Default)]
+struct CycleOutcome {
+    /// Endpoints whose check finished, with its success
+    completed: Vec<(Uuid, bool)>,
+    /// Endpoints whose check was cancelled or never started because the
+    /// cycle deadline was reached
+    timed_out: Vec<Uuid>,
+}
+
+/// Await `future`, giving up at `deadline` if there is one
+async fn until<F: Future>(deadline: Option<tokio::time::Instant>, future: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// Split `capacity` slots among kinds wanting `demands` slots each
+///
+/// Shares are max-min fair: a kind wanting less than an equal split gets
+/// what it wants, and the rest is split evenly among the others. Every kind
+/// gets at least one slot, even with more kinds than slots.
+fn fair_shares(demands: &[usize], capacity: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..demands.len()).collect();
+    order.sort_by_key(|&index| demands[index]);
+
+    let mut shares = vec![0; demands.len()];
+    let mut remaining = capacity;
+    for (position, &index) in order.iter().enumerate() {
+        let equal_split = remaining / (order.len() - position);
+        shares[index] = demands[index].min(equal_split).max(1);
+        remaining = remaining.saturating_sub(shares[index]);
+    }
+    shares
+}
+
+/// Slots each check kind of a cycle may hold at once
+///
+/// Starts from the [`fair_shares`] of all kinds. A kind that has dispatched
+/// all of its endpoints hands its share to the kinds still dispatching, so
+/// no slot stays reserved for a kind with nothing left to start.
+struct KindShares {
+    capacity: usize,
+    demands: Vec<usize>,
+    shares: Vec<usize>,
+    dispatching: Vec<bool>,
+    semaphores: Vec<Arc<Semaphore>>,
+}
+
+impl KindShares {
+    fn new(demands: Vec<usize>, capacity: usize) -> Self {
+        let shares = fair_shares(&demands, capacity);
+        Self {
+            capacity,
+            semaphores: shares
+                .iter()
+                .map(|&share| Arc::new(Semaphore::new(share)))
+                .collect(),
+            dispatching: vec![true; demands.len()],
+            demands,
+            shares,
+        }
+    }
+
+    /// Record that kind `index` has nothing left to dispatch and raise the
+    /// shares of the others
+    fn finished(&mut self, index: usize) {
+        self.dispatching[index] = false;
+        let active: Vec<usize> = (0..self.demands.len())
+            .filter(|&index| self.dispatching[index])
+            .collect();
+        let demands: Vec<usize> = active.iter().map(|&index| self.demands[index]).collect();
+
+        // Without a kind, no other kind's fair share shrinks
+        for (&index, share) in active.iter().zip(fair_shares(&demands, self.capacity)) {
+            if share > self.shares[index] {
+                self.semaphores[index].add_permits(share - self.shares[index]);
+                self.shares[index] = share;
+            }
+        }
+    }
+}
+
+/// Check `endpoints` with at most `max_concurrent` checks in flight
+///
+/// Each check kind is dispatched on its own, with at most `limit_for(kind)`
+/// checks of that kind in flight. Kinds due together share the
+/// `max_concurrent` slots fairly (see [`KindShares`]), so slow checks of one
+/// kind cannot hold back the others even without per-kind limits.
+///
+/// `check` runs the check of one endpoint and returns whether it succeeded.
+/// With a `deadline`, endpoints still waiting for a slot when it expires are
+/// not started and running checks are aborted; both are returned as timed out.
+async fn run_cycle<F, Fut, L>(
+    endpoints: Vec<Endpoint>,
+    max_concurrent: usize,
+    limit_for: L,
+    deadline: Option<Duration>,
+    check: F,
+) -> CycleOutcome
+where
+    F: Fn(Endpoint) -> Fut,
+    Fut: Future<Output = bool> + Send + 'static,
+    L: Fn(CheckKind) -> usize,
+{
+    let deadline = deadline.map(|deadline| tokio::time::Instant::now() + deadline);
+    let global = Arc::new(Semaphore::new(max_concurrent));
+    let mut outcome = CycleOutcome::default();
+
+    // Group endpoints by kind, keeping the dispatch order within each kind
+    let mut groups: Vec<(CheckKind, Vec<Endpoint>)> = Vec::new();
+    for endpoint in endpoints {
+        let kind = endpoint.check_kind();
+        match groups
+            .iter_mut()
+            .find(|(group_kind, _)| *group_kind == kind)
+        {
+            Some((_, group)) => group.push(endpoint),
+            None => groups.push((kind, vec![endpoint])),
+        }
+    }
+
+    let shares = parking_lot::Mutex::new(KindShares::new(
+        groups
+            .iter()
+            .map(|(kind, group)| limit_for(*kind).min(group.len()))
+            .collect(),
+        max_concurrent,
+    ));
+
+    let dispatchers = groups.into_iter().enumerate().map(|(index, (_, group))| {
+        let per_kind = Arc::clone(&shares.lock().semaphores[index]);
+        let global = Arc::clone(&global);
+        let check = &check;
+        let shares = &shares;
+        async move {
+            let mut tasks = Vec::new();
+            let mut not_started = Vec::new();
+
+            let mut group = group.into_iter();
+            for endpoint in group.by_ref() {
+                let permits = until(deadline, async {
+                    let kind_permit = per_kind.clone().acquire_owned().await;
+                    let global_permit = global.clone().acquire_owned().await;
+                    (kind_permit, global_permit)
+                })
+                .await;
+                let Some((kind_permit, global_permit)) = permits else {
+                    not_started.push(endpoint.id);
+                    break;
+                };
+                let permits = (
+                    kind_permit.expect("semaphore is never closed"),
+                    global_permit.expect("semaphore is never closed"),
+                );
+
+                let endpoint_id = endpoint.id;
+                let check = check(endpoint);
+                let task = tokio::spawn(async move {
+                    let success = check.await;
+                    drop(permits);
+                    success
+                });
+
+                tasks.push((endpoint_id, task));
+            }
+            not_started.extend(group.map(|endpoint| endpoint.id));
+            shares.lock().finished(index);
+
+            (tasks, not_started)
+        }
+    });
+
+    let mut tasks = Vec::new();
+    for (dispatched, not_started) in futures::future::join_all(dispatchers).await {
+        tasks.extend(dispatched);
+        outcome.timed_out.extend(not_started);
+    }
+
+    // Wait for all checks to complete
+    for (endpoint_id, mut task) in tasks {
+        match until(deadline, &mut task).await {
+            Some(Ok(success)) => outcome.completed.push((endpoint_id, success)),
+            Some(Err(e)) => error!("Check task of endpoint {} failed: {}", endpoint_id, e),
+            None => {
+                task.abort();
+                outcome.timed_out.push(endpoint_id);
+            }
+        }
+    }
+
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{EndpointCheckType, MetricStatus};
+
+    /// Endpoint whose check takes `delay`
+    fn endpoint(delay: Duration) -> (Endpoint, Duration) {
+        (Endpoint::new("10.0.0.1"), delay)
+    }
+
+    async fn run(
+        endpoints: &[(Endpoint, Duration)],
+        max_concurrent: usize,
+        deadline: Option<Duration>,
+    ) -> CycleOutcome {
+        let delays: std::collections::HashMap<Uuid, Duration> =
+            endpoints.iter().map(|(e, d)| (e.id, *d)).collect();
+        run_cycle(
+            endpoints.iter().map(|(e, _)| e.clone()).collect(),
+            max_concurrent,
+            |_| max_concurrent,
+            deadline,
+            |endpoint| {
+                let delay = delays[&endpoint.id];
+                async move {
+                    tokio::time::sleep(delay).await;
+                    true
+                }
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_cycle_ends_at_deadline_and_cancels_slow_checks() {
+        let fast = [
+            endpoint(Duration::ZERO),
+            endpoint(Duration::from_millis(10)),
+        ];
+        let slow = [
+            endpoint(Duration::from_secs(30)),
+            endpoint(Duration::from_secs(30)),
+            endpoint(Duration::from_secs(30)),
+        ];
+        let endpoints: Vec<_> = fast.iter().chain(&slow).cloned().collect();
+
+        let start = Instant::now();
+        let outcome = run(&endpoints, 10, Some(Duration::from_millis(200))).await;
+        let elapsed = start.elapsed();
+
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(
+            elapsed < Duration::from_secs(5),
+            "cycle overran: {:?}",
+            elapsed
+        );
+
+        let completed: Vec<Uuid> = outcome.completed.iter().map(|(id, _)| *id).collect();
+        assert_eq!(
+            completed,
+            fast.iter().map(|(e, _)| e.id).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            outcome.timed_out,
+            slow.iter().map(|(e, _)| e.id).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_endpoints_waiting_for_a_slot_at_deadline_are_not_started() {
+        let endpoints = [
+            endpoint(Duration::from_secs(30)),
+            endpoint(Duration::ZERO),
+            endpoint(Duration::ZERO),
+        ];
+
+        let start = Instant::now();
+        let outcome = run(&endpoints, 1, Some(Duration::from_millis(100))).await;
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(outcome.completed.is_empty());
+        let mut timed_out = outcome.timed_out;
+        let mut expected: Vec<Uuid> = endpoints.iter().map(|(e, _)| e.id).collect();
+        timed_out.sort();
+        expected.sort();
+        assert_eq!(timed_out, expected);
+    }
+
+    #[tokio::test]
+    async fn test_without_deadline_every_check_completes() {
+        let endpoints = [
+            endpoint(Duration::from_millis(50)),
+            endpoint(Duration::ZERO),
+        ];
+
+        let outcome = run(&endpoints, 1, None).await;
+
+        assert_eq!(outcome.completed.len(), 2);
+        assert!(outcome.timed_out.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_readiness_waits_for_a_successful_check_cycle() {
+        let mut server = mockito::Server::new_async().await;
+        let failing = server
+            .mock("GET", "/health")
+            .with_status(503)
+            .create_async()
+            .await;
+
+        let mut config = Config {
+            endpoints: vec![Endpoint::new(format!("{}/health", server.url()))],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 1;
+        config.monitoring.ready_after_successful_check = true;
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let readiness = Readiness::default();
+        readiness.complete(ReadinessStep::ConfigLoaded, "config version 1 loaded");
+        readiness.complete(ReadinessStep::ServerReachable, "no server configured");
+
+        let monitor_handle = {
+            let cache = Arc::clone(&cache);
+            let readiness = readiness.clone();
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::default(),
+                    readiness,
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        // A cycle of failed checks is scheduled but does not make the agent ready
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while cache.stats().await.len == 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("endpoint produced no results");
+        assert!(!readiness.is_ready());
+        assert_eq!(readiness.pending(), vec![ReadinessStep::CheckCycle]);
+
+        failing.remove_async().await;
+        server
+            .mock("GET", "/health")
+            .with_status(200)
+            .create_async()
+            .await;
+        tokio::time::timeout(Duration::from_secs(10), readiness.wait())
+            .await
+            .expect("agent never became ready");
+
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+    }
+
+    /// Results produced by the monitoring loop reach the server through the
+    /// result reporter, separately from status reports.
+    #[tokio::test]
+    async fn test_check_results_are_posted_to_the_server() {
+        use parking_lot::Mutex;
+
+        let tcp_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp_port = tcp_listener.local_addr().unwrap().port();
+        tokio::spawn(async move { while tcp_listener.accept().await.is_ok() {} });
+
+        let endpoint = Endpoint::new(format!("tcp://127.0.0.1:{}", tcp_port));
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            endpoints: vec![endpoint.clone()],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 1;
+        config.storage.cache_report_interval_secs = 1;
+
+        let bodies = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+        let mut server = mockito::Server::new_async().await;
+        let results_mock = server
+            .mock(
+                "POST",
+                format!("/agent/{}/results", config.agent_id).as_str(),
+            )
+            .match_request({
+                let bodies = Arc::clone(&bodies);
+                move |req| {
+                    bodies
+                        .lock()
+                        .push(serde_json::from_slice(req.body().unwrap()).unwrap());
+                    true
+                }
+            })
+            .with_status(202)
+            // The first accepted batch counts as a reconnect and flushes again
+            .expect_at_least(1)
+            .with_body(
+                r#"{"submission_id":"00000000-0000-0000-0000-000000000001","accepted":1,"received_at":"2026-01-01T00:00:00Z"}"#,
+            )
+            .create_async()
+            .await;
+        config.server.url = server.url();
+        config.server.api_key = Some("test-api-key".to_string());
+
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let config = Arc::clone(&config);
+            let cache = Arc::clone(&cache);
+            let status = Arc::clone(&status);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+        let reporter_handle = tokio::spawn(crate::results::run_result_reporter(
+            Arc::clone(&config),
+            Arc::clone(&cache),
+            status,
+            crate::reporter::ServerClient::default(),
+            crate::core::ReportingSwitch::default(),
+            shutdown_rx,
+        ));
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !results_mock.matched_async().await {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("no results were posted");
+
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+        reporter_handle.await.unwrap().unwrap();
+
+        let bodies = bodies.lock();
+        let result = &bodies[0]["results"][0];
+        assert_eq!(result["agent_id"], config.read().agent_id.to_string());
+        assert_eq!(result["endpoint_id"], endpoint.id.to_string());
+        assert_eq!(result["check_type"]["type"], "tcpconnect");
+        assert_eq!(result["check_type"]["result"]["connected"], true);
+        assert_eq!(result["check_type"]["result"]["resolved_ip"], "127.0.0.1");
+        assert!(result["check_type"]["result"]["connect_time_ms"].is_number());
+    }
+
+    #[tokio::test]
+    async fn test_endpoints_are_dispatched_by_check_type() {
+        let tcp_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp_port = tcp_listener.local_addr().unwrap().port();
+        let udp_socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let udp_port = udp_socket.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = udp_socket.recv_from(&mut buf).await {
+                let _ = udp_socket.send_to(&buf[..len], peer).await;
+            }
+        });
+
+        // Both endpoints are plain addresses; only check_type tells them apart
+        let tcp = Endpoint::new("127.0.0.1")
+            .with_port(tcp_port)
+            .with_check_type(EndpointCheckType::Tcp);
+        let udp = Endpoint::new("127.0.0.1")
+            .with_port(udp_port)
+            .with_check_type(EndpointCheckType::Udp);
+        let (tcp_id, udp_id) = (tcp.id, udp.id);
+        let mut config = Config {
+            endpoints: vec![tcp, udp],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 60;
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let cache = Arc::clone(&cache);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while cache.stats().await.len < 2 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("endpoints produced no results");
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+
+        let results = cache.peek_batch(10).await;
+        assert_eq!(results.len(), 2);
+        for result in &results {
+            match &result.check_type {
+                CheckType::TcpConnectCheck(check) => {
+                    assert_eq!(result.endpoint_id, tcp_id);
+                    assert!(check.result.connected);
+                }
+                CheckType::UdpConnectCheck(check) => {
+                    assert_eq!(result.endpoint_id, udp_id);
+                    assert!(check.result.probe_successful);
+                }
+                other => panic!("unexpected check type: {:?}", other),
+            }
+        }
+    }
+
+    /// Plugin reporting every endpoint as up
+    struct UpPlugin;
+
+    #[async_trait::async_trait]
+    impl crate::plugin::MonitoringPlugin for UpPlugin {
+        fn name(&self) -> &str {
+            "up"
+        }
+
+        fn version(&self) -> &str {
+            "0.1.0"
+        }
+
+        async fn check(&self, agent_id: &Uuid, endpoint: &Endpoint) -> Result<MonitoringResult> {
+            Ok(MonitoringResult {
+                id: Uuid::now_v7(),
+                agent_id: *agent_id,
+                endpoint_id: endpoint.id,
+                check_type: CheckType::PluginCheck(crate::core::PluginCheck {
+                    r#type: crate::core::PluginCheckType::Plugin,
+                    result: crate::core::PluginResult {
+                        plugin_name: self.name().to_string(),
+                        plugin_version: self.version().to_string(),
+                        success: true,
+                        response_time_ms: Some(0.5),
+                        error_details: None,
+                        data: std::collections::HashMap::new(),
+                    },
+                }),
+                timestamp: chrono::Utc::now(),
+                warmup: None,
+                degraded: None,
+                failure_kind: None,
+                status: None,
+                notes: Vec::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugin_endpoints_are_checked_by_their_plugin() {
+        let endpoint = Endpoint::new("up://service.example.com").with_plugin("up");
+        let endpoint_id = endpoint.id;
+        let mut config = Config {
+            endpoints: vec![endpoint],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 60;
+        let agent_id = config.agent_id;
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+        let mut plugins = PluginRegistry::new();
+        plugins.register(Box::new(UpPlugin));
+
+        let monitor_handle = {
+            let cache = Arc::clone(&cache);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::new(plugins),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while cache.stats().await.len == 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("plugin endpoint produced no results");
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+
+        let results = cache.peek_batch(10).await;
+        assert_eq!(results.len(), 1);
+        let result = &results[0];
+        assert_eq!(result.agent_id, agent_id);
+        assert_eq!(result.endpoint_id, endpoint_id);
+        assert!(result.is_successful());
+        match &result.check_type {
+            CheckType::PluginCheck(check) => {
+                assert_eq!(check.result.plugin_name, "up");
+                assert_eq!(check.result.plugin_version, "0.1.0");
+            }
+            other => panic!("unexpected check type: {:?}", other),
+        }
+    }
+
+    /// Plugin whose checks never return
+    struct HangingPlugin;
+
+    #[async_trait::async_trait]
+    impl crate::plugin::MonitoringPlugin for HangingPlugin {
+        fn name(&self) -> &str {
+            "hanging"
+        }
+
+        fn version(&self) -> &str {
+            "0.1.0"
+        }
+
+        async fn check(&self, _agent_id: &Uuid, _endpoint: &Endpoint) -> Result<MonitoringResult> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hanging_plugin_times_out_without_starving_others() {
+        let hanging = Endpoint::new("hang://service.example.com").with_plugin("hanging");
+        let up = Endpoint::new("up://service.example.com").with_plugin("up");
+        let (hanging_id, up_id) = (hanging.id, up.id);
+        let mut config = Config {
+            endpoints: vec![hanging, up],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 60;
+        config.monitoring.timeout_secs = 1;
+        // A single slot: the second endpoint only runs once the first is done
+        config.monitoring.max_concurrent = 1;
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+        let mut plugins = PluginRegistry::new();
+        plugins.register(Box::new(HangingPlugin));
+        plugins.register(Box::new(UpPlugin));
+
+        let monitor_handle = {
+            let cache = Arc::clone(&cache);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::new(plugins),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while cache.stats().await.len < 2 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("plugin endpoints produced no results");
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+
+        let results = cache.peek_batch(10).await;
+        let result_of = |id: Uuid| results.iter().find(|r| r.endpoint_id == id).unwrap();
+        let timed_out = result_of(hanging_id);
+        assert!(!timed_out.is_successful());
+        assert_eq!(
+            timed_out.error_message().as_deref(),
+            Some("Plugin hanging timed out after 1s")
+        );
+        assert!(result_of(up_id).is_successful());
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_interval_override_is_checked_more_often() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+
+        let fast = Endpoint::new("127.0.0.1")
+            .with_port(port)
+            .with_check_type(EndpointCheckType::Tcp)
+            .with_interval_secs(1);
+        let default = Endpoint::new("127.0.0.1")
+            .with_port(port)
+            .with_check_type(EndpointCheckType::Tcp);
+        let (fast_id, default_id) = (fast.id, default.id);
+        let mut config = Config {
+            endpoints: vec![fast, default],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 60;
+        config.monitoring.timeout_secs = 1;
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let cache = Arc::clone(&cache);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+
+        let results = cache.peek_batch(100).await;
+        let checks_of = |id: Uuid| results.iter().filter(|r| r.endpoint_id == id).count();
+        assert!(
+            checks_of(fast_id) >= 2,
+            "{} fast checks",
+            checks_of(fast_id)
+        );
+        assert_eq!(checks_of(default_id), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_ping_is_followed_by_a_traceroute() {
+        // ICMP sockets may be unavailable in restricted environments.
+        if crate::monitor::PingChecker::new(Duration::from_secs(1), 1).is_err() {
+            return;
+        }
+
+        // TEST-NET-2 is never routed to a host that answers
+        let endpoint = Endpoint::new("198.51.100.1");
+        let endpoint_id = endpoint.id;
+        let mut config = Config {
+            endpoints: vec![endpoint],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 60;
+        config.monitoring.timeout_secs = 1;
+        config.monitoring.ping_count = 1;
+        config.monitoring.traceroute_on_failure = true;
+        config.monitoring.traceroute_max_hops = 2;
+        config.monitoring.traceroute_probes_per_hop = Some(1);
+        let config = Arc::new(RwLock::new(config));
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let cache = Arc::clone(&cache);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    status,
+                    cache,
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        tokio::time::timeout(Duration::from_secs(20), async {
+            while cache.stats().await.len < 2 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("no ping and traceroute results");
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+
+        let results = cache.peek_batch(10).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.endpoint_id == endpoint_id));
+        match &results[0].check_type {
+            CheckType::PingCheck(ping) => assert_eq!(ping.result.successes, 0),
+            other => panic!("expected the ping result first, got {:?}", other),
+        }
+        match &results[1].check_type {
+            CheckType::TracerouteCheck(traceroute) => {
+                assert!(!traceroute.result.target_reached);
+                assert_eq!(traceroute.result.hops.len(), 2);
+            }
+            other => panic!("expected a traceroute result, got {:?}", other),
+        }
+    }
+
+    /// A traceroute runs outside the cycle: one per endpoint at a time, at
+    /// most as many as there are slots, each cut off at its timeout.
+    #[tokio::test]
+    async fn test_tracer_limits_and_times_out_traceroutes() {
+        let (_shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+        let tracer = Tracer::new(2, shutdown_rx);
+        let (result_tx, mut result_rx) = mpsc::unbounded_channel();
+        let hanging = || std::future::pending::<Option<MonitoringResult>>();
+        let timeout = Duration::from_millis(200);
+
+        let endpoint = Endpoint::new("10.0.0.1");
+        let first = endpoint.id;
+        assert!(tracer.spawn(first, hanging(), timeout, result_tx.clone()));
+        assert!(
+            !tracer.spawn(first, hanging(), timeout, result_tx.clone()),
+            "already being traced"
+        );
+        assert!(tracer.spawn(Uuid::now_v7(), hanging(), timeout, result_tx.clone()));
+        assert!(
+            !tracer.spawn(Uuid::now_v7(), hanging(), timeout, result_tx.clone()),
+            "no slot left"
+        );
+
+        // Both time out and free their slots
+        tokio::time::sleep(timeout * 2).await;
+        let result = crate::plugin::MonitoringPlugin::check(&UpPlugin, &Uuid::nil(), &endpoint)
+            .await
+            .unwrap();
+        assert!(tracer.spawn(first, async move { Some(result) }, timeout, result_tx));
+        let traced = tokio::time::timeout(Duration::from_secs(1), result_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(traced.endpoint_id, first);
+    }
+
+    /// Run slow HTTP checks and instant pings under `monitoring`, returning
+    /// how long after the cycle started each ping was dispatched and how long
+    /// the whole cycle took
+    async fn ping_dispatch_delays(monitoring: &MonitoringConfig) -> (Vec<Duration>, Duration) {
+        let mut endpoints: Vec<Endpoint> = (0..40)
+            .map(|i| Endpoint::new(format!("https://slow{}.example.com/", i)))
+            .collect();
+        endpoints.extend((0..3).map(|i| Endpoint::new(format!("10.0.0.{}", i + 1))));
+
+        let start = Instant::now();
+        let dispatched = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let outcome = run_cycle(
+            endpoints,
+            monitoring.max_concurrent,
+            |kind| monitoring.max_concurrent_for(kind),
+            None,
+            |endpoint| {
+                let dispatched = Arc::clone(&dispatched);
+                async move {
+                    if endpoint.check_kind() == CheckKind::Http {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                    } else {
+                        dispatched.lock().push(start.elapsed());
+                    }
+                    true
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(outcome.completed.len(), 43);
+        let delays = dispatched.lock().clone();
+        (delays, start.elapsed())
+    }
+
+    #[tokio::test]
+    async fn test_per_kind_limit_keeps_slots_for_pings() {
+        let mut monitoring = MonitoringConfig::default();
+        monitoring
+            .max_concurrent_per_kind
+            .insert(CheckKind::Http, 2);
+        let (delays, _) = ping_dispatch_delays(&monitoring).await;
+
+        assert_eq!(delays.len(), 3);
+        assert!(
+            delays
+                .iter()
+                .all(|delay| *delay < Duration::from_millis(150)),
+            "{:?}",
+            delays
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_config_keeps_slots_for_pings() {
+        // Without per-kind limits 40 HTTP checks of 200ms could take all 10
+        // slots for most of a second; the fair share keeps 3 for the pings,
+        // which are dispatched at once
+        let monitoring = MonitoringConfig::default();
+        assert!(monitoring.max_concurrent_per_kind.is_empty());
+        let (delays, elapsed) = ping_dispatch_delays(&monitoring).await;
+
+        assert_eq!(delays.len(), 3);
+        assert!(
+            delays
+                .iter()
+                .all(|delay| *delay < Duration::from_millis(150)),
+            "{:?}",
+            delays
+        );
+        // Once the pings are dispatched the HTTP checks get every slot: 4
+        // rounds of 200ms rather than 6 with the initial share of 7
+        assert!(elapsed < Duration::from_millis(1100), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_fair_shares() {
+        assert_eq!(fair_shares(&[10, 3], 10), [7, 3]);
+        assert_eq!(fair_shares(&[10, 10], 10), [5, 5]);
+        assert_eq!(fair_shares(&[10, 10, 10], 10), [3, 3, 4]);
+        assert_eq!(fair_shares(&[2, 1], 10), [2, 1]);
+        assert_eq!(fair_shares(&[4], 10), [4]);
+        // Every kind gets a slot, the global limit still caps the total
+        assert_eq!(fair_shares(&[5, 5, 5], 2), [1, 1, 1]);
+    }
+
+    /// A reload to a shorter monitoring interval reschedules endpoints
+    /// instead of waiting for the check due under the old interval.
+    #[tokio::test]
+    async fn test_reloaded_interval_speeds_up_checks() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let tcp_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp_port = tcp_listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        {
+            let connections = Arc::clone(&connections);
+            tokio::spawn(async move {
+                while tcp_listener.accept().await.is_ok() {
+                    connections.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+
+        let mut config = Config {
+            endpoints: vec![Endpoint::new(format!("tcp://127.0.0.1:{}", tcp_port))],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 3600;
+        config.monitoring.timeout_secs = 1;
+        let config = Arc::new(RwLock::new(config));
+        let (reload_tx, reload_rx) = watch::channel(0);
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let config = Arc::clone(&config);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    config,
+                    Arc::new(RwLock::new(AgentStatus::new())),
+                    Arc::new(ResultCache::new(1000, Duration::from_secs(3600))),
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    reload_rx,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        let wait_for_checks = |count: usize| {
+            let connections = Arc::clone(&connections);
+            tokio::time::timeout(Duration::from_secs(5), async move {
+                while connections.load(Ordering::SeqCst) < count {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+            })
+        };
+        wait_for_checks(1)
+            .await
+            .expect("endpoint was never checked");
+
+        config.write().monitoring.interval_secs = 1;
+        reload_tx.send_modify(|generation| *generation += 1);
+
+        wait_for_checks(3)
+            .await
+            .expect("the shorter interval did not take effect");
+
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failures_while_warming_up_do_not_open_the_circuit() {
+        let closed_port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let mut config = Config {
+            endpoints: vec![Endpoint::new(format!("tcp://127.0.0.1:{}", closed_port))],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 1;
+        config.monitoring.timeout_secs = 1;
+        config.monitoring.warmup_secs = Some(3600);
+        config.monitoring.circuit_breaker = Some(crate::agent_config::CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown_secs: 3600,
+        });
+        let agent_status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (_reload_tx, reload_rx) = watch::channel(0);
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let agent_status = Arc::clone(&agent_status);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    Arc::new(RwLock::new(config)),
+                    agent_status,
+                    Arc::new(ResultCache::new(1000, Duration::from_secs(3600))),
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    reload_rx,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        // With a threshold of one failure, a second check only runs if the
+        // first failure was not counted by the circuit breaker
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while agent_status.read().checks_failed_warmup < 2 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("the circuit opened during warmup");
+        {
+            let status = agent_status.read();
+            assert_eq!(status.checks_skipped.circuit_open, 0);
+            assert_eq!(status.checks_failed, 0);
+        }
+
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_per_endpoint_health_tracks_each_endpoint() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let reachable = Endpoint::new(format!("tcp://{}", listener.local_addr().unwrap()));
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+        // Nothing listens on a port that was just released
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = Endpoint::new(format!("tcp://{}", closed.local_addr().unwrap()));
+        drop(closed);
+
+        let mut config = Config {
+            endpoints: vec![reachable.clone(), unreachable.clone()],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 1;
+        config.monitoring.timeout_secs = 1;
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let status = Arc::clone(&status);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    Arc::new(RwLock::new(config)),
+                    status,
+                    Arc::new(ResultCache::new(1000, Duration::from_secs(3600))),
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        // Two cycles, so the unreachable endpoint fails twice in a row
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let failures = status
+                    .read()
+                    .per_endpoint
+                    .get(&unreachable.id.to_string())
+                    .map(|health| health.consecutive_failures);
+                if failures >= Some(2) && status.read().per_endpoint.len() == 2 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("both endpoints should have been checked twice");
+
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+
+        let status = status.read();
+        let up = &status.per_endpoint[&reachable.id.to_string()];
+        assert_eq!(up.address, reachable.address);
+        assert!(up.last_successful);
+        assert!(up.last_latency_ms.is_some());
+        assert_eq!(up.consecutive_failures, 0);
+
+        let down = &status.per_endpoint[&unreachable.id.to_string()];
+        assert_eq!(down.address, unreachable.address);
+        assert!(!down.last_successful);
+        assert!(down.consecutive_failures >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_results_carry_threshold_status() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut endpoint = Endpoint::new(format!("tcp://{}", listener.local_addr().unwrap()));
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+        // Any successful connect is slower than this
+        endpoint.warning_ms = Some(0.000_001);
+        let endpoint_id = endpoint.id;
+
+        let mut config = Config {
+            endpoints: vec![endpoint],
+            ..Config::default()
+        };
+        config.monitoring.interval_secs = 60;
+        config.monitoring.timeout_secs = 1;
+        let cache = Arc::new(ResultCache::new(1000, Duration::from_secs(3600)));
+        let status = Arc::new(RwLock::new(AgentStatus::new()));
+        let (shutdown_tx, shutdown_rx) = crate::core::shutdown_channel();
+
+        let monitor_handle = {
+            let cache = Arc::clone(&cache);
+            let status = Arc::clone(&status);
+            let mut shutdown_rx = shutdown_rx.clone();
+            tokio::spawn(async move {
+                run_monitoring(
+                    Arc::new(RwLock::new(config)),
+                    status,
+                    cache,
+                    Arc::default(),
+                    Readiness::default(),
+                    CheckMetrics::default(),
+                    watch::channel(0).1,
+                    &mut shutdown_rx,
+                )
+                .await
+            })
+        };
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while cache.stats().await.len < 1 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("no tcp result");
+        crate::core::trigger_shutdown(&shutdown_tx);
+        monitor_handle.await.unwrap().unwrap();
+
+        let results = cache.peek_batch(10).await;
+        assert!(results[0].is_successful());
+        assert_eq!(results[0].status, Some(MetricStatus::Degraded));
+        assert_eq!(
+            status.read().per_endpoint[&endpoint_id.to_string()].last_status,
+            Some(MetricStatus::Degraded)
+        );
+    }
+}
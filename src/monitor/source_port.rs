@@ -0,0 +1,363 @@
+//! Local address and port range for outbound check connections
+//!
+//! Firewalls sometimes only allow outbound traffic from a fixed range of
+//! source ports. With `monitoring.source_port_range` set, checks that open
+//! their own TCP connections or UDP sockets bind the local socket to a port
+//! of that range before connecting. The search starts at a random port of the range and
+//! moves on to the next port while the current one is in use. ICMP has no
+//! ports and is not affected.
+//!
+//! On multi-homed hosts `monitoring.source_address` selects the local
+//! address, and so the interface, check traffic leaves from. Sockets are
+//! bound to it before connecting, with or without a port range. A
+//! destination of the other address family cannot be reached from it and
+//! fails without a connection attempt.
+
+use super::qos;
+use rand::random_range;
+use socket2::SockRef;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::{TcpSocket, TcpStream, UdpSocket};
+use tracing::{debug, warn};
+
+/// Ports tried before giving up on a range where every port is taken
+const MAX_ATTEMPTS: u32 = 64;
+
+/// Inclusive range of local ports to bind outbound sockets to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SourcePortRange {
+    start: u16,
+    end: u16,
+}
+
+impl SourcePortRange {
+    /// Range from a validated `(start, end)` pair
+    pub(crate) fn new((start, end): (u16, u16)) -> Self {
+        Self { start, end }
+    }
+
+    /// Ports to try: up to `MAX_ATTEMPTS` consecutive ports of the range,
+    /// starting at a random one and wrapping around at the end
+    fn candidates(&self) -> impl Iterator<Item = u16> {
+        let start = u32::from(self.start);
+        let len = u32::from(self.end) - start + 1;
+        let offset = random_range(0..len);
+        (0..len.min(MAX_ATTEMPTS)).map(move |i| (start + (offset + i) % len) as u16)
+    }
+
+    /// Call `bind` with ports of the range until one is not in use
+    async fn bind_with<T, F, Fut>(&self, mut bind: F) -> io::Result<T>
+    where
+        F: FnMut(u16) -> Fut,
+        Fut: Future<Output = io::Result<T>>,
+    {
+        for port in self.candidates() {
+            match bind(port).await {
+                Ok(socket) => return Ok(socket),
+                Err(e) if is_port_conflict(&e) => {
+                    debug!("Source port {} unavailable: {}", port, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("No free source port in {}-{}", self.start, self.end),
+        ))
+    }
+
+    /// Open a TCP connection to `addr` from `local` and a port of the range
+    async fn connect(
+        &self,
+        local: IpAddr,
+        addr: SocketAddr,
+        dscp: Option<u8>,
+    ) -> io::Result<TcpStream> {
+        self.bind_with(|port| async move {
+            let socket = tcp_socket(addr, dscp)?;
+            // Lets a port whose previous connection is in TIME_WAIT be reused
+            socket.set_reuseaddr(true)?;
+            socket.bind(SocketAddr::new(local, port))?;
+            socket.connect(addr).await
+        })
+        .await
+    }
+
+    /// Bind a UDP socket to `local` and a port of the range
+    async fn bind_udp(&self, local: IpAddr) -> io::Result<UdpSocket> {
+        self.bind_with(|port| async move { UdpSocket::bind(SocketAddr::new(local, port)).await })
+            .await
+    }
+}
+
+/// Local end of outbound check sockets
+///
+/// Without a source address or port range the operating system picks both,
+/// as for any outbound connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Source {
+    address: Option<IpAddr>,
+    ports: Option<SourcePortRange>,
+}
+
+impl Source {
+    /// Bind to `address` and a port of `ports`, each when set
+    pub(crate) fn new(address: Option<IpAddr>, ports: Option<SourcePortRange>) -> Self {
+        Self { address, ports }
+    }
+
+    /// Local address for sockets sending to `addr`
+    fn local_ip(&self, addr: SocketAddr) -> io::Result<IpAddr> {
+        match self.address {
+            Some(source) if source.is_ipv4() != addr.is_ipv4() => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Source address {} cannot reach {}", source, addr.ip()),
+            )),
+            Some(source) => Ok(source),
+            None => Ok(unspecified(addr)),
+        }
+    }
+
+    /// Open a TCP connection to `addr`
+    pub(crate) async fn connect(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        self.connect_marked(addr, None).await
+    }
+
+    /// Open a TCP connection to `addr` whose packets, starting with the SYN,
+    /// are marked with `dscp` if set
+    pub(crate) async fn connect_marked(
+        &self,
+        addr: SocketAddr,
+        dscp: Option<u8>,
+    ) -> io::Result<TcpStream> {
+        let local = self.local_ip(addr)?;
+        match (self.ports, self.address, dscp) {
+            (Some(ports), _, _) => ports.connect(local, addr, dscp).await,
+            (None, None, None) => TcpStream::connect(addr).await,
+            (None, address, _) => {
+                let socket = tcp_socket(addr, dscp)?;
+                if address.is_some() {
+                    socket.bind(SocketAddr::new(local, 0))?;
+                }
+                socket.connect(addr).await
+            }
+        }
+    }
+
+    /// Bind a UDP socket for sending to `addr`
+    pub(crate) async fn bind_udp(&self, addr: SocketAddr) -> io::Result<UdpSocket> {
+        let local = self.local_ip(addr)?;
+        match self.ports {
+            Some(ports) => ports.bind_udp(local).await,
+            None => UdpSocket::bind(SocketAddr::new(local, 0)).await,
+        }
+    }
+}
+
+/// Unbound TCP socket of `addr`'s family, marked with `dscp` if set
+///
+/// Marking is best effort: a socket that cannot be marked is still used.
+fn tcp_socket(addr: SocketAddr, dscp: Option<u8>) -> io::Result<TcpSocket> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4(),
+        SocketAddr::V6(_) => TcpSocket::new_v6(),
+    }?;
+    if let Some(dscp) = dscp {
+        if let Err(e) = qos::apply_dscp(SockRef::from(&socket), dscp, addr.is_ipv6()) {
+            warn!("Failed to apply DSCP {} to TCP socket: {}", dscp, e);
+        }
+    }
+    Ok(socket)
+}
+
+/// Unspecified local address of `addr`'s family
+fn unspecified(addr: SocketAddr) -> IpAddr {
+    match addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }
+}
+
+/// Whether `e` means the chosen local port (or 4-tuple) is already taken
+fn is_port_conflict(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::AddrInUse | io::ErrorKind::AddrNotAvailable
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Range of test ports above the usual ephemeral port ranges' start
+    const RANGE: (u16, u16) = (41000, 41999);
+
+    #[test]
+    fn test_candidates_stay_within_range_and_wrap() {
+        let range = SourcePortRange::new((65530, 65535));
+        let ports: Vec<u16> = range.candidates().collect();
+
+        assert_eq!(ports.len(), 6);
+        assert!(ports.iter().all(|port| (65530..=65535).contains(port)));
+        let mut sorted = ports.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 6, "every port is tried once: {:?}", ports);
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_port_is_skipped() {
+        let range = SourcePortRange::new((50000, 50002));
+        let mut tried = Vec::new();
+
+        let port = range
+            .bind_with(|port| {
+                tried.push(port);
+                let result = if tried.len() == 1 {
+                    Err(io::Error::from(io::ErrorKind::AddrInUse))
+                } else {
+                    Ok(port)
+                };
+                async move { result }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(tried.len(), 2);
+        assert_ne!(tried[0], port);
+        assert!((50000..=50002).contains(&port));
+    }
+
+    #[tokio::test]
+    async fn test_fully_used_range_fails() {
+        let listener = TcpListener::bind("0.0.0.0:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let source = Source::new(None, Some(SourcePortRange::new((port, port))));
+
+        let err = source
+            .connect(listener.local_addr().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connection_binds_within_range() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let source = Source::new(None, Some(SourcePortRange::new(RANGE)));
+
+        let stream = source
+            .connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+
+        let local_port = stream.local_addr().unwrap().port();
+        assert_eq!(peer.port(), local_port);
+        assert!((RANGE.0..=RANGE.1).contains(&local_port));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_tcp_connection_is_marked_before_connecting() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        for source in [
+            Source::default(),
+            Source::new(Some("127.0.0.1".parse().unwrap()), None),
+            Source::new(None, Some(SourcePortRange::new(RANGE))),
+        ] {
+            let stream = source.connect_marked(addr, Some(46)).await.unwrap();
+            listener.accept().await.unwrap();
+            assert_eq!(
+                SockRef::from(&stream).tos_v4().unwrap(),
+                qos::tos_from_dscp(46),
+                "{:?}",
+                source
+            );
+        }
+
+        // IPv6 may be disabled in the build environment.
+        let Ok(listener) = TcpListener::bind("[::1]:0").await else {
+            return;
+        };
+        let stream = Source::default()
+            .connect_marked(listener.local_addr().unwrap(), Some(34))
+            .await
+            .unwrap();
+        assert_eq!(
+            SockRef::from(&stream).tclass_v6().unwrap(),
+            qos::tos_from_dscp(34)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_udp_socket_binds_within_range() {
+        let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let source = Source::new(None, Some(SourcePortRange::new(RANGE)));
+
+        let socket = source.bind_udp(peer.local_addr().unwrap()).await.unwrap();
+
+        let local_port = socket.local_addr().unwrap().port();
+        assert!((RANGE.0..=RANGE.1).contains(&local_port));
+    }
+
+    /// Loopback address other than the default 127.0.0.1
+    const SOURCE: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+
+    #[tokio::test]
+    async fn test_tcp_connection_binds_to_source_address() {
+        let listener = TcpListener::bind("0.0.0.0:0").await.unwrap();
+        let addr = SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            listener.local_addr().unwrap().port(),
+        );
+
+        let plain = Source::new(Some(SOURCE), None).connect(addr).await.unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer.ip(), SOURCE);
+        assert_eq!(plain.local_addr().unwrap().ip(), SOURCE);
+
+        let ranged = Source::new(Some(SOURCE), Some(SourcePortRange::new(RANGE)))
+            .connect(addr)
+            .await
+            .unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer.ip(), SOURCE);
+        assert!((RANGE.0..=RANGE.1).contains(&ranged.local_addr().unwrap().port()));
+    }
+
+    #[tokio::test]
+    async fn test_udp_socket_binds_to_source_address() {
+        let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let socket = Source::new(Some(SOURCE), None)
+            .bind_udp(peer.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(socket.local_addr().unwrap().ip(), SOURCE);
+    }
+
+    #[tokio::test]
+    async fn test_source_address_of_other_family_fails() {
+        let source = Source::new(Some(SOURCE), None);
+
+        let err = source
+            .connect("[::1]:80".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(
+            err.to_string().contains("127.0.0.2 cannot reach ::1"),
+            "{}",
+            err
+        );
+    }
+}
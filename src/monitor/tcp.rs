@@ -0,0 +1,365 @@
+//! TCP connect monitoring
+//!
+//! Endpoints whose address is a `tcp://host:port` URL are checked by opening
+//! a TCP connection and closing it again once the handshake completed. The
+//! port comes from the URL or, failing that, the endpoint's `port` field; an
+//! endpoint with neither fails without a connection attempt.
+//!
+//! `connect_time_ms` covers the handshake only. Name resolution is timed
+//! separately as `dns_resolution_ms`, like for ping checks. A refused
+//! connection fails as `refused`, one that is not answered within the check
+//! timeout as `unreachable`.
+//!
+//! With `monitoring.source_address` or `monitoring.source_port_range` set,
+//! the connection is opened from that local address or a port of that range.
+//! With `monitoring.dscp` set, the socket is marked (`IP_TOS`, or
+//! `IPV6_TCLASS` for IPv6 targets) before connecting, so the handshake
+//! already travels in that traffic class.
+
+use super::failure::classify_io;
+use super::resolver::Resolver;
+use super::source_port::Source;
+use crate::core::{
+    CheckType, Endpoint, ErrorDetails, FailureKind, MonitoringResult, TcpConnectCheck,
+    TcpConnectCheckType, TcpConnectResult,
+};
+use chrono::Utc;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tracing::trace;
+use uuid::Uuid;
+
+/// TCP checker for endpoints whose address is a `tcp://` URL
+pub struct TcpConnectChecker {
+    timeout: Duration,
+    resolver: Resolver,
+    source: Source,
+    dscp: Option<u8>,
+}
+
+impl TcpConnectChecker {
+    /// Create a TCP checker that gives up on a connection after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            resolver: Resolver::new(timeout),
+            source: Source::default(),
+            dscp: None,
+        }
+    }
+
+    /// Resolve hostnames with `resolver`
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Connect from the local address and port range of `source`
+    pub(crate) fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Mark connections with the given DSCP value
+    pub fn with_dscp(mut self, dscp: Option<u8>) -> Self {
+        self.dscp = dscp;
+        self
+    }
+
+    /// Perform a TCP connect check on the given endpoint
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let target = endpoint.check_target();
+
+        let (result, failure_kind) = match target.port {
+            Some(port) => self.connect(&target.host, port).await,
+            None => failure(
+                format!("Endpoint {} has no port to connect to", endpoint.address),
+                String::new(),
+                None,
+                FailureKind::Other,
+            ),
+        };
+
+        trace!(
+            "TCP check to {} ({}): connected={}, time={:.2?} ms, dns_time={:.2?} ms",
+            endpoint.address,
+            result.resolved_ip,
+            result.connected,
+            result.connect_time_ms,
+            result.dns_resolution_ms
+        );
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: endpoint.id,
+            check_type: CheckType::TcpConnectCheck(TcpConnectCheck {
+                r#type: TcpConnectCheckType::Tcpconnect,
+                result,
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Resolve `host` and time a connection to `port` on it. Returns the
+    /// kind of failure if no connection was established.
+    async fn connect(&self, host: &str, port: u16) -> (TcpConnectResult, Option<FailureKind>) {
+        let (resolved, lookup_time) = self.resolver.resolve(host).await;
+        let dns_resolution_ms = lookup_time.map(|d| d.as_secs_f64() * 1000.0);
+        let ip = match resolved {
+            Ok(ip) => ip,
+            Err(e) => {
+                return failure(
+                    format!("Failed to resolve address: {}", e),
+                    String::new(),
+                    dns_resolution_ms,
+                    FailureKind::Unresolved,
+                )
+            }
+        };
+        let addr = SocketAddr::new(ip, port);
+
+        let start = Instant::now();
+        let connected =
+            tokio::time::timeout(self.timeout, self.source.connect_marked(addr, self.dscp)).await;
+        let connect_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        match connected {
+            // Dropping the stream closes the connection again
+            Ok(Ok(_stream)) => (
+                TcpConnectResult {
+                    connected: true,
+                    connect_time_ms: Some(connect_time_ms),
+                    error_details: None,
+                    resolved_ip: ip.to_string(),
+                    dns_resolution_ms,
+                },
+                None,
+            ),
+            Ok(Err(e)) => failure(
+                format!("Failed to connect to {}: {}", addr, e),
+                ip.to_string(),
+                dns_resolution_ms,
+                classify_io(&e),
+            ),
+            Err(_) => failure(
+                format!("Connect timeout after {:?}", self.timeout),
+                ip.to_string(),
+                dns_resolution_ms,
+                FailureKind::Unreachable,
+            ),
+        }
+    }
+}
+
+/// Failed result for checks that did not establish a connection
+fn failure(
+    error: String,
+    resolved_ip: String,
+    dns_resolution_ms: Option<f64>,
+    kind: FailureKind,
+) -> (TcpConnectResult, Option<FailureKind>) {
+    let result = TcpConnectResult {
+        connected: false,
+        connect_time_ms: None,
+        error_details: Some(ErrorDetails {
+            errors: Some(vec![error]),
+        }),
+        resolved_ip,
+        dns_resolution_ms,
+    };
+    (result, Some(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::source_port::SourcePortRange;
+    use tokio::net::TcpListener;
+
+    fn tcp_result(result: &MonitoringResult) -> &TcpConnectResult {
+        match &result.check_type {
+            CheckType::TcpConnectCheck(c) => &c.result,
+            other => panic!("expected a TCP connect result, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_port_connects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = tokio::spawn(async move { listener.accept().await.is_ok() });
+
+        let checker = TcpConnectChecker::new(Duration::from_secs(5));
+        let endpoint = Endpoint::new(format!("tcp://{}", addr));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(accepted.await.unwrap());
+        let tcp = tcp_result(&result);
+        assert!(tcp.connected);
+        assert!(tcp.connect_time_ms.is_some());
+        assert_eq!(tcp.resolved_ip, "127.0.0.1");
+        assert_eq!(tcp.dns_resolution_ms, None);
+        assert!(tcp.error_details.is_none());
+        assert!(result.is_successful());
+        assert_eq!(result.failure_kind, None);
+        assert_eq!(result.endpoint_id, endpoint.id);
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_port_is_used_when_url_has_none() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let checker = TcpConnectChecker::new(Duration::from_secs(5));
+        let endpoint = Endpoint::new("tcp://127.0.0.1").with_port(port);
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(result.is_successful(), "{:?}", tcp_result(&result));
+    }
+
+    #[tokio::test]
+    async fn test_marked_connection_connects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let checker = TcpConnectChecker::new(Duration::from_secs(5)).with_dscp(Some(46));
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new(format!("tcp://{}", addr)))
+            .await;
+
+        assert!(result.is_successful(), "{:?}", tcp_result(&result));
+    }
+
+    /// A listener on all addresses holding a port whose successor is free,
+    /// so that the two form a source port range with its first port taken
+    fn taken_port_with_free_successor() -> (std::net::TcpListener, u16) {
+        loop {
+            let blocker = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+            let port = blocker.local_addr().unwrap().port();
+            if port < u16::MAX && std::net::TcpListener::bind(("0.0.0.0", port + 1)).is_ok() {
+                return (blocker, port);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_binds_free_port_of_source_range() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_blocker, taken) = taken_port_with_free_successor();
+
+        // Whichever port the search starts at, the taken one is skipped
+        let range = SourcePortRange::new((taken, taken + 1));
+        let checker = TcpConnectChecker::new(Duration::from_secs(5))
+            .with_source(Source::new(None, Some(range)));
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new(format!("tcp://{}", addr)))
+            .await;
+
+        assert!(result.is_successful(), "{:?}", tcp_result(&result));
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer.port(), taken + 1);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_source_range_fails_without_connecting() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_blocker, taken) = taken_port_with_free_successor();
+
+        let range = SourcePortRange::new((taken, taken));
+        let checker = TcpConnectChecker::new(Duration::from_secs(5))
+            .with_source(Source::new(None, Some(range)));
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new(format!("tcp://{}", addr)))
+            .await;
+
+        let tcp = tcp_result(&result);
+        assert!(!tcp.connected);
+        let error = result.error_message().unwrap();
+        assert!(
+            error.contains(&format!("No free source port in {}-{}", taken, taken)),
+            "{}",
+            error
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), listener.accept())
+                .await
+                .is_err(),
+            "no connection reaches the target"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_closed_port_is_refused() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let checker = TcpConnectChecker::new(Duration::from_secs(5));
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new(format!("tcp://{}", addr)))
+            .await;
+
+        let tcp = tcp_result(&result);
+        assert!(!tcp.connected);
+        assert_eq!(tcp.connect_time_ms, None);
+        assert_eq!(tcp.resolved_ip, "127.0.0.1");
+        assert!(result
+            .error_message()
+            .unwrap()
+            .contains("Failed to connect"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Refused));
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_connect_times_out() {
+        use socket2::{Domain, Socket, Type};
+
+        // A listener with a zero backlog whose only queue slot is taken:
+        // further connection attempts are left unanswered.
+        let listener = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+        listener
+            .bind(&"127.0.0.1:0".parse::<SocketAddr>().unwrap().into())
+            .unwrap();
+        listener.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap().as_socket().unwrap();
+        let _queued = std::net::TcpStream::connect(addr).unwrap();
+
+        let checker = TcpConnectChecker::new(Duration::from_millis(200));
+        let start = Instant::now();
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new(format!("tcp://{}", addr)))
+            .await;
+
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert!(!result.is_successful());
+        assert_eq!(
+            result.error_message().as_deref(),
+            Some("Connect timeout after 200ms")
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Unreachable));
+    }
+
+    #[tokio::test]
+    async fn test_missing_port_fails_without_connecting() {
+        let checker = TcpConnectChecker::new(Duration::from_secs(1));
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("tcp://db.example.com"))
+            .await;
+
+        let tcp = tcp_result(&result);
+        assert!(!tcp.connected);
+        assert_eq!(tcp.resolved_ip, "");
+        assert!(result.error_message().unwrap().contains("has no port"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+    }
+}
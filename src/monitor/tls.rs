@@ -0,0 +1,520 @@
+//! TLS certificate expiry monitoring
+//!
+//! Endpoints with `check_type = "tls_cert"` are checked by completing a TLS
+//! handshake with the host and reading the certificate it presents. The port
+//! comes from the address (an `https://`, `grpcs://` or `tcp://` URL) or the
+//! endpoint's `port` field, and is 443 without either. `server_name`, or
+//! else a hostname address, is sent as SNI.
+//!
+//! The check watches expiry only: the certificate chain and the host name
+//! are not verified, so certificates of internal CAs and self-signed ones
+//! are checked like any other. HTTP checks of the same URL verify the chain.
+//!
+//! The result records the certificate's subject, issuer, expiry time and
+//! the whole days left until then. It is `valid` while the certificate is
+//! within its validity period and at least the endpoint's `warn_days` from
+//! expiry, so an alert fires ahead of the expiry.
+//!
+//! A refused connection fails as `refused`, a connection or handshake that
+//! does not finish within the check timeout as `unreachable`. A certificate
+//! that expires too soon, expired or is not valid yet fails as `other`.
+
+use super::failure::classify_io;
+use super::resolver::Resolver;
+use super::source_port::Source;
+use crate::core::{
+    CheckType, Endpoint, ErrorDetails, FailureKind, MonitoringResult, TlsCertCheck,
+    TlsCertCheckType, TlsCertResult,
+};
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{
+    ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider,
+};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio_rustls::TlsConnector;
+use tracing::trace;
+use uuid::Uuid;
+
+/// Port checked when neither the address nor the endpoint has one
+const DEFAULT_TLS_PORT: u16 = 443;
+
+/// TLS certificate checker for endpoints with `check_type = "tls_cert"`
+pub struct TlsCertChecker {
+    timeout: Duration,
+    resolver: Resolver,
+    source: Source,
+    connector: TlsConnector,
+}
+
+impl TlsCertChecker {
+    /// Create a certificate checker that gives up on the connection and
+    /// handshake after `timeout`
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let provider = Arc::new(ring::default_provider());
+        let config = ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::Network(format!("Failed to create TLS client: {}", e)))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+            .with_no_client_auth();
+
+        Ok(Self {
+            timeout,
+            resolver: Resolver::new(timeout),
+            source: Source::default(),
+            connector: TlsConnector::from(Arc::new(config)),
+        })
+    }
+
+    /// Resolve hostnames with `resolver`
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Connect from the local address and port range of `source`
+    pub(crate) fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Perform a TLS certificate check on the given endpoint
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let (result, failure_kind) = self.inspect(endpoint).await;
+
+        trace!(
+            "TLS certificate check to {} ({}): valid={}, days_until_expiry={:?}, time={:.2?} ms",
+            endpoint.address,
+            result.resolved_ip,
+            result.valid,
+            result.days_until_expiry,
+            result.handshake_time_ms
+        );
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: endpoint.id,
+            check_type: CheckType::TlsCertCheck(TlsCertCheck {
+                r#type: TlsCertCheckType::Tlscert,
+                result,
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Fetch and evaluate `endpoint`'s certificate. Returns the kind of
+    /// failure if the check failed.
+    async fn inspect(&self, endpoint: &Endpoint) -> (TlsCertResult, Option<FailureKind>) {
+        let target = endpoint.check_target();
+        let port = target.port.unwrap_or(DEFAULT_TLS_PORT);
+        let name = endpoint.server_name.as_deref().unwrap_or(&target.host);
+        let server_name = match ServerName::try_from(name.to_string()) {
+            Ok(server_name) => server_name,
+            Err(e) => {
+                return failure(
+                    format!("Invalid server name {}: {}", name, e),
+                    String::new(),
+                    None,
+                    FailureKind::Other,
+                )
+            }
+        };
+
+        let (resolved, lookup_time) = self.resolver.resolve(&target.host).await;
+        let dns_resolution_ms = lookup_time.map(|d| d.as_secs_f64() * 1000.0);
+        let ip = match resolved {
+            Ok(ip) => ip,
+            Err(e) => {
+                return failure(
+                    format!("Failed to resolve address: {}", e),
+                    String::new(),
+                    dns_resolution_ms,
+                    FailureKind::Unresolved,
+                )
+            }
+        };
+        let addr = SocketAddr::new(ip, port);
+
+        let start = Instant::now();
+        let handshake = tokio::time::timeout(self.timeout, async {
+            let stream = self.source.connect(addr).await?;
+            self.connector.connect(server_name, stream).await
+        })
+        .await;
+        let handshake_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        let stream = match handshake {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                return failure(
+                    format!("TLS handshake with {} failed: {}", addr, e),
+                    ip.to_string(),
+                    dns_resolution_ms,
+                    classify_io(&e),
+                )
+            }
+            Err(_) => {
+                return failure(
+                    format!("TLS handshake timeout after {:?}", self.timeout),
+                    ip.to_string(),
+                    dns_resolution_ms,
+                    FailureKind::Unreachable,
+                )
+            }
+        };
+
+        let certificate = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|certificates| certificates.first());
+        let Some(certificate) = certificate else {
+            return failure(
+                format!("{} presented no certificate", addr),
+                ip.to_string(),
+                dns_resolution_ms,
+                FailureKind::Other,
+            );
+        };
+        let certificate = match Certificate::parse(certificate) {
+            Ok(certificate) => certificate,
+            Err(e) => return failure(e, ip.to_string(), dns_resolution_ms, FailureKind::Other),
+        };
+
+        let warn_days = endpoint.warn_days.unwrap_or(0);
+        let (days_until_expiry, error) = certificate.evaluate(Utc::now(), warn_days);
+        let valid = error.is_none();
+        let result = TlsCertResult {
+            valid,
+            days_until_expiry: Some(days_until_expiry),
+            not_after: Some(certificate.not_after),
+            subject: Some(certificate.subject),
+            issuer: Some(certificate.issuer),
+            handshake_time_ms: Some(handshake_time_ms),
+            error_details: error.map(|error| ErrorDetails {
+                errors: Some(vec![error]),
+            }),
+            resolved_ip: ip.to_string(),
+            dns_resolution_ms,
+        };
+        (result, (!valid).then_some(FailureKind::Other))
+    }
+}
+
+/// Fields of a server certificate the check reports on
+#[derive(Debug)]
+struct Certificate {
+    subject: String,
+    issuer: String,
+    not_before: DateTime<Utc>,
+    not_after: DateTime<Utc>,
+}
+
+impl Certificate {
+    fn parse(der: &CertificateDer<'_>) -> std::result::Result<Self, String> {
+        let (_, certificate) = x509_parser::parse_x509_certificate(der)
+            .map_err(|e| format!("Failed to parse the server certificate: {}", e))?;
+        let validity = certificate.validity();
+        let time = |time: x509_parser::time::ASN1Time| {
+            DateTime::from_timestamp(time.timestamp(), 0)
+                .ok_or_else(|| format!("Certificate validity time {} is out of range", time))
+        };
+
+        Ok(Self {
+            subject: certificate.subject().to_string(),
+            issuer: certificate.issuer().to_string(),
+            not_before: time(validity.not_before)?,
+            not_after: time(validity.not_after)?,
+        })
+    }
+
+    /// Whole days from `now` until expiry, negative once expired, and why
+    /// the certificate fails with `warn_days`, `None` if it passes
+    fn evaluate(&self, now: DateTime<Utc>, warn_days: i64) -> (i64, Option<String>) {
+        let days = (self.not_after - now).num_seconds().div_euclid(86_400);
+
+        let error = if now < self.not_before {
+            Some(format!(
+                "Certificate is not valid before {}",
+                self.not_before
+            ))
+        } else if now >= self.not_after {
+            Some(format!(
+                "Certificate expired {} days ago, on {}",
+                -days - 1,
+                self.not_after
+            ))
+        } else if days < warn_days {
+            Some(format!(
+                "Certificate expires in {} days, on {}, within the {} day warning window",
+                days, self.not_after, warn_days
+            ))
+        } else {
+            None
+        };
+        (days, error)
+    }
+}
+
+/// Certificate verifier accepting any certificate the server can prove to
+/// own, so the expiry of every certificate can be read
+#[derive(Debug)]
+struct AnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Failed result for checks that got no certificate to evaluate
+fn failure(
+    error: String,
+    resolved_ip: String,
+    dns_resolution_ms: Option<f64>,
+    kind: FailureKind,
+) -> (TlsCertResult, Option<FailureKind>) {
+    let result = TlsCertResult {
+        valid: false,
+        days_until_expiry: None,
+        not_after: None,
+        subject: None,
+        issuer: None,
+        handshake_time_ms: None,
+        error_details: Some(ErrorDetails {
+            errors: Some(vec![error]),
+        }),
+        resolved_ip,
+        dns_resolution_ms,
+    };
+    (result, Some(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::EndpointCheckType;
+    use chrono::Datelike;
+    use rcgen::{date_time_ymd, CertificateParams, DistinguishedName, DnType, KeyPair};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    fn tls_result(result: &MonitoringResult) -> &TlsCertResult {
+        match &result.check_type {
+            CheckType::TlsCertCheck(c) => &c.result,
+            other => panic!("expected a TLS certificate result, got {:?}", other),
+        }
+    }
+
+    /// Serve a self-signed certificate for `localhost`, valid from
+    /// `not_before` until `not_after`, on a local port
+    async fn serve(not_before: DateTime<Utc>, not_after: DateTime<Utc>) -> SocketAddr {
+        let date =
+            |time: DateTime<Utc>| date_time_ymd(time.year(), time.month() as u8, time.day() as u8);
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        params.distinguished_name = DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "localhost");
+        params.not_before = date(not_before);
+        params.not_after = date(not_after);
+        let certificate = params.self_signed(&key).unwrap();
+
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![certificate.der().clone()],
+                PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let _ = acceptor.accept(stream).await;
+            }
+        });
+        addr
+    }
+
+    async fn check(addr: SocketAddr, warn_days: Option<u32>) -> MonitoringResult {
+        let mut endpoint = Endpoint::new(format!("https://{}/", addr))
+            .with_check_type(EndpointCheckType::TlsCert)
+            .with_server_name("localhost");
+        if let Some(warn_days) = warn_days {
+            endpoint = endpoint.with_warn_days(warn_days);
+        }
+        TlsCertChecker::new(Duration::from_secs(5))
+            .unwrap()
+            .check(Uuid::now_v7(), &endpoint)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_certificate_outside_warning_window_is_valid() {
+        let now = Utc::now();
+        let addr = serve(
+            now - chrono::Duration::days(2),
+            now + chrono::Duration::days(30),
+        )
+        .await;
+
+        let result = check(addr, Some(7)).await;
+
+        let tls = tls_result(&result);
+        assert!(result.is_successful(), "{:?}", tls);
+        assert_eq!(result.failure_kind, None);
+        assert!(matches!(tls.days_until_expiry, Some(28..=30)), "{:?}", tls);
+        assert_eq!(tls.subject.as_deref(), Some("CN=localhost"));
+        assert_eq!(tls.issuer.as_deref(), Some("CN=localhost"));
+        assert!(tls.not_after.is_some_and(|not_after| not_after > now));
+        assert!(tls.handshake_time_ms.is_some());
+        assert_eq!(tls.resolved_ip, "127.0.0.1");
+        assert!(tls.error_details.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_certificate_within_warning_window_is_invalid() {
+        let now = Utc::now();
+        let addr = serve(
+            now - chrono::Duration::days(2),
+            now + chrono::Duration::days(30),
+        )
+        .await;
+
+        let result = check(addr, Some(60)).await;
+
+        let tls = tls_result(&result);
+        assert!(!tls.valid);
+        assert!(tls.days_until_expiry.is_some());
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+        let error = result.error_message().unwrap();
+        assert!(
+            error.contains("within the 60 day warning window"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_certificate_is_invalid() {
+        let now = Utc::now();
+        let addr = serve(
+            now - chrono::Duration::days(400),
+            now - chrono::Duration::days(10),
+        )
+        .await;
+
+        let result = check(addr, None).await;
+
+        let tls = tls_result(&result);
+        assert!(!tls.valid);
+        assert!(
+            tls.days_until_expiry.is_some_and(|days| days < 0),
+            "{:?}",
+            tls
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+        assert!(result
+            .error_message()
+            .unwrap()
+            .contains("Certificate expired"));
+    }
+
+    #[tokio::test]
+    async fn test_closed_port_is_refused() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let result = check(addr, None).await;
+
+        let tls = tls_result(&result);
+        assert!(!tls.valid);
+        assert_eq!(tls.days_until_expiry, None);
+        assert_eq!(tls.resolved_ip, "127.0.0.1");
+        assert_eq!(result.failure_kind, Some(FailureKind::Refused));
+    }
+
+    #[test]
+    fn test_days_until_expiry_rounds_down() {
+        let now = Utc::now();
+        let certificate = Certificate {
+            subject: String::new(),
+            issuer: String::new(),
+            not_before: now - chrono::Duration::days(1),
+            not_after: now + chrono::Duration::hours(36),
+        };
+
+        assert_eq!(certificate.evaluate(now, 0), (1, None));
+        assert_eq!(certificate.evaluate(now, 1), (1, None));
+        let (days, error) = certificate.evaluate(now, 2);
+        assert_eq!(days, 1);
+        assert!(error.is_some());
+
+        let (days, error) = certificate.evaluate(now + chrono::Duration::hours(37), 0);
+        assert_eq!(days, -1);
+        assert!(error.unwrap().starts_with("Certificate expired 0 days ago"));
+    }
+}
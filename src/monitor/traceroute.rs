@@ -0,0 +1,677 @@
+//! Hop-by-hop traceroute probing
+//!
+//! [`trace`] walks TTLs from 1 to `monitoring.traceroute_max_hops` and
+//! probes every TTL `monitoring.traceroute_probes_per_hop` times (default
+//! 3), so a single dropped probe does not hide a live hop. A hop is
+//! responsive if any of its probes was answered. Its `success_latencies`
+//! hold every answered probe's RTT, and `best_latency_ms` and
+//! `median_latency_ms` summarize them. Tracing stops at the first hop
+//! answered by the target itself.
+//!
+//! Sending the probes is left to a [`HopProber`], so the hop logic is
+//! independent of the socket type used. [`TracerouteChecker`] probes with
+//! ICMP echo requests through a surge-ping socket, like [`PingChecker`]:
+//! routers on the path answer with "time exceeded", the target with an echo
+//! reply.
+//!
+//! Platform limitations:
+//! - Raw ICMP sockets (root or `CAP_NET_RAW`) see every hop.
+//! - Unprivileged ICMP sockets (`net.ipv4.ping_group_range` on Linux) only
+//!   receive echo replies, so routers on the path show as unresponsive hops
+//!   while the target is still detected.
+//!
+//! With `monitoring.source_address` set, probes to destinations of its
+//! address family are sent from it. Other destinations fail without a
+//! probe.
+//!
+//! With `monitoring.reverse_dns` enabled, every responsive hop's `hostname`
+//! is looked up by PTR once the trace is complete, all hops at once and
+//! through the shared PTR cache. A hop without a PTR record keeps `None`.
+//!
+//! [`PingChecker`]: super::PingChecker
+
+use super::resolver::Resolver;
+use super::reverse_dns::ReverseResolver;
+use crate::core::{
+    CheckType, Endpoint, ErrorDetails, FailureKind, MonitoringResult, TracerouteCheck,
+    TracerouteCheckType, TracerouteHop, TracerouteResult,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use socket2::SockRef;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+use surge_ping::{AsyncSocket, Config, IcmpPacket, Icmpv4Packet, Icmpv6Packet, ICMP};
+use tracing::trace;
+use uuid::Uuid;
+
+/// Payload of each probe, so probes are recognizable in packet captures
+const PROBE_PAYLOAD: &[u8] = b"smotra traceroute probe";
+
+/// ICMP types of the messages a probe can be answered with
+const ICMPV4_ECHO_REPLY: u8 = 0;
+const ICMPV4_DEST_UNREACHABLE: u8 = 3;
+const ICMPV4_TIME_EXCEEDED: u8 = 11;
+const ICMPV6_DEST_UNREACHABLE: u8 = 1;
+const ICMPV6_TIME_EXCEEDED: u8 = 3;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+/// Answer to a single probe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ProbeReply {
+    /// Address that answered: a router on the path, or the target
+    pub from: IpAddr,
+
+    /// Round-trip time of the probe
+    pub rtt: Duration,
+
+    /// The target itself answered, so later TTLs need not be probed
+    pub reached: bool,
+}
+
+/// Sends one probe with a given TTL towards the traced target
+#[async_trait]
+pub(crate) trait HopProber: Send + Sync {
+    /// Probe with `ttl`, `Ok(None)` if no answer arrived before the probe
+    /// timeout
+    async fn probe(&self, ttl: u8) -> io::Result<Option<ProbeReply>>;
+}
+
+/// Traceroute checker, tracing the path to an endpoint with ICMP probes
+pub struct TracerouteChecker {
+    timeout: Duration,
+    max_hops: u8,
+    probes_per_hop: u8,
+    resolver: Resolver,
+    source_address: Option<IpAddr>,
+    reverse_resolver: Option<ReverseResolver>,
+}
+
+impl TracerouteChecker {
+    /// Create a checker that traces up to `max_hops` hops and waits up to
+    /// `timeout` for the answer to each probe
+    pub fn new(timeout: Duration, max_hops: u8) -> Self {
+        Self {
+            timeout,
+            max_hops,
+            probes_per_hop: 3,
+            resolver: Resolver::new(timeout),
+            source_address: None,
+            reverse_resolver: None,
+        }
+    }
+
+    /// Send probes from `source_address`. `None` lets the operating system
+    /// choose.
+    pub fn with_source_address(mut self, source_address: Option<IpAddr>) -> Self {
+        self.source_address = source_address;
+        self
+    }
+
+    /// Send `probes_per_hop` probes for every TTL instead of 3
+    pub fn with_probes_per_hop(mut self, probes_per_hop: u8) -> Self {
+        self.probes_per_hop = probes_per_hop;
+        self
+    }
+
+    /// Resolve hostnames with `resolver`
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Look up the hostnames of the hops with `reverse_resolver`. `None`
+    /// disables reverse lookups.
+    pub(crate) fn with_reverse_dns(mut self, reverse_resolver: Option<ReverseResolver>) -> Self {
+        self.reverse_resolver = reverse_resolver;
+        self
+    }
+
+    /// Trace the path to the host of the given endpoint
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let host = endpoint.check_target().host;
+
+        let (result, failure_kind) = match self.resolver.resolve(&host).await.0 {
+            Ok(addr) => match IcmpProber::new(addr, self.timeout, self.source_address) {
+                Ok(prober) => {
+                    let mut result = trace(&prober, self.max_hops, self.probes_per_hop).await;
+                    if let Some(reverse_resolver) = &self.reverse_resolver {
+                        fill_hostnames(&mut result.hops, reverse_resolver).await;
+                    }
+                    let failure_kind = (!result.target_reached).then_some(FailureKind::Unreachable);
+                    (result, failure_kind)
+                }
+                Err(e) => failure(
+                    format!("Failed to open ICMP socket: {}", e),
+                    FailureKind::Other,
+                ),
+            },
+            Err(e) => failure(
+                format!("Failed to resolve address: {}", e),
+                FailureKind::Unresolved,
+            ),
+        };
+
+        trace!(
+            "Traceroute to {}: {} hops, target_reached={}",
+            endpoint.address,
+            result.hops.len(),
+            result.target_reached
+        );
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: endpoint.id,
+            check_type: CheckType::TracerouteCheck(TracerouteCheck {
+                r#type: TracerouteCheckType::Traceroute,
+                result,
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+}
+
+/// Failed result for traces that sent no probe
+fn failure(error: String, kind: FailureKind) -> (TracerouteResult, Option<FailureKind>) {
+    let result = TracerouteResult {
+        hops: Vec::new(),
+        target_reached: false,
+        error_details: Some(ErrorDetails {
+            errors: Some(vec![error]),
+        }),
+    };
+    (result, Some(kind))
+}
+
+/// Sends ICMP echo requests with a limited TTL through one surge-ping socket
+struct IcmpProber {
+    target: IpAddr,
+    timeout: Duration,
+    socket: AsyncSocket,
+    identifier: u16,
+    sequence: AtomicU16,
+}
+
+impl IcmpProber {
+    fn new(target: IpAddr, timeout: Duration, source: Option<IpAddr>) -> io::Result<Self> {
+        let kind = if target.is_ipv4() { ICMP::V4 } else { ICMP::V6 };
+        let mut config = Config::builder().kind(kind);
+        if let Some(source) = source {
+            if source.is_ipv4() != target.is_ipv4() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Source address {} cannot reach {}", source, target),
+                ));
+            }
+            config = config.bind(SocketAddr::new(source, 0));
+        }
+        let socket = AsyncSocket::new(&config.build())?;
+
+        Ok(Self {
+            target,
+            timeout,
+            socket,
+            identifier: rand::random(),
+            sequence: AtomicU16::new(rand::random()),
+        })
+    }
+
+    /// Reply to the probe with `sequence` in a received packet, if it is one
+    fn reply(&self, buf: &[u8], from: IpAddr, sequence: u16, rtt: Duration) -> Option<ProbeReply> {
+        let (packet, echo_reply) = match (from, self.target) {
+            (IpAddr::V4(from), IpAddr::V4(_)) => {
+                let packet =
+                    Icmpv4Packet::decode(buf, self.socket.get_type(), from, Ipv4Addr::UNSPECIFIED)
+                        .ok()?;
+                let icmp_type = packet.get_icmp_type().0;
+                if icmp_type != ICMPV4_ECHO_REPLY
+                    && IpAddr::V4(packet.get_real_dest()) != self.target
+                {
+                    return None;
+                }
+                let answers = matches!(
+                    icmp_type,
+                    ICMPV4_ECHO_REPLY | ICMPV4_DEST_UNREACHABLE | ICMPV4_TIME_EXCEEDED
+                );
+                (
+                    answers.then_some(IcmpPacket::V4(packet))?,
+                    icmp_type == ICMPV4_ECHO_REPLY,
+                )
+            }
+            (IpAddr::V6(from), IpAddr::V6(_)) => {
+                let packet = Icmpv6Packet::decode(buf, from).ok()?;
+                let icmp_type = packet.get_icmpv6_type().0;
+                let answers = matches!(
+                    icmp_type,
+                    ICMPV6_ECHO_REPLY | ICMPV6_DEST_UNREACHABLE | ICMPV6_TIME_EXCEEDED
+                );
+                (
+                    answers.then_some(IcmpPacket::V6(packet))?,
+                    icmp_type == ICMPV6_ECHO_REPLY,
+                )
+            }
+            _ => return None,
+        };
+
+        // Unprivileged sockets get their identifier assigned by the kernel,
+        // which also only delivers the socket's own replies
+        let own_identifier = self.socket.get_type() != socket2::Type::RAW
+            || packet.get_identifier().0 == self.identifier;
+        if packet.get_sequence().0 != sequence || !own_identifier {
+            return None;
+        }
+        if echo_reply && from != self.target {
+            return None;
+        }
+
+        Some(ProbeReply {
+            from,
+            rtt,
+            reached: from == self.target,
+        })
+    }
+
+    /// Limit the hops of the next probes to `ttl`
+    fn set_ttl(&self, ttl: u8) -> io::Result<()> {
+        #[cfg(unix)]
+        // SAFETY: the descriptor is owned by `self.socket`, which outlives the borrow.
+        let raw = unsafe { std::os::fd::BorrowedFd::borrow_raw(self.socket.get_native_sock()) };
+        #[cfg(windows)]
+        // SAFETY: the socket is owned by `self.socket`, which outlives the borrow.
+        let raw = unsafe {
+            std::os::windows::io::BorrowedSocket::borrow_raw(self.socket.get_native_sock())
+        };
+
+        let socket = SockRef::from(&raw);
+        if self.target.is_ipv4() {
+            socket.set_ttl_v4(u32::from(ttl))
+        } else {
+            socket.set_unicast_hops_v6(u32::from(ttl))
+        }
+    }
+}
+
+#[async_trait]
+impl HopProber for IcmpProber {
+    async fn probe(&self, ttl: u8) -> io::Result<Option<ProbeReply>> {
+        self.set_ttl(ttl)?;
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let mut packet = echo_request(self.target, self.identifier, sequence);
+
+        self.socket
+            .send_to(&mut packet, &SocketAddr::new(self.target, 0))
+            .await?;
+        let sent = Instant::now();
+        let deadline = tokio::time::Instant::from_std(sent + self.timeout);
+
+        // Skip late answers to earlier probes and unrelated ICMP traffic
+        let mut buf = [0u8; 2048];
+        loop {
+            let (len, from) =
+                match tokio::time::timeout_at(deadline, self.socket.recv_from(&mut buf)).await {
+                    Ok(received) => received?,
+                    Err(_) => return Ok(None),
+                };
+            if let Some(reply) = self.reply(&buf[..len], from.ip(), sequence, sent.elapsed()) {
+                return Ok(Some(reply));
+            }
+        }
+    }
+}
+
+/// Echo request to `target` with `identifier` and `sequence`
+fn echo_request(target: IpAddr, identifier: u16, sequence: u16) -> Vec<u8> {
+    let icmp_type = if target.is_ipv4() { 8 } else { 128 };
+    let mut packet = vec![icmp_type, 0, 0, 0];
+    packet.extend_from_slice(&identifier.to_be_bytes());
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(PROBE_PAYLOAD);
+    // The kernel fills in ICMPv6 checksums and those of unprivileged ICMPv4
+    // sockets, but raw ICMPv4 sockets send the packet as is
+    if target.is_ipv4() {
+        let checksum = internet_checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+    packet
+}
+
+/// RFC 1071 checksum of an ICMPv4 message
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Trace the path to the prober's target
+pub(crate) async fn trace(
+    prober: &dyn HopProber,
+    max_hops: u8,
+    probes_per_hop: u8,
+) -> TracerouteResult {
+    let mut hops = Vec::new();
+    let mut errors = Vec::new();
+    let mut target_reached = false;
+
+    for ttl in 1..=max_hops {
+        let mut replies = Vec::new();
+        for _ in 0..probes_per_hop.max(1) {
+            match prober.probe(ttl).await {
+                Ok(Some(reply)) => replies.push(reply),
+                Ok(None) => {}
+                Err(e) => errors.push(format!("hop {}: {}", ttl, e)),
+            }
+        }
+
+        target_reached = replies.iter().any(|reply| reply.reached);
+        hops.push(hop(ttl, &replies));
+        if target_reached {
+            break;
+        }
+    }
+
+    TracerouteResult {
+        hops,
+        target_reached,
+        error_details: (!errors.is_empty()).then_some(ErrorDetails {
+            errors: Some(errors),
+        }),
+    }
+}
+
+/// Summarize the replies to the probes of one TTL
+fn hop(ttl: u8, replies: &[ProbeReply]) -> TracerouteHop {
+    let mut latencies: Vec<f64> = replies
+        .iter()
+        .map(|reply| reply.rtt.as_secs_f64() * 1000.0)
+        .collect();
+    latencies.sort_by(f64::total_cmp);
+
+    // Prefer the target's address if it answered any probe of this TTL
+    let from = replies
+        .iter()
+        .find(|reply| reply.reached)
+        .or(replies.first())
+        .map(|reply| reply.from.to_string());
+
+    TracerouteHop {
+        hop: ttl as i64,
+        resolved_ip: from,
+        best_latency_ms: latencies.first().copied(),
+        median_latency_ms: median(&latencies),
+        responsive: !replies.is_empty(),
+        success_latencies: (!latencies.is_empty()).then_some(latencies),
+        hostname: None,
+    }
+}
+
+/// Set the PTR hostname of every hop that answered, looking all of them up
+/// concurrently
+pub(crate) async fn fill_hostnames(hops: &mut [TracerouteHop], reverse_resolver: &ReverseResolver) {
+    let lookups = hops.iter_mut().filter_map(|hop| {
+        let ip = hop.resolved_ip.as_deref()?.parse::<IpAddr>().ok()?;
+        Some(async move { hop.hostname = reverse_resolver.hostname(ip).await })
+    });
+    futures::future::join_all(lookups).await;
+}
+
+/// Median of ascending `sorted` values
+fn median(sorted: &[f64]) -> Option<f64> {
+    let mid = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(sorted[mid]),
+        _ => Some((sorted[mid - 1] + sorted[mid]) / 2.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
+
+    /// Path of routers `10.0.0.{ttl}` with the target at `target_hop`
+    struct MockPath {
+        target_hop: u8,
+        /// Probes of each TTL answered so far, to drop the first one
+        probes: Mutex<HashMap<u8, u32>>,
+        drop_first: bool,
+        silent_hops: Vec<u8>,
+    }
+
+    impl MockPath {
+        fn new(target_hop: u8) -> Self {
+            Self {
+                target_hop,
+                probes: Mutex::new(HashMap::new()),
+                drop_first: false,
+                silent_hops: Vec::new(),
+            }
+        }
+
+        fn probes(&self, ttl: u8) -> u32 {
+            self.probes.lock().get(&ttl).copied().unwrap_or(0)
+        }
+    }
+
+    #[async_trait]
+    impl HopProber for MockPath {
+        async fn probe(&self, ttl: u8) -> io::Result<Option<ProbeReply>> {
+            let nth = {
+                let mut probes = self.probes.lock();
+                let count = probes.entry(ttl).or_default();
+                *count += 1;
+                *count
+            };
+
+            if (self.drop_first && nth == 1) || self.silent_hops.contains(&ttl) {
+                return Ok(None);
+            }
+
+            let hop = ttl.min(self.target_hop);
+            Ok(Some(ProbeReply {
+                from: IpAddr::V4(Ipv4Addr::new(10, 0, 0, hop)),
+                rtt: Duration::from_millis(u64::from(ttl) * 10 + u64::from(nth)),
+                reached: ttl >= self.target_hop,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hop_registers_when_first_probe_is_dropped() {
+        let path = MockPath {
+            drop_first: true,
+            ..MockPath::new(3)
+        };
+
+        let result = trace(&path, 30, 3).await;
+
+        assert!(result.target_reached);
+        assert_eq!(result.hops.len(), 3);
+        for (i, hop) in result.hops.iter().enumerate() {
+            let ttl = i as u8 + 1;
+            assert!(hop.responsive, "hop {} must register", ttl);
+            assert_eq!(hop.resolved_ip, Some(format!("10.0.0.{}", ttl)));
+            assert_eq!(hop.success_latencies.as_ref().map(Vec::len), Some(2));
+            assert_eq!(path.probes(ttl), 3);
+        }
+
+        // Replies to the second and third probe of TTL 2: 22 ms and 23 ms
+        assert_eq!(result.hops[1].best_latency_ms, Some(22.0));
+        assert_eq!(result.hops[1].median_latency_ms, Some(22.5));
+    }
+
+    #[tokio::test]
+    async fn test_hop_hostnames_are_filled_by_reverse_dns() {
+        use crate::monitor::reverse_dns::tests::CountingLookup;
+        use crate::monitor::reverse_dns::PtrCache;
+
+        let path = MockPath {
+            silent_hops: vec![2],
+            ..MockPath::new(4)
+        };
+        let mut result = trace(&path, 30, 3).await;
+        assert!(result.hops.iter().all(|hop| hop.hostname.is_none()));
+
+        let lookup = CountingLookup::new(Some("router.example."));
+        let reverse_resolver = ReverseResolver::with_lookup(
+            lookup.clone(),
+            Duration::from_secs(1),
+            PtrCache::default(),
+        );
+        fill_hostnames(&mut result.hops, &reverse_resolver).await;
+
+        let hostnames: Vec<Option<&str>> = result
+            .hops
+            .iter()
+            .map(|hop| hop.hostname.as_deref())
+            .collect();
+        assert_eq!(
+            hostnames,
+            [
+                Some("router.example"),
+                None,
+                Some("router.example"),
+                Some("router.example")
+            ]
+        );
+        assert_eq!(lookup.calls(), 3, "silent hops are not looked up");
+    }
+
+    #[tokio::test]
+    async fn test_single_probe_per_hop_misses_dropped_hops() {
+        let path = MockPath {
+            drop_first: true,
+            ..MockPath::new(3)
+        };
+
+        let result = trace(&path, 5, 1).await;
+
+        assert!(!result.target_reached);
+        assert_eq!(result.hops.len(), 5);
+        assert!(result.hops.iter().all(|hop| !hop.responsive));
+        assert!(result.hops.iter().all(|hop| hop.resolved_ip.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_silent_hop_is_kept_and_tracing_continues() {
+        let path = MockPath {
+            silent_hops: vec![2],
+            ..MockPath::new(4)
+        };
+
+        let result = trace(&path, 30, 3).await;
+
+        assert!(result.target_reached);
+        assert_eq!(result.hops.len(), 4);
+        assert!(!result.hops[1].responsive);
+        assert_eq!(result.hops[1].best_latency_ms, None);
+        assert_eq!(result.hops[1].success_latencies, None);
+        assert_eq!(result.hops[3].median_latency_ms, Some(42.0));
+    }
+
+    #[test]
+    fn test_echo_request_checksum_verifies() {
+        let packet = echo_request(IpAddr::V4(Ipv4Addr::LOCALHOST), 0x1234, 7);
+
+        assert_eq!(&packet[..2], &[8, 0]);
+        assert_eq!(&packet[4..8], &[0x12, 0x34, 0, 7]);
+        assert_eq!(internet_checksum(&packet), 0);
+    }
+
+    #[tokio::test]
+    async fn test_localhost_is_reached_at_the_first_hop() {
+        // ICMP sockets may be unavailable in restricted environments.
+        if AsyncSocket::new(&Config::default()).is_err() {
+            return;
+        }
+
+        let checker = TracerouteChecker::new(Duration::from_secs(1), 30);
+        let endpoint = Endpoint::new("127.0.0.1");
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let traceroute = match &result.check_type {
+            CheckType::TracerouteCheck(c) => &c.result,
+            other => panic!("expected a traceroute result, got {:?}", other),
+        };
+        assert!(traceroute.target_reached, "{:?}", traceroute);
+        assert_eq!(traceroute.hops.len(), 1);
+        assert_eq!(traceroute.hops[0].resolved_ip.as_deref(), Some("127.0.0.1"));
+        assert!(traceroute.hops[0].responsive);
+        assert!(result.is_successful());
+        assert_eq!(result.failure_kind, None);
+    }
+
+    #[tokio::test]
+    async fn test_unreached_target_stops_at_the_hop_cap() {
+        // ICMP sockets may be unavailable in restricted environments.
+        if AsyncSocket::new(&Config::default()).is_err() {
+            return;
+        }
+
+        // TEST-NET-2 is never routed to a host that answers
+        let checker = TracerouteChecker::new(Duration::from_millis(100), 2).with_probes_per_hop(1);
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("198.51.100.1"))
+            .await;
+
+        let traceroute = match &result.check_type {
+            CheckType::TracerouteCheck(c) => &c.result,
+            other => panic!("expected a traceroute result, got {:?}", other),
+        };
+        assert!(!traceroute.target_reached, "{:?}", traceroute);
+        assert_eq!(traceroute.hops.len(), 2);
+        assert_eq!(traceroute.hops[1].hop, 2);
+        assert_eq!(result.failure_kind, Some(FailureKind::Unreachable));
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_target_fails_without_probing() {
+        use crate::monitor::resolver::tests::SlowLookup;
+        use std::sync::Arc;
+
+        let resolver = Resolver::with_lookup(
+            Arc::new(SlowLookup {
+                delay: Duration::ZERO,
+                addrs: Vec::new(),
+            }),
+            Duration::from_secs(1),
+        );
+        let checker = TracerouteChecker::new(Duration::from_secs(1), 30).with_resolver(resolver);
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("missing.smotra.test"))
+            .await;
+
+        assert!(!result.is_successful());
+        assert_eq!(result.failure_kind, Some(FailureKind::Unresolved));
+        assert!(result
+            .error_message()
+            .unwrap()
+            .starts_with("Failed to resolve address"));
+    }
+
+    #[tokio::test]
+    async fn test_tracing_stops_at_max_hops() {
+        let path = MockPath::new(20);
+
+        let result = trace(&path, 5, 3).await;
+
+        assert!(!result.target_reached);
+        assert_eq!(result.hops.len(), 5);
+        assert_eq!(path.probes(6), 0);
+    }
+}
@@ -0,0 +1,392 @@
+//! UDP probe monitoring
+//!
+//! Endpoints whose address is a `udp://host:port` URL are probed by sending
+//! one datagram with `monitoring.udp.payload` (empty by default) and waiting
+//! up to the check timeout for an answer. The port comes from the URL or,
+//! failing that, the endpoint's `port` field.
+//!
+//! UDP is connectionless, so "up" can only be inferred:
+//!
+//! - Any datagram received back from the target is a success, timed as
+//!   `response_time_ms` from sending the probe.
+//! - An ICMP port unreachable, surfaced by the kernel as a refused
+//!   connection on the connected socket, is a failure of kind `refused`.
+//! - No answer within the timeout is a failure of kind `unreachable`,
+//!   unless `monitoring.udp.no_response_is_success` is set. Many services
+//!   ignore datagrams they cannot parse, and firewalls commonly drop the
+//!   ICMP errors, so silence is ambiguous. A probe counted as successful
+//!   without a response carries a note saying so.
+//!
+//! With `monitoring.source_port_range` set, the socket is bound to a local
+//! port of that range. With `monitoring.dscp` set, the probe is marked with
+//! that DSCP value.
+
+use super::failure::classify_io;
+use super::qos;
+use super::resolver::Resolver;
+use super::source_port::Source;
+use crate::core::{
+    CheckType, Endpoint, ErrorDetails, FailureKind, MonitoringResult, UdpConnectCheck,
+    UdpConnectCheckType, UdpConnectResult,
+};
+use chrono::Utc;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tracing::{trace, warn};
+use uuid::Uuid;
+
+/// Largest response read; longer datagrams are truncated, which does not
+/// matter since only their arrival counts
+const RESPONSE_BUFFER: usize = 2048;
+
+/// Outcome of a probe
+struct Probe {
+    result: UdpConnectResult,
+    failure_kind: Option<FailureKind>,
+    note: Option<String>,
+}
+
+/// UDP checker for endpoints whose address is a `udp://` URL
+pub struct UdpConnectChecker {
+    timeout: Duration,
+    payload: Vec<u8>,
+    no_response_is_success: bool,
+    resolver: Resolver,
+    source: Source,
+    dscp: Option<u8>,
+}
+
+impl UdpConnectChecker {
+    /// Create a UDP checker that waits up to `timeout` for a response
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            payload: Vec::new(),
+            no_response_is_success: false,
+            resolver: Resolver::new(timeout),
+            source: Source::default(),
+            dscp: None,
+        }
+    }
+
+    /// Send `payload` in each probe instead of an empty datagram
+    pub fn with_payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+
+    /// Count probes without any response as successful
+    pub fn with_no_response_is_success(mut self, no_response_is_success: bool) -> Self {
+        self.no_response_is_success = no_response_is_success;
+        self
+    }
+
+    /// Mark probes with the given DSCP value
+    pub fn with_dscp(mut self, dscp: Option<u8>) -> Self {
+        self.dscp = dscp;
+        self
+    }
+
+    /// Resolve hostnames with `resolver`
+    pub(crate) fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Bind to the local address and port range of `source`
+    pub(crate) fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Perform a UDP probe of the given endpoint
+    pub async fn check(&self, agent_id: Uuid, endpoint: &Endpoint) -> MonitoringResult {
+        let target = endpoint.check_target();
+
+        let probe = match target.port {
+            Some(port) => self.probe(&target.host, port).await,
+            None => failure(
+                format!("Endpoint {} has no port to probe", endpoint.address),
+                String::new(),
+                None,
+                FailureKind::Other,
+            ),
+        };
+
+        trace!(
+            "UDP probe to {} ({}): success={}, time={:.2?} ms, dns_time={:.2?} ms",
+            endpoint.address,
+            probe.result.resolved_ip,
+            probe.result.probe_successful,
+            probe.result.response_time_ms,
+            probe.result.dns_resolution_ms
+        );
+
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: endpoint.id,
+            check_type: CheckType::UdpConnectCheck(UdpConnectCheck {
+                r#type: UdpConnectCheckType::Udpconnect,
+                result: probe.result,
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: probe.failure_kind,
+            status: None,
+            notes: probe.note.into_iter().collect(),
+        }
+    }
+
+    /// Resolve `host`, send the probe to `port` on it and wait for a response
+    async fn probe(&self, host: &str, port: u16) -> Probe {
+        let (resolved, lookup_time) = self.resolver.resolve(host).await;
+        let dns_resolution_ms = lookup_time.map(|d| d.as_secs_f64() * 1000.0);
+        let ip = match resolved {
+            Ok(ip) => ip,
+            Err(e) => {
+                return failure(
+                    format!("Failed to resolve address: {}", e),
+                    String::new(),
+                    dns_resolution_ms,
+                    FailureKind::Unresolved,
+                )
+            }
+        };
+        let addr = SocketAddr::new(ip, port);
+        let resolved_ip = ip.to_string();
+
+        let socket = match self.bind(addr).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                return failure(
+                    format!("Failed to open UDP socket to {}: {}", addr, e),
+                    resolved_ip,
+                    dns_resolution_ms,
+                    classify_io(&e),
+                )
+            }
+        };
+
+        let start = Instant::now();
+        if let Err(e) = socket.send(&self.payload).await {
+            return failure(
+                format!("Failed to send probe to {}: {}", addr, e),
+                resolved_ip,
+                dns_resolution_ms,
+                classify_io(&e),
+            );
+        }
+
+        let mut buf = [0u8; RESPONSE_BUFFER];
+        match tokio::time::timeout(self.timeout, socket.recv(&mut buf)).await {
+            Ok(Ok(_)) => Probe {
+                result: UdpConnectResult {
+                    probe_successful: true,
+                    response_time_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
+                    error_details: None,
+                    resolved_ip,
+                    dns_resolution_ms,
+                },
+                failure_kind: None,
+                note: None,
+            },
+            Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => failure(
+                format!("Port unreachable: {}", addr),
+                resolved_ip,
+                dns_resolution_ms,
+                FailureKind::Refused,
+            ),
+            Ok(Err(e)) => failure(
+                format!("Failed to receive from {}: {}", addr, e),
+                resolved_ip,
+                dns_resolution_ms,
+                classify_io(&e),
+            ),
+            Err(_) if self.no_response_is_success => Probe {
+                result: UdpConnectResult {
+                    probe_successful: true,
+                    response_time_ms: None,
+                    error_details: None,
+                    resolved_ip,
+                    dns_resolution_ms,
+                },
+                failure_kind: None,
+                note: Some(format!(
+                    "no response within {:?}; counted as success",
+                    self.timeout
+                )),
+            },
+            Err(_) => failure(
+                format!("No response within {:?}", self.timeout),
+                resolved_ip,
+                dns_resolution_ms,
+                FailureKind::Unreachable,
+            ),
+        }
+    }
+
+    /// Open a socket connected to `addr`, so that ICMP errors for it are
+    /// reported on the socket and datagrams from other peers are ignored
+    async fn bind(&self, addr: SocketAddr) -> io::Result<UdpSocket> {
+        let socket = self.source.bind_udp(addr).await?;
+        if let Some(dscp) = self.dscp {
+            let ipv6 = socket.local_addr()?.is_ipv6();
+            if let Err(e) = qos::apply_dscp(socket2::SockRef::from(&socket), dscp, ipv6) {
+                warn!("Failed to apply DSCP {} to UDP probe socket: {}", dscp, e);
+            }
+        }
+        socket.connect(addr).await?;
+        Ok(socket)
+    }
+}
+
+/// Failed probe
+fn failure(
+    error: String,
+    resolved_ip: String,
+    dns_resolution_ms: Option<f64>,
+    kind: FailureKind,
+) -> Probe {
+    Probe {
+        result: UdpConnectResult {
+            probe_successful: false,
+            response_time_ms: None,
+            error_details: Some(ErrorDetails {
+                errors: Some(vec![error]),
+            }),
+            resolved_ip,
+            dns_resolution_ms,
+        },
+        failure_kind: Some(kind),
+        note: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_result(result: &MonitoringResult) -> &UdpConnectResult {
+        match &result.check_type {
+            CheckType::UdpConnectCheck(c) => &c.result,
+            other => panic!("expected a UDP connect result, got {:?}", other),
+        }
+    }
+
+    /// Start a UDP server that echoes every datagram, returning its address
+    /// and the payloads it received
+    async fn start_echo_server() -> (SocketAddr, tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let (received_tx, received_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut buf = [0u8; RESPONSE_BUFFER];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let _ = received_tx.send(buf[..len].to_vec());
+                let _ = socket.send_to(&buf[..len], peer).await;
+            }
+        });
+        (addr, received_rx)
+    }
+
+    #[tokio::test]
+    async fn test_echoed_probe_succeeds() {
+        let (addr, mut received) = start_echo_server().await;
+
+        let checker = UdpConnectChecker::new(Duration::from_secs(5)).with_payload("ping\n");
+        let endpoint = Endpoint::new(format!("udp://{}", addr));
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert_eq!(received.recv().await.unwrap(), b"ping\n");
+        let udp = udp_result(&result);
+        assert!(udp.probe_successful);
+        assert!(udp.response_time_ms.is_some());
+        assert_eq!(udp.resolved_ip, "127.0.0.1");
+        assert!(udp.error_details.is_none());
+        assert!(result.is_successful());
+        assert_eq!(result.failure_kind, None);
+        assert!(result.notes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_port_is_used_when_url_has_none() {
+        let (addr, _received) = start_echo_server().await;
+
+        let checker = UdpConnectChecker::new(Duration::from_secs(5));
+        let endpoint = Endpoint::new("udp://127.0.0.1").with_port(addr.port());
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(result.is_successful(), "{:?}", udp_result(&result));
+    }
+
+    #[tokio::test]
+    async fn test_silent_target_times_out() {
+        // Bound but never read: no ICMP error and no answer
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = Endpoint::new(format!("udp://{}", silent.local_addr().unwrap()));
+
+        let checker = UdpConnectChecker::new(Duration::from_millis(200));
+        let start = Instant::now();
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let udp = udp_result(&result);
+        assert!(!udp.probe_successful);
+        assert_eq!(udp.response_time_ms, None);
+        assert_eq!(
+            result.error_message().as_deref(),
+            Some("No response within 200ms")
+        );
+        assert_eq!(result.failure_kind, Some(FailureKind::Unreachable));
+
+        let checker = checker.with_no_response_is_success(true);
+        let result = checker.check(Uuid::now_v7(), &endpoint).await;
+
+        let udp = udp_result(&result);
+        assert!(udp.probe_successful);
+        assert_eq!(udp.response_time_ms, None);
+        assert_eq!(result.failure_kind, None);
+        assert_eq!(
+            result.notes,
+            vec!["no response within 200ms; counted as success".to_string()]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_closed_port_is_refused() {
+        let addr = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let checker =
+            UdpConnectChecker::new(Duration::from_secs(5)).with_no_response_is_success(true);
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new(format!("udp://{}", addr)))
+            .await;
+
+        assert!(!result.is_successful());
+        assert!(result
+            .error_message()
+            .unwrap()
+            .starts_with("Port unreachable"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Refused));
+    }
+
+    #[tokio::test]
+    async fn test_missing_port_fails_without_probing() {
+        let checker = UdpConnectChecker::new(Duration::from_secs(1));
+        let result = checker
+            .check(Uuid::now_v7(), &Endpoint::new("udp://dns.example.com"))
+            .await;
+
+        assert!(!result.is_successful());
+        assert!(result.error_message().unwrap().contains("has no port"));
+        assert_eq!(result.failure_kind, Some(FailureKind::Other));
+    }
+}
@@ -0,0 +1,170 @@
+//! Warmup window for new and changed endpoints
+//!
+//! The first checks of an endpoint often fail for reasons that have nothing
+//! to do with the endpoint: a cold DNS cache, ARP resolution, a connection
+//! pool that is still empty. For `monitoring.warmup_secs` after an endpoint
+//! is first seen, its definition changes, or a new configuration version is
+//! loaded, its results are marked with `warmup: true`. They are still cached
+//! and reported, but failures among them do not count toward alert
+//! thresholds, `AgentStatus::checks_failed` or the circuit breaker.
+//!
+//! The check loop compares the configured endpoints on every tick, so an
+//! endpoint added or edited by a hot reload starts its own window as soon
+//! as the reloaded config is picked up, while the other endpoints keep
+//! theirs.
+
+use crate::core::Endpoint;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Start of the current warmup window of one endpoint
+#[derive(Debug)]
+struct EndpointWarmup {
+    /// Endpoint definition the window was started for
+    definition: Option<serde_json::Value>,
+    started: Instant,
+}
+
+/// Warmup windows of every endpoint, kept across results
+#[derive(Debug, Default)]
+pub(crate) struct Warmup {
+    window: Duration,
+    config_version: u32,
+    endpoints: HashMap<Uuid, EndpointWarmup>,
+}
+
+impl Warmup {
+    pub(crate) fn new(window: Duration, config_version: u32) -> Self {
+        Self {
+            window,
+            config_version,
+            endpoints: HashMap::new(),
+        }
+    }
+
+    /// Apply hot-reloaded settings. A new configuration version restarts
+    /// every endpoint's window.
+    pub(crate) fn update_config(&mut self, window: Duration, config_version: u32) {
+        if config_version != self.config_version {
+            *self = Self::new(window, config_version);
+        }
+        self.window = window;
+    }
+
+    /// Track the configured `endpoints`: start a window at `now` for every
+    /// endpoint that is new or whose definition changed, and forget those
+    /// that are no longer configured
+    pub(crate) fn update_endpoints(&mut self, endpoints: &[Endpoint], now: Instant) {
+        self.endpoints
+            .retain(|id, _| endpoints.iter().any(|e| e.id == *id));
+
+        for endpoint in endpoints {
+            let definition = serde_json::to_value(endpoint).ok();
+            let unchanged = self
+                .endpoints
+                .get(&endpoint.id)
+                .is_some_and(|warmup| warmup.definition == definition);
+            if !unchanged {
+                self.endpoints.insert(
+                    endpoint.id,
+                    EndpointWarmup {
+                        definition,
+                        started: now,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Whether a check of `endpoint_id` started at `now` falls into its
+    /// warmup window
+    pub(crate) fn is_warming_up(&self, endpoint_id: Uuid, now: Instant) -> bool {
+        !self.window.is_zero()
+            && self
+                .endpoints
+                .get(&endpoint_id)
+                .is_some_and(|warmup| now.saturating_duration_since(warmup.started) < self.window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn test_new_endpoint_warms_up_for_window() {
+        let mut warmup = Warmup::new(WINDOW, 1);
+        let endpoint = Endpoint::new("10.0.0.1");
+        let now = Instant::now();
+
+        warmup.update_endpoints(std::slice::from_ref(&endpoint), now);
+        assert!(warmup.is_warming_up(endpoint.id, now));
+        assert!(warmup.is_warming_up(endpoint.id, now + Duration::from_secs(29)));
+        assert!(!warmup.is_warming_up(endpoint.id, now + WINDOW));
+    }
+
+    #[test]
+    fn test_reload_restarts_window_of_added_and_changed_endpoints_only() {
+        let mut warmup = Warmup::new(WINDOW, 1);
+        let unchanged = Endpoint::new("10.0.0.1");
+        let mut changed = Endpoint::new("10.0.0.2");
+        let now = Instant::now();
+        let reload = now + Duration::from_secs(60);
+
+        warmup.update_endpoints(&[unchanged.clone(), changed.clone()], now);
+        assert!(!warmup.is_warming_up(changed.id, reload));
+
+        // A file reload edits one endpoint and adds another, keeping the
+        // configuration version
+        changed.address = "10.0.0.3".to_string();
+        let added = Endpoint::new("10.0.0.4");
+        warmup.update_config(WINDOW, 1);
+        warmup.update_endpoints(&[unchanged.clone(), changed.clone(), added.clone()], reload);
+
+        assert!(!warmup.is_warming_up(unchanged.id, reload));
+        assert!(warmup.is_warming_up(changed.id, reload));
+        assert!(warmup.is_warming_up(added.id, reload));
+        assert!(!warmup.is_warming_up(added.id, reload + WINDOW));
+    }
+
+    #[test]
+    fn test_removed_endpoint_is_forgotten() {
+        let mut warmup = Warmup::new(WINDOW, 1);
+        let endpoint = Endpoint::new("10.0.0.1");
+        let now = Instant::now();
+
+        warmup.update_endpoints(std::slice::from_ref(&endpoint), now);
+        warmup.update_endpoints(&[], now);
+        assert!(!warmup.is_warming_up(endpoint.id, now));
+    }
+
+    #[test]
+    fn test_new_config_version_restarts_windows() {
+        let mut warmup = Warmup::new(WINDOW, 1);
+        let endpoints = [Endpoint::new("10.0.0.1")];
+        let now = Instant::now();
+        let later = now + Duration::from_secs(60);
+
+        warmup.update_endpoints(&endpoints, now);
+        warmup.update_config(WINDOW, 1);
+        warmup.update_endpoints(&endpoints, later);
+        assert!(!warmup.is_warming_up(endpoints[0].id, later));
+
+        warmup.update_config(WINDOW, 2);
+        warmup.update_endpoints(&endpoints, later);
+        assert!(warmup.is_warming_up(endpoints[0].id, later));
+    }
+
+    #[test]
+    fn test_zero_window_never_warms_up() {
+        let mut warmup = Warmup::new(Duration::ZERO, 1);
+        let endpoints = [Endpoint::new("10.0.0.1")];
+        let now = Instant::now();
+
+        warmup.update_endpoints(&endpoints, now);
+        assert!(!warmup.is_warming_up(endpoints[0].id, now));
+    }
+}
@@ -0,0 +1,632 @@
+//!
+//! Hand-written extensions of the generated OpenAPI models
+//!
+//! The agent reports fields and check kinds the published spec does not
+//! describe yet. Types defined here replace the generated type of the same
+//! name: this module re-exports everything from `generated::models` and its
+//! own definitions shadow those imports, so `crate::openapi::AgentStatus` is
+//! the extended type while untouched models still come from the generator.
+//!
+//! Keep `generated/` exactly as `just generate-omg` writes it. When the spec
+//! gains one of these fields or types, regenerate and delete the definition
+//! here.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+pub use super::generated::models::*;
+
+/// AgentStatus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStatus {
+    /// Version of the agent
+    pub agent_version: String,
+    /// Version of the agent configuration
+    pub config_version: i64,
+    /// Whether the agent is currently running
+    pub is_running: bool,
+    /// Timestamp when the agent started in UTC (RFC3339), null if never started
+    pub started_at: DateTime<Utc>,
+    /// Timestamp when the agent stopped in UTC (RFC3339), null if running
+    pub stopped_at: Option<DateTime<Utc>>,
+    /// Number of times the agent has been started, persisted across restarts
+    pub restart_count: Option<i64>,
+    /// Seconds the agent has been running when the report was taken, null
+    /// outside reports
+    #[serde(default)]
+    pub uptime_secs: Option<i64>,
+    /// Total number of checks performed by the agent
+    pub checks_performed: i64,
+    /// Number of successful checks
+    pub checks_successful: i64,
+    /// Number of failed checks
+    pub checks_failed: i64,
+    /// Number of failed checks during an endpoint's warmup window, not
+    /// included in `checks_failed`
+    #[serde(default)]
+    pub checks_failed_warmup: i64,
+    /// Number of checks intentionally not run, by reason
+    #[serde(default)]
+    pub checks_skipped: AgentSkippedChecks,
+    /// Timestamp of the last report sent by the agent (RFC3339)
+    pub reported_at: DateTime<Utc>,
+    /// Total number of failed report attempts
+    pub failed_report_count: i64,
+    /// Number of failed report attempts since the last successful report
+    #[serde(default)]
+    pub consecutive_failed_reports: i64,
+    /// Echo requests sent by ping checks
+    #[serde(default)]
+    pub ping_requests_sent: i64,
+    /// Echo requests of ping checks that got no reply
+    #[serde(default)]
+    pub ping_requests_lost: i64,
+    /// Health of every configured endpoint from its latest checks, keyed by
+    /// endpoint ID
+    #[serde(default)]
+    pub per_endpoint: std::collections::HashMap<String, EndpointHealth>,
+    /// Whether the agent is currently connected to the server; turns false
+    /// only after `server.connection_failure_grace` consecutive failures
+    pub server_connected: bool,
+    pub cache_stats: AgentCacheStats,
+}
+
+/// Health of one endpoint from its latest checks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndpointHealth {
+    /// Address of the endpoint
+    pub address: String,
+    /// Whether the latest check succeeded
+    pub last_successful: bool,
+    /// Timestamp of the latest check (RFC3339)
+    pub last_checked_at: DateTime<Utc>,
+    /// Response time of the latest check in milliseconds, if it measured one
+    pub last_latency_ms: Option<f64>,
+    /// Checks that failed since the last successful one
+    pub consecutive_failures: i64,
+    /// Status of the latest check against the endpoint's thresholds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_status: Option<MetricStatus>,
+}
+
+/// Checks intentionally not run, counted per skip reason
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentSkippedChecks {
+    /// Checks skipped because the endpoint is disabled
+    pub disabled: i64,
+    /// Checks skipped because the endpoint's circuit breaker is open
+    pub circuit_open: i64,
+    /// Checks cancelled because the cycle deadline was reached
+    #[serde(default)]
+    pub deadline_exceeded: i64,
+}
+
+/// Why a check was not run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    Disabled,
+    CircuitOpen,
+    DeadlineExceeded,
+}
+
+/// Why a check failed, as far as the agent can tell from the network.
+/// Distinguishes a host that actively rejected the connection from one that
+/// never answered and from a path where a firewall reported the traffic as
+/// administratively prohibited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The target answered but rejected the connection (TCP RST)
+    Refused,
+    /// No answer arrived in time, or the route to the target is down
+    Unreachable,
+    /// A firewall or the local host prohibited the traffic
+    Blocked,
+    /// The target's hostname did not resolve
+    Unresolved,
+    /// The target answered but the check still failed, e.g. on an HTTP
+    /// status or body assertion
+    Other,
+}
+
+/// AgentConfig
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Configuration version (used for syncing with server)
+    pub version: i64,
+    pub agent_id: UUIDv7,
+    /// Human-readable agent name
+    pub agent_name: String,
+    /// Tags for this agent (used for mesh organization)
+    pub tags: Option<Vec<String>>,
+    pub monitoring: MonitoringConfig,
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub self_upgrade: SelfUpgradeConfig,
+    /// Endpoints to monitor
+    pub endpoints: Vec<Endpoint>,
+}
+
+/// SelfUpgradeConfig
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, schemars::JsonSchema)]
+pub struct SelfUpgradeConfig {
+    /// Enables self-upgrade checks when true
+    pub enabled: bool,
+    /// GitHub repository URL for checking latest releases (must be a public repo)
+    #[validate(url)]
+    pub github_repo_url: String,
+    /// Periodic self-upgrade check interval in seconds
+    #[validate(range(min = 60))]
+    pub check_interval_secs: i64,
+}
+
+/// An endpoint to monitor (IP address, hostname, or URL)
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, schemars::JsonSchema)]
+pub struct Endpoint {
+    pub id: UUIDv7,
+    /// IP address, hostname, or URL
+    pub address: String,
+    #[validate(range(min = 1, max = 65535))]
+    pub port: Option<i64>,
+    pub enabled: bool,
+    /// Tags associated with the target
+    pub tags: Vec<String>,
+    /// Successful samples required for a check to succeed, e.g. "2" or "60%";
+    /// overrides the global monitoring setting
+    pub success_quorum: Option<String>,
+    /// Virtual host presented as TLS SNI and HTTP `Host` / gRPC `:authority`
+    /// while connecting to the address' host
+    #[serde(alias = "host_override")]
+    pub server_name: Option<String>,
+    /// Response time in milliseconds above which a result is a warning;
+    /// overrides thresholds inherited from the endpoint's tags
+    pub warning_ms: Option<f64>,
+    /// Response time in milliseconds above which a result is critical;
+    /// overrides thresholds inherited from the endpoint's tags
+    pub critical_ms: Option<f64>,
+    /// Substring the HTTP response body must contain for the check to succeed
+    pub body_must_contain: Option<String>,
+    /// Regular expression the HTTP response body must match for the check to succeed
+    pub body_regex: Option<String>,
+    /// Request method, headers, accepted status codes and redirect handling
+    /// of the HTTP check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpCheckConfig>,
+    /// Days before expiry from which a TLS certificate check fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(range(min = 0))]
+    pub warn_days: Option<i64>,
+    /// Record type queried by a DNS check; A if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<DnsRecordType>,
+    /// Answers a DNS check must return, all of them, to succeed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expected_answers: Vec<String>,
+    /// Seconds between checks of this endpoint; overrides the global
+    /// monitoring interval
+    #[validate(range(min = 1))]
+    pub interval_secs: Option<i64>,
+    /// Check to run against a plain address; absent selects it from the
+    /// address (a ping, or the kind of a URL's scheme)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_type: Option<EndpointCheckType>,
+    /// Name of the registered plugin that checks this endpoint instead of
+    /// a built-in check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+}
+
+/// Request settings of an endpoint's HTTP check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct HttpCheckConfig {
+    /// Request method; GET if absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Headers sent with the request, e.g. `Authorization`
+    #[serde(skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub headers: std::collections::HashMap<String, String>,
+    /// Status codes counted as success; any 2xx status if empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expected_status: Vec<u16>,
+    /// Whether redirects are followed (up to 10); a redirect response is
+    /// checked as-is otherwise
+    pub follow_redirects: bool,
+}
+
+/// Check run against an endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointCheckType {
+    /// ICMP echo to the host
+    Ping,
+    /// TCP connection to the endpoint's port
+    Tcp,
+    /// UDP probe datagram to the endpoint's port
+    Udp,
+    /// HTTP GET of `/` on the host
+    Http,
+    /// gRPC health check of the host
+    Grpc,
+    /// Expiry check of the TLS certificate the host presents
+    TlsCert,
+    /// DNS query for records of the host name
+    Dns,
+}
+
+/// DNS record type queried by a DNS check
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema,
+)]
+pub enum DnsRecordType {
+    /// IPv4 address
+    #[default]
+    A,
+    /// IPv6 address
+    #[serde(rename = "AAAA")]
+    Aaaa,
+    /// Mail exchanger
+    #[serde(rename = "MX")]
+    Mx,
+    /// Text record
+    #[serde(rename = "TXT")]
+    Txt,
+    /// Canonical name
+    #[serde(rename = "CNAME")]
+    Cname,
+}
+
+/// MonitoringResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitoringResult {
+    pub id: UUIDv7,
+    pub agent_id: UUIDv7,
+    pub endpoint_id: UUIDv7,
+    pub check_type: CheckType,
+    /// Timestamp when the report was generated (RFC3339)
+    pub timestamp: DateTime<Utc>,
+    /// Whether the check ran during the endpoint's warmup window. Failures
+    /// during warmup are excluded from alerting and success rates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warmup: Option<bool>,
+    /// Whether the check needs attention regardless of its outcome, e.g.
+    /// because it exceeded its latency budget. `notes` explains why.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<bool>,
+    /// Why the check failed; only set on failed results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_kind: Option<FailureKind>,
+    /// Status of the check against the endpoint's thresholds; set once the
+    /// result is collected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<MetricStatus>,
+    /// Human-readable remarks about the check, such as a latency budget breach
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// A batch of monitoring results submitted by an agent from its local cache.
+/// The server deduplicates entries by `MonitoringResult.id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchMonitoringResults {
+    /// Ordered list of monitoring results (oldest-first)
+    pub results: Vec<MonitoringResult>,
+}
+
+/// CheckType (oneOf)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CheckType {
+    PingCheck(PingCheck),
+    TracerouteCheck(TracerouteCheck),
+    TcpConnectCheck(TcpConnectCheck),
+    UdpConnectCheck(UdpConnectCheck),
+    HttpGetCheck(HttpGetCheck),
+    GrpcHealthCheck(GrpcHealthCheck),
+    TlsCertCheck(TlsCertCheck),
+    DnsCheck(DnsCheck),
+    PluginCheck(PluginCheck),
+}
+
+/// GrpcHealthCheckType
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GrpcHealthCheckType {
+    #[serde(rename = "grpchealth")]
+    Grpchealth,
+}
+
+/// TlsCertCheckType
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TlsCertCheckType {
+    #[serde(rename = "tlscert")]
+    Tlscert,
+}
+
+/// DnsCheckType
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DnsCheckType {
+    #[serde(rename = "dns")]
+    Dns,
+}
+
+/// PingCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingCheck {
+    #[serde(rename = "type")]
+    pub r#type: PingCheckType,
+    pub result: PingResult,
+}
+
+/// PingResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingResult {
+    /// Resolved IP address of the target
+    pub resolved_ip: String,
+    pub successes: i64,
+    pub failures: i64,
+    pub success_latencies: Vec<f64>,
+    pub error_details: Option<ErrorDetails>,
+    /// Time spent resolving the target hostname in milliseconds; absent for IP literals
+    pub dns_resolution_ms: Option<f64>,
+    /// Replies required for the check to succeed; absent means one reply
+    pub required_successes: Option<i64>,
+    /// PTR hostname of `resolved_ip`; absent unless reverse DNS is enabled and a PTR record exists
+    pub resolved_hostname: Option<String>,
+    /// Share of echo requests without a reply, 0-100
+    pub packet_loss_percent: Option<f64>,
+    /// Standard deviation of `success_latencies` in milliseconds; absent with fewer than two replies
+    pub jitter_ms: Option<f64>,
+}
+
+/// TracerouteCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracerouteCheck {
+    #[serde(rename = "type")]
+    pub r#type: TracerouteCheckType,
+    pub result: TracerouteResult,
+}
+
+/// TracerouteResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracerouteResult {
+    pub hops: Vec<TracerouteHop>,
+    pub target_reached: bool,
+    pub error_details: Option<ErrorDetails>,
+}
+
+/// TracerouteHop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracerouteHop {
+    pub hop: i64,
+    /// Resolved IP address of the target
+    pub resolved_ip: Option<String>,
+    pub success_latencies: Option<Vec<f64>>,
+    pub hostname: Option<String>,
+    /// Lowest RTT of the hop's answered probes in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_latency_ms: Option<f64>,
+    /// Median RTT of the hop's answered probes in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub median_latency_ms: Option<f64>,
+    /// Whether any probe of this hop was answered
+    #[serde(default)]
+    pub responsive: bool,
+}
+
+/// TcpConnectCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpConnectCheck {
+    #[serde(rename = "type")]
+    pub r#type: TcpConnectCheckType,
+    pub result: TcpConnectResult,
+}
+
+/// TcpConnectResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpConnectResult {
+    pub connected: bool,
+    pub connect_time_ms: Option<f64>,
+    pub error_details: Option<ErrorDetails>,
+    pub resolved_ip: String,
+    /// Time spent resolving the target hostname in milliseconds; absent for IP literals
+    pub dns_resolution_ms: Option<f64>,
+}
+
+/// UdpConnectCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UdpConnectCheck {
+    #[serde(rename = "type")]
+    pub r#type: UdpConnectCheckType,
+    pub result: UdpConnectResult,
+}
+
+/// UdpConnectResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UdpConnectResult {
+    pub probe_successful: bool,
+    pub response_time_ms: Option<f64>,
+    pub error_details: Option<ErrorDetails>,
+    pub resolved_ip: String,
+    /// Time spent resolving the target hostname in milliseconds; absent for IP literals
+    pub dns_resolution_ms: Option<f64>,
+}
+
+/// HttpGetCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpGetCheck {
+    #[serde(rename = "type")]
+    pub r#type: HttpGetCheckType,
+    pub result: HttpGetResult,
+}
+
+/// HttpGetResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpGetResult {
+    pub status_code: i64,
+    pub response_time_ms: Option<f64>,
+    /// Time spent resolving the URL's hostname in milliseconds; absent for IP literals
+    pub dns_resolution_ms: Option<f64>,
+    pub response_size_bytes: Option<i64>,
+    /// Whether the body was cut off at the configured maximum read size;
+    /// `response_size_bytes` then counts only the bytes read
+    pub body_truncated: Option<bool>,
+    /// Whether the body satisfied the endpoint's body assertions; absent
+    /// when the endpoint has none
+    pub body_matched: Option<bool>,
+    /// What the body assertions found, e.g. `body contains "ok"`
+    pub body_match_detail: Option<String>,
+    pub error_details: Option<ErrorDetails>,
+    pub success: bool,
+}
+
+/// GrpcHealthCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcHealthCheck {
+    #[serde(rename = "type")]
+    pub r#type: GrpcHealthCheckType,
+    pub result: GrpcHealthResult,
+}
+
+/// GrpcHealthResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcHealthResult {
+    /// Service name sent in the health check request (empty for the whole server)
+    pub service: String,
+    /// Serving status reported by the server (e.g. SERVING, NOT_SERVING)
+    pub serving_status: Option<String>,
+    pub response_time_ms: Option<f64>,
+    pub error_details: Option<ErrorDetails>,
+    pub success: bool,
+}
+
+/// TlsCertCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsCertCheck {
+    #[serde(rename = "type")]
+    pub r#type: TlsCertCheckType,
+    pub result: TlsCertResult,
+}
+
+/// TlsCertResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsCertResult {
+    /// Whether the certificate is within its validity period and further
+    /// than the endpoint's `warn_days` from expiry
+    pub valid: bool,
+    /// Whole days until the certificate expires, negative once it expired
+    pub days_until_expiry: Option<i64>,
+    /// When the certificate expires (RFC3339)
+    pub not_after: Option<DateTime<Utc>>,
+    /// Subject distinguished name of the server certificate
+    pub subject: Option<String>,
+    /// Issuer distinguished name of the server certificate
+    pub issuer: Option<String>,
+    /// Time to connect and complete the TLS handshake in milliseconds
+    pub handshake_time_ms: Option<f64>,
+    pub error_details: Option<ErrorDetails>,
+    pub resolved_ip: String,
+    /// Time spent resolving the target hostname in milliseconds; absent for IP literals
+    pub dns_resolution_ms: Option<f64>,
+}
+
+/// DnsCheck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsCheck {
+    #[serde(rename = "type")]
+    pub r#type: DnsCheckType,
+    pub result: DnsResult,
+}
+
+/// DnsResult
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsResult {
+    /// Name queried
+    pub name: String,
+    /// Record type queried
+    pub record_type: DnsRecordType,
+    /// Nameserver queried (`ip:port`); absent for the agent's resolver
+    pub nameserver: Option<String>,
+    /// Records of the queried type in the answer, e.g. `192.0.2.1` or
+    /// `10 mail.example.com`
+    pub answers: Vec<String>,
+    /// Time until the answer arrived in milliseconds
+    pub resolve_time_ms: Option<f64>,
+    /// Whether the query was answered with records of the queried type,
+    /// including every expected answer
+    pub success: bool,
+    pub error_details: Option<ErrorDetails>,
+}
+
+/// AgentHeartbeat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentHeartbeat {
+    /// Agent-local timestamp when the heartbeat was generated (RFC3339)
+    pub timestamp: DateTime<Utc>,
+    pub health_status: AgentHealthStatus,
+    pub metrics: AgentMetrics,
+    pub agent_status: AgentStatus,
+}
+
+/// Capabilities probed by the agent at startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentCapabilities {
+    /// An ICMP socket could be opened, so ping checks can run
+    pub icmp_available: bool,
+    /// The server hostname could be resolved
+    pub dns_works: bool,
+    /// A file could be written to the cache directory
+    pub cache_writable: bool,
+}
+
+/// AgentDiagnostics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentDiagnostics {
+    /// Agent-local timestamp when the diagnostics were collected (RFC3339)
+    pub timestamp: DateTime<Utc>,
+    pub agent_version: String,
+    /// Operating system, e.g. `linux` or `windows`
+    pub os: String,
+    /// CPU architecture, e.g. `x86_64` or `aarch64`
+    pub arch: String,
+    pub capabilities: AgentCapabilities,
+    /// Effective configuration with secrets replaced by `"[redacted]"`
+    pub config: serde_json::Value,
+}
+
+/// ServerHealth
+///
+/// Response of `GET /health`: server liveness and the API versions it speaks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerHealth {
+    /// Liveness, `ok` when the server is serving requests
+    pub status: String,
+    /// Semantic version of the agent API, e.g. `1.4.0`
+    pub api_version: String,
+    /// Version of the result/status report schema the server accepts
+    pub report_schema_version: i64,
+}
+
+/// ReportAcknowledgment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportAcknowledgment {
+    pub status: ReportAckStatus,
+    /// Latest configuration version the server holds for the agent
+    pub configuration_version: i64,
+    /// Whether the server holds a newer configuration than the agent reported
+    pub update_available: Option<bool>,
+}
+
+/// Status of a monitoring check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MetricStatus {
+    #[serde(rename = "reachable")]
+    Reachable,
+    #[serde(rename = "unreachable")]
+    Unreachable,
+    #[serde(rename = "degraded")]
+    Degraded,
+    #[serde(rename = "unknown")]
+    Unknown,
+}
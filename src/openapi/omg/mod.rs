@@ -3,9 +3,12 @@
 #[allow(clippy::enum_variant_names)]
 mod generated;
 
+#[allow(dead_code)]
+mod extensions;
+
 #[allow(dead_code)]
 mod responses;
 
-pub use generated::models::*;
+pub use extensions::*;
 #[allow(unused_imports)]
 pub use responses::*;
@@ -36,7 +36,7 @@
 //!
 //! See [docs/OPENAPI_CODE_GENERATION.md] for more details.
 
-use super::generated::models::{AgentCacheStats, AgentStatus, Error};
+use super::extensions::{AgentCacheStats, AgentSkippedChecks, AgentStatus, Error, SkipReason};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -78,11 +78,19 @@ impl Default for AgentStatus {
             is_running: false,
             started_at: DateTime::<Utc>::UNIX_EPOCH,
             stopped_at: None,
+            restart_count: None,
+            uptime_secs: None,
             checks_performed: 0,
             checks_successful: 0,
             checks_failed: 0,
+            checks_failed_warmup: 0,
+            checks_skipped: AgentSkippedChecks::default(),
             reported_at: DateTime::<Utc>::UNIX_EPOCH,
             failed_report_count: 0,
+            consecutive_failed_reports: 0,
+            ping_requests_sent: 0,
+            ping_requests_lost: 0,
+            per_endpoint: Default::default(),
             server_connected: false,
             cache_stats: AgentCacheStats {
                 len: 0,
@@ -98,6 +106,15 @@ impl AgentStatus {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Count a check that was not run for `reason`
+    pub fn record_skip(&mut self, reason: SkipReason) {
+        match reason {
+            SkipReason::Disabled => self.checks_skipped.disabled += 1,
+            SkipReason::CircuitOpen => self.checks_skipped.circuit_open += 1,
+            SkipReason::DeadlineExceeded => self.checks_skipped.deadline_exceeded += 1,
+        }
+    }
 }
 
 // Example for a new endpoint:
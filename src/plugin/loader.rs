@@ -0,0 +1,247 @@
+//! Dynamic plugin loading from plugin directories
+//!
+//! `plugins.dirs` lists directories holding plugin libraries (`.so` on
+//! Linux, `.dylib` on macOS, `.dll` on Windows). Directories are scanned in
+//! the configured order and the files of one directory in file name order.
+//! A plugin is named after its file, without a `lib` prefix and the
+//! extension, so `libdns_probe.so` provides the plugin `dns_probe`.
+//!
+//! When two directories provide a plugin of the same name, the one found
+//! later wins and a warning is logged. Listing system plugins before user
+//! plugins therefore lets users override system plugins by name.
+//!
+//! A plugin library exports a function named [`PLUGIN_ENTRY_SYMBOL`] with the
+//! [`PluginCreate`] signature:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub fn smotra_plugin_create() -> Box<dyn smotra::MonitoringPlugin> {
+//!     Box::new(MyPlugin::default())
+//! }
+//! ```
+//!
+//! The Rust ABI is not stable, so a plugin must be built with the same
+//! compiler and `smotra` version as the agent loading it.
+
+use crate::error::{Error, Result};
+use crate::plugin::MonitoringPlugin;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Name of the function every plugin library exports
+pub const PLUGIN_ENTRY_SYMBOL: &str = "smotra_plugin_create";
+
+/// Signature of [`PLUGIN_ENTRY_SYMBOL`]
+pub type PluginCreate = fn() -> Box<dyn MonitoringPlugin>;
+
+/// A plugin library selected for loading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PluginFile {
+    /// Plugin name derived from the file name
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+}
+
+/// Plugin libraries in `dirs`, in load order, one per plugin name.
+///
+/// A directory that does not exist is skipped with a warning; any other
+/// error reading a directory is returned.
+pub(crate) fn discover_plugins<P: AsRef<Path>>(dirs: &[P]) -> Result<Vec<PluginFile>> {
+    let mut plugins: Vec<PluginFile> = Vec::new();
+
+    for dir in dirs {
+        let dir = dir.as_ref();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                warn!(
+                    "Plugin directory {} does not exist, skipping",
+                    dir.display()
+                );
+                continue;
+            }
+            Err(e) => {
+                return Err(Error::Plugin(format!(
+                    "Cannot read plugin directory {}: {}",
+                    dir.display(),
+                    e
+                )))
+            }
+        };
+
+        let mut paths = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.sort();
+
+        for path in paths {
+            let Some(name) = plugin_name(&path) else {
+                continue;
+            };
+
+            if let Some(index) = plugins.iter().position(|plugin| plugin.name == name) {
+                let replaced = plugins.remove(index);
+                warn!(
+                    "Plugin {} from {} overrides the one from {}",
+                    name,
+                    path.display(),
+                    replaced.path.display()
+                );
+            }
+            debug!("Found plugin {} at {}", name, path.display());
+            plugins.push(PluginFile { name, path });
+        }
+    }
+
+    Ok(plugins)
+}
+
+/// Plugin name of a library file, `None` if `path` is not a plugin library
+fn plugin_name(path: &Path) -> Option<String> {
+    if !path.is_file() || path.extension()? != std::env::consts::DLL_EXTENSION {
+        return None;
+    }
+
+    let stem = path.file_stem()?.to_str()?;
+    let name = stem.strip_prefix("lib").unwrap_or(stem);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Load the plugin library at `path`.
+///
+/// The returned library must outlive the plugin.
+pub(crate) fn load_plugin(path: &Path) -> Result<(libloading::Library, Box<dyn MonitoringPlugin>)> {
+    let error = |e: libloading::Error| {
+        Error::Plugin(format!("Cannot load plugin {}: {}", path.display(), e))
+    };
+
+    // SAFETY: loading a library runs its initializers. Plugin directories are
+    // configured by the operator, who vouches for the libraries in them.
+    let library = unsafe { libloading::Library::new(path) }.map_err(error)?;
+    // SAFETY: the plugin contract requires PLUGIN_ENTRY_SYMBOL to have the
+    // `PluginCreate` signature.
+    let create =
+        unsafe { library.get::<PluginCreate>(PLUGIN_ENTRY_SYMBOL.as_bytes()) }.map_err(error)?;
+    let plugin = create();
+
+    Ok((library, plugin))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::fmt;
+    use std::fs;
+    use std::sync::Arc;
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Level, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    #[derive(Default)]
+    struct Message(String);
+
+    impl Visit for Message {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    /// Layer recording the message of every warning
+    #[derive(Clone, Default)]
+    pub(crate) struct WarningCapture(Arc<Mutex<Vec<String>>>);
+
+    impl WarningCapture {
+        pub(crate) fn warnings(&self) -> Vec<String> {
+            self.0.lock().clone()
+        }
+
+        /// Record warnings on the current thread until the guard is dropped
+        pub(crate) fn install(&self) -> tracing::subscriber::DefaultGuard {
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(self.clone()))
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for WarningCapture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            if *event.metadata().level() == Level::WARN {
+                let mut message = Message::default();
+                event.record(&mut message);
+                self.0.lock().push(message.0);
+            }
+        }
+    }
+
+    /// Create an (empty) plugin library fixture `{stem}.{DLL_EXTENSION}` in `dir`
+    fn fixture(dir: &Path, stem: &str) -> PathBuf {
+        let path = dir.join(format!("{}.{}", stem, std::env::consts::DLL_EXTENSION));
+        fs::write(&path, b"").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_later_directory_wins_with_warning() {
+        let system = tempfile::tempdir().unwrap();
+        let user = tempfile::tempdir().unwrap();
+        fixture(system.path(), "libdns_probe");
+        fixture(system.path(), "libsmtp_probe");
+        let user_dns = fixture(user.path(), "libdns_probe");
+
+        let capture = WarningCapture::default();
+        let _guard = capture.install();
+        let plugins = discover_plugins(&[system.path(), user.path()]).unwrap();
+
+        let names: Vec<_> = plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["smtp_probe", "dns_probe"]);
+        assert_eq!(plugins[1].path, user_dns, "the later directory wins");
+
+        let warnings = capture.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("dns_probe"));
+        assert!(warnings[0].contains(&user_dns.display().to_string()));
+    }
+
+    #[test]
+    fn test_directories_and_files_load_in_order() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fixture(first.path(), "b_probe");
+        fixture(first.path(), "a_probe");
+        fixture(second.path(), "0_probe");
+        fs::write(first.path().join("README.txt"), b"not a plugin").unwrap();
+        fs::create_dir(
+            first
+                .path()
+                .join(format!("dir.{}", std::env::consts::DLL_EXTENSION)),
+        )
+        .unwrap();
+
+        let plugins = discover_plugins(&[first.path(), second.path()]).unwrap();
+
+        let names: Vec<_> = plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["a_probe", "b_probe", "0_probe"]);
+    }
+
+    #[test]
+    fn test_missing_directory_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        fixture(dir.path(), "probe");
+
+        let plugins =
+            discover_plugins(&[dir.path().join("missing").as_path(), dir.path()]).unwrap();
+        assert_eq!(plugins.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_library_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path(), "broken");
+
+        let err = load_plugin(&path).err().unwrap();
+        assert!(err.to_string().contains("Cannot load plugin"));
+    }
+}
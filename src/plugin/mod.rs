@@ -1,7 +1,12 @@
 //! Plugin system for extending agent functionality
 
+mod loader;
 mod registry;
 mod trait_def;
 
-pub use registry::PluginRegistry;
+pub use loader::{PluginCreate, PLUGIN_ENTRY_SYMBOL};
+pub use registry::{PluginInitPolicy, PluginRegistry};
 pub use trait_def::MonitoringPlugin;
+
+#[cfg(test)]
+pub(crate) use loader::tests::WarningCapture;
@@ -1,26 +1,90 @@
 //! Plugin registry for managing plugins
 
+use super::loader::{discover_plugins, load_plugin};
 use crate::error::Result;
 use crate::plugin::MonitoringPlugin;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// What `PluginRegistry::initialize_all` does when a plugin fails to initialize
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginInitPolicy {
+    /// Stop at the first failure and return its error
+    #[default]
+    #[serde(alias = "fail")]
+    FailFast,
+
+    /// Log the failure, remove the plugin from the registry and continue
+    #[serde(alias = "skip")]
+    SkipFailed,
+}
 
 /// Plugin registry for managing plugins
 #[derive(Default)]
 pub struct PluginRegistry {
     plugins: Vec<Box<dyn MonitoringPlugin>>,
+    init_policy: PluginInitPolicy,
+    /// Libraries of dynamically loaded plugins. Declared after `plugins` so
+    /// the plugins are dropped before their code is unloaded.
+    libraries: Vec<libloading::Library>,
 }
 
 impl PluginRegistry {
     pub fn new() -> Self {
         Self {
             plugins: Vec::new(),
+            init_policy: PluginInitPolicy::default(),
+            libraries: Vec::new(),
         }
     }
 
+    /// Handle plugin initialization failures according to `policy`
+    pub fn with_init_policy(mut self, policy: PluginInitPolicy) -> Self {
+        self.init_policy = policy;
+        self
+    }
+
     /// Register a new plugin
+    ///
+    /// A plugin with the same name as an already registered one replaces it
+    /// (last wins) and a warning is logged.
     pub fn register(&mut self, plugin: Box<dyn MonitoringPlugin>) {
+        if let Some(index) = self.plugins.iter().position(|p| p.name() == plugin.name()) {
+            let replaced = self.plugins.remove(index);
+            warn!(
+                "Plugin {} v{} replaces previously registered v{}",
+                plugin.name(),
+                plugin.version(),
+                replaced.version()
+            );
+        }
         self.plugins.push(plugin);
     }
 
+    /// Load and register the plugin libraries found in `dirs`
+    ///
+    /// Directories are loaded in order; a plugin from a later directory
+    /// overrides a same-named plugin from an earlier one (see
+    /// `plugin::loader`). Returns the number of plugins loaded.
+    pub fn load_dirs<P: AsRef<Path>>(&mut self, dirs: &[P]) -> Result<usize> {
+        let files = discover_plugins(dirs)?;
+        for file in &files {
+            let (library, plugin) = load_plugin(&file.path)?;
+            info!(
+                "Loaded plugin {} v{} from {}",
+                plugin.name(),
+                plugin.version(),
+                file.path.display()
+            );
+            self.register(plugin);
+            self.libraries.push(library);
+        }
+        Ok(files.len())
+    }
+
     /// Get a plugin by name
     pub fn get(&self, name: &str) -> Option<&dyn MonitoringPlugin> {
         self.plugins
@@ -38,10 +102,36 @@ impl PluginRegistry {
     }
 
     /// Initialize all plugins
+    ///
+    /// Under [`PluginInitPolicy::FailFast`] the first error is returned and
+    /// the remaining plugins are left uninitialized. Under
+    /// [`PluginInitPolicy::SkipFailed`] plugins that fail are removed from
+    /// the registry and the others are still initialized.
     pub async fn initialize_all(&mut self) -> Result<()> {
-        for plugin in &mut self.plugins {
-            plugin.initialize().await?;
+        let mut initialized = Vec::with_capacity(self.plugins.len());
+        let mut pending = std::mem::take(&mut self.plugins).into_iter();
+
+        while let Some(mut plugin) = pending.next() {
+            match plugin.initialize().await {
+                Ok(()) => initialized.push(plugin),
+                Err(e) if self.init_policy == PluginInitPolicy::SkipFailed => {
+                    warn!(
+                        "Skipping plugin {} v{}: initialization failed: {}",
+                        plugin.name(),
+                        plugin.version(),
+                        e
+                    );
+                }
+                Err(e) => {
+                    initialized.push(plugin);
+                    initialized.extend(pending);
+                    self.plugins = initialized;
+                    return Err(e);
+                }
+            }
         }
+
+        self.plugins = initialized;
         Ok(())
     }
 
@@ -53,3 +143,145 @@ impl PluginRegistry {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Endpoint, MonitoringResult};
+    use crate::error::Error;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct TestPlugin {
+        name: &'static str,
+        fails: bool,
+        initialized: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl MonitoringPlugin for TestPlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        async fn check(
+            &self,
+            _agent_id: &uuid::Uuid,
+            _endpoint: &Endpoint,
+        ) -> Result<MonitoringResult> {
+            Err(Error::Plugin("not implemented".to_string()))
+        }
+
+        async fn initialize(&mut self) -> Result<()> {
+            if self.fails {
+                return Err(Error::Plugin(format!("{} failed to start", self.name)));
+            }
+            self.initialized.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Registry of a healthy, a failing and another healthy plugin, with
+    /// flags telling whether the healthy ones were initialized
+    fn registry(policy: PluginInitPolicy) -> (PluginRegistry, Arc<AtomicBool>, Arc<AtomicBool>) {
+        let first = Arc::new(AtomicBool::new(false));
+        let last = Arc::new(AtomicBool::new(false));
+        let mut registry = PluginRegistry::new().with_init_policy(policy);
+        for (name, fails, initialized) in [
+            ("first", false, &first),
+            ("broken", true, &Arc::new(AtomicBool::new(false))),
+            ("last", false, &last),
+        ] {
+            registry.register(Box::new(TestPlugin {
+                name,
+                fails,
+                initialized: Arc::clone(initialized),
+            }));
+        }
+        (registry, first, last)
+    }
+
+    fn names(registry: &PluginRegistry) -> Vec<&str> {
+        registry.list().into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_aborts_on_first_failure() {
+        let (mut registry, first, last) = registry(PluginInitPolicy::FailFast);
+
+        let err = registry.initialize_all().await.unwrap_err();
+
+        assert!(err.to_string().contains("broken failed to start"));
+        assert!(first.load(Ordering::SeqCst));
+        assert!(
+            !last.load(Ordering::SeqCst),
+            "later plugins stay uninitialized"
+        );
+        assert_eq!(names(&registry), ["first", "broken", "last"]);
+    }
+
+    #[tokio::test]
+    async fn test_skip_failed_removes_failed_plugin_and_continues() {
+        let (mut registry, first, last) = registry(PluginInitPolicy::SkipFailed);
+
+        registry.initialize_all().await.unwrap();
+
+        assert!(first.load(Ordering::SeqCst));
+        assert!(last.load(Ordering::SeqCst));
+        assert_eq!(names(&registry), ["first", "last"]);
+        assert!(registry.get("broken").is_none());
+    }
+
+    #[test]
+    fn test_register_same_name_last_wins_with_warning() {
+        use crate::plugin::loader::tests::WarningCapture;
+
+        let capture = WarningCapture::default();
+        let _guard = capture.install();
+        let system = Arc::new(AtomicBool::new(false));
+        let user = Arc::new(AtomicBool::new(false));
+        let mut registry = PluginRegistry::new();
+        for (name, initialized) in [("probe", &system), ("other", &system), ("probe", &user)] {
+            registry.register(Box::new(TestPlugin {
+                name,
+                fails: false,
+                initialized: Arc::clone(initialized),
+            }));
+        }
+
+        assert_eq!(names(&registry), ["other", "probe"]);
+        let warnings = capture.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("probe"));
+    }
+
+    #[tokio::test]
+    async fn test_load_dirs_without_plugins_registers_nothing() {
+        let empty = tempfile::tempdir().unwrap();
+        let mut registry = PluginRegistry::new();
+
+        assert_eq!(registry.load_dirs(&[empty.path()]).unwrap(), 0);
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn test_policy_names() {
+        assert_eq!(
+            PluginRegistry::new().init_policy,
+            PluginInitPolicy::FailFast
+        );
+        assert_eq!(
+            serde_json::to_string(&PluginInitPolicy::SkipFailed).unwrap(),
+            "\"skip-failed\""
+        );
+        assert_eq!(
+            serde_json::from_str::<PluginInitPolicy>("\"fail-fast\"").unwrap(),
+            PluginInitPolicy::FailFast
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! HTTP client shared by the status reporter, the result reporter and the
+//! heartbeat
+//!
+//! Every `reqwest::Client` has its own connection pool, so a client built
+//! per request opens a new connection (and TLS handshake) each time. The
+//! agent builds one client from the `[server]` settings it depends on,
+//! `timeout_secs` and `verify_tls`, and shares it. A hot reload that changes
+//! either setting replaces the client on the next request; other changes,
+//! such as the URL or API key, keep it.
+
+use crate::agent_config::ServerConfig;
+use crate::error::Result;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Settings a built client depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ClientSettings {
+    timeout: Duration,
+    verify_tls: bool,
+}
+
+impl ClientSettings {
+    fn of(server: &ServerConfig) -> Self {
+        Self {
+            timeout: server.timeout(),
+            verify_tls: server.verify_tls,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    client: Option<(ClientSettings, reqwest::Client)>,
+    builds: usize,
+}
+
+/// Lazily built `reqwest::Client` for requests to the server
+///
+/// Clones share the same client and connection pool.
+#[derive(Debug, Clone, Default)]
+pub struct ServerClient {
+    state: Arc<Mutex<State>>,
+}
+
+impl ServerClient {
+    /// Client for `server`, built on first use and whenever its timeout or
+    /// TLS verification changed since
+    pub fn get(&self, server: &ServerConfig) -> Result<reqwest::Client> {
+        let settings = ClientSettings::of(server);
+        let mut state = self.state.lock();
+        if let Some((built_for, client)) = &state.client {
+            if *built_for == settings {
+                return Ok(client.clone());
+            }
+            debug!("Server connection settings changed, rebuilding HTTP client");
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(settings.timeout)
+            .danger_accept_invalid_certs(!settings.verify_tls)
+            .build()?;
+        state.client = Some((settings, client.clone()));
+        state.builds += 1;
+        Ok(client)
+    }
+
+    /// Number of clients built so far
+    #[cfg(test)]
+    pub(crate) fn builds(&self) -> usize {
+        self.state.lock().builds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{CertificateParams, KeyPair};
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::crypto::ring;
+    use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+    use tokio_rustls::TlsAcceptor;
+
+    fn server_config(url: &str) -> ServerConfig {
+        ServerConfig {
+            url: url.to_string(),
+            ..ServerConfig::default()
+        }
+    }
+
+    /// Answer every HTTPS request with 204, using a self-signed certificate
+    async fn serve_self_signed() -> SocketAddr {
+        let key = KeyPair::generate().unwrap();
+        let certificate = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let config = tokio_rustls::rustls::ServerConfig::builder_with_provider(Arc::new(
+            ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![certificate.der().clone()],
+            PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+        )
+        .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Ok(mut stream) = acceptor.accept(stream).await {
+                        let mut buf = [0u8; 4096];
+                        let _ = stream.read(&mut buf).await;
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                            .await;
+                        let _ = stream.shutdown().await;
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_client_is_built_once_and_reused() {
+        let client = ServerClient::default();
+        let shared = client.clone();
+        let mut server = server_config("https://api.example.com");
+
+        client.get(&server).unwrap();
+        shared.get(&server).unwrap();
+        server.url = "https://other.example.com".to_string();
+        server.api_key = Some("new-key".to_string());
+        client.get(&server).unwrap();
+        assert_eq!(client.builds(), 1, "clones share one client");
+
+        server.timeout_secs += 1;
+        client.get(&server).unwrap();
+        client.get(&server).unwrap();
+        assert_eq!(client.builds(), 2, "a new timeout rebuilds the client once");
+
+        server.verify_tls = !server.verify_tls;
+        shared.get(&server).unwrap();
+        assert_eq!(client.builds(), 3, "a new TLS setting rebuilds the client");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_is_honored() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+
+        let mut server = server_config(&format!("http://{}", addr));
+        server.timeout_secs = 1;
+        let client = ServerClient::default().get(&server).unwrap();
+
+        let started = std::time::Instant::now();
+        let err = client.get(&server.url).send().await.unwrap_err();
+        assert!(err.is_timeout(), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_tls_verification_is_honored() {
+        let addr = serve_self_signed().await;
+        let url = format!("https://localhost:{}/", addr.port());
+        let mut server = server_config(&url);
+        let client = ServerClient::default();
+
+        server.verify_tls = true;
+        let verified = client.get(&server).unwrap().get(&url).send().await;
+        assert!(
+            verified.is_err(),
+            "self-signed certificate must be rejected"
+        );
+
+        server.verify_tls = false;
+        let unverified = client.get(&server).unwrap().get(&url).send().await.unwrap();
+        assert_eq!(unverified.status().as_u16(), 204);
+    }
+}
@@ -0,0 +1,121 @@
+//! Gzip-compressed JSON request bodies
+//!
+//! With `server.compress_reports = true` the JSON bodies of status reports,
+//! result batches and bucketed reports are gzip-compressed and sent with
+//! `Content-Encoding: gzip`. The server has no way to advertise support, so
+//! compression stays off unless the operator enables it for a server known
+//! to accept it.
+
+use crate::agent_config::Config;
+use crate::error::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::RequestBuilder;
+use serde::Serialize;
+use std::io::Write;
+
+/// Set `payload` as the JSON body of `request`, gzip-compressed if
+/// `server.compress_reports` is enabled
+pub(crate) fn json_body<T: Serialize + ?Sized>(
+    config: &Config,
+    request: RequestBuilder,
+    payload: &T,
+) -> Result<RequestBuilder> {
+    if !config.server.compress_reports {
+        return Ok(request.json(payload));
+    }
+
+    let body = gzip(&serde_json::to_vec(payload)?)?;
+    Ok(request
+        .header(CONTENT_TYPE, "application/json")
+        .header(CONTENT_ENCODING, "gzip")
+        .body(body))
+}
+
+/// Gzip-compress `bytes`
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::AgentStatus;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn gunzip(bytes: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut decoded).unwrap();
+        decoded
+    }
+
+    #[test]
+    fn test_compressed_report_round_trips() {
+        let mut status = AgentStatus::new();
+        status.checks_performed = 1200;
+        status.checks_failed = 7;
+        let json = serde_json::to_vec(&status).unwrap();
+
+        let compressed = gzip(&json).unwrap();
+        assert_eq!(&compressed[..2], &[0x1f, 0x8b], "gzip magic bytes");
+        assert_eq!(gunzip(&compressed), json);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_body_is_sent_with_content_encoding() {
+        let mut config = Config::default();
+        config.server.compress_reports = true;
+        let status = AgentStatus::new();
+        let json = serde_json::to_vec(&status).unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/report")
+            .match_header("content-encoding", "gzip")
+            .match_header("content-type", "application/json")
+            .match_request(move |req| gunzip(req.body().unwrap()) == json)
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let request = reqwest::Client::new().post(format!("{}/report", server.url()));
+        let response = json_body(&config, request, &status)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        assert!(response.status().is_success());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_body_is_plain_json_by_default() {
+        let config = Config::default();
+        let status = AgentStatus::new();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/report")
+            .match_header("content-encoding", mockito::Matcher::Missing)
+            .match_body(mockito::Matcher::Json(
+                serde_json::to_value(&status).unwrap(),
+            ))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let request = reqwest::Client::new().post(format!("{}/report", server.url()));
+        json_body(&config, request, &status)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+}
@@ -1,13 +1,17 @@
 //! Heartbeat reporting to central server
 
-use crate::agent_config::Config;
+use crate::agent_config::{Config, HeartbeatMetrics, HeartbeatThresholds};
 use crate::core::{AgentHealthStatus, AgentHeartbeat, AgentMetrics, AgentStatus};
 use crate::error::{Error, Result};
+use crate::reporter::ServerClient;
 use chrono::Utc;
 use parking_lot::RwLock;
 use std::sync::Arc;
 use std::time::Instant;
-use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+use sysinfo::{
+    CpuRefreshKind, MemoryRefreshKind, Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind,
+    System,
+};
 use tokio::sync::Mutex;
 use tracing::{debug, error, warn};
 
@@ -15,14 +19,42 @@ use tracing::{debug, error, warn};
 ///
 /// Holds a shared reference to the live config so any hot-reload applied by
 /// `Agent::reload_config()` is immediately reflected in subsequent heartbeats.
+///
+/// CPU and memory figures come from one `sysinfo::System` kept across
+/// heartbeats, so CPU usage is measured over the time since the previous
+/// heartbeat. `server.heartbeat_metrics` selects whether they describe the
+/// host or the agent process; process CPU is scaled to the whole host
+/// (0–100%) and `memory_total_mb` stays the host's physical memory.
 #[derive(Debug)]
 pub struct HeartbeatReporter {
     config: Arc<RwLock<Config>>,
     status: Arc<RwLock<AgentStatus>>,
+    client: ServerClient,
     system: Mutex<System>,
+    pid: Option<Pid>,
     started_at: Instant,
 }
 
+/// `Degraded` if any resource use in `metrics` is above its threshold
+fn health_status(metrics: &AgentMetrics, thresholds: &HeartbeatThresholds) -> AgentHealthStatus {
+    let memory_percent = if metrics.memory_total_mb > 0.0 {
+        metrics.memory_usage_mb / metrics.memory_total_mb * 100.0
+    } else {
+        0.0
+    };
+
+    if metrics.cpu_usage_percent > thresholds.cpu_percent
+        || memory_percent > thresholds.memory_percent
+        || thresholds
+            .memory_mb
+            .is_some_and(|limit| metrics.memory_usage_mb > limit)
+    {
+        AgentHealthStatus::Degraded
+    } else {
+        AgentHealthStatus::Healthy
+    }
+}
+
 impl HeartbeatReporter {
     /// Create a new heartbeat reporter
     ///
@@ -41,39 +73,50 @@ impl HeartbeatReporter {
         Ok(Self {
             config,
             status,
+            client: ServerClient::default(),
             system: Mutex::new(system),
+            pid: sysinfo::get_current_pid().ok(),
             started_at: Instant::now(),
         })
     }
 
+    /// Send heartbeats with `client`, e.g. one shared with the status reporter
+    pub fn with_client(mut self, client: ServerClient) -> Self {
+        self.client = client;
+        self
+    }
+
     /// Collect current system metrics for heartbeat
     async fn collect_metrics(&self) -> AgentHeartbeat {
-        let cpu_usage_percent = self.get_cpu_usage().await;
-        let (memory_usage_mb, memory_total_mb) = self.get_memory_mb().await;
+        let (scope, thresholds) = {
+            let config = self.config.read();
+            (
+                config.server.heartbeat_metrics,
+                config.server.heartbeat_thresholds,
+            )
+        };
+        let (cpu_usage_percent, memory_usage_mb, memory_total_mb) = match scope {
+            HeartbeatMetrics::Host => {
+                let (usage_mb, total_mb) = self.get_memory_mb().await;
+                (self.get_cpu_usage().await, usage_mb, total_mb)
+            }
+            HeartbeatMetrics::Process => self.get_process_usage().await,
+        };
         let system_uptime_secs = self.get_uptime_secs().await;
         let agent_uptime_secs = self.started_at.elapsed().as_secs() as i64;
 
-        // Determine health status based on metrics
-        let mut health_status = AgentHealthStatus::Healthy;
-
-        if cpu_usage_percent > 90.0 {
-            health_status = AgentHealthStatus::Degraded;
-        }
-
-        if memory_total_mb > 0.0 && (memory_usage_mb / memory_total_mb) * 100.0 > 90.0 {
-            health_status = AgentHealthStatus::Degraded;
-        }
+        let metrics = AgentMetrics {
+            agent_uptime_secs,
+            cpu_usage_percent,
+            memory_usage_mb,
+            memory_total_mb,
+            system_uptime_secs,
+        };
 
         AgentHeartbeat {
             timestamp: Utc::now(),
-            health_status,
-            metrics: AgentMetrics {
-                agent_uptime_secs,
-                cpu_usage_percent,
-                memory_usage_mb,
-                memory_total_mb,
-                system_uptime_secs,
-            },
+            health_status: health_status(&metrics, &thresholds),
+            metrics,
             agent_status: self.status.read().clone(),
         }
     }
@@ -83,10 +126,7 @@ impl HeartbeatReporter {
         // Snapshot the live config so all fields within this call are consistent.
         let config = self.config.read().clone();
 
-        let client = reqwest::Client::builder()
-            .timeout(config.server.timeout())
-            .danger_accept_invalid_certs(!config.server.verify_tls)
-            .build()?;
+        let client = self.client.get(&config.server)?;
 
         let heartbeat = self.collect_metrics().await;
         let heartbeat_url = format!("{}/agent/{}/heartbeat", config.server.url, config.agent_id);
@@ -153,6 +193,37 @@ impl HeartbeatReporter {
         (usage_mb, total_mb)
     }
 
+    /// Get the agent process's CPU usage (0.0–100.0 of the whole host), its
+    /// resident memory and the host's total memory in megabytes.
+    /// All are 0.0 where the process cannot be inspected.
+    async fn get_process_usage(&self) -> (f64, f64, f64) {
+        let Some(pid) = self.pid else {
+            return (0.0, 0.0, 0.0);
+        };
+        let mut system = self.system.lock().await;
+        system.refresh_memory();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_cpu().with_memory(),
+        );
+        let total_mb = system.total_memory() as f64 / 1024.0 / 1024.0;
+        let Some(process) = system.process(pid) else {
+            return (0.0, 0.0, total_mb);
+        };
+
+        // Process CPU usage is relative to one core
+        let cores = system.cpus().len().max(1) as f64;
+        let cpu_usage = process.cpu_usage() as f64 / cores;
+        let cpu_usage = if cpu_usage.is_finite() {
+            cpu_usage.clamp(0.0, 100.0)
+        } else {
+            0.0
+        };
+        let usage_mb = process.memory() as f64 / 1024.0 / 1024.0;
+        (cpu_usage, usage_mb, total_mb)
+    }
+
     /// Get system uptime in seconds. Returns 0 on unsupported platforms.
     async fn get_uptime_secs(&self) -> i64 {
         System::uptime() as i64
@@ -171,12 +242,24 @@ mod tests {
             version: 1,
             agent_id: uuid::Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap(),
             agent_name: "Test Agent".to_string(),
+            hostname: None,
             tags: vec!["test".to_string()],
+            metrics_addr: None,
             monitoring: MonitoringConfig::default(),
             server: ServerConfig::default(),
             storage: StorageConfig::default(),
             update: Default::default(),
+            audit: Default::default(),
+            sinks: Default::default(),
+            reporting: Default::default(),
+            control: Default::default(),
+            alerting: Default::default(),
+            hot_reload: Default::default(),
+            plugins: Default::default(),
+            logging: Default::default(),
+            thresholds: Vec::new(),
             endpoints: vec![],
+            env_templates: Vec::new(),
         }))
     }
 
@@ -314,6 +397,100 @@ mod tests {
         assert!(uptime >= 0, "System uptime should be non-negative");
     }
 
+    fn metrics(cpu_usage_percent: f64, memory_usage_mb: f64) -> AgentMetrics {
+        AgentMetrics {
+            agent_uptime_secs: 60,
+            cpu_usage_percent,
+            memory_usage_mb,
+            memory_total_mb: 8192.0,
+            system_uptime_secs: 3600,
+        }
+    }
+
+    #[test]
+    fn test_health_status_follows_thresholds() {
+        let thresholds = HeartbeatThresholds {
+            cpu_percent: 75.0,
+            memory_percent: 50.0,
+            memory_mb: None,
+        };
+        let degraded =
+            |m: AgentMetrics| matches!(health_status(&m, &thresholds), AgentHealthStatus::Degraded);
+
+        assert!(!degraded(metrics(75.0, 4096.0)), "limits are inclusive");
+        assert!(degraded(metrics(75.1, 1024.0)));
+        assert!(degraded(metrics(10.0, 4097.0)), "above 50% of 8192 MB");
+
+        let thresholds = HeartbeatThresholds {
+            memory_mb: Some(512.0),
+            ..thresholds
+        };
+        assert!(matches!(
+            health_status(&metrics(10.0, 600.0), &thresholds),
+            AgentHealthStatus::Degraded
+        ));
+        assert!(matches!(
+            health_status(&metrics(10.0, 500.0), &thresholds),
+            AgentHealthStatus::Healthy
+        ));
+    }
+
+    #[test]
+    fn test_default_thresholds_degrade_above_90_percent() {
+        let thresholds = HeartbeatThresholds::default();
+        assert!(matches!(
+            health_status(&metrics(90.0, 7372.0), &thresholds),
+            AgentHealthStatus::Healthy
+        ));
+        assert!(matches!(
+            health_status(&metrics(95.0, 1024.0), &thresholds),
+            AgentHealthStatus::Degraded
+        ));
+        assert!(matches!(
+            health_status(&metrics(10.0, 7500.0), &thresholds),
+            AgentHealthStatus::Degraded
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_process_metrics_are_plausible() {
+        let config = create_test_config();
+        config.write().server.heartbeat_metrics = HeartbeatMetrics::Process;
+        let reporter = HeartbeatReporter::new(config, create_test_status()).unwrap();
+
+        // CPU usage is measured between two refreshes; burn some in between
+        reporter.collect_metrics().await;
+        let started = Instant::now();
+        let mut spin = 0u64;
+        while started.elapsed() < std::time::Duration::from_millis(300) {
+            spin = std::hint::black_box(spin.wrapping_add(1));
+        }
+        let metrics = reporter.collect_metrics().await.metrics;
+
+        assert!((0.0..=100.0).contains(&metrics.cpu_usage_percent));
+        assert!(
+            metrics.memory_usage_mb > 0.0,
+            "the test process uses memory"
+        );
+        assert!(
+            metrics.memory_usage_mb < metrics.memory_total_mb,
+            "process memory {} MB exceeds host memory {} MB",
+            metrics.memory_usage_mb,
+            metrics.memory_total_mb
+        );
+    }
+
+    #[tokio::test]
+    async fn test_host_metrics_are_plausible() {
+        let reporter = HeartbeatReporter::new(create_test_config(), create_test_status()).unwrap();
+        let metrics = reporter.collect_metrics().await.metrics;
+
+        assert!((0.0..=100.0).contains(&metrics.cpu_usage_percent));
+        assert!(metrics.memory_total_mb > 0.0);
+        assert!(metrics.memory_usage_mb > 0.0);
+        assert!(metrics.memory_usage_mb <= metrics.memory_total_mb);
+    }
+
     #[tokio::test]
     async fn test_metrics_in_heartbeat() {
         let reporter = HeartbeatReporter::new(create_test_config(), create_test_status()).unwrap();
@@ -330,4 +507,53 @@ mod tests {
         // Verify timestamp is set
         assert!(heartbeat.timestamp.timestamp() > 0);
     }
+
+    /// Mock heartbeat endpoint accepting only requests authenticated with
+    /// `api_key` in `X-Agent-API-Key` (or without the header for `None`)
+    async fn heartbeat_mock(
+        server: &mut mockito::Server,
+        agent_id: uuid::Uuid,
+        api_key: Option<&str>,
+    ) -> mockito::Mock {
+        let key = match api_key {
+            Some(key) => mockito::Matcher::Exact(key.to_string()),
+            None => mockito::Matcher::Missing,
+        };
+        server
+            .mock("POST", format!("/agent/{}/heartbeat", agent_id).as_str())
+            .match_header("X-Agent-API-Key", key)
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(204)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_sends_api_key_header() {
+        let mut server = mockito::Server::new_async().await;
+        let config = create_test_config();
+        config.write().server.url = server.url();
+        config.write().server.api_key = Some("test-api-key".to_string());
+        let agent_id = config.read().agent_id;
+        let mock = heartbeat_mock(&mut server, agent_id, Some("test-api-key")).await;
+
+        let reporter = HeartbeatReporter::new(config, create_test_status()).unwrap();
+        reporter.send_heartbeat().await.unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_without_api_key_is_unauthenticated() {
+        let mut server = mockito::Server::new_async().await;
+        let config = create_test_config();
+        config.write().server.url = server.url();
+        let agent_id = config.read().agent_id;
+        let mock = heartbeat_mock(&mut server, agent_id, None).await;
+
+        let reporter = HeartbeatReporter::new(config, create_test_status()).unwrap();
+        reporter.send_heartbeat().await.unwrap();
+
+        mock.assert_async().await;
+    }
 }
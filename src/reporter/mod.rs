@@ -1,7 +1,15 @@
 //! Data reporting to central server with local caching
 
+mod client;
+mod compression;
+mod diagnostics;
 mod heartbeat;
+mod preflight;
 mod server;
 
+pub use client::ServerClient;
+pub(crate) use compression::json_body;
+pub use diagnostics::run_startup_diagnostics;
 pub use heartbeat::HeartbeatReporter;
+pub use preflight::run_preflight;
 pub use server::{run_heartbeat, run_reporter};
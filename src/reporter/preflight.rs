@@ -0,0 +1,228 @@
+//! Startup check that the server speaks a compatible API
+//!
+//! Before any result is sent, a claimed agent asks `GET /health` which agent
+//! API and report schema versions the server implements. The major API
+//! version must match and the report schema version must be one this agent
+//! can produce. On a mismatch `server.preflight` decides between a warning
+//! (`warn`, the default) and refusing to start (`enforce`).
+//!
+//! A server that cannot be reached or does not answer the health check is
+//! never fatal: the agent is offline-first and caches results until the
+//! server comes back.
+
+use crate::agent_config::{Config, PreflightMode};
+use crate::error::{Error, Result};
+use crate::openapi::ServerHealth;
+use std::ops::RangeInclusive;
+use tracing::{debug, error, info, warn};
+
+/// Major agent API version this agent implements
+const SUPPORTED_API_MAJOR: u64 = 1;
+
+/// Report schema versions this agent can produce
+const SUPPORTED_REPORT_SCHEMAS: RangeInclusive<i64> = 1..=1;
+
+/// Check the server API version according to `server.preflight`
+///
+/// Returns an error only in `enforce` mode when the server reported an
+/// incompatible version.
+pub async fn run_preflight(config: &Config) -> Result<()> {
+    let mode = config.server.preflight;
+    if mode == PreflightMode::Off {
+        return Ok(());
+    }
+    if !config.server.is_configured() {
+        debug!("Agent not claimed, skipping server preflight");
+        return Ok(());
+    }
+
+    let health = match fetch_health(config).await {
+        Ok(health) => health,
+        Err(e) => {
+            warn!("Server preflight skipped, health check failed: {}", e);
+            return Ok(());
+        }
+    };
+
+    match check_compatibility(&health) {
+        Ok(()) => {
+            info!(
+                "Server API {} (report schema {}) is compatible",
+                health.api_version, health.report_schema_version
+            );
+            Ok(())
+        }
+        Err(reason) if mode == PreflightMode::Enforce => {
+            error!("Refusing to start: {}", reason);
+            Err(Error::IncompatibleServer(reason))
+        }
+        Err(reason) => {
+            warn!("{}; results may be rejected by the server", reason);
+            Ok(())
+        }
+    }
+}
+
+async fn fetch_health(config: &Config) -> Result<ServerHealth> {
+    let client = reqwest::Client::builder()
+        .timeout(config.server.timeout())
+        .danger_accept_invalid_certs(!config.server.verify_tls)
+        .build()?;
+
+    let url = format!("{}/health", config.server.url);
+    let mut request = client.get(&url);
+
+    if let Some(api_key) = &config.server.api_key {
+        request = request.header("X-Agent-API-Key", api_key);
+    }
+
+    let response = request.send().await?;
+
+    if !response.status().is_success() {
+        return Err(Error::Network(format!(
+            "Server returned {} for GET /health",
+            response.status()
+        )));
+    }
+
+    Ok(response.json().await?)
+}
+
+/// Describe why `health` is incompatible with this agent, if it is
+fn check_compatibility(health: &ServerHealth) -> std::result::Result<(), String> {
+    let version = semver::Version::parse(&health.api_version).map_err(|e| {
+        format!(
+            "server reported an invalid API version {:?}: {}",
+            health.api_version, e
+        )
+    })?;
+
+    if version.major != SUPPORTED_API_MAJOR {
+        return Err(format!(
+            "server API version {} is incompatible, this agent supports {}.x",
+            version, SUPPORTED_API_MAJOR
+        ));
+    }
+
+    if !SUPPORTED_REPORT_SCHEMAS.contains(&health.report_schema_version) {
+        return Err(format!(
+            "server report schema version {} is incompatible, this agent supports {}-{}",
+            health.report_schema_version,
+            SUPPORTED_REPORT_SCHEMAS.start(),
+            SUPPORTED_REPORT_SCHEMAS.end()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::WarningCapture;
+    use uuid::Uuid;
+
+    fn claimed_config(server_url: &str, mode: PreflightMode) -> Config {
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.server.url = server_url.to_string();
+        config.server.api_key = Some("test-api-key".to_string());
+        config.server.preflight = mode;
+        config
+    }
+
+    async fn health_server(api_version: &str, report_schema_version: i64) -> mockito::ServerGuard {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/health")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "status": "ok",
+                    "api_version": api_version,
+                    "report_schema_version": report_schema_version,
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_compatible_server_passes() {
+        let server = health_server("1.4.2", 1).await;
+        let capture = WarningCapture::default();
+        let _guard = capture.install();
+
+        let config = claimed_config(&server.url(), PreflightMode::Enforce);
+        run_preflight(&config).await.unwrap();
+
+        assert!(capture.warnings().is_empty(), "{:?}", capture.warnings());
+    }
+
+    #[tokio::test]
+    async fn test_incompatible_server_warns() {
+        let server = health_server("2.0.0", 1).await;
+        let capture = WarningCapture::default();
+        let _guard = capture.install();
+
+        let config = claimed_config(&server.url(), PreflightMode::Warn);
+        run_preflight(&config).await.unwrap();
+
+        let warnings = capture.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("API version 2.0.0"), "{:?}", warnings);
+    }
+
+    #[tokio::test]
+    async fn test_incompatible_server_enforced_refuses_start() {
+        let server = health_server("1.0.0", 7).await;
+
+        let config = claimed_config(&server.url(), PreflightMode::Enforce);
+        let err = run_preflight(&config).await.unwrap_err();
+
+        assert!(matches!(err, Error::IncompatibleServer(_)));
+        assert!(
+            err.to_string().contains("report schema version 7"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_off_does_not_query_server() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let config = claimed_config(&server.url(), PreflightMode::Off);
+        run_preflight(&config).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_is_not_fatal() {
+        // Nothing listens on port 9 (discard) on test hosts
+        let config = claimed_config("http://127.0.0.1:9", PreflightMode::Enforce);
+        run_preflight(&config).await.unwrap();
+    }
+
+    #[test]
+    fn test_invalid_api_version_is_incompatible() {
+        let health = ServerHealth {
+            status: "ok".to_string(),
+            api_version: "v1".to_string(),
+            report_schema_version: 1,
+        };
+        assert!(check_compatibility(&health)
+            .unwrap_err()
+            .contains("invalid API version"));
+    }
+}
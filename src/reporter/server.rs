@@ -1,24 +1,69 @@
 //! Server reporting functionality
 
-use crate::agent_config::Config;
-use crate::core::AgentStatus;
+use crate::agent_config::{Config, ReloadTrigger};
+use crate::core::{
+    wait_for_shutdown, AgentStatus, Readiness, ReadinessStep, ReportingSwitch, ShutdownReceiver,
+    SHUTDOWN_FLUSH_TIMEOUT,
+};
 use crate::error::{Error, Result};
-use crate::reporter::HeartbeatReporter;
-use chrono::Utc;
+use crate::openapi::ReportAcknowledgment;
+use crate::reporter::{json_body, HeartbeatReporter, ServerClient};
 use parking_lot::RwLock;
+use std::collections::VecDeque;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::time::interval;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{interval, Instant};
 use tracing::{debug, error, info, warn};
 
+/// Delay before the first retry of a failed report, doubled on every retry
+const REPORT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Undelivered reports kept for the next tick; the oldest is dropped first
+const MAX_CACHED_REPORTS: usize = 16;
+
+/// Times the server may reject a cached report before it is dropped
+const MAX_REPORT_REJECTIONS: u32 = 3;
+
 /// Run the reporter loop
 ///
 /// Accepts a shared `Arc<RwLock<Config>>` so that config hot-reloads applied by
-/// `Agent::reload_config()` are picked up on every reporting tick.
+/// `Agent::reload_config()` are picked up on every reporting tick. The first
+/// accepted report completes the server step of `readiness`. Reports are sent
+/// with `client`, so consecutive reports reuse its connections.
+///
+/// A failed report is retried `server.retry_attempts` times with exponential
+/// backoff, for no longer than one report interval in total and never past
+/// shutdown. Once the retries are exhausted the failure is recorded and the
+/// report is cached; the next tick sends the cached reports, oldest first,
+/// before the current one. At most [`MAX_CACHED_REPORTS`] are kept. A report
+/// the server rejects is not retried within the tick and is dropped once it
+/// has been rejected [`MAX_REPORT_REJECTIONS`] times.
+///
+/// A report accepted while the agent was disconnected signals `reporting`,
+/// so the result reporter flushes the results cached during the outage
+/// without waiting for its next tick.
+///
+/// A report acknowledgment advertising a configuration version newer than
+/// the local one sends a `ReloadTrigger::ServerVersionChange` through
+/// `reload_tx`, so the hot reload task pulls the server-side configuration.
+///
+/// On shutdown the cached reports and a final one are sent once, without
+/// retries, so the server sees the agent's last status. They are abandoned
+/// after [`SHUTDOWN_FLUSH_TIMEOUT`].
+///
+/// Every report carries `uptime_secs`, the time since `started`, the
+/// instant the agent started.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_reporter(
     config: Arc<RwLock<Config>>,
     agent_status: Arc<RwLock<AgentStatus>>,
-    agent_shutdown_rx: &mut broadcast::Receiver<()>,
+    started: Instant,
+    client: ServerClient,
+    readiness: Readiness,
+    reporting: ReportingSwitch,
+    reload_tx: mpsc::UnboundedSender<ReloadTrigger>,
+    agent_shutdown_rx: &mut ShutdownReceiver,
 ) -> Result<()> {
     info!("Starting reporter");
 
@@ -30,6 +75,7 @@ pub async fn run_reporter(
     let mut current_interval_duration = config.read().server.report_interval();
     let mut iv = interval(current_interval_duration);
     iv.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut cached_reports = VecDeque::new();
 
     loop {
         tokio::select! {
@@ -51,30 +97,52 @@ pub async fn run_reporter(
 
                 match config_snapshot.server.is_configured() {
                     true => {
-                        match send_agent_report(&config_snapshot, &agent_status).await {
+                        cache_report(&mut cached_reports, snapshot(&agent_status, started));
+                        let deadline = Instant::now() + current_interval_duration;
+                        match send_cached_reports(
+                            &config_snapshot,
+                            &client,
+                            &mut cached_reports,
+                            &reload_tx,
+                            deadline,
+                            agent_shutdown_rx,
+                        )
+                        .await
+                        {
                             Ok(_) => {
-                                let mut s = agent_status.write();
-                                s.server_connected = true;
-                                s.reported_at = Utc::now();
+                                let reconnected =
+                                    reporting.record_report_success(&mut agent_status.write());
+                                if reconnected {
+                                    info!("Server reachable, flushing cached results");
+                                }
+                                readiness.complete(
+                                    ReadinessStep::ServerReachable,
+                                    format!("server {} reachable", config_snapshot.server.url),
+                                );
                                 debug!("Report sent successfully");
                             }
                             Err(e) => {
+                                let grace = config_snapshot.server.connection_failure_grace();
                                 let mut s = agent_status.write();
-                                s.server_connected = false;
-                                s.failed_report_count += 1;
-                                error!("Failed to send report: {}", e);
+                                reporting.record_report_failure(&mut s, grace);
+                                error!(
+                                    "Failed to send report ({} in a row, {} cached): {}",
+                                    s.consecutive_failed_reports,
+                                    cached_reports.len(),
+                                    e
+                                );
                             }
                         }
                     }
                     false => {
-                        warn!("Server not configured, reporter will cache data locally");
-
-                        warn!("Implement local caching logic");
+                        debug!("Server not configured, results stay cached locally");
                     }
                 }
             }
-            _ = agent_shutdown_rx.recv() => {
+            _ = wait_for_shutdown(agent_shutdown_rx) => {
                 info!("Agent status reporter shutting down");
+                let last = snapshot(&agent_status, started);
+                send_final_report(&config, &client, last, cached_reports, &reload_tx).await;
                 break;
             }
         }
@@ -83,19 +151,170 @@ pub async fn run_reporter(
     Ok(())
 }
 
-/// Send an agent report to the server
-async fn send_agent_report(config: &Config, agent_status: &Arc<RwLock<AgentStatus>>) -> Result<()> {
-    let server_url = &config.server.url;
+/// Send the cached reports and `last` before shutdown, giving up after
+/// [`SHUTDOWN_FLUSH_TIMEOUT`]
+async fn send_final_report(
+    config: &Arc<RwLock<Config>>,
+    client: &ServerClient,
+    last: AgentStatus,
+    mut cached_reports: VecDeque<CachedReport>,
+    reload_tx: &mpsc::UnboundedSender<ReloadTrigger>,
+) {
+    let config_snapshot = config.read().clone();
+    if !config_snapshot.server.is_configured() {
+        return;
+    }
+
+    cache_report(&mut cached_reports, last);
+    let reports = async {
+        for cached in &cached_reports {
+            send_agent_report(&config_snapshot, client, &cached.report, reload_tx).await?;
+        }
+        Ok::<_, Error>(())
+    };
+    match tokio::time::timeout(SHUTDOWN_FLUSH_TIMEOUT, reports).await {
+        Ok(Ok(())) => debug!("Final report sent"),
+        Ok(Err(e)) => warn!("Failed to send final report: {}", e),
+        Err(_) => warn!(
+            "Final report not sent within {:?}, shutting down without it",
+            SHUTDOWN_FLUSH_TIMEOUT
+        ),
+    }
+}
+
+/// The agent status to report, with the uptime since `started`
+fn snapshot(agent_status: &RwLock<AgentStatus>, started: Instant) -> AgentStatus {
+    let mut report = agent_status.read().clone();
+    report.uptime_secs = Some(started.elapsed().as_secs() as i64);
+    report
+}
+
+/// An undelivered report and how often the server has rejected it
+struct CachedReport {
+    report: AgentStatus,
+    rejections: u32,
+}
 
-    let client = reqwest::Client::builder()
-        .timeout(config.server.timeout())
-        .danger_accept_invalid_certs(!config.server.verify_tls)
-        .build()?;
+/// Add `report` to the end of `reports`, dropping the oldest report if
+/// [`MAX_CACHED_REPORTS`] are already cached
+fn cache_report(reports: &mut VecDeque<CachedReport>, report: AgentStatus) {
+    if reports.len() >= MAX_CACHED_REPORTS {
+        warn!(
+            "{} undelivered reports cached, dropping the oldest",
+            reports.len()
+        );
+        reports.pop_front();
+    }
+    reports.push_back(CachedReport {
+        report,
+        rejections: 0,
+    });
+}
+
+/// Send the cached reports oldest first, each with retries, and remove the
+/// delivered ones from `reports`
+///
+/// Stops at the first report that cannot be delivered and returns its
+/// error. Retries end at `deadline` or on shutdown. A report rejected for
+/// the [`MAX_REPORT_REJECTIONS`]th time is dropped and the next one sent.
+async fn send_cached_reports(
+    config: &Config,
+    client: &ServerClient,
+    reports: &mut VecDeque<CachedReport>,
+    reload_tx: &mpsc::UnboundedSender<ReloadTrigger>,
+    deadline: Instant,
+    shutdown_rx: &mut ShutdownReceiver,
+) -> Result<()> {
+    while let Some(cached) = reports.front_mut() {
+        match send_agent_report_with_retry(
+            config,
+            client,
+            &cached.report,
+            reload_tx,
+            REPORT_RETRY_DELAY,
+            deadline,
+            shutdown_rx,
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(Error::Rejected(reason)) if cached.rejections + 1 >= MAX_REPORT_REJECTIONS => {
+                warn!(
+                    "Dropping report rejected {} times: {}",
+                    MAX_REPORT_REJECTIONS, reason
+                );
+            }
+            Err(e) => {
+                if matches!(e, Error::Rejected(_)) {
+                    cached.rejections += 1;
+                }
+                return Err(e);
+            }
+        }
+        reports.pop_front();
+    }
+    Ok(())
+}
+
+/// Send an agent report, retrying failures with exponential backoff
+///
+/// Makes up to `server.retry_attempts + 1` attempts, waiting `delay` before
+/// the first retry and twice as long before each further one. No retry is
+/// started that would wait past `deadline` or after the server rejected the
+/// report, and the wait is cut short by shutdown. Returns the error of the
+/// last attempt.
+async fn send_agent_report_with_retry(
+    config: &Config,
+    client: &ServerClient,
+    report: &AgentStatus,
+    reload_tx: &mpsc::UnboundedSender<ReloadTrigger>,
+    mut delay: Duration,
+    deadline: Instant,
+    shutdown_rx: &mut ShutdownReceiver,
+) -> Result<()> {
+    let attempts = config.server.retry_attempts.saturating_add(1);
+    let mut attempt = 1;
+
+    loop {
+        let e = match send_agent_report(config, client, report, reload_tx).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if attempt >= attempts
+            || matches!(e, Error::Rejected(_))
+            || Instant::now() + delay >= deadline
+        {
+            return Err(e);
+        }
+
+        warn!("Report attempt {} of {} failed: {}", attempt, attempts, e);
+        debug!("Retrying report in {:?}", delay);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = wait_for_shutdown(shutdown_rx) => return Err(e),
+        }
+        delay *= 2; // Exponential backoff
+        attempt += 1;
+    }
+}
+
+/// Send `report`, a snapshot of the agent status, to the server
+///
+/// If the acknowledgment advertises a configuration version newer than
+/// `config.version`, a `ReloadTrigger::ServerVersionChange` is sent through
+/// `reload_tx`.
+async fn send_agent_report(
+    config: &Config,
+    client: &ServerClient,
+    report: &AgentStatus,
+    reload_tx: &mpsc::UnboundedSender<ReloadTrigger>,
+) -> Result<()> {
+    let server_url = &config.server.url;
+    let client = client.get(&config.server)?;
 
-    let status_data = agent_status.read().clone();
     let report_url = format!("{}/agent/{}/report", server_url, config.agent_id);
 
-    let mut request = client.post(&report_url).json(&status_data);
+    let mut request = json_body(config, client.post(&report_url), report)?;
 
     if let Some(api_key) = &config.server.api_key {
         request = request.header("X-Agent-API-Key", api_key);
@@ -103,26 +322,65 @@ async fn send_agent_report(config: &Config, agent_status: &Arc<RwLock<AgentStatu
 
     let response = request.send().await?;
 
-    if !response.status().is_success() {
-        return Err(Error::Network(format!(
-            "Server returned error: {}",
-            response.status()
-        )));
+    let status = response.status();
+    if is_rejection(status) {
+        return Err(Error::Rejected(format!("report answered with {}", status)));
+    }
+    if !status.is_success() {
+        return Err(Error::Network(format!("Server returned error: {}", status)));
+    }
+
+    match response.json::<ReportAcknowledgment>().await {
+        Ok(ack) => {
+            debug!(
+                "Server acknowledged report: status={:?}, configuration_version={}",
+                ack.status, ack.configuration_version
+            );
+            if let Some(version) = newer_configuration_version(&ack, config.version) {
+                info!(
+                    "Server has configuration version {} (local: {}), requesting reload",
+                    version, config.version
+                );
+                // A closed channel means hot reload has stopped; nothing to do
+                let _ = reload_tx.send(ReloadTrigger::ServerVersionChange(version));
+            }
+        }
+        Err(e) => {
+            // A parse failure is non-fatal — the server already returned 2xx.
+            debug!("Could not parse ReportAcknowledgment body: {}", e);
+        }
     }
 
     Ok(())
 }
 
+/// Whether `status` refuses the report itself, so sending it again cannot
+/// succeed. Timeouts and rate limiting are worth a retry.
+fn is_rejection(status: reqwest::StatusCode) -> bool {
+    status.is_client_error()
+        && status != reqwest::StatusCode::REQUEST_TIMEOUT
+        && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Configuration version advertised in `ack`, if it is newer than `local`
+fn newer_configuration_version(ack: &ReportAcknowledgment, local: u32) -> Option<u32> {
+    u32::try_from(ack.configuration_version)
+        .ok()
+        .filter(|&version| version > local)
+}
+
 /// Run the heartbeat loop in a separate task
 ///
 /// Accepts a shared `Arc<RwLock<Config>>` so that config hot-reloads are
 /// reflected in subsequent heartbeat payloads automatically.
 /// Accepts a shared `Arc<RwLock<AgentStatus>>` so each heartbeat payload
-/// includes the latest agent status snapshot.
+/// includes the latest agent status snapshot. Heartbeats are sent with
+/// `client`, shared with the status reporter.
 pub async fn run_heartbeat(
     config: Arc<RwLock<Config>>,
     agent_status: Arc<RwLock<AgentStatus>>,
-    mut agent_shutdown_rx: broadcast::Receiver<()>,
+    client: ServerClient,
+    mut agent_shutdown_rx: ShutdownReceiver,
 ) -> Result<()> {
     info!("Starting heartbeat reporter");
 
@@ -132,7 +390,7 @@ pub async fn run_heartbeat(
     }
 
     let heartbeat_reporter =
-        HeartbeatReporter::new(Arc::clone(&config), Arc::clone(&agent_status))?;
+        HeartbeatReporter::new(Arc::clone(&config), Arc::clone(&agent_status))?.with_client(client);
 
     // Track the current interval so we can hot-reload it when config changes.
     let mut current_interval_duration = config.read().server.heartbeat_interval();
@@ -174,7 +432,7 @@ pub async fn run_heartbeat(
                     }
                 }
             }
-            _ = agent_shutdown_rx.recv() => {
+            _ = wait_for_shutdown(&mut agent_shutdown_rx) => {
                 info!("Heartbeat reporter shutting down");
                 break;
             }
@@ -183,3 +441,460 @@ pub async fn run_heartbeat(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn report_config(server_url: &str, retry_attempts: u32) -> Config {
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.server.url = server_url.to_string();
+        config.server.api_key = Some("test-api-key".to_string());
+        config.server.retry_attempts = retry_attempts;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_report_is_retried_until_accepted() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 3);
+        let path = format!("/agent/{}/report", config.agent_id);
+
+        // First 2 attempts fail with 500
+        let failed = server
+            .mock("POST", path.as_str())
+            .with_status(500)
+            .expect(2)
+            .create_async()
+            .await;
+        // Third attempt succeeds
+        let accepted = server
+            .mock("POST", path.as_str())
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let status = AgentStatus::new();
+        let client = ServerClient::default();
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        let (_shutdown_tx, mut shutdown_rx) = crate::core::shutdown_channel();
+        send_agent_report_with_retry(
+            &config,
+            &client,
+            &status,
+            &reload_tx,
+            Duration::from_millis(10),
+            Instant::now() + Duration::from_secs(60),
+            &mut shutdown_rx,
+        )
+        .await
+        .expect("third attempt is accepted");
+
+        failed.assert_async().await;
+        accepted.assert_async().await;
+        assert_eq!(client.builds(), 1, "every attempt reuses the client");
+    }
+
+    #[tokio::test]
+    async fn test_report_fails_after_retries_are_exhausted() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 1);
+        let failed = server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let status = AgentStatus::new();
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        let (_shutdown_tx, mut shutdown_rx) = crate::core::shutdown_channel();
+        let result = send_agent_report_with_retry(
+            &config,
+            &ServerClient::default(),
+            &status,
+            &reload_tx,
+            Duration::from_millis(10),
+            Instant::now() + Duration::from_secs(60),
+            &mut shutdown_rx,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Network(_))));
+        failed.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_report_retries_stop_at_the_deadline() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 5);
+        // The second retry would wait past the deadline
+        let failed = server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        let (_shutdown_tx, mut shutdown_rx) = crate::core::shutdown_channel();
+        let result = send_agent_report_with_retry(
+            &config,
+            &ServerClient::default(),
+            &AgentStatus::new(),
+            &reload_tx,
+            Duration::from_millis(60),
+            Instant::now() + Duration::from_millis(100),
+            &mut shutdown_rx,
+        )
+        .await;
+
+        assert!(result.is_err());
+        failed.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_report_backoff_ends_on_shutdown() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 3);
+        let failed = server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let (shutdown_tx, mut shutdown_rx) = crate::core::shutdown_channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            crate::core::trigger_shutdown(&shutdown_tx);
+        });
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        let result = tokio::time::timeout(
+            Duration::from_secs(2),
+            send_agent_report_with_retry(
+                &config,
+                &ServerClient::default(),
+                &AgentStatus::new(),
+                &reload_tx,
+                Duration::from_secs(60),
+                Instant::now() + Duration::from_secs(600),
+                &mut shutdown_rx,
+            ),
+        )
+        .await
+        .expect("shutdown should end the backoff");
+
+        assert!(result.is_err());
+        failed.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_reports_stay_cached_until_accepted() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 0);
+        let path = format!("/agent/{}/report", config.agent_id);
+        let failed = server
+            .mock("POST", path.as_str())
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut reports = VecDeque::new();
+        let mut older = AgentStatus::new();
+        older.checks_performed = 1;
+        cache_report(&mut reports, older);
+        cache_report(&mut reports, AgentStatus::new());
+
+        let client = ServerClient::default();
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        let (_shutdown_tx, mut shutdown_rx) = crate::core::shutdown_channel();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let result = send_cached_reports(
+            &config,
+            &client,
+            &mut reports,
+            &reload_tx,
+            deadline,
+            &mut shutdown_rx,
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(reports.len(), 2, "nothing is dropped");
+        failed.assert_async().await;
+
+        let accepted = server
+            .mock("POST", path.as_str())
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "checks_performed": 1 }),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        let current = server
+            .mock("POST", path.as_str())
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "checks_performed": 0 }),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        send_cached_reports(
+            &config,
+            &client,
+            &mut reports,
+            &reload_tx,
+            deadline,
+            &mut shutdown_rx,
+        )
+        .await
+        .unwrap();
+        assert!(reports.is_empty());
+        accepted.assert_async().await;
+        current.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_rejected_report_is_dropped_after_bounded_rejections() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 3);
+        let rejected = server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .with_status(422)
+            .expect(MAX_REPORT_REJECTIONS as usize)
+            .create_async()
+            .await;
+
+        let mut reports = VecDeque::new();
+        cache_report(&mut reports, AgentStatus::new());
+
+        let client = ServerClient::default();
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        let (_shutdown_tx, mut shutdown_rx) = crate::core::shutdown_channel();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        for tick in 1..MAX_REPORT_REJECTIONS {
+            let result = send_cached_reports(
+                &config,
+                &client,
+                &mut reports,
+                &reload_tx,
+                deadline,
+                &mut shutdown_rx,
+            )
+            .await;
+            assert!(matches!(result, Err(Error::Rejected(_))), "{:?}", result);
+            assert_eq!(reports.len(), 1, "kept after {} rejections", tick);
+        }
+
+        send_cached_reports(
+            &config,
+            &client,
+            &mut reports,
+            &reload_tx,
+            deadline,
+            &mut shutdown_rx,
+        )
+        .await
+        .unwrap();
+        assert!(reports.is_empty(), "dropped once the bound is reached");
+        // One attempt per tick: a rejection is not retried
+        rejected.assert_async().await;
+    }
+
+    #[test]
+    fn test_rejection_statuses() {
+        use reqwest::StatusCode;
+
+        assert!(is_rejection(StatusCode::BAD_REQUEST));
+        assert!(is_rejection(StatusCode::PAYLOAD_TOO_LARGE));
+        assert!(!is_rejection(StatusCode::REQUEST_TIMEOUT));
+        assert!(!is_rejection(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_rejection(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[test]
+    fn test_report_cache_drops_oldest_when_full() {
+        let mut reports = VecDeque::new();
+        for checks in 0..=MAX_CACHED_REPORTS as i64 {
+            let mut report = AgentStatus::new();
+            report.checks_performed = checks;
+            cache_report(&mut reports, report);
+        }
+
+        assert_eq!(reports.len(), MAX_CACHED_REPORTS);
+        assert_eq!(reports.front().unwrap().report.checks_performed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_report_authenticates_with_api_key_header() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 0);
+        let mock = server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .match_header("X-Agent-API-Key", "test-api-key")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let status = AgentStatus::new();
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        send_agent_report(&config, &ServerClient::default(), &status, &reload_tx)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_report_without_api_key_is_unauthenticated() {
+        let mut server = mockito::Server::new_async().await;
+        let mut config = report_config(&server.url(), 0);
+        config.server.api_key = None;
+        let mock = server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .match_header("X-Agent-API-Key", mockito::Matcher::Missing)
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let status = AgentStatus::new();
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        send_agent_report(&config, &ServerClient::default(), &status, &reload_tx)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_final_report_is_sent_on_shutdown() {
+        let mut server = mockito::Server::new_async().await;
+        let config = report_config(&server.url(), 0);
+        // One report on the first tick and the final one on shutdown, both
+        // with the uptime since the agent started 90 seconds ago
+        let reports = server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .match_body(mockito::Matcher::Regex(
+                r#""uptime_secs":9\d\b"#.to_string(),
+            ))
+            .with_status(200)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let (shutdown_tx, mut shutdown_rx) = crate::core::shutdown_channel();
+        let started = Instant::now() - Duration::from_secs(90);
+        let reporter = tokio::spawn(async move {
+            let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+            run_reporter(
+                Arc::new(RwLock::new(config)),
+                Arc::new(RwLock::new(AgentStatus::new())),
+                started,
+                ServerClient::default(),
+                Readiness::default(),
+                ReportingSwitch::default(),
+                reload_tx,
+                &mut shutdown_rx,
+            )
+            .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        crate::core::trigger_shutdown(&shutdown_tx);
+        tokio::time::timeout(Duration::from_secs(2), reporter)
+            .await
+            .expect("reporter should stop within the flush timeout")
+            .unwrap()
+            .unwrap();
+
+        reports.assert_async().await;
+    }
+
+    /// Send one report to a server acknowledging it with `configuration_version`
+    /// and return the reload triggers it caused
+    async fn report_acknowledged_with(
+        configuration_version: i64,
+        local_version: u32,
+    ) -> Vec<ReloadTrigger> {
+        let mut server = mockito::Server::new_async().await;
+        let mut config = report_config(&server.url(), 0);
+        config.version = local_version;
+        server
+            .mock(
+                "POST",
+                format!("/agent/{}/report", config.agent_id).as_str(),
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "status": "accepted",
+                    "configuration_version": configuration_version,
+                    "update_available": configuration_version > local_version as i64,
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let status = AgentStatus::new();
+        let (reload_tx, mut reload_rx) = mpsc::unbounded_channel();
+        send_agent_report(&config, &ServerClient::default(), &status, &reload_tx)
+            .await
+            .unwrap();
+
+        drop(reload_tx);
+        let mut triggers = Vec::new();
+        while let Some(trigger) = reload_rx.recv().await {
+            triggers.push(trigger);
+        }
+        triggers
+    }
+
+    #[tokio::test]
+    async fn test_newer_configuration_version_triggers_reload() {
+        let triggers = report_acknowledged_with(8, 7).await;
+        assert!(
+            matches!(triggers[..], [ReloadTrigger::ServerVersionChange(8)]),
+            "{:?}",
+            triggers
+        );
+    }
+
+    #[tokio::test]
+    async fn test_current_configuration_version_does_not_trigger_reload() {
+        assert!(report_acknowledged_with(7, 7).await.is_empty());
+        assert!(report_acknowledged_with(6, 7).await.is_empty());
+    }
+}
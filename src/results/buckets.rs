@@ -0,0 +1,294 @@
+//! Time-bucketed result reports
+//!
+//! With `reporting.bucket_secs` set, the reporter does not send individual
+//! results. It groups them into fixed time buckets aligned to the UNIX epoch
+//! (with `bucket_secs = 60`, one bucket per wall-clock minute) and sends each
+//! bucket as an `openapi::ResultReport` to
+//! `POST {server.url}/agent/{agent_id}/reports`. A report covers its bucket
+//! in `time_range` and holds one `AggregatedMetric` per endpoint and check
+//! type with the check count, success rate and latency statistics, which
+//! matches the server's ingestion model.
+//!
+//! Only closed buckets are sent: results of the bucket that is still open
+//! stay cached until it ends. A bucket can still arrive in more than one
+//! report, e.g. when it spans two cache pages; reports with the same
+//! `time_range` are meant to be merged by the server.
+
+use crate::agent_config::Config;
+use crate::core::{CheckType, MonitoringResult};
+use crate::openapi::{
+    AggregatedMetric, AggregatedMetricStatus, Pagination, ResultReport, TimeRange,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+impl Config {
+    /// URL time-bucketed reports are sent to
+    pub(crate) fn bucket_report_url(&self) -> String {
+        format!("{}/agent/{}/reports", self.server.url, self.agent_id)
+    }
+}
+
+/// Start of the `bucket_secs` bucket containing `timestamp`
+pub(crate) fn bucket_start(timestamp: DateTime<Utc>, bucket_secs: u64) -> DateTime<Utc> {
+    let secs = timestamp.timestamp();
+    let start = secs - secs.rem_euclid(bucket_secs as i64);
+    DateTime::from_timestamp(start, 0).unwrap_or(timestamp)
+}
+
+/// Number of leading `results` whose bucket has ended at `now`
+pub(crate) fn closed_prefix(
+    results: &[MonitoringResult],
+    bucket_secs: u64,
+    now: DateTime<Utc>,
+) -> usize {
+    let open_bucket = bucket_start(now, bucket_secs);
+    results
+        .iter()
+        .take_while(|result| bucket_start(result.timestamp, bucket_secs) < open_bucket)
+        .count()
+}
+
+/// Aggregate `results` into one report per bucket, oldest bucket first
+pub(crate) fn bucket_reports(
+    config: &Config,
+    results: &[MonitoringResult],
+    bucket_secs: u64,
+    hostname: &str,
+) -> Vec<ResultReport> {
+    let mut buckets: BTreeMap<DateTime<Utc>, Vec<&MonitoringResult>> = BTreeMap::new();
+    for result in results {
+        buckets
+            .entry(bucket_start(result.timestamp, bucket_secs))
+            .or_default()
+            .push(result);
+    }
+
+    buckets
+        .into_iter()
+        .map(|(start, results)| {
+            let data = aggregate(config, start, &results, hostname);
+            let items = data.len() as i64;
+            ResultReport {
+                time_range: TimeRange {
+                    start,
+                    end: start + TimeDelta::seconds(bucket_secs as i64),
+                },
+                aggregation: Some(format!("{}s", bucket_secs)),
+                data,
+                pagination: Pagination {
+                    page: 1,
+                    page_size: items.max(1),
+                    total_items: items,
+                    total_pages: 1,
+                    has_next: Some(false),
+                    has_previous: Some(false),
+                },
+                metadata: None,
+            }
+        })
+        .collect()
+}
+
+/// One metric per endpoint and check type, in order of first appearance
+fn aggregate(
+    config: &Config,
+    timestamp: DateTime<Utc>,
+    results: &[&MonitoringResult],
+    hostname: &str,
+) -> Vec<AggregatedMetric> {
+    let mut groups: Vec<((Uuid, &'static str), Vec<&MonitoringResult>)> = Vec::new();
+    for result in results {
+        let key = (result.endpoint_id, metric_type(result));
+        match groups.iter_mut().find(|(group, _)| *group == key) {
+            Some((_, group)) => group.push(result),
+            None => groups.push((key, vec![result])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|((endpoint_id, metric_type), results)| {
+            let target = config
+                .endpoints
+                .iter()
+                .find(|endpoint| endpoint.id == endpoint_id)
+                .map(|endpoint| endpoint.address.clone())
+                .unwrap_or_else(|| endpoint_id.to_string());
+
+            let successes = results.iter().filter(|r| r.is_successful()).count();
+            let success_rate = successes as f64 * 100.0 / results.len() as f64;
+            let status = match successes {
+                0 => AggregatedMetricStatus::Unreachable,
+                n if n == results.len() => AggregatedMetricStatus::Reachable,
+                _ => AggregatedMetricStatus::Degraded,
+            };
+
+            let mut latencies: Vec<f64> = results
+                .iter()
+                .filter_map(|r| r.response_time_ms())
+                .collect();
+            latencies.sort_by(f64::total_cmp);
+
+            AggregatedMetric {
+                timestamp,
+                agent_id: config.agent_id,
+                hostname: Some(hostname.to_string()),
+                target,
+                metric_type: metric_type.to_string(),
+                status: Some(status),
+                avg_response_time_ms: (!latencies.is_empty())
+                    .then(|| latencies.iter().sum::<f64>() / latencies.len() as f64),
+                min_response_time_ms: latencies.first().copied(),
+                max_response_time_ms: latencies.last().copied(),
+                p50_response_time_ms: percentile(&latencies, 50.0),
+                p95_response_time_ms: percentile(&latencies, 95.0),
+                p99_response_time_ms: percentile(&latencies, 99.0),
+                success_rate_percent: Some(success_rate),
+                check_count: Some(results.len() as i64),
+            }
+        })
+        .collect()
+}
+
+/// Wire name of the result's check type, as in `check_type.type`
+fn metric_type(result: &MonitoringResult) -> &'static str {
+    match &result.check_type {
+        CheckType::PingCheck(_) => "ping",
+        CheckType::TracerouteCheck(_) => "traceroute",
+        CheckType::TcpConnectCheck(_) => "tcpconnect",
+        CheckType::UdpConnectCheck(_) => "udpconnect",
+        CheckType::HttpGetCheck(_) => "httpget",
+        CheckType::GrpcHealthCheck(_) => "grpchealth",
+        CheckType::TlsCertCheck(_) => "tlscert",
+        CheckType::DnsCheck(_) => "dns",
+        CheckType::PluginCheck(_) => "plugin",
+    }
+}
+
+/// Nearest-rank percentile of ascending `sorted` values
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Endpoint, HttpGetCheck, HttpGetCheckType, HttpGetResult};
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn http_result(
+        endpoint: &Endpoint,
+        timestamp: &str,
+        success: bool,
+        latency: f64,
+    ) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::nil(),
+            endpoint_id: endpoint.id,
+            check_type: CheckType::HttpGetCheck(HttpGetCheck {
+                r#type: HttpGetCheckType::Httpget,
+                result: HttpGetResult {
+                    status_code: if success { 200 } else { 503 },
+                    response_time_ms: Some(latency),
+                    dns_resolution_ms: None,
+                    response_size_bytes: None,
+                    body_truncated: None,
+                    body_matched: None,
+                    body_match_detail: None,
+                    error_details: None,
+                    success,
+                },
+            }),
+            timestamp: at(timestamp),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_bucket_start_aligns_to_epoch() {
+        assert_eq!(
+            bucket_start(at("2026-03-01T12:00:59.900Z"), 60),
+            at("2026-03-01T12:00:00Z")
+        );
+        assert_eq!(
+            bucket_start(at("2026-03-01T12:07:00Z"), 300),
+            at("2026-03-01T12:05:00Z")
+        );
+    }
+
+    #[test]
+    fn test_only_closed_buckets_are_ready() {
+        let endpoint = Endpoint::new("https://example.com/");
+        let results = [
+            http_result(&endpoint, "2026-03-01T12:00:10Z", true, 1.0),
+            http_result(&endpoint, "2026-03-01T12:01:10Z", true, 1.0),
+            http_result(&endpoint, "2026-03-01T12:02:10Z", true, 1.0),
+        ];
+
+        assert_eq!(closed_prefix(&results, 60, at("2026-03-01T12:02:30Z")), 2);
+        assert_eq!(closed_prefix(&results, 60, at("2026-03-01T12:03:00Z")), 3);
+        assert_eq!(closed_prefix(&results, 60, at("2026-03-01T12:00:30Z")), 0);
+    }
+
+    #[test]
+    fn test_bucket_aggregates_per_endpoint() {
+        let api = Endpoint::new("https://api.example.com/");
+        let web = Endpoint::new("https://www.example.com/");
+        let config = Config {
+            endpoints: vec![api.clone(), web.clone()],
+            ..Config::default()
+        };
+        let results = [
+            http_result(&api, "2026-03-01T12:00:05Z", true, 10.0),
+            http_result(&web, "2026-03-01T12:00:06Z", true, 50.0),
+            http_result(&api, "2026-03-01T12:00:35Z", true, 30.0),
+            http_result(&api, "2026-03-01T12:00:45Z", false, 20.0),
+            http_result(&api, "2026-03-01T12:00:55Z", true, 40.0),
+        ];
+
+        let reports = bucket_reports(&config, &results, 60, "edge-01");
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.aggregation.as_deref(), Some("60s"));
+        assert_eq!(report.pagination.total_items, 2);
+
+        let api_metric = &report.data[0];
+        assert_eq!(api_metric.target, api.address);
+        assert_eq!(api_metric.metric_type, "httpget");
+        assert_eq!(api_metric.hostname.as_deref(), Some("edge-01"));
+        assert_eq!(api_metric.timestamp, at("2026-03-01T12:00:00Z"));
+        assert_eq!(api_metric.check_count, Some(4));
+        assert_eq!(api_metric.success_rate_percent, Some(75.0));
+        assert!(matches!(
+            api_metric.status,
+            Some(AggregatedMetricStatus::Degraded)
+        ));
+        assert_eq!(api_metric.avg_response_time_ms, Some(25.0));
+        assert_eq!(api_metric.min_response_time_ms, Some(10.0));
+        assert_eq!(api_metric.max_response_time_ms, Some(40.0));
+        assert_eq!(api_metric.p50_response_time_ms, Some(20.0));
+        assert_eq!(api_metric.p99_response_time_ms, Some(40.0));
+
+        let web_metric = &report.data[1];
+        assert_eq!(web_metric.target, web.address);
+        assert_eq!(web_metric.check_count, Some(1));
+        assert!(matches!(
+            web_metric.status,
+            Some(AggregatedMetricStatus::Reachable)
+        ));
+    }
+}
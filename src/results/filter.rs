@@ -0,0 +1,201 @@
+//! Filtering of results before they are cached for reporting
+//!
+//! `[reporting.filter]` narrows down which results are sent to the server,
+//! e.g. only failures or only endpoints with certain tags. The filter runs in
+//! the result collection loop right before a result is pushed into the
+//! `ResultCache`, after local statistics, the audit log and sinks have seen it.
+//! The reporter applies it again when sending, so a filter tightened by a hot
+//! reload also drops results that were cached before the change.
+
+use crate::agent_config::{Config, ResultFilter, StatusFilter};
+use crate::core::{Endpoint, MonitoringResult};
+
+impl Config {
+    /// Whether `result` passes `[reporting.filter]`, looking up its endpoint
+    /// in the configured endpoints
+    pub(crate) fn should_report(&self, result: &MonitoringResult) -> bool {
+        let filter = &self.reporting.filter;
+        if filter.is_pass_all() {
+            return true;
+        }
+
+        let endpoint = self.endpoints.iter().find(|e| e.id == result.endpoint_id);
+        filter.matches(result, endpoint)
+    }
+}
+
+impl ResultFilter {
+    /// Whether every result passes this filter
+    pub fn is_pass_all(&self) -> bool {
+        self.status == StatusFilter::All && self.kinds.is_empty() && self.tags.is_empty()
+    }
+
+    /// Whether `result` should be reported.
+    ///
+    /// `endpoint` is the configured endpoint the result belongs to, used for
+    /// kind and tag predicates. Results whose endpoint is no longer configured
+    /// only pass filters without such predicates.
+    pub fn matches(&self, result: &MonitoringResult, endpoint: Option<&Endpoint>) -> bool {
+        let status_matches = match self.status {
+            StatusFilter::All => true,
+            StatusFilter::Failures => !result.is_successful(),
+            StatusFilter::Successes => result.is_successful(),
+        };
+
+        let kind_matches =
+            self.kinds.is_empty() || endpoint.is_some_and(|e| self.kinds.contains(&e.check_kind()));
+
+        let tag_matches = self.tags.is_empty()
+            || endpoint.is_some_and(|e| e.tags.iter().any(|tag| self.tags.contains(tag)));
+
+        status_matches && kind_matches && tag_matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CheckKind, CheckType, PingCheck, PingCheckType, PingResult};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn make_result(endpoint: &Endpoint, successful: bool) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: endpoint.id,
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "127.0.0.1".to_string(),
+                    successes: if successful { 3 } else { 0 },
+                    failures: if successful { 0 } else { 3 },
+                    success_latencies: Vec::new(),
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn tagged(address: &str, tags: &[&str]) -> Endpoint {
+        let mut endpoint = Endpoint::new(address);
+        endpoint.tags = tags.iter().map(|t| t.to_string()).collect();
+        endpoint
+    }
+
+    #[test]
+    fn test_default_filter_reports_everything() {
+        let filter = ResultFilter::default();
+        let endpoint = Endpoint::new("8.8.8.8");
+
+        assert!(filter.is_pass_all());
+        assert!(filter.matches(&make_result(&endpoint, true), Some(&endpoint)));
+        assert!(filter.matches(&make_result(&endpoint, false), None));
+    }
+
+    #[test]
+    fn test_failures_only() {
+        let filter = ResultFilter {
+            status: StatusFilter::Failures,
+            ..Default::default()
+        };
+        let endpoint = Endpoint::new("8.8.8.8");
+
+        assert!(!filter.is_pass_all());
+        assert!(!filter.matches(&make_result(&endpoint, true), Some(&endpoint)));
+        assert!(filter.matches(&make_result(&endpoint, false), Some(&endpoint)));
+    }
+
+    #[test]
+    fn test_successes_only() {
+        let filter = ResultFilter {
+            status: StatusFilter::Successes,
+            ..Default::default()
+        };
+        let endpoint = Endpoint::new("8.8.8.8");
+
+        assert!(filter.matches(&make_result(&endpoint, true), Some(&endpoint)));
+        assert!(!filter.matches(&make_result(&endpoint, false), Some(&endpoint)));
+    }
+
+    #[test]
+    fn test_tag_scoped() {
+        let filter = ResultFilter {
+            tags: vec!["prod".to_string(), "critical".to_string()],
+            ..Default::default()
+        };
+        let prod = tagged("10.0.0.1", &["dns", "prod"]);
+        let staging = tagged("10.0.0.2", &["dns", "staging"]);
+
+        assert!(filter.matches(&make_result(&prod, true), Some(&prod)));
+        assert!(!filter.matches(&make_result(&staging, true), Some(&staging)));
+        assert!(!filter.matches(&make_result(&prod, true), None));
+    }
+
+    #[test]
+    fn test_kind_scoped() {
+        let filter = ResultFilter {
+            kinds: vec![CheckKind::Http],
+            ..Default::default()
+        };
+        let http = Endpoint::new("https://example.com/health");
+        let ping = Endpoint::new("example.com");
+
+        assert!(filter.matches(&make_result(&http, true), Some(&http)));
+        assert!(!filter.matches(&make_result(&ping, true), Some(&ping)));
+    }
+
+    #[test]
+    fn test_predicates_are_combined() {
+        let filter = ResultFilter {
+            status: StatusFilter::Failures,
+            tags: vec!["prod".to_string()],
+            ..Default::default()
+        };
+        let prod = tagged("10.0.0.1", &["prod"]);
+        let staging = tagged("10.0.0.2", &["staging"]);
+
+        assert!(filter.matches(&make_result(&prod, false), Some(&prod)));
+        assert!(!filter.matches(&make_result(&prod, true), Some(&prod)));
+        assert!(!filter.matches(&make_result(&staging, false), Some(&staging)));
+    }
+
+    #[test]
+    fn test_should_report_looks_up_endpoint_tags() {
+        let prod = tagged("10.0.0.1", &["prod"]);
+        let staging = tagged("10.0.0.2", &["staging"]);
+        let mut config = Config {
+            endpoints: vec![prod.clone(), staging.clone()],
+            ..Default::default()
+        };
+        config.reporting.filter.tags = vec!["prod".to_string()];
+
+        assert!(config.should_report(&make_result(&prod, true)));
+        assert!(!config.should_report(&make_result(&staging, true)));
+    }
+
+    #[test]
+    fn test_filter_from_toml() {
+        let toml = r#"
+            status = "failures"
+            kinds = ["ping", "http"]
+            tags = ["prod"]
+        "#;
+        let filter: ResultFilter = toml::from_str(toml).unwrap();
+
+        assert_eq!(filter.status, StatusFilter::Failures);
+        assert_eq!(filter.kinds, vec![CheckKind::Ping, CheckKind::Http]);
+        assert_eq!(filter.tags, vec!["prod".to_string()]);
+    }
+}
@@ -1,3 +1,8 @@
+mod buckets;
+mod filter;
+mod routes;
 mod server;
+mod stream;
 
+pub(crate) use server::flush_on_shutdown;
 pub use server::run_result_reporter;
@@ -0,0 +1,161 @@
+//! Routing of reported results to URLs by check kind
+//!
+//! By default every result is POSTed to `{server.url}/agent/{agent_id}/results`.
+//! `[reporting.routes]` maps check kinds to other URLs, e.g. so a backend can
+//! ingest ping and HTTP metrics at different paths:
+//!
+//! ```toml
+//! [reporting.routes]
+//! ping = "/ingest/ping"                         # relative to server.url
+//! http = "https://metrics.example.com/http"     # absolute
+//! ```
+//!
+//! Kinds without a route, and result types no check kind produces, use the
+//! default URL.
+
+use crate::agent_config::Config;
+use crate::core::MonitoringResult;
+
+impl Config {
+    /// Default URL results are reported to
+    pub(crate) fn default_report_url(&self) -> String {
+        format!("{}/agent/{}/results", self.server.url, self.agent_id)
+    }
+
+    /// URL `result` is reported to
+    pub(crate) fn report_url(&self, result: &MonitoringResult) -> String {
+        let route = result
+            .check_kind()
+            .and_then(|kind| self.reporting.routes.get(&kind));
+
+        match route {
+            Some(route) if route.starts_with('/') => format!("{}{}", self.server.url, route),
+            Some(route) => route.clone(),
+            None => self.default_report_url(),
+        }
+    }
+
+    /// Split `results` into batches per report URL, keeping the order of
+    /// results within each batch and of first appearance across batches
+    pub(crate) fn partition_by_route(
+        &self,
+        results: Vec<MonitoringResult>,
+    ) -> Vec<(String, Vec<MonitoringResult>)> {
+        let mut batches: Vec<(String, Vec<MonitoringResult>)> = Vec::new();
+        for result in results {
+            let url = self.report_url(&result);
+            match batches.iter_mut().find(|(batch_url, _)| *batch_url == url) {
+                Some((_, batch)) => batch.push(result),
+                None => batches.push((url, vec![result])),
+            }
+        }
+        batches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        CheckKind, CheckType, GrpcHealthCheck, GrpcHealthCheckType, GrpcHealthResult, PingCheck,
+        PingCheckType, PingResult,
+    };
+    use uuid::Uuid;
+
+    fn result(check_type: CheckType) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::nil(),
+            endpoint_id: Uuid::now_v7(),
+            check_type,
+            timestamp: chrono::Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn ping() -> MonitoringResult {
+        result(CheckType::PingCheck(PingCheck {
+            r#type: PingCheckType::Ping,
+            result: PingResult {
+                resolved_ip: "10.0.0.1".to_string(),
+                successes: 1,
+                failures: 0,
+                success_latencies: vec![1.0],
+                error_details: None,
+                dns_resolution_ms: None,
+                required_successes: None,
+                resolved_hostname: None,
+                packet_loss_percent: None,
+                jitter_ms: None,
+            },
+        }))
+    }
+
+    fn grpc() -> MonitoringResult {
+        result(CheckType::GrpcHealthCheck(GrpcHealthCheck {
+            r#type: GrpcHealthCheckType::Grpchealth,
+            result: GrpcHealthResult {
+                service: String::new(),
+                serving_status: Some("SERVING".to_string()),
+                response_time_ms: Some(1.0),
+                error_details: None,
+                success: true,
+            },
+        }))
+    }
+
+    fn routed_config() -> Config {
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Default::default()
+        };
+        config.server.url = "https://api.example.com".to_string();
+        config
+            .reporting
+            .routes
+            .insert(CheckKind::Ping, "/ingest/ping".to_string());
+        config.reporting.routes.insert(
+            CheckKind::Http,
+            "https://metrics.example.com/http".to_string(),
+        );
+        config
+    }
+
+    #[test]
+    fn test_report_url_resolution() {
+        let mut config = routed_config();
+        assert_eq!(
+            config.report_url(&ping()),
+            "https://api.example.com/ingest/ping"
+        );
+        assert_eq!(config.report_url(&grpc()), config.default_report_url());
+
+        config
+            .reporting
+            .routes
+            .insert(CheckKind::Grpc, "https://grpc.example.com/in".to_string());
+        assert_eq!(config.report_url(&grpc()), "https://grpc.example.com/in");
+    }
+
+    #[test]
+    fn test_partition_keeps_order_within_routes() {
+        let config = routed_config();
+        let results = vec![ping(), grpc(), ping()];
+        let ids: Vec<_> = results.iter().map(|r| r.id).collect();
+
+        let batches = config.partition_by_route(results);
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].0, "https://api.example.com/ingest/ping");
+        assert_eq!(
+            batches[0].1.iter().map(|r| r.id).collect::<Vec<_>>(),
+            [ids[0], ids[2]]
+        );
+        assert_eq!(batches[1].0, config.default_report_url());
+        assert_eq!(batches[1].1[0].id, ids[1]);
+    }
+}
@@ -8,23 +8,93 @@
 //! are now the canonical types used end-to-end, results are serialized directly
 //! without any adapter layer.
 //!
+//! Results are the agent's main payload and are sent here only; the status
+//! reporter posts `AgentStatus` to `/agent/{agentId}/report` on its own
+//! interval. The spec's `Metric` shape belongs to the server's query API
+//! and is not used for ingestion.
+//!
 //! # Peek-then-drain semantics
 //!
 //! 1. `peek_batch(n)` — clone the first N items without removing them.
 //! 2. POST the batch.
 //! 3. `drain_front(n)` on success only — guarantees at-least-once delivery.
 //!    On failure the same items will be retried on the next tick.
+//!
+//! # Paged flushing
+//!
+//! After an outage the cache may hold a large backlog. Each tick drains the
+//! backlog in pages of `cache_batch_size` results, one request per page, so
+//! only a single page is ever cloned and serialized at a time.
+//!
+//! # Filtering
+//!
+//! Results are filtered by `[reporting.filter]` before they are cached. Each
+//! page is filtered again before sending, so results cached before a filter
+//! change are not reported if they no longer match. Filtered-out results are
+//! drained together with the rest of their page.
+//!
+//! # Routing
+//!
+//! `[reporting.routes]` can send each check kind to its own URL (see
+//! `routes.rs`). A page is split into one request per route; it is drained
+//! only after every request succeeded, so a failed route re-sends the whole
+//! page on the next tick.
+//!
+//! # Size limit
+//!
+//! With `server.max_report_bytes` set, each route's batch is split into as
+//! many requests as needed to keep every JSON payload within the limit. A
+//! result that exceeds the limit on its own can never be delivered; it is
+//! moved to the dead-letter file with a warning and treated as acknowledged
+//! so it does not block the results behind it. Bucketed reports are not
+//! split.
+//!
+//! # Idempotent retries
+//!
+//! Every result is sent with its UUID `id` and the server skips IDs it has
+//! already stored, so a request that failed after the server processed it
+//! (e.g. the connection dropped before the response) can be retried without
+//! double counting. Requests that did succeed are marked acknowledged in the
+//! cache: when their page is retried only the unacknowledged results are
+//! sent again, and drained results are never cached a second time.
+//!
+//! # Time buckets
+//!
+//! With `reporting.bucket_secs` set, each page is cut at the first result
+//! whose time bucket is still open and sent as one aggregated
+//! `ResultReport` per bucket instead (see `buckets.rs`). Results of the
+//! open bucket stay cached for a later tick.
+//!
+//! # Streaming
+//!
+//! With `reporting.mode = "stream"` the reporter sends the backlog as above
+//! and then streams new results over one long-lived request (see
+//! `stream.rs`). Batches are still sent on every tick while the stream is
+//! reconnecting or when the server does not support it.
+//!
+//! # Tracing
+//!
+//! Each request runs inside a `report_results` span with the number of
+//! `results`, the server `host` and, once answered, the HTTP `status`.
+//! Bucketed reports use a `report_bucket` span with the number of
+//! `metrics` instead. The full URL and the API key are not recorded.
 
+use super::buckets::{bucket_reports, bucket_start, closed_prefix};
+use super::stream::Streamer;
 use crate::agent_config::Config;
-use crate::cache::ResultCache;
-use crate::core::{AgentStatus, MonitoringResult};
+use crate::cache::{DeadLetter, ResultCache, SegmentCodec};
+use crate::core::{
+    agent_hostname, wait_for_shutdown, AgentStatus, MonitoringResult, ReportingSwitch,
+    ShutdownReceiver, SHUTDOWN_FLUSH_TIMEOUT,
+};
 use crate::error::{Error, Result};
 use crate::openapi;
+use crate::reporter::{json_body, ServerClient};
 use parking_lot::RwLock;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::time::interval;
-use tracing::{debug, error, info};
+use tokio::time::{interval, sleep_until, Instant};
+use tracing::field::Empty;
+use tracing::{debug, error, info, instrument, warn};
 
 // ============================================================
 // Reporter loop
@@ -32,18 +102,30 @@ use tracing::{debug, error, info};
 
 /// Run the result-reporter loop.
 ///
-/// Periodically drains a batch from `result_cache` and posts it to the server.
-/// Items are only removed from the cache after the server acknowledges the POST
+/// Periodically drains `result_cache` page by page and posts each page to the
+/// server. Items are only removed from the cache after the server acknowledges the POST
 /// (peek-then-drain semantics). If the POST fails, the same items are retried
 /// on the next tick.
 ///
 /// The loop respects hot-reloaded configuration (server URL, API key, intervals,
 /// cache settings) by reading a fresh config snapshot on every tick.
+///
+/// While `reporting` is paused, ticks send nothing and results stay cached.
+/// Resuming flushes the backlog right away rather than on the next tick, and
+/// so does the server becoming reachable again after an outage.
+///
+/// The last flush on shutdown is left to `Agent::start`, which runs
+/// [`flush_on_shutdown`] once every task producing results has stopped.
+///
+/// Batches are posted with `client`, the connection pool shared with the
+/// status reporter and the heartbeat.
 pub async fn run_result_reporter(
     config: Arc<RwLock<Config>>,
     result_cache: Arc<ResultCache>,
     agent_status: Arc<RwLock<AgentStatus>>,
-    mut agent_shutdown_rx: broadcast::Receiver<()>,
+    client: ServerClient,
+    reporting: ReportingSwitch,
+    mut agent_shutdown_rx: ShutdownReceiver,
 ) -> Result<()> {
     info!("Starting result reporter");
 
@@ -57,99 +139,334 @@ pub async fn run_result_reporter(
     let mut iv = interval(current_interval_duration);
     iv.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
+    let mut paused_rx = reporting.subscribe();
+    let mut streamer = Streamer::default();
+
     loop {
+        let stream_retry_at = streamer.retry_at();
         tokio::select! {
-            _ = iv.tick() => {
-                let config_snapshot = config.read().clone();
-
-                // Exit early if caching is disabled via hot-reload.
-                if !config_snapshot.storage.cache_enabled {
-                    info!("Result cache disabled via hot-reload, result reporter exiting");
-                    break;
+            _ = iv.tick() => {}
+            _ = sleep_until(stream_retry_at.unwrap_or_else(Instant::now)), if stream_retry_at.is_some() => {
+                streamer.clear_retry();
+            }
+            Ok(()) = paused_rx.changed() => {
+                if *paused_rx.borrow_and_update() {
+                    info!("Result reporting paused, results stay cached");
+                    continue;
                 }
+                info!("Result reporting resumed, flushing cached results");
+            }
+            _ = reporting.reconnected() => {
+                debug!("Server reachable again, flushing cached results");
+            }
+            _ = wait_for_shutdown(&mut agent_shutdown_rx) => {
+                info!("Result reporter shutting down");
+                break;
+            }
+        }
 
-                // Hot-reload interval changes.
-                let new_interval = std::time::Duration::from_secs(
-                    config_snapshot.storage.cache_report_interval_secs,
-                );
-                if new_interval != current_interval_duration {
-                    info!(
-                        "Result report interval changed from {:?} to {:?}, recreating timer",
-                        current_interval_duration, new_interval
-                    );
-                    current_interval_duration = new_interval;
-                    iv = interval(current_interval_duration);
-                    iv.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-                }
+        let config_snapshot = config.read().clone();
 
-                if !config_snapshot.server.is_configured() {
-                    debug!("Server not configured, skipping result batch send");
-                    continue;
-                }
+        // Exit early if caching is disabled via hot-reload.
+        if !config_snapshot.storage.cache_enabled {
+            info!("Result cache disabled via hot-reload, result reporter exiting");
+            break;
+        }
 
-                let batch_size = config_snapshot.storage.cache_batch_size;
-                let batch = result_cache.peek_batch(batch_size).await;
+        // Hot-reload interval changes.
+        let new_interval =
+            std::time::Duration::from_secs(config_snapshot.storage.cache_report_interval_secs);
+        if new_interval != current_interval_duration {
+            info!(
+                "Result report interval changed from {:?} to {:?}, recreating timer",
+                current_interval_duration, new_interval
+            );
+            current_interval_duration = new_interval;
+            iv = interval(current_interval_duration);
+            iv.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        }
 
-                if batch.is_empty() {
-                    debug!("Result cache empty, nothing to send");
-                    continue;
-                }
+        if reporting.is_paused() {
+            debug!("Result reporting paused, skipping result batch send");
+            continue;
+        }
 
-                debug!("Sending batch of {} results to server", batch.len());
-
-                match send_result_batch(&config_snapshot, &batch).await {
-                    Ok(()) => {
-                        let sent = batch.len();
-                        result_cache.drain_front(sent).await;
-                        let stats = result_cache.stats().await;
-                        {
-                            let mut s = agent_status.write();
-                            s.cache_stats.len = stats.len as i64;
-                            s.cache_stats.capacity = stats.capacity as i64;
-                        }
-                        debug!(
-                            "Sent {} results, {} remaining in cache",
-                            sent, stats.len
-                        );
-                    }
-                    Err(e) => {
-                        agent_status.write().failed_report_count += 1;
-                        error!("Failed to send result batch: {}", e);
-                    }
-                }
-            }
-            _ = agent_shutdown_rx.recv() => {
+        if !config_snapshot.server.is_configured() {
+            debug!("Server not configured, skipping result batch send");
+            continue;
+        }
+
+        if streamer.is_due(&config_snapshot) {
+            let running = streamer
+                .run(
+                    &config,
+                    &result_cache,
+                    &agent_status,
+                    &client,
+                    &reporting,
+                    &mut agent_shutdown_rx,
+                )
+                .await;
+            if !running {
                 info!("Result reporter shutting down");
                 break;
             }
+            continue;
+        }
+
+        match flush_cache_paged(&config_snapshot, &result_cache, &agent_status, &client).await {
+            Ok(0) => debug!("Result cache empty, nothing to send"),
+            Ok(sent) => {
+                reporting.record_report_success(&mut agent_status.write());
+                let stats = result_cache.stats().await;
+                debug!("Sent {} results, {} remaining in cache", sent, stats.len);
+            }
+            Err(e) => {
+                reporting.record_report_failure(
+                    &mut agent_status.write(),
+                    config_snapshot.server.connection_failure_grace(),
+                );
+                error!("Failed to send result batch: {}", e);
+            }
         }
     }
 
     Ok(())
 }
 
-/// POST `POST /agent/{agentId}/results` with a batch payload.
-async fn send_result_batch(config: &Config, batch: &[MonitoringResult]) -> Result<()> {
-    let agent_id = config.agent_id;
-    let server_url = &config.server.url;
+/// Flush the cache a last time before shutdown, giving up after
+/// [`SHUTDOWN_FLUSH_TIMEOUT`]
+///
+/// Sends nothing while `reporting` is paused or without a server. Returns the
+/// number of results removed from the cache; the rest stay cached.
+pub(crate) async fn flush_on_shutdown(
+    config: &Config,
+    result_cache: &ResultCache,
+    agent_status: &RwLock<AgentStatus>,
+    client: &ServerClient,
+    reporting: &ReportingSwitch,
+) -> usize {
+    if !config.storage.cache_enabled || reporting.is_paused() || !config.server.is_configured() {
+        return 0;
+    }
+
+    let flush = flush_cache_paged(config, result_cache, agent_status, client);
+    match tokio::time::timeout(SHUTDOWN_FLUSH_TIMEOUT, flush).await {
+        Ok(Ok(sent)) => sent,
+        Ok(Err(e)) => {
+            warn!("Failed to send cached results before shutdown: {}", e);
+            0
+        }
+        Err(_) => {
+            warn!(
+                "Cached results not sent within {:?}, they stay cached",
+                SHUTDOWN_FLUSH_TIMEOUT
+            );
+            0
+        }
+    }
+}
+
+/// Drain the cache to the server one bounded page at a time.
+///
+/// Each page holds at most `storage.cache_batch_size` results and is sent as a
+/// separate request. A page is removed from the cache only after the server
+/// acknowledges it, so peak memory is bounded by the page size regardless of
+/// how large the backlog grew during an outage.
+///
+/// The number of pages sent per call is capped by the cache depth observed at
+/// the start of the flush, so results produced while flushing are left for the
+/// next tick instead of keeping this loop busy indefinitely.
+///
+/// Returns the number of results removed from the cache, i.e. acknowledged by
+/// the server or dropped by the reporting filter. If a page fails, the error is
+/// returned and the page (and everything behind it) stays cached.
+pub(super) async fn flush_cache_paged(
+    config: &Config,
+    result_cache: &ResultCache,
+    agent_status: &RwLock<AgentStatus>,
+    client: &ServerClient,
+) -> Result<usize> {
+    let page_size = config.storage.cache_batch_size.max(1);
+    let mut remaining = result_cache.stats().await.len;
+    let mut sent = 0;
+
+    while remaining > 0 {
+        let page = result_cache.peek_batch(page_size.min(remaining)).await;
+        if page.is_empty() {
+            break;
+        }
+
+        let acked = match config.reporting.bucket_secs {
+            Some(bucket_secs) => closed_prefix(&page, bucket_secs, chrono::Utc::now()),
+            None => page.len(),
+        };
+        if acked == 0 {
+            debug!("Only results of the open time bucket are cached, waiting for it to end");
+            break;
+        }
+
+        let reportable: Vec<_> = page
+            .into_iter()
+            .take(acked)
+            .filter(|result| config.should_report(result))
+            .collect();
+        let reportable = result_cache.unacknowledged(reportable).await;
+
+        if reportable.is_empty() {
+            debug!(
+                "Page of {} results filtered out or already acknowledged, nothing to send",
+                acked
+            );
+        } else if let Some(bucket_secs) = config.reporting.bucket_secs {
+            let url = config.bucket_report_url();
+            let hostname = agent_hostname(config);
+            for report in bucket_reports(config, &reportable, bucket_secs, &hostname) {
+                debug!("Sending report of {} to {}", report.time_range.start, url);
+                send_bucket_report(config, client, &url, &report).await?;
+                let start = report.time_range.start;
+                result_cache
+                    .acknowledge(
+                        reportable
+                            .iter()
+                            .filter(|result| bucket_start(result.timestamp, bucket_secs) == start)
+                            .map(|result| result.id),
+                    )
+                    .await;
+            }
+        } else {
+            for (url, batch) in config.partition_by_route(reportable) {
+                let batches = match config.server.max_report_bytes {
+                    Some(max_bytes) => {
+                        split_by_size(config, result_cache, batch, max_bytes).await?
+                    }
+                    None => vec![batch],
+                };
+                for batch in batches {
+                    debug!("Sending {} results to {}", batch.len(), url);
+                    send_result_batch(config, client, &url, &batch).await?;
+                    result_cache
+                        .acknowledge(batch.iter().map(|result| result.id))
+                        .await;
+                }
+            }
+        }
+        result_cache.drain_front(acked).await;
+
+        let stats = result_cache.stats().await;
+        {
+            let mut s = agent_status.write();
+            s.cache_stats.len = stats.len as i64;
+            s.cache_stats.capacity = stats.capacity as i64;
+        }
+
+        sent += acked;
+        remaining = remaining.saturating_sub(acked);
+    }
+
+    Ok(sent)
+}
+
+/// JSON envelope of a batch, `{"results":[` and `]}`
+const BATCH_ENVELOPE_BYTES: usize = r#"{"results":[]}"#.len();
+
+/// Split `batch` into batches whose payload fits in `max_bytes`
+///
+/// Results keep their order. A result too large for any batch is written to
+/// the dead-letter file and acknowledged, so it is drained with its page
+/// instead of being retried forever.
+async fn split_by_size(
+    config: &Config,
+    result_cache: &ResultCache,
+    batch: Vec<MonitoringResult>,
+    max_bytes: usize,
+) -> Result<Vec<Vec<MonitoringResult>>> {
+    let mut batches = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = BATCH_ENVELOPE_BYTES;
+
+    for result in batch {
+        let size = serde_json::to_vec(&result)?.len();
+        if BATCH_ENVELOPE_BYTES + size > max_bytes {
+            let reason = format!(
+                "result of {} bytes exceeds server.max_report_bytes ({})",
+                size, max_bytes
+            );
+            let dead_letter = DeadLetter::new(
+                config.storage.dead_letter_file(),
+                SegmentCodec::from_config(&config.storage)?,
+            );
+            warn!(
+                "Result {} of endpoint {} not sent, {}; moved to {}",
+                result.id,
+                result.endpoint_id,
+                reason,
+                dead_letter.path().display()
+            );
+            dead_letter.record(&result, &reason)?;
+            result_cache.acknowledge([result.id]).await;
+            continue;
+        }
+
+        // Results after the first are preceded by a comma
+        let added = size + usize::from(!current.is_empty());
+        if current_bytes + added > max_bytes {
+            batches.push(std::mem::take(&mut current));
+            current_bytes = BATCH_ENVELOPE_BYTES;
+            current_bytes += size;
+        } else {
+            current_bytes += added;
+        }
+        current.push(result);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    if batches.len() > 1 {
+        debug!(
+            "Split results into {} requests of at most {} bytes",
+            batches.len(),
+            max_bytes
+        );
+    }
+    Ok(batches)
+}
+
+/// Host of `url` for tracing, without credentials, path or query
+fn url_host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
 
-    let client = reqwest::Client::builder()
-        .timeout(config.server.timeout())
-        .danger_accept_invalid_certs(!config.server.verify_tls)
-        .build()?;
+/// POST a batch payload to `url`, by default `POST /agent/{agentId}/results`.
+#[instrument(
+    name = "report_results",
+    skip_all,
+    fields(results = batch.len(), host = %url_host(url), status = Empty)
+)]
+async fn send_result_batch(
+    config: &Config,
+    client: &ServerClient,
+    url: &str,
+    batch: &[MonitoringResult],
+) -> Result<()> {
+    let client = client.get(&config.server)?;
 
-    let url = format!("{}/agent/{}/results", server_url, agent_id);
     let payload = openapi::BatchMonitoringResults {
         results: batch.to_vec(),
     };
 
-    let mut request = client.post(&url).json(&payload);
+    let mut request = json_body(config, client.post(url), &payload)?;
 
     if let Some(api_key) = &config.server.api_key {
         request = request.header("X-Agent-API-Key", api_key);
     }
 
     let response = request.send().await?;
+    tracing::Span::current().record("status", response.status().as_u16());
 
     if !response.status().is_success() {
         return Err(Error::Network(format!(
@@ -177,6 +494,39 @@ async fn send_result_batch(config: &Config, batch: &[MonitoringResult]) -> Resul
     Ok(())
 }
 
+/// POST one time bucket to `url`, by default `POST /agent/{agentId}/reports`.
+#[instrument(
+    name = "report_bucket",
+    skip_all,
+    fields(metrics = report.data.len(), host = %url_host(url), status = Empty)
+)]
+async fn send_bucket_report(
+    config: &Config,
+    client: &ServerClient,
+    url: &str,
+    report: &openapi::ResultReport,
+) -> Result<()> {
+    let client = client.get(&config.server)?;
+
+    let mut request = json_body(config, client.post(url), report)?;
+
+    if let Some(api_key) = &config.server.api_key {
+        request = request.header("X-Agent-API-Key", api_key);
+    }
+
+    let response = request.send().await?;
+    tracing::Span::current().record("status", response.status().as_u16());
+
+    if !response.status().is_success() {
+        return Err(Error::Network(format!(
+            "Server returned {} for result report POST",
+            response.status()
+        )));
+    }
+
+    Ok(())
+}
+
 // ============================================================
 // Tests
 // ============================================================
@@ -201,9 +551,19 @@ mod tests {
                     failures: 0,
                     success_latencies: vec![1.0, 2.0, 3.0],
                     error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
                 },
             }),
             timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
         }
     }
 
@@ -243,6 +603,7 @@ mod tests {
                     connect_time_ms: Some(5.0),
                     error_details: None,
                     resolved_ip: String::new(),
+                    dns_resolution_ms: None,
                 },
             });
             let batch = make_batch(vec![result]);
@@ -262,7 +623,11 @@ mod tests {
                 result: HttpGetResult {
                     status_code: 200,
                     response_time_ms: Some(100.0),
+                    dns_resolution_ms: None,
                     response_size_bytes: Some(1024),
+                    body_truncated: None,
+                    body_matched: None,
+                    body_match_detail: None,
                     error_details: None,
                     success: true,
                 },
@@ -307,12 +672,15 @@ mod tests {
 
 #[cfg(test)]
 mod reporter_loop_tests {
-    use super::run_result_reporter;
+    use super::{flush_on_shutdown, run_result_reporter};
     use crate::agent_config::{Config, MonitoringConfig, ServerConfig, StorageConfig};
     use crate::cache::ResultCache;
     use crate::core::{
-        AgentStatus, CheckType, MonitoringResult, PingCheck, PingCheckType, PingResult,
+        shutdown_channel, trigger_shutdown, AgentStatus, CheckType, Endpoint, MonitoringResult,
+        PingCheck, PingCheckType, PingResult, ReportingSwitch,
     };
+    use crate::error::Result;
+    use crate::reporter::ServerClient;
     use parking_lot::RwLock;
     use std::sync::Arc;
     use std::time::Duration;
@@ -331,9 +699,19 @@ mod reporter_loop_tests {
                     failures: 0,
                     success_latencies: vec![1.0, 2.0, 3.0],
                     error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
                 },
             }),
             timestamp: chrono::Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
         }
     }
 
@@ -360,12 +738,24 @@ mod reporter_loop_tests {
             version: 1,
             agent_id: Uuid::now_v7(),
             agent_name: "Test Agent".to_string(),
+            hostname: None,
             tags: vec![],
+            metrics_addr: None,
             monitoring: MonitoringConfig::default(),
             server,
             storage,
             update: Default::default(),
+            audit: Default::default(),
+            sinks: Default::default(),
+            reporting: Default::default(),
+            control: Default::default(),
+            alerting: Default::default(),
+            hot_reload: Default::default(),
+            plugins: Default::default(),
+            logging: Default::default(),
+            thresholds: Vec::new(),
             endpoints: vec![],
+            env_templates: Vec::new(),
         }))
     }
 
@@ -441,13 +831,23 @@ mod reporter_loop_tests {
         assert_eq!(cache.len().await, 5);
 
         let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
-        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
 
         let reporter_task = tokio::spawn({
             let cache = Arc::clone(&cache);
             let config = Arc::clone(&config);
             let status = Arc::clone(&agent_status);
-            async move { run_result_reporter(config, cache, status, shutdown_rx).await }
+            async move {
+                run_result_reporter(
+                    config,
+                    cache,
+                    status,
+                    ServerClient::default(),
+                    ReportingSwitch::default(),
+                    shutdown_rx,
+                )
+                .await
+            }
         });
 
         let req_body = tokio::time::timeout(Duration::from_secs(5), body_rx)
@@ -456,7 +856,7 @@ mod reporter_loop_tests {
             .expect("server channel closed");
 
         tokio::time::sleep(Duration::from_millis(100)).await;
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let _ = tokio::time::timeout(Duration::from_secs(2), reporter_task).await;
 
         assert_eq!(
@@ -471,6 +871,120 @@ mod reporter_loop_tests {
         );
     }
 
+    /// Spawn a mock server answering 503 while `up` is false and 202 with an
+    /// acknowledgement once it is true.
+    async fn spawn_mock_server_toggle(
+        up: Arc<std::sync::atomic::AtomicBool>,
+    ) -> std::net::SocketAddr {
+        use std::sync::atomic::Ordering;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let ack_body = r#"{"submission_id":"00000000-0000-0000-0000-000000000001","accepted":1,"received_at":"2026-01-01T00:00:00Z"}"#;
+        let accepted = format!(
+            "HTTP/1.1 202 Accepted\r\nConnection: close\r\nContent-Length: {}\r\nContent-Type: application/json\r\n\r\n{}",
+            ack_body.len(),
+            ack_body,
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                if let Ok((mut stream, _)) = listener.accept().await {
+                    let mut buf = vec![0u8; 16384];
+                    let _ = stream.read(&mut buf).await;
+                    let response = if up.load(Ordering::SeqCst) {
+                        accepted.as_str()
+                    } else {
+                        "HTTP/1.1 503 Service Unavailable\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"
+                    };
+                    let _ = stream.write_all(response.as_bytes()).await;
+                }
+            }
+        });
+
+        addr
+    }
+
+    /// Results cached while the server is down are flushed as soon as a
+    /// status report reaches it again, long before the next result tick.
+    #[tokio::test]
+    async fn reporter_flushes_cache_when_server_comes_back() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let up = Arc::new(AtomicBool::new(false));
+        let addr = spawn_mock_server_toggle(Arc::clone(&up)).await;
+
+        let cache = make_cache(100, 3600);
+        let config = make_config(&format!("http://{}", addr));
+        {
+            let mut config = config.write();
+            config.storage.cache_report_interval_secs = 3600;
+            config.server.report_interval_secs = 1;
+        }
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let reporting = ReportingSwitch::default();
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let result_reporter = tokio::spawn(run_result_reporter(
+            Arc::clone(&config),
+            Arc::clone(&cache),
+            Arc::clone(&agent_status),
+            ServerClient::default(),
+            reporting.clone(),
+            shutdown_rx,
+        ));
+        let status_reporter = tokio::spawn({
+            let config = Arc::clone(&config);
+            let status = Arc::clone(&agent_status);
+            let reporting = reporting.clone();
+            let mut shutdown_rx = shutdown_tx.subscribe();
+            async move {
+                crate::reporter::run_reporter(
+                    config,
+                    status,
+                    tokio::time::Instant::now(),
+                    crate::reporter::ServerClient::default(),
+                    crate::core::Readiness::default(),
+                    reporting,
+                    tokio::sync::mpsc::unbounded_channel().0,
+                    &mut shutdown_rx,
+                )
+                .await
+            }
+        });
+
+        for i in 0..5 {
+            cache.push(make_ping_result(&format!("10.0.0.{}", i))).await;
+        }
+        // Both reporters have tried and failed while the server is down
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            cache.len().await,
+            5,
+            "nothing is sent while the server is down"
+        );
+        assert!(agent_status.read().failed_report_count > 0);
+
+        up.store(true, Ordering::SeqCst);
+        let drained = tokio::time::timeout(Duration::from_secs(5), async {
+            while cache.len().await > 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+
+        trigger_shutdown(&shutdown_tx);
+        let _ = tokio::time::timeout(Duration::from_secs(2), result_reporter).await;
+        let _ = tokio::time::timeout(Duration::from_secs(2), status_reporter).await;
+
+        assert!(drained.is_ok(), "cache must empty once the server is back");
+        assert!(agent_status.read().server_connected);
+        assert_eq!(agent_status.read().cache_stats.len, 0);
+    }
+
     #[tokio::test]
     async fn reporter_does_not_drain_cache_on_server_error() {
         let addr = spawn_mock_server_503().await;
@@ -479,24 +993,38 @@ mod reporter_loop_tests {
         let cache = make_cache(100, 3600);
         let config = make_config(&server_url);
         config.write().storage.cache_report_interval_secs = 1;
+        config.write().server.connection_failure_grace = Some(1);
 
         for i in 0..3 {
             cache.push(make_ping_result(&format!("10.0.0.{}", i))).await;
         }
         assert_eq!(cache.len().await, 3);
 
-        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
-        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let agent_status = Arc::new(RwLock::new(AgentStatus {
+            server_connected: true,
+            ..AgentStatus::default()
+        }));
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
 
         let reporter_task = tokio::spawn({
             let cache = Arc::clone(&cache);
             let config = Arc::clone(&config);
             let status = Arc::clone(&agent_status);
-            async move { run_result_reporter(config, cache, status, shutdown_rx).await }
+            async move {
+                run_result_reporter(
+                    config,
+                    cache,
+                    status,
+                    ServerClient::default(),
+                    ReportingSwitch::default(),
+                    shutdown_rx,
+                )
+                .await
+            }
         });
 
         tokio::time::sleep(Duration::from_millis(1500)).await;
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let _ = tokio::time::timeout(Duration::from_secs(2), reporter_task).await;
 
         assert_eq!(
@@ -508,6 +1036,599 @@ mod reporter_loop_tests {
             agent_status.read().failed_report_count > 0,
             "failed_report_count must be incremented on server error"
         );
+        assert!(
+            !agent_status.read().server_connected,
+            "failed result batches must count towards the connection state"
+        );
+    }
+
+    /// A large synthetic backlog must be sent in pages no larger than
+    /// `cache_batch_size`, one request per page, until the cache is empty.
+    #[tokio::test]
+    async fn flush_sends_large_backlog_in_bounded_pages() {
+        use parking_lot::Mutex;
+
+        let mut server = mockito::Server::new_async().await;
+        let config = make_config(&server.url());
+        config.write().storage.cache_batch_size = 100;
+        let agent_id = config.read().agent_id;
+
+        let page_sizes = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&page_sizes);
+        let mock = server
+            .mock("POST", format!("/agent/{}/results", agent_id).as_str())
+            .match_request(move |req| {
+                let body: serde_json::Value = serde_json::from_slice(req.body().unwrap()).unwrap();
+                recorder
+                    .lock()
+                    .push(body["results"].as_array().map(|a| a.len()).unwrap_or(0));
+                true
+            })
+            .with_status(202)
+            .expect(25)
+            .create_async()
+            .await;
+
+        let cache = make_cache(0, 3600);
+        for i in 0..2500 {
+            cache
+                .push(make_ping_result(&format!("10.0.{}.{}", i / 256, i % 256)))
+                .await;
+        }
+
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let snapshot = config.read().clone();
+        let client = ServerClient::default();
+        let sent = super::flush_cache_paged(&snapshot, &cache, &agent_status, &client)
+            .await
+            .expect("flush should succeed");
+
+        mock.assert_async().await;
+        assert_eq!(
+            client.builds(),
+            1,
+            "every page must reuse the shared client"
+        );
+        assert_eq!(sent, 2500, "every cached result must be sent");
+        assert_eq!(cache.len().await, 0, "cache must be empty after flush");
+        let sizes = page_sizes.lock();
+        assert!(
+            sizes.iter().all(|&n| n <= 100),
+            "no request may carry more than one page of results: {:?}",
+            *sizes
+        );
+        assert_eq!(sizes.iter().sum::<usize>(), 2500);
+        assert_eq!(agent_status.read().cache_stats.len, 0);
+    }
+
+    /// When a page fails, already-acknowledged pages stay drained and the
+    /// failed page plus the rest of the backlog remain cached.
+    #[tokio::test]
+    async fn flush_stops_at_first_failed_page() {
+        let mut server = mockito::Server::new_async().await;
+        let config = make_config(&server.url());
+        config.write().storage.cache_batch_size = 10;
+        let agent_id = config.read().agent_id;
+        let path = format!("/agent/{}/results", agent_id);
+
+        // The first two pages are acknowledged, the third is rejected.
+        let ok = server
+            .mock("POST", path.as_str())
+            .with_status(202)
+            .expect(2)
+            .create_async()
+            .await;
+        let rejected = server
+            .mock("POST", path.as_str())
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let cache = make_cache(0, 3600);
+        for i in 0..50 {
+            cache.push(make_ping_result(&format!("10.0.0.{}", i))).await;
+        }
+
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let snapshot = config.read().clone();
+        let result =
+            super::flush_cache_paged(&snapshot, &cache, &agent_status, &ServerClient::default())
+                .await;
+
+        ok.assert_async().await;
+        rejected.assert_async().await;
+        assert!(result.is_err(), "a failed page must surface as an error");
+        assert_eq!(
+            cache.len().await,
+            30,
+            "the failed page and everything behind it must remain cached"
+        );
+    }
+
+    /// Flush `cache` against a mock that records every reported result's
+    /// resolved IP. Returns the flush result and the recorded IPs.
+    async fn flush_recording_ips(
+        config: &Config,
+        cache: &ResultCache,
+    ) -> (Result<usize>, Vec<String>) {
+        use parking_lot::Mutex;
+
+        let mut server = mockito::Server::new_async().await;
+        let mut config = config.clone();
+        config.server.url = server.url();
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&reported);
+        let _mock = server
+            .mock(
+                "POST",
+                format!("/agent/{}/results", config.agent_id).as_str(),
+            )
+            .match_request(move |req| {
+                let body: serde_json::Value = serde_json::from_slice(req.body().unwrap()).unwrap();
+                for result in body["results"].as_array().unwrap() {
+                    recorder.lock().push(
+                        result["check_type"]["result"]["resolved_ip"]
+                            .as_str()
+                            .unwrap()
+                            .to_string(),
+                    );
+                }
+                true
+            })
+            .with_status(202)
+            .create_async()
+            .await;
+
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let result =
+            super::flush_cache_paged(&config, cache, &agent_status, &ServerClient::default()).await;
+        let reported = reported.lock().clone();
+        (result, reported)
+    }
+
+    #[tokio::test]
+    async fn flush_reports_only_failures_with_failures_filter() {
+        use crate::agent_config::StatusFilter;
+
+        let config = make_config("http://unused");
+        config.write().reporting.filter.status = StatusFilter::Failures;
+
+        let cache = make_cache(0, 3600);
+        cache.push(make_ping_result("10.0.0.1")).await;
+        let mut failed = make_ping_result("10.0.0.2");
+        if let CheckType::PingCheck(c) = &mut failed.check_type {
+            c.result.successes = 0;
+            c.result.failures = 3;
+        }
+        cache.push(failed).await;
+        cache.push(make_ping_result("10.0.0.3")).await;
+
+        let snapshot = config.read().clone();
+        let (result, reported) = flush_recording_ips(&snapshot, &cache).await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(reported, vec!["10.0.0.2".to_string()]);
+        assert_eq!(cache.len().await, 0, "filtered results are drained too");
+    }
+
+    #[tokio::test]
+    async fn flush_reports_only_tagged_endpoints_with_tag_filter() {
+        let config = make_config("http://unused");
+        let mut prod = Endpoint::new("10.0.0.1");
+        prod.tags = vec!["prod".to_string()];
+        let mut staging = Endpoint::new("10.0.0.2");
+        staging.tags = vec!["staging".to_string()];
+        {
+            let mut c = config.write();
+            c.endpoints = vec![prod.clone(), staging.clone()];
+            c.reporting.filter.tags = vec!["prod".to_string()];
+        }
+
+        let cache = make_cache(0, 3600);
+        for endpoint in [&prod, &staging, &prod] {
+            let mut result = make_ping_result(&endpoint.address);
+            result.endpoint_id = endpoint.id;
+            cache.push(result).await;
+        }
+
+        let snapshot = config.read().clone();
+        let (result, reported) = flush_recording_ips(&snapshot, &cache).await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(
+            reported,
+            vec!["10.0.0.1".to_string(), "10.0.0.1".to_string()]
+        );
+        assert_eq!(cache.len().await, 0);
+    }
+
+    #[tokio::test]
+    async fn flush_skips_request_when_whole_page_is_filtered() {
+        use crate::agent_config::StatusFilter;
+
+        let config = make_config("http://unused");
+        config.write().reporting.filter.status = StatusFilter::Failures;
+
+        let cache = make_cache(0, 3600);
+        cache.push(make_ping_result("10.0.0.1")).await;
+
+        let snapshot = config.read().clone();
+        let (result, reported) = flush_recording_ips(&snapshot, &cache).await;
+
+        assert_eq!(result.unwrap(), 1);
+        assert!(reported.is_empty(), "no request should carry results");
+        assert_eq!(cache.len().await, 0);
+    }
+
+    #[tokio::test]
+    async fn flush_sends_each_check_kind_to_its_route() {
+        use crate::core::{CheckKind, HttpGetCheck, HttpGetCheckType, HttpGetResult};
+
+        fn all_of_type(req: &mockito::Request, check_type: &str) -> bool {
+            let body: serde_json::Value = serde_json::from_slice(req.body().unwrap()).unwrap();
+            body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|r| r["check_type"]["type"] == check_type)
+        }
+
+        let mut server = mockito::Server::new_async().await;
+        let ping_route = server
+            .mock("POST", "/ingest/ping")
+            .match_request(|req| all_of_type(req, "ping"))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        let http_route = server
+            .mock("POST", "/ingest/http")
+            .match_request(|req| all_of_type(req, "httpget"))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = make_config(&server.url());
+        {
+            let mut c = config.write();
+            c.reporting
+                .routes
+                .insert(CheckKind::Ping, "/ingest/ping".to_string());
+            c.reporting
+                .routes
+                .insert(CheckKind::Http, format!("{}/ingest/http", server.url()));
+        }
+
+        let cache = make_cache(0, 3600);
+        let mut http = make_ping_result("unused");
+        http.check_type = CheckType::HttpGetCheck(HttpGetCheck {
+            r#type: HttpGetCheckType::Httpget,
+            result: HttpGetResult {
+                status_code: 200,
+                response_time_ms: Some(10.0),
+                dns_resolution_ms: None,
+                response_size_bytes: Some(2),
+                body_truncated: None,
+                body_matched: None,
+                body_match_detail: None,
+                error_details: None,
+                success: true,
+            },
+        });
+        cache.push(make_ping_result("10.0.0.1")).await;
+        cache.push(http).await;
+        cache.push(make_ping_result("10.0.0.2")).await;
+
+        let snapshot = config.read().clone();
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let sent =
+            super::flush_cache_paged(&snapshot, &cache, &agent_status, &ServerClient::default())
+                .await
+                .unwrap();
+
+        assert_eq!(sent, 3);
+        ping_route.assert_async().await;
+        http_route.assert_async().await;
+        assert_eq!(cache.len().await, 0);
+    }
+
+    /// Spawn a server that stores result IDs idempotently. It processes the
+    /// first request but drops the connection before answering, then answers
+    /// every later request with an acknowledgment. Returns the address, the
+    /// stored IDs and the `duplicates_skipped` count of every answer.
+    async fn spawn_ambiguous_server() -> (
+        std::net::SocketAddr,
+        Arc<parking_lot::Mutex<Vec<Uuid>>>,
+        Arc<parking_lot::Mutex<Vec<usize>>>,
+    ) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stored = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let duplicates = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let (stored_ids, skipped) = (Arc::clone(&stored), Arc::clone(&duplicates));
+        tokio::spawn(async move {
+            let mut requests = 0;
+            while let Ok((mut stream, _)) = listener.accept().await {
+                // Read the headers, then the body up to Content-Length.
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let body_start = loop {
+                    let n = stream.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        break None;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break Some(pos + 4);
+                    }
+                };
+                let Some(body_start) = body_start else {
+                    continue;
+                };
+                let headers = String::from_utf8_lossy(&buf[..body_start]).to_lowercase();
+                let length: usize = headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|value| value.trim().parse().ok())
+                    .unwrap_or(0);
+                while buf.len() < body_start + length {
+                    let n = stream.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let batch: crate::openapi::BatchMonitoringResults =
+                    serde_json::from_slice(&buf[body_start..]).unwrap();
+                let mut skipped_now = 0;
+                {
+                    let mut ids = stored_ids.lock();
+                    for result in &batch.results {
+                        if ids.contains(&result.id) {
+                            skipped_now += 1;
+                        } else {
+                            ids.push(result.id);
+                        }
+                    }
+                }
+
+                requests += 1;
+                if requests == 1 {
+                    // Processed, but the agent never sees the answer.
+                    drop(stream);
+                    continue;
+                }
+
+                skipped.lock().push(skipped_now);
+                let ack = format!(
+                    r#"{{"submission_id":"00000000-0000-0000-0000-000000000001","accepted":{},"duplicates_skipped":{},"received_at":"2026-01-01T00:00:00Z"}}"#,
+                    batch.results.len() - skipped_now,
+                    skipped_now,
+                );
+                let response = format!(
+                    "HTTP/1.1 202 Accepted\r\nContent-Length: {}\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n{}",
+                    ack.len(),
+                    ack,
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (addr, stored, duplicates)
+    }
+
+    #[tokio::test]
+    async fn flush_retries_ambiguous_send_without_duplicates() {
+        let (addr, stored, duplicates) = spawn_ambiguous_server().await;
+        let config = make_config(&format!("http://{}", addr));
+        let snapshot = config.read().clone();
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+
+        let cache = make_cache(100, 3600);
+        let results: Vec<_> = (0..3)
+            .map(|i| make_ping_result(&format!("10.0.0.{}", i)))
+            .collect();
+        for result in &results {
+            cache.push(result.clone()).await;
+        }
+
+        // The server stores the batch, but the agent sees a network error.
+        assert!(super::flush_cache_paged(
+            &snapshot,
+            &cache,
+            &agent_status,
+            &ServerClient::default()
+        )
+        .await
+        .is_err());
+        assert_eq!(cache.len().await, 3, "unacknowledged page must stay cached");
+        assert_eq!(stored.lock().len(), 3);
+
+        // The retry carries the same IDs, which the server skips.
+        let sent =
+            super::flush_cache_paged(&snapshot, &cache, &agent_status, &ServerClient::default())
+                .await
+                .unwrap();
+        assert_eq!(sent, 3);
+        assert_eq!(cache.len().await, 0);
+        assert_eq!(*duplicates.lock(), vec![3]);
+
+        let mut expected: Vec<_> = results.iter().map(|r| r.id).collect();
+        let mut stored_ids = stored.lock().clone();
+        expected.sort();
+        stored_ids.sort();
+        assert_eq!(stored_ids, expected, "every result stored exactly once");
+
+        // Acknowledged results are not cached again.
+        for result in results {
+            cache.push(result).await;
+        }
+        assert_eq!(cache.len().await, 0);
+    }
+
+    #[tokio::test]
+    async fn flush_retry_skips_routes_already_acknowledged() {
+        use crate::core::{CheckKind, HttpGetCheck, HttpGetCheckType, HttpGetResult};
+
+        let mut server = mockito::Server::new_async().await;
+        let ping_route = server
+            .mock("POST", "/ingest/ping")
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        let failing_http_route = server
+            .mock("POST", "/ingest/http")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = make_config(&server.url());
+        {
+            let mut c = config.write();
+            c.reporting
+                .routes
+                .insert(CheckKind::Ping, "/ingest/ping".to_string());
+            c.reporting
+                .routes
+                .insert(CheckKind::Http, "/ingest/http".to_string());
+        }
+        let snapshot = config.read().clone();
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+
+        let cache = make_cache(0, 3600);
+        let mut http = make_ping_result("unused");
+        http.check_type = CheckType::HttpGetCheck(HttpGetCheck {
+            r#type: HttpGetCheckType::Httpget,
+            result: HttpGetResult {
+                status_code: 200,
+                response_time_ms: Some(10.0),
+                dns_resolution_ms: None,
+                response_size_bytes: Some(2),
+                body_truncated: None,
+                body_matched: None,
+                body_match_detail: None,
+                error_details: None,
+                success: true,
+            },
+        });
+        cache.push(make_ping_result("10.0.0.1")).await;
+        cache.push(http).await;
+
+        assert!(super::flush_cache_paged(
+            &snapshot,
+            &cache,
+            &agent_status,
+            &ServerClient::default()
+        )
+        .await
+        .is_err());
+        assert_eq!(cache.len().await, 2);
+        failing_http_route.assert_async().await;
+        failing_http_route.remove_async().await;
+
+        let http_route = server
+            .mock("POST", "/ingest/http")
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        let sent =
+            super::flush_cache_paged(&snapshot, &cache, &agent_status, &ServerClient::default())
+                .await
+                .unwrap();
+
+        assert_eq!(sent, 2);
+        assert_eq!(cache.len().await, 0);
+        ping_route.assert_async().await;
+        http_route.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn flush_sends_one_report_per_time_bucket() {
+        use parking_lot::Mutex;
+
+        let mut server = mockito::Server::new_async().await;
+        let config = make_config(&server.url());
+        config.write().reporting.bucket_secs = Some(60);
+        let agent_id = config.read().agent_id;
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&reports);
+        let mock = server
+            .mock("POST", format!("/agent/{}/reports", agent_id).as_str())
+            .match_header("X-Agent-API-Key", "test-api-key")
+            .match_request(move |req| {
+                let body: serde_json::Value = serde_json::from_slice(req.body().unwrap()).unwrap();
+                recorder.lock().push(body);
+                true
+            })
+            .with_status(202)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let endpoint_id = Uuid::now_v7();
+        let cache = make_cache(0, 3600);
+        for timestamp in [
+            "2026-03-01T12:00:05Z",
+            "2026-03-01T12:00:35Z",
+            "2026-03-01T12:01:05Z",
+        ] {
+            let mut result = make_ping_result("10.0.0.1");
+            result.endpoint_id = endpoint_id;
+            result.timestamp = timestamp.parse().unwrap();
+            cache.push(result).await;
+        }
+        // Still in the open bucket, must stay cached
+        cache.push(make_ping_result("10.0.0.1")).await;
+
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let snapshot = config.read().clone();
+        let sent =
+            super::flush_cache_paged(&snapshot, &cache, &agent_status, &ServerClient::default())
+                .await
+                .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(sent, 3);
+        assert_eq!(cache.len().await, 1);
+
+        let reports = reports.lock();
+        let ranges: Vec<_> = reports
+            .iter()
+            .map(|r| {
+                (
+                    r["time_range"]["start"].as_str().unwrap().to_string(),
+                    r["time_range"]["end"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (
+                    "2026-03-01T12:00:00Z".to_string(),
+                    "2026-03-01T12:01:00Z".to_string()
+                ),
+                (
+                    "2026-03-01T12:01:00Z".to_string(),
+                    "2026-03-01T12:02:00Z".to_string()
+                ),
+            ]
+        );
+        assert_eq!(reports[0]["data"][0]["check_count"], 2);
+        assert_eq!(reports[1]["data"][0]["check_count"], 1);
+        assert_eq!(reports[0]["data"][0]["success_rate_percent"], 100.0);
     }
 
     #[tokio::test]
@@ -517,7 +1638,9 @@ mod reporter_loop_tests {
             version: 1,
             agent_id: Uuid::now_v7(),
             agent_name: "Test".to_string(),
+            hostname: None,
             tags: vec![],
+            metrics_addr: None,
             monitoring: MonitoringConfig::default(),
             server: ServerConfig {
                 url: "http://127.0.0.1:1".to_string(),
@@ -528,18 +1651,35 @@ mod reporter_loop_tests {
                 ..StorageConfig::default()
             },
             update: Default::default(),
+            audit: Default::default(),
+            sinks: Default::default(),
+            reporting: Default::default(),
+            control: Default::default(),
+            alerting: Default::default(),
+            hot_reload: Default::default(),
+            plugins: Default::default(),
+            logging: Default::default(),
+            thresholds: Vec::new(),
             endpoints: vec![],
+            env_templates: Vec::new(),
         }));
 
         cache.push(make_ping_result("1.1.1.1")).await;
         assert_eq!(cache.len().await, 1);
 
         let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
-        let (_shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let (_shutdown_tx, shutdown_rx) = shutdown_channel();
 
         let result = tokio::time::timeout(
             Duration::from_secs(2),
-            run_result_reporter(config, Arc::clone(&cache), agent_status, shutdown_rx),
+            run_result_reporter(
+                config,
+                Arc::clone(&cache),
+                agent_status,
+                ServerClient::default(),
+                ReportingSwitch::default(),
+                shutdown_rx,
+            ),
         )
         .await
         .expect("reporter should exit quickly when cache is disabled");
@@ -562,17 +1702,27 @@ mod reporter_loop_tests {
         config.write().storage.cache_report_interval_secs = 1;
 
         let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
-        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
 
         let reporter_task = tokio::spawn({
             let cache = Arc::clone(&cache);
             let config = Arc::clone(&config);
             let status = Arc::clone(&agent_status);
-            async move { run_result_reporter(config, cache, status, shutdown_rx).await }
+            async move {
+                run_result_reporter(
+                    config,
+                    cache,
+                    status,
+                    ServerClient::default(),
+                    ReportingSwitch::default(),
+                    shutdown_rx,
+                )
+                .await
+            }
         });
 
         tokio::time::sleep(Duration::from_millis(1200)).await;
-        let _ = shutdown_tx.send(());
+        trigger_shutdown(&shutdown_tx);
         let _ = tokio::time::timeout(Duration::from_secs(2), reporter_task).await;
 
         assert_eq!(cache.len().await, 0);
@@ -582,4 +1732,157 @@ mod reporter_loop_tests {
             "no failures should be recorded when cache is empty"
         );
     }
+
+    #[tokio::test]
+    async fn flush_on_shutdown_sends_cached_results() {
+        let (addr, body_rx) = spawn_mock_server_202().await;
+        let cache = make_cache(100, 3600);
+        let config = make_config(&format!("http://{}", addr)).read().clone();
+        let status = RwLock::new(AgentStatus::default());
+        cache.push(make_ping_result("1.1.1.1")).await;
+
+        let paused = ReportingSwitch::default();
+        paused.pause();
+        assert_eq!(
+            flush_on_shutdown(&config, &cache, &status, &ServerClient::default(), &paused).await,
+            0
+        );
+        assert_eq!(
+            cache.len().await,
+            1,
+            "paused reporting keeps results cached"
+        );
+
+        let sent = flush_on_shutdown(
+            &config,
+            &cache,
+            &status,
+            &ServerClient::default(),
+            &ReportingSwitch::default(),
+        )
+        .await;
+        assert_eq!(sent, 1);
+        assert!(body_rx.await.is_ok(), "cached result was not sent");
+        assert_eq!(cache.len().await, 0);
+    }
+    /// A batch larger than `server.max_report_bytes` is split into several
+    /// requests that each fit, and every result is still delivered.
+    #[tokio::test]
+    async fn flush_splits_batches_over_the_size_limit() {
+        use parking_lot::Mutex;
+
+        let mut server = mockito::Server::new_async().await;
+        let config = make_config(&server.url());
+        let agent_id = config.read().agent_id;
+
+        let results: Vec<_> = (0..10).map(|_| make_ping_result("10.0.0.1")).collect();
+        let result_bytes = serde_json::to_vec(&results[0]).unwrap().len();
+        // Room for three results and the commas between them
+        let max_bytes = super::BATCH_ENVELOPE_BYTES + 3 * result_bytes + 2;
+        config.write().server.max_report_bytes = Some(max_bytes);
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&requests);
+        let mock = server
+            .mock("POST", format!("/agent/{}/results", agent_id).as_str())
+            .match_request(move |req| {
+                let body = req.body().unwrap();
+                let value: serde_json::Value = serde_json::from_slice(body).unwrap();
+                recorder
+                    .lock()
+                    .push((body.len(), value["results"].as_array().unwrap().len()));
+                true
+            })
+            .with_status(202)
+            .expect(4)
+            .create_async()
+            .await;
+
+        let cache = make_cache(0, 3600);
+        for result in results {
+            cache.push(result).await;
+        }
+
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let snapshot = config.read().clone();
+        let sent =
+            super::flush_cache_paged(&snapshot, &cache, &agent_status, &ServerClient::default())
+                .await
+                .expect("flush should succeed");
+
+        mock.assert_async().await;
+        assert_eq!(sent, 10);
+        assert_eq!(cache.len().await, 0);
+        let requests = requests.lock();
+        assert_eq!(
+            requests.iter().map(|&(_, n)| n).collect::<Vec<_>>(),
+            vec![3, 3, 3, 1]
+        );
+        assert!(
+            requests.iter().all(|&(bytes, _)| bytes <= max_bytes),
+            "every request must fit in {} bytes: {:?}",
+            max_bytes,
+            *requests
+        );
+    }
+
+    /// A single result larger than the limit is moved to the dead-letter
+    /// file with a warning instead of blocking the rest of the cache.
+    #[tokio::test]
+    async fn flush_dead_letters_results_over_the_size_limit() {
+        use crate::cache::{DeadLetter, SegmentCodec};
+        use crate::plugin::WarningCapture;
+
+        let mut server = mockito::Server::new_async().await;
+        let config = make_config(&server.url());
+        let agent_id = config.read().agent_id;
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        config.write().storage.cache_dir = cache_dir.path().display().to_string();
+
+        let small = make_ping_result("10.0.0.1");
+        let mut huge = make_ping_result("10.0.0.2");
+        huge.notes = vec!["x".repeat(4096)];
+        let max_bytes = 2048;
+        config.write().server.max_report_bytes = Some(max_bytes);
+
+        let mock = server
+            .mock("POST", format!("/agent/{}/results", agent_id).as_str())
+            .match_request(|req| req.body().unwrap().len() <= 2048)
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let cache = make_cache(0, 3600);
+        cache.push(huge.clone()).await;
+        cache.push(small).await;
+
+        let capture = WarningCapture::default();
+        let _guard = capture.install();
+        let agent_status = Arc::new(RwLock::new(AgentStatus::default()));
+        let snapshot = config.read().clone();
+        let sent =
+            super::flush_cache_paged(&snapshot, &cache, &agent_status, &ServerClient::default())
+                .await
+                .expect("flush should succeed");
+
+        mock.assert_async().await;
+        assert_eq!(sent, 2);
+        assert_eq!(cache.len().await, 0);
+
+        let entries = DeadLetter::new(snapshot.storage.dead_letter_file(), SegmentCodec::plain())
+            .entries()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].result.id, huge.id);
+        assert!(entries[0].reason.contains("max_report_bytes"));
+        assert!(
+            capture
+                .warnings()
+                .iter()
+                .any(|w| w.contains(&huge.id.to_string())),
+            "{:?}",
+            capture.warnings()
+        );
+    }
 }
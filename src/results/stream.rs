@@ -0,0 +1,752 @@
+//! Streaming result reporter
+//!
+//! With `reporting.mode = "stream"` results are sent as they are produced
+//! instead of once per report interval: one JSON object per line (NDJSON) in
+//! the chunked body of a long-lived request
+//!
+//! ```text
+//! POST {server.url}/agent/{agent_id}/results/stream
+//! Content-Type: application/x-ndjson
+//! ```
+//!
+//! # Delivery
+//!
+//! The result cache stays the source of truth. Before every stream the
+//! backlog is sent in batches as in `batch` mode; afterwards each newly
+//! cached result that passes `[reporting.filter]` and has no route of its
+//! own is written to the stream. Filtered and routed results stay cached for
+//! the batch flush before the next stream.
+//!
+//! Written results are only acknowledged once the server confirms them: the
+//! agent ends the body every `storage.cache_report_interval_secs` and drains
+//! the written results when the server answers with a success, then opens
+//! the next stream on the same connection. Results written to a stream that
+//! drops, or that the server ends on its own, stay cached and are sent again;
+//! the server skips result IDs it already stored.
+//!
+//! A stream is also ended early, the same way, when reporting is paused, the
+//! server settings change or the stream fell behind the cache.
+//!
+//! # Reconnecting
+//!
+//! A dropped connection is retried after a backoff doubling from 1 s up to
+//! 60 s, reset once a stream delivered a result. Until then cached results
+//! are sent in batches on every report tick.
+//!
+//! # Fallback
+//!
+//! A server answering 404, 405, 415 or 501 does not support streaming. The
+//! agent logs a warning and reports in batches, as in `batch` mode, until
+//! the stream URL changes or the agent restarts.
+
+use super::server::flush_cache_paged;
+use crate::agent_config::{Config, ReportMode};
+use crate::cache::ResultCache;
+use crate::core::{
+    wait_for_shutdown, AgentStatus, MonitoringResult, ReportingSwitch, ShutdownReceiver,
+};
+use crate::error::{Error, Result};
+use crate::reporter::ServerClient;
+use parking_lot::RwLock;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{interval, timeout, Instant};
+use tracing::{debug, error, info, trace, warn};
+use uuid::Uuid;
+
+/// How often a running stream checks whether it should end
+const STREAM_CHECK: Duration = Duration::from_secs(1);
+
+/// First reconnect delay after a dropped stream
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest reconnect delay
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Lines buffered between the reporter and the connection
+const BODY_BUFFER: usize = 16;
+
+impl Config {
+    /// URL results are streamed to
+    pub(crate) fn stream_report_url(&self) -> String {
+        format!("{}/results/stream", self.default_report_url())
+    }
+
+    /// Whether `result` is written to the result stream rather than left
+    /// for the batch flush
+    fn should_stream(&self, result: &MonitoringResult) -> bool {
+        self.should_report(result) && self.report_url(result) == self.default_report_url()
+    }
+}
+
+/// Why a stream ended
+#[derive(Debug)]
+enum StreamEnd {
+    /// The agent is shutting down
+    Shutdown,
+
+    /// Ended on purpose or by the server; reconnect right away
+    Closed,
+
+    /// The server does not support streaming
+    Unsupported(StatusCode),
+
+    /// The connection failed; reconnect after the backoff
+    Dropped(Error),
+}
+
+impl StreamEnd {
+    /// How a stream ended with the server's `response`
+    fn from_response(response: reqwest::Result<reqwest::Response>) -> Self {
+        match response {
+            Ok(response) if is_unsupported(response.status()) => {
+                Self::Unsupported(response.status())
+            }
+            Ok(response) if response.status().is_success() => Self::Closed,
+            Ok(response) => Self::Dropped(Error::Network(format!(
+                "Server returned {} for the result stream",
+                response.status()
+            ))),
+            Err(e) => Self::Dropped(e.into()),
+        }
+    }
+}
+
+/// Connection state of the streaming reporter across report ticks
+#[derive(Debug)]
+pub(super) struct Streamer {
+    backoff: Duration,
+    retry_at: Option<Instant>,
+    unsupported_url: Option<String>,
+    /// Client of the stream requests, kept so consecutive streams reuse the
+    /// connection, with the settings it was built for
+    client: Option<((Duration, bool), reqwest::Client)>,
+}
+
+impl Default for Streamer {
+    fn default() -> Self {
+        Self {
+            backoff: INITIAL_BACKOFF,
+            retry_at: None,
+            unsupported_url: None,
+            client: None,
+        }
+    }
+}
+
+impl Streamer {
+    /// When the next connection attempt is due, if one is scheduled
+    pub(super) fn retry_at(&self) -> Option<Instant> {
+        self.retry_at
+    }
+
+    /// Let the scheduled connection attempt happen on the next check
+    pub(super) fn clear_retry(&mut self) {
+        self.retry_at = None;
+    }
+
+    /// Whether `config` asks for a stream and a connection may be opened now
+    pub(super) fn is_due(&self, config: &Config) -> bool {
+        config.reporting.mode == ReportMode::Stream
+            && self.unsupported_url.as_deref() != Some(config.stream_report_url().as_str())
+            && self.retry_at.is_none_or(|at| at <= Instant::now())
+    }
+
+    /// Flush the backlog, then stream new results until the stream ends
+    ///
+    /// Subscribes to the cache before reading the backlog, so no result
+    /// falls between the two. Returns `false` when the agent is shutting
+    /// down.
+    pub(super) async fn run(
+        &mut self,
+        live_config: &RwLock<Config>,
+        result_cache: &ResultCache,
+        agent_status: &RwLock<AgentStatus>,
+        client: &ServerClient,
+        reporting: &ReportingSwitch,
+        shutdown: &mut ShutdownReceiver,
+    ) -> bool {
+        let pushed = result_cache.subscribe();
+        let config = live_config.read().clone();
+        self.retry_at = None;
+
+        if let Err(e) = flush_cache_paged(&config, result_cache, agent_status, client).await {
+            reporting.record_report_failure(
+                &mut agent_status.write(),
+                config.server.connection_failure_grace(),
+            );
+            error!("Failed to send result batch: {}", e);
+            self.schedule_retry();
+            return true;
+        }
+
+        let url = config.stream_report_url();
+        info!("Streaming results to {}", url);
+        let (end, delivered) = match self.client(&config) {
+            Ok(stream_client) => {
+                stream_results(
+                    &config,
+                    &stream_client,
+                    live_config,
+                    result_cache,
+                    agent_status,
+                    reporting,
+                    pushed,
+                    shutdown,
+                )
+                .await
+            }
+            Err(e) => (StreamEnd::Dropped(e), 0),
+        };
+        if delivered > 0 {
+            self.backoff = INITIAL_BACKOFF;
+            reporting.record_report_success(&mut agent_status.write());
+        }
+
+        match end {
+            StreamEnd::Shutdown => return false,
+            StreamEnd::Closed => {
+                debug!("Result stream closed after {} results", delivered);
+                self.retry_at = Some(Instant::now());
+            }
+            StreamEnd::Unsupported(status) => {
+                warn!(
+                    "Server answered {} to the result stream, reporting in batches instead",
+                    status
+                );
+                self.unsupported_url = Some(url);
+            }
+            StreamEnd::Dropped(e) => {
+                reporting.record_report_failure(
+                    &mut agent_status.write(),
+                    config.server.connection_failure_grace(),
+                );
+                warn!(
+                    "Result stream dropped: {}; reconnecting in {:?}",
+                    e, self.backoff
+                );
+                self.schedule_retry();
+            }
+        }
+        true
+    }
+
+    /// Client for stream requests under `config`, built on first use and
+    /// whenever the settings it depends on change
+    fn client(&mut self, config: &Config) -> Result<reqwest::Client> {
+        let settings = (config.server.timeout(), config.server.verify_tls);
+        if let Some((built_for, client)) = &self.client {
+            if *built_for == settings {
+                return Ok(client.clone());
+            }
+        }
+
+        // No overall timeout: it would cut the long-lived request
+        let client = reqwest::Client::builder()
+            .connect_timeout(settings.0)
+            .danger_accept_invalid_certs(!settings.1)
+            .build()?;
+        self.client = Some((settings, client.clone()));
+        Ok(client)
+    }
+
+    fn schedule_retry(&mut self) {
+        self.retry_at = Some(Instant::now() + self.backoff);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Settings a running stream depends on; a change ends the stream
+fn stream_settings(config: &Config) -> (String, Option<&str>, bool, ReportMode, bool) {
+    (
+        config.stream_report_url(),
+        config.server.api_key.as_deref(),
+        config.server.verify_tls,
+        config.reporting.mode,
+        config.storage.cache_enabled,
+    )
+}
+
+/// Whether the server answered that it does not implement the stream
+fn is_unsupported(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::NOT_FOUND
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::UNSUPPORTED_MEDIA_TYPE
+            | StatusCode::NOT_IMPLEMENTED
+    )
+}
+
+/// Stream results published by the cache until the stream ends
+///
+/// Returns why it ended and how many results the server confirmed.
+#[allow(clippy::too_many_arguments)]
+async fn stream_results(
+    config: &Config,
+    client: &reqwest::Client,
+    live_config: &RwLock<Config>,
+    result_cache: &ResultCache,
+    agent_status: &RwLock<AgentStatus>,
+    reporting: &ReportingSwitch,
+    mut pushed: broadcast::Receiver<MonitoringResult>,
+    shutdown: &mut ShutdownReceiver,
+) -> (StreamEnd, usize) {
+    let (body_tx, body_rx) = mpsc::channel::<Vec<u8>>(BODY_BUFFER);
+    let body = futures::stream::unfold(body_rx, |mut body_rx| async move {
+        let line = body_rx.recv().await?;
+        Some((Ok::<_, std::io::Error>(line), body_rx))
+    });
+
+    let mut request = client
+        .post(config.stream_report_url())
+        .header(CONTENT_TYPE, "application/x-ndjson")
+        .body(reqwest::Body::wrap_stream(body));
+    if let Some(api_key) = &config.server.api_key {
+        request = request.header("X-Agent-API-Key", api_key);
+    }
+    let response = request.send();
+    tokio::pin!(response);
+
+    let settings = stream_settings(config);
+    let rotate_at = Instant::now() + Duration::from_secs(config.storage.cache_report_interval_secs);
+    let mut written: Vec<Uuid> = Vec::new();
+    let mut check = interval(STREAM_CHECK);
+
+    let end = loop {
+        tokio::select! {
+            response = &mut response => {
+                // Lines may still have been in flight, so nothing written
+                // counts as confirmed; the next batch flush resends them
+                let end = StreamEnd::from_response(response);
+                if matches!(end, StreamEnd::Closed) {
+                    debug!("Server ended the result stream");
+                }
+                return (end, 0);
+            }
+            received = pushed.recv() => match received {
+                Ok(result) => {
+                    if !live_config.read().should_stream(&result)
+                        || result_cache.unacknowledged(vec![result.clone()]).await.is_empty()
+                    {
+                        continue;
+                    }
+                    let mut line = match serde_json::to_vec(&result) {
+                        Ok(line) => line,
+                        Err(e) => break StreamEnd::Dropped(e.into()),
+                    };
+                    line.push(b'\n');
+                    match timeout(config.server.timeout(), body_tx.send(line)).await {
+                        Ok(Ok(())) => {
+                            trace!(result_id = %result.id, "Streamed result");
+                            written.push(result.id);
+                        }
+                        // The connection is gone; its response ends the loop
+                        Ok(Err(_)) => {}
+                        Err(_) => {
+                            break StreamEnd::Dropped(Error::Network(
+                                "Server stopped reading the result stream".to_string(),
+                            ))
+                        }
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(
+                        "Result stream fell {} results behind, sending them from the cache",
+                        missed
+                    );
+                    break StreamEnd::Closed;
+                }
+                Err(RecvError::Closed) => break StreamEnd::Closed,
+            },
+            _ = check.tick() => {
+                let live = live_config.read();
+                if reporting.is_paused() || stream_settings(&live) != settings {
+                    debug!("Reporting paused or server settings changed, closing the result stream");
+                    break StreamEnd::Closed;
+                }
+                if Instant::now() >= rotate_at {
+                    break StreamEnd::Closed;
+                }
+            }
+            _ = wait_for_shutdown(shutdown) => break StreamEnd::Shutdown,
+        }
+    };
+    if !matches!(end, StreamEnd::Closed) {
+        return (end, 0);
+    }
+
+    // End the body and wait for the server to confirm everything written
+    drop(body_tx);
+    match timeout(config.server.timeout(), &mut response).await {
+        Ok(response) => match StreamEnd::from_response(response) {
+            StreamEnd::Closed => {
+                let delivered = acknowledge(result_cache, agent_status, written).await;
+                (StreamEnd::Closed, delivered)
+            }
+            end => (end, 0),
+        },
+        Err(_) => (
+            StreamEnd::Dropped(Error::Network(
+                "Server did not confirm the result stream".to_string(),
+            )),
+            0,
+        ),
+    }
+}
+
+/// Acknowledge results confirmed by the server and drain them from the cache
+async fn acknowledge(
+    result_cache: &ResultCache,
+    agent_status: &RwLock<AgentStatus>,
+    ids: Vec<Uuid>,
+) -> usize {
+    if ids.is_empty() {
+        return 0;
+    }
+    result_cache.acknowledge(ids.iter().copied()).await;
+    result_cache.drain_acknowledged().await;
+
+    let stats = result_cache.stats().await;
+    let mut status = agent_status.write();
+    status.cache_stats.len = stats.len as i64;
+    status.cache_stats.capacity = stats.capacity as i64;
+    ids.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::run_result_reporter;
+    use super::*;
+    use crate::core::{
+        shutdown_channel, trigger_shutdown, CheckType, PingCheck, PingCheckType, PingResult,
+    };
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// What the test server received
+    #[derive(Debug, PartialEq)]
+    enum Received {
+        StreamOpened,
+        StreamRejected,
+        Line(Uuid),
+        Batch(usize),
+    }
+
+    fn make_ping_result() -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "192.0.2.1".to_string(),
+                    successes: 3,
+                    failures: 0,
+                    success_latencies: vec![1.0, 2.0, 3.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: chrono::Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn stream_config(addr: SocketAddr) -> Arc<RwLock<Config>> {
+        let mut config = Config {
+            agent_id: Uuid::now_v7(),
+            ..Config::default()
+        };
+        config.server.url = format!("http://{}", addr);
+        config.server.api_key = Some("test-api-key".to_string());
+        config.storage.cache_enabled = true;
+        config.storage.cache_report_interval_secs = 1;
+        config.reporting.mode = ReportMode::Stream;
+        Arc::new(RwLock::new(config))
+    }
+
+    /// How the test server answers result streams
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum StreamMode {
+        /// Read the whole body, then answer 200
+        Confirm,
+        /// Answer 404 right away
+        Unsupported,
+        /// Close the connection after the first line, without answering
+        Drop,
+    }
+
+    /// HTTP/1.1 server that reads streamed NDJSON bodies chunk by chunk and
+    /// answers them according to `mode`, and accepts result batches. Also
+    /// returns the number of connections accepted.
+    async fn spawn_server(
+        mode: StreamMode,
+    ) -> (
+        SocketAddr,
+        mpsc::UnboundedReceiver<Received>,
+        Arc<AtomicUsize>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let accepted = Arc::clone(&connections);
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(serve(socket, mode, tx.clone()));
+            }
+        });
+        (addr, rx, connections)
+    }
+
+    async fn serve(socket: TcpStream, mode: StreamMode, tx: mpsc::UnboundedSender<Received>) {
+        let mut reader = BufReader::new(socket);
+        loop {
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).await.unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                let header = header.to_ascii_lowercase();
+                if let Some(value) = header.strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+
+            if !request_line.contains("/results/stream") {
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).await.unwrap();
+                let batch: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let _ = tx.send(Received::Batch(batch["results"].as_array().unwrap().len()));
+                let _ = reader
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n")
+                    .await;
+                continue;
+            }
+
+            if mode == StreamMode::Unsupported {
+                let _ = tx.send(Received::StreamRejected);
+                let _ = reader
+                    .get_mut()
+                    .write_all(
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .await;
+                return;
+            }
+
+            let _ = tx.send(Received::StreamOpened);
+            let mut pending = Vec::new();
+            loop {
+                let mut size = String::new();
+                if reader.read_line(&mut size).await.unwrap_or(0) == 0 {
+                    return;
+                }
+                let size = usize::from_str_radix(size.trim(), 16).unwrap();
+                let mut chunk = vec![0; size + 2];
+                reader.read_exact(&mut chunk).await.unwrap();
+                if size == 0 {
+                    break;
+                }
+                pending.extend_from_slice(&chunk[..size]);
+                while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    let result: MonitoringResult = serde_json::from_slice(&line).unwrap();
+                    let _ = tx.send(Received::Line(result.id));
+                    if mode == StreamMode::Drop {
+                        return;
+                    }
+                }
+            }
+            let _ = reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await;
+        }
+    }
+
+    async fn next(rx: &mut mpsc::UnboundedReceiver<Received>) -> Received {
+        timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out waiting for the server")
+            .expect("server stopped")
+    }
+
+    fn spawn_reporter(
+        config: &Arc<RwLock<Config>>,
+        cache: &Arc<ResultCache>,
+        shutdown_rx: ShutdownReceiver,
+    ) -> tokio::task::JoinHandle<crate::error::Result<()>> {
+        tokio::spawn(run_result_reporter(
+            Arc::clone(config),
+            Arc::clone(cache),
+            Arc::new(RwLock::new(AgentStatus::default())),
+            ServerClient::default(),
+            ReportingSwitch::default(),
+            shutdown_rx,
+        ))
+    }
+
+    async fn wait_drained(cache: &ResultCache) {
+        let drained = async {
+            while cache.len().await > 0 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        };
+        timeout(Duration::from_secs(5), drained)
+            .await
+            .expect("results must be drained from the cache");
+    }
+
+    #[tokio::test]
+    async fn test_results_are_streamed_as_they_are_cached() {
+        let (addr, mut received, connections) = spawn_server(StreamMode::Confirm).await;
+        let config = stream_config(addr);
+        let cache = Arc::new(ResultCache::new(100, Duration::from_secs(3600)));
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let reporter = spawn_reporter(&config, &cache, shutdown_rx);
+        assert_eq!(next(&mut received).await, Received::StreamOpened);
+
+        // Each result reaches the server before the next one is produced
+        for _ in 0..3 {
+            let result = make_ping_result();
+            cache.push(result.clone()).await;
+            assert_eq!(next(&mut received).await, Received::Line(result.id));
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        assert_eq!(cache.len().await, 3, "not confirmed by the server yet");
+
+        // The stream is ended after the report interval; the server's answer
+        // drains the results and the next stream reuses the connection
+        wait_drained(&cache).await;
+        assert_eq!(next(&mut received).await, Received::StreamOpened);
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert!(received.try_recv().is_err(), "no batches");
+
+        trigger_shutdown(&shutdown_tx);
+        timeout(Duration::from_secs(2), reporter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_keeps_results_cached() {
+        let (addr, mut received, _) = spawn_server(StreamMode::Drop).await;
+        let config = stream_config(addr);
+        let cache = Arc::new(ResultCache::new(100, Duration::from_secs(3600)));
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let reporter = spawn_reporter(&config, &cache, shutdown_rx);
+        assert_eq!(next(&mut received).await, Received::StreamOpened);
+
+        let result = make_ping_result();
+        cache.push(result.clone()).await;
+        assert_eq!(next(&mut received).await, Received::Line(result.id));
+
+        // Never confirmed, so it is sent again before the next stream
+        assert_eq!(next(&mut received).await, Received::Batch(1));
+        wait_drained(&cache).await;
+
+        trigger_shutdown(&shutdown_tx);
+        timeout(Duration::from_secs(2), reporter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_filtered_and_routed_results_are_not_streamed() {
+        use crate::agent_config::StatusFilter;
+        use crate::core::CheckKind;
+
+        let (addr, mut received, _) = spawn_server(StreamMode::Confirm).await;
+        let config = stream_config(addr);
+        config.write().reporting.filter.status = StatusFilter::Failures;
+        config
+            .write()
+            .reporting
+            .routes
+            .insert(CheckKind::Ping, "/ingest/ping".to_string());
+        let cache = Arc::new(ResultCache::new(100, Duration::from_secs(3600)));
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let reporter = spawn_reporter(&config, &cache, shutdown_rx);
+        assert_eq!(next(&mut received).await, Received::StreamOpened);
+
+        // Filtered out
+        cache.push(make_ping_result()).await;
+        // Routed to /ingest/ping
+        let mut failed = make_ping_result();
+        if let CheckType::PingCheck(check) = &mut failed.check_type {
+            check.result.successes = 0;
+            check.result.failures = 3;
+        }
+        cache.push(failed).await;
+
+        // Both wait for the batch flush before the next stream
+        assert_eq!(next(&mut received).await, Received::Batch(1));
+        assert_eq!(next(&mut received).await, Received::StreamOpened);
+        wait_drained(&cache).await;
+
+        trigger_shutdown(&shutdown_tx);
+        timeout(Duration::from_secs(2), reporter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_stream_falls_back_to_batches() {
+        let (addr, mut received, _) = spawn_server(StreamMode::Unsupported).await;
+        let config = stream_config(addr);
+        let cache = Arc::new(ResultCache::new(100, Duration::from_secs(3600)));
+        let (shutdown_tx, shutdown_rx) = shutdown_channel();
+
+        let reporter = spawn_reporter(&config, &cache, shutdown_rx);
+        assert_eq!(next(&mut received).await, Received::StreamRejected);
+
+        cache.push(make_ping_result()).await;
+        assert_eq!(next(&mut received).await, Received::Batch(1));
+
+        // The stream is not attempted again on later ticks
+        cache.push(make_ping_result()).await;
+        assert_eq!(next(&mut received).await, Received::Batch(1));
+
+        trigger_shutdown(&shutdown_tx);
+        timeout(Duration::from_secs(2), reporter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -2,17 +2,17 @@ use super::environment::is_containerized;
 use super::replacer;
 use super::{download_release_binary, fetch_latest_version, is_newer_than_current};
 use crate::agent_config::Config;
+use crate::core::{wait_for_shutdown, ShutdownReceiver};
 use crate::error::{Error, Result};
 use octocrab::Octocrab;
 use parking_lot::RwLock;
 use std::sync::Arc;
-use tokio::sync::broadcast;
 use tokio::time::interval;
 use tracing::{error, info, warn};
 
 pub async fn run_update_checker(
     config: Arc<RwLock<Config>>,
-    mut shutdown_rx: broadcast::Receiver<()>,
+    mut shutdown_rx: ShutdownReceiver,
 ) -> Result<()> {
     if is_containerized() {
         info!("Running in containerized environment. Self-upgrade disabled");
@@ -67,7 +67,7 @@ pub async fn run_update_checker(
                     }
                 }
             }
-            _ = shutdown_rx.recv() => {
+            _ = wait_for_shutdown(&mut shutdown_rx) => {
                 info!("Update checker shutting down");
                 break;
             }
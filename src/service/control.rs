@@ -0,0 +1,88 @@
+//! Mapping of service control requests to agent actions
+//!
+//! The Windows service control manager (SCM) talks to a service through
+//! control codes. This module decides what the agent does for each of them.
+//! It is platform independent so the mapping is tested everywhere; the
+//! Windows entry point converts `windows_service` control codes into
+//! [`ServiceRequest`] and carries out the returned [`ServiceAction`].
+
+/// Name the agent is registered under with the service control manager
+pub const SERVICE_NAME: &str = "smotra";
+
+/// Control request sent by the service control manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceRequest {
+    /// `sc stop smotra` or stopping the service in services.msc
+    Stop,
+    /// The system is shutting down
+    Shutdown,
+    /// The system is about to shut down (sent before `Shutdown`)
+    Preshutdown,
+    /// `sc control smotra paramchange`: the service parameters changed
+    ParamChange,
+    /// The SCM asks for the current service status
+    Interrogate,
+    /// Any other control code (pause, power and session events, ...)
+    Other,
+}
+
+/// What the agent does in response to a [`ServiceRequest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceAction {
+    /// Shut the agent down gracefully, like Ctrl+C
+    StopAgent,
+    /// Reload the config file, the Windows counterpart of SIGHUP
+    ReloadConfig,
+    /// Only acknowledge the request; the status is reported as is
+    ReportStatus,
+    /// The request is not supported by the agent
+    NotImplemented,
+}
+
+/// Action the agent takes for `request`
+pub fn action_for(request: ServiceRequest) -> ServiceAction {
+    match request {
+        ServiceRequest::Stop | ServiceRequest::Shutdown | ServiceRequest::Preshutdown => {
+            ServiceAction::StopAgent
+        }
+        ServiceRequest::ParamChange => ServiceAction::ReloadConfig,
+        ServiceRequest::Interrogate => ServiceAction::ReportStatus,
+        ServiceRequest::Other => ServiceAction::NotImplemented,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_requests_stop_the_agent() {
+        for request in [
+            ServiceRequest::Stop,
+            ServiceRequest::Shutdown,
+            ServiceRequest::Preshutdown,
+        ] {
+            assert_eq!(action_for(request), ServiceAction::StopAgent);
+        }
+    }
+
+    #[test]
+    fn test_param_change_reloads_config() {
+        assert_eq!(
+            action_for(ServiceRequest::ParamChange),
+            ServiceAction::ReloadConfig
+        );
+    }
+
+    #[test]
+    fn test_interrogate_and_unknown_requests() {
+        assert_eq!(
+            action_for(ServiceRequest::Interrogate),
+            ServiceAction::ReportStatus
+        );
+        assert_eq!(
+            action_for(ServiceRequest::Other),
+            ServiceAction::NotImplemented
+        );
+    }
+}
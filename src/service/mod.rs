@@ -0,0 +1,11 @@
+mod control;
+#[cfg(not(windows))]
+mod unsupported;
+#[cfg(windows)]
+mod windows;
+
+pub use control::{action_for, ServiceAction, ServiceRequest, SERVICE_NAME};
+#[cfg(not(windows))]
+pub use unsupported::{install, run, uninstall};
+#[cfg(windows)]
+pub use windows::{install, run, uninstall};
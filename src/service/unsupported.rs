@@ -0,0 +1,39 @@
+//! Service management on platforms without Windows services
+//!
+//! Elsewhere the agent runs under systemd or another supervisor instead, so
+//! every command fails with a clear error.
+
+use crate::error::{Error, Result};
+use std::path::Path;
+
+fn unsupported() -> Error {
+    Error::Service("Windows services are only supported on Windows".to_string())
+}
+
+/// Register the agent as a Windows service (Windows only)
+pub fn install(_config_path: &Path, _log_file: Option<&Path>) -> Result<()> {
+    Err(unsupported())
+}
+
+/// Remove the agent's Windows service (Windows only)
+pub fn uninstall() -> Result<()> {
+    Err(unsupported())
+}
+
+/// Run the agent under the service control manager (Windows only)
+pub fn run(_config_path: &Path) -> Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_commands_fail_outside_windows() {
+        let config = Path::new("config.toml");
+        assert!(matches!(install(config, None), Err(Error::Service(_))));
+        assert!(matches!(uninstall(), Err(Error::Service(_))));
+        assert!(matches!(run(config), Err(Error::Service(_))));
+    }
+}
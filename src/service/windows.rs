@@ -0,0 +1,257 @@
+//! Windows service entry point
+//!
+//! `smotra --service install` registers the agent with the service control
+//! manager (SCM), which then starts it as `smotra --config <path> --service
+//! run`. [`run`] hands the process over to the SCM dispatcher; the service
+//! main function runs [`Agent::start`] on the binary's tokio runtime and
+//! maps control requests through [`action_for`]: stop and system shutdown
+//! trigger the agent's broadcast shutdown, `paramchange` reloads the config
+//! like SIGHUP does on Unix.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{
+    self, ServiceControlHandlerResult, ServiceStatusHandle,
+};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+use super::control::{action_for, ServiceAction, ServiceRequest, SERVICE_NAME};
+use crate::core::Agent;
+use crate::error::{Error, Result};
+
+const SERVICE_DISPLAY_NAME: &str = "Smotra Agent";
+const SERVICE_DESCRIPTION: &str = "Smotra distributed monitoring agent";
+
+/// Config path and runtime handed from [`run`] to the service main function,
+/// which the SCM calls on a thread of its own
+static SERVICE_CONTEXT: OnceLock<(PathBuf, Handle)> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+impl From<ServiceControl> for ServiceRequest {
+    fn from(control: ServiceControl) -> Self {
+        match control {
+            ServiceControl::Stop => Self::Stop,
+            ServiceControl::Shutdown => Self::Shutdown,
+            ServiceControl::Preshutdown => Self::Preshutdown,
+            ServiceControl::ParamChange => Self::ParamChange,
+            ServiceControl::Interrogate => Self::Interrogate,
+            _ => Self::Other,
+        }
+    }
+}
+
+fn service_error(e: windows_service::Error) -> Error {
+    Error::Service(e.to_string())
+}
+
+/// Run the agent under the service control manager
+///
+/// Blocks until the service stops. Must be called from within a tokio
+/// runtime (e.g. through `spawn_blocking`), which the agent then runs on.
+pub fn run(config_path: &Path) -> Result<()> {
+    let config_path = std::path::absolute(config_path)?;
+    SERVICE_CONTEXT
+        .set((config_path, Handle::current()))
+        .map_err(|_| Error::Service("Service is already running".to_string()))?;
+
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main).map_err(service_error)
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        error!("Service failed: {}", e);
+    }
+}
+
+fn set_status(
+    status_handle: &ServiceStatusHandle,
+    current_state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    exit_code: ServiceExitCode,
+) -> Result<()> {
+    status_handle
+        .set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state,
+            controls_accepted,
+            exit_code,
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        })
+        .map_err(service_error)
+}
+
+fn run_service() -> Result<()> {
+    let (config_path, runtime) = SERVICE_CONTEXT
+        .get()
+        .cloned()
+        .ok_or_else(|| Error::Service("Service started without a context".to_string()))?;
+
+    let (action_tx, mut action_rx) = mpsc::unbounded_channel();
+    let event_handler = move |control: ServiceControl| match action_for(control.into()) {
+        ServiceAction::NotImplemented => ServiceControlHandlerResult::NotImplemented,
+        ServiceAction::ReportStatus => ServiceControlHandlerResult::NoError,
+        action => {
+            let _ = action_tx.send(action);
+            ServiceControlHandlerResult::NoError
+        }
+    };
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, event_handler).map_err(service_error)?;
+
+    set_status(
+        &status_handle,
+        ServiceState::Running,
+        ServiceControlAccept::STOP
+            | ServiceControlAccept::SHUTDOWN
+            | ServiceControlAccept::PARAM_CHANGE,
+        ServiceExitCode::Win32(0),
+    )?;
+    info!("Service {} running", SERVICE_NAME);
+
+    let result = runtime.block_on(async move {
+        let agent = Agent::new(config_path)?;
+        let start = agent.start();
+        tokio::pin!(start);
+
+        loop {
+            tokio::select! {
+                result = &mut start => return result,
+                Some(action) = action_rx.recv() => match action {
+                    ServiceAction::StopAgent => agent.stop()?,
+                    ServiceAction::ReloadConfig => agent.request_reload(),
+                    ServiceAction::ReportStatus | ServiceAction::NotImplemented => {}
+                },
+            }
+        }
+    });
+
+    if let Err(e) = &result {
+        error!("Agent error: {}", e);
+    }
+    let exit_code = match result {
+        Ok(()) => ServiceExitCode::Win32(0),
+        Err(_) => ServiceExitCode::ServiceSpecific(1),
+    };
+    set_status(
+        &status_handle,
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+    )?;
+    info!("Service {} stopped", SERVICE_NAME);
+    Ok(())
+}
+
+/// Register the agent as an automatically started Windows service
+///
+/// The service runs the current executable with `--config <config_path>
+/// --service run`, plus `--log-file <log_file>` when given, since a service
+/// has no console to log to. Both paths are made absolute.
+pub fn install(config_path: &Path, log_file: Option<&Path>) -> Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(service_error)?;
+
+    let mut launch_arguments = vec![
+        OsString::from("--config"),
+        std::path::absolute(config_path)?.into_os_string(),
+        OsString::from("--service"),
+        OsString::from("run"),
+    ];
+    if let Some(log_file) = log_file {
+        launch_arguments.push(OsString::from("--log-file"));
+        launch_arguments.push(std::path::absolute(log_file)?.into_os_string());
+    }
+
+    let service_info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(SERVICE_DISPLAY_NAME),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments,
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&service_info, ServiceAccess::CHANGE_CONFIG)
+        .map_err(service_error)?;
+    service
+        .set_description(SERVICE_DESCRIPTION)
+        .map_err(service_error)?;
+
+    info!("Installed service {}", SERVICE_NAME);
+    Ok(())
+}
+
+/// Stop the agent's Windows service if it is running and remove it
+pub fn uninstall() -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(service_error)?;
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .map_err(service_error)?;
+
+    // The SCM removes a service marked for deletion once it has stopped
+    service.delete().map_err(service_error)?;
+    if service.query_status().map_err(service_error)?.current_state != ServiceState::Stopped {
+        service.stop().map_err(service_error)?;
+    }
+
+    info!("Uninstalled service {}", SERVICE_NAME);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_control_mapping() {
+        assert_eq!(
+            action_for(ServiceControl::Stop.into()),
+            ServiceAction::StopAgent
+        );
+        assert_eq!(
+            action_for(ServiceControl::Shutdown.into()),
+            ServiceAction::StopAgent
+        );
+        assert_eq!(
+            action_for(ServiceControl::Preshutdown.into()),
+            ServiceAction::StopAgent
+        );
+        assert_eq!(
+            action_for(ServiceControl::ParamChange.into()),
+            ServiceAction::ReloadConfig
+        );
+        assert_eq!(
+            action_for(ServiceControl::Interrogate.into()),
+            ServiceAction::ReportStatus
+        );
+        assert_eq!(
+            action_for(ServiceControl::Pause.into()),
+            ServiceAction::NotImplemented
+        );
+    }
+}
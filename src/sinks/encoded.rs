@@ -0,0 +1,134 @@
+//! Serialization cache shared by the result sinks
+//!
+//! The audit log and the MQTT sink both export a result as the same JSON
+//! document. Instead of each sink serializing it again, the result
+//! collection loop wraps every result in an [`EncodedResult`] that
+//! serializes it at most once per format, on first use, and hands the same
+//! bytes to every sink using that format. Formats no enabled sink asks for
+//! are never produced.
+
+use crate::core::MonitoringResult;
+use crate::error::Result;
+use std::cell::{Cell, OnceCell};
+
+/// A result together with its lazily computed serializations
+pub(crate) struct EncodedResult<'a> {
+    result: &'a MonitoringResult,
+    json: OnceCell<Vec<u8>>,
+    serializations: Cell<usize>,
+}
+
+impl<'a> EncodedResult<'a> {
+    pub(crate) fn new(result: &'a MonitoringResult) -> Self {
+        Self {
+            result,
+            json: OnceCell::new(),
+            serializations: Cell::new(0),
+        }
+    }
+
+    /// The wrapped result
+    pub(crate) fn result(&self) -> &'a MonitoringResult {
+        self.result
+    }
+
+    /// The result serialized as JSON, computed on the first call
+    pub(crate) fn json(&self) -> Result<&[u8]> {
+        if let Some(json) = self.json.get() {
+            return Ok(json);
+        }
+
+        let json = serde_json::to_vec(self.result)?;
+        self.serializations.set(self.serializations.get() + 1);
+        Ok(self.json.get_or_init(|| json))
+    }
+
+    /// How many serializations have been computed so far
+    #[cfg(test)]
+    pub(crate) fn serializations(&self) -> usize {
+        self.serializations.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_config::AuditConfig;
+    use crate::audit::AuditLog;
+    use crate::core::{CheckType, HttpGetCheck, HttpGetCheckType, HttpGetResult};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn make_result() -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: Uuid::now_v7(),
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::HttpGetCheck(HttpGetCheck {
+                r#type: HttpGetCheckType::Httpget,
+                result: HttpGetResult {
+                    status_code: 200,
+                    response_time_ms: Some(12.0),
+                    dns_resolution_ms: None,
+                    response_size_bytes: Some(512),
+                    body_truncated: None,
+                    body_matched: None,
+                    body_match_detail: None,
+                    error_details: None,
+                    success: true,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_is_serialized_once_regardless_of_sink_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let sinks: Vec<AuditLog> = (0..3)
+            .map(|i| {
+                AuditLog::new(AuditConfig {
+                    enabled: true,
+                    path: dir
+                        .path()
+                        .join(format!("audit-{}.ndjson", i))
+                        .to_string_lossy()
+                        .into_owned(),
+                    ..AuditConfig::default()
+                })
+            })
+            .collect();
+
+        let result = make_result();
+        let encoded = EncodedResult::new(&result);
+        assert_eq!(encoded.serializations(), 0);
+
+        for sink in &sinks {
+            sink.write(&encoded).unwrap();
+        }
+        assert_eq!(encoded.serializations(), 1);
+        for sink in sinks {
+            sink.close().await;
+        }
+
+        let expected = serde_json::to_vec(&result).unwrap();
+        for i in 0..3 {
+            let written = std::fs::read(dir.path().join(format!("audit-{}.ndjson", i))).unwrap();
+            assert_eq!(written, [expected.as_slice(), b"\n"].concat());
+        }
+    }
+
+    #[test]
+    fn test_unused_formats_are_not_serialized() {
+        let result = make_result();
+        let encoded = EncodedResult::new(&result);
+
+        assert_eq!(encoded.result().id, result.id);
+        assert_eq!(encoded.serializations(), 0);
+    }
+}
@@ -0,0 +1,14 @@
+//! Export of check results to external systems.
+//!
+//! See [`mqtt::MqttSink`] and [`remote_write::RemoteWriteSink`] for full
+//! documentation.
+
+mod encoded;
+mod mqtt;
+mod remote_write;
+
+pub(crate) use encoded::EncodedResult;
+pub(crate) use mqtt::parse_broker_url;
+pub use mqtt::MqttSink;
+pub(crate) use remote_write::check_label;
+pub use remote_write::RemoteWriteSink;
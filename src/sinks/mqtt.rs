@@ -0,0 +1,356 @@
+//! MQTT result sink
+//!
+//! Publishes every `MonitoringResult` as JSON to an MQTT broker, one topic
+//! per endpoint rendered from `topic_template`:
+//!
+//! ```text
+//! smotra/{agent_id}/{endpoint}  ->  smotra/0193.../0194...
+//! ```
+//!
+//! The connection is driven by a background task polling the `rumqttc`
+//! event loop. When the broker drops the connection the task waits
+//! `RECONNECT_DELAY` and polls again, which reconnects. The client uses a
+//! persistent session, so QoS 1/2 messages that were not acknowledged before
+//! the drop are re-sent once the broker resumes the session. Publishing never blocks the result
+//! pipeline: while the broker is unreachable, results are queued up to
+//! `REQUEST_QUEUE_CAPACITY` and dropped beyond that.
+
+use crate::agent_config::MqttSinkConfig;
+use crate::core::MonitoringResult;
+use crate::error::{Error, Result};
+use crate::sinks::EncodedResult;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS, Transport};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use url::Url;
+use uuid::Uuid;
+
+/// Delay before polling the event loop again after a connection error
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Publish requests buffered while the broker is unreachable
+const REQUEST_QUEUE_CAPACITY: usize = 1000;
+
+/// Publishes check results to an MQTT broker
+pub struct MqttSink {
+    config: MqttSinkConfig,
+    agent_id: Uuid,
+    client: AsyncClient,
+    event_loop: JoinHandle<()>,
+}
+
+impl MqttSink {
+    /// Create a sink for `config` and start connecting in the background.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn new(config: MqttSinkConfig, agent_id: Uuid) -> Result<Self> {
+        let (host, port, tls) = parse_broker_url(&config.broker_url)?;
+        let qos = qos(config.qos)?;
+        let client_id = config
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("smotra-{}", agent_id));
+
+        let mut options = MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(config.keep_alive());
+        // Keep the broker session so unacknowledged results are re-sent
+        // after a reconnect instead of being discarded.
+        options.set_clean_session(false);
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+        if tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+
+        let (client, event_loop) = AsyncClient::new(options, REQUEST_QUEUE_CAPACITY);
+        let broker_url = config.broker_url.clone();
+        let event_loop = tokio::spawn(run_event_loop(event_loop, broker_url));
+
+        debug!(
+            "MQTT sink created for {} (qos {:?})",
+            config.broker_url, qos
+        );
+        Ok(Self {
+            config,
+            agent_id,
+            client,
+            event_loop,
+        })
+    }
+
+    /// Configuration this sink was created with
+    pub fn config(&self) -> &MqttSinkConfig {
+        &self.config
+    }
+
+    /// Agent ID used for the client ID and topics
+    pub fn agent_id(&self) -> Uuid {
+        self.agent_id
+    }
+
+    /// Queue `result` for publishing to its endpoint topic
+    pub(crate) fn publish(&self, result: &EncodedResult) -> Result<()> {
+        let topic = render_topic(&self.config.topic_template, result.result());
+        let payload = result.json()?.to_vec();
+
+        self.client
+            .try_publish(topic, qos(self.config.qos)?, false, payload)
+            .map_err(|e| Error::Network(format!("Failed to queue MQTT publish: {}", e)))
+    }
+}
+
+impl Drop for MqttSink {
+    fn drop(&mut self) {
+        self.event_loop.abort();
+    }
+}
+
+/// Drive the MQTT connection, reconnecting after errors
+async fn run_event_loop(mut event_loop: EventLoop, broker_url: String) {
+    // Warn once per outage rather than on every retry.
+    let mut outage_reported = false;
+
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("Connected to MQTT broker {}", broker_url);
+                outage_reported = false;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                if outage_reported {
+                    debug!("MQTT connection to {} failed: {}", broker_url, e);
+                } else {
+                    warn!("MQTT connection to {} failed: {}", broker_url, e);
+                    outage_reported = true;
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Split an `mqtt://` or `mqtts://` broker URL into host, port and TLS flag
+pub(crate) fn parse_broker_url(broker_url: &str) -> Result<(String, u16, bool)> {
+    let url = Url::parse(broker_url)
+        .map_err(|e| Error::Config(format!("Invalid MQTT broker URL {}: {}", broker_url, e)))?;
+
+    let (tls, default_port) = match url.scheme() {
+        "mqtt" | "tcp" => (false, 1883),
+        "mqtts" | "ssl" => (true, 8883),
+        scheme => {
+            return Err(Error::Config(format!(
+                "Unsupported MQTT broker URL scheme: {}",
+                scheme
+            )))
+        }
+    };
+
+    let host = url
+        .host_str()
+        .filter(|h| !h.is_empty())
+        .ok_or_else(|| Error::Config(format!("MQTT broker URL has no host: {}", broker_url)))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+
+    Ok((host, url.port().unwrap_or(default_port), tls))
+}
+
+/// Render the topic for `result` from `template`
+fn render_topic(template: &str, result: &MonitoringResult) -> String {
+    template
+        .replace("{agent_id}", &result.agent_id.to_string())
+        .replace("{endpoint}", &result.endpoint_id.to_string())
+}
+
+fn qos(level: u8) -> Result<QoS> {
+    rumqttc::qos(level).map_err(|_| Error::Config(format!("Invalid MQTT QoS level: {}", level)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CheckType, PingCheck, PingCheckType, PingResult};
+    use bytes::BytesMut;
+    use chrono::Utc;
+    use rumqttc::{ConnAck, ConnectReturnCode, PubAck, Publish};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn make_result(agent_id: Uuid) -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id,
+            endpoint_id: Uuid::now_v7(),
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "127.0.0.1".to_string(),
+                    successes: 3,
+                    failures: 0,
+                    success_latencies: vec![1.0, 2.0, 3.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc::now(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn sink_config(port: u16) -> MqttSinkConfig {
+        MqttSinkConfig {
+            broker_url: format!("mqtt://127.0.0.1:{}", port),
+            ..Default::default()
+        }
+    }
+
+    async fn write_packet(stream: &mut TcpStream, packet: Packet) {
+        let mut buf = BytesMut::new();
+        packet.write(&mut buf, usize::MAX).unwrap();
+        stream.write_all(&buf).await.unwrap();
+    }
+
+    /// Minimal broker side of one connection: accept CONNECT and return the
+    /// first PUBLISH to `topic`, acknowledging every QoS 1 PUBLISH
+    async fn receive_publish(
+        stream: &mut TcpStream,
+        session_present: bool,
+        topic: &str,
+    ) -> Publish {
+        let mut buf = BytesMut::new();
+        loop {
+            match Packet::read(&mut buf, usize::MAX) {
+                Ok(Packet::Connect(connect)) => {
+                    assert!(!connect.clean_session);
+                    let ack = ConnAck::new(ConnectReturnCode::Success, session_present);
+                    write_packet(stream, Packet::ConnAck(ack)).await;
+                }
+                Ok(Packet::Publish(publish)) => {
+                    if publish.qos == QoS::AtLeastOnce {
+                        write_packet(stream, Packet::PubAck(PubAck::new(publish.pkid))).await;
+                    }
+                    if publish.topic == topic {
+                        return publish;
+                    }
+                }
+                Ok(_) => {}
+                Err(rumqttc::Error::InsufficientBytes(_)) => {
+                    let n = stream.read_buf(&mut buf).await.unwrap();
+                    assert!(n > 0, "client closed the connection");
+                }
+                Err(e) => panic!("invalid MQTT packet: {}", e),
+            }
+        }
+    }
+
+    fn topic(result: &MonitoringResult) -> String {
+        format!("smotra/{}/{}", result.agent_id, result.endpoint_id)
+    }
+
+    #[tokio::test]
+    async fn test_publishes_result_to_endpoint_topic() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let agent_id = Uuid::now_v7();
+
+        let sink = MqttSink::new(sink_config(port), agent_id).unwrap();
+        let result = make_result(agent_id);
+        sink.publish(&EncodedResult::new(&result)).unwrap();
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let publish = tokio::time::timeout(
+            Duration::from_secs(5),
+            receive_publish(&mut stream, false, &topic(&result)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(publish.qos, QoS::AtLeastOnce);
+        let payload: serde_json::Value = serde_json::from_slice(&publish.payload).unwrap();
+        assert_eq!(payload, serde_json::to_value(&result).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_broker_drop() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let agent_id = Uuid::now_v7();
+        let sink = MqttSink::new(sink_config(port), agent_id).unwrap();
+
+        let first = make_result(agent_id);
+        sink.publish(&EncodedResult::new(&first)).unwrap();
+        let (mut stream, _) = listener.accept().await.unwrap();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            receive_publish(&mut stream, false, &topic(&first)),
+        )
+        .await
+        .unwrap();
+        drop(stream);
+
+        let second = make_result(agent_id);
+        sink.publish(&EncodedResult::new(&second)).unwrap();
+        let (mut stream, _) = tokio::time::timeout(Duration::from_secs(10), listener.accept())
+            .await
+            .unwrap()
+            .unwrap();
+        let publish = tokio::time::timeout(
+            Duration::from_secs(5),
+            receive_publish(&mut stream, true, &topic(&second)),
+        )
+        .await
+        .unwrap();
+
+        let payload: serde_json::Value = serde_json::from_slice(&publish.payload).unwrap();
+        assert_eq!(payload, serde_json::to_value(&second).unwrap());
+    }
+
+    #[test]
+    fn test_render_topic() {
+        let result = make_result(Uuid::now_v7());
+        assert_eq!(
+            render_topic("agents/{agent_id}/results/{endpoint}", &result),
+            format!("agents/{}/results/{}", result.agent_id, result.endpoint_id)
+        );
+        assert_eq!(render_topic("static", &result), "static");
+    }
+
+    #[test]
+    fn test_parse_broker_url() {
+        assert_eq!(
+            parse_broker_url("mqtt://broker.local").unwrap(),
+            ("broker.local".to_string(), 1883, false)
+        );
+        assert_eq!(
+            parse_broker_url("mqtts://broker.local:9883").unwrap(),
+            ("broker.local".to_string(), 9883, true)
+        );
+        assert_eq!(
+            parse_broker_url("mqtt://[::1]:1884").unwrap(),
+            ("::1".to_string(), 1884, false)
+        );
+        assert!(parse_broker_url("http://broker.local").is_err());
+        assert!(parse_broker_url("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_qos_rejected() {
+        let config = MqttSinkConfig {
+            qos: 3,
+            ..Default::default()
+        };
+        assert!(MqttSink::new(config, Uuid::now_v7()).is_err());
+    }
+}
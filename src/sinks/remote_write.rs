@@ -0,0 +1,520 @@
+//! Prometheus remote-write sink
+//!
+//! Converts check results into Prometheus time series and pushes them to a
+//! remote-write endpoint (Prometheus, Cortex, Mimir, ...) as a snappy
+//! block-compressed protobuf `WriteRequest`, following the remote-write 1.0
+//! specification.
+//!
+//! Every result produces these samples, timestamped with the result's
+//! timestamp:
+//!
+//! | Series | Value |
+//! |--------|-------|
+//! | `smotra_check_success` | `1` if the check succeeded, else `0` |
+//! | `smotra_check_latency_seconds` | response time, when the check measured one |
+//! | `smotra_ping_packet_loss_ratio` | share of lost echo requests (ping only) |
+//! | `smotra_ping_jitter_seconds` | standard deviation of the reply latencies (ping only, two or more replies) |
+//!
+//! Each series is labeled with `agent_id`, `endpoint_id`, `address` (when
+//! the endpoint is still configured), `check` (`ping`, `http`, `grpc`, ...)
+//! and the configured static `labels`; the built-in labels win on a clash.
+//!
+//! Publishing never blocks the result pipeline. Results are queued to a
+//! background task that pushes once `batch_size` results are buffered or
+//! every `flush_interval_secs`. A failed push is logged and its samples are
+//! dropped; results beyond `QUEUE_CAPACITY` pending ones are dropped too.
+
+use crate::agent_config::RemoteWriteSinkConfig;
+use crate::core::{CheckType, MonitoringResult};
+use crate::error::{Error, Result};
+use prost::Message;
+use std::collections::BTreeMap;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Results queued for the push task before new ones are dropped
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// Remote-write `WriteRequest`
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub timeseries: Vec<TimeSeries>,
+}
+
+/// Remote-write `TimeSeries`: a label set and its samples
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct TimeSeries {
+    /// Sorted by name
+    #[prost(message, repeated, tag = "1")]
+    pub labels: Vec<Label>,
+    /// Sorted by timestamp
+    #[prost(message, repeated, tag = "2")]
+    pub samples: Vec<Sample>,
+}
+
+/// Remote-write `Label`
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Message)]
+pub(crate) struct Label {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+/// Remote-write `Sample`
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct Sample {
+    #[prost(double, tag = "1")]
+    pub value: f64,
+    /// Milliseconds since the Unix epoch
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+}
+
+/// Pushes check metrics to a Prometheus remote-write endpoint
+pub struct RemoteWriteSink {
+    config: RemoteWriteSinkConfig,
+    agent_id: Uuid,
+    queue: mpsc::Sender<Vec<TimeSeries>>,
+    task: JoinHandle<()>,
+}
+
+impl RemoteWriteSink {
+    /// Create a sink for `config` and start its push task.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn new(config: RemoteWriteSinkConfig, agent_id: Uuid) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout())
+            .build()?;
+        let (queue, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let task = tokio::spawn(run_push_loop(rx, client, config.clone()));
+
+        debug!("Remote-write sink created for {}", config.url);
+        Ok(Self {
+            config,
+            agent_id,
+            queue,
+            task,
+        })
+    }
+
+    /// Configuration this sink was created with
+    pub fn config(&self) -> &RemoteWriteSinkConfig {
+        &self.config
+    }
+
+    /// Agent ID used for the `agent_id` label
+    pub fn agent_id(&self) -> Uuid {
+        self.agent_id
+    }
+
+    /// Queue `result`'s samples for the next push. `address` is added as
+    /// the `address` label.
+    pub fn publish(&self, result: &MonitoringResult, address: Option<&str>) -> Result<()> {
+        let series = result_series(result, address, &self.config.labels);
+        self.queue
+            .try_send(series)
+            .map_err(|e| Error::Network(format!("Failed to queue remote-write samples: {}", e)))
+    }
+}
+
+impl Drop for RemoteWriteSink {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Batch queued series and push them to the endpoint
+async fn run_push_loop(
+    mut rx: mpsc::Receiver<Vec<TimeSeries>>,
+    client: reqwest::Client,
+    config: RemoteWriteSinkConfig,
+) {
+    let mut flush = tokio::time::interval(config.flush_interval());
+    flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut batch = Vec::new();
+    let mut results = 0;
+
+    loop {
+        tokio::select! {
+            received = rx.recv() => {
+                let Some(series) = received else { break };
+                batch.extend(series);
+                results += 1;
+                if results < config.batch_size {
+                    continue;
+                }
+            }
+            _ = flush.tick() => {
+                if batch.is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        let request = write_request(std::mem::take(&mut batch));
+        results = 0;
+        if let Err(e) = push(&client, &config, &request).await {
+            warn!(
+                "Failed to push {} series to {}: {}",
+                request.timeseries.len(),
+                config.url,
+                e
+            );
+        }
+    }
+}
+
+/// POST `request` to the remote-write endpoint
+async fn push(
+    client: &reqwest::Client,
+    config: &RemoteWriteSinkConfig,
+    request: &WriteRequest,
+) -> Result<()> {
+    let mut builder = client
+        .post(&config.url)
+        .header("Content-Type", "application/x-protobuf")
+        .header("Content-Encoding", "snappy")
+        .header("X-Prometheus-Remote-Write-Version", "0.1.0")
+        .header("User-Agent", concat!("smotra/", env!("CARGO_PKG_VERSION")))
+        .body(encode(request)?);
+    if let Some(token) = &config.bearer_token {
+        builder = builder.bearer_auth(token);
+    }
+
+    let response = builder.send().await?;
+    if !response.status().is_success() {
+        return Err(Error::Network(format!(
+            "Remote-write endpoint returned {}",
+            response.status()
+        )));
+    }
+    debug!(
+        "Pushed {} series to {}",
+        request.timeseries.len(),
+        config.url
+    );
+    Ok(())
+}
+
+/// Protobuf-encode and snappy-compress `request`
+pub(crate) fn encode(request: &WriteRequest) -> Result<Vec<u8>> {
+    snap::raw::Encoder::new()
+        .compress_vec(&request.encode_to_vec())
+        .map_err(|e| Error::Network(format!("Failed to compress remote-write request: {}", e)))
+}
+
+/// Merge series with the same label set, keeping samples in time order
+fn write_request(series: Vec<TimeSeries>) -> WriteRequest {
+    let mut merged: BTreeMap<Vec<Label>, Vec<Sample>> = BTreeMap::new();
+    for s in series {
+        merged.entry(s.labels).or_default().extend(s.samples);
+    }
+
+    WriteRequest {
+        timeseries: merged
+            .into_iter()
+            .map(|(labels, mut samples)| {
+                samples.sort_by_key(|sample| sample.timestamp);
+                TimeSeries { labels, samples }
+            })
+            .collect(),
+    }
+}
+
+/// Time series of a single result
+fn result_series(
+    result: &MonitoringResult,
+    address: Option<&str>,
+    static_labels: &BTreeMap<String, String>,
+) -> Vec<TimeSeries> {
+    let mut labels = static_labels.clone();
+    labels.insert("agent_id".to_string(), result.agent_id.to_string());
+    labels.insert("endpoint_id".to_string(), result.endpoint_id.to_string());
+    labels.insert(
+        "check".to_string(),
+        check_label(&result.check_type).to_string(),
+    );
+    if let Some(address) = address {
+        labels.insert("address".to_string(), address.to_string());
+    }
+
+    let timestamp = result.timestamp.timestamp_millis();
+    let series = |name: &str, value: f64| {
+        let mut labels = labels.clone();
+        labels.insert("__name__".to_string(), name.to_string());
+        TimeSeries {
+            labels: labels
+                .into_iter()
+                .map(|(name, value)| Label { name, value })
+                .collect(),
+            samples: vec![Sample { value, timestamp }],
+        }
+    };
+
+    let mut out = vec![series(
+        "smotra_check_success",
+        if result.is_successful() { 1.0 } else { 0.0 },
+    )];
+    if let Some(latency_ms) = result.response_time_ms() {
+        out.push(series("smotra_check_latency_seconds", latency_ms / 1000.0));
+    }
+    if let CheckType::PingCheck(ping) = &result.check_type {
+        if let Some(loss) = ping.result.packet_loss_percent() {
+            out.push(series("smotra_ping_packet_loss_ratio", loss / 100.0));
+        }
+        if let Some(jitter_ms) = ping.result.jitter_ms() {
+            out.push(series("smotra_ping_jitter_seconds", jitter_ms / 1000.0));
+        }
+    }
+    if let CheckType::TlsCertCheck(tls) = &result.check_type {
+        if let Some(not_after) = tls.result.not_after {
+            out.push(series(
+                "smotra_tls_cert_expiry_timestamp_seconds",
+                not_after.timestamp() as f64,
+            ));
+        }
+    }
+    out
+}
+
+/// Value of the `check` label
+pub(crate) fn check_label(check_type: &CheckType) -> &'static str {
+    match check_type {
+        CheckType::PingCheck(_) => "ping",
+        CheckType::TracerouteCheck(_) => "traceroute",
+        CheckType::TcpConnectCheck(_) => "tcp",
+        CheckType::UdpConnectCheck(_) => "udp",
+        CheckType::HttpGetCheck(_) => "http",
+        CheckType::GrpcHealthCheck(_) => "grpc",
+        CheckType::TlsCertCheck(_) => "tls_cert",
+        CheckType::DnsCheck(_) => "dns",
+        CheckType::PluginCheck(_) => "plugin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        HttpGetCheck, HttpGetCheckType, HttpGetResult, PingCheck, PingCheckType, PingResult,
+    };
+    use chrono::{TimeZone, Utc};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const AGENT_ID: Uuid = Uuid::from_u128(1);
+    const ENDPOINT_ID: Uuid = Uuid::from_u128(2);
+
+    fn ping_result() -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: AGENT_ID,
+            endpoint_id: ENDPOINT_ID,
+            check_type: CheckType::PingCheck(PingCheck {
+                r#type: PingCheckType::Ping,
+                result: PingResult {
+                    resolved_ip: "10.0.0.1".to_string(),
+                    successes: 3,
+                    failures: 1,
+                    success_latencies: vec![10.0, 20.0, 30.0],
+                    error_details: None,
+                    dns_resolution_ms: None,
+                    required_successes: None,
+                    resolved_hostname: None,
+                    packet_loss_percent: None,
+                    jitter_ms: None,
+                },
+            }),
+            timestamp: Utc.timestamp_millis_opt(1_700_000_000_000).unwrap(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn http_result() -> MonitoringResult {
+        MonitoringResult {
+            id: Uuid::now_v7(),
+            agent_id: AGENT_ID,
+            endpoint_id: Uuid::from_u128(3),
+            check_type: CheckType::HttpGetCheck(HttpGetCheck {
+                r#type: HttpGetCheckType::Httpget,
+                result: HttpGetResult {
+                    status_code: 503,
+                    response_time_ms: None,
+                    dns_resolution_ms: None,
+                    response_size_bytes: None,
+                    body_truncated: None,
+                    body_matched: None,
+                    body_match_detail: None,
+                    error_details: None,
+                    success: false,
+                },
+            }),
+            timestamp: Utc.timestamp_millis_opt(1_700_000_001_000).unwrap(),
+            warmup: None,
+            degraded: None,
+            failure_kind: None,
+            status: None,
+            notes: Vec::new(),
+        }
+    }
+
+    fn labels(pairs: &[(&str, &str)]) -> Vec<Label> {
+        pairs
+            .iter()
+            .map(|(name, value)| Label {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+            .collect()
+    }
+
+    fn ping_labels(name: &str) -> Vec<Label> {
+        labels(&[
+            ("__name__", name),
+            ("address", "10.0.0.1"),
+            ("agent_id", &AGENT_ID.to_string()),
+            ("check", "ping"),
+            ("endpoint_id", &ENDPOINT_ID.to_string()),
+            ("job", "smotra"),
+        ])
+    }
+
+    fn decode(body: &[u8]) -> WriteRequest {
+        let raw = snap::raw::Decoder::new().decompress_vec(body).unwrap();
+        WriteRequest::decode(raw.as_slice()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pushed_body_decodes_to_expected_series() {
+        let mut server = mockito::Server::new_async().await;
+        let body = Arc::new(Mutex::new(None::<Vec<u8>>));
+        let mock = {
+            let body = Arc::clone(&body);
+            server
+                .mock("POST", "/api/v1/write")
+                .match_header("content-encoding", "snappy")
+                .match_header("content-type", "application/x-protobuf")
+                .match_header("x-prometheus-remote-write-version", "0.1.0")
+                .match_header("authorization", "Bearer secret")
+                .match_request(move |request| {
+                    *body.lock() = request.body().ok().cloned();
+                    true
+                })
+                .with_status(204)
+                .create_async()
+                .await
+        };
+
+        let config = RemoteWriteSinkConfig {
+            url: format!("{}/api/v1/write", server.url()),
+            bearer_token: Some("secret".to_string()),
+            labels: BTreeMap::from([("job".to_string(), "smotra".to_string())]),
+            batch_size: 2,
+            ..Default::default()
+        };
+        let sink = RemoteWriteSink::new(config, AGENT_ID).unwrap();
+        sink.publish(&ping_result(), Some("10.0.0.1")).unwrap();
+        sink.publish(&http_result(), None).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while body.lock().is_none() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("no remote-write request received");
+        mock.assert_async().await;
+
+        let request = decode(body.lock().as_ref().unwrap());
+        let ts = 1_700_000_000_000;
+        let expected_ping = [
+            TimeSeries {
+                labels: ping_labels("smotra_check_latency_seconds"),
+                samples: vec![Sample {
+                    value: 0.02,
+                    timestamp: ts,
+                }],
+            },
+            TimeSeries {
+                labels: ping_labels("smotra_check_success"),
+                samples: vec![Sample {
+                    value: 1.0,
+                    timestamp: ts,
+                }],
+            },
+            TimeSeries {
+                labels: ping_labels("smotra_ping_packet_loss_ratio"),
+                samples: vec![Sample {
+                    value: 0.25,
+                    timestamp: ts,
+                }],
+            },
+            TimeSeries {
+                labels: ping_labels("smotra_ping_jitter_seconds"),
+                samples: vec![Sample {
+                    // Standard deviation of 10, 20 and 30 ms
+                    value: (200.0f64 / 3.0).sqrt() / 1000.0,
+                    timestamp: ts,
+                }],
+            },
+        ];
+        let expected_http = TimeSeries {
+            labels: labels(&[
+                ("__name__", "smotra_check_success"),
+                ("agent_id", &AGENT_ID.to_string()),
+                ("check", "http"),
+                ("endpoint_id", &Uuid::from_u128(3).to_string()),
+                ("job", "smotra"),
+            ]),
+            samples: vec![Sample {
+                value: 0.0,
+                timestamp: ts + 1000,
+            }],
+        };
+
+        assert_eq!(request.timeseries.len(), 5);
+        for series in expected_ping.iter().chain([&expected_http]) {
+            assert!(
+                request.timeseries.contains(series),
+                "missing {:?} in {:?}",
+                series,
+                request.timeseries
+            );
+        }
+    }
+
+    #[test]
+    fn test_samples_of_the_same_series_are_merged_in_time_order() {
+        let mut later = ping_result();
+        later.timestamp = Utc.timestamp_millis_opt(1_700_000_060_000).unwrap();
+
+        let mut series = result_series(&later, None, &BTreeMap::new());
+        series.extend(result_series(&ping_result(), None, &BTreeMap::new()));
+        let request = write_request(series);
+
+        assert_eq!(request.timeseries.len(), 4);
+        for series in &request.timeseries {
+            let timestamps: Vec<_> = series.samples.iter().map(|s| s.timestamp).collect();
+            assert_eq!(timestamps, [1_700_000_000_000, 1_700_000_060_000]);
+        }
+    }
+
+    #[test]
+    fn test_builtin_labels_win_over_static_labels() {
+        let static_labels = BTreeMap::from([("check".to_string(), "custom".to_string())]);
+        let series = result_series(&http_result(), None, &static_labels);
+
+        let check = series[0].labels.iter().find(|l| l.name == "check").unwrap();
+        assert_eq!(check.value, "http");
+    }
+}
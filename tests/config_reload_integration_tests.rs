@@ -0,0 +1,107 @@
+//! Integration tests for applying a reloaded configuration to a running agent
+
+use smotra::{Agent, Config, Endpoint};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+use uuid::Uuid;
+
+/// Start an agent from `config` and return it with its task handle
+async fn start_agent(
+    config: &Config,
+) -> (
+    Arc<Agent>,
+    tokio::task::JoinHandle<smotra::Result<()>>,
+    NamedTempFile,
+) {
+    let config_file = NamedTempFile::new().unwrap();
+    config
+        .save_to_file_secure(config_file.path())
+        .await
+        .unwrap();
+
+    let agent = Arc::new(Agent::new(config_file.path().to_path_buf()).unwrap());
+    let handle = {
+        let agent = Arc::clone(&agent);
+        tokio::spawn(async move { agent.start().await })
+    };
+    (agent, handle, config_file)
+}
+
+#[tokio::test]
+async fn test_endpoints_added_by_reload_are_checked_immediately() {
+    let tcp_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tcp_port = tcp_listener.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+    {
+        let connections = Arc::clone(&connections);
+        tokio::spawn(async move {
+            while tcp_listener.accept().await.is_ok() {
+                connections.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    }
+
+    let cache_dir = TempDir::new().unwrap();
+    let mut config = Config {
+        agent_id: Uuid::now_v7(),
+        ..Config::default()
+    };
+    // Far longer than the test waits, so only the reload can wake the loop
+    config.monitoring.interval_secs = 3600;
+    config.server.report_interval_secs = 3600;
+    config.monitoring.timeout_secs = 1;
+    config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+    let (agent, agent_handle, _config_file) = start_agent(&config).await;
+
+    // Let the first, empty check cycle run
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+    let mut new_config = agent.config_clone();
+    new_config.version += 1;
+    new_config.endpoints = vec![Endpoint::new(
+        format!("tcp://127.0.0.1:{}", tcp_port).as_str(),
+    )];
+    agent.reload_config(new_config).unwrap();
+    assert_eq!(agent.config_clone().endpoints.len(), 1);
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while connections.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("the added endpoint was not checked after the reload");
+
+    agent.stop().unwrap();
+    agent_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_reload_keeps_running_config() {
+    let cache_dir = TempDir::new().unwrap();
+    let mut config = Config {
+        agent_id: Uuid::now_v7(),
+        ..Config::default()
+    };
+    config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+    let (agent, agent_handle, _config_file) = start_agent(&config).await;
+    let mut shutdown_rx = agent.subscribe_shutdown();
+
+    let mut invalid = agent.config_clone();
+    invalid.version += 1;
+    invalid.monitoring.interval_secs = 0;
+    assert!(agent.reload_config(invalid).is_err());
+    assert_eq!(agent.config_clone().version, config.version);
+    assert_eq!(
+        agent.config_clone().monitoring.interval_secs,
+        config.monitoring.interval_secs
+    );
+
+    agent.stop().unwrap();
+    shutdown_rx.changed().await.unwrap();
+    assert!(*shutdown_rx.borrow());
+    agent_handle.await.unwrap().unwrap();
+}
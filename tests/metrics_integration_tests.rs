@@ -0,0 +1,175 @@
+//! Integration test for the local Prometheus `/metrics` endpoint
+
+use smotra::{Agent, Config, Endpoint};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+use uuid::Uuid;
+
+/// One sample line of the text exposition format
+#[derive(Debug)]
+struct Sample {
+    name: String,
+    labels: HashMap<String, String>,
+    value: f64,
+}
+
+/// Parse `text` as the Prometheus text exposition format, panicking on any
+/// malformed line or on a sample whose metric has no preceding `# TYPE`
+fn parse_exposition(text: &str) -> Vec<Sample> {
+    let mut types = HashMap::new();
+    let mut samples = Vec::new();
+
+    for line in text.lines().filter(|line| !line.is_empty()) {
+        if let Some(rest) = line.strip_prefix("# TYPE ") {
+            let (name, kind) = rest.split_once(' ').expect("TYPE needs a metric type");
+            assert!(
+                ["counter", "gauge", "histogram", "summary", "untyped"].contains(&kind),
+                "unknown metric type in {:?}",
+                line
+            );
+            types.insert(name.to_string(), kind.to_string());
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# HELP ") {
+            assert!(
+                rest.split_once(' ').is_some(),
+                "HELP needs a text: {:?}",
+                line
+            );
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let name_end = line.find(['{', ' ']).expect("sample needs a value");
+        let name = &line[..name_end];
+        assert!(
+            name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':'),
+            "invalid metric name in {:?}",
+            line
+        );
+        assert!(types.contains_key(name), "{} has no TYPE line", name);
+
+        let mut rest = &line[name_end..];
+        let mut labels = HashMap::new();
+        if let Some(after_brace) = rest.strip_prefix('{') {
+            rest = after_brace;
+            loop {
+                if let Some(after) = rest.strip_prefix('}') {
+                    rest = after;
+                    break;
+                }
+                let (label, after) = rest.split_once("=\"").expect("label needs a value");
+                let mut value = String::new();
+                let mut chars = after.char_indices();
+                let end = loop {
+                    match chars.next().expect("unterminated label value") {
+                        (_, '\\') => match chars.next().expect("dangling escape").1 {
+                            'n' => value.push('\n'),
+                            c @ ('\\' | '"') => value.push(c),
+                            c => panic!("invalid escape \\{} in {:?}", c, line),
+                        },
+                        (i, '"') => break i,
+                        (_, c) => value.push(c),
+                    }
+                };
+                labels.insert(label.to_string(), value);
+                rest = &after[end + 1..];
+                rest = rest.strip_prefix(',').unwrap_or(rest);
+            }
+        }
+
+        let value = rest
+            .trim()
+            .split(' ')
+            .next()
+            .unwrap()
+            .parse::<f64>()
+            .unwrap_or_else(|e| panic!("invalid sample value in {:?}: {}", line, e));
+        samples.push(Sample {
+            name: name.to_string(),
+            labels,
+            value,
+        });
+    }
+
+    samples
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_serves_check_results() {
+    let tcp_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tcp_port = tcp_listener.local_addr().unwrap().port();
+    tokio::spawn(async move { while tcp_listener.accept().await.is_ok() {} });
+    let target = format!("tcp://127.0.0.1:{}", tcp_port);
+
+    let metrics_addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let cache_dir = TempDir::new().unwrap();
+    let mut config = Config {
+        agent_id: Uuid::now_v7(),
+        metrics_addr: Some(metrics_addr),
+        endpoints: vec![Endpoint::new(target.as_str())],
+        ..Config::default()
+    };
+    config.monitoring.interval_secs = 2;
+    config.monitoring.timeout_secs = 1;
+    config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+    let config_file = NamedTempFile::new().unwrap();
+    config
+        .save_to_file_secure(config_file.path())
+        .await
+        .unwrap();
+
+    let agent = Arc::new(Agent::new(config_file.path().to_path_buf()).unwrap());
+    let agent_handle = {
+        let agent = Arc::clone(&agent);
+        tokio::spawn(async move { agent.start().await })
+    };
+
+    let url = format!("http://{}/metrics", metrics_addr);
+    let success_line = format!(
+        "smotra_check_success_total{{target=\"{}\",check=\"tcp\"}}",
+        target
+    );
+    let body = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Ok(response) = reqwest::get(&url).await {
+                assert_eq!(response.status(), 200);
+                let content_type = response.headers()["content-type"].to_str().unwrap();
+                assert!(content_type.starts_with("text/plain; version=0.0.4"));
+                let body = response.text().await.unwrap();
+                if body.contains(&success_line) {
+                    return body;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("the check result was not exposed");
+
+    agent.stop().unwrap();
+    agent_handle.await.unwrap().unwrap();
+
+    let samples = parse_exposition(&body);
+    let sample = |name: &str| {
+        samples
+            .iter()
+            .find(|s| s.name == name && s.labels.get("target") == Some(&target))
+            .unwrap_or_else(|| panic!("no {} sample for {} in:\n{}", name, target, body))
+    };
+
+    let success = sample("smotra_check_success_total");
+    assert_eq!(success.labels["check"], "tcp");
+    assert!(success.value >= 1.0);
+    assert_eq!(sample("smotra_check_failure_total").value, 0.0);
+    assert!(sample("smotra_response_time_ms").value >= 0.0);
+}
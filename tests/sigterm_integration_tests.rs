@@ -0,0 +1,48 @@
+//! Graceful shutdown on SIGTERM
+//!
+//! Kept in its own test binary: the signal is raised for the whole process,
+//! so any other agent running in the same binary would stop as well.
+
+#![cfg(unix)]
+
+use smotra::{Agent, Config};
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_sigterm_stops_the_agent_gracefully() {
+    let cache_dir = TempDir::new().unwrap();
+    let mut config = Config {
+        agent_id: Uuid::now_v7(),
+        ..Config::default()
+    };
+    config.storage.cache_dir = cache_dir.path().to_string_lossy().into_owned();
+    let config_file = NamedTempFile::new().unwrap();
+    config
+        .save_to_file_secure(config_file.path())
+        .await
+        .unwrap();
+
+    let agent = Agent::new(config_file.path().to_path_buf()).unwrap();
+    let mut shutdown_rx = agent.subscribe_shutdown();
+    let agent_handle = tokio::spawn(async move { agent.start().await });
+
+    // Give start() time to install its SIGTERM handler
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let kill = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(kill.success());
+
+    let result = tokio::time::timeout(Duration::from_secs(5), agent_handle)
+        .await
+        .expect("agent did not stop after SIGTERM")
+        .unwrap();
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(
+        *shutdown_rx.borrow_and_update(),
+        "SIGTERM takes the graceful shutdown path"
+    );
+}